@@ -0,0 +1,305 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Setting the values of many signals at once, e.g. to initialize a bench
+//! setup, from a JSON object mapping paths to values
+//!
+//! ```json
+//! {"Vehicle.VehicleIdentification.VIN": "WVW1234", "Vehicle.Cabin.Seat.Row1.DriverSide.Position": 100}
+//! ```
+//!
+//! or from a CSV file with one `path,value` per line.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use databroker_proto::kuksa::val::v1 as proto;
+use kuksa::KuksaClient;
+
+use crate::cli;
+
+/// Number of values sent in one request
+const BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Set the current values of all entries
+    Publish,
+    /// Set the current values of sensors and attributes, and the targets of
+    /// actuators
+    Set,
+}
+
+impl Mode {
+    fn operation(self) -> &'static str {
+        match self {
+            Mode::Publish => "publish",
+            Mode::Set => "set",
+        }
+    }
+}
+
+/// Reads the paths and values of `file`, as CSV if it has the extension
+/// `.csv`, as JSON otherwise.
+pub fn read_values(file: &Path) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let content = std::fs::read_to_string(file)?;
+    if file
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"))
+    {
+        parse_csv(&content)
+    } else {
+        parse_json(&content)
+    }
+}
+
+fn parse_json(content: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let values: serde_json::Map<String, serde_json::Value> = serde_json::from_str(content)?;
+    values
+        .into_iter()
+        .map(|(path, value)| match value {
+            serde_json::Value::String(value) => Ok((path, value)),
+            serde_json::Value::Null | serde_json::Value::Object(_) => {
+                Err(format!("{path}: expected a string, number, boolean or array").into())
+            }
+            // Arrays are parsed from their JSON representation like arrays
+            // entered on the command line
+            value => Ok((path, value.to_string())),
+        })
+        .collect()
+}
+
+fn parse_csv(content: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut values = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || (index == 0 && line.eq_ignore_ascii_case("path,value"))
+        {
+            continue;
+        }
+        let Some((path, value)) = line.split_once(',') else {
+            return Err(format!("line {}: expected PATH,VALUE", index + 1).into());
+        };
+        values.push((path.trim().to_owned(), unquote(value.trim())));
+    }
+    Ok(values)
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(value) => value.replace("\"\"", "\""),
+        None => value.to_owned(),
+    }
+}
+
+fn describe(error: &proto::Error) -> String {
+    format!("{} ({})", error.message, error.reason)
+}
+
+/// Sets the values of `file` in batches, after checking them against the
+/// data types of the entries, and prints the result of every path.
+pub async fn apply(
+    client: &mut KuksaClient,
+    file: &Path,
+    mode: Mode,
+    parse: impl Fn(&str, proto::DataType) -> Option<proto::datapoint::Value>,
+) -> Result<(), Box<dyn Error>> {
+    let values = read_values(file)?;
+    let mut val = proto::val_client::ValClient::with_interceptor(
+        client.basic_client.get_channel().await?.clone(),
+        client.basic_client.get_auth_interceptor(),
+    );
+
+    let mut results: BTreeMap<String, Result<(), String>> = BTreeMap::new();
+    for batch in values.chunks(BATCH_SIZE) {
+        let request = proto::GetRequest {
+            entries: batch
+                .iter()
+                .map(|(path, _)| proto::EntryRequest {
+                    path: path.clone(),
+                    view: proto::View::Metadata.into(),
+                    fields: vec![proto::Field::Metadata.into()],
+                })
+                .collect(),
+        };
+        let response = val.get(request).await?.into_inner();
+        // The error of the response sums up the errors of the paths, if any
+        if let (Some(error), true) = (&response.error, response.errors.is_empty()) {
+            return Err(describe(error).into());
+        }
+        for error in response.errors {
+            let message = error.error.as_ref().map(describe).unwrap_or_default();
+            results.insert(error.path, Err(message));
+        }
+        let metadata: BTreeMap<_, _> = response
+            .entries
+            .into_iter()
+            .filter_map(|entry| Some((entry.path, entry.metadata?)))
+            .collect();
+
+        let mut updates = Vec::new();
+        for (path, value) in batch {
+            if results.contains_key(path) {
+                continue;
+            }
+            let Some(metadata) = metadata.get(path) else {
+                results.insert(path.clone(), Err("No metadata available".to_owned()));
+                continue;
+            };
+            let data_type = proto::DataType::try_from(metadata.data_type)
+                .unwrap_or(proto::DataType::Unspecified);
+            let Some(value) = parse(value, data_type) else {
+                results.insert(
+                    path.clone(),
+                    Err(format!("Could not parse \"{value}\" as {data_type:?}")),
+                );
+                continue;
+            };
+            let datapoint = Some(proto::Datapoint::now(value));
+            let target =
+                mode == Mode::Set && metadata.entry_type == proto::EntryType::Actuator as i32;
+            updates.push(if target {
+                proto::EntryUpdate {
+                    entry: Some(proto::DataEntry {
+                        path: path.clone(),
+                        value: None,
+                        actuator_target: datapoint,
+                        metadata: None,
+                    }),
+                    fields: vec![
+                        proto::Field::ActuatorTarget.into(),
+                        proto::Field::Path.into(),
+                    ],
+                }
+            } else {
+                proto::EntryUpdate {
+                    entry: Some(proto::DataEntry {
+                        path: path.clone(),
+                        value: datapoint,
+                        actuator_target: None,
+                        metadata: None,
+                    }),
+                    fields: vec![proto::Field::Value.into(), proto::Field::Path.into()],
+                }
+            });
+        }
+        if updates.is_empty() {
+            continue;
+        }
+
+        let paths: Vec<String> = updates
+            .iter()
+            .filter_map(|update| Some(update.entry.as_ref()?.path.clone()))
+            .collect();
+        match val.set(proto::SetRequest { updates }).await {
+            Ok(response) => {
+                let response = response.into_inner();
+                let failed = response.error.filter(|_| response.errors.is_empty());
+                let mut errors: BTreeMap<_, _> = response
+                    .errors
+                    .into_iter()
+                    .map(|error| (error.path, error.error))
+                    .collect();
+                for path in paths {
+                    let result = match (errors.remove(&path), &failed) {
+                        (Some(error), _) => Err(error.as_ref().map(describe).unwrap_or_default()),
+                        (None, Some(error)) => Err(describe(error)),
+                        (None, None) => Ok(()),
+                    };
+                    results.insert(path, result);
+                }
+            }
+            // The whole batch was rejected, e.g. for a value out of range
+            Err(status) => {
+                for path in paths {
+                    results.insert(path, Err(status.message().to_owned()));
+                }
+            }
+        }
+    }
+
+    let operation = mode.operation();
+    let failed = results.values().filter(|result| result.is_err()).count();
+    if failed == 0 {
+        cli::print_resp_ok(operation)?;
+    } else {
+        cli::print_error(
+            operation,
+            format!("{failed} of {} values failed", results.len()),
+        )?;
+    }
+    for (path, result) in &results {
+        match result {
+            Ok(()) => println!("{path}: OK"),
+            Err(message) => println!("{path}: {message}"),
+        }
+    }
+    println!("{} values set, {failed} failed", results.len() - failed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let values = parse_json(
+            r#"{"Vehicle.Speed": 50.5, "Vehicle.VehicleIdentification.VIN": "WVW1234",
+                "Vehicle.IsMoving": true, "Vehicle.Tags": ["a", "b"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                ("Vehicle.IsMoving".to_owned(), "true".to_owned()),
+                ("Vehicle.Speed".to_owned(), "50.5".to_owned()),
+                ("Vehicle.Tags".to_owned(), r#"["a","b"]"#.to_owned()),
+                (
+                    "Vehicle.VehicleIdentification.VIN".to_owned(),
+                    "WVW1234".to_owned()
+                ),
+            ]
+        );
+        assert!(parse_json(r#"{"Vehicle.Speed": null}"#).is_err());
+        assert!(parse_json(r#"["Vehicle.Speed"]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let values = parse_csv(
+            "path,value\n\
+             # Comment\n\
+             Vehicle.Speed, 50.5\n\
+             \n\
+             Vehicle.Tags,\"[\"\"a\"\", \"\"b\"\"]\"\n\
+             Vehicle.Ids,[1, 2]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                ("Vehicle.Speed".to_owned(), "50.5".to_owned()),
+                ("Vehicle.Tags".to_owned(), r#"["a", "b"]"#.to_owned()),
+                ("Vehicle.Ids".to_owned(), "[1, 2]".to_owned()),
+            ]
+        );
+        assert!(parse_csv("Vehicle.Speed").is_err());
+    }
+}
@@ -11,8 +11,8 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
-use http::Uri;
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::{fmt, io};
 
@@ -20,6 +20,8 @@ use ansi_term::Color;
 
 use clap::{Parser, Subcommand, ValueEnum};
 
+use crate::provider;
+use crate::recorder;
 use linefeed::{DefaultTerminal, Function, Interface, Prompter, Terminal};
 
 #[derive(Debug)]
@@ -36,13 +38,13 @@ impl fmt::Display for ParseError {
 #[derive(Debug, Parser, Clone)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
-    /// Server to connect to
+    /// Server to connect to, or unix:///PATH of a unix socket
     #[clap(long, display_order = 1, default_value = "http://127.0.0.1:55555")]
     server: String,
 
     // #[clap(short, long)]
     // port: Option<u16>,
-    /// File containing access token
+    /// File containing access token, re-read when it changes
     #[clap(long, value_name = "FILE", display_order = 2)]
     token_file: Option<String>,
 
@@ -90,27 +92,116 @@ pub enum Commands {
         #[clap(value_name = "PATH")]
         paths: Vec<String>,
     },
-    /// Set a datapoint
+    /// Set a datapoint, or the values of a file
     Set {
+        #[clap(value_name = "PATH", required_unless_present = "from_file")]
+        path: Option<String>,
+        #[clap(value_name = "VALUE", required_unless_present = "from_file")]
+        value: Option<String>,
+        /// Set the values of a JSON or CSV file of paths and values, the
+        /// targets of actuators and the current values of other entries
+        #[clap(long, value_name = "FILE", conflicts_with = "path")]
+        from_file: Option<PathBuf>,
+    },
+    /// Publish a datapoint PATH VALUE, or the values of a file
+    Publish {
+        #[clap(value_name = "PATH", required_unless_present = "from_file")]
+        path: Option<String>,
+        #[clap(value_name = "VALUE", required_unless_present = "from_file")]
+        value: Option<String>,
+        /// Publish the values of a JSON or CSV file of paths and values
+        #[clap(long, value_name = "FILE", conflicts_with = "path")]
+        from_file: Option<PathBuf>,
+    },
+    /// Request an actuation PATH VALUE
+    Actuate {
         #[clap(value_name = "PATH")]
         path: String,
         #[clap(value_name = "VALUE")]
         value: String,
     },
-    /// Publish a datapoint PATH VALUE
-    Publish {
+    /// Subscribe to signals and print or record updates as JSON lines
+    Subscribe {
+        #[clap(value_name = "PATH", required = true)]
+        paths: Vec<String>,
+        /// Write updates to files in DIR instead of printing them
+        #[clap(long, value_name = "DIR")]
+        log_dir: Option<PathBuf>,
+        /// Start a new file when the current one reaches SIZE, e.g. 100MB
+        #[clap(long, value_name = "SIZE", requires = "log_dir", value_parser = recorder::parse_size)]
+        rotate: Option<u64>,
+    },
+    /// List actuators whose current value differs from their target, or
+    /// compare current values and metadata with another server
+    Diff {
+        /// Server to compare with
+        #[clap(long, value_name = "SERVER")]
+        other: Option<String>,
+        /// File containing access token for the other server, defaults to --token-file
+        #[clap(long, value_name = "FILE", requires = "other")]
+        other_token_file: Option<String>,
+        #[clap(value_name = "PATH", default_value = "**")]
+        paths: Vec<String>,
+    },
+    /// Feed PATH with generated values, or the values entered on stdin
+    Provide {
         #[clap(value_name = "PATH")]
         path: String,
-        #[clap(value_name = "VALUE")]
-        value: String,
+        /// Values published per second, e.g. 10hz
+        #[clap(long, default_value = "10hz", value_parser = provider::parse_rate)]
+        rate: f64,
+        #[clap(long, value_enum, default_value_t = provider::Pattern::Sine)]
+        pattern: provider::Pattern,
     },
-    /// Request an actuation PATH VALUE
-    Actuate {
+    /// Measure the round-trip latency of publishing PATH and receiving it on a subscription
+    PingSignal {
         #[clap(value_name = "PATH")]
         path: String,
-        #[clap(value_name = "VALUE")]
-        value: String,
+        /// Number of values to publish
+        #[clap(long, short = 'n', default_value_t = 100)]
+        count: usize,
+        /// Milliseconds to wait between values
+        #[clap(long, value_name = "MS", default_value_t = 10)]
+        interval: u64,
     },
+    /// Export the metadata of the server's signals as VSS JSON
+    ExportVss {
+        /// Only export signals below this branch
+        #[clap(value_name = "PREFIX")]
+        prefix: Option<String>,
+        /// File to write the VSS JSON to
+        #[clap(long, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Show a full-screen dashboard of signals and their history
+    Dashboard {
+        /// Signals to watch, more can be added on the dashboard
+        #[clap(value_name = "PATH")]
+        paths: Vec<String>,
+    },
+    /// Manage the access token obtained from an identity provider
+    Token {
+        #[clap(subcommand)]
+        command: TokenCommands,
+    },
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum TokenCommands {
+    /// Log in using the OAuth 2.0 device authorization flow and cache the token
+    Login {
+        /// Issuer URL of the identity provider
+        #[clap(long, env = "KUKSA_CLI_OAUTH_ISSUER", value_name = "URL")]
+        issuer: String,
+        /// Client ID registered with the identity provider
+        #[clap(long, env = "KUKSA_CLI_OAUTH_CLIENT_ID", value_name = "ID")]
+        client_id: String,
+        /// Scopes to request, separated by spaces
+        #[clap(long, env = "KUKSA_CLI_OAUTH_SCOPE", value_name = "SCOPE")]
+        scope: Option<String>,
+    },
+    /// Remove the cached token
+    Logout,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -381,34 +472,3 @@ impl<Term: Terminal> Function<Term> for EnterFunction {
         }
     }
 }
-
-pub fn to_uri(uri: impl AsRef<str>) -> Result<Uri, String> {
-    let uri = uri
-        .as_ref()
-        .parse::<tonic::transport::Uri>()
-        .map_err(|err| format!("{err}"))?;
-    let mut parts = uri.into_parts();
-
-    if parts.scheme.is_none() {
-        parts.scheme = Some("http".parse().expect("http should be valid scheme"));
-    }
-
-    match &parts.authority {
-        Some(_authority) => {
-            // match (authority.port_u16(), port) {
-            //     (Some(uri_port), Some(port)) => {
-            //         if uri_port != port {
-            //             parts.authority = format!("{}:{}", authority.host(), port)
-            //                 .parse::<Authority>()
-            //                 .map_err(|err| format!("{}", err))
-            //                 .ok();
-            //         }
-            //     }
-            //     (_, _) => {}
-            // }
-        }
-        None => return Err("No server uri specified".to_owned()),
-    }
-    parts.path_and_query = Some("".parse().expect("uri path should be empty string"));
-    tonic::transport::Uri::from_parts(parts).map_err(|err| format!("{err}"))
-}
@@ -0,0 +1,685 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Full-screen dashboard showing the latest values of watched signals, the
+//! history of the selected one and the state of the connection.
+//!
+//! Commands are entered after pressing `:`:
+//!
+//! ```text
+//! watch <PATH>            Add the signals matching PATH
+//! unwatch <PATH>          Remove a signal
+//! publish <PATH> <VALUE>  Publish a current value
+//! actuate <PATH> <VALUE>  Request a target value
+//! quit                    Leave the dashboard
+//! ```
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use databroker_proto::kuksa::val::v1 as proto;
+use kuksa::api::KuksaClientApi;
+use kuksa::{ClientError, KuksaClient};
+use proto::datapoint::Value;
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Sparkline, Table, TableState};
+use ratatui::Frame;
+use serde_json::Value as JsonValue;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
+
+/// Number of values kept per signal for the sparkline
+pub const HISTORY_LEN: usize = 512;
+
+/// Redraw at least this often, to keep the age of values current
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+enum Event {
+    Key(KeyEvent),
+    Update(Box<proto::DataEntry>),
+    Connection(bool),
+    SubscriptionEnded(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Watch(String),
+    Unwatch(String),
+    Publish(String, String),
+    Actuate(String, String),
+    Quit,
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let cmd = words.next().unwrap_or_default();
+        let path = words.next().map(str::to_owned);
+        let value = words.collect::<Vec<_>>().join(" ");
+        match (cmd, path, value.is_empty()) {
+            ("watch", Some(path), true) => Ok(Command::Watch(path)),
+            ("unwatch", Some(path), true) => Ok(Command::Unwatch(path)),
+            ("publish", Some(path), false) => Ok(Command::Publish(path, value)),
+            ("actuate", Some(path), false) => Ok(Command::Actuate(path, value)),
+            ("quit" | "q", None, true) => Ok(Command::Quit),
+            ("watch" | "unwatch", _, _) => Err(format!("Usage: {cmd} <PATH>")),
+            ("publish" | "actuate", _, _) => Err(format!("Usage: {cmd} <PATH> <VALUE>")),
+            _ => Err(format!("Unknown command \"{cmd}\"")),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Signal {
+    value: Option<Value>,
+    unit: Option<String>,
+    updated: Option<Instant>,
+    /// Numeric values received, oldest first
+    history: VecDeque<f64>,
+}
+
+struct Dashboard {
+    uri: String,
+    connected: bool,
+    updates: u64,
+    signals: BTreeMap<String, Signal>,
+    table: TableState,
+    /// Command being entered, `None` if not in command mode
+    input: Option<String>,
+    status: String,
+    quit: bool,
+}
+
+impl Dashboard {
+    fn new(uri: String) -> Self {
+        Dashboard {
+            uri,
+            connected: false,
+            updates: 0,
+            signals: BTreeMap::new(),
+            table: TableState::default(),
+            input: None,
+            status: String::new(),
+            quit: false,
+        }
+    }
+
+    fn selected(&self) -> Option<(&String, &Signal)> {
+        self.signals.iter().nth(self.table.selected()?)
+    }
+
+    fn update(&mut self, entry: proto::DataEntry) {
+        let Some(signal) = self.signals.get_mut(&entry.path) else {
+            return;
+        };
+        let Some(value) = entry.value.and_then(|datapoint| datapoint.value) else {
+            return;
+        };
+        if let Some(number) = numeric(&value) {
+            if signal.history.len() == HISTORY_LEN {
+                signal.history.pop_front();
+            }
+            signal.history.push_back(number);
+        }
+        signal.value = Some(value);
+        signal.updated = Some(Instant::now());
+        self.updates += 1;
+    }
+
+    fn watch(&mut self, entries: Vec<proto::DataEntry>) {
+        let selected = self.selected().map(|(path, _)| path.clone());
+        for entry in entries {
+            self.signals.entry(entry.path).or_insert_with(|| Signal {
+                unit: entry.metadata.and_then(|metadata| metadata.unit),
+                ..Default::default()
+            });
+        }
+        // Keep the selection on the same signal
+        let index =
+            selected.and_then(|selected| self.signals.keys().position(|path| *path == selected));
+        if index.is_some() || !self.signals.is_empty() {
+            self.table.select(Some(index.unwrap_or_default()));
+        }
+    }
+
+    fn unwatch(&mut self, path: &str) -> bool {
+        if self.signals.remove(path).is_none() {
+            return false;
+        }
+        match self.signals.len() {
+            0 => self.table.select(None),
+            len => self
+                .table
+                .select(self.table.selected().map(|selected| selected.min(len - 1))),
+        }
+        true
+    }
+
+    /// Handle a key press, returning the command entered if any.
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Result<Command, String>> {
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Enter => {
+                    let line = self.input.take().unwrap_or_default();
+                    if !line.trim().is_empty() {
+                        return Some(Command::parse(&line));
+                    }
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return None;
+        }
+        match key.code {
+            KeyCode::Char(':') => self.input = Some(String::new()),
+            KeyCode::Char('q') => return Some(Ok(Command::Quit)),
+            KeyCode::Down | KeyCode::Char('j') if !self.signals.is_empty() => {
+                let next = self.table.selected().map_or(0, |selected| selected + 1);
+                self.table.select(Some(next.min(self.signals.len() - 1)));
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.signals.is_empty() => {
+                let previous = self
+                    .table
+                    .selected()
+                    .map_or(0, |selected| selected.saturating_sub(1));
+                self.table.select(Some(previous));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, status, command] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [signals, history] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(body);
+
+        let connection = if self.connected {
+            Span::styled(
+                format!(" Connected to {} ", self.uri),
+                Style::new().fg(Color::Black).bg(Color::Green),
+            )
+        } else {
+            Span::styled(
+                format!(" Not connected to {} ", self.uri),
+                Style::new().fg(Color::White).bg(Color::Red),
+            )
+        };
+        frame.render_widget(
+            Line::from(vec![
+                connection,
+                Span::raw(format!(" {} updates", self.updates)),
+            ]),
+            header,
+        );
+
+        let now = Instant::now();
+        let rows = self.signals.iter().map(|(path, signal)| {
+            Row::new(vec![
+                Cell::from(path.as_str()),
+                Cell::from(signal.value.as_ref().map(format_value).unwrap_or_default()),
+                Cell::from(signal.unit.clone().unwrap_or_default()),
+                Cell::from(
+                    signal
+                        .updated
+                        .map(|updated| format!("{:.1}s", (now - updated).as_secs_f64()))
+                        .unwrap_or_else(|| "-".to_owned()),
+                ),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(16),
+                Constraint::Length(6),
+                Constraint::Length(7),
+            ],
+        )
+        .header(
+            Row::new(["Path", "Value", "Unit", "Age"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title(" Signals "))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, signals, &mut self.table);
+
+        let (title, data) = match self.selected() {
+            Some((path, signal)) => {
+                // Only the most recent values fit
+                let width = usize::from(history.width.saturating_sub(2));
+                let values: Vec<f64> = signal
+                    .history
+                    .iter()
+                    .skip(signal.history.len().saturating_sub(width))
+                    .copied()
+                    .collect();
+                let (data, min, max) = scale(&values);
+                let title = match (min, max) {
+                    (Some(min), Some(max)) => format!(" {path} [{min} .. {max}] "),
+                    _ => format!(" {path} "),
+                };
+                (title, data)
+            }
+            None => (" History ".to_owned(), vec![]),
+        };
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .data(&data)
+                .max(100)
+                .style(Style::new().fg(Color::Cyan)),
+            history,
+        );
+
+        frame.render_widget(Paragraph::new(self.status.as_str()), status);
+
+        match &self.input {
+            Some(input) => {
+                frame.render_widget(Paragraph::new(format!(":{input}")), command);
+                let x = command.x + 1 + input.chars().count() as u16;
+                frame.set_cursor_position((x.min(command.right()), command.y));
+            }
+            None => frame.render_widget(
+                Paragraph::new("Press : to enter a command, up/down to select a signal, q to quit")
+                    .style(Style::new().add_modifier(Modifier::DIM)),
+                command,
+            ),
+        }
+    }
+}
+
+/// Value plotted in the sparkline, booleans as 0 and 1.
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Bool(value) => Some(f64::from(u8::from(*value))),
+        value => f64::try_from(value.clone()).ok(),
+    }
+}
+
+/// Scale `values` to bars of 1 to 100, so that the minimum stays visible.
+fn scale(values: &[f64]) -> (Vec<u64>, Option<f64>, Option<f64>) {
+    let min = values.iter().copied().reduce(f64::min);
+    let max = values.iter().copied().reduce(f64::max);
+    let (Some(low), Some(high)) = (min, max) else {
+        return (vec![], None, None);
+    };
+    let data = values
+        .iter()
+        .map(|value| match high - low {
+            range if range > 0.0 => 1 + ((value - low) / range * 99.0).round() as u64,
+            _ => 50,
+        })
+        .collect();
+    (data, min, max)
+}
+
+fn format_value(value: &Value) -> String {
+    match kuksa::branch::to_json(value) {
+        JsonValue::String(value) => value,
+        value => value.to_string(),
+    }
+}
+
+fn describe(err: ClientError) -> String {
+    match err {
+        ClientError::Status(status) => format!("{:?}: {}", status.code(), status.message()),
+        ClientError::Connection(msg) => msg,
+        ClientError::Function(errors) => format!("{errors:?}"),
+    }
+}
+
+/// Subscribe to the watched signals, forwarding updates until the task is
+/// aborted or the subscription ends.
+async fn subscribe(
+    client: &mut KuksaClient,
+    dashboard: &Dashboard,
+    events: &mpsc::UnboundedSender<Event>,
+) -> Result<Option<JoinHandle<()>>, ClientError> {
+    if dashboard.signals.is_empty() {
+        return Ok(None);
+    }
+    let mut subscription = client
+        .subscribe_current_values(dashboard.signals.keys().cloned().collect())
+        .await?;
+    let events = events.clone();
+    Ok(Some(tokio::spawn(async move {
+        while let Some(response) = subscription.next().await {
+            match response {
+                Ok(response) => {
+                    for entry in response
+                        .updates
+                        .into_iter()
+                        .filter_map(|update| update.entry)
+                    {
+                        let _ = events.send(Event::Update(Box::new(entry)));
+                    }
+                }
+                Err(status) => {
+                    let _ = events.send(Event::SubscriptionEnded(status.message().to_owned()));
+                    return;
+                }
+            }
+        }
+        let _ = events.send(Event::SubscriptionEnded("server gone".to_owned()));
+    })))
+}
+
+async fn parse_value(
+    client: &mut KuksaClient,
+    path: &str,
+    input: &str,
+    parse: &impl Fn(&str, proto::DataType) -> Option<Value>,
+) -> Result<Value, String> {
+    let data_type = client
+        .get_metadata(vec![path.to_owned()])
+        .await
+        .map_err(describe)?
+        .into_iter()
+        .find(|entry| entry.path == path)
+        .and_then(|entry| entry.metadata)
+        .and_then(|metadata| proto::DataType::try_from(metadata.data_type).ok())
+        .ok_or_else(|| format!("{path} is not a signal"))?;
+    parse(input, data_type).ok_or_else(|| format!("Could not parse \"{input}\" as {data_type:?}"))
+}
+
+fn datapoint(value: Value) -> proto::Datapoint {
+    proto::Datapoint {
+        timestamp: None,
+        value: Some(value),
+    }
+}
+
+/// Run a command, returning whether the set of watched signals changed.
+async fn execute(
+    client: &mut KuksaClient,
+    dashboard: &mut Dashboard,
+    command: Command,
+    parse: &impl Fn(&str, proto::DataType) -> Option<Value>,
+) -> Result<bool, String> {
+    match command {
+        Command::Watch(path) => {
+            let entries = client
+                .get_metadata(vec![path.clone()])
+                .await
+                .map_err(describe)?;
+            if entries.is_empty() {
+                return Err(format!("No signal matches {path}"));
+            }
+            dashboard.status = format!("Watching {} signal(s) more", entries.len());
+            dashboard.watch(entries);
+            Ok(true)
+        }
+        Command::Unwatch(path) => {
+            if !dashboard.unwatch(&path) {
+                return Err(format!("{path} isn't watched"));
+            }
+            dashboard.status = format!("Stopped watching {path}");
+            Ok(true)
+        }
+        Command::Publish(path, input) => {
+            let value = parse_value(client, &path, &input, parse).await?;
+            client
+                .set_current_values(HashMap::from([(path.clone(), datapoint(value))]))
+                .await
+                .map_err(describe)?;
+            dashboard.status = format!("Published {input} to {path}");
+            Ok(false)
+        }
+        Command::Actuate(path, input) => {
+            let value = parse_value(client, &path, &input, parse).await?;
+            client
+                .set_target_values(HashMap::from([(path.clone(), datapoint(value))]))
+                .await
+                .map_err(describe)?;
+            dashboard.status = format!("Requested {input} as target of {path}");
+            Ok(false)
+        }
+        Command::Quit => {
+            dashboard.quit = true;
+            Ok(false)
+        }
+    }
+}
+
+/// Show the dashboard for the signals matching `paths` until the user
+/// quits. `parse` converts values entered with `publish` and `actuate`.
+pub async fn run(
+    client: &mut KuksaClient,
+    paths: Vec<String>,
+    parse: impl Fn(&str, proto::DataType) -> Option<Value>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dashboard = Dashboard::new(client.basic_client.get_uri());
+    let (events, mut received) = mpsc::unbounded_channel();
+
+    let mut connection_state = client.basic_client.subscribe_to_connection_state();
+    let connection_events = events.clone();
+    tokio::spawn(async move {
+        while let Some(Ok(state)) = connection_state.next().await {
+            let connected = matches!(state, kuksa_common::ConnectionState::Connected);
+            if connection_events
+                .send(Event::Connection(connected))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    // Key presses are read on a thread of their own, as reading blocks
+    let key_events = events.clone();
+    std::thread::spawn(move || loop {
+        if key_events.is_closed() {
+            break;
+        }
+        match event::poll(REDRAW_INTERVAL) {
+            Ok(true) => match event::read() {
+                Ok(TermEvent::Key(key)) if key.kind == KeyEventKind::Press => {
+                    let _ = key_events.send(Event::Key(key));
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            },
+            Ok(false) => {}
+            Err(_) => break,
+        }
+    });
+
+    match client.basic_client.try_connect().await {
+        Ok(()) => dashboard.connected = true,
+        Err(err) => dashboard.status = describe(err),
+    }
+    for path in paths {
+        if let Err(err) = execute(client, &mut dashboard, Command::Watch(path), &parse).await {
+            dashboard.status = err;
+        }
+    }
+    let mut subscription = match subscribe(client, &dashboard, &events).await {
+        Ok(subscription) => subscription,
+        Err(err) => {
+            dashboard.status = describe(err);
+            None
+        }
+    };
+
+    let mut terminal = ratatui::init();
+    let mut redraw = tokio::time::interval(REDRAW_INTERVAL);
+    let result = loop {
+        if let Err(err) = terminal.draw(|frame| dashboard.draw(frame)) {
+            break Err(err.into());
+        }
+        let event = tokio::select! {
+            event = received.recv() => event,
+            _ = redraw.tick() => continue,
+        };
+        match event {
+            Some(Event::Key(key)) => {
+                let command = match dashboard.handle_key(key) {
+                    Some(Ok(command)) => command,
+                    Some(Err(err)) => {
+                        dashboard.status = err;
+                        continue;
+                    }
+                    None => continue,
+                };
+                match execute(client, &mut dashboard, command, &parse).await {
+                    Ok(true) => {
+                        if let Some(subscription) = subscription.take() {
+                            subscription.abort();
+                        }
+                        match subscribe(client, &dashboard, &events).await {
+                            Ok(resubscribed) => subscription = resubscribed,
+                            Err(err) => dashboard.status = describe(err),
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(err) => dashboard.status = err,
+                }
+                if dashboard.quit {
+                    break Ok(());
+                }
+            }
+            Some(Event::Update(entry)) => dashboard.update(*entry),
+            Some(Event::Connection(connected)) => dashboard.connected = connected,
+            Some(Event::SubscriptionEnded(reason)) => {
+                dashboard.status = format!("Subscription stopped: {reason}, use watch to resume");
+            }
+            None => break Ok(()),
+        }
+    };
+    ratatui::restore();
+    if let Some(subscription) = subscription {
+        subscription.abort();
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn entry(path: &str, value: Option<Value>) -> proto::DataEntry {
+        proto::DataEntry {
+            path: path.to_owned(),
+            value: value.map(datapoint),
+            actuator_target: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            Command::parse("watch Vehicle.Cabin.**"),
+            Ok(Command::Watch("Vehicle.Cabin.**".to_owned()))
+        );
+        assert_eq!(
+            Command::parse("publish Vehicle.Cabin.Infotainment.Media.Played.Track Take Five"),
+            Ok(Command::Publish(
+                "Vehicle.Cabin.Infotainment.Media.Played.Track".to_owned(),
+                "Take Five".to_owned()
+            ))
+        );
+        assert_eq!(Command::parse("quit"), Ok(Command::Quit));
+        assert!(Command::parse("actuate Vehicle.Speed").is_err());
+        assert!(Command::parse("unwatch").is_err());
+        assert!(Command::parse("rm -rf").is_err());
+    }
+
+    #[test]
+    fn test_command_input() {
+        let mut dashboard = Dashboard::new("http://127.0.0.1:55555".to_owned());
+        assert!(dashboard.handle_key(key(KeyCode::Char(':'))).is_none());
+        for c in "quiz".chars() {
+            assert!(dashboard.handle_key(key(KeyCode::Char(c))).is_none());
+        }
+        dashboard.handle_key(key(KeyCode::Backspace));
+        dashboard.handle_key(key(KeyCode::Char('t')));
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Enter)),
+            Some(Ok(Command::Quit))
+        );
+        assert_eq!(dashboard.input, None);
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Char('q'))),
+            Some(Ok(Command::Quit))
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        let mut dashboard = Dashboard::new("http://127.0.0.1:55555".to_owned());
+        dashboard.watch(vec![
+            entry("Vehicle.Speed", None),
+            entry("Vehicle.VehicleIdentification.VIN", None),
+        ]);
+        assert_eq!(dashboard.table.selected(), Some(0));
+
+        for speed in 0..HISTORY_LEN + 2 {
+            dashboard.update(entry("Vehicle.Speed", Some(Value::Float(speed as f32))));
+        }
+        dashboard.update(entry(
+            "Vehicle.VehicleIdentification.VIN",
+            Some(Value::String("WVW".to_owned())),
+        ));
+        // Not watched
+        dashboard.update(entry("Vehicle.TraveledDistance", Some(Value::Float(1.0))));
+        assert_eq!(dashboard.updates, HISTORY_LEN as u64 + 3);
+
+        let (path, speed) = dashboard.selected().unwrap();
+        assert_eq!(path, "Vehicle.Speed");
+        assert_eq!(speed.history.len(), HISTORY_LEN);
+        assert_eq!(speed.history.front(), Some(&2.0));
+        assert!(dashboard.signals["Vehicle.VehicleIdentification.VIN"]
+            .history
+            .is_empty());
+
+        dashboard.watch(vec![entry("Vehicle.Acceleration.Lateral", None)]);
+        assert_eq!(dashboard.selected().unwrap().0, "Vehicle.Speed");
+
+        dashboard.handle_key(key(KeyCode::Down));
+        assert!(dashboard.unwatch("Vehicle.VehicleIdentification.VIN"));
+        assert_eq!(dashboard.selected().unwrap().0, "Vehicle.Speed");
+        assert!(!dashboard.unwatch("Vehicle.VehicleIdentification.VIN"));
+    }
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(
+            scale(&[10.0, 20.0, 15.0]),
+            (vec![1, 100, 51], Some(10.0), Some(20.0))
+        );
+        assert_eq!(scale(&[3.0, 3.0]), (vec![50, 50], Some(3.0), Some(3.0)));
+        assert_eq!(scale(&[]), (vec![], None, None));
+    }
+}
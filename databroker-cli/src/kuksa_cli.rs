@@ -15,27 +15,42 @@ use databroker_proto::kuksa::val as proto;
 use kuksa::*;
 use kuksa_common::ClientTraitV1;
 
-use prost_types::Timestamp;
 use tokio_stream::StreamExt;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
 use ansi_term::Color;
 
+use crate::bulk;
 use crate::cli::ParseError;
 use crate::cli::{self, Cli};
+use crate::dashboard;
+use crate::ping;
+use crate::provider;
+use crate::recorder::{self, Recorder, RotatingFiles};
+use crate::token;
 use linefeed::complete::{Completer, Completion, Suffix};
 use linefeed::terminal::Terminal;
-use linefeed::{Command, Interface, Prompter, ReadResult};
+use linefeed::{Command, DefaultTerminal, Interface, Prompter, ReadResult};
 
 const VERSION: &str = "kuksa.val.v1";
 const TIMEOUT: Duration = Duration::from_millis(500);
 
+/// Name of the connection to the server given on the command line
+const DEFAULT_CONNECTION: &str = "default";
+
 const CLI_COMMANDS: &[(&str, &str, &str)] = &[
-    ("connect", "[URI]", "Connect to server"),
+    (
+        "connect",
+        "[NAME] [URI]",
+        "Connect to server, with NAME as an additional connection",
+    ),
+    ("use", "<NAME>", "Send commands to connection NAME"),
+    ("connections", "", "List connections"),
     ("get", "<PATH> [[PATH] ...]", "Get signal value(s)"),
     ("gettarget", "<PATH> [[PATH] ...]", "Get target value(s)"),
     ("actuate", "<PATH> <VALUE>", "Set actuator signal"),
@@ -129,13 +144,9 @@ async fn handle_actuate_command(
                     continue;
                 }
 
-                let ts = Timestamp::from(SystemTime::now());
                 let datapoints = HashMap::from([(
                     path.to_string(),
-                    proto::v1::Datapoint {
-                        timestamp: Some(ts),
-                        value: Some(data_value.unwrap()),
-                    },
+                    proto::v1::Datapoint::now(data_value.unwrap()),
                 )]);
 
                 match client.set_target_values(datapoints).await {
@@ -175,13 +186,9 @@ async fn handle_publish_command(
                     );
                     continue;
                 }
-                let ts = Timestamp::from(SystemTime::now());
                 let datapoints = HashMap::from([(
                     path.to_string().clone(),
-                    proto::v1::Datapoint {
-                        timestamp: Some(ts),
-                        value: Some(data_value.unwrap()),
-                    },
+                    proto::v1::Datapoint::now(data_value.unwrap()),
                 )]);
 
                 match client.set_current_values(datapoints).await {
@@ -243,27 +250,197 @@ async fn handle_get_command(
     Ok(())
 }
 
-pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Using {VERSION}");
+async fn handle_diff_command(
+    client: &mut KuksaClient,
+    other: &mut KuksaClient,
+    paths: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match kuksa::diff::diff(client, other, paths).await {
+        Ok(diff) => {
+            cli::print_resp_ok("diff")?;
+            for difference in &diff.differences {
+                println!("{difference}");
+            }
+            println!(
+                "{} paths compared, {} differences",
+                diff.compared,
+                diff.differences.len()
+            );
+        }
+        Err(kuksa_common::ClientError::Status(status)) => cli::print_resp_err("diff", &status)?,
+        Err(kuksa_common::ClientError::Connection(msg)) => cli::print_error("diff", msg)?,
+        Err(kuksa_common::ClientError::Function(msg)) => {
+            cli::print_resp_err_fmt("diff", format_args!("Error {msg:?}"))?
+        }
+    }
 
-    let mut subscription_nbr = 1;
+    Ok(())
+}
 
-    let completer = CliCompleter::new();
-    let interface = Arc::new(Interface::new("client")?);
-    interface.set_completer(Arc::new(completer));
+async fn handle_subscribe_command(
+    paths: Vec<String>,
+    log_dir: Option<PathBuf>,
+    rotate: Option<u64>,
+    client: &mut KuksaClient,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut recorder = match log_dir {
+        Some(dir) => Recorder::Files(RotatingFiles::new(
+            dir,
+            rotate.unwrap_or(recorder::DEFAULT_ROTATE_SIZE),
+        )?),
+        None => Recorder::Stdout,
+    };
+    let mut subscription = match client.subscribe(paths).await {
+        Ok(subscription) => subscription,
+        Err(kuksa_common::ClientError::Status(status)) => {
+            return Ok(cli::print_resp_err("subscribe", &status)?)
+        }
+        Err(kuksa_common::ClientError::Connection(msg)) => {
+            return Ok(cli::print_error("subscribe", msg)?)
+        }
+        Err(kuksa_common::ClientError::Function(msg)) => {
+            return Ok(cli::print_resp_err_fmt(
+                "subscribe",
+                format_args!("Error {msg:?}"),
+            )?)
+        }
+    };
+    // Status goes to stderr, keeping stdout to the recorded lines
+    eprintln!("Subscribed, stop with Ctrl+C");
 
-    interface.define_function("enter-function", Arc::new(cli::EnterFunction));
-    interface.bind_sequence("\r", Command::from_str("enter-function"));
-    interface.bind_sequence("\n", Command::from_str("enter-function"));
+    loop {
+        tokio::select! {
+            message = subscription.message() => match message {
+                Ok(Some(response)) => {
+                    for entry in response.updates.into_iter().filter_map(|update| update.entry) {
+                        recorder.record(&entry)?;
+                    }
+                    recorder.flush()?;
+                }
+                Ok(None) => {
+                    eprintln!("Server gone. Subscription stopped");
+                    break;
+                }
+                Err(status) => {
+                    cli::print_resp_err("subscribe", &status)?;
+                    break;
+                }
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    recorder.flush()?;
+    Ok(())
+}
 
-    cli::set_disconnected_prompt(&interface);
+async fn handle_ping_signal_command(
+    client: &mut KuksaClient,
+    path: &str,
+    count: usize,
+    interval: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match ping::ping_signal(client, path, count, interval).await {
+        Ok(stats) => {
+            cli::print_resp_ok("ping-signal")?;
+            println!("{stats}");
+        }
+        Err(err) => cli::print_error("ping-signal", err.to_string())?,
+    }
+    Ok(())
+}
 
-    let mut cli = _cli;
-    let mut client = KuksaClient::new(kuksa_common::to_uri(cli.get_server())?);
+async fn handle_export_vss_command(
+    client: &mut KuksaClient,
+    prefix: Option<String>,
+    out: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match kuksa::vss::export_vss(client, prefix.as_deref()).await {
+        Ok(tree) => {
+            std::fs::write(&out, serde_json::to_string_pretty(&tree)? + "\n")?;
+            cli::print_resp_ok("export-vss")?;
+            cli::print_info(format!("VSS tree written to {}", out.display()))?;
+        }
+        Err(kuksa_common::ClientError::Status(status)) => {
+            cli::print_resp_err("export-vss", &status)?
+        }
+        Err(kuksa_common::ClientError::Connection(msg)) => cli::print_error("export-vss", msg)?,
+        Err(kuksa_common::ClientError::Function(msg)) => {
+            cli::print_resp_err_fmt("export-vss", format_args!("Error {msg:?}"))?
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_target_diff_command(
+    client: &mut KuksaClient,
+    paths: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match kuksa::diff::target_diff(client, paths).await {
+        Ok(pending) => {
+            cli::print_resp_ok("diff")?;
+            let now = SystemTime::now();
+            for actuation in &pending {
+                match actuation
+                    .target_ts
+                    .and_then(|ts| now.duration_since(ts).ok())
+                {
+                    Some(age) => println!("{actuation} (target set {}s ago)", age.as_secs()),
+                    None => println!("{actuation}"),
+                }
+            }
+            println!("{} actuators not at their target", pending.len());
+        }
+        Err(kuksa_common::ClientError::Status(status)) => cli::print_resp_err("diff", &status)?,
+        Err(kuksa_common::ClientError::Connection(msg)) => cli::print_error("diff", msg)?,
+        Err(kuksa_common::ClientError::Function(msg)) => {
+            cli::print_resp_err_fmt("diff", format_args!("Error {msg:?}"))?
+        }
+    }
+
+    Ok(())
+}
+
+/// The connections of the interactive client. Commands go to the active
+/// one unless prefixed with `@NAME`.
+struct Connections {
+    clients: BTreeMap<String, KuksaClient>,
+    /// Shared with the tasks updating the prompt
+    active: Arc<Mutex<String>>,
+}
+
+impl Connections {
+    fn active(&self) -> String {
+        self.active.lock().unwrap().clone()
+    }
+
+    fn set_active(&self, name: &str) {
+        *self.active.lock().unwrap() = name.to_owned();
+    }
+}
+
+/// Split off the `@NAME` a command is prefixed with.
+fn split_target(line: &str) -> (Option<&str>, &str) {
+    let (first, rest) = cli::split_first_word(line);
+    match first.strip_prefix('@') {
+        Some(name) => (Some(name), rest),
+        None => (None, line),
+    }
+}
+
+/// A client for `uri`, using the token file given on the command line or
+/// the token cached by `token login`.
+async fn new_client(uri: &str, cli: &mut Cli) -> Result<KuksaClient, Box<dyn std::error::Error>> {
+    let mut client = KuksaClient::new(kuksa_common::to_uri(uri)?);
 
     if let Some(token_filename) = cli.get_token_file() {
-        let token = std::fs::read_to_string(token_filename)?;
-        client.basic_client.set_access_token(token)?;
+        client.basic_client.set_access_token_file(token_filename)?;
+    } else {
+        match token::cached_access_token().await {
+            Ok(Some(token)) => client.basic_client.set_access_token(token)?,
+            Ok(None) => {}
+            Err(err) => eprintln!("Warning: not using cached access token: {err}"),
+        }
     }
 
     #[cfg(feature = "tls")]
@@ -276,20 +453,36 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         client.basic_client.set_tls_config(tls_config);
     }
 
+    Ok(client)
+}
+
+fn set_prompt(interface: &Arc<Interface<DefaultTerminal>>, name: &str, connected: bool) {
+    match (connected, name) {
+        (false, _) => cli::set_disconnected_prompt(interface),
+        (true, DEFAULT_CONNECTION) => cli::set_connected_prompt(interface, VERSION.to_string()),
+        (true, name) => cli::set_connected_prompt(interface, format!("{VERSION} @{name}")),
+    }
+}
+
+/// Keep the prompt up to date with the state of connection `name` while
+/// it's the active one.
+fn watch_connection_state(
+    name: String,
+    client: &mut KuksaClient,
+    interface: Arc<Interface<DefaultTerminal>>,
+    active: Arc<Mutex<String>>,
+) {
     let mut connection_state_subscription = client.basic_client.subscribe_to_connection_state();
-    let interface_ref = interface.clone();
 
     tokio::spawn(async move {
         while let Some(state) = connection_state_subscription.next().await {
             match state {
-                Ok(state) => match state {
-                    kuksa_common::ConnectionState::Connected => {
-                        cli::set_connected_prompt(&interface_ref, VERSION.to_string());
+                Ok(state) => {
+                    if *active.lock().unwrap() == name {
+                        let connected = matches!(state, kuksa_common::ConnectionState::Connected);
+                        set_prompt(&interface, &name, connected);
                     }
-                    kuksa_common::ConnectionState::Disconnected => {
-                        cli::set_disconnected_prompt(&interface_ref);
-                    }
-                },
+                }
                 Err(err) => {
                     cli::print_error(
                         "connection",
@@ -300,20 +493,148 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     });
+}
+
+async fn refresh_completer(
+    interface: &Arc<Interface<DefaultTerminal>>,
+    client: &mut KuksaClient,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if client.basic_client.is_connected() {
+        if let Some(entries) = handle_get_metadata(vec!["**"], client).await? {
+            interface.set_completer(Arc::new(CliCompleter::from_metadata(&entries)));
+        }
+    }
+    Ok(())
+}
+
+pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Using {VERSION}");
+
+    let mut subscription_nbr = 1;
+
+    let completer = CliCompleter::new();
+    let interface = Arc::new(Interface::new("client")?);
+    interface.set_completer(Arc::new(completer));
+
+    interface.define_function("enter-function", Arc::new(cli::EnterFunction));
+    interface.bind_sequence("\r", Command::from_str("enter-function"));
+    interface.bind_sequence("\n", Command::from_str("enter-function"));
+
+    cli::set_disconnected_prompt(&interface);
+
+    let mut cli = _cli;
+    let server = cli.get_server();
+    let mut client = new_client(&server, &mut cli).await?;
+
+    let active = Arc::new(Mutex::new(DEFAULT_CONNECTION.to_owned()));
+    watch_connection_state(
+        DEFAULT_CONNECTION.to_owned(),
+        &mut client,
+        interface.clone(),
+        active.clone(),
+    );
 
     match cli.get_command() {
         Some(cli::Commands::Get { paths }) => {
             return handle_get_command(paths, &mut client).await;
         }
-        Some(cli::Commands::Set { path: _, value: _ }) => {
+        Some(cli::Commands::Set {
+            from_file: Some(file),
+            ..
+        }) => {
+            return bulk::apply(&mut client, &file, bulk::Mode::Set, |input, data_type| {
+                try_into_data_value(input, data_type).ok()
+            })
+            .await;
+        }
+        Some(cli::Commands::Set { .. }) => {
             unimplemented!("The set command is not implemented for kuksa.val.v1 protocol because it is not intended to be named like this anymore. Use publish instead.");
         }
         Some(cli::Commands::Actuate { path, value }) => {
             return handle_actuate_command(&path, &value, &mut client).await;
         }
-        Some(cli::Commands::Publish { path, value }) => {
+        Some(cli::Commands::Publish {
+            from_file: Some(file),
+            ..
+        }) => {
+            return bulk::apply(
+                &mut client,
+                &file,
+                bulk::Mode::Publish,
+                |input, data_type| try_into_data_value(input, data_type).ok(),
+            )
+            .await;
+        }
+        Some(cli::Commands::Publish { path, value, .. }) => {
+            let (Some(path), Some(value)) = (path, value) else {
+                unreachable!("PATH and VALUE are required without --from-file");
+            };
             return handle_publish_command(&path, &value, &mut client).await;
         }
+        Some(cli::Commands::Subscribe {
+            paths,
+            log_dir,
+            rotate,
+        }) => {
+            return handle_subscribe_command(paths, log_dir, rotate, &mut client).await;
+        }
+        Some(cli::Commands::Diff {
+            other,
+            other_token_file,
+            paths,
+        }) => {
+            let Some(other) = other else {
+                return handle_target_diff_command(&mut client, paths).await;
+            };
+            let mut other_client = KuksaClient::new(kuksa_common::to_uri(other)?);
+            if let Some(token_filename) = other_token_file.or(cli.get_token_file()) {
+                let token = std::fs::read_to_string(token_filename)?;
+                other_client.basic_client.set_access_token(token)?;
+            }
+            #[cfg(feature = "tls")]
+            if let Some(ca_cert_filename) = cli.get_ca_cert() {
+                let pem = std::fs::read(ca_cert_filename)?;
+                let ca_cert = tonic::transport::Certificate::from_pem(pem);
+                let tls_config = tonic::transport::ClientTlsConfig::new().ca_certificate(ca_cert);
+                other_client.basic_client.set_tls_config(tls_config);
+            }
+            return handle_diff_command(&mut client, &mut other_client, paths).await;
+        }
+        Some(cli::Commands::Provide {
+            path,
+            rate,
+            pattern,
+        }) => {
+            return provider::provide(&mut client, &path, rate, pattern, |input, data_type| {
+                try_into_data_value(input, data_type).ok()
+            })
+            .await;
+        }
+        Some(cli::Commands::PingSignal {
+            path,
+            count,
+            interval,
+        }) => {
+            return handle_ping_signal_command(
+                &mut client,
+                &path,
+                count,
+                Duration::from_millis(interval),
+            )
+            .await;
+        }
+        Some(cli::Commands::ExportVss { prefix, out }) => {
+            return handle_export_vss_command(&mut client, prefix, out).await;
+        }
+        Some(cli::Commands::Dashboard { paths }) => {
+            return dashboard::run(&mut client, paths, |input, data_type| {
+                try_into_data_value(input, data_type).ok()
+            })
+            .await;
+        }
+        Some(cli::Commands::Token { command }) => {
+            return token::handle_token_command(command).await;
+        }
         None => {
             // No subcommand => run interactive client
             let version = match option_env!("CARGO_PKG_VERSION") {
@@ -343,11 +664,27 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    let mut connections = Connections {
+        clients: BTreeMap::from([(DEFAULT_CONNECTION.to_owned(), client)]),
+        active,
+    };
+
     loop {
         if let Some(res) = interface.read_line_step(Some(TIMEOUT))? {
             match res {
                 ReadResult::Input(line) => {
-                    let (cmd, args) = cli::split_first_word(&line);
+                    let (target, input) = split_target(&line);
+                    let (cmd, args) = cli::split_first_word(input);
+                    let name = match target {
+                        Some(name) => name.to_owned(),
+                        None => connections.active(),
+                    };
+                    let is_active = name == connections.active();
+                    let Some(client) = connections.clients.get_mut(&name) else {
+                        interface.add_history_unique(line.clone());
+                        cli::print_error(cmd, format!("Unknown connection \"{name}\""))?;
+                        continue;
+                    };
                     match cmd {
                         "help" => {
                             println!();
@@ -355,6 +692,10 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 println!("  {:24} {}", format!("{cmd} {args}"), help);
                             }
                             println!();
+                            println!(
+                                "  Prefix a command with @NAME to send it to connection NAME."
+                            );
+                            println!();
                         }
                         "get" => {
                             interface.add_history_unique(line.clone());
@@ -368,7 +709,7 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 .map(|path| path.to_owned())
                                 .collect();
 
-                            handle_get_command(paths, &mut client).await?
+                            handle_get_command(paths, client).await?
                         }
                         "gettarget" => {
                             interface.add_history_unique(line.clone());
@@ -424,7 +765,7 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 Ok(()) => {
                                     cli::print_info("Access token set.")?;
                                     if let Some(entries) =
-                                        handle_get_metadata(vec![], &mut client).await.unwrap()
+                                        handle_get_metadata(vec![], client).await.unwrap()
                                     {
                                         interface.set_completer(Arc::new(
                                             CliCompleter::from_metadata(&entries),
@@ -450,7 +791,7 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     Ok(()) => {
                                         cli::print_info("Access token set.")?;
                                         if let Some(entries) =
-                                            handle_get_metadata(vec![], &mut client).await.unwrap()
+                                            handle_get_metadata(vec![], client).await.unwrap()
                                         {
                                             interface.set_completer(Arc::new(
                                                 CliCompleter::from_metadata(&entries),
@@ -479,7 +820,7 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 continue;
                             }
 
-                            handle_actuate_command(path, value, &mut client).await?
+                            handle_actuate_command(path, value, client).await?
                         }
                         "publish" => {
                             interface.add_history_unique(line.clone());
@@ -491,7 +832,7 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 continue;
                             }
 
-                            handle_publish_command(path, value, &mut client).await?
+                            handle_publish_command(path, value, client).await?
                         }
                         "subscribe" => {
                             interface.add_history_unique(line.clone());
@@ -616,6 +957,76 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                         }
+                        "use" => {
+                            interface.add_history_unique(line.clone());
+
+                            if args.is_empty() {
+                                print_usage(cmd);
+                                continue;
+                            }
+                            let name = args.trim();
+                            let Some(client) = connections.clients.get_mut(name) else {
+                                cli::print_error(cmd, format!("Unknown connection \"{name}\""))?;
+                                continue;
+                            };
+                            set_prompt(&interface, name, client.basic_client.is_connected());
+                            cli::print_info(format!(
+                                "Using connection {name} to {}",
+                                client.basic_client.get_uri()
+                            ))?;
+                            refresh_completer(&interface, client).await?;
+                            connections.set_active(name);
+                        }
+                        "connections" => {
+                            interface.add_history_unique(line.clone());
+
+                            let active = connections.active();
+                            for (name, client) in &connections.clients {
+                                println!(
+                                    "{} {name}: {} ({})",
+                                    if *name == active { "*" } else { " " },
+                                    client.basic_client.get_uri(),
+                                    if client.basic_client.is_connected() {
+                                        "connected"
+                                    } else {
+                                        "not connected"
+                                    }
+                                );
+                            }
+                        }
+                        "connect" if args.split_whitespace().count() == 2 => {
+                            interface.add_history_unique(line.clone());
+
+                            let (name, uri) = cli::split_first_word(args);
+                            let mut client = match new_client(uri, &mut cli).await {
+                                Ok(client) => client,
+                                Err(err) => {
+                                    cli::print_error(
+                                        cmd,
+                                        format!("Failed to parse endpoint address: {err}"),
+                                    )?;
+                                    continue;
+                                }
+                            };
+                            watch_connection_state(
+                                name.to_owned(),
+                                &mut client,
+                                interface.clone(),
+                                connections.active.clone(),
+                            );
+                            match client.basic_client.try_connect().await {
+                                Ok(()) => {
+                                    cli::print_info(format!(
+                                        "[{cmd}] Successfully connected {name} to {}",
+                                        client.basic_client.get_uri()
+                                    ))?;
+                                }
+                                Err(err) => {
+                                    cli::print_error(cmd, format!("{err}"))?;
+                                }
+                            }
+                            connections.clients.insert(name.to_owned(), client);
+                        }
                         "connect" => {
                             interface.add_history_unique(line.clone());
                             if !client.basic_client.is_connected() || !args.is_empty() {
@@ -658,14 +1069,8 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                         }
                                     }
                                 };
-                                if client.basic_client.is_connected() {
-                                    if let Some(entries) =
-                                        handle_get_metadata(vec!["**"], &mut client).await.unwrap()
-                                    {
-                                        interface.set_completer(Arc::new(
-                                            CliCompleter::from_metadata(&entries),
-                                        ));
-                                    }
+                                if is_active {
+                                    refresh_completer(&interface, client).await?;
                                 }
                             };
                         }
@@ -677,7 +1082,7 @@ pub async fn kuksa_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                             if paths.is_empty() {
                                 cli::print_info("If you want to list metadata of signals, use `metadata PATTERN`")?;
                             } else if let Some(entries) =
-                                handle_get_metadata(paths, &mut client).await.unwrap()
+                                handle_get_metadata(paths, client).await.unwrap()
                             {
                                 cli::print_resp_ok(cmd)?;
                                 if !entries.is_empty() {
@@ -1296,4 +1701,17 @@ mod test {
         let max = 7;
         assert_eq!("hej     1    4", format!("{:<max$} {:<4} {}", "hej", 1, 4));
     }
+
+    #[test]
+    fn test_split_target() {
+        assert_eq!(
+            split_target("@bench get Vehicle.Speed"),
+            (Some("bench"), "get Vehicle.Speed")
+        );
+        assert_eq!(
+            split_target("get Vehicle.Speed"),
+            (None, "get Vehicle.Speed")
+        );
+        assert_eq!(split_target("@twin"), (Some("twin"), ""));
+    }
 }
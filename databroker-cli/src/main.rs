@@ -14,9 +14,15 @@
 use clap::Parser;
 use cli::Protocol;
 
+mod bulk;
 pub mod cli;
+mod dashboard;
 mod kuksa_cli;
+mod ping;
+mod provider;
+mod recorder;
 mod sdv_cli;
+mod token;
 
 #[tokio::main]
 async fn main() {
@@ -0,0 +1,308 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Round-trip latency of the broker: publish a value of a signal and wait
+//! until it arrives on a subscription to the same signal.
+//!
+//! Two values are published in turn, so that every update is a change. They
+//! are taken from the signal's value restriction where it has one.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use databroker_proto::kuksa::val::v1 as proto;
+use kuksa::api::KuksaClientApi;
+use kuksa::ClientError;
+use proto::datapoint::Value;
+use proto::value_restriction::Type as Restriction;
+use tokio_stream::StreamExt;
+
+/// Updates taking longer than this count as lost
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum Error {
+    Client(ClientError),
+    Unsupported(String),
+    SubscriptionEnded,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Client(ClientError::Status(status)) => {
+                write!(f, "{:?}: {}", status.code(), status.message())
+            }
+            Error::Client(ClientError::Connection(msg)) => write!(f, "{msg}"),
+            Error::Client(ClientError::Function(errors)) => write!(f, "{errors:?}"),
+            Error::Unsupported(msg) => write!(f, "{msg}"),
+            Error::SubscriptionEnded => write!(f, "subscription ended"),
+        }
+    }
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        Error::Client(err)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PingStats {
+    pub sent: usize,
+    /// Round-trip times of the updates received, sorted
+    pub latencies: Vec<Duration>,
+}
+
+impl PingStats {
+    /// Nearest-rank percentile, `p` in 0..=100.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (p / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        Some(self.latencies[rank.clamp(1, self.latencies.len()) - 1])
+    }
+}
+
+impl fmt::Display for PingStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sent, {} received", self.sent, self.latencies.len())?;
+        if let (Some(min), Some(max)) = (self.latencies.first(), self.latencies.last()) {
+            let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+            write!(
+                f,
+                "\nmin {:.3} ms, p50 {:.3} ms, p90 {:.3} ms, p99 {:.3} ms, max {:.3} ms",
+                ms(*min),
+                ms(self.percentile(50.0).unwrap_or_default()),
+                ms(self.percentile(90.0).unwrap_or_default()),
+                ms(self.percentile(99.0).unwrap_or_default()),
+                ms(*max),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Publish `count` values of `path`, one every `interval`, and measure
+/// how long each takes to come back on a subscription.
+pub async fn ping_signal(
+    client: &mut impl KuksaClientApi,
+    path: &str,
+    count: usize,
+    interval: Duration,
+) -> Result<PingStats, Error> {
+    let metadata = client
+        .get_metadata(vec![path.to_owned()])
+        .await?
+        .into_iter()
+        .find(|entry| entry.path == path)
+        .and_then(|entry| entry.metadata)
+        .ok_or_else(|| Error::Unsupported(format!("{path} is not a signal")))?;
+    let values = ping_values(&metadata)
+        .ok_or_else(|| Error::Unsupported(format!("{path} has no supported data type")))?;
+
+    // Start with the value that isn't current, otherwise there's no update
+    let current = client
+        .get_current_values(vec![path.to_owned()])
+        .await?
+        .into_iter()
+        .next()
+        .and_then(|entry| entry.value)
+        .and_then(|datapoint| datapoint.value);
+    let offset = usize::from(current.as_ref() == Some(&values[0]));
+
+    let mut subscription = client
+        .subscribe_current_values(vec![path.to_owned()])
+        .await?;
+
+    let mut stats = PingStats::default();
+    for i in 0..count {
+        if i > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        let value = values[(i + offset) % 2].clone();
+        let start = Instant::now();
+        client
+            .set_current_values(HashMap::from([(
+                path.to_owned(),
+                proto::Datapoint {
+                    timestamp: None,
+                    value: Some(value.clone()),
+                },
+            )]))
+            .await?;
+        stats.sent += 1;
+
+        let received = tokio::time::timeout(RECEIVE_TIMEOUT, async {
+            while let Some(response) = subscription.next().await {
+                let response =
+                    response.map_err(|status| Error::Client(ClientError::Status(status)))?;
+                let arrived = response.updates.iter().any(|update| {
+                    update
+                        .entry
+                        .as_ref()
+                        .and_then(|entry| entry.value.as_ref())
+                        .and_then(|datapoint| datapoint.value.as_ref())
+                        == Some(&value)
+                });
+                if arrived {
+                    return Ok(());
+                }
+            }
+            Err(Error::SubscriptionEnded)
+        })
+        .await;
+        match received {
+            Ok(Ok(())) => stats.latencies.push(start.elapsed()),
+            Ok(Err(err)) => return Err(err),
+            // Lost, the next value is a change again
+            Err(_) => {}
+        }
+    }
+    stats.latencies.sort();
+    Ok(stats)
+}
+
+/// Two different values valid for the signal.
+fn ping_values(metadata: &proto::Metadata) -> Option<[Value; 2]> {
+    let restriction = metadata
+        .value_restriction
+        .as_ref()
+        .and_then(|restriction| restriction.r#type.as_ref());
+    let (low, high) = match restriction {
+        Some(Restriction::Signed(r)) if r.allowed_values.len() >= 2 => {
+            (r.allowed_values[0] as f64, r.allowed_values[1] as f64)
+        }
+        Some(Restriction::Signed(r)) => (
+            r.min.unwrap_or(0) as f64,
+            r.max.unwrap_or(r.min.unwrap_or(0) + 1) as f64,
+        ),
+        Some(Restriction::Unsigned(r)) if r.allowed_values.len() >= 2 => {
+            (r.allowed_values[0] as f64, r.allowed_values[1] as f64)
+        }
+        Some(Restriction::Unsigned(r)) => (
+            r.min.unwrap_or(0) as f64,
+            r.max.unwrap_or(r.min.unwrap_or(0) + 1) as f64,
+        ),
+        Some(Restriction::FloatingPoint(r)) => (
+            r.min.unwrap_or(0.0),
+            r.max.unwrap_or(r.min.unwrap_or(0.0) + 1.0),
+        ),
+        _ => (0.0, 1.0),
+    };
+
+    let values = match proto::DataType::try_from(metadata.data_type).ok()? {
+        proto::DataType::Boolean => [Value::Bool(false), Value::Bool(true)],
+        proto::DataType::String => match restriction {
+            Some(Restriction::String(r)) if r.allowed_values.len() >= 2 => [
+                Value::String(r.allowed_values[0].clone()),
+                Value::String(r.allowed_values[1].clone()),
+            ],
+            _ => [
+                Value::String("ping".to_owned()),
+                Value::String("pong".to_owned()),
+            ],
+        },
+        proto::DataType::Int8 | proto::DataType::Int16 | proto::DataType::Int32 => {
+            [Value::Int32(low as i32), Value::Int32(high as i32)]
+        }
+        proto::DataType::Int64 => [Value::Int64(low as i64), Value::Int64(high as i64)],
+        proto::DataType::Uint8 | proto::DataType::Uint16 | proto::DataType::Uint32 => {
+            [Value::Uint32(low as u32), Value::Uint32(high as u32)]
+        }
+        proto::DataType::Uint64 => [Value::Uint64(low as u64), Value::Uint64(high as u64)],
+        proto::DataType::Float => [Value::Float(low as f32), Value::Float(high as f32)],
+        proto::DataType::Double => [Value::Double(low), Value::Double(high)],
+        _ => return None,
+    };
+    Some(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kuksa::mock::MockClient;
+
+    fn metadata(data_type: proto::DataType) -> proto::Metadata {
+        proto::Metadata {
+            data_type: data_type.into(),
+            entry_type: proto::EntryType::Sensor.into(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ping_signal() {
+        let mock = MockClient::new();
+        mock.set_metadata("Vehicle.Speed", metadata(proto::DataType::Float));
+        mock.set_current_value("Vehicle.Speed", Value::Float(0.0));
+
+        let stats = ping_signal(&mut mock.clone(), "Vehicle.Speed", 3, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(stats.sent, 3);
+        assert_eq!(stats.latencies.len(), 3);
+        // Started with the value that wasn't current
+        assert_eq!(
+            mock.current_value("Vehicle.Speed").unwrap().value,
+            Some(Value::Float(1.0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ping_unsupported() {
+        let mock = MockClient::new();
+        mock.set_metadata(
+            "Vehicle.Cabin.Seats",
+            metadata(proto::DataType::StringArray),
+        );
+        assert!(matches!(
+            ping_signal(&mut mock.clone(), "Vehicle.Cabin.Seats", 1, Duration::ZERO).await,
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_ping_values() {
+        let mut restricted = metadata(proto::DataType::Uint8);
+        restricted.value_restriction = Some(proto::ValueRestriction {
+            r#type: Some(Restriction::Unsigned(proto::ValueRestrictionUint {
+                min: Some(10),
+                max: Some(20),
+                allowed_values: vec![],
+            })),
+        });
+        assert_eq!(
+            ping_values(&restricted),
+            Some([Value::Uint32(10), Value::Uint32(20)])
+        );
+        assert_eq!(
+            ping_values(&metadata(proto::DataType::Boolean)),
+            Some([Value::Bool(false), Value::Bool(true)])
+        );
+    }
+
+    #[test]
+    fn test_percentile() {
+        let stats = PingStats {
+            sent: 10,
+            latencies: (1..=10).map(Duration::from_millis).collect(),
+        };
+        assert_eq!(stats.percentile(50.0), Some(Duration::from_millis(5)));
+        assert_eq!(stats.percentile(90.0), Some(Duration::from_millis(9)));
+        assert_eq!(stats.percentile(99.0), Some(Duration::from_millis(10)));
+        assert_eq!(PingStats::default().percentile(50.0), None);
+    }
+}
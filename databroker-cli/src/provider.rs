@@ -0,0 +1,267 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Feed a signal with generated values, standing in for a real provider
+//! while testing applications.
+//!
+//! Generated values follow a pattern over [`PERIOD`] between the minimum
+//! and maximum of the signal (or 0 and 100 if it has none). In manual mode
+//! every line read from stdin is published instead. If the signal is an
+//! actuator, its targets are applied as current values as well.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use databroker_proto::kuksa::val::v1 as proto;
+use kuksa::api::KuksaClientApi;
+use proto::datapoint::Value;
+use proto::value_restriction::Type as Restriction;
+use tokio::io::AsyncBufReadExt;
+use tokio_stream::StreamExt;
+
+use crate::cli;
+
+/// Duration of one cycle of the generated patterns
+pub const PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Pattern {
+    /// Sine wave between minimum and maximum
+    Sine,
+    /// Minimum for the first half of the period, maximum for the second
+    Step,
+    /// Publish the values entered on stdin, one per line
+    Manual,
+}
+
+/// Parse a rate like `10hz`, `0.5Hz` or `10`.
+pub fn parse_rate(rate: &str) -> Result<f64, String> {
+    let rate = rate.trim();
+    let number = rate
+        .strip_suffix("hz")
+        .or_else(|| rate.strip_suffix("Hz"))
+        .unwrap_or(rate)
+        .trim();
+    match number.parse::<f64>() {
+        Ok(hz) if hz > 0.0 && hz.is_finite() => Ok(hz),
+        _ => Err(format!("invalid rate \"{rate}\"")),
+    }
+}
+
+/// Publish values of `path` until stopped with Ctrl+C. `parse` converts
+/// lines entered in manual mode.
+pub async fn provide(
+    client: &mut impl KuksaClientApi,
+    path: &str,
+    rate: f64,
+    pattern: Pattern,
+    parse: impl Fn(&str, proto::DataType) -> Option<Value>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = client
+        .get_metadata(vec![path.to_owned()])
+        .await?
+        .into_iter()
+        .find(|entry| entry.path == path)
+        .and_then(|entry| entry.metadata)
+        .ok_or_else(|| format!("{path} is not a signal"))?;
+    let data_type = proto::DataType::try_from(metadata.data_type)?;
+    let range = value_range(&metadata);
+    if pattern != Pattern::Manual && pattern_value(pattern, data_type, range, PERIOD).is_none() {
+        return Err(format!(
+            "the {pattern:?} pattern needs a numeric or boolean signal, use --pattern manual"
+        )
+        .into());
+    }
+
+    let mut targets = if metadata.entry_type == proto::EntryType::Actuator as i32 {
+        Some(
+            client
+                .subscribe_target_values(vec![path.to_owned()])
+                .await?,
+        )
+    } else {
+        None
+    };
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+    let started = Instant::now();
+
+    match pattern {
+        Pattern::Manual => eprintln!("Providing {path}, enter values, stop with Ctrl+C"),
+        _ => eprintln!("Providing {path} ({pattern:?} at {rate} Hz), stop with Ctrl+C"),
+    }
+    loop {
+        let value = tokio::select! {
+            _ = ticker.tick(), if pattern != Pattern::Manual => {
+                pattern_value(pattern, data_type, range, started.elapsed())
+            }
+            line = lines.next_line(), if pattern == Pattern::Manual => {
+                let Some(line) = line? else {
+                    break;
+                };
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let value = parse(line, data_type);
+                if value.is_none() {
+                    let msg = format!("Could not parse \"{line}\" as {data_type:?}");
+                    cli::print_error("provide", msg)?;
+                }
+                value
+            }
+            target = async {
+                match &mut targets {
+                    Some(targets) => targets.next().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                let Some(response) = target else {
+                    return Err("target subscription ended".into());
+                };
+                let target = response?
+                    .updates
+                    .into_iter()
+                    .filter_map(|update| update.entry?.actuator_target?.value)
+                    .next_back();
+                if let Some(target) = &target {
+                    cli::print_info(format!("Applying target {target:?}"))?;
+                }
+                target
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        };
+        let Some(value) = value else {
+            continue;
+        };
+        client
+            .set_current_values(HashMap::from([(
+                path.to_owned(),
+                proto::Datapoint {
+                    timestamp: None,
+                    value: Some(value),
+                },
+            )]))
+            .await?;
+    }
+    Ok(())
+}
+
+fn value_range(metadata: &proto::Metadata) -> (f64, f64) {
+    let (min, max) = match metadata
+        .value_restriction
+        .as_ref()
+        .and_then(|restriction| restriction.r#type.as_ref())
+    {
+        Some(Restriction::Signed(r)) => (r.min.map(|v| v as f64), r.max.map(|v| v as f64)),
+        Some(Restriction::Unsigned(r)) => (r.min.map(|v| v as f64), r.max.map(|v| v as f64)),
+        Some(Restriction::FloatingPoint(r)) => (r.min, r.max),
+        _ => (None, None),
+    };
+    match (min, max) {
+        (Some(min), Some(max)) => (min, max),
+        (Some(min), None) => (min, min + 100.0),
+        (None, Some(max)) => (max - 100.0, max),
+        (None, None) => (0.0, 100.0),
+    }
+}
+
+/// Value of `pattern` at `elapsed` for a signal ranging from `low` to
+/// `high`, `None` if the data type isn't supported.
+fn pattern_value(
+    pattern: Pattern,
+    data_type: proto::DataType,
+    (low, high): (f64, f64),
+    elapsed: Duration,
+) -> Option<Value> {
+    let phase = (elapsed.as_secs_f64() / PERIOD.as_secs_f64()).fract();
+    let level = match pattern {
+        Pattern::Sine => (1.0 + (2.0 * PI * phase).sin()) / 2.0,
+        Pattern::Step if phase < 0.5 => 0.0,
+        Pattern::Step => 1.0,
+        Pattern::Manual => return None,
+    };
+    let value = low + level * (high - low);
+    Some(match data_type {
+        proto::DataType::Boolean => Value::Bool(level >= 0.5),
+        proto::DataType::Int8 | proto::DataType::Int16 | proto::DataType::Int32 => {
+            Value::Int32(value.round() as i32)
+        }
+        proto::DataType::Int64 => Value::Int64(value.round() as i64),
+        proto::DataType::Uint8 | proto::DataType::Uint16 | proto::DataType::Uint32 => {
+            Value::Uint32(value.round() as u32)
+        }
+        proto::DataType::Uint64 => Value::Uint64(value.round() as u64),
+        proto::DataType::Float => Value::Float(value as f32),
+        proto::DataType::Double => Value::Double(value),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("10hz"), Ok(10.0));
+        assert_eq!(parse_rate("0.5 Hz"), Ok(0.5));
+        assert_eq!(parse_rate("2"), Ok(2.0));
+        assert!(parse_rate("0hz").is_err());
+        assert!(parse_rate("fast").is_err());
+    }
+
+    #[test]
+    fn test_pattern_value() {
+        let quarter = PERIOD / 4;
+        let range = (0.0, 100.0);
+        assert_eq!(
+            pattern_value(Pattern::Sine, proto::DataType::Float, range, Duration::ZERO),
+            Some(Value::Float(50.0))
+        );
+        assert_eq!(
+            pattern_value(Pattern::Sine, proto::DataType::Uint8, range, quarter),
+            Some(Value::Uint32(100))
+        );
+        assert_eq!(
+            pattern_value(Pattern::Step, proto::DataType::Int32, (-5.0, 5.0), quarter),
+            Some(Value::Int32(-5))
+        );
+        assert_eq!(
+            pattern_value(Pattern::Step, proto::DataType::Boolean, range, quarter * 3),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(
+            pattern_value(Pattern::Sine, proto::DataType::String, range, quarter),
+            None
+        );
+    }
+
+    #[test]
+    fn test_value_range() {
+        let metadata = proto::Metadata {
+            value_restriction: Some(proto::ValueRestriction {
+                r#type: Some(Restriction::Unsigned(proto::ValueRestrictionUint {
+                    min: Some(10),
+                    max: None,
+                    allowed_values: vec![],
+                })),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(value_range(&metadata), (10.0, 110.0));
+        assert_eq!(value_range(&proto::Metadata::default()), (0.0, 100.0));
+    }
+}
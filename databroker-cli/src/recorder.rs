@@ -0,0 +1,244 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Recording of subscribed updates as JSON lines, one update per line:
+//!
+//! ```text
+//! {"path":"Vehicle.Speed","source_ts":1700000000100,"ts":1700000000123,"value":42.5}
+//! ```
+//!
+//! `ts` is when the update was received, `source_ts` the timestamp of the
+//! datapoint, both in milliseconds since the UNIX epoch. Written to a
+//! directory, the recording is split into files of a maximum size.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use databroker_proto::kuksa::val::v1 as proto;
+use serde_json::json;
+
+/// Rotate files at this size unless told otherwise
+pub const DEFAULT_ROTATE_SIZE: u64 = 100 * 1000 * 1000;
+
+/// Parse a size like `100MB`, `512KiB` or `4096`.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size \"{size}\""))?;
+    let factor = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1000,
+        "M" | "MB" => 1000 * 1000,
+        "G" | "GB" => 1000 * 1000 * 1000,
+        "KIB" => 1 << 10,
+        "MIB" => 1 << 20,
+        "GIB" => 1 << 30,
+        _ => return Err(format!("invalid size unit \"{unit}\"")),
+    };
+    match number.checked_mul(factor) {
+        Some(0) | None => Err(format!("invalid size \"{size}\"")),
+        Some(bytes) => Ok(bytes),
+    }
+}
+
+/// Where recorded lines go.
+pub enum Recorder {
+    Stdout,
+    Files(RotatingFiles),
+}
+
+impl Recorder {
+    pub fn record(&mut self, entry: &proto::DataEntry) -> io::Result<()> {
+        let Some(line) = to_json_line(entry, SystemTime::now()) else {
+            return Ok(());
+        };
+        match self {
+            Recorder::Stdout => println!("{line}"),
+            Recorder::Files(files) => files.write_line(&line)?,
+        }
+        Ok(())
+    }
+
+    /// Flush buffered lines, called after each batch of updates.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Recorder::Stdout => io::stdout().flush(),
+            Recorder::Files(files) => files.flush(),
+        }
+    }
+}
+
+/// Files `updates-<start>-<n>.jsonl` in a directory, starting a new one
+/// once the current one would exceed `max_size` bytes.
+pub struct RotatingFiles {
+    dir: PathBuf,
+    max_size: u64,
+    /// Milliseconds since the UNIX epoch, keeps recordings apart
+    started: u128,
+    index: usize,
+    file: Option<BufWriter<File>>,
+    written: u64,
+}
+
+impl RotatingFiles {
+    pub fn new(dir: impl Into<PathBuf>, max_size: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(RotatingFiles {
+            dir,
+            max_size,
+            started: millis(SystemTime::now()),
+            index: 0,
+            file: None,
+            written: 0,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        // A line larger than max_size still gets a file of its own
+        if self.written > 0 && self.written + len > self.max_size {
+            self.flush()?;
+            self.file = None;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                self.index += 1;
+                self.written = 0;
+                let path = self
+                    .dir
+                    .join(format!("updates-{}-{:04}.jsonl", self.started, self.index));
+                self.file.insert(BufWriter::new(File::create(path)?))
+            }
+        };
+        writeln!(file, "{line}")?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn to_json_line(entry: &proto::DataEntry, received: SystemTime) -> Option<String> {
+    let datapoint = entry.value.as_ref()?;
+    let source_ts = datapoint
+        .timestamp
+        .as_ref()
+        .map(|ts| i128::from(ts.seconds) * 1000 + i128::from(ts.nanos) / 1_000_000);
+    let line = json!({
+        "ts": millis(received),
+        "path": entry.path,
+        "value": datapoint.value.as_ref().map(kuksa::branch::to_json),
+        "source_ts": source_ts,
+    });
+    Some(line.to_string())
+}
+
+fn millis(time: SystemTime) -> u128 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry(path: &str, value: proto::datapoint::Value) -> proto::DataEntry {
+        proto::DataEntry {
+            path: path.to_owned(),
+            value: Some(proto::Datapoint {
+                timestamp: Some(prost_types::Timestamp {
+                    seconds: 1_700_000_000,
+                    nanos: 100_000_000,
+                }),
+                value: Some(value),
+            }),
+            actuator_target: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100MB"), Ok(100_000_000));
+        assert_eq!(parse_size("512 KiB"), Ok(512 * 1024));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("1g"), Ok(1_000_000_000));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_json_line() {
+        let received = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(
+            to_json_line(
+                &entry("Vehicle.Speed", proto::datapoint::Value::Float(42.5)),
+                received
+            )
+            .unwrap(),
+            r#"{"path":"Vehicle.Speed","source_ts":1700000000100,"ts":1700000000123,"value":42.5}"#
+        );
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("kuksa-recorder-test-{}", std::process::id()));
+        let mut recorder = Recorder::Files(RotatingFiles::new(&dir, 200).unwrap());
+        for speed in 0..5 {
+            recorder
+                .record(&entry(
+                    "Vehicle.Speed",
+                    proto::datapoint::Value::Float(speed as f32),
+                ))
+                .unwrap();
+        }
+        recorder.flush().unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        // Lines are 83 bytes, two fit in a file
+        assert_eq!(files.len(), 3);
+        let lines: Vec<String> = files
+            .iter()
+            .flat_map(|file| {
+                let content = std::fs::read_to_string(file).unwrap();
+                assert!(content.len() <= 200);
+                content.lines().map(str::to_owned).collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[4].contains(r#""value":4.0"#));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -15,24 +15,30 @@ use databroker_proto::sdv::databroker as proto;
 use kuksa_common::SDVClientTraitV1;
 use kuksa_sdv::*;
 
-use prost_types::Timestamp;
 use tokio_stream::StreamExt;
 
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
 use ansi_term::Color;
 
 use crate::cli::ParseError;
 use crate::cli::{self, Cli};
+use crate::token;
 use linefeed::complete::{Completer, Completion, Suffix};
 use linefeed::terminal::Terminal;
 use linefeed::{Command, Interface, Prompter, ReadResult};
 
 const VERSION: &str = "sdv.databroker.v1";
 const TIMEOUT: Duration = Duration::from_millis(500);
+/// Rows shown by `query` and how long to wait for them
+const PREVIEW_ROWS: usize = 5;
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Operators of the query dialect, offered after a path in WHERE
+const QUERY_OPERATORS: &[&str] = &["=", "<>", "<", "<=", ">", ">="];
 
 const CLI_COMMANDS: &[(&str, &str, &str)] = &[
     ("connect", "[URI]", "Connect to server"),
@@ -43,6 +49,11 @@ const CLI_COMMANDS: &[(&str, &str, &str)] = &[
         "<QUERY>",
         "Subscribe to signals with QUERY, if you use kuksa feature comma separated list",
     ),
+    (
+        "query",
+        "<QUERY>",
+        "Check QUERY with the server and preview its first rows without subscribing",
+    ),
     ("publish", "<PATH> <VALUE>", "Publish signal value"),
     (
         "metadata",
@@ -92,6 +103,67 @@ async fn handle_get_command(
     Ok(())
 }
 
+async fn handle_query_command(
+    query: &str,
+    client: &mut SDVClient,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The server parses the query when subscribing, which is the only
+    // way to validate it against the server's dialect and metadata
+    let mut subscription = match client.subscribe(query.to_owned()).await {
+        Ok(subscription) => subscription,
+        Err(kuksa_common::ClientError::Status(status)) => {
+            return Ok(cli::print_resp_err("query", &status)?)
+        }
+        Err(kuksa_common::ClientError::Connection(msg)) => {
+            return Ok(cli::print_error("query", msg)?)
+        }
+        Err(kuksa_common::ClientError::Function(msg)) => {
+            return Ok(cli::print_resp_err_fmt(
+                "query",
+                format_args!("Error {msg:?}"),
+            )?)
+        }
+    };
+    cli::print_resp_ok("query")?;
+
+    let mut rows = 0;
+    while rows < PREVIEW_ROWS {
+        match tokio::time::timeout(PREVIEW_TIMEOUT, subscription.message()).await {
+            Ok(Ok(Some(reply))) => {
+                rows += 1;
+                let row = format!("[row {rows}]");
+                let pad = " ".repeat(row.len());
+                let mut fields = reply.fields.into_iter().collect::<Vec<_>>();
+                fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (i, (name, value)) in fields.into_iter().enumerate() {
+                    let prefix = if i == 0 { &row } else { &pad };
+                    println!(
+                        "{} {}: {}",
+                        Color::White.dimmed().paint(prefix),
+                        name,
+                        DisplayDatapoint(value)
+                    );
+                }
+            }
+            Ok(Ok(None)) => break,
+            Ok(Err(status)) => {
+                cli::print_resp_err("query", &status)?;
+                return Ok(());
+            }
+            // No (further) rows in time
+            Err(_) => break,
+        }
+    }
+    if rows == 0 {
+        cli::print_info(format!(
+            "No rows within {}s, the WHERE condition might not be met yet",
+            PREVIEW_TIMEOUT.as_secs()
+        ))?;
+    }
+    cli::print_info("Query is valid, use subscribe to follow it")?;
+    Ok(())
+}
+
 pub async fn sdv_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
     let mut properties = Vec::<proto::v1::Metadata>::new();
     println!("Using {VERSION}");
@@ -112,8 +184,13 @@ pub async fn sdv_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
     let mut client = SDVClient::new(kuksa_common::to_uri(cli.get_server())?);
 
     if let Some(token_filename) = cli.get_token_file() {
-        let token = std::fs::read_to_string(token_filename)?;
-        client.basic_client.set_access_token(token)?;
+        client.basic_client.set_access_token_file(token_filename)?;
+    } else {
+        match token::cached_access_token().await {
+            Ok(Some(token)) => client.basic_client.set_access_token(token)?,
+            Ok(None) => {}
+            Err(err) => eprintln!("Warning: not using cached access token: {err}"),
+        }
     }
 
     #[cfg(feature = "tls")]
@@ -155,15 +232,36 @@ pub async fn sdv_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
         Some(cli::Commands::Get { paths }) => {
             return handle_get_command(paths, &mut client).await;
         }
-        Some(cli::Commands::Set { path: _, value: _ }) => {
+        Some(cli::Commands::Set { .. }) => {
             unimplemented!("The set command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
         }
-        Some(cli::Commands::Publish { path: _, value: _ }) => {
+        Some(cli::Commands::Publish { .. }) => {
             unimplemented!("The publish command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
         }
         Some(cli::Commands::Actuate { path: _, value: _ }) => {
             unimplemented!("The actuate command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
         }
+        Some(cli::Commands::Subscribe { .. }) => {
+            unimplemented!("The subscribe command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
+        }
+        Some(cli::Commands::Diff { .. }) => {
+            unimplemented!("The diff command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
+        }
+        Some(cli::Commands::Provide { .. }) => {
+            unimplemented!("The provide command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
+        }
+        Some(cli::Commands::PingSignal { .. }) => {
+            unimplemented!("The ping-signal command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
+        }
+        Some(cli::Commands::ExportVss { .. }) => {
+            unimplemented!("The export-vss command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
+        }
+        Some(cli::Commands::Dashboard { paths: _ }) => {
+            unimplemented!("The dashboard command is not implemented for sdv.databroker.v1 protocol because it is already deprecated.");
+        }
+        Some(cli::Commands::Token { command }) => {
+            return token::handle_token_command(command).await;
+        }
         None => {
             // No subcommand => run interactive client
             let version = match option_env!("CARGO_PKG_VERSION") {
@@ -363,13 +461,9 @@ pub async fn sdv_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     continue;
                                 }
 
-                                let ts = Timestamp::from(SystemTime::now());
                                 let datapoints = HashMap::from([(
                                     metadata.name.clone(),
-                                    proto::v1::Datapoint {
-                                        timestamp: Some(ts),
-                                        value: Some(data_value.unwrap()),
-                                    },
+                                    proto::v1::Datapoint::now(data_value.unwrap()),
                                 )]);
 
                                 match client.set_datapoints(datapoints).await {
@@ -450,13 +544,9 @@ pub async fn sdv_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                     );
                                     continue;
                                 }
-                                let ts = Timestamp::from(SystemTime::now());
                                 let datapoints = HashMap::from([(
                                     metadata.name.clone(),
-                                    proto::v1::Datapoint {
-                                        timestamp: Some(ts),
-                                        value: Some(data_value.unwrap()),
-                                    },
+                                    proto::v1::Datapoint::now(data_value.unwrap()),
                                 )]);
 
                                 match client.update_datapoints(datapoints).await {
@@ -500,6 +590,15 @@ pub async fn sdv_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             }
                         }
+                        "query" => {
+                            interface.add_history_unique(line.clone());
+
+                            if args.is_empty() {
+                                print_usage(cmd);
+                                continue;
+                            }
+                            handle_query_command(args, &mut client).await?;
+                        }
                         "subscribe" => {
                             interface.add_history_unique(line.clone());
 
@@ -617,7 +716,7 @@ pub async fn sdv_main(_cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
                                         }
                                     }
                                 } else {
-                                    match cli::to_uri(args) {
+                                    match kuksa_common::to_uri(args) {
                                         Ok(valid_uri) => {
                                             match client
                                                 .basic_client
@@ -892,6 +991,48 @@ impl CliCompleter {
     }
 }
 
+impl CliCompleter {
+    /// Complete `word` of a query following `words`: keywords and
+    /// operators where the query dialect expects them, paths otherwise.
+    fn complete_query(&self, words: Vec<&str>, word: &str) -> Option<Vec<Completion>> {
+        let Some(last) = words.last() else {
+            return Some(complete_keywords(&["SELECT"], word));
+        };
+        let last = last.to_ascii_uppercase();
+        if matches!(last.as_str(), "SELECT" | "WHERE" | "AND" | "OR" | "NOT") || last.ends_with(',')
+        {
+            return self.complete_entry_path(word);
+        }
+        if !words.iter().any(|w| w.eq_ignore_ascii_case("WHERE")) {
+            // After a selected path either another one follows (separated
+            // by a comma) or the condition
+            if word.contains('.') {
+                return self.complete_entry_path(word);
+            }
+            return Some(complete_keywords(&["WHERE"], word));
+        }
+        if QUERY_OPERATORS.contains(&last.as_str()) {
+            // A value or another path to compare with
+            return self.complete_entry_path(word);
+        }
+        let after_operator = words.len() >= 2
+            && QUERY_OPERATORS.contains(&words[words.len() - 2].to_ascii_uppercase().as_str());
+        if after_operator {
+            Some(complete_keywords(&["AND", "OR"], word))
+        } else {
+            Some(complete_keywords(QUERY_OPERATORS, word))
+        }
+    }
+}
+
+fn complete_keywords(keywords: &[&str], word: &str) -> Vec<Completion> {
+    keywords
+        .iter()
+        .filter(|keyword| keyword.starts_with(&word.to_ascii_uppercase()))
+        .map(|keyword| Completion::simple((*keyword).to_owned()))
+        .collect()
+}
+
 impl<Term: Terminal> Completer<Term> for CliCompleter {
     fn complete(
         &self,
@@ -930,16 +1071,7 @@ impl<Term: Terminal> Completer<Term> for CliCompleter {
                 }
             }
             Some("get") | Some("metadata") => self.complete_entry_path(word),
-            Some("subscribe") => match words.next() {
-                None => Some(vec![Completion::simple("SELECT".to_owned())]),
-                Some(next) => {
-                    if next == "SELECT" {
-                        self.complete_entry_path(word)
-                    } else {
-                        None
-                    }
-                }
-            },
+            Some("subscribe") | Some("query") => self.complete_query(words.collect(), word),
             Some("token-file") => {
                 let path_completer = linefeed::complete::PathCompleter;
                 path_completer.complete(word, prompter, start, _end)
@@ -1340,6 +1472,46 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_query_completion() {
+        let metadata = [proto::v1::Metadata {
+            id: 1,
+            name: "Vehicle.Speed".into(),
+            data_type: proto::v1::DataType::Float.into(),
+            entry_type: proto::v1::EntryType::Sensor.into(),
+            change_type: proto::v1::ChangeType::OnChange.into(),
+            description: "".into(),
+            allowed: None,
+            min: None,
+            max: None,
+        }];
+        let completer = CliCompleter::from_metadata(&metadata);
+        let complete = |line: &str, word: &str| -> Vec<String> {
+            completer
+                .complete_query(line.split_whitespace().collect(), word)
+                .unwrap_or_default()
+                .iter()
+                .map(|completion| completion.completion.clone())
+                .collect()
+        };
+
+        assert_eq!(complete("", "s"), vec!["SELECT"]);
+        assert_eq!(complete("SELECT", "Vehicle.Sp"), vec!["Vehicle.Speed"]);
+        assert_eq!(complete("SELECT Vehicle.Speed", "w"), vec!["WHERE"]);
+        assert_eq!(
+            complete("SELECT Vehicle.Speed WHERE", "vehicle.s"),
+            vec!["Vehicle.Speed"]
+        );
+        assert_eq!(
+            complete("SELECT Vehicle.Speed WHERE Vehicle.Speed", "<"),
+            vec!["<>", "<", "<="]
+        );
+        assert_eq!(
+            complete("SELECT Vehicle.Speed WHERE Vehicle.Speed > 50", ""),
+            vec!["AND", "OR"]
+        );
+    }
+
     #[test]
     fn test_alignment() {
         let max = 7;
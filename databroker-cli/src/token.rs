@@ -0,0 +1,471 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Access tokens obtained with the OAuth 2.0 device authorization flow
+//! (RFC 8628).
+//!
+//! `token login` asks the identity provider for a device code, lets the
+//! user confirm it in a browser and caches the token it then gets. The
+//! cached token is used by later commands unless `--token-file` is given,
+//! and refreshed once it has expired.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::TokenCommands;
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Polling interval if the provider doesn't tell one
+const DEFAULT_INTERVAL: u64 = 5;
+/// Refresh tokens which expire within this many seconds
+const EXPIRY_MARGIN: u64 = 30;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(String),
+    Protocol(String),
+    /// The user denied the request or didn't confirm it in time
+    Denied(String),
+    Cache(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(msg) => write!(f, "request to identity provider failed: {msg}"),
+            Error::Protocol(msg) => write!(f, "unexpected response from identity provider: {msg}"),
+            Error::Denied(msg) => write!(f, "login failed: {msg}"),
+            Error::Cache(msg) => write!(f, "token cache: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    device_authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceAuthorization {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Token as stored in the cache file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedToken {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Seconds since the UNIX epoch
+    pub expires_at: Option<u64>,
+    pub token_endpoint: String,
+    pub client_id: String,
+}
+
+impl CachedToken {
+    fn from_response(response: TokenResponse, token_endpoint: &str, client_id: &str) -> Self {
+        CachedToken {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: response.expires_in.map(|expires_in| now() + expires_in),
+            token_endpoint: token_endpoint.to_owned(),
+            client_id: client_id.to_owned(),
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= now() + EXPIRY_MARGIN)
+    }
+}
+
+pub async fn handle_token_command(
+    command: TokenCommands,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        TokenCommands::Login {
+            issuer,
+            client_id,
+            scope,
+        } => {
+            let path = cache_path()?;
+            let token = tokio::task::spawn_blocking(move || {
+                login(&issuer, &client_id, scope.as_deref(), |authorization| {
+                    println!(
+                        "To log in, open {} and enter the code {}",
+                        authorization.verification_uri, authorization.user_code
+                    );
+                    if let Some(uri) = &authorization.verification_uri_complete {
+                        println!("or open {uri}");
+                    }
+                })
+            })
+            .await??;
+            save(&path, &token)?;
+            println!("Logged in, token cached in {}", path.display());
+        }
+        TokenCommands::Logout => {
+            let path = cache_path()?;
+            match std::fs::remove_file(&path) {
+                Ok(()) => println!("Removed cached token {}", path.display()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    println!("Not logged in")
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run the device authorization flow against the provider at `issuer`.
+/// `prompt` is called with the code the user has to confirm.
+fn login(
+    issuer: &str,
+    client_id: &str,
+    scope: Option<&str>,
+    prompt: impl FnOnce(&DeviceAuthorization),
+) -> Result<CachedToken, Error> {
+    let discovery_url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    let discovery: Discovery = ureq::get(&discovery_url)
+        .call()
+        .map_err(http_error)?
+        .into_json()
+        .map_err(|err| Error::Protocol(err.to_string()))?;
+
+    let mut form = vec![("client_id", client_id)];
+    if let Some(scope) = scope {
+        form.push(("scope", scope));
+    }
+    let authorization: DeviceAuthorization = ureq::post(&discovery.device_authorization_endpoint)
+        .send_form(&form)
+        .map_err(http_error)?
+        .into_json()
+        .map_err(|err| Error::Protocol(err.to_string()))?;
+    prompt(&authorization);
+
+    let deadline = SystemTime::now() + Duration::from_secs(authorization.expires_in);
+    let mut interval = authorization.interval.unwrap_or(DEFAULT_INTERVAL);
+    loop {
+        thread::sleep(Duration::from_secs(interval));
+        if SystemTime::now() > deadline {
+            return Err(Error::Denied("the code expired".to_owned()));
+        }
+        let response = ureq::post(&discovery.token_endpoint).send_form(&[
+            ("grant_type", DEVICE_CODE_GRANT),
+            ("device_code", &authorization.device_code),
+            ("client_id", client_id),
+        ]);
+        match response {
+            Ok(response) => {
+                let response: TokenResponse = response
+                    .into_json()
+                    .map_err(|err| Error::Protocol(err.to_string()))?;
+                return Ok(CachedToken::from_response(
+                    response,
+                    &discovery.token_endpoint,
+                    client_id,
+                ));
+            }
+            Err(ureq::Error::Status(_, response)) => {
+                let error: ErrorResponse = response
+                    .into_json()
+                    .map_err(|err| Error::Protocol(err.to_string()))?;
+                match error.error.as_str() {
+                    "authorization_pending" => {}
+                    // RFC 8628, 3.5: increase the interval by 5 seconds
+                    "slow_down" => interval += 5,
+                    _ => {
+                        return Err(Error::Denied(
+                            error.error_description.unwrap_or(error.error),
+                        ))
+                    }
+                }
+            }
+            Err(err) => return Err(http_error(err)),
+        }
+    }
+}
+
+fn refresh(token: &CachedToken) -> Result<CachedToken, Error> {
+    let Some(refresh_token) = &token.refresh_token else {
+        return Err(Error::Denied(
+            "token expired, log in again with `token login`".to_owned(),
+        ));
+    };
+    let response: TokenResponse = ureq::post(&token.token_endpoint)
+        .send_form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", &token.client_id),
+        ])
+        .map_err(http_error)?
+        .into_json()
+        .map_err(|err| Error::Protocol(err.to_string()))?;
+    let mut refreshed =
+        CachedToken::from_response(response, &token.token_endpoint, &token.client_id);
+    // Providers may keep the refresh token unchanged and not send it again
+    if refreshed.refresh_token.is_none() {
+        refreshed.refresh_token = token.refresh_token.clone();
+    }
+    Ok(refreshed)
+}
+
+/// The cached access token, refreshed if it has expired. `None` if not
+/// logged in.
+pub async fn cached_access_token() -> Result<Option<String>, Error> {
+    let path = cache_path()?;
+    tokio::task::spawn_blocking(move || cached_access_token_from(&path))
+        .await
+        .map_err(|err| Error::Cache(err.to_string()))?
+}
+
+fn cached_access_token_from(path: &Path) -> Result<Option<String>, Error> {
+    let token = match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str::<CachedToken>(&content)
+            .map_err(|err| Error::Cache(format!("{}: {err}", path.display())))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(Error::Cache(format!("{}: {err}", path.display()))),
+    };
+    if !token.is_expired() {
+        return Ok(Some(token.access_token));
+    }
+    let token = refresh(&token)?;
+    save(path, &token)?;
+    Ok(Some(token.access_token))
+}
+
+/// `$XDG_CACHE_HOME/kuksa/token.json`, or `~/.cache/kuksa/token.json`.
+fn cache_path() -> Result<PathBuf, Error> {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .ok_or_else(|| Error::Cache("neither XDG_CACHE_HOME nor HOME is set".to_owned()))?;
+    Ok(cache_dir.join("kuksa").join("token.json"))
+}
+
+fn save(path: &Path, token: &CachedToken) -> Result<(), Error> {
+    let cache_error = |err: std::io::Error| Error::Cache(format!("{}: {err}", path.display()));
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(cache_error)?;
+    }
+    let content = serde_json::to_string_pretty(token).expect("token should be serializable");
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        // The token grants access to the vehicle, keep it to the user
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(cache_error)?;
+    std::io::Write::write_all(&mut file, content.as_bytes()).map_err(cache_error)
+}
+
+fn http_error(err: ureq::Error) -> Error {
+    match err {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            Error::Http(format!("{status}: {body}"))
+        }
+        ureq::Error::Transport(transport) => Error::Http(transport.to_string()),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Minimal identity provider answering the requests of the device flow
+    /// with the given token responses in turn. Returns its URL and the
+    /// bodies of the requests it got.
+    fn identity_provider(
+        token_responses: Vec<(u16, &'static str)>,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let issuer = url.clone();
+        let received = requests.clone();
+        thread::spawn(move || {
+            let mut token_responses = token_responses.into_iter();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8(body).unwrap());
+
+                let (status, body) = if request_line.contains("openid-configuration") {
+                    (
+                        200,
+                        format!(
+                            r#"{{"device_authorization_endpoint": "{issuer}/device", "token_endpoint": "{issuer}/token"}}"#
+                        ),
+                    )
+                } else if request_line.contains("/device") {
+                    (
+                        200,
+                        r#"{"device_code": "dev-1", "user_code": "ABCD-EFGH", "verification_uri": "https://idp/device", "expires_in": 60, "interval": 0}"#
+                            .to_owned(),
+                    )
+                } else {
+                    let (status, body) = token_responses.next().unwrap();
+                    (status, body.to_owned())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    #[test]
+    fn test_login() {
+        let (issuer, requests) = identity_provider(vec![
+            (400, r#"{"error": "authorization_pending"}"#),
+            (
+                200,
+                r#"{"access_token": "access-1", "refresh_token": "refresh-1", "expires_in": 3600}"#,
+            ),
+        ]);
+        let mut user_code = None;
+        let token = login(&issuer, "kuksa-cli", Some("openid"), |authorization| {
+            user_code = Some(authorization.user_code.clone());
+        })
+        .unwrap();
+        assert_eq!(user_code.as_deref(), Some("ABCD-EFGH"));
+        assert_eq!(token.access_token, "access-1");
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh-1"));
+        assert_eq!(token.token_endpoint, format!("{issuer}/token"));
+        assert!(!token.is_expired());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[1], "client_id=kuksa-cli&scope=openid");
+        assert!(requests[2].contains("device_code=dev-1"));
+    }
+
+    #[test]
+    fn test_login_denied() {
+        let (issuer, _) = identity_provider(vec![(
+            400,
+            r#"{"error": "access_denied", "error_description": "The user denied the request"}"#,
+        )]);
+        match login(&issuer, "kuksa-cli", None, |_| {}) {
+            Err(Error::Denied(msg)) => assert_eq!(msg, "The user denied the request"),
+            other => panic!("expected the login to be denied, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_cached_token_refresh() {
+        let (issuer, requests) = identity_provider(vec![(
+            200,
+            r#"{"access_token": "access-2", "expires_in": 3600}"#,
+        )]);
+        let dir = std::env::temp_dir().join(format!("kuksa-token-test-{}", std::process::id()));
+        let path = dir.join("token.json");
+
+        assert!(cached_access_token_from(&path).unwrap().is_none());
+
+        let valid = CachedToken {
+            access_token: "access-1".to_owned(),
+            refresh_token: Some("refresh-1".to_owned()),
+            expires_at: Some(now() + 3600),
+            token_endpoint: format!("{issuer}/token"),
+            client_id: "kuksa-cli".to_owned(),
+        };
+        save(&path, &valid).unwrap();
+        assert_eq!(
+            cached_access_token_from(&path).unwrap().as_deref(),
+            Some("access-1")
+        );
+
+        let expired = CachedToken {
+            expires_at: Some(now()),
+            ..valid
+        };
+        save(&path, &expired).unwrap();
+        assert_eq!(
+            cached_access_token_from(&path).unwrap().as_deref(),
+            Some("access-2")
+        );
+        assert!(requests.lock().unwrap()[0].contains("refresh_token=refresh-1"));
+
+        // The refresh token is kept when the provider doesn't send a new one
+        let cached: CachedToken =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(cached.refresh_token.as_deref(), Some("refresh-1"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,558 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::time::{Duration, SystemTime};
+
+use databroker_proto::kuksa::val::{v1, v2};
+use tokio_stream::StreamExt;
+use tonic::{
+    metadata::AsciiMetadataValue, service::interceptor::InterceptedService, transport::Channel,
+    Code,
+};
+
+pub struct Context {
+    pub channel: Channel,
+    pub token: Option<String>,
+    pub sensor: String,
+    pub actuator: String,
+    pub timeout: Duration,
+}
+
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    Skip(String),
+}
+
+#[derive(Clone)]
+pub struct Auth(Option<AsciiMetadataValue>);
+
+impl tonic::service::Interceptor for Auth {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        if let Some(token) = &self.0 {
+            request
+                .metadata_mut()
+                .insert("authorization", token.clone());
+        }
+        Ok(request)
+    }
+}
+
+type V1Client = v1::val_client::ValClient<InterceptedService<Channel, Auth>>;
+type V2Client = v2::val_client::ValClient<InterceptedService<Channel, Auth>>;
+
+impl Context {
+    fn auth(&self, token: Option<&str>) -> Auth {
+        Auth(token.and_then(|token| AsciiMetadataValue::try_from(format!("Bearer {token}")).ok()))
+    }
+
+    fn v1(&self) -> V1Client {
+        v1::val_client::ValClient::with_interceptor(
+            self.channel.clone(),
+            self.auth(self.token.as_deref()),
+        )
+    }
+
+    fn v2(&self) -> V2Client {
+        self.v2_with_token(self.token.as_deref())
+    }
+
+    fn v2_with_token(&self, token: Option<&str>) -> V2Client {
+        v2::val_client::ValClient::with_interceptor(self.channel.clone(), self.auth(token))
+    }
+
+    fn sensor_branch(&self) -> &str {
+        self.sensor
+            .rsplit_once('.')
+            .map(|(branch, _)| branch)
+            .unwrap_or(&self.sensor)
+    }
+}
+
+fn signal_id(path: &str) -> Option<v2::SignalId> {
+    Some(v2::SignalId {
+        signal: Some(v2::signal_id::Signal::Path(path.to_owned())),
+    })
+}
+
+fn float_value(value: f32) -> Option<v2::Value> {
+    Some(v2::Value {
+        typed_value: Some(v2::value::TypedValue::Float(value)),
+    })
+}
+
+/// A value that differs between runs, so a stale value is not mistaken
+/// for a successful update.
+fn fresh_value() -> f32 {
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.subsec_millis())
+        .unwrap_or_default();
+    1.0 + (millis % 100) as f32
+}
+
+fn expect_code<T>(result: Result<T, tonic::Status>, expected: Code) -> Outcome {
+    match result {
+        Ok(_) => Outcome::Fail(format!("expected {expected:?}, got OK")),
+        Err(status) if status.code() == expected => Outcome::Pass,
+        Err(status) => Outcome::Fail(format!(
+            "expected {expected:?}, got {:?} ({})",
+            status.code(),
+            status.message()
+        )),
+    }
+}
+
+fn expect_ok<T>(result: Result<T, tonic::Status>, check: impl FnOnce(T) -> Outcome) -> Outcome {
+    match result {
+        Ok(response) => check(response),
+        Err(status) => Outcome::Fail(format!(
+            "unexpected {:?} ({})",
+            status.code(),
+            status.message()
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Check {
+    ServerInfoV1,
+    ServerInfoV2,
+    AuthMissingToken,
+    AuthInvalidToken,
+    GetValue,
+    GetValueNotFound,
+    PublishValue,
+    PublishValueWrongType,
+    WildcardLeaf,
+    WildcardBranch,
+    WildcardAnyDepth,
+    WildcardNotFound,
+    WildcardInvalid,
+    V1GetWildcard,
+    V1GetNotFound,
+    ActuateSensor,
+    ActuateWithoutProvider,
+    SubscribeEmpty,
+    SubscribeNotFound,
+    SubscribeBufferSize,
+    SubscribeInitialValue,
+    SubscribeUpdate,
+}
+
+impl Check {
+    pub const ALL: [Check; 22] = [
+        Check::ServerInfoV1,
+        Check::ServerInfoV2,
+        Check::AuthMissingToken,
+        Check::AuthInvalidToken,
+        Check::GetValue,
+        Check::GetValueNotFound,
+        Check::PublishValue,
+        Check::PublishValueWrongType,
+        Check::WildcardLeaf,
+        Check::WildcardBranch,
+        Check::WildcardAnyDepth,
+        Check::WildcardNotFound,
+        Check::WildcardInvalid,
+        Check::V1GetWildcard,
+        Check::V1GetNotFound,
+        Check::ActuateSensor,
+        Check::ActuateWithoutProvider,
+        Check::SubscribeEmpty,
+        Check::SubscribeNotFound,
+        Check::SubscribeBufferSize,
+        Check::SubscribeInitialValue,
+        Check::SubscribeUpdate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Check::ServerInfoV1 => "v1.server_info",
+            Check::ServerInfoV2 => "v2.server_info",
+            Check::AuthMissingToken => "auth.missing_token",
+            Check::AuthInvalidToken => "auth.invalid_token",
+            Check::GetValue => "v2.get_value",
+            Check::GetValueNotFound => "v2.get_value.not_found",
+            Check::PublishValue => "v2.publish_value",
+            Check::PublishValueWrongType => "v2.publish_value.wrong_type",
+            Check::WildcardLeaf => "wildcard.leaf",
+            Check::WildcardBranch => "wildcard.branch",
+            Check::WildcardAnyDepth => "wildcard.any_depth",
+            Check::WildcardNotFound => "wildcard.not_found",
+            Check::WildcardInvalid => "wildcard.invalid",
+            Check::V1GetWildcard => "v1.get.wildcard",
+            Check::V1GetNotFound => "v1.get.not_found",
+            Check::ActuateSensor => "v2.actuate.sensor",
+            Check::ActuateWithoutProvider => "v2.actuate.no_provider",
+            Check::SubscribeEmpty => "stream.subscribe.empty",
+            Check::SubscribeNotFound => "stream.subscribe.not_found",
+            Check::SubscribeBufferSize => "stream.subscribe.buffer_size",
+            Check::SubscribeInitialValue => "stream.initial_value",
+            Check::SubscribeUpdate => "stream.update",
+        }
+    }
+
+    pub async fn run(&self, context: &Context) -> Outcome {
+        match self {
+            Check::ServerInfoV1 => {
+                let result = context
+                    .v1()
+                    .get_server_info(v1::GetServerInfoRequest {})
+                    .await;
+                expect_ok(result, |response| {
+                    if response.into_inner().version.is_empty() {
+                        Outcome::Fail("empty version".to_owned())
+                    } else {
+                        Outcome::Pass
+                    }
+                })
+            }
+            Check::ServerInfoV2 => {
+                let result = context
+                    .v2()
+                    .get_server_info(v2::GetServerInfoRequest {})
+                    .await;
+                expect_ok(result, |response| {
+                    let response = response.into_inner();
+                    if response.name.is_empty() || response.version.is_empty() {
+                        Outcome::Fail("empty name or version".to_owned())
+                    } else {
+                        Outcome::Pass
+                    }
+                })
+            }
+            Check::AuthMissingToken | Check::AuthInvalidToken => {
+                if context.token.is_none() {
+                    return Outcome::Skip("no token given".to_owned());
+                }
+                let token = match self {
+                    Check::AuthInvalidToken => Some("invalid"),
+                    _ => None,
+                };
+                let result = context
+                    .v2_with_token(token)
+                    .get_value(v2::GetValueRequest {
+                        signal_id: signal_id(&context.sensor),
+                    })
+                    .await;
+                expect_code(result, Code::Unauthenticated)
+            }
+            Check::GetValue => {
+                let result = context
+                    .v2()
+                    .get_value(v2::GetValueRequest {
+                        signal_id: signal_id(&context.sensor),
+                    })
+                    .await;
+                expect_ok(result, |_| Outcome::Pass)
+            }
+            Check::GetValueNotFound => {
+                let result = context
+                    .v2()
+                    .get_value(v2::GetValueRequest {
+                        signal_id: signal_id("Vehicle.Conformance.DoesNotExist"),
+                    })
+                    .await;
+                expect_code(result, Code::NotFound)
+            }
+            Check::PublishValue => {
+                let value = fresh_value();
+                let mut client = context.v2();
+                let result = client
+                    .publish_value(v2::PublishValueRequest {
+                        signal_id: signal_id(&context.sensor),
+                        data_point: Some(v2::Datapoint {
+                            timestamp: None,
+                            value: float_value(value),
+                        }),
+                    })
+                    .await;
+                if let Outcome::Fail(reason) = expect_ok(result, |_| Outcome::Pass) {
+                    return Outcome::Fail(reason);
+                }
+                let result = client
+                    .get_value(v2::GetValueRequest {
+                        signal_id: signal_id(&context.sensor),
+                    })
+                    .await;
+                expect_ok(result, |response| {
+                    let value_read = response
+                        .into_inner()
+                        .data_point
+                        .and_then(|data_point| data_point.value);
+                    if value_read == float_value(value) {
+                        Outcome::Pass
+                    } else {
+                        Outcome::Fail(format!("published {value}, read {value_read:?}"))
+                    }
+                })
+            }
+            Check::PublishValueWrongType => {
+                let result = context
+                    .v2()
+                    .publish_value(v2::PublishValueRequest {
+                        signal_id: signal_id(&context.sensor),
+                        data_point: Some(v2::Datapoint {
+                            timestamp: None,
+                            value: Some(v2::Value {
+                                typed_value: Some(v2::value::TypedValue::String(
+                                    "conformance".to_owned(),
+                                )),
+                            }),
+                        }),
+                    })
+                    .await;
+                expect_code(result, Code::InvalidArgument)
+            }
+            Check::WildcardLeaf => {
+                let result = list_metadata(context, &context.sensor).await;
+                expect_ok(result, |paths| {
+                    if paths == [context.sensor.clone()] {
+                        Outcome::Pass
+                    } else {
+                        Outcome::Fail(format!("expected only {}, got {paths:?}", context.sensor))
+                    }
+                })
+            }
+            Check::WildcardBranch => {
+                let branch = context.sensor_branch();
+                let result = list_metadata(context, branch).await;
+                expect_ok(result, |paths| {
+                    let prefix = format!("{branch}.");
+                    if !paths.contains(&context.sensor) {
+                        Outcome::Fail(format!("{} missing below {branch}", context.sensor))
+                    } else if let Some(path) = paths.iter().find(|path| !path.starts_with(&prefix))
+                    {
+                        Outcome::Fail(format!("{path} is not below {branch}"))
+                    } else {
+                        Outcome::Pass
+                    }
+                })
+            }
+            Check::WildcardAnyDepth => {
+                let leaf = context
+                    .sensor
+                    .rsplit_once('.')
+                    .map(|(_, leaf)| leaf)
+                    .unwrap_or(&context.sensor);
+                let result = list_metadata(context, &format!("**.{leaf}")).await;
+                expect_ok(result, |paths| {
+                    if !paths.contains(&context.sensor) {
+                        Outcome::Fail(format!("{} missing", context.sensor))
+                    } else if let Some(path) = paths.iter().find(|path| !path.ends_with(leaf)) {
+                        Outcome::Fail(format!("{path} does not end with {leaf}"))
+                    } else {
+                        Outcome::Pass
+                    }
+                })
+            }
+            Check::WildcardNotFound => {
+                let result = list_metadata(context, "Vehicle.Conformance.DoesNotExist").await;
+                expect_code(result, Code::NotFound)
+            }
+            Check::WildcardInvalid => {
+                let result = list_metadata(context, "Vehicle..Speed").await;
+                expect_code(result, Code::InvalidArgument)
+            }
+            Check::V1GetWildcard => {
+                let branch = context.sensor_branch();
+                let result = v1_get(context, &format!("{branch}.*")).await;
+                expect_ok(result, |response| {
+                    if response
+                        .entries
+                        .iter()
+                        .any(|entry| entry.path == context.sensor)
+                    {
+                        Outcome::Pass
+                    } else {
+                        Outcome::Fail(format!("{} missing in {branch}.*", context.sensor))
+                    }
+                })
+            }
+            Check::V1GetNotFound => {
+                let result = v1_get(context, "Vehicle.Conformance.DoesNotExist").await;
+                expect_ok(result, |response| {
+                    let code = response
+                        .errors
+                        .first()
+                        .and_then(|error| error.error.as_ref())
+                        .map(|error| error.code);
+                    if code == Some(404) {
+                        Outcome::Pass
+                    } else {
+                        Outcome::Fail(format!("expected error code 404, got {code:?}"))
+                    }
+                })
+            }
+            Check::ActuateSensor => {
+                let result = context
+                    .v2()
+                    .actuate(v2::ActuateRequest {
+                        signal_id: signal_id(&context.sensor),
+                        value: float_value(1.0),
+                    })
+                    .await;
+                expect_code(result, Code::InvalidArgument)
+            }
+            Check::ActuateWithoutProvider => {
+                let result = context
+                    .v2()
+                    .actuate(v2::ActuateRequest {
+                        signal_id: signal_id(&context.actuator),
+                        value: Some(v2::Value {
+                            typed_value: Some(v2::value::TypedValue::Bool(true)),
+                        }),
+                    })
+                    .await;
+                expect_code(result, Code::Unavailable)
+            }
+            Check::SubscribeEmpty => {
+                let result = subscribe(context, vec![], 0).await;
+                expect_code(result, Code::InvalidArgument)
+            }
+            Check::SubscribeNotFound => {
+                let result = subscribe(
+                    context,
+                    vec!["Vehicle.Conformance.DoesNotExist".to_owned()],
+                    0,
+                )
+                .await;
+                expect_code(result, Code::NotFound)
+            }
+            Check::SubscribeBufferSize => {
+                let result = subscribe(context, vec![context.sensor.clone()], 1001).await;
+                expect_code(result, Code::InvalidArgument)
+            }
+            Check::SubscribeInitialValue => {
+                let mut stream = match subscribe(context, vec![context.sensor.clone()], 0).await {
+                    Ok(stream) => stream,
+                    Err(status) => return expect_ok(Err::<(), _>(status), |_| Outcome::Pass),
+                };
+                match tokio::time::timeout(context.timeout, stream.next()).await {
+                    Ok(Some(Ok(response))) if response.entries.contains_key(&context.sensor) => {
+                        Outcome::Pass
+                    }
+                    Ok(Some(Ok(response))) => Outcome::Fail(format!(
+                        "initial message without {}: {:?}",
+                        context.sensor, response.entries
+                    )),
+                    Ok(Some(Err(status))) => Outcome::Fail(format!("stream error: {status}")),
+                    Ok(None) => Outcome::Fail("stream closed".to_owned()),
+                    Err(_) => Outcome::Fail("no initial message".to_owned()),
+                }
+            }
+            Check::SubscribeUpdate => {
+                let mut stream = match subscribe(context, vec![context.sensor.clone()], 0).await {
+                    Ok(stream) => stream,
+                    Err(status) => return expect_ok(Err::<(), _>(status), |_| Outcome::Pass),
+                };
+                // Skip the initial message
+                if tokio::time::timeout(context.timeout, stream.next())
+                    .await
+                    .is_err()
+                {
+                    return Outcome::Fail("no initial message".to_owned());
+                }
+
+                let value = fresh_value() + 100.0;
+                let result = context
+                    .v2()
+                    .publish_value(v2::PublishValueRequest {
+                        signal_id: signal_id(&context.sensor),
+                        data_point: Some(v2::Datapoint {
+                            timestamp: None,
+                            value: float_value(value),
+                        }),
+                    })
+                    .await;
+                if let Outcome::Fail(reason) = expect_ok(result, |_| Outcome::Pass) {
+                    return Outcome::Fail(reason);
+                }
+
+                let received = tokio::time::timeout(context.timeout, async {
+                    while let Some(Ok(response)) = stream.next().await {
+                        let value_received = response
+                            .entries
+                            .get(&context.sensor)
+                            .and_then(|data_point| data_point.value.clone());
+                        if value_received == float_value(value) {
+                            return true;
+                        }
+                    }
+                    false
+                })
+                .await;
+                match received {
+                    Ok(true) => Outcome::Pass,
+                    Ok(false) => Outcome::Fail("stream closed before update".to_owned()),
+                    Err(_) => Outcome::Fail(format!("update to {value} not received")),
+                }
+            }
+        }
+    }
+}
+
+async fn list_metadata(context: &Context, root: &str) -> Result<Vec<String>, tonic::Status> {
+    let response = context
+        .v2()
+        .list_metadata(v2::ListMetadataRequest {
+            root: root.to_owned(),
+            filter: String::new(),
+            ..Default::default()
+        })
+        .await?;
+    Ok(response
+        .into_inner()
+        .metadata
+        .into_iter()
+        .map(|metadata| metadata.path)
+        .collect())
+}
+
+async fn v1_get(context: &Context, path: &str) -> Result<v1::GetResponse, tonic::Status> {
+    let response = context
+        .v1()
+        .get(v1::GetRequest {
+            entries: vec![v1::EntryRequest {
+                path: path.to_owned(),
+                view: v1::View::CurrentValue.into(),
+                fields: vec![v1::Field::Path.into()],
+            }],
+        })
+        .await?;
+    Ok(response.into_inner())
+}
+
+async fn subscribe(
+    context: &Context,
+    signal_paths: Vec<String>,
+    buffer_size: u32,
+) -> Result<tonic::Streaming<v2::SubscribeResponse>, tonic::Status> {
+    let response = context
+        .v2()
+        .subscribe(v2::SubscribeRequest {
+            signal_paths,
+            buffer_size,
+            filter: None,
+            changes_only: false,
+            delta_encoding: None,
+            backfill_since: None,
+            priority: 0,
+        })
+        .await?;
+    Ok(response.into_inner())
+}
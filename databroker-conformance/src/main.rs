@@ -0,0 +1,139 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Runs protocol level checks against a running broker and prints a report.
+//!
+//! The broker is expected to be loaded with the standard VSS catalog (or at
+//! least with the signals given by `--sensor` and `--actuator`), and the
+//! token (if any) must allow reading and writing those signals.
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+
+mod checks;
+
+use checks::{Check, Context, Outcome};
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Server to check
+    #[clap(long, display_order = 1, default_value = "http://127.0.0.1:55555")]
+    server: String,
+
+    /// File containing an access token with read and write access to the
+    /// checked signals. Enables the authorization checks.
+    #[clap(long, value_name = "FILE", display_order = 2)]
+    token_file: Option<String>,
+
+    /// CA certificate used to verify server certificate
+    #[cfg(feature = "tls")]
+    #[clap(long, value_name = "CERT", display_order = 3)]
+    ca_cert: Option<String>,
+
+    /// Float sensor used for value and subscription checks
+    #[clap(long, display_order = 4, default_value = "Vehicle.Speed")]
+    sensor: String,
+
+    /// Actuator without provider used for actuation checks
+    #[clap(
+        long,
+        display_order = 5,
+        default_value = "Vehicle.Body.Trunk.Rear.IsOpen"
+    )]
+    actuator: String,
+
+    /// How long to wait for stream messages, in milliseconds
+    #[clap(long, display_order = 6, default_value_t = 2000)]
+    timeout_ms: u64,
+
+    /// Only run checks whose name contains FILTER
+    #[clap(long, value_name = "FILTER", display_order = 7)]
+    only: Option<String>,
+}
+
+async fn connect(args: &Args) -> Result<Context, Box<dyn std::error::Error>> {
+    let uri = kuksa_common::to_uri(&args.server)?;
+    let mut client = kuksa_common::Client::new(uri);
+
+    #[cfg(feature = "tls")]
+    if let Some(ca_cert_filename) = &args.ca_cert {
+        let pem = std::fs::read(ca_cert_filename)?;
+        let ca_cert = tonic::transport::Certificate::from_pem(pem);
+        let tls_config = tonic::transport::ClientTlsConfig::new().ca_certificate(ca_cert);
+        client.set_tls_config(tls_config);
+    }
+
+    let token = match &args.token_file {
+        Some(token_filename) => Some(std::fs::read_to_string(token_filename)?.trim().to_owned()),
+        None => None,
+    };
+
+    let channel = client.get_channel().await?.clone();
+    Ok(Context {
+        channel,
+        token,
+        sensor: args.sensor.clone(),
+        actuator: args.actuator.clone(),
+        timeout: Duration::from_millis(args.timeout_ms),
+    })
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let context = match connect(&args).await {
+        Ok(context) => context,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            return ExitCode::from(2);
+        }
+    };
+
+    println!("Checking {}", args.server);
+    println!();
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for check in Check::ALL {
+        if let Some(only) = &args.only {
+            if !check.name().contains(only.as_str()) {
+                continue;
+            }
+        }
+        match check.run(&context).await {
+            Outcome::Pass => {
+                passed += 1;
+                println!("PASS  {}", check.name());
+            }
+            Outcome::Fail(reason) => {
+                failed += 1;
+                println!("FAIL  {}: {}", check.name(), reason);
+            }
+            Outcome::Skip(reason) => {
+                skipped += 1;
+                println!("SKIP  {}: {}", check.name(), reason);
+            }
+        }
+    }
+
+    println!();
+    println!("{passed} passed, {failed} failed, {skipped} skipped");
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
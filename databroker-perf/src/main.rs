@@ -0,0 +1,403 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Generates load on a running broker and reports latency and throughput.
+//!
+//! Providers publish sequence numbers to numeric sensors found under
+//! `--root`, subscribers receive them and look up when each number was
+//! published. The broker is expected to be loaded with the standard VSS
+//! catalog and the token (if any) must allow reading and providing the
+//! used signals.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::Parser;
+use databroker_proto::kuksa::val::v2::{
+    self, open_provider_stream_request, open_provider_stream_response, val_client::ValClient,
+};
+use tokio::sync::{mpsc, watch};
+use tokio::time::timeout_at;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::{
+    metadata::AsciiMetadataValue, service::interceptor::InterceptedService, transport::Channel,
+};
+
+mod signals;
+mod stats;
+
+use signals::{SendLog, Signal};
+use stats::Latencies;
+
+#[derive(Debug, Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Server to connect to
+    #[clap(long, display_order = 1, default_value = "http://127.0.0.1:55555")]
+    server: String,
+
+    /// File containing an access token allowing to read and provide the
+    /// used signals
+    #[clap(long, value_name = "FILE", display_order = 2)]
+    token_file: Option<String>,
+
+    /// CA certificate used to verify server certificate
+    #[cfg(feature = "tls")]
+    #[clap(long, value_name = "CERT", display_order = 3)]
+    ca_cert: Option<String>,
+
+    /// Number of providers publishing values
+    #[clap(long, display_order = 4, default_value_t = 1)]
+    providers: usize,
+
+    /// Number of subscribers, each subscribing to all used signals
+    #[clap(long, display_order = 5, default_value_t = 1)]
+    subscribers: usize,
+
+    /// Number of signals, distributed over the providers
+    #[clap(long, display_order = 6, default_value_t = 10)]
+    signals: usize,
+
+    /// Updates per second of each signal
+    #[clap(long, display_order = 7, default_value_t = 100.0)]
+    rate: f64,
+
+    /// Length of the run in seconds
+    #[clap(long, display_order = 8, default_value_t = 10)]
+    duration: u64,
+
+    /// Subscription buffer size, see SubscribeRequest.buffer_size
+    #[clap(long, display_order = 9, default_value_t = 0)]
+    buffer_size: u32,
+
+    /// Branch to pick the signals from
+    #[clap(long, display_order = 10, default_value = "Vehicle")]
+    root: String,
+}
+
+#[derive(Clone)]
+struct Auth(Option<AsciiMetadataValue>);
+
+impl tonic::service::Interceptor for Auth {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        if let Some(token) = &self.0 {
+            request
+                .metadata_mut()
+                .insert("authorization", token.clone());
+        }
+        Ok(request)
+    }
+}
+
+type Client = ValClient<InterceptedService<Channel, Auth>>;
+
+/// Time given to subscribers to receive the last updates after the
+/// providers stopped.
+const DRAIN_TIME: Duration = Duration::from_millis(500);
+
+async fn connect(args: &Args) -> Result<Client, Box<dyn std::error::Error>> {
+    let uri = kuksa_common::to_uri(&args.server)?;
+    let mut client = kuksa_common::Client::new(uri);
+
+    #[cfg(feature = "tls")]
+    if let Some(ca_cert_filename) = &args.ca_cert {
+        let pem = std::fs::read(ca_cert_filename)?;
+        let ca_cert = tonic::transport::Certificate::from_pem(pem);
+        let tls_config = tonic::transport::ClientTlsConfig::new().ca_certificate(ca_cert);
+        client.set_tls_config(tls_config);
+    }
+
+    let auth = match &args.token_file {
+        Some(token_filename) => {
+            let token = std::fs::read_to_string(token_filename)?;
+            Auth(Some(AsciiMetadataValue::try_from(format!(
+                "Bearer {}",
+                token.trim()
+            ))?))
+        }
+        None => Auth(None),
+    };
+
+    let channel = client.get_channel().await?.clone();
+    Ok(ValClient::with_interceptor(channel, auth))
+}
+
+async fn select_signals(
+    client: &mut Client,
+    root: &str,
+    count: usize,
+) -> Result<Vec<Signal>, Box<dyn std::error::Error>> {
+    let metadata = client
+        .list_metadata(v2::ListMetadataRequest {
+            root: root.to_owned(),
+            filter: String::new(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner()
+        .metadata;
+    let signals: Vec<Signal> = metadata
+        .iter()
+        .filter_map(Signal::from_metadata)
+        .take(count)
+        .collect();
+    if signals.len() < count {
+        return Err(format!(
+            "Only {} numeric sensors without restrictions found under '{root}', {count} requested",
+            signals.len()
+        )
+        .into());
+    }
+    Ok(signals)
+}
+
+struct ProviderResult {
+    published: u64,
+    errors: u64,
+}
+
+async fn run_provider(
+    mut client: Client,
+    signals: Vec<(usize, Signal)>,
+    send_log: Arc<SendLog>,
+    rate: f64,
+    deadline: tokio::time::Instant,
+) -> Result<ProviderResult, tonic::Status> {
+    let (sender, receiver) = mpsc::channel(16);
+    let mut responses = client
+        .open_provider_stream(ReceiverStream::new(receiver))
+        .await?
+        .into_inner();
+
+    let errors = tokio::spawn(async move {
+        let mut errors = 0;
+        while let Some(Ok(response)) = responses.next().await {
+            if let Some(open_provider_stream_response::Action::PublishValuesResponse(response)) =
+                response.action
+            {
+                errors += response.status.len() as u64;
+            }
+        }
+        errors
+    });
+
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+    let mut published = 0;
+    let mut seq = 0;
+    while timeout_at(deadline, interval.tick()).await.is_ok() {
+        seq += 1;
+        let timestamp = Some(SystemTime::now().into());
+        let data_points = signals
+            .iter()
+            .map(|(_, signal)| {
+                (
+                    signal.id,
+                    v2::Datapoint {
+                        timestamp: timestamp.clone(),
+                        value: Some(signal.encode(seq)),
+                    },
+                )
+            })
+            .collect();
+        let sent = Instant::now();
+        for (index, _) in &signals {
+            send_log.record(*index, seq, sent);
+        }
+        let request = v2::OpenProviderStreamRequest {
+            action: Some(open_provider_stream_request::Action::PublishValuesRequest(
+                v2::PublishValuesRequest {
+                    request_id: seq as u32,
+                    data_points,
+                },
+            )),
+        };
+        if sender.send(request).await.is_err() {
+            break;
+        }
+        published += signals.len() as u64;
+    }
+
+    // Closing the request stream ends the response stream
+    drop(sender);
+    let errors = errors.await.unwrap_or_default();
+    Ok(ProviderResult { published, errors })
+}
+
+async fn run_subscriber(
+    mut client: Client,
+    signals: Arc<HashMap<String, usize>>,
+    send_log: Arc<SendLog>,
+    buffer_size: u32,
+    ready: mpsc::Sender<()>,
+    mut stop: watch::Receiver<bool>,
+) -> Result<Latencies, tonic::Status> {
+    let mut stream = client
+        .subscribe(v2::SubscribeRequest {
+            signal_paths: signals.keys().cloned().collect(),
+            buffer_size,
+            filter: None,
+            changes_only: false,
+            delta_encoding: None,
+            backfill_since: None,
+            priority: 0,
+        })
+        .await?
+        .into_inner();
+
+    let mut latencies = Latencies::default();
+    let mut ready = Some(ready);
+    loop {
+        let response = tokio::select! {
+            response = stream.next() => match response {
+                Some(response) => response,
+                None => break,
+            },
+            _ = stop.changed() => break,
+        };
+        let received = Instant::now();
+        for (path, datapoint) in response?.entries {
+            let sent = signals.get(&path).and_then(|index| {
+                let seq = signals::decode(datapoint.value.as_ref()?)?;
+                send_log.sent(*index, seq)
+            });
+            match sent {
+                Some(sent) => latencies.add(received.saturating_duration_since(sent)),
+                None => latencies.unmatched += 1,
+            }
+        }
+        // The first response carries the initial values
+        if let Some(ready) = ready.take() {
+            let _ = ready.send(()).await;
+        }
+    }
+    Ok(latencies)
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    if args.providers == 0 || args.signals < args.providers {
+        return Err("At least one signal per provider is needed".into());
+    }
+    if !args.rate.is_finite() || args.rate <= 0.0 {
+        return Err("Rate must be a positive number".into());
+    }
+
+    let mut client = connect(&args).await?;
+    let signals = select_signals(&mut client, &args.root, args.signals).await?;
+    let send_log = Arc::new(SendLog::new(signals.len()));
+    let paths: Arc<HashMap<String, usize>> = Arc::new(
+        signals
+            .iter()
+            .enumerate()
+            .map(|(index, signal)| (signal.path.clone(), index))
+            .collect(),
+    );
+
+    println!(
+        "{} providers, {} subscribers, {} signals at {} Hz for {} s",
+        args.providers, args.subscribers, args.signals, args.rate, args.duration
+    );
+
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let (ready_sender, mut ready_receiver) = mpsc::channel(args.subscribers.max(1));
+    let subscribers: Vec<_> = (0..args.subscribers)
+        .map(|_| {
+            tokio::spawn(run_subscriber(
+                client.clone(),
+                paths.clone(),
+                send_log.clone(),
+                args.buffer_size,
+                ready_sender.clone(),
+                stop_receiver.clone(),
+            ))
+        })
+        .collect();
+    drop(ready_sender);
+    for _ in 0..args.subscribers {
+        if ready_receiver.recv().await.is_none() {
+            break;
+        }
+    }
+
+    let start = tokio::time::Instant::now();
+    let deadline = start + Duration::from_secs(args.duration);
+    let providers: Vec<_> = (0..args.providers)
+        .map(|provider| {
+            let provided = signals
+                .iter()
+                .cloned()
+                .enumerate()
+                .filter(|(index, _)| index % args.providers == provider)
+                .collect();
+            tokio::spawn(run_provider(
+                client.clone(),
+                provided,
+                send_log.clone(),
+                args.rate,
+                deadline,
+            ))
+        })
+        .collect();
+
+    let (mut published, mut errors) = (0, 0);
+    for provider in providers {
+        let result = provider.await??;
+        published += result.published;
+        errors += result.errors;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    tokio::time::sleep(DRAIN_TIME).await;
+    let _ = stop_sender.send(true);
+    let mut latencies = Latencies::default();
+    for subscriber in subscribers {
+        latencies.merge(subscriber.await??);
+    }
+
+    let received = latencies.len() as u64;
+    let expected = published * args.subscribers as u64;
+    println!();
+    println!(
+        "Published:  {published} updates ({:.0}/s), {errors} rejected",
+        published as f64 / elapsed
+    );
+    println!(
+        "Received:   {received} of {expected} updates ({:.0}/s), {} unmatched",
+        received as f64 / elapsed,
+        latencies.unmatched
+    );
+    if let Some(summary) = latencies.summary() {
+        println!();
+        println!("Latency (publish to notification):");
+        println!("  mean   {:>10.3} ms", ms(summary.mean));
+        println!("  p50    {:>10.3} ms", ms(summary.p50));
+        println!("  p90    {:>10.3} ms", ms(summary.p90));
+        println!("  p99    {:>10.3} ms", ms(summary.p99));
+        println!("  p99.9  {:>10.3} ms", ms(summary.p999));
+        println!("  max    {:>10.3} ms", ms(summary.max));
+    }
+    Ok(())
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(Args::parse()).await
+}
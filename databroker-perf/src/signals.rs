@@ -0,0 +1,159 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use databroker_proto::kuksa::val::v2::{self, value::TypedValue, DataType, EntryType};
+
+/// Number of recent sends remembered per signal. A notification arriving
+/// after this many newer updates of the same signal is not measured.
+const SEND_LOG_SIZE: usize = 4096;
+
+/// Floats can represent every integer up to 2^24 exactly.
+const FLOAT_SEQUENCE_LIMIT: u64 = 1 << 24;
+
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub id: i32,
+    pub path: String,
+    pub data_type: DataType,
+}
+
+impl Signal {
+    /// Sensors of a numeric type without value restrictions, so that any
+    /// sequence number can be published.
+    pub fn from_metadata(metadata: &v2::Metadata) -> Option<Signal> {
+        if metadata.entry_type() != EntryType::Sensor
+            || metadata.min.is_some()
+            || metadata.max.is_some()
+            || metadata.allowed_values.is_some()
+        {
+            return None;
+        }
+        match metadata.data_type() {
+            data_type @ (DataType::Float
+            | DataType::Double
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::Uint32
+            | DataType::Uint64) => Some(Signal {
+                id: metadata.id,
+                path: metadata.path.clone(),
+                data_type,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Encode the sequence number `seq` as a value of this signal's type.
+    pub fn encode(&self, seq: u64) -> v2::Value {
+        let typed_value = match self.data_type {
+            DataType::Float => TypedValue::Float((seq % FLOAT_SEQUENCE_LIMIT) as f32),
+            DataType::Double => TypedValue::Double(seq as f64),
+            DataType::Int32 => TypedValue::Int32((seq % i32::MAX as u64) as i32),
+            DataType::Int64 => TypedValue::Int64(seq as i64),
+            DataType::Uint32 => TypedValue::Uint32(seq as u32),
+            _ => TypedValue::Uint64(seq),
+        };
+        v2::Value {
+            typed_value: Some(typed_value),
+        }
+    }
+}
+
+/// Decode a sequence number encoded with [`Signal::encode`].
+pub fn decode(value: &v2::Value) -> Option<u64> {
+    match value.typed_value.as_ref()? {
+        TypedValue::Float(value) => Some(*value as u64),
+        TypedValue::Double(value) => Some(*value as u64),
+        TypedValue::Int32(value) => Some(*value as u64),
+        TypedValue::Int64(value) => Some(*value as u64),
+        TypedValue::Uint32(value) => Some(*value as u64),
+        TypedValue::Uint64(value) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Remembers when each (signal, sequence number) was published, so the
+/// subscribers can compute the end-to-end latency.
+pub struct SendLog {
+    signals: Vec<Mutex<Vec<Option<Sent>>>>,
+}
+
+/// Sequence number and the time it was published.
+type Sent = (u64, Instant);
+
+impl SendLog {
+    pub fn new(signal_count: usize) -> Self {
+        Self {
+            signals: (0..signal_count)
+                .map(|_| Mutex::new(vec![None; SEND_LOG_SIZE]))
+                .collect(),
+        }
+    }
+
+    pub fn record(&self, signal: usize, seq: u64, sent: Instant) {
+        let mut log = self.signals[signal].lock().expect("send log lock poisoned");
+        log[seq as usize % SEND_LOG_SIZE] = Some((seq, sent));
+    }
+
+    pub fn sent(&self, signal: usize, seq: u64) -> Option<Instant> {
+        let log = self.signals[signal].lock().expect("send log lock poisoned");
+        match log[seq as usize % SEND_LOG_SIZE] {
+            Some((logged_seq, sent)) if logged_seq == seq => Some(sent),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(data_type: DataType) -> Signal {
+        Signal {
+            id: 1,
+            path: "Vehicle.Speed".to_owned(),
+            data_type,
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for data_type in [
+            DataType::Float,
+            DataType::Double,
+            DataType::Int32,
+            DataType::Int64,
+            DataType::Uint32,
+            DataType::Uint64,
+        ] {
+            let value = signal(data_type).encode(12345);
+            assert_eq!(decode(&value), Some(12345), "{data_type:?}");
+        }
+    }
+
+    #[test]
+    fn test_send_log() {
+        let log = SendLog::new(1);
+        let sent = Instant::now();
+        log.record(0, 7, sent);
+        assert_eq!(log.sent(0, 7), Some(sent));
+        assert_eq!(log.sent(0, 8), None);
+
+        // Overwritten by a newer sequence number
+        log.record(0, 7 + SEND_LOG_SIZE as u64, sent);
+        assert_eq!(log.sent(0, 7), None);
+    }
+}
@@ -0,0 +1,95 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::time::Duration;
+
+/// Latencies collected by a subscriber.
+#[derive(Debug, Default)]
+pub struct Latencies {
+    samples: Vec<Duration>,
+    /// Notifications that could not be matched to a publish, e.g. the
+    /// initial values or updates that were too old.
+    pub unmatched: u64,
+}
+
+impl Latencies {
+    pub fn add(&mut self, latency: Duration) {
+        self.samples.push(latency);
+    }
+
+    pub fn merge(&mut self, other: Latencies) {
+        self.samples.extend(other.samples);
+        self.unmatched += other.unmatched;
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn summary(mut self) -> Option<Summary> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        self.samples.sort_unstable();
+        let total: Duration = self.samples.iter().sum();
+        Some(Summary {
+            mean: total / self.samples.len() as u32,
+            p50: percentile(&self.samples, 50.0),
+            p90: percentile(&self.samples, 90.0),
+            p99: percentile(&self.samples, 99.0),
+            p999: percentile(&self.samples, 99.9),
+            max: self.samples[self.samples.len() - 1],
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+}
+
+/// Nearest-rank percentile of the (sorted, non-empty) `samples`.
+fn percentile(samples: &[Duration], percentile: f64) -> Duration {
+    let rank = (percentile / 100.0 * samples.len() as f64).ceil() as usize;
+    samples[rank.clamp(1, samples.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut latencies = Latencies::default();
+        for ms in (1..=100).rev() {
+            latencies.add(Duration::from_millis(ms));
+        }
+        let summary = latencies.summary().unwrap();
+        assert_eq!(summary.p50, Duration::from_millis(50));
+        assert_eq!(summary.p90, Duration::from_millis(90));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+        assert_eq!(summary.p999, Duration::from_millis(100));
+        assert_eq!(summary.max, Duration::from_millis(100));
+        assert_eq!(summary.mean, Duration::from_micros(50_500));
+    }
+
+    #[test]
+    fn test_empty_summary() {
+        assert_eq!(Latencies::default().summary(), None);
+    }
+}
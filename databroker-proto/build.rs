@@ -13,56 +13,54 @@
 
 use std::{env, path::PathBuf};
 
+/// API versions, the feature enabling each and its proto files
+const APIS: &[(&str, &str, &[&str])] = &[
+    (
+        "sdv.databroker.v1",
+        "CARGO_FEATURE_SDV_V1",
+        &[
+            "proto/sdv/databroker/v1/broker.proto",
+            "proto/sdv/databroker/v1/types.proto",
+            "proto/sdv/databroker/v1/collector.proto",
+        ],
+    ),
+    (
+        "kuksa.val.v1",
+        "CARGO_FEATURE_KUKSA_VAL_V1",
+        &[
+            "proto/kuksa/val/v1/val.proto",
+            "proto/kuksa/val/v1/types.proto",
+        ],
+    ),
+    (
+        "kuksa.val.v2",
+        "CARGO_FEATURE_KUKSA_VAL_V2",
+        &[
+            "proto/kuksa/val/v2/val.proto",
+            "proto/kuksa/val/v2/types.proto",
+        ],
+    ),
+];
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("PROTOC", protobuf_src::protoc());
-    tonic_build::configure()
-        .compile_well_known_types(false)
-        .protoc_arg("--experimental_allow_proto3_optional")
-        .compile(
-            &[
-                "proto/sdv/databroker/v1/broker.proto",
-                "proto/sdv/databroker/v1/types.proto",
-                "proto/sdv/databroker/v1/collector.proto",
-                "proto/kuksa/val/v1/val.proto",
-                "proto/kuksa/val/v1/types.proto",
-                "proto/kuksa/val/v2/val.proto",
-                "proto/kuksa/val/v2/types.proto",
-            ],
-            &["proto"],
-        )?;
-
+    // The generated `connect` functions need tonic's transport, which isn't
+    // available e.g. on wasm32
+    let transport = env::var_os("CARGO_FEATURE_TRANSPORT").is_some();
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
-    tonic_build::configure()
-        .file_descriptor_set_path(out_dir.join("kuksa.val.v2_descriptor.bin"))
-        .compile(
-            &[
-                "proto/kuksa/val/v2/val.proto",
-                "proto/kuksa/val/v2/types.proto",
-            ],
-            &["proto"],
-        )
-        .unwrap();
-    tonic_build::configure()
-        .file_descriptor_set_path(out_dir.join("kuksa.val.v1_descriptor.bin"))
-        .compile(
-            &[
-                "proto/kuksa/val/v1/val.proto",
-                "proto/kuksa/val/v1/types.proto",
-            ],
-            &["proto"],
-        )
-        .unwrap();
-    tonic_build::configure()
-        .file_descriptor_set_path(out_dir.join("sdv.databroker.v1_descriptor.bin"))
-        .compile(
-            &[
-                "proto/sdv/databroker/v1/broker.proto",
-                "proto/sdv/databroker/v1/types.proto",
-                "proto/sdv/databroker/v1/collector.proto",
-            ],
-            &["proto"],
-        )
-        .unwrap();
+
+    // Only the enabled API versions are compiled, to keep clients small
+    for (package, feature, protos) in APIS {
+        if env::var_os(feature).is_none() {
+            continue;
+        }
+        tonic_build::configure()
+            .build_transport(transport)
+            .compile_well_known_types(false)
+            .protoc_arg("--experimental_allow_proto3_optional")
+            .file_descriptor_set_path(out_dir.join(format!("{package}_descriptor.bin")))
+            .compile(protos, &["proto"])?;
+    }
 
     Ok(())
 }
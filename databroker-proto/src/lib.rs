@@ -13,6 +13,13 @@
 
 #![allow(unknown_lints)]
 #![allow(clippy::derive_partial_eq_without_eq)]
+#[cfg(any(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
+pub mod value;
+
+#[cfg(any(feature = "kuksa-val-v1", feature = "kuksa-val-v2", feature = "sdv-v1"))]
+mod timestamp;
+
+#[cfg(feature = "sdv-v1")]
 pub mod sdv {
     pub mod databroker {
         pub mod v1 {
@@ -23,8 +30,10 @@ pub mod sdv {
     }
 }
 
+#[cfg(any(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 pub mod kuksa {
     pub mod val {
+        #[cfg(feature = "kuksa-val-v1")]
         pub mod v1 {
             pub const FILE_DESCRIPTOR_SET: &[u8] =
                 tonic::include_file_descriptor_set!("kuksa.val.v1_descriptor");
@@ -147,6 +156,7 @@ pub mod kuksa {
                 }
             }
         }
+        #[cfg(feature = "kuksa-val-v2")]
         pub mod v2 {
             tonic::include_proto!("kuksa.val.v2");
 
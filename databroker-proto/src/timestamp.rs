@@ -0,0 +1,103 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Creating timestamped datapoints and reading their timestamps.
+//!
+//! Any time that converts into a [`SystemTime`] can be used, which includes
+//! `chrono::DateTime`, e.g. `Datapoint::at(42.0f32, chrono::Utc::now())`.
+
+use std::time::{Duration, SystemTime};
+
+macro_rules! timestamp_helpers {
+    ($datapoint:ty, $value:ty) => {
+        impl $datapoint {
+            /// A datapoint of `value` stamped with the current time.
+            pub fn now(value: impl Into<$value>) -> Self {
+                Self::at(value, SystemTime::now())
+            }
+
+            /// A datapoint of `value` stamped with `time`.
+            pub fn at(value: impl Into<$value>, time: impl Into<SystemTime>) -> Self {
+                Self {
+                    timestamp: Some(time.into().into()),
+                    value: Some(value.into()),
+                }
+            }
+
+            /// The timestamp, `None` if there is none or it is out of range.
+            pub fn system_time(&self) -> Option<SystemTime> {
+                SystemTime::try_from(self.timestamp.clone()?).ok()
+            }
+
+            /// The time passed since the value was sampled, `None` if there
+            /// is no timestamp or it is in the future.
+            pub fn age(&self) -> Option<Duration> {
+                SystemTime::now().duration_since(self.system_time()?).ok()
+            }
+        }
+    };
+}
+
+#[cfg(feature = "kuksa-val-v1")]
+timestamp_helpers!(
+    crate::kuksa::val::v1::Datapoint,
+    crate::kuksa::val::v1::datapoint::Value
+);
+#[cfg(feature = "kuksa-val-v2")]
+timestamp_helpers!(
+    crate::kuksa::val::v2::Datapoint,
+    crate::kuksa::val::v2::Value
+);
+#[cfg(feature = "sdv-v1")]
+timestamp_helpers!(
+    crate::sdv::databroker::v1::Datapoint,
+    crate::sdv::databroker::v1::datapoint::Value
+);
+
+#[cfg(all(test, feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
+mod tests {
+    use super::*;
+    use crate::kuksa::val::{v1, v2};
+
+    #[test]
+    fn test_at() {
+        let time = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 500);
+        let datapoint = v1::Datapoint::at(true, time);
+        assert_eq!(
+            datapoint.timestamp,
+            Some(prost_types::Timestamp {
+                seconds: 1_700_000_000,
+                nanos: 500
+            })
+        );
+        assert_eq!(datapoint.value, Some(v1::datapoint::Value::Bool(true)));
+        assert_eq!(datapoint.system_time(), Some(time));
+        assert!(datapoint.age().unwrap() > Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_now() {
+        let datapoint = v2::Datapoint::now(3u32);
+        assert!(datapoint.age().unwrap() < Duration::from_secs(60));
+
+        let future = v2::Datapoint::at(3u32, SystemTime::now() + Duration::from_secs(60));
+        assert!(future.system_time().is_some());
+        assert_eq!(future.age(), None);
+
+        let unstamped = v2::Datapoint {
+            timestamp: None,
+            value: None,
+        };
+        assert_eq!(unstamped.system_time(), None);
+    }
+}
@@ -0,0 +1,390 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Conversions between Rust values and the value types of kuksa.val.
+//!
+//! Rust values convert into the value oneofs, the `Datapoint`s and the
+//! kuksa.val.v2 `Value` with `From`, the other direction is `TryFrom`:
+//!
+//! - integers convert into any integer type they fit into,
+//! - integers and floats convert into `f32` and `f64`, a `double` only into
+//!   `f32` if it is within its range (losing precision),
+//! - floats don't convert into integers, `bool` and `String` only convert
+//!   into themselves,
+//! - arrays convert into `Vec`s element by element, following the same
+//!   rules.
+//!
+//! The VSS types `int8`, `int16`, `uint8` and `uint16` are sent as `int32`
+//! and `uint32`, so `i8`, `i16`, `u8` and `u16` are supported as well.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionError {
+    /// The value is of a type that doesn't convert into the requested one
+    Mismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The value doesn't fit into the requested type
+    OutOfRange {
+        expected: &'static str,
+        value: String,
+    },
+    /// The datapoint doesn't have a value
+    Missing,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::Mismatch { expected, found } => {
+                write!(
+                    f,
+                    "expected a value convertible to {expected}, found {found}"
+                )
+            }
+            ConversionError::OutOfRange { expected, value } => {
+                write!(f, "{value} is out of range for {expected}")
+            }
+            ConversionError::Missing => write!(f, "the datapoint has no value"),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Implement the conversions for the value oneof, which has to be in scope
+/// as `Value` together with the array messages.
+macro_rules! value_conversions {
+    () => {
+        fn kind(value: &Value) -> &'static str {
+            match value {
+                Value::String(_) => "string",
+                Value::Bool(_) => "bool",
+                Value::Int32(_) => "int32",
+                Value::Int64(_) => "int64",
+                Value::Uint32(_) => "uint32",
+                Value::Uint64(_) => "uint64",
+                Value::Float(_) => "float",
+                Value::Double(_) => "double",
+                Value::StringArray(_) => "string[]",
+                Value::BoolArray(_) => "bool[]",
+                Value::Int32Array(_) => "int32[]",
+                Value::Int64Array(_) => "int64[]",
+                Value::Uint32Array(_) => "uint32[]",
+                Value::Uint64Array(_) => "uint64[]",
+                Value::FloatArray(_) => "float[]",
+                Value::DoubleArray(_) => "double[]",
+            }
+        }
+
+        fn mismatch(expected: &'static str, value: &Value) -> ConversionError {
+            ConversionError::Mismatch {
+                expected,
+                found: kind(value),
+            }
+        }
+
+        fn out_of_range(expected: &'static str, value: impl ToString) -> ConversionError {
+            ConversionError::OutOfRange {
+                expected,
+                value: value.to_string(),
+            }
+        }
+
+        value_conversions!(@from Value::Int32, Int32Array: i8, i16, i32);
+        value_conversions!(@from Value::Int64, Int64Array: i64);
+        value_conversions!(@from Value::Uint32, Uint32Array: u8, u16, u32);
+        value_conversions!(@from Value::Uint64, Uint64Array: u64);
+        value_conversions!(@from Value::Float, FloatArray: f32);
+        value_conversions!(@from Value::Double, DoubleArray: f64);
+        value_conversions!(@from Value::Bool, BoolArray: bool);
+        value_conversions!(@from Value::String, StringArray: String);
+
+        impl From<&str> for Value {
+            fn from(value: &str) -> Self {
+                Value::String(value.to_owned())
+            }
+        }
+
+        value_conversions!(@try_integer i8, i16, i32, i64, u8, u16, u32, u64);
+
+        impl TryFrom<Value> for f64 {
+            type Error = ConversionError;
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::Float(v) => Ok(v.into()),
+                    Value::Double(v) => Ok(v),
+                    Value::Int32(v) => Ok(v.into()),
+                    Value::Int64(v) => Ok(v as f64),
+                    Value::Uint32(v) => Ok(v.into()),
+                    Value::Uint64(v) => Ok(v as f64),
+                    other => Err(mismatch("f64", &other)),
+                }
+            }
+        }
+
+        impl TryFrom<Value> for f32 {
+            type Error = ConversionError;
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::Float(v) => Ok(v),
+                    Value::Double(v) if v.is_finite() && v.abs() > f32::MAX.into() => {
+                        Err(out_of_range("f32", v))
+                    }
+                    Value::Double(v) => Ok(v as f32),
+                    Value::Int32(v) => Ok(v as f32),
+                    Value::Int64(v) => Ok(v as f32),
+                    Value::Uint32(v) => Ok(v as f32),
+                    Value::Uint64(v) => Ok(v as f32),
+                    other => Err(mismatch("f32", &other)),
+                }
+            }
+        }
+
+        impl TryFrom<Value> for bool {
+            type Error = ConversionError;
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::Bool(v) => Ok(v),
+                    other => Err(mismatch("bool", &other)),
+                }
+            }
+        }
+
+        impl TryFrom<Value> for String {
+            type Error = ConversionError;
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::String(v) => Ok(v),
+                    other => Err(mismatch("String", &other)),
+                }
+            }
+        }
+
+        value_conversions!(@try_array i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, bool, String);
+    };
+    (@from $variant:path, $array:ident: $($t:ty),*) => {$(
+        impl From<$t> for Value {
+            fn from(value: $t) -> Self {
+                $variant(value.into())
+            }
+        }
+
+        impl From<Vec<$t>> for Value {
+            fn from(values: Vec<$t>) -> Self {
+                Value::$array($array {
+                    values: values.into_iter().map(Into::into).collect(),
+                })
+            }
+        }
+    )*};
+    (@try_integer $($t:ty),*) => {$(
+        impl TryFrom<Value> for $t {
+            type Error = ConversionError;
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value::Int32(v) => <$t>::try_from(v).map_err(|_| out_of_range(stringify!($t), v)),
+                    Value::Int64(v) => <$t>::try_from(v).map_err(|_| out_of_range(stringify!($t), v)),
+                    Value::Uint32(v) => <$t>::try_from(v).map_err(|_| out_of_range(stringify!($t), v)),
+                    Value::Uint64(v) => <$t>::try_from(v).map_err(|_| out_of_range(stringify!($t), v)),
+                    other => Err(mismatch(stringify!($t), &other)),
+                }
+            }
+        }
+    )*};
+    (@try_array $($t:ty),*) => {$(
+        impl TryFrom<Value> for Vec<$t> {
+            type Error = ConversionError;
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                fn convert<T>(values: Vec<T>, element: fn(T) -> Value) -> Result<Vec<$t>, ConversionError> {
+                    values.into_iter().map(|v| <$t>::try_from(element(v))).collect()
+                }
+                match value {
+                    Value::StringArray(array) => convert(array.values, Value::String),
+                    Value::BoolArray(array) => convert(array.values, Value::Bool),
+                    Value::Int32Array(array) => convert(array.values, Value::Int32),
+                    Value::Int64Array(array) => convert(array.values, Value::Int64),
+                    Value::Uint32Array(array) => convert(array.values, Value::Uint32),
+                    Value::Uint64Array(array) => convert(array.values, Value::Uint64),
+                    Value::FloatArray(array) => convert(array.values, Value::Float),
+                    Value::DoubleArray(array) => convert(array.values, Value::Double),
+                    other => Err(mismatch(concat!(stringify!($t), "[]"), &other)),
+                }
+            }
+        }
+    )*};
+}
+
+/// Implement the conversions for a message wrapping the value in the
+/// optional field `$field`, on top of the ones of the wrapped value.
+macro_rules! wrapper_conversions {
+    ($wrapper:ty, $field:ident) => {
+        wrapper_conversions!(@from $wrapper, $field: &str);
+        wrapper_conversions!(
+            @from $wrapper, $field: i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, bool, String
+        );
+        wrapper_conversions!(
+            @from $wrapper, $field: Vec<i8>, Vec<i16>, Vec<i32>, Vec<i64>, Vec<u8>, Vec<u16>,
+            Vec<u32>, Vec<u64>, Vec<f32>, Vec<f64>, Vec<bool>, Vec<String>
+        );
+        wrapper_conversions!(
+            @try $wrapper, $field: i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, bool, String
+        );
+        wrapper_conversions!(
+            @try $wrapper, $field: Vec<i8>, Vec<i16>, Vec<i32>, Vec<i64>, Vec<u8>, Vec<u16>,
+            Vec<u32>, Vec<u64>, Vec<f32>, Vec<f64>, Vec<bool>, Vec<String>
+        );
+    };
+    (@from $wrapper:ty, $field:ident: $($t:ty),*) => {$(
+        impl From<$t> for $wrapper {
+            // The update is needed for wrappers with more than the value
+            #[allow(clippy::needless_update)]
+            fn from(value: $t) -> Self {
+                Self {
+                    $field: Some(value.into()),
+                    ..Default::default()
+                }
+            }
+        }
+    )*};
+    (@try $wrapper:ty, $field:ident: $($t:ty),*) => {$(
+        impl TryFrom<$wrapper> for $t {
+            type Error = ConversionError;
+            fn try_from(wrapper: $wrapper) -> Result<Self, Self::Error> {
+                wrapper.$field.ok_or(ConversionError::Missing)?.try_into()
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "kuksa-val-v1")]
+mod v1 {
+    use super::ConversionError;
+    use crate::kuksa::val::v1::{
+        datapoint::Value, BoolArray, Datapoint, DoubleArray, FloatArray, Int32Array, Int64Array,
+        StringArray, Uint32Array, Uint64Array,
+    };
+
+    value_conversions!();
+    wrapper_conversions!(Datapoint, value);
+}
+
+#[cfg(feature = "kuksa-val-v2")]
+mod v2 {
+    use super::ConversionError;
+    use crate::kuksa::val::v2::{
+        self as proto, value::TypedValue as Value, BoolArray, DoubleArray, FloatArray, Int32Array,
+        Int64Array, StringArray, Uint32Array, Uint64Array,
+    };
+
+    value_conversions!();
+    wrapper_conversions!(proto::Value, typed_value);
+    wrapper_conversions!(proto::Datapoint, value);
+}
+
+#[cfg(all(test, feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
+mod tests {
+    use super::ConversionError;
+    use crate::kuksa::val::{v1, v2};
+    use v1::datapoint::Value;
+
+    #[test]
+    fn test_into_value() {
+        assert_eq!(Value::from(-3i8), Value::Int32(-3));
+        assert_eq!(Value::from(7u16), Value::Uint32(7));
+        assert_eq!(Value::from("on"), Value::String("on".to_owned()));
+        assert_eq!(
+            Value::from(vec![1u8, 2]),
+            Value::Uint32Array(v1::Uint32Array { values: vec![1, 2] })
+        );
+
+        let datapoint = v1::Datapoint::from(42.0f32);
+        assert_eq!(datapoint.timestamp, None);
+        assert_eq!(datapoint.value, Some(Value::Float(42.0)));
+
+        let datapoint = v2::Datapoint::from(true);
+        assert_eq!(
+            datapoint.value,
+            Some(v2::Value {
+                typed_value: Some(v2::value::TypedValue::Bool(true))
+            })
+        );
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(i64::try_from(Value::Int32(-5)), Ok(-5));
+        assert_eq!(u8::try_from(Value::Int64(200)), Ok(200));
+        assert_eq!(
+            u8::try_from(Value::Int32(-1)),
+            Err(ConversionError::OutOfRange {
+                expected: "u8",
+                value: "-1".to_owned()
+            })
+        );
+        assert!(i32::try_from(Value::Uint64(u64::MAX)).is_err());
+        assert_eq!(
+            i32::try_from(Value::Float(1.0)),
+            Err(ConversionError::Mismatch {
+                expected: "i32",
+                found: "float"
+            })
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(f64::try_from(Value::Float(0.5)), Ok(0.5));
+        assert_eq!(f64::try_from(Value::Uint32(3)), Ok(3.0));
+        assert_eq!(f32::try_from(Value::Double(0.25)), Ok(0.25));
+        assert!(f32::try_from(Value::Double(1e300)).is_err());
+        assert!(f32::try_from(Value::Double(f64::INFINITY)).is_ok());
+        assert!(f64::try_from(Value::Bool(true)).is_err());
+    }
+
+    #[test]
+    fn test_arrays() {
+        let value = Value::Int32Array(v1::Int32Array {
+            values: vec![1, 2, 3],
+        });
+        assert_eq!(Vec::<u16>::try_from(value.clone()), Ok(vec![1, 2, 3]));
+        assert_eq!(Vec::<f64>::try_from(value.clone()), Ok(vec![1.0, 2.0, 3.0]));
+        assert!(Vec::<bool>::try_from(value.clone()).is_err());
+        assert!(i32::try_from(value).is_err());
+
+        let value = Value::Int32Array(v1::Int32Array { values: vec![-1] });
+        assert!(Vec::<u32>::try_from(value).is_err());
+    }
+
+    #[test]
+    fn test_wrappers() {
+        let datapoint = v1::Datapoint {
+            timestamp: None,
+            value: None,
+        };
+        assert_eq!(bool::try_from(datapoint), Err(ConversionError::Missing));
+
+        let datapoint = v2::Datapoint::from(vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(
+            Vec::<String>::try_from(datapoint),
+            Ok(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert_eq!(String::try_from(v2::Value::from("x")), Ok("x".to_owned()));
+        assert_eq!(
+            u64::try_from(v2::Value { typed_value: None }),
+            Err(ConversionError::Missing)
+        );
+    }
+}
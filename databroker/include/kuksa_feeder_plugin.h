@@ -0,0 +1,99 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+/*
+ * Interface of feeder plugins loaded into KUKSA Databroker, see
+ * `--feeder-plugins` in the user guide.
+ */
+
+#ifndef KUKSA_FEEDER_PLUGIN_H
+#define KUKSA_FEEDER_PLUGIN_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define KUKSA_FEEDER_ABI_VERSION 1
+
+#define KUKSA_VALUE_NOT_AVAILABLE 0
+#define KUKSA_VALUE_BOOL 1
+#define KUKSA_VALUE_INT 2
+#define KUKSA_VALUE_UINT 3
+#define KUKSA_VALUE_FLOAT 4
+#define KUKSA_VALUE_STRING 5
+
+#define KUKSA_PUBLISH_OK 0
+/* The value was dropped, the broker is behind */
+#define KUKSA_PUBLISH_QUEUE_FULL 1
+#define KUKSA_PUBLISH_UNKNOWN_ID -1
+/* The value doesn't fit the data type of the signal */
+#define KUKSA_PUBLISH_WRONG_TYPE -2
+#define KUKSA_PUBLISH_STOPPED -3
+
+#define KUKSA_LOG_ERROR 0
+#define KUKSA_LOG_WARN 1
+#define KUKSA_LOG_INFO 2
+#define KUKSA_LOG_DEBUG 3
+
+/* A value, held by the field `kind` tells */
+typedef struct KuksaValue {
+    uint32_t kind;
+    bool bool_value;
+    int64_t int_value;
+    uint64_t uint_value;
+    double float_value;
+    /* NUL terminated UTF-8 */
+    const char *string_value;
+} KuksaValue;
+
+/* The functions of the broker, which may be called from any thread */
+typedef struct KuksaHostV1 {
+    uint32_t abi_version;
+    void *context;
+    /*
+     * The id of the signal at `path`, -1 if there is none. Only signals known
+     * when the plugin was started can be resolved and published.
+     */
+    int32_t (*resolve)(void *context, const char *path);
+    /*
+     * Publish the current value of a signal. `timestamp_ns` is the time the
+     * value was sampled in nanoseconds since the UNIX epoch, 0 if unknown.
+     * Returns one of the KUKSA_PUBLISH_* codes.
+     */
+    int32_t (*publish)(void *context, int32_t id, const KuksaValue *value, uint64_t timestamp_ns);
+    /* Log `message` at one of the KUKSA_LOG_* levels */
+    void (*log)(void *context, uint32_t level, const char *message);
+} KuksaHostV1;
+
+/* Exported by the plugin */
+
+/* Returns KUKSA_FEEDER_ABI_VERSION */
+uint32_t kuksa_feeder_abi_version(void);
+
+/*
+ * Start feeding. `config` is the plugin's configuration as JSON. `host`
+ * stays valid until kuksa_feeder_stop() returns. Returns 0 when started.
+ */
+int32_t kuksa_feeder_start(const KuksaHostV1 *host, const char *config);
+
+/* Stop feeding. `host` must not be used after returning. */
+void kuksa_feeder_stop(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* KUKSA_FEEDER_PLUGIN_H */
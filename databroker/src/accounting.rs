@@ -0,0 +1,141 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Accounting of the load each client puts on the broker, so that load on
+//! a shared broker can be attributed and runaway clients found. Clients are
+//! identified by the subject of their access token, like for quotas.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The usage of a client since startup.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientUsage {
+    /// The subject of the client's access token
+    pub client: Option<String>,
+    /// Calls to any of the APIs
+    pub requests: u64,
+    /// Currently active subscriptions
+    pub subscriptions: usize,
+    /// Approximate size of the notifications sent to the client
+    pub streamed_bytes: u64,
+}
+
+/// Counters of a single client, shared with its subscriptions.
+#[derive(Debug, Default)]
+pub struct ClientCounters {
+    requests: AtomicU64,
+    streamed_bytes: AtomicU64,
+}
+
+impl ClientCounters {
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_streamed(&self, bytes: usize) {
+        self.streamed_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Accounting {
+    clients: Mutex<HashMap<Option<String>, Arc<ClientCounters>>>,
+}
+
+impl Accounting {
+    /// The counters of `client`, created on first use.
+    pub fn client(&self, client: Option<&str>) -> Arc<ClientCounters> {
+        self.clients
+            .lock()
+            .expect("accounting should not be poisoned")
+            .entry(client.map(str::to_owned))
+            .or_default()
+            .clone()
+    }
+
+    /// The usage of every client seen so far, ordered by client. The
+    /// subscriptions are filled in from `subscriptions`, the number of
+    /// active subscriptions per client.
+    pub fn usage(&self, subscriptions: &HashMap<Option<String>, usize>) -> Vec<ClientUsage> {
+        let clients = self
+            .clients
+            .lock()
+            .expect("accounting should not be poisoned");
+        let mut usage: Vec<ClientUsage> = clients
+            .iter()
+            .map(|(client, counters)| ClientUsage {
+                client: client.clone(),
+                requests: counters.requests.load(Ordering::Relaxed),
+                subscriptions: subscriptions.get(client).copied().unwrap_or_default(),
+                streamed_bytes: counters.streamed_bytes.load(Ordering::Relaxed),
+            })
+            .collect();
+        // Clients that only hold subscriptions created internally
+        for (client, count) in subscriptions {
+            if !clients.contains_key(client) {
+                usage.push(ClientUsage {
+                    client: client.clone(),
+                    subscriptions: *count,
+                    ..Default::default()
+                });
+            }
+        }
+        usage.sort_by(|a, b| a.client.cmp(&b.client));
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_per_client() {
+        let accounting = Accounting::default();
+        let a = accounting.client(Some("a"));
+        a.record_request();
+        a.record_request();
+        a.record_streamed(100);
+        accounting.client(None).record_request();
+        // The same counters are shared
+        accounting.client(Some("a")).record_streamed(20);
+
+        let subscriptions = HashMap::from([(Some("a".to_owned()), 2), (Some("b".to_owned()), 1)]);
+        assert_eq!(
+            accounting.usage(&subscriptions),
+            vec![
+                ClientUsage {
+                    client: None,
+                    requests: 1,
+                    subscriptions: 0,
+                    streamed_bytes: 0,
+                },
+                ClientUsage {
+                    client: Some("a".to_owned()),
+                    requests: 2,
+                    subscriptions: 2,
+                    streamed_bytes: 120,
+                },
+                ClientUsage {
+                    client: Some("b".to_owned()),
+                    requests: 0,
+                    subscriptions: 1,
+                    streamed_bytes: 0,
+                },
+            ]
+        );
+    }
+}
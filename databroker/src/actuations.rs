@@ -0,0 +1,217 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Tracking of actuation requests. Every actuation gets an id that is
+//! returned to the requester and forwarded to the provider along with the
+//! request. The provider reports the outcome with that id, so the
+//! requester can look up what became of its request instead of waiting for
+//! the value of the actuator to change.
+//!
+//! An actuation is `Pending` until it is forwarded to the provider and
+//! `Delivered` afterwards. The provider reports it as `Executed` or
+//! `Failed`. Without such a report within [`TIMEOUT`], it is `Expired`.
+//! Finished actuations are forgotten after [`RETENTION`].
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Time a provider has to report the outcome of an actuation.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+/// Time the outcome of an actuation can be looked up for.
+pub const RETENTION: Duration = Duration::from_secs(300);
+/// Upper bound of the number of tracked actuations, the oldest ones are
+/// forgotten first.
+const MAX_ACTUATIONS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActuationStatus {
+    Pending,
+    Delivered,
+    Executed,
+    Failed,
+    Expired,
+}
+
+impl ActuationStatus {
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            ActuationStatus::Executed | ActuationStatus::Failed | ActuationStatus::Expired
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActuationInfo {
+    pub id: u64,
+    /// Id of the actuator
+    pub vss_id: i32,
+    pub status: ActuationStatus,
+    /// Reason of a failure, as reported by the provider
+    pub message: Option<String>,
+    /// Time (clock elapsed) of the request and of the latest status change
+    pub requested: Duration,
+    pub updated: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct Actuations {
+    last_id: AtomicU64,
+    actuations: Mutex<BTreeMap<u64, ActuationInfo>>,
+}
+
+impl Actuations {
+    /// Start tracking an actuation of `vss_id`. Returns its id.
+    pub fn add(&self, vss_id: i32, now: Duration) -> u64 {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut actuations = self
+            .actuations
+            .lock()
+            .expect("actuations should not be poisoned");
+        actuations.insert(
+            id,
+            ActuationInfo {
+                id,
+                vss_id,
+                status: ActuationStatus::Pending,
+                message: None,
+                requested: now,
+                updated: now,
+            },
+        );
+        while actuations.len() > MAX_ACTUATIONS {
+            actuations.pop_first();
+        }
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<ActuationInfo> {
+        self.actuations
+            .lock()
+            .expect("actuations should not be poisoned")
+            .get(&id)
+            .cloned()
+    }
+
+    /// Set the status of actuation `id`. The status of a finished actuation
+    /// doesn't change anymore, nor does an actuation go back from
+    /// `Delivered` to `Pending`. Returns false if the actuation isn't
+    /// tracked.
+    pub fn set_status(
+        &self,
+        id: u64,
+        status: ActuationStatus,
+        message: Option<String>,
+        now: Duration,
+    ) -> bool {
+        let mut actuations = self
+            .actuations
+            .lock()
+            .expect("actuations should not be poisoned");
+        let Some(actuation) = actuations.get_mut(&id) else {
+            return false;
+        };
+        if actuation.status.is_final() || status == ActuationStatus::Pending {
+            return true;
+        }
+        actuation.status = status;
+        actuation.message = message;
+        actuation.updated = now;
+        true
+    }
+
+    /// Expire the actuations without outcome after [`TIMEOUT`] and forget
+    /// the finished ones after [`RETENTION`].
+    pub fn expire(&self, now: Duration) {
+        let mut actuations = self
+            .actuations
+            .lock()
+            .expect("actuations should not be poisoned");
+        actuations.retain(|_, actuation| {
+            if !actuation.status.is_final() && now >= actuation.requested + TIMEOUT {
+                actuation.status = ActuationStatus::Expired;
+                actuation.updated = now;
+            }
+            !actuation.status.is_final() || now < actuation.updated + RETENTION
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.actuations
+            .lock()
+            .expect("actuations should not be poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let actuations = Actuations::default();
+        let id = actuations.add(7, Duration::ZERO);
+        assert_eq!(actuations.get(id).unwrap().status, ActuationStatus::Pending);
+        assert_ne!(actuations.add(7, Duration::ZERO), id);
+
+        let at = Duration::from_secs(1);
+        assert!(actuations.set_status(id, ActuationStatus::Delivered, None, at));
+        assert!(actuations.set_status(id, ActuationStatus::Pending, None, at));
+        assert_eq!(
+            actuations.get(id).unwrap().status,
+            ActuationStatus::Delivered
+        );
+
+        let failed = Some("jammed".to_owned());
+        assert!(actuations.set_status(id, ActuationStatus::Failed, failed.clone(), at));
+        // Finished is finished
+        assert!(actuations.set_status(id, ActuationStatus::Executed, None, at));
+        let actuation = actuations.get(id).unwrap();
+        assert_eq!(actuation.status, ActuationStatus::Failed);
+        assert_eq!(actuation.message, failed);
+
+        assert!(!actuations.set_status(1234, ActuationStatus::Executed, None, at));
+    }
+
+    #[test]
+    fn test_expire() {
+        let actuations = Actuations::default();
+        let delivered = actuations.add(1, Duration::ZERO);
+        actuations.set_status(delivered, ActuationStatus::Delivered, None, Duration::ZERO);
+        let executed = actuations.add(1, Duration::ZERO);
+        actuations.set_status(executed, ActuationStatus::Executed, None, Duration::ZERO);
+
+        actuations.expire(TIMEOUT);
+        assert_eq!(
+            actuations.get(delivered).unwrap().status,
+            ActuationStatus::Expired
+        );
+        assert_eq!(
+            actuations.get(executed).unwrap().status,
+            ActuationStatus::Executed
+        );
+
+        actuations.expire(RETENTION);
+        assert!(actuations.get(executed).is_none());
+        assert!(actuations.get(delivered).is_some());
+        actuations.expire(TIMEOUT + RETENTION);
+        assert!(actuations.is_empty());
+    }
+}
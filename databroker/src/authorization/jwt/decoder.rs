@@ -14,16 +14,19 @@
 use std::convert::TryFrom;
 
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::permissions::{Permission, Permissions, PermissionsBuildError};
+use crate::broker::SubscriptionPriority;
+use crate::permissions::{Permission, PermissionBuilder, Permissions, PermissionsBuildError};
 
-use super::scope;
+use super::{scope, ClaimMapping, TokenExchange};
 
 #[derive(Debug)]
 pub enum Error {
     PublicKeyError(String),
+    PrivateKeyError(String),
     DecodeError(String),
+    EncodeError(String),
     ClaimsError,
 }
 
@@ -38,9 +41,11 @@ impl std::fmt::Display for Error {
 pub struct Decoder {
     decoding_key: DecodingKey,
     validator: Validation,
+    claim_mapping: ClaimMapping,
+    token_exchange: Option<TokenExchange>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Claims {
     #[allow(dead_code)]
     pub sub: String, // Subject (whom token refers to)
@@ -57,6 +62,19 @@ pub struct Claims {
     pub scope: String,
 }
 
+/// Claims as found in the token, the scopes are looked up through the
+/// [`ClaimMapping`].
+#[derive(Deserialize)]
+struct TokenClaims {
+    sub: String,
+    iss: String,
+    aud: Vec<String>,
+    iat: u64,
+    exp: u64,
+    #[serde(flatten)]
+    other: serde_json::Map<String, serde_json::Value>,
+}
+
 impl Decoder {
     pub fn new(public_key: impl Into<String>) -> Result<Decoder, Error> {
         let decoding_key = match DecodingKey::from_rsa_pem(public_key.into().as_bytes()) {
@@ -76,61 +94,110 @@ impl Decoder {
         Ok(Decoder {
             decoding_key,
             validator,
+            claim_mapping: ClaimMapping::default(),
+            token_exchange: None,
         })
     }
 
+    /// Read the scopes from the claims given by `claim_mapping` rather than
+    /// from `scope`.
+    pub fn with_claim_mapping(mut self, claim_mapping: ClaimMapping) -> Self {
+        self.claim_mapping = claim_mapping;
+        self
+    }
+
+    /// Accept the tokens minted by `token_exchange` as well.
+    pub fn with_token_exchange(mut self, token_exchange: TokenExchange) -> Self {
+        self.token_exchange = Some(token_exchange);
+        self
+    }
+
     pub fn decode(&self, token: impl AsRef<str>) -> Result<Claims, Error> {
-        match decode::<Claims>(token.as_ref(), &self.decoding_key, &self.validator) {
-            Ok(token) => Ok(token.claims),
-            Err(err) => Err(Error::DecodeError(err.to_string())),
+        if let Some(token_exchange) = &self.token_exchange {
+            if TokenExchange::is_derived(token.as_ref()) {
+                return token_exchange.decode(token.as_ref());
+            }
         }
+        let claims =
+            match decode::<TokenClaims>(token.as_ref(), &self.decoding_key, &self.validator) {
+                Ok(token) => token.claims,
+                Err(err) => return Err(Error::DecodeError(err.to_string())),
+            };
+        let scope = self
+            .claim_mapping
+            .scope(&claims.other)?
+            .ok_or(Error::ClaimsError)?;
+        Ok(Claims {
+            sub: claims.sub,
+            iss: claims.iss,
+            aud: claims.aud,
+            iat: claims.iat,
+            exp: claims.exp,
+            scope,
+        })
     }
 }
 
-impl TryFrom<Claims> for Permissions {
-    type Error = Error;
+/// The permissions granted by the (whitespace separated) `scope`.
+pub(super) fn scope_permissions(scope: &str) -> Result<PermissionBuilder, Error> {
+    let scopes = scope::parse_whitespace_separated(scope).map_err(|err| match err {
+        scope::Error::ParseError => Error::ClaimsError,
+    })?;
 
-    fn try_from(claims: Claims) -> Result<Self, Self::Error> {
-        let scopes = scope::parse_whitespace_separated(&claims.scope).map_err(|err| match err {
-            scope::Error::ParseError => Error::ClaimsError,
-        })?;
-
-        let mut permissions = Permissions::builder();
-        for scope in scopes {
-            match scope.path {
-                Some(path) => {
-                    permissions = match scope.action {
-                        scope::Action::Read => {
-                            permissions.add_read_permission(Permission::Glob(path))
-                        }
-                        scope::Action::Actuate => {
-                            permissions.add_actuate_permission(Permission::Glob(path))
-                        }
-                        scope::Action::Provide => {
-                            permissions.add_provide_permission(Permission::Glob(path))
-                        }
-                        scope::Action::Create => {
-                            permissions.add_create_permission(Permission::Glob(path))
-                        }
+    let mut permissions = Permissions::builder();
+    for scope in scopes {
+        match scope.path {
+            Some(path) => {
+                permissions = match scope.action {
+                    scope::Action::Read => permissions.add_read_permission(Permission::Glob(path)),
+                    scope::Action::Actuate => {
+                        permissions.add_actuate_permission(Permission::Glob(path))
                     }
+                    scope::Action::ActuateSafety => {
+                        permissions.add_actuate_safety_permission(Permission::Glob(path))
+                    }
+                    scope::Action::Provide => {
+                        permissions.add_provide_permission(Permission::Glob(path))
+                    }
+                    scope::Action::Create => {
+                        permissions.add_create_permission(Permission::Glob(path))
+                    }
+                    // Administration and priorities aren't about paths
+                    scope::Action::Admin
+                    | scope::Action::SubscribeControl
+                    | scope::Action::SubscribeSafety => return Err(Error::ClaimsError),
                 }
-                None => {
-                    // Empty path => all paths
-                    permissions = match scope.action {
-                        scope::Action::Read => permissions.add_read_permission(Permission::All),
-                        scope::Action::Actuate => {
-                            permissions.add_actuate_permission(Permission::All)
-                        }
-                        scope::Action::Provide => {
-                            permissions.add_provide_permission(Permission::All)
-                        }
-                        scope::Action::Create => permissions.add_create_permission(Permission::All),
-                    };
-                }
+            }
+            None => {
+                // Empty path => all paths
+                permissions = match scope.action {
+                    scope::Action::Read => permissions.add_read_permission(Permission::All),
+                    scope::Action::Actuate => permissions.add_actuate_permission(Permission::All),
+                    scope::Action::ActuateSafety => {
+                        permissions.add_actuate_safety_permission(Permission::All)
+                    }
+                    scope::Action::Provide => permissions.add_provide_permission(Permission::All),
+                    scope::Action::Create => permissions.add_create_permission(Permission::All),
+                    scope::Action::Admin => permissions.add_admin_permission(),
+                    scope::Action::SubscribeControl => {
+                        permissions.add_subscription_priority(SubscriptionPriority::Control)
+                    }
+                    scope::Action::SubscribeSafety => {
+                        permissions.add_subscription_priority(SubscriptionPriority::Safety)
+                    }
+                };
             }
         }
+    }
+    Ok(permissions)
+}
+
+impl TryFrom<Claims> for Permissions {
+    type Error = Error;
 
-        permissions = permissions
+    fn try_from(claims: Claims) -> Result<Self, Self::Error> {
+        let permissions = scope_permissions(&claims.scope)?
+            .subject(claims.sub)
             .expires_at(std::time::UNIX_EPOCH + std::time::Duration::from_secs(claims.exp));
 
         permissions.build().map_err(|err| match err {
@@ -171,4 +238,46 @@ AEiqOjPq0D6X45wCzIwjILUCAwEAAQ==
             Err(err) => panic!("decode should succeed but failed with:{}", err),
         }
     }
+
+    #[test]
+    fn test_claim_mapping() {
+        use super::super::{ScopeClaim, DEV_PRIVATE_KEY, DEV_PUBLIC_KEY};
+
+        let encode = |claims: serde_json::Value| {
+            let key = jsonwebtoken::EncodingKey::from_rsa_pem(DEV_PRIVATE_KEY.as_bytes()).unwrap();
+            jsonwebtoken::encode(&jsonwebtoken::Header::new(Algorithm::RS256), &claims, &key)
+                .unwrap()
+        };
+        let exp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 60;
+        let token = encode(serde_json::json!({
+            "sub": "test", "iss": "idp", "aud": ["kuksa.val"], "iat": 0, "exp": exp,
+            "scp": ["read:Vehicle.Speed"],
+            "roles": ["admin", "kuksa:provide:Vehicle.Speed"],
+        }));
+
+        let decoder = Decoder::new(DEV_PUBLIC_KEY).unwrap();
+        assert!(matches!(decoder.decode(&token), Err(Error::ClaimsError)));
+
+        let decoder = decoder.with_claim_mapping(ClaimMapping {
+            scopes: vec![
+                ScopeClaim {
+                    claim: "scp".to_owned(),
+                    prefix: None,
+                },
+                ScopeClaim {
+                    claim: "roles".to_owned(),
+                    prefix: Some("kuksa:".to_owned()),
+                },
+            ],
+        });
+        let claims = decoder.decode(&token).expect("decode should succeed");
+        assert_eq!(claims.scope, "read:Vehicle.Speed provide:Vehicle.Speed");
+        let permissions = Permissions::try_from(claims).expect("claims should be valid");
+        assert!(permissions.can_read("Vehicle.Speed").is_ok());
+        assert!(permissions.can_read("Vehicle.Width").is_err());
+    }
 }
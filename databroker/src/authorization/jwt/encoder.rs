@@ -0,0 +1,149 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+
+use super::decoder::{Claims, Error};
+use super::scope;
+
+/// Private key of the development key pair in `certificates/jwt`.
+/// Tokens signed with it are accepted by a broker started with
+/// `--jwt-public-key certificates/jwt/jwt.key.pub`.
+pub const DEV_PRIVATE_KEY: &str = include_str!("../../../../certificates/jwt/jwt.key");
+
+/// Public key of the development key pair in `certificates/jwt`.
+pub const DEV_PUBLIC_KEY: &str = include_str!("../../../../certificates/jwt/jwt.key.pub");
+
+const DEFAULT_ISSUER: &str = "databroker-token";
+const AUDIENCE: &str = "kuksa.val";
+
+/// Creates signed access tokens with the claims expected by the broker.
+///
+/// Meant for tests and demos, production deployments are expected to get
+/// their tokens from an identity provider.
+#[derive(Clone)]
+pub struct Encoder {
+    encoding_key: EncodingKey,
+    issuer: String,
+}
+
+impl Encoder {
+    pub fn new(private_key: impl Into<String>) -> Result<Encoder, Error> {
+        let encoding_key =
+            EncodingKey::from_rsa_pem(private_key.into().as_bytes()).map_err(|err| {
+                Error::PrivateKeyError(format!("Error processing private key: {err}"))
+            })?;
+        Ok(Encoder {
+            encoding_key,
+            issuer: DEFAULT_ISSUER.to_owned(),
+        })
+    }
+
+    /// Encoder using the bundled development key pair.
+    pub fn dev() -> Encoder {
+        Encoder::new(DEV_PRIVATE_KEY).expect("bundled development key should be valid")
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = issuer.into();
+        self
+    }
+
+    /// Create a token for `subject` with the (whitespace separated) `scope`,
+    /// valid for `valid_for` from now.
+    pub fn mint(
+        &self,
+        subject: impl Into<String>,
+        scope: impl Into<String>,
+        valid_for: Duration,
+    ) -> Result<String, Error> {
+        let scope = scope.into();
+        scope::parse_whitespace_separated(&scope).map_err(|_| Error::ClaimsError)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let claims = Claims {
+            sub: subject.into(),
+            iss: self.issuer.clone(),
+            aud: vec![AUDIENCE.to_owned()],
+            iat: now.as_secs(),
+            exp: (now + valid_for).as_secs(),
+            scope,
+        };
+        self.encode(&claims)
+    }
+
+    pub fn encode(&self, claims: &Claims) -> Result<String, Error> {
+        encode(&Header::new(Algorithm::RS256), claims, &self.encoding_key)
+            .map_err(|err| Error::EncodeError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::authorization::jwt::Decoder;
+    use crate::permissions::Permissions;
+
+    #[test]
+    fn test_mint_and_decode() {
+        let token = Encoder::dev()
+            .mint(
+                "test",
+                "read:Vehicle.Speed provide:Vehicle.Speed",
+                Duration::from_secs(60),
+            )
+            .expect("minting should succeed");
+
+        let decoder = Decoder::new(DEV_PUBLIC_KEY).expect("Creation of decoder should succeed");
+        let claims = decoder.decode(&token).expect("decode should succeed");
+        assert_eq!(claims.sub, "test");
+        assert_eq!(claims.aud, vec!["kuksa.val"]);
+        assert_eq!(claims.scope, "read:Vehicle.Speed provide:Vehicle.Speed");
+        assert_eq!(claims.exp - claims.iat, 60);
+
+        let permissions = Permissions::try_from(claims).expect("claims should be valid");
+        assert!(permissions.can_read("Vehicle.Speed").is_ok());
+        assert!(permissions.can_read("Vehicle.Width").is_err());
+    }
+
+    #[test]
+    fn test_mint_invalid_scope() {
+        assert!(matches!(
+            Encoder::dev().mint("test", "fly:Vehicle", Duration::from_secs(60)),
+            Err(Error::ClaimsError)
+        ));
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let claims = Claims {
+            sub: "test".to_owned(),
+            iss: DEFAULT_ISSUER.to_owned(),
+            aud: vec![AUDIENCE.to_owned()],
+            iat: 1516239022,
+            exp: 1516239082,
+            scope: "read".to_owned(),
+        };
+        let token = Encoder::dev()
+            .encode(&claims)
+            .expect("encode should succeed");
+
+        let decoder = Decoder::new(DEV_PUBLIC_KEY).expect("Creation of decoder should succeed");
+        assert!(decoder.decode(token).is_err());
+    }
+}
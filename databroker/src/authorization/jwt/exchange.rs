@@ -0,0 +1,240 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Exchange of an access token for a narrower, shorter-lived one, so that
+//! e.g. a gateway app can hand reduced-privilege tokens to its plugins
+//! instead of sharing its own.
+//!
+//! The scope of the derived token has to be granted by the presented one,
+//! and the derived token expires with it at the latest. Derived tokens keep
+//! the subject of the presented token, so they count against its quotas.
+//! They are signed with a key generated at startup, i.e. they are only
+//! accepted by the broker that minted them, and not after a restart.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::permissions::{PermissionError, Permissions};
+
+use super::decoder::{scope_permissions, Claims, Error};
+
+/// Upper bound of the lifetime of a derived token.
+pub const MAX_LIFETIME: Duration = Duration::from_secs(3600);
+
+const ISSUER: &str = "databroker";
+const AUDIENCE: &str = "kuksa.val";
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, PartialEq)]
+pub enum ExchangeError {
+    InvalidScope,
+    // The presented token doesn't grant the requested scope, with the first
+    // scope not granted
+    NotGranted(String),
+    PresentedExpired,
+    EncodeError(String),
+}
+
+#[derive(Clone)]
+pub struct TokenExchange {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    validator: Validation,
+}
+
+impl TokenExchange {
+    /// Token exchange with a randomly generated signing key.
+    pub fn generate() -> Result<TokenExchange, Error> {
+        let mut key = [0; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| Error::PrivateKeyError("Failed to generate signing key".to_owned()))?;
+        Ok(TokenExchange::from_secret(&key))
+    }
+
+    pub fn from_secret(key: &[u8]) -> TokenExchange {
+        let mut validator = Validation::new(Algorithm::HS256);
+        validator.set_audience(&[AUDIENCE]);
+        validator.set_issuer(&[ISSUER]);
+        TokenExchange {
+            encoding_key: EncodingKey::from_secret(key),
+            decoding_key: DecodingKey::from_secret(key),
+            validator,
+        }
+    }
+
+    /// Mint a token for the (whitespace separated) `scope`, valid for
+    /// `valid_for` but at most [`MAX_LIFETIME`] and no longer than
+    /// `presented`. Returns the token and its expiry.
+    pub fn exchange(
+        &self,
+        presented: &Permissions,
+        scope: &str,
+        valid_for: Duration,
+    ) -> Result<(String, SystemTime), ExchangeError> {
+        if scope.split_whitespace().next().is_none() {
+            return Err(ExchangeError::InvalidScope);
+        }
+        for requested in scope.split_whitespace() {
+            let permissions = scope_permissions(requested)
+                .and_then(|permissions| permissions.build().map_err(|_| Error::ClaimsError))
+                .map_err(|_| ExchangeError::InvalidScope)?;
+            match presented.includes(&permissions) {
+                Ok(true) => {}
+                Ok(false) | Err(PermissionError::Denied) => {
+                    return Err(ExchangeError::NotGranted(requested.to_owned()))
+                }
+                Err(PermissionError::Expired) => return Err(ExchangeError::PresentedExpired),
+            }
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut exp = now + valid_for.min(MAX_LIFETIME);
+        if let Some(expires_at) = presented.expires_at() {
+            exp = exp.min(expires_at.duration_since(UNIX_EPOCH).unwrap_or_default());
+        }
+        let claims = Claims {
+            sub: presented.subject().unwrap_or_default().to_owned(),
+            iss: ISSUER.to_owned(),
+            aud: vec![AUDIENCE.to_owned()],
+            iat: now.as_secs(),
+            exp: exp.as_secs(),
+            scope: scope.to_owned(),
+        };
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+            .map_err(|err| ExchangeError::EncodeError(err.to_string()))?;
+        Ok((token, UNIX_EPOCH + Duration::from_secs(claims.exp)))
+    }
+
+    /// Whether `token` looks like one minted by a token exchange, rather
+    /// than by the identity provider.
+    pub(super) fn is_derived(token: &str) -> bool {
+        matches!(jsonwebtoken::decode_header(token), Ok(header) if header.alg == Algorithm::HS256)
+    }
+
+    pub(super) fn decode(&self, token: &str) -> Result<Claims, Error> {
+        decode::<Claims>(token, &self.decoding_key, &self.validator)
+            .map(|token| token.claims)
+            .map_err(|err| Error::DecodeError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::authorization::jwt::{Decoder, Encoder, DEV_PUBLIC_KEY};
+
+    fn presented(scope: &str) -> Permissions {
+        let token = Encoder::dev()
+            .mint("gateway", scope, Duration::from_secs(600))
+            .unwrap();
+        let claims = Decoder::new(DEV_PUBLIC_KEY).unwrap().decode(token).unwrap();
+        Permissions::try_from(claims).unwrap()
+    }
+
+    #[test]
+    fn test_exchange() {
+        let exchange = TokenExchange::generate().unwrap();
+        let decoder = Decoder::new(DEV_PUBLIC_KEY)
+            .unwrap()
+            .with_token_exchange(exchange.clone());
+        let gateway = presented("read:Vehicle actuate:Vehicle.Cabin");
+
+        let (token, expires_at) = exchange
+            .exchange(
+                &gateway,
+                "read:Vehicle.Speed actuate:Vehicle.Cabin.Light.*",
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        let claims = decoder.decode(&token).unwrap();
+        assert_eq!(claims.sub, "gateway");
+        assert_eq!(claims.exp - claims.iat, 60);
+        assert_eq!(UNIX_EPOCH + Duration::from_secs(claims.exp), expires_at);
+        let plugin = Permissions::try_from(claims).unwrap();
+        assert!(plugin.can_read("Vehicle.Speed").is_ok());
+        assert!(plugin.can_read("Vehicle.Cabin.Light.IsDomeOn").is_ok());
+        assert!(plugin.can_read("Vehicle.Width").is_err());
+        assert!(plugin
+            .can_write_actuator_target("Vehicle.Cabin.Light.IsDomeOn")
+            .is_ok());
+        assert!(plugin
+            .can_write_actuator_target("Vehicle.Cabin.Seat.Heating")
+            .is_err());
+
+        // Derived tokens can be narrowed further
+        assert!(exchange
+            .exchange(&plugin, "read:Vehicle.Speed", Duration::from_secs(60))
+            .is_ok());
+
+        // Tokens of another broker aren't accepted
+        let (token, _) = TokenExchange::generate()
+            .unwrap()
+            .exchange(&gateway, "read:Vehicle.Speed", Duration::from_secs(60))
+            .unwrap();
+        assert!(decoder.decode(token).is_err());
+    }
+
+    #[test]
+    fn test_exchange_broader_scope() {
+        let exchange = TokenExchange::generate().unwrap();
+        let gateway = presented("read:Vehicle.Cabin.* provide:Vehicle.Speed");
+        for (scope, not_granted) in [
+            ("read", "read"),
+            ("read:Vehicle.*", "read:Vehicle.*"),
+            // Includes everything below Vehicle.Cabin.Door
+            ("read:Vehicle.Cabin.Door", "read:Vehicle.Cabin.Door"),
+            (
+                "read:Vehicle.Cabin.* actuate:Vehicle.Cabin.*",
+                "actuate:Vehicle.Cabin.*",
+            ),
+            ("provide:Vehicle.*", "provide:Vehicle.*"),
+            ("admin", "admin"),
+        ] {
+            assert_eq!(
+                exchange
+                    .exchange(&gateway, scope, Duration::from_secs(60))
+                    .unwrap_err(),
+                ExchangeError::NotGranted(not_granted.to_owned()),
+                "{scope}"
+            );
+        }
+        for scope in ["", "fly:Vehicle", "admin:Vehicle.Speed"] {
+            assert_eq!(
+                exchange
+                    .exchange(&gateway, scope, Duration::from_secs(60))
+                    .unwrap_err(),
+                ExchangeError::InvalidScope,
+                "{scope}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_exchange_expiry() {
+        let exchange = TokenExchange::generate().unwrap();
+        // Expires in 600 seconds
+        let gateway = presented("read");
+        let now = SystemTime::now();
+        let (_, expires_at) = exchange
+            .exchange(&gateway, "read", Duration::from_secs(3 * 3600))
+            .unwrap();
+        assert!(expires_at <= now + Duration::from_secs(601));
+        assert!(expires_at >= now + Duration::from_secs(599));
+    }
+}
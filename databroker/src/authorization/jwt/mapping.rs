@@ -0,0 +1,200 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Where the scopes are found in an access token.
+//!
+//! By default they are read from the whitespace separated `scope` claim.
+//! Identity providers put them elsewhere, e.g. in `scp`, `permissions` or
+//! nested in `realm_access.roles`, which can be configured in JSON:
+//!
+//! ```json
+//! {
+//!   "scopes": [
+//!     { "claim": "scp" },
+//!     { "claim": "/realm_access/roles", "prefix": "kuksa:" }
+//!   ]
+//! }
+//! ```
+//!
+//! A claim starting with `/` is a JSON pointer into the token, other claims
+//! are top level claim names, which may contain dots, e.g.
+//! `https://example.com/permissions`. Claims may be whitespace separated
+//! strings or (nested) arrays of them. With a `prefix`, only the values
+//! starting with it are scopes, the others, e.g. roles unrelated to the
+//! broker, are ignored.
+
+use std::io::Read;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::Error;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimMapping {
+    pub scopes: Vec<ScopeClaim>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScopeClaim {
+    pub claim: String,
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl Default for ClaimMapping {
+    fn default() -> Self {
+        ClaimMapping {
+            scopes: vec![ScopeClaim {
+                claim: "scope".to_owned(),
+                prefix: None,
+            }],
+        }
+    }
+}
+
+impl ClaimMapping {
+    pub fn from_reader<R: Read>(reader: R) -> Result<ClaimMapping, serde_json::Error> {
+        let mapping: ClaimMapping = serde_json::from_reader(reader)?;
+        if mapping.scopes.is_empty() {
+            return Err(serde::de::Error::custom("no scope claims configured"));
+        }
+        Ok(mapping)
+    }
+
+    /// The whitespace separated scopes in `claims`, `None` if none of the
+    /// configured claims is present.
+    pub(super) fn scope(&self, claims: &Map<String, Value>) -> Result<Option<String>, Error> {
+        let mut found = false;
+        let mut scopes = Vec::new();
+        for source in &self.scopes {
+            let Some(value) = lookup(claims, &source.claim) else {
+                continue;
+            };
+            found = true;
+            let mut values = Vec::new();
+            collect(value, &mut values)?;
+            scopes.extend(values.into_iter().filter_map(|scope| match &source.prefix {
+                Some(prefix) => scope.strip_prefix(prefix.as_str()),
+                None => Some(scope),
+            }));
+        }
+        Ok(found.then(|| scopes.join(" ")))
+    }
+}
+
+fn lookup<'a>(claims: &'a Map<String, Value>, claim: &str) -> Option<&'a Value> {
+    let Some(pointer) = claim.strip_prefix('/') else {
+        return claims.get(claim);
+    };
+    let (first, rest) = match pointer.find('/') {
+        Some(index) => pointer.split_at(index),
+        None => (pointer, ""),
+    };
+    let first = first.replace("~1", "/").replace("~0", "~");
+    let value = claims.get(&first)?;
+    if rest.is_empty() {
+        Some(value)
+    } else {
+        value.pointer(rest)
+    }
+}
+
+fn collect<'a>(value: &'a Value, scopes: &mut Vec<&'a str>) -> Result<(), Error> {
+    match value {
+        Value::String(scope) => scopes.extend(scope.split_whitespace()),
+        Value::Array(values) => {
+            for value in values {
+                collect(value, scopes)?;
+            }
+        }
+        _ => return Err(Error::ClaimsError),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(json: Value) -> Map<String, Value> {
+        match json {
+            Value::Object(claims) => claims,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_default_mapping() {
+        let mapping = ClaimMapping::default();
+        let scope = mapping
+            .scope(&claims(
+                serde_json::json!({"scope": "read:Vehicle.Speed provide"}),
+            ))
+            .unwrap();
+        assert_eq!(scope.as_deref(), Some("read:Vehicle.Speed provide"));
+        assert_eq!(
+            mapping
+                .scope(&claims(serde_json::json!({"scp": "read"})))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_configured_mapping() {
+        let mapping = ClaimMapping::from_reader(
+            r#"{
+                "scopes": [
+                    { "claim": "scp" },
+                    { "claim": "https://example.com/permissions" },
+                    { "claim": "/realm_access/roles", "prefix": "kuksa:" }
+                ]
+            }"#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let scope = mapping
+            .scope(&claims(serde_json::json!({
+                "scp": ["read:Vehicle.Speed", ["actuate:Vehicle.Body"]],
+                "https://example.com/permissions": "provide:Vehicle.Cabin",
+                "realm_access": {"roles": ["offline_access", "kuksa:create"]}
+            })))
+            .unwrap();
+        assert_eq!(
+            scope.as_deref(),
+            Some("read:Vehicle.Speed actuate:Vehicle.Body provide:Vehicle.Cabin create")
+        );
+
+        // Only the roles, none of them for the broker
+        let scope = mapping
+            .scope(&claims(
+                serde_json::json!({"realm_access": {"roles": ["offline_access"]}}),
+            ))
+            .unwrap();
+        assert_eq!(scope.as_deref(), Some(""));
+
+        assert!(mapping
+            .scope(&claims(serde_json::json!({"scp": 1})))
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_mapping() {
+        assert!(ClaimMapping::from_reader(r#"{"scopes": []}"#.as_bytes()).is_err());
+        assert!(ClaimMapping::from_reader(r#"{"scopes": [{"name": "scp"}]}"#.as_bytes()).is_err());
+    }
+}
@@ -12,6 +12,12 @@
 ********************************************************************************/
 
 mod decoder;
+mod encoder;
+mod exchange;
+mod mapping;
 mod scope;
 
 pub use decoder::{Claims, Decoder, Error};
+pub use encoder::{Encoder, DEV_PRIVATE_KEY, DEV_PUBLIC_KEY};
+pub use exchange::{ExchangeError, TokenExchange, MAX_LIFETIME};
+pub use mapping::{ClaimMapping, ScopeClaim};
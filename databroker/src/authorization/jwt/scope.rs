@@ -21,8 +21,13 @@ pub struct Scope {
 pub enum Action {
     Read,
     Actuate,
+    ActuateSafety,
     Provide,
     Create,
+    Admin,
+    // Priority classes of change subscriptions
+    SubscribeControl,
+    SubscribeSafety,
 }
 
 #[derive(Debug)]
@@ -67,8 +72,12 @@ pub fn parse_whitespace_separated(scope: &str) -> Result<Vec<Scope>, Error> {
                     Some(action) => match action.as_str() {
                         "read" => Action::Read,
                         "actuate" => Action::Actuate,
+                        "actuate_safety" => Action::ActuateSafety,
                         "provide" => Action::Provide,
                         "create" => Action::Create,
+                        "admin" => Action::Admin,
+                        "subscribe_control" => Action::SubscribeControl,
+                        "subscribe_safety" => Action::SubscribeSafety,
                         _ => {
                             // Unknown action
                             return Err(Error::ParseError);
@@ -184,6 +193,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_scope_actuate_safety() {
+        match parse_whitespace_separated("actuate_safety:Vehicle.ADAS.*") {
+            Ok(scopes) => {
+                assert_eq!(scopes.len(), 1);
+                assert!(matches!(scopes[0].action, Action::ActuateSafety));
+                assert_eq!(scopes[0].path.as_deref(), Some("Vehicle.ADAS.*"));
+            }
+            Err(_) => todo!(),
+        }
+    }
+
     #[test]
     fn test_scope_actuate_sep_no_path() {
         match parse_whitespace_separated("actuate:") {
@@ -244,6 +265,31 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_scope_admin() {
+        match parse_whitespace_separated("admin") {
+            Ok(scopes) => {
+                assert_eq!(scopes.len(), 1);
+                assert!(matches!(scopes[0].action, Action::Admin));
+                assert_eq!(scopes[0].path, None);
+            }
+            Err(_) => todo!(),
+        }
+    }
+
+    #[test]
+    fn test_scope_subscribe_priority() {
+        match parse_whitespace_separated("subscribe_control subscribe_safety") {
+            Ok(scopes) => {
+                assert_eq!(scopes.len(), 2);
+                assert!(matches!(scopes[0].action, Action::SubscribeControl));
+                assert!(matches!(scopes[1].action, Action::SubscribeSafety));
+                assert_eq!(scopes[1].path, None);
+            }
+            Err(_) => todo!(),
+        }
+    }
+
     #[test]
     fn test_scope_create_no_path() {
         match parse_whitespace_separated("create") {
@@ -13,6 +13,8 @@
 
 use thiserror::Error;
 
+use crate::permissions::{self, Permissions};
+
 pub mod jwt;
 
 #[derive(Clone)]
@@ -28,10 +30,57 @@ pub enum Error {
     InvalidPublicKey,
 }
 
+#[derive(Error, Debug, PartialEq)]
+pub enum TokenError {
+    #[error("no access token provided")]
+    Missing,
+    #[error("invalid token: {0}")]
+    Invalid(String),
+}
+
+/// The permissions granted by `token`, or all of them if authorization is
+/// disabled.
+pub fn permissions_from_token(
+    authorization: &Authorization,
+    token: Option<&str>,
+) -> Result<Permissions, TokenError> {
+    match authorization {
+        Authorization::Disabled => Ok(permissions::ALLOW_ALL.clone()),
+        Authorization::Enabled { token_decoder } => {
+            let token = token.ok_or(TokenError::Missing)?;
+            token_decoder
+                .decode(token)
+                .map_err(|err| err.to_string())
+                .and_then(|claims| Permissions::try_from(claims).map_err(|err| err.to_string()))
+                .map_err(TokenError::Invalid)
+        }
+    }
+}
+
 impl Authorization {
     pub fn new(public_key: String) -> Result<Authorization, Error> {
-        Ok(Authorization::Enabled {
-            token_decoder: jwt::Decoder::new(public_key).map_err(|_| Error::InvalidPublicKey)?,
-        })
+        Self::with_claim_mapping(public_key, jwt::ClaimMapping::default())
+    }
+
+    /// Authorize with tokens whose scopes are found as described by
+    /// `claim_mapping`.
+    pub fn with_claim_mapping(
+        public_key: String,
+        claim_mapping: jwt::ClaimMapping,
+    ) -> Result<Authorization, Error> {
+        let token_decoder = jwt::Decoder::new(public_key)
+            .map_err(|_| Error::InvalidPublicKey)?
+            .with_claim_mapping(claim_mapping);
+        Ok(Authorization::Enabled { token_decoder })
+    }
+
+    /// Accept the tokens minted by `token_exchange` as well.
+    pub fn with_token_exchange(self, token_exchange: jwt::TokenExchange) -> Authorization {
+        match self {
+            Authorization::Disabled => Authorization::Disabled,
+            Authorization::Enabled { token_decoder } => Authorization::Enabled {
+                token_decoder: token_decoder.with_token_exchange(token_exchange),
+            },
+        }
     }
 }
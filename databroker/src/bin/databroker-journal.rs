@@ -0,0 +1,150 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Export the change journal written with `databroker --journal-dir` as
+//! CSV or JSON.
+
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use clap::{Arg, ArgAction, Command};
+
+use databroker::encryption::{Encryption, KeyFile};
+use databroker::glob::Matcher;
+use databroker::journal::{self, Change};
+use databroker::recording;
+use databroker::types::DataValue;
+
+fn since_epoch(ts: SystemTime) -> Duration {
+    ts.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Quote a CSV field if needed (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn write_csv(out: &mut impl Write, change: Change) -> io::Result<()> {
+    let value = match change.value {
+        DataValue::NotAvailable => String::new(),
+        value => value.to_string(),
+    };
+    let ts = since_epoch(change.ts);
+    writeln!(
+        out,
+        "{}.{:06},{},{}",
+        ts.as_secs(),
+        ts.subsec_micros(),
+        csv_field(&change.path),
+        csv_field(&value)
+    )
+}
+
+fn write_json(out: &mut impl Write, change: Change) -> io::Result<()> {
+    let line = serde_json::json!({
+        "ts_us": since_epoch(change.ts).as_micros() as u64,
+        "path": change.path,
+        "value": serde_json::Value::from(change.value),
+    });
+    writeln!(out, "{line}")
+}
+
+fn files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path.is_dir() {
+        journal::segments(path)
+    } else {
+        Ok(vec![path.to_owned()])
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Command::new("databroker-journal")
+        .version(option_env!("CARGO_PKG_VERSION").unwrap_or_default())
+        .about("Export the databroker change journal as CSV or JSON")
+        .arg(
+            Arg::new("journal")
+                .help("Journal directory or a single segment of it")
+                .value_name("PATH")
+                .required(true),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("Output format; json writes one object per line")
+                .value_parser(["csv", "json"])
+                .default_value("csv"),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .help("Only include paths matching the (comma-separated) list of patterns")
+                .action(ArgAction::Set)
+                .value_delimiter(',')
+                .value_name("PATTERN"),
+        )
+        .arg(
+            Arg::new("encryption-key-file")
+                .long("encryption-key-file")
+                .help("Key of an encrypted journal (64 hex digits)")
+                .value_name("FILE"),
+        )
+        .get_matches();
+
+    let filter = match args.get_many::<String>("path") {
+        Some(patterns) => patterns
+            .map(|pattern| Matcher::new(pattern).map_err(|_| format!("Invalid path '{pattern}'")))
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+    let encryption = match args.get_one::<String>("encryption-key-file") {
+        Some(key_file) => Some(Encryption::from_provider(&KeyFile(key_file.into()))?),
+        None => None,
+    };
+    let csv = args.get_one::<String>("format").map(String::as_str) == Some("csv");
+    let path = args
+        .get_one::<String>("journal")
+        .expect("journal is required");
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if csv {
+        writeln!(out, "ts,path,value")?;
+    }
+    for file in files(Path::new(path))? {
+        for change in journal::open_segment(&file, encryption.as_ref())? {
+            // The newest segment may end with a change cut off by a crash
+            let change = match change {
+                Ok(change) => change,
+                Err(err) => {
+                    eprintln!("Ignoring the rest of {}: {}", file.display(), err);
+                    break;
+                }
+            };
+            if !recording::matches(&filter, &change.path) {
+                continue;
+            }
+            if csv {
+                write_csv(&mut out, change)?;
+            } else {
+                write_json(&mut out, change)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
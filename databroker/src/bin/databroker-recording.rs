@@ -0,0 +1,199 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Inspect and trim recordings created with `databroker --record`.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::time::Duration;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use databroker::encryption::{self, Encryption, KeyFile};
+use databroker::glob::Matcher;
+use databroker::recording::{self, RecordingReader};
+
+fn encryption(args: &ArgMatches) -> Result<Option<Encryption>, Box<dyn std::error::Error>> {
+    match args.get_one::<String>("encryption-key-file") {
+        Some(key_file) => Ok(Some(Encryption::from_provider(&KeyFile(key_file.into()))?)),
+        None => Ok(None),
+    }
+}
+
+fn open(
+    args: &ArgMatches,
+) -> Result<RecordingReader<Box<dyn Read + Send>>, Box<dyn std::error::Error>> {
+    let file = args.get_one::<String>("file").expect("file is required");
+    let reader = encryption::reader(
+        BufReader::new(File::open(file)?),
+        encryption(args)?.as_ref(),
+    )?;
+    Ok(RecordingReader::new(reader)?)
+}
+
+fn filter(args: &ArgMatches) -> Result<Vec<Matcher>, Box<dyn std::error::Error>> {
+    match args.get_many::<String>("path") {
+        Some(patterns) => Ok(patterns
+            .map(|pattern| Matcher::new(pattern).map_err(|_| format!("Invalid path '{pattern}'")))
+            .collect::<Result<_, _>>()?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn info(args: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = open(args)?;
+    let start_time = reader.start_time();
+    let mut paths: BTreeMap<String, usize> = BTreeMap::new();
+    let mut duration = Duration::ZERO;
+    let mut count = 0;
+    for record in reader {
+        let record = record?;
+        *paths.entry(record.path).or_default() += 1;
+        duration = record.offset;
+        count += 1;
+    }
+
+    let start = start_time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    println!(
+        "Started:  {}.{:06} (unix time)",
+        start.as_secs(),
+        start.subsec_micros()
+    );
+    println!("Duration: {:.3} s", duration.as_secs_f64());
+    println!("Updates:  {}", count);
+    println!("Paths:    {}", paths.len());
+    for (path, count) in paths {
+        println!("  {path}: {count}");
+    }
+    Ok(())
+}
+
+fn dump(args: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let filter = filter(args)?;
+    for record in open(args)? {
+        let record = record?;
+        if recording::matches(&filter, &record.path) {
+            println!(
+                "{:>12.6} {} {}",
+                record.offset.as_secs_f64(),
+                record.path,
+                record.value
+            );
+        }
+    }
+    Ok(())
+}
+
+fn trim(args: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let file = args.get_one::<String>("file").expect("file is required");
+    let output = args
+        .get_one::<String>("output")
+        .expect("output is required");
+    if file == output {
+        return Err("Output must differ from the input recording".into());
+    }
+    let from = Duration::from_secs_f64(*args.get_one::<f64>("from").unwrap_or(&0.0));
+    let to = args
+        .get_one::<f64>("to")
+        .map(|to| Duration::from_secs_f64(*to));
+
+    // The trimmed recording is encrypted like the input
+    let encryption = encryption(args)?;
+    let count = recording::trim(
+        encryption::reader(BufReader::new(File::open(file)?), encryption.as_ref())?,
+        BufWriter::new(encryption::writer(
+            File::create(output)?,
+            encryption.as_ref(),
+        )?),
+        from,
+        to,
+        &filter(args)?,
+    )?;
+    println!("Wrote {count} updates to {output}");
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let file_arg = Arg::new("file")
+        .help("Recording file")
+        .value_name("FILE")
+        .required(true);
+    let path_arg = Arg::new("path")
+        .long("path")
+        .help("Only include paths matching the (comma-separated) list of patterns")
+        .action(ArgAction::Set)
+        .value_delimiter(',')
+        .value_name("PATTERN");
+
+    let args = Command::new("databroker-recording")
+        .version(option_env!("CARGO_PKG_VERSION").unwrap_or_default())
+        .about("Inspect and trim databroker recordings")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("encryption-key-file")
+                .long("encryption-key-file")
+                .help("Key of encrypted recordings (64 hex digits)")
+                .value_name("FILE")
+                .global(true),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Show a summary of a recording")
+                .arg(file_arg.clone()),
+        )
+        .subcommand(
+            Command::new("dump")
+                .about("Print the updates of a recording")
+                .arg(file_arg.clone())
+                .arg(path_arg.clone()),
+        )
+        .subcommand(
+            Command::new("trim")
+                .about("Write a part of a recording to a new file")
+                .arg(file_arg)
+                .arg(path_arg)
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("File to write the trimmed recording to")
+                        .value_name("FILE")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .help("Drop updates before SECONDS into the recording")
+                        .value_name("SECONDS")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .help("Drop updates after SECONDS into the recording")
+                        .value_name("SECONDS")
+                        .value_parser(clap::value_parser!(f64)),
+                ),
+        )
+        .get_matches();
+
+    match args.subcommand() {
+        Some(("info", args)) => info(args),
+        Some(("dump", args)) => dump(args),
+        Some(("trim", args)) => trim(args),
+        _ => unreachable!("subcommand is required"),
+    }
+}
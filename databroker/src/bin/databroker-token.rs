@@ -0,0 +1,93 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Create signed access tokens for tests and demos.
+
+use std::time::Duration;
+
+use clap::{Arg, ArgAction, Command};
+
+use databroker::authorization::jwt::Encoder;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Command::new("databroker-token")
+        .version(option_env!("CARGO_PKG_VERSION").unwrap_or_default())
+        .about("Create signed access tokens for tests and demos. Uses the development key pair in certificates/jwt unless --private-key is given")
+        .arg(
+            Arg::new("scope")
+                .long("scope")
+                .help("Scopes granted by the token, e.g. \"read provide:Vehicle.Speed\"")
+                .action(ArgAction::Set)
+                .value_name("SCOPE")
+                .required(true),
+        )
+        .arg(
+            Arg::new("subject")
+                .long("subject")
+                .help("Subject of the token")
+                .action(ArgAction::Set)
+                .value_name("SUBJECT")
+                .default_value("local dev"),
+        )
+        .arg(
+            Arg::new("expires-in")
+                .long("expires-in")
+                .help("Validity of the token in seconds")
+                .action(ArgAction::Set)
+                .value_name("SECONDS")
+                .default_value("86400")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("issuer")
+                .long("issuer")
+                .help("Issuer of the token")
+                .action(ArgAction::Set)
+                .value_name("ISSUER"),
+        )
+        .arg(
+            Arg::new("private-key")
+                .long("private-key")
+                .help("RSA private key (.pem) used to sign the token")
+                .action(ArgAction::Set)
+                .value_name("FILE"),
+        )
+        .get_matches();
+
+    let mut encoder = match args.get_one::<String>("private-key") {
+        Some(filename) => Encoder::new(std::fs::read_to_string(filename)?)?,
+        None => Encoder::dev(),
+    };
+    if let Some(issuer) = args.get_one::<String>("issuer") {
+        encoder = encoder.with_issuer(issuer);
+    }
+
+    let scope = args.get_one::<String>("scope").expect("scope is required");
+    let subject = args
+        .get_one::<String>("subject")
+        .expect("subject should have a default");
+    let expires_in = *args
+        .get_one::<u64>("expires-in")
+        .expect("expires-in should have a default");
+
+    let token = encoder
+        .mint(subject, scope, Duration::from_secs(expires_in))
+        .map_err(|err| match err {
+            databroker::authorization::jwt::Error::ClaimsError => {
+                format!("Invalid scope '{scope}'").into()
+            }
+            err => Box::<dyn std::error::Error>::from(err),
+        })?;
+    println!("{token}");
+    Ok(())
+}
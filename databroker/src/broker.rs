@@ -15,26 +15,46 @@ use crate::permissions::{PermissionError, Permissions};
 pub use crate::types;
 
 use crate::query;
-pub use crate::types::{ChangeType, DataType, DataValue, EntryType};
+pub use crate::types::{ChangeType, DataType, DataValue, EntryType, SafetyLevel};
 
 use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::{Stream, StreamExt};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
-use std::sync::atomic::{AtomicI32, Ordering};
-use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use crate::query::{CompiledQuery, ExecutionInput};
 use crate::types::ExecutionInputImplData;
 use tracing::{debug, info, warn};
 
+use crate::accounting::{Accounting, ClientCounters, ClientUsage};
+use crate::actuations::{ActuationInfo, ActuationStatus, Actuations};
+use crate::authorization::jwt::TokenExchange;
+use crate::call_stats::CallStats;
+#[cfg(feature = "chaos")]
+use crate::chaos::{Chaos, ChaosConfig};
+use crate::clock::{self, Clock};
+use crate::enrollment::ProviderEnrollment;
+use crate::events::{Event, EventBus};
 use crate::glob;
+use crate::history::{History, RetentionPolicies};
+use crate::latency::{LatencyStats, Stage, Stopwatch};
+use crate::log_filter::LogFilter;
+use crate::policy::Policies;
+use crate::sessions::{SessionInfo, Sessions};
+use crate::signal_groups::SignalGroups;
+use crate::validation::Validators;
 
-const MAX_SUBSCRIBE_BUFFER_SIZE: usize = 1000;
+pub const MAX_SUBSCRIBE_BUFFER_SIZE: usize = 1000;
+
+// Seconds between the housekeeping passes compacting the subscriptions
+const COMPACTION_INTERVAL_SECS: u64 = 60;
 
 #[derive(Debug)]
 pub enum ActuationError {
@@ -47,18 +67,28 @@ pub enum ActuationError {
     ProviderNotAvailable,
     ProviderAlreadyExists,
     TransmissionFailure,
+    // Denied by a policy rule while its condition holds
+    PolicyViolation,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UpdateError {
     NotFound,
     WrongType,
     OutOfBoundsAllowed,
     OutOfBoundsMinMax,
     OutOfBoundsType,
+    // An array element update addressed an element the array doesn't have
+    OutOfBoundsIndex,
     UnsupportedType,
     PermissionDenied,
     PermissionExpired,
+    // The condition of a conditional update didn't hold
+    Conflict,
+    // Rejected by a plausibility validator, with its reason
+    Implausible(String),
+    // Denied by a policy rule, with its condition
+    PolicyViolation(String),
 }
 
 #[derive(Debug, Clone)]
@@ -73,6 +103,7 @@ pub enum RegistrationError {
     ValidationError,
     PermissionDenied,
     PermissionExpired,
+    QuotaExceeded,
 }
 
 #[derive(Debug, Clone)]
@@ -89,6 +120,12 @@ pub struct Metadata {
     pub max: Option<types::DataValue>,
     pub allowed: Option<types::DataValue>,
     pub unit: Option<String>,
+    pub safety_level: Option<SafetyLevel>,
+    // Time the provider has to confirm an actuator target, overrides the
+    // broker-wide default
+    pub target_ttl: Option<Duration>,
+    // Number of decimals float values are rounded to
+    pub precision: Option<u32>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -111,25 +148,50 @@ pub enum Field {
     Datapoint,
     ActuatorTarget,
     MetadataUnit,
+    // Any of description, min, max, allowed and unit
+    Metadata,
 }
 
 #[derive(Default)]
 pub struct Database {
     next_id: AtomicI32,
     path_to_id: HashMap<String, i32>,
+    // Ids to (re)use when the paths get registered
+    reserved_ids: HashMap<String, i32>,
+    // Lowercase path -> id, for the first of several paths only differing
+    // in case (can only happen with case sensitive paths)
+    folded_path_to_id: HashMap<String, i32>,
+    case_insensitive_paths: bool,
+    max_entries: Option<usize>,
+    safety_gate: Option<SafetyGate>,
+    validators: Validators,
+    policies: Policies,
+    target_ttl: Option<Duration>,
+    // Clock elapsed time by which the provider needs to confirm the
+    // actuator target, per entry
+    target_deadlines: HashMap<i32, Duration>,
+    history: History,
+    // Datapoint updates per entry, see [`DataBroker::get_update_stats`]
+    update_stats: HashMap<i32, UpdateStats>,
     entries: HashMap<i32, Entry>,
 }
 
 #[derive(Default)]
 pub struct Subscriptions {
     actuation_subscriptions: Vec<ActuationSubscription>,
+    // Handle the actuators no connected provider claimed, see
+    // [`crate::simulator`]
+    simulated_actuations: Vec<ActuationSubscription>,
     query_subscriptions: Vec<QuerySubscription>,
     change_subscriptions: Vec<ChangeSubscription>,
+    // Total number of subscriptions removed by cleanup
+    removed: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct QueryResponse {
     pub fields: Vec<QueryField>,
+    pub reason: UpdateReason,
 }
 
 #[derive(Debug, Clone)]
@@ -148,11 +210,113 @@ pub struct ChangeNotification {
 #[derive(Debug, Default, Clone)]
 pub struct EntryUpdates {
     pub updates: Vec<ChangeNotification>,
+    /// What caused the notification, without duplicates
+    pub reasons: Vec<UpdateReason>,
+    /// Values were held back by a bandwidth budget since the previous
+    /// notification, i.e. intermediate values were skipped
+    pub shaped: bool,
+    /// When the notification was queued, if latency stats are enabled
+    pub trace: Option<crate::latency::Trace>,
+}
+
+/// Whether a new subscription starts with a notification holding the
+/// current state of everything subscribed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitialSnapshot {
+    /// Send the current state, then the changes
+    #[default]
+    Send,
+    /// Only send changes
+    Skip,
+}
+
+/// Limits how often the values of the entries of a change subscription
+/// are sent. Each entry is filtered on its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SubscriptionFilter {
+    /// Minimum time between two values sent for an entry. Values changing
+    /// faster are held back and the latest one is sent once the interval
+    /// has passed, with the next notification or housekeeping pass.
+    pub min_interval: Option<Duration>,
+    /// Minimum difference to the last value sent for an entry. Smaller
+    /// changes are dropped. Only applies to numeric values.
+    pub min_change: Option<f64>,
+    /// End the subscription after this long.
+    pub duration: Option<Duration>,
+    /// Maximum size of the notifications sent per second. Values over it
+    /// are held back and the latest one is sent once the budget allows.
+    pub max_bytes_per_sec: Option<u32>,
+    /// Start with the values kept in history after this time, e.g. the
+    /// timestamp of the last value received before a reconnect. Entries
+    /// without history don't get any.
+    pub backfill_since: Option<SystemTime>,
+    /// Requested priority class, capped by the permissions of the
+    /// subscriber.
+    pub priority: SubscriptionPriority,
+    /// Send the values of all entries whenever any of them changes, all
+    /// read from the same state, e.g. for a signal group. Values aren't
+    /// filtered or held back by bandwidth limits then.
+    pub snapshots: bool,
+}
+
+/// Priority class of a change subscription. Under load, higher classes are
+/// notified first and held back later by the dispatch budget
+/// ([`Quotas::max_dispatch_bytes_per_sec`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum SubscriptionPriority {
+    /// E.g. cloud uploads and dashboards; shed first
+    #[default]
+    Telemetry,
+    /// E.g. control loops of applications
+    Control,
+    /// Never held back by the dispatch budget
+    Safety,
+}
+
+impl SubscriptionPriority {
+    /// Share of the dispatch budget kept for higher classes, None if the
+    /// class is never held back.
+    fn reserved_share(&self) -> Option<f64> {
+        match self {
+            SubscriptionPriority::Telemetry => Some(0.5),
+            SubscriptionPriority::Control => Some(0.0),
+            SubscriptionPriority::Safety => None,
+        }
+    }
+}
+
+impl SubscriptionFilter {
+    fn filters_values(&self) -> bool {
+        self.min_interval.is_some() || self.min_change.is_some()
+    }
+}
+
+/// Why subscribers are notified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum UpdateReason {
+    /// The current state, sent right after subscribing
+    Initial,
+    /// A past value from history, sent right after subscribing
+    Backfill,
+    ValueChanged,
+    TargetChanged,
+    MetadataChanged,
+}
+
+impl UpdateReason {
+    fn from_field(field: &Field) -> Self {
+        match field {
+            Field::Datapoint => UpdateReason::ValueChanged,
+            Field::ActuatorTarget => UpdateReason::TargetChanged,
+            Field::MetadataUnit | Field::Metadata => UpdateReason::MetadataChanged,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum QueryError {
     CompilationError(String),
+    QuotaExceeded,
     InternalError,
 }
 
@@ -161,9 +325,402 @@ pub enum SubscriptionError {
     NotFound,
     InvalidInput,
     InvalidBufferSize,
+    QuotaExceeded,
     InternalError,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum ProviderStreamError {
+    QuotaExceeded,
+    Throttled,
+}
+
+/// Limits protecting the broker from misbehaving clients. Clients are
+/// identified by the subject of their access token, all clients without
+/// one (e.g. with authorization disabled) count as one client.
+#[derive(Debug, Clone, Default)]
+pub struct Quotas {
+    pub max_entries: Option<usize>,
+    pub max_subscriptions_per_client: Option<usize>,
+    pub max_provider_streams_per_client: Option<usize>,
+    /// Maximum number of values a single provider stream may update per
+    /// second, averaged over bursts
+    pub max_provider_stream_rate: Option<u32>,
+    /// Maximum size of the notifications sent per second to all
+    /// subscriptions of a client together. Values over it are held back
+    /// and the latest one is sent once the budget allows.
+    pub max_subscriber_bytes_per_sec: Option<u32>,
+    /// Maximum size of the notifications sent per second to all
+    /// subscriptions together. Telemetry class subscriptions are held back
+    /// once half of it is used, control class ones once all of it is, see
+    /// [`SubscriptionPriority`].
+    pub max_dispatch_bytes_per_sec: Option<u32>,
+}
+
+/// Server side limits on how long gRPC operations may take, so that hung
+/// calls and abandoned streams get cleaned up.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deadlines {
+    /// Maximum time to handle a call, until the response (stream) starts
+    pub request_timeout: Option<Duration>,
+    /// Maximum time a stream stays open
+    pub max_stream_lifetime: Option<Duration>,
+    /// Maximum time without messages sent or received on a stream
+    pub stream_idle_timeout: Option<Duration>,
+}
+
+/// What happens to actuation requests for entries above the safety level
+/// of a [`SafetyGate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafetyGateMode {
+    /// Only clients with the `actuate_safety` scope may actuate them
+    RequireScope,
+    /// Nobody may actuate them
+    Reject,
+}
+
+/// Restricts the actuation of safety relevant entries, on top of the
+/// regular permissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafetyGate {
+    /// Entries up to this level are actuated as usual
+    pub max_level: SafetyLevel,
+    pub mode: SafetyGateMode,
+}
+
+/// Current usage of the resources limited by [`Quotas`] and related
+/// counters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    pub entries: usize,
+    pub subscriptions: usize,
+    pub provider_streams: usize,
+    /// Requests rejected because a quota was exceeded
+    pub quota_rejections: u64,
+    /// Subscriptions removed since startup because the subscriber or
+    /// provider was gone or its permissions expired
+    pub removed_subscriptions: u64,
+    /// Values updated through provider streams since startup
+    pub ingress_updates: u64,
+    /// Size of the messages received on provider streams since startup
+    pub ingress_bytes: u64,
+    /// Values rejected because a provider stream exceeded its rate
+    pub throttled_updates: u64,
+    /// Values of provider streams rejected by the broker, e.g. as out of
+    /// bounds
+    pub rejected_updates: u64,
+    /// Values dropped since startup because they were the same as the
+    /// current value of their entry
+    pub suppressed_updates: u64,
+    /// Calls to deprecated APIs since startup
+    pub deprecated_api_calls: u64,
+    /// Notifications missed since startup by subscribers too slow to keep
+    /// up
+    pub lagged_notifications: u64,
+    /// Calls rejected since startup for a missing, invalid or expired
+    /// access token
+    pub authorization_failures: u64,
+}
+
+/// How often a client called an API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiUsage {
+    pub api: String,
+    /// The subject of the client's access token
+    pub client: Option<String>,
+    pub deprecated: bool,
+    pub calls: u64,
+}
+
+/// The state of the broker, for post-mortem analysis of stuck systems.
+#[derive(Debug, Clone)]
+pub struct BrokerState {
+    /// Ordered by path
+    pub entries: Vec<EntryState>,
+    pub subscriptions: Vec<SubscriptionState>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryState {
+    pub metadata: Metadata,
+    pub datapoint: Datapoint,
+    pub actuator_target: Option<Datapoint>,
+    /// The providers the actuation of the entry is forwarded to, in the
+    /// order they are tried
+    pub providers: Vec<ProviderState>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderState {
+    /// The subject of the provider's access token
+    pub client: Option<String>,
+    /// The session of the provider, see [`crate::sessions`]
+    pub session: Option<u64>,
+    pub available: bool,
+    /// The actuation is simulated, see [`crate::simulator`]
+    pub simulated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    Change,
+    Query,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionState {
+    pub kind: SubscriptionKind,
+    /// The subject of the subscriber's access token
+    pub client: Option<String>,
+    /// The session of the subscriber, see [`crate::sessions`]
+    pub session: Option<u64>,
+    /// The paths subscribed to, ordered
+    pub paths: Vec<String>,
+    /// Whether the subscriber is still there. Subscriptions of subscribers
+    /// that went away are removed shortly after.
+    pub active: bool,
+}
+
+/// What the broker supports, for clients to find out without trial calls.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capabilities {
+    /// The APIs the broker is served with, e.g. `kuksa.val.v2`
+    pub apis: Vec<String>,
+    /// Whether paths may contain wildcards
+    pub wildcards: bool,
+    /// Whether past values can be queried
+    pub history: bool,
+    /// Version of the loaded VSS tree, from `Vehicle.VersionVSS`
+    pub vss_version: Option<String>,
+}
+
+/// Ingress statistics of a provider, i.e. all provider streams of a client.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderStats {
+    pub updates: u64,
+    pub bytes: u64,
+    pub throttled: u64,
+    /// Values rejected by the broker, e.g. as out of bounds. Counted as
+    /// updates as well.
+    pub rejected: u64,
+    /// Values updated within the last full second
+    pub updates_per_second: u64,
+}
+
+/// A value of a provider the broker rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedUpdate {
+    pub id: i32,
+    /// None if there is no such entry or the provider may not read it
+    pub path: Option<String>,
+    pub datapoint: Datapoint,
+    pub error: UpdateError,
+}
+
+#[derive(Debug, Default)]
+struct ProviderIngress {
+    stats: ProviderStats,
+    window_start: Duration,
+    window_updates: u64,
+}
+
+impl ProviderIngress {
+    fn roll_window(&mut self, now: Duration) {
+        let elapsed = now.saturating_sub(self.window_start);
+        if elapsed >= Duration::from_secs(1) {
+            self.stats.updates_per_second = if elapsed < Duration::from_secs(2) {
+                self.window_updates
+            } else {
+                0
+            };
+            self.window_start = now;
+            self.window_updates = 0;
+        }
+    }
+}
+
+// Window the update rate of an entry is measured over, long enough for
+// signals updated every few seconds
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Update statistics of a signal, or of all signals of a branch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalStats {
+    pub path: String,
+    /// Number of signals the statistics cover
+    pub signals: usize,
+    /// Number of datapoint updates, including those not changing the value
+    pub updates: u64,
+    /// Updates per second, measured over the last full window of 10 seconds
+    pub updates_per_second: f64,
+    /// Time since the latest update, `None` if never updated
+    pub last_update_age: Option<Duration>,
+    /// Number of subscriptions including any of the signals
+    pub subscribers: usize,
+}
+
+#[derive(Debug, Default)]
+struct UpdateStats {
+    updates: u64,
+    last_update: Option<Duration>,
+    window_start: Duration,
+    window_updates: u64,
+    // Rate of the last full window
+    rate: f64,
+}
+
+impl UpdateStats {
+    fn record(&mut self, now: Duration) {
+        let elapsed = now.saturating_sub(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            self.rate = self.rate(now);
+            self.window_start = now;
+            self.window_updates = 0;
+        }
+        self.updates += 1;
+        self.window_updates += 1;
+        self.last_update = Some(now);
+    }
+
+    fn rate(&self, now: Duration) -> f64 {
+        let elapsed = now.saturating_sub(self.window_start);
+        if elapsed < RATE_WINDOW {
+            self.rate
+        } else if elapsed < 2 * RATE_WINDOW {
+            self.window_updates as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        }
+    }
+}
+
+// Token bucket allowing bursts of up to one second worth of updates. A
+// message larger than that is let through once the bucket is not empty,
+// and pays off its debt before the next one is admitted.
+struct RateLimit {
+    rate: f64,
+    tokens: f64,
+    last_refill: Duration,
+}
+
+type SharedRateLimit = Arc<Mutex<RateLimit>>;
+
+impl RateLimit {
+    fn new(rate: u32, now: Duration) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last_refill: now,
+        }
+    }
+
+    fn admit(&mut self, updates: usize, now: Duration) -> bool {
+        if !self.available(now) {
+            return false;
+        }
+        self.spend(updates);
+        true
+    }
+
+    fn available(&mut self, now: Duration) -> bool {
+        self.available_above(now, 0.0)
+    }
+
+    /// Whether more than `reserved_share` of the bucket is left.
+    fn available_above(&mut self, now: Duration, reserved_share: f64) -> bool {
+        let elapsed = now.saturating_sub(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        self.last_refill = now;
+        self.tokens > reserved_share * self.rate
+    }
+
+    fn spend(&mut self, amount: usize) {
+        self.tokens -= amount as f64;
+    }
+}
+
+/// Counts as an open provider stream of a client until dropped.
+pub struct ProviderStreamGuard {
+    provider_streams: Arc<Mutex<HashMap<Option<String>, usize>>>,
+    provider_ingress: Arc<Mutex<HashMap<Option<String>, ProviderIngress>>>,
+    clock: Arc<dyn Clock>,
+    rate_limit: Option<RateLimit>,
+    events: EventBus,
+    client: Option<String>,
+}
+
+impl ProviderStreamGuard {
+    /// Account for a message of `bytes` bytes updating `updates` values.
+    /// Fails if the stream exceeded its rate, in which case the updates
+    /// must be dropped.
+    pub fn admit(&mut self, updates: usize, bytes: usize) -> Result<(), ProviderStreamError> {
+        let now = self.clock.elapsed();
+        let admitted = match &mut self.rate_limit {
+            Some(rate_limit) => rate_limit.admit(updates, now),
+            None => true,
+        };
+
+        let mut provider_ingress = self
+            .provider_ingress
+            .lock()
+            .expect("provider statistics should not be poisoned");
+        let ingress = provider_ingress.entry(self.client.clone()).or_default();
+        ingress.roll_window(now);
+        ingress.stats.bytes += bytes as u64;
+        if admitted {
+            ingress.stats.updates += updates as u64;
+            ingress.window_updates += updates as u64;
+            Ok(())
+        } else {
+            ingress.stats.throttled += updates as u64;
+            Err(ProviderStreamError::Throttled)
+        }
+    }
+
+    /// Account for values the broker rejected and publish them, so that
+    /// the provider finds out on its feedback stream.
+    pub fn reject(&self, rejected: Vec<RejectedUpdate>) {
+        if rejected.is_empty() {
+            return;
+        }
+        self.provider_ingress
+            .lock()
+            .expect("provider statistics should not be poisoned")
+            .entry(self.client.clone())
+            .or_default()
+            .stats
+            .rejected += rejected.len() as u64;
+        for update in rejected {
+            debug!(
+                "Rejected update of id {} by {:?}: {:?}",
+                update.id, self.client, update.error
+            );
+            self.events.publish(Event::UpdateRejected {
+                client: self.client.clone(),
+                update,
+            });
+        }
+    }
+}
+
+impl Drop for ProviderStreamGuard {
+    fn drop(&mut self) {
+        let mut provider_streams = self
+            .provider_streams
+            .lock()
+            .expect("provider stream counts should not be poisoned");
+        if let Some(count) = provider_streams.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                provider_streams.remove(&self.client);
+            }
+        }
+        self.events.publish(Event::ProviderDisconnected {
+            client: self.client.clone(),
+        });
+    }
+}
+
 #[derive(Clone)]
 pub struct DataBroker {
     database: Arc<RwLock<Database>>,
@@ -171,6 +728,32 @@ pub struct DataBroker {
     version: String,
     commit_sha: String,
     shutdown_trigger: broadcast::Sender<()>,
+    clock: Arc<dyn Clock>,
+    case_insensitive_paths: bool,
+    quotas: Quotas,
+    deadlines: Deadlines,
+    provider_streams: Arc<Mutex<HashMap<Option<String>, usize>>>,
+    provider_ingress: Arc<Mutex<HashMap<Option<String>, ProviderIngress>>>,
+    quota_rejections: Arc<AtomicU64>,
+    suppressed_updates: Arc<AtomicU64>,
+    lagged_notifications: Arc<AtomicU64>,
+    authorization_failures: Arc<AtomicU64>,
+    call_stats: Arc<CallStats>,
+    events: EventBus,
+    served_apis: Arc<Mutex<BTreeSet<String>>>,
+    api_usage: Arc<Mutex<Vec<ApiUsage>>>,
+    provider_enrollment: Option<Arc<ProviderEnrollment>>,
+    token_exchange: Option<Arc<TokenExchange>>,
+    subscriber_budgets: Arc<Mutex<HashMap<Option<String>, SharedRateLimit>>>,
+    dispatch_budget: Arc<Mutex<Option<SharedRateLimit>>>,
+    accounting: Arc<Accounting>,
+    sessions: Arc<Sessions>,
+    actuations: Arc<Actuations>,
+    log_filter: Option<Arc<LogFilter>>,
+    latency: Option<Arc<LatencyStats>>,
+    signal_groups: Arc<Mutex<SignalGroups>>,
+    #[cfg(feature = "chaos")]
+    chaos: Arc<Chaos>,
 }
 
 #[async_trait::async_trait]
@@ -186,6 +769,8 @@ pub trait ActuationProvider {
 pub struct ActuationChange {
     pub id: i32,
     pub data_value: DataValue,
+    /// Id the provider reports the outcome with, see [`crate::actuations`]
+    pub actuation_id: u64,
 }
 
 pub struct ActuationSubscription {
@@ -204,6 +789,28 @@ pub struct ChangeSubscription {
     entries: HashMap<i32, HashSet<Field>>,
     sender: broadcast::Sender<EntryUpdates>,
     permissions: Permissions,
+    filter: SubscriptionFilter,
+    filter_state: Mutex<FilterState>,
+    // Bandwidth budgets of the subscription and of the client, in bytes
+    budgets: Vec<SharedRateLimit>,
+    // Bandwidth budget of all subscriptions, in bytes, see `priority`
+    dispatch_budget: Option<SharedRateLimit>,
+    priority: SubscriptionPriority,
+    // Accounting of the client, counting the bytes sent
+    counters: Arc<ClientCounters>,
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Default)]
+struct FilterState {
+    // The last value sent per entry and when (clock elapsed time)
+    sent: HashMap<i32, (Duration, DataValue)>,
+    // Entries whose latest value was held back by the minimum interval
+    // or the bandwidth budget
+    held: HashSet<i32>,
+    // Values were held back by the bandwidth budget since the last
+    // notification
+    shaped: bool,
 }
 
 #[derive(Debug)]
@@ -233,23 +840,145 @@ pub struct EntryUpdate {
     pub unit: Option<String>,
 }
 
+/// Condition for a conditional update, checked against the field being
+/// updated (the actuator target if the update sets one, the datapoint
+/// otherwise).
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateCondition {
+    /// The current value equals the given value. An actuator target that
+    /// was never set matches `DataValue::NotAvailable`.
+    Value(DataValue),
+    /// The current value was written at the given time.
+    Timestamp(SystemTime),
+}
+
+fn patch<T: Clone>(array: &[T], indices: &[usize], values: &[T]) -> Result<Vec<T>, UpdateError> {
+    if indices.len() != values.len() {
+        return Err(UpdateError::OutOfBoundsIndex);
+    }
+    let mut array = array.to_vec();
+    for (index, value) in indices.iter().zip(values) {
+        *array.get_mut(*index).ok_or(UpdateError::OutOfBoundsIndex)? = value.clone();
+    }
+    Ok(array)
+}
+
+/// `array` with the elements at `indices` replaced by `values`, an array
+/// of the same type.
+fn patch_array(
+    array: &DataValue,
+    indices: &[usize],
+    values: &DataValue,
+) -> Result<DataValue, UpdateError> {
+    match (array, values) {
+        (DataValue::BoolArray(array), DataValue::BoolArray(values)) => {
+            patch(array, indices, values).map(DataValue::BoolArray)
+        }
+        (DataValue::StringArray(array), DataValue::StringArray(values)) => {
+            patch(array, indices, values).map(DataValue::StringArray)
+        }
+        (DataValue::Int32Array(array), DataValue::Int32Array(values)) => {
+            patch(array, indices, values).map(DataValue::Int32Array)
+        }
+        (DataValue::Int64Array(array), DataValue::Int64Array(values)) => {
+            patch(array, indices, values).map(DataValue::Int64Array)
+        }
+        (DataValue::Uint32Array(array), DataValue::Uint32Array(values)) => {
+            patch(array, indices, values).map(DataValue::Uint32Array)
+        }
+        (DataValue::Uint64Array(array), DataValue::Uint64Array(values)) => {
+            patch(array, indices, values).map(DataValue::Uint64Array)
+        }
+        (DataValue::FloatArray(array), DataValue::FloatArray(values)) => {
+            patch(array, indices, values).map(DataValue::FloatArray)
+        }
+        (DataValue::DoubleArray(array), DataValue::DoubleArray(values)) => {
+            patch(array, indices, values).map(DataValue::DoubleArray)
+        }
+        // No elements to replace yet
+        (DataValue::NotAvailable, _) => Err(UpdateError::OutOfBoundsIndex),
+        _ => Err(UpdateError::WrongType),
+    }
+}
+
+impl EntryUpdates {
+    /// Approximate size of the notification on the wire, in bytes: the
+    /// paths and values plus a fixed overhead per update for the
+    /// timestamp and framing.
+    fn size(&self) -> usize {
+        const OVERHEAD: usize = 16;
+        self.updates
+            .iter()
+            .map(|notification| {
+                let update = &notification.update;
+                OVERHEAD
+                    + update.path.as_ref().map_or(0, String::len)
+                    + update
+                        .datapoint
+                        .as_ref()
+                        .map_or(0, |datapoint| datapoint.value.encoded_size())
+                    + update
+                        .actuator_target
+                        .as_ref()
+                        .and_then(Option::as_ref)
+                        .map_or(0, |target| target.value.encoded_size())
+            })
+            .sum()
+    }
+}
+
+impl EntryUpdate {
+    /// Set all metadata fields to the values of `metadata`.
+    fn fill_metadata(&mut self, metadata: &Metadata) {
+        self.entry_type = Some(metadata.entry_type.clone());
+        self.data_type = Some(metadata.data_type.clone());
+        self.description = Some(metadata.description.clone());
+        self.allowed = Some(metadata.allowed.clone());
+        self.min = Some(metadata.min.clone());
+        self.max = Some(metadata.max.clone());
+        self.unit.clone_from(&metadata.unit);
+    }
+}
+
 impl Entry {
     #[cfg_attr(feature="otel",tracing::instrument(name="entry_diff", skip(self, update), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn diff(&self, mut update: EntryUpdate) -> EntryUpdate {
-        if let Some(datapoint) = &update.datapoint {
-            if self.metadata.change_type != ChangeType::Continuous {
-                // TODO: Compare timestamps as well?
-                if datapoint.value == self.datapoint.value {
-                    update.datapoint = None;
-                }
+        if let Some(datapoint) = &mut update.datapoint {
+            if let Some(precision) = self.metadata.precision {
+                datapoint.value = datapoint.value.rounded(precision);
+            }
+            // Values of continuous entries with a precision only count as
+            // changed if they differ once rounded, to not notify jitter
+            let compare = match self.metadata.change_type {
+                ChangeType::Event => false,
+                ChangeType::Continuous => self.metadata.precision.is_some(),
+                ChangeType::OnChange | ChangeType::Static => true,
+            };
+            // TODO: Compare timestamps as well?
+            if compare && datapoint.value == self.datapoint.value {
+                update.datapoint = None;
             }
         }
 
+        if update.description.as_ref() == Some(&self.metadata.description) {
+            update.description = None;
+        }
+        if update.allowed.as_ref() == Some(&self.metadata.allowed) {
+            update.allowed = None;
+        }
+        if update.min.as_ref() == Some(&self.metadata.min) {
+            update.min = None;
+        }
+        if update.max.as_ref() == Some(&self.metadata.max) {
+            update.max = None;
+        }
+        if update.unit.is_some() && update.unit == self.metadata.unit {
+            update.unit = None;
+        }
+
         // TODO: Implement for .path
         //                     .entry_type
         //                     .data_type
-        //                     .description
-        //                     .allowed
 
         update
     }
@@ -729,11 +1458,35 @@ impl Entry {
             changed.insert(Field::ActuatorTarget);
         }
 
+        if let Some(description) = update.description {
+            if description != self.metadata.description {
+                self.metadata.description = description;
+                changed.insert(Field::Metadata);
+            }
+        }
         if let Some(updated_allowed) = update.allowed {
             if updated_allowed != self.metadata.allowed {
                 self.metadata.allowed = updated_allowed;
+                changed.insert(Field::Metadata);
+            }
+        }
+        if let Some(min) = update.min {
+            if min != self.metadata.min {
+                self.metadata.min = min;
+                changed.insert(Field::Metadata);
             }
         }
+        if let Some(max) = update.max {
+            if max != self.metadata.max {
+                self.metadata.max = max;
+                changed.insert(Field::Metadata);
+            }
+        }
+        if update.unit.is_some() && update.unit != self.metadata.unit {
+            self.metadata.unit = update.unit;
+            changed.insert(Field::Metadata);
+            changed.insert(Field::MetadataUnit);
+        }
 
         // TODO: Apply the other fields as well
 
@@ -756,9 +1509,86 @@ impl Subscriptions {
         self.query_subscriptions.push(subscription)
     }
 
+    /// The subscription providing the actuator `vss_id`, the simulated one
+    /// if no connected provider claimed it.
+    fn actuation_subscription(&self, vss_id: i32) -> Option<&ActuationSubscription> {
+        let provided = self
+            .actuation_subscriptions
+            .iter()
+            .find(|subscription| subscription.vss_ids.contains(&vss_id));
+        match provided {
+            Some(subscription) if subscription.actuation_provider.is_available() => provided,
+            _ => self
+                .simulated_actuations
+                .iter()
+                .find(|subscription| subscription.vss_ids.contains(&vss_id))
+                .or(provided),
+        }
+    }
+
+    /// The number of live change and query subscriptions with permissions
+    /// matching `filter`.
+    pub fn count(&self, filter: impl Fn(&Permissions) -> bool) -> usize {
+        let changes = self
+            .change_subscriptions
+            .iter()
+            .filter(|sub| sub.sender.receiver_count() > 0 && filter(&sub.permissions))
+            .count();
+        let queries = self
+            .query_subscriptions
+            .iter()
+            .filter(|sub| !sub.sender.is_closed() && filter(&sub.permissions))
+            .count();
+        changes + queries
+    }
+
+    /// The number of active subscriptions of each client.
+    pub fn count_by_client(&self) -> HashMap<Option<String>, usize> {
+        let changes = self
+            .change_subscriptions
+            .iter()
+            .filter(|sub| sub.sender.receiver_count() > 0)
+            .map(|sub| &sub.permissions);
+        let queries = self
+            .query_subscriptions
+            .iter()
+            .filter(|sub| !sub.sender.is_closed())
+            .map(|sub| &sub.permissions);
+        let mut counts = HashMap::new();
+        for permissions in changes.chain(queries) {
+            *counts
+                .entry(permissions.subject().map(str::to_owned))
+                .or_default() += 1;
+        }
+        counts
+    }
+
+    /// The number of active subscriptions of each session.
+    pub fn count_by_session(&self) -> HashMap<u64, usize> {
+        let changes = self
+            .change_subscriptions
+            .iter()
+            .filter(|sub| sub.sender.receiver_count() > 0)
+            .map(|sub| &sub.permissions);
+        let queries = self
+            .query_subscriptions
+            .iter()
+            .filter(|sub| !sub.sender.is_closed())
+            .map(|sub| &sub.permissions);
+        let mut counts = HashMap::new();
+        for session in changes.chain(queries).filter_map(Permissions::session) {
+            *counts.entry(session).or_default() += 1;
+        }
+        counts
+    }
+
     #[cfg_attr(feature="otel", tracing::instrument(name="subscriptions_add_change_subscription",skip(self, subscription), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn add_change_subscription(&mut self, subscription: ChangeSubscription) {
-        self.change_subscriptions.push(subscription)
+        // Ordered by priority, so that higher classes are notified first
+        let at = self
+            .change_subscriptions
+            .partition_point(|sub| sub.priority >= subscription.priority);
+        self.change_subscriptions.insert(at, subscription)
     }
 
     #[cfg_attr(
@@ -769,9 +1599,21 @@ impl Subscriptions {
         &self,
         changed: Option<&HashMap<i32, HashSet<Field>>>,
         db: &Database,
+        stopwatch: &mut Stopwatch<'_>,
     ) -> Result<Option<HashMap<String, ()>>, NotificationError> {
         let mut error = None;
         let mut lag_updates: HashMap<String, ()> = HashMap::new();
+        // Query subscriptions count as telemetry
+        let (prioritized, telemetry) = self.change_subscriptions.split_at(
+            self.change_subscriptions
+                .partition_point(|sub| sub.priority > SubscriptionPriority::Telemetry),
+        );
+        for sub in prioritized {
+            if let Err(err) = sub.notify(changed, db, stopwatch).await {
+                error = Some(err);
+            }
+        }
+
         for sub in &self.query_subscriptions {
             match sub.notify(changed, db).await {
                 Ok(None) => {}
@@ -786,12 +1628,12 @@ impl Subscriptions {
             }
         }
 
-        for sub in &self.change_subscriptions {
-            match sub.notify(changed, db).await {
-                Ok(_) => {}
-                Err(err) => error = Some(err),
+        for sub in telemetry {
+            if let Err(err) = sub.notify(changed, db, stopwatch).await {
+                error = Some(err);
             }
         }
+        stopwatch.lap(Stage::Match);
 
         match error {
             Some(err) => Err(err),
@@ -805,14 +1647,28 @@ impl Subscriptions {
         }
     }
 
+    /// Send the values change subscriptions held back by their minimum
+    /// interval once it has passed.
+    pub async fn notify_held(&self, db: &Database) {
+        for sub in &self.change_subscriptions {
+            // Closed subscriptions are removed by cleanup
+            let _ = sub.notify_held(db).await;
+        }
+    }
+
     pub fn clear(&mut self) {
         self.actuation_subscriptions.clear();
+        self.simulated_actuations.clear();
         self.query_subscriptions.clear();
         self.change_subscriptions.clear();
     }
 
+    /// Remove the subscriptions of subscribers and providers that are gone
+    /// or whose permissions expired. Returns the number of removed
+    /// subscriptions.
     #[cfg_attr(feature="otel", tracing::instrument(name="subscriptions_cleanup", skip(self), fields(timestamp=chrono::Utc::now().to_string())))]
-    pub fn cleanup(&mut self) {
+    pub fn cleanup(&mut self) -> usize {
+        let before = self.len();
         self.query_subscriptions.retain(|sub| {
             if sub.sender.is_closed() {
                 info!("Subscriber gone: removing subscription");
@@ -844,6 +1700,35 @@ impl Subscriptions {
                 true
             }
         });
+        let removed = before - self.len();
+        self.removed += removed as u64;
+        removed
+    }
+
+    fn len(&self) -> usize {
+        self.actuation_subscriptions.len()
+            + self.query_subscriptions.len()
+            + self.change_subscriptions.len()
+    }
+
+    /// Release the memory left over by removed subscriptions, e.g. after
+    /// many clients reconnected.
+    pub fn compact(&mut self) {
+        fn shrink<T>(subscriptions: &mut Vec<T>) -> bool {
+            // Leave room to grow again
+            if subscriptions.capacity() > 2 * subscriptions.len() + 16 {
+                subscriptions.shrink_to(2 * subscriptions.len());
+                true
+            } else {
+                false
+            }
+        }
+        let actuation = shrink(&mut self.actuation_subscriptions);
+        let query = shrink(&mut self.query_subscriptions);
+        let change = shrink(&mut self.change_subscriptions);
+        if actuation || query || change {
+            debug!("Compacted subscriptions");
+        }
     }
 }
 
@@ -856,9 +1741,23 @@ impl ChangeSubscription {
         &self,
         changed: Option<&HashMap<i32, HashSet<Field>>>,
         db: &Database,
+        stopwatch: &mut Stopwatch<'_>,
     ) -> Result<(), NotificationError> {
         let db_read = db.authorized_read_access(&self.permissions);
         match changed {
+            Some(changed) if self.filter.snapshots => {
+                let matches = changed.iter().any(|(id, changed_fields)| {
+                    self.entries
+                        .get(id)
+                        .is_some_and(|fields| !fields.is_disjoint(changed_fields))
+                });
+                if matches {
+                    let notifications = self.snapshot(&db_read, UpdateReason::ValueChanged);
+                    self.send(notifications, stopwatch)
+                } else {
+                    Ok(())
+                }
+            }
             Some(changed) => {
                 let mut matches = false;
                 for (id, changed_fields) in changed {
@@ -869,7 +1768,8 @@ impl ChangeSubscription {
                         }
                     }
                 }
-                if matches {
+                let due = self.take_due(Some(changed));
+                if matches || !due.is_empty() {
                     // notify
                     let notifications = {
                         let mut notifications = EntryUpdates::default();
@@ -880,13 +1780,19 @@ impl ChangeSubscription {
                                         Ok(entry) => {
                                             let mut update = EntryUpdate::default();
                                             let mut notify_fields = HashSet::new();
+                                            let mut filtered = false;
                                             // TODO: Perhaps make path optional
                                             update.path = Some(entry.metadata.path.clone());
                                             if changed_fields.contains(&Field::Datapoint)
                                                 && fields.contains(&Field::Datapoint)
                                             {
-                                                update.datapoint = Some(entry.datapoint.clone());
-                                                notify_fields.insert(Field::Datapoint);
+                                                if self.filter_value(*id, &entry.datapoint) {
+                                                    update.datapoint =
+                                                        Some(entry.datapoint.clone());
+                                                    notify_fields.insert(Field::Datapoint);
+                                                } else {
+                                                    filtered = true;
+                                                }
                                             }
                                             if changed_fields.contains(&Field::ActuatorTarget)
                                                 && fields.contains(&Field::ActuatorTarget)
@@ -895,6 +1801,20 @@ impl ChangeSubscription {
                                                     Some(entry.actuator_target.clone());
                                                 notify_fields.insert(Field::ActuatorTarget);
                                             }
+                                            if changed_fields.contains(&Field::MetadataUnit)
+                                                && fields.contains(&Field::MetadataUnit)
+                                            {
+                                                notify_fields.insert(Field::MetadataUnit);
+                                            }
+                                            if changed_fields.contains(&Field::Metadata)
+                                                && fields.contains(&Field::Metadata)
+                                            {
+                                                update.fill_metadata(&entry.metadata);
+                                                notify_fields.insert(Field::Metadata);
+                                            }
+                                            if filtered && notify_fields.is_empty() {
+                                                continue;
+                                            }
                                             // fill unit field always
                                             update.unit.clone_from(&entry.metadata.unit);
                                             notifications.updates.push(ChangeNotification {
@@ -914,81 +1834,313 @@ impl ChangeSubscription {
                                 }
                             }
                         }
+                        self.push_held(&mut notifications, due, &db_read)?;
+                        self.shape(&mut notifications);
+                        notifications.reasons = notifications
+                            .updates
+                            .iter()
+                            .flat_map(|notification| notification.fields.iter())
+                            .map(UpdateReason::from_field)
+                            .collect::<BTreeSet<_>>()
+                            .into_iter()
+                            .collect();
                         notifications
                     };
                     if notifications.updates.is_empty() {
                         Ok(())
                     } else {
-                        match self.sender.send(notifications) {
-                            Ok(_number_of_receivers) => Ok(()),
-                            Err(err) => {
-                                debug!("Send error for entry{}: ", err);
-                                Err(NotificationError {})
-                            }
-                        }
+                        self.send(notifications, stopwatch)
                     }
                 } else {
                     Ok(())
                 }
             }
             None => {
-                let notifications = {
-                    let mut notifications = EntryUpdates::default();
-
-                    for (id, fields) in &self.entries {
-                        match db_read.get_entry_by_id(*id) {
-                            Ok(entry) => {
-                                let mut update = EntryUpdate::default();
-                                let mut notify_fields = HashSet::new();
-                                // TODO: Perhaps make path optional
-                                update.path = Some(entry.metadata.path.clone());
-                                if fields.contains(&Field::Datapoint) {
-                                    update.datapoint = Some(entry.datapoint.clone());
-                                    notify_fields.insert(Field::Datapoint);
-                                }
-                                if fields.contains(&Field::ActuatorTarget) {
-                                    update.actuator_target = Some(entry.actuator_target.clone());
-                                    notify_fields.insert(Field::ActuatorTarget);
-                                }
-                                notifications.updates.push(ChangeNotification {
-                                    id: *id,
-                                    update,
-                                    fields: notify_fields,
-                                });
-                            }
-                            Err(_) => {
-                                debug!("notify: could not find entry with id {}", id)
-                            }
-                        }
+                let notifications = self.snapshot(&db_read, UpdateReason::Initial);
+                self.send(notifications, stopwatch)
+            }
+        }
+    }
+
+    /// The state of everything subscribed to.
+    fn snapshot(&self, db_read: &DatabaseReadAccess, reason: UpdateReason) -> EntryUpdates {
+        let mut notifications = EntryUpdates {
+            reasons: vec![reason],
+            ..Default::default()
+        };
+
+        for (id, fields) in &self.entries {
+            match db_read.get_entry_by_id(*id) {
+                Ok(entry) => {
+                    let mut update = EntryUpdate::default();
+                    let mut notify_fields = HashSet::new();
+                    // TODO: Perhaps make path optional
+                    update.path = Some(entry.metadata.path.clone());
+                    if fields.contains(&Field::Datapoint) {
+                        self.mark_sent(*id, &entry.datapoint);
+                        update.datapoint = Some(entry.datapoint.clone());
+                        notify_fields.insert(Field::Datapoint);
                     }
-                    notifications
-                };
-                match self.sender.send(notifications) {
-                    Ok(_number_of_receivers) => Ok(()),
-                    Err(err) => {
-                        debug!("Send error for entry{}: ", err);
-                        Err(NotificationError {})
+                    if fields.contains(&Field::ActuatorTarget) {
+                        update.actuator_target = Some(entry.actuator_target.clone());
+                        notify_fields.insert(Field::ActuatorTarget);
+                    }
+                    if fields.contains(&Field::Metadata) {
+                        update.fill_metadata(&entry.metadata);
+                        notify_fields.insert(Field::Metadata);
                     }
+                    notifications.updates.push(ChangeNotification {
+                        id: *id,
+                        update,
+                        fields: notify_fields,
+                    });
+                }
+                Err(_) => {
+                    debug!("notify: could not find entry with id {}", id)
                 }
             }
         }
+        self.charge(notifications.size());
+        notifications
     }
-}
 
-impl QuerySubscription {
-    #[cfg_attr(feature="otel", tracing::instrument(name="query_subscription_find_in_db_and_add", skip(self, name, db, input), fields(timestamp=chrono::Utc::now().to_string())))]
-    fn find_in_db_and_add(
+    fn send(
         &self,
-        name: &String,
-        db: &DatabaseReadAccess,
-        input: &mut query::ExecutionInputImpl,
-    ) {
-        match db.get_entry_by_path(name) {
-            Ok(entry) => {
-                input.add(
-                    name.to_owned(),
-                    ExecutionInputImplData {
-                        value: entry.datapoint.value.to_owned(),
+        mut notifications: EntryUpdates,
+        stopwatch: &mut Stopwatch<'_>,
+    ) -> Result<(), NotificationError> {
+        stopwatch.lap(Stage::Match);
+        notifications.trace = stopwatch.trace();
+        let size = notifications.size();
+        let result = match self.sender.send(notifications) {
+            Ok(_number_of_receivers) => {
+                self.counters.record_streamed(size);
+                Ok(())
+            }
+            Err(err) => {
+                debug!("Send error for entry{}: ", err);
+                Err(NotificationError {})
+            }
+        };
+        stopwatch.lap(Stage::Dispatch);
+        result
+    }
+
+    /// Send the values held back by the minimum interval whose interval
+    /// has passed in the meantime.
+    async fn notify_held(&self, db: &Database) -> Result<(), NotificationError> {
+        let due = self.take_due(None);
+        if due.is_empty() {
+            return Ok(());
+        }
+        let db_read = db.authorized_read_access(&self.permissions);
+        let mut notifications = EntryUpdates::default();
+        self.push_held(&mut notifications, due, &db_read)?;
+        self.shape(&mut notifications);
+        if notifications.updates.is_empty() {
+            return Ok(());
+        }
+        notifications.reasons = vec![UpdateReason::ValueChanged];
+        self.send(notifications, &mut Stopwatch::default())
+    }
+
+    /// Whether a changed value gets sent. Records it as sent if it does.
+    fn filter_value(&self, id: i32, datapoint: &Datapoint) -> bool {
+        if !self.filter.filters_values() {
+            return true;
+        }
+        let now = self.clock.elapsed();
+        let mut state = self
+            .filter_state
+            .lock()
+            .expect("filter state lock poisoned");
+        if let Some((sent_at, sent_value)) = state.sent.get(&id) {
+            if let (Some(min_change), Some(value), Some(sent_value)) = (
+                self.filter.min_change,
+                datapoint.value.as_f64(),
+                sent_value.as_f64(),
+            ) {
+                if (value - sent_value).abs() < min_change {
+                    // Back within range of what the subscriber has seen
+                    state.held.remove(&id);
+                    return false;
+                }
+            }
+            if let Some(min_interval) = self.filter.min_interval {
+                if now.saturating_sub(*sent_at) < min_interval {
+                    state.held.insert(id);
+                    return false;
+                }
+            }
+        }
+        state.held.remove(&id);
+        state.sent.insert(id, (now, datapoint.value.clone()));
+        true
+    }
+
+    fn mark_sent(&self, id: i32, datapoint: &Datapoint) {
+        if self.filter.filters_values() {
+            let now = self.clock.elapsed();
+            let mut state = self
+                .filter_state
+                .lock()
+                .expect("filter state lock poisoned");
+            state.held.remove(&id);
+            state.sent.insert(id, (now, datapoint.value.clone()));
+        }
+    }
+
+    /// Take the held back entries that are due. Entries whose value
+    /// changed again in `changed` are left to the filter.
+    fn take_due(&self, changed: Option<&HashMap<i32, HashSet<Field>>>) -> Vec<i32> {
+        if self.filter.min_interval.is_none() && !self.has_budget() {
+            return Vec::new();
+        }
+        let min_interval = self.filter.min_interval.unwrap_or_default();
+        let now = self.clock.elapsed();
+        if !self.within_budget(now) {
+            return Vec::new();
+        }
+        let mut state = self
+            .filter_state
+            .lock()
+            .expect("filter state lock poisoned");
+        if state.held.is_empty() {
+            return Vec::new();
+        }
+        let FilterState { sent, held, .. } = &mut *state;
+        let due: Vec<i32> = held
+            .iter()
+            .filter(|id| {
+                changed.is_none_or(|changed| {
+                    !changed
+                        .get(id)
+                        .is_some_and(|fields| fields.contains(&Field::Datapoint))
+                })
+            })
+            .filter(|id| {
+                sent.get(id)
+                    .is_none_or(|(sent_at, _)| now.saturating_sub(*sent_at) >= min_interval)
+            })
+            .copied()
+            .collect();
+        for id in &due {
+            held.remove(id);
+        }
+        due
+    }
+
+    fn has_budget(&self) -> bool {
+        !self.budgets.is_empty() || self.dispatch_budget.is_some()
+    }
+
+    fn within_budget(&self, now: Duration) -> bool {
+        let dispatch = match (&self.dispatch_budget, self.priority.reserved_share()) {
+            (Some(budget), Some(reserved_share)) => budget
+                .lock()
+                .expect("dispatch budget lock poisoned")
+                .available_above(now, reserved_share),
+            _ => true,
+        };
+        dispatch
+            && self.budgets.iter().all(|budget| {
+                budget
+                    .lock()
+                    .expect("subscriber budget lock poisoned")
+                    .available(now)
+            })
+    }
+
+    fn charge(&self, size: usize) {
+        for budget in self.budgets.iter().chain(&self.dispatch_budget) {
+            budget
+                .lock()
+                .expect("subscriber budget lock poisoned")
+                .spend(size);
+        }
+    }
+
+    /// Hold back the values of `notifications` if they exceed the
+    /// bandwidth budgets. The held back entries are sent with their
+    /// latest value once the budgets allow.
+    fn shape(&self, notifications: &mut EntryUpdates) {
+        if !self.has_budget() {
+            return;
+        }
+        let admitted = self.within_budget(self.clock.elapsed());
+        let mut state = self
+            .filter_state
+            .lock()
+            .expect("filter state lock poisoned");
+        if !admitted {
+            state.shaped = true;
+            notifications.updates.retain_mut(|notification| {
+                if notification.fields.remove(&Field::Datapoint) {
+                    notification.update.datapoint = None;
+                    // The subscriber hasn't seen it, the next value is due
+                    state.sent.remove(&notification.id);
+                    state.held.insert(notification.id);
+                }
+                !notification.fields.is_empty()
+            });
+        }
+        if !notifications.updates.is_empty() {
+            // Entries still held back are flagged when they are sent
+            notifications.shaped = !admitted || std::mem::take(&mut state.shaped);
+            drop(state);
+            self.charge(notifications.size());
+        }
+    }
+
+    fn push_held(
+        &self,
+        notifications: &mut EntryUpdates,
+        due: Vec<i32>,
+        db_read: &DatabaseReadAccess,
+    ) -> Result<(), NotificationError> {
+        for id in due {
+            match db_read.get_entry_by_id(id) {
+                Ok(entry) => {
+                    self.mark_sent(id, &entry.datapoint);
+                    let update = EntryUpdate {
+                        path: Some(entry.metadata.path.clone()),
+                        datapoint: Some(entry.datapoint.clone()),
+                        unit: entry.metadata.unit.clone(),
+                        ..Default::default()
+                    };
+                    notifications.updates.push(ChangeNotification {
+                        id,
+                        update,
+                        fields: HashSet::from([Field::Datapoint]),
+                    });
+                }
+                Err(ReadError::PermissionExpired) => {
+                    debug!("notify: token expired, closing subscription channel");
+                    return Err(NotificationError {});
+                }
+                Err(_) => {
+                    debug!("notify: could not find entry with id {}", id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl QuerySubscription {
+    #[cfg_attr(feature="otel", tracing::instrument(name="query_subscription_find_in_db_and_add", skip(self, name, db, input), fields(timestamp=chrono::Utc::now().to_string())))]
+    fn find_in_db_and_add(
+        &self,
+        name: &String,
+        db: &DatabaseReadAccess,
+        input: &mut query::ExecutionInputImpl,
+    ) {
+        match db.get_entry_by_path(name) {
+            Ok(entry) => {
+                input.add(
+                    name.to_owned(),
+                    ExecutionInputImplData {
+                        value: entry.datapoint.value.to_owned(),
                         lag_value: entry.lag_datapoint.value.to_owned(),
                     },
                 );
@@ -1091,6 +2243,11 @@ impl QuerySubscription {
                         Some(fields) => match self
                             .sender
                             .send(QueryResponse {
+                                reason: if changed.is_some() {
+                                    UpdateReason::ValueChanged
+                                } else {
+                                    UpdateReason::Initial
+                                },
                                 fields: fields
                                     .iter()
                                     .map(|e| QueryField {
@@ -1205,8 +2362,8 @@ impl DatabaseReadAccess<'_, '_> {
     }
 
     pub fn get_entry_by_path(&self, path: impl AsRef<str>) -> Result<&Entry, ReadError> {
-        match self.db.path_to_id.get(path.as_ref()) {
-            Some(id) => self.get_entry_by_id(*id),
+        match self.db.get_id(path.as_ref()) {
+            Some(id) => self.get_entry_by_id(id),
             None => Err(ReadError::NotFound),
         }
     }
@@ -1218,8 +2375,8 @@ impl DatabaseReadAccess<'_, '_> {
 
     #[cfg_attr(feature="otel", tracing::instrument(name="database_read_access_get_metadata_by_path", skip(self, path), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn get_metadata_by_path(&self, path: &str) -> Option<&Metadata> {
-        let id = self.db.path_to_id.get(path)?;
-        self.get_metadata_by_id(*id)
+        let id = self.db.get_id(path)?;
+        self.get_metadata_by_id(id)
     }
 
     #[cfg_attr(feature="otel", tracing::instrument(name="database_read_access_iter_entries", skip(self), fields(timestamp=chrono::Utc::now().to_string())))]
@@ -1237,16 +2394,16 @@ impl DatabaseWriteAccess<'_, '_> {
         path: &str,
         update: EntryUpdate,
     ) -> Result<HashSet<Field>, UpdateError> {
-        match self.db.path_to_id.get(path) {
-            Some(id) => self.update(*id, update),
+        match self.db.get_id(path) {
+            Some(id) => self.update(id, update),
             None => Err(UpdateError::NotFound),
         }
     }
 
     #[cfg_attr(feature="otel", tracing::instrument(name="database_write_access_update_entry_lag_to_be_equal", skip(self, path), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn update_entry_lag_to_be_equal(&mut self, path: &str) -> Result<(), UpdateError> {
-        match self.db.path_to_id.get(path) {
-            Some(id) => match self.db.entries.get_mut(id) {
+        match self.db.get_id(path) {
+            Some(id) => match self.db.entries.get_mut(&id) {
                 Some(entry) => {
                     entry.apply_lag_after_execute();
                     Ok(())
@@ -1259,15 +2416,80 @@ impl DatabaseWriteAccess<'_, '_> {
 
     #[cfg_attr(feature="otel", tracing::instrument(name="database_write_access_update", skip(self, id, update), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn update(&mut self, id: i32, update: EntryUpdate) -> Result<HashSet<Field>, UpdateError> {
+        let update = self.check_update(id, update)?;
+        self.apply(id, update)
+    }
+
+    /// Apply an update that passed [`check_update`](Self::check_update).
+    fn apply(&mut self, id: i32, update: EntryUpdate) -> Result<HashSet<Field>, UpdateError> {
         match self.db.entries.get_mut(&id) {
+            Some(entry) => Ok(entry.apply(update)),
+            None => Err(UpdateError::NotFound),
+        }
+    }
+
+    /// Check that `condition` holds for the entry, evaluated against the
+    /// field that `update` would change.
+    pub fn check_condition(
+        &self,
+        id: i32,
+        condition: &UpdateCondition,
+        update: &EntryUpdate,
+    ) -> Result<(), UpdateError> {
+        let entry = self.db.entries.get(&id).ok_or(UpdateError::NotFound)?;
+        // Whether the condition holds reveals the current value
+        self.permissions
+            .can_read(&entry.metadata.path)
+            .map_err(|err| match err {
+                PermissionError::Denied => UpdateError::PermissionDenied,
+                PermissionError::Expired => UpdateError::PermissionExpired,
+            })?;
+
+        let current = if update.actuator_target.is_some() {
+            entry.actuator_target.as_ref()
+        } else {
+            Some(&entry.datapoint)
+        };
+        let holds = match (condition, current) {
+            (UpdateCondition::Value(expected), Some(current)) => *expected == current.value,
+            (UpdateCondition::Value(expected), None) => *expected == DataValue::NotAvailable,
+            (UpdateCondition::Timestamp(expected), Some(current)) => *expected == current.ts,
+            (UpdateCondition::Timestamp(_), None) => false,
+        };
+        if holds {
+            Ok(())
+        } else {
+            Err(UpdateError::Conflict)
+        }
+    }
+
+    /// Check permissions and validate `update` without applying it.
+    ///
+    /// Returns the update reduced to the fields that would change.
+    pub fn check_update(&self, id: i32, update: EntryUpdate) -> Result<EntryUpdate, UpdateError> {
+        match self.db.entries.get(&id) {
             Some(entry) => {
                 if update.path.is_some()
                     || update.entry_type.is_some()
                     || update.data_type.is_some()
-                    || update.description.is_some()
                 {
                     return Err(UpdateError::PermissionDenied);
                 }
+                // Changing metadata requires the same permission as registering it
+                if update.description.is_some()
+                    || update.allowed.is_some()
+                    || update.min.is_some()
+                    || update.max.is_some()
+                    || update.unit.is_some()
+                {
+                    match self.permissions.can_create(&entry.metadata.path) {
+                        Err(PermissionError::Denied) => return Err(UpdateError::PermissionDenied),
+                        Err(PermissionError::Expired) => {
+                            return Err(UpdateError::PermissionExpired)
+                        }
+                        Ok(()) => {}
+                    }
+                }
                 match (
                     &update.datapoint,
                     self.permissions.can_write_datapoint(&entry.metadata.path),
@@ -1296,17 +2518,34 @@ impl DatabaseWriteAccess<'_, '_> {
                     }
                     (_, _) => {}
                 }
+                if update.actuator_target.is_some() {
+                    self.db
+                        .check_safety_gate(&entry.metadata, self.permissions)
+                        .map_err(|err| match err {
+                            PermissionError::Denied => UpdateError::PermissionDenied,
+                            PermissionError::Expired => UpdateError::PermissionExpired,
+                        })?;
+                }
+                if let Some(Some(_)) = update.actuator_target {
+                    self.db
+                        .check_policies(&entry.metadata.path)
+                        .map_err(UpdateError::PolicyViolation)?;
+                }
 
                 // Reduce update to only include changes
                 let update = entry.diff(update);
                 // Validate update
-                match entry.validate(&update) {
-                    Ok(_) => {
-                        let changed_fields = entry.apply(update);
-                        Ok(changed_fields)
-                    }
-                    Err(err) => Err(err),
+                entry.validate(&update)?;
+                if let Some(datapoint) = &update.datapoint {
+                    self.db
+                        .validators
+                        .validate(&entry.metadata.path, &entry.datapoint, datapoint)
+                        .map_err(|reason| {
+                            debug!("Rejected value of {}: {}", entry.metadata.path, reason);
+                            UpdateError::Implausible(reason)
+                        })?;
                 }
+                Ok(update)
             }
             None => Err(UpdateError::NotFound),
         }
@@ -1337,9 +2576,9 @@ impl DatabaseWriteAccess<'_, '_> {
                 PermissionError::Expired => RegistrationError::PermissionExpired,
             })?;
 
-        if let Some(id) = self.db.path_to_id.get(&name) {
+        if let Some(id) = self.db.get_id(&name) {
             // It already exists
-            return Ok(*id);
+            return Ok(id);
         };
 
         let temp_id = 0;
@@ -1357,6 +2596,9 @@ impl DatabaseWriteAccess<'_, '_> {
                 min,
                 max,
                 unit,
+                safety_level: None,
+                target_ttl: None,
+                precision: None,
             },
             datapoint: match datapoint.clone() {
                 Some(datapoint) => datapoint,
@@ -1381,10 +2623,23 @@ impl DatabaseWriteAccess<'_, '_> {
             .validate_allowed_type(&new_entry.metadata.allowed)
             .map_err(|_err| RegistrationError::ValidationError)?;
 
-        // Get next id (and bump it)
-        let id = self.db.next_id.fetch_add(1, Ordering::SeqCst);
+        if let Some(max_entries) = self.db.max_entries {
+            if self.db.entries.len() >= max_entries {
+                return Err(RegistrationError::QuotaExceeded);
+            }
+        }
+
+        let id = match self.db.reserved_ids.get(&name) {
+            Some(id) if !self.db.entries.contains_key(id) => *id,
+            // Get next id (and bump it)
+            _ => self.db.next_id.fetch_add(1, Ordering::SeqCst),
+        };
 
         // Map name -> id
+        self.db
+            .folded_path_to_id
+            .entry(name.to_lowercase())
+            .or_insert(id);
         self.db.path_to_id.insert(name, id);
 
         new_entry.metadata.id = id;
@@ -1402,10 +2657,207 @@ impl Database {
         Self {
             next_id: Default::default(),
             path_to_id: Default::default(),
+            reserved_ids: Default::default(),
+            folded_path_to_id: Default::default(),
+            case_insensitive_paths: false,
+            max_entries: None,
+            safety_gate: None,
+            validators: Default::default(),
+            policies: Default::default(),
+            target_ttl: None,
+            target_deadlines: Default::default(),
+            history: Default::default(),
+            update_stats: Default::default(),
             entries: Default::default(),
         }
     }
 
+    /// Whether `permissions` allow actuating an entry with `metadata`
+    /// under the safety gate, if any.
+    fn check_safety_gate(
+        &self,
+        metadata: &Metadata,
+        permissions: &Permissions,
+    ) -> Result<(), PermissionError> {
+        let (Some(gate), Some(safety_level)) = (self.safety_gate, metadata.safety_level) else {
+            return Ok(());
+        };
+        if safety_level <= gate.max_level {
+            return Ok(());
+        }
+        let result = match gate.mode {
+            SafetyGateMode::RequireScope => permissions.can_actuate_safety_relevant(&metadata.path),
+            SafetyGateMode::Reject => Err(PermissionError::Denied),
+        };
+        if let Err(PermissionError::Denied) = result {
+            warn!(
+                "Client {} may not actuate {} ({})",
+                permissions.subject().unwrap_or("<anonymous>"),
+                metadata.path,
+                safety_level
+            );
+        }
+        result
+    }
+
+    /// Whether the policies allow actuating the entry at `path` given the
+    /// current values. Otherwise returns the condition denying it.
+    fn check_policies(&self, path: &str) -> Result<(), String> {
+        // Interlocks apply whatever the caller may read
+        let db_read = self.authorized_read_access(&crate::permissions::ALLOW_ALL);
+        for condition in self.policies.actuate_conditions(path) {
+            let compiled =
+                match query::compile(&format!("SELECT {path} WHERE {condition}"), &db_read) {
+                    Ok(compiled) => compiled,
+                    Err(err) => {
+                        warn!(
+                            "Could not compile policy condition '{}': {:?}",
+                            condition, err
+                        );
+                        return Err(condition.to_owned());
+                    }
+                };
+            let mut input = query::ExecutionInputImpl::new();
+            for name in &compiled.input_spec {
+                if let Ok(entry) = db_read.get_entry_by_path(name) {
+                    input.add(
+                        name.to_owned(),
+                        ExecutionInputImplData {
+                            value: entry.datapoint.value.to_owned(),
+                            lag_value: entry.lag_datapoint.value.to_owned(),
+                        },
+                    );
+                }
+            }
+            match compiled.execute(&input) {
+                Ok(None) => {}
+                Ok(Some(_)) => {
+                    info!("Actuation of {} denied by policy: {}", path, condition);
+                    return Err(condition.to_owned());
+                }
+                Err(err) => {
+                    warn!(
+                        "Could not evaluate policy condition '{}': {:?}",
+                        condition, err
+                    );
+                    return Err(condition.to_owned());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the datapoint updates of the entries `updated`.
+    fn count_updates(&mut self, updated: &[i32], now: Duration) {
+        for id in updated {
+            self.update_stats.entry(*id).or_default().record(now);
+        }
+    }
+
+    /// Add the new values among `changed` to the history of their entries.
+    fn record_history(&mut self, changed: &HashMap<i32, HashSet<Field>>) {
+        if !self.history.is_enabled() {
+            return;
+        }
+        for (id, fields) in changed {
+            if !fields.contains(&Field::Datapoint) {
+                continue;
+            }
+            if let Some(entry) = self.entries.get(id) {
+                self.history
+                    .record(*id, &entry.metadata.path, &entry.datapoint);
+            }
+        }
+    }
+
+    /// The event entries among `changed` that got a new value.
+    fn changed_events(&self, changed: &HashMap<i32, HashSet<Field>>) -> Vec<i32> {
+        changed
+            .iter()
+            .filter(|(id, fields)| {
+                fields.contains(&Field::Datapoint)
+                    && self
+                        .entries
+                        .get(id)
+                        .is_some_and(|entry| entry.metadata.change_type == ChangeType::Event)
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Drop the values of the event entries `events` once delivered, so
+    /// that they don't show up as current values. The time of the latest
+    /// occurrence is kept.
+    fn expire_events(&mut self, events: &[i32]) {
+        for id in events {
+            if let Some(entry) = self.entries.get_mut(id) {
+                entry.datapoint.value = DataValue::NotAvailable;
+                entry.lag_datapoint = entry.datapoint.clone();
+            }
+        }
+    }
+
+    /// Track the deadlines of the actuator targets in `changed`. A target
+    /// counts as confirmed once the current value matches it.
+    fn track_target_deadlines(&mut self, changed: &HashMap<i32, HashSet<Field>>, now: Duration) {
+        for (id, fields) in changed {
+            let Some(entry) = self.entries.get(id) else {
+                continue;
+            };
+            if fields.contains(&Field::ActuatorTarget) {
+                match (
+                    &entry.actuator_target,
+                    entry.metadata.target_ttl.or(self.target_ttl),
+                ) {
+                    (Some(_), Some(ttl)) => {
+                        self.target_deadlines.insert(*id, now + ttl);
+                    }
+                    _ => {
+                        self.target_deadlines.remove(id);
+                    }
+                }
+            }
+            if let Some(target) = &entry.actuator_target {
+                if target.value == entry.datapoint.value {
+                    self.target_deadlines.remove(id);
+                }
+            }
+        }
+    }
+
+    /// Clear the actuator targets that weren't confirmed by `now`.
+    fn expire_targets(&mut self, now: Duration) -> HashMap<i32, HashSet<Field>> {
+        let expired: Vec<i32> = self
+            .target_deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut changed = HashMap::new();
+        for id in expired {
+            self.target_deadlines.remove(&id);
+            if let Some(entry) = self.entries.get_mut(&id) {
+                warn!(
+                    "Target of {} expired without being confirmed by its provider",
+                    entry.metadata.path
+                );
+                entry.actuator_target = None;
+                changed.insert(id, HashSet::from([Field::ActuatorTarget]));
+            }
+        }
+        changed
+    }
+
+    fn get_id(&self, path: &str) -> Option<i32> {
+        match self.path_to_id.get(path) {
+            Some(id) => Some(*id),
+            None if self.case_insensitive_paths => {
+                self.folded_path_to_id.get(&path.to_lowercase()).copied()
+            }
+            None => None,
+        }
+    }
+
     #[cfg_attr(feature="otel", tracing::instrument(name="database_authorized_read_access", skip(self, permissions), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn authorized_read_access<'a, 'b>(
         &'a self,
@@ -1457,23 +2909,87 @@ impl AuthorizedAccess<'_, '_> {
         allowed: Option<types::DataValue>,
         unit: Option<String>,
     ) -> Result<i32, RegistrationError> {
-        self.broker
-            .database
-            .write()
-            .await
-            .authorized_write_access(self.permissions)
-            .add(
-                name,
-                data_type,
-                change_type,
-                entry_type,
-                description,
-                min,
-                max,
-                allowed,
-                None,
-                unit,
-            )
+        let mut db = self.broker.database.write().await;
+        let entries = db.entries.len();
+        let path = name.clone();
+        let result = db.authorized_write_access(self.permissions).add(
+            name,
+            data_type,
+            change_type,
+            entry_type,
+            description,
+            min,
+            max,
+            allowed,
+            None,
+            unit,
+        );
+        match result {
+            // Registering an existing entry returns its id
+            Ok(id) if db.entries.len() > entries => {
+                self.broker.events.publish(Event::EntryAdded { id, path });
+            }
+            _ => {}
+        }
+        drop(db);
+        if result == Err(RegistrationError::QuotaExceeded) {
+            self.broker
+                .reject_quota_exceeded(self.permissions, "registered entries");
+        }
+        result
+    }
+
+    /// Add a subscription unless the client already has the maximum number
+    /// of subscriptions. Returns whether it was added.
+    async fn add_subscription(&self, add: impl FnOnce(&mut Subscriptions)) -> bool {
+        let mut subscriptions = self.broker.subscriptions.write().await;
+        if let Some(max) = self.broker.quotas.max_subscriptions_per_client {
+            let subject = self.permissions.subject();
+            if subscriptions.count(|permissions| permissions.subject() == subject) >= max {
+                self.broker
+                    .reject_quota_exceeded(self.permissions, "subscriptions");
+                return false;
+            }
+        }
+        add(&mut subscriptions);
+        true
+    }
+
+    /// Count an opened provider stream of the client, until the returned
+    /// guard is dropped.
+    pub fn open_provider_stream(&self) -> Result<ProviderStreamGuard, ProviderStreamError> {
+        let client = self.permissions.subject().map(str::to_owned);
+        let mut provider_streams = self
+            .broker
+            .provider_streams
+            .lock()
+            .expect("provider stream counts should not be poisoned");
+        let count = provider_streams.entry(client.clone()).or_default();
+        if let Some(max) = self.broker.quotas.max_provider_streams_per_client {
+            if *count >= max {
+                drop(provider_streams);
+                self.broker
+                    .reject_quota_exceeded(self.permissions, "provider streams");
+                return Err(ProviderStreamError::QuotaExceeded);
+            }
+        }
+        *count += 1;
+        drop(provider_streams);
+        self.broker.events.publish(Event::ProviderConnected {
+            client: client.clone(),
+        });
+        Ok(ProviderStreamGuard {
+            provider_streams: self.broker.provider_streams.clone(),
+            provider_ingress: self.broker.provider_ingress.clone(),
+            clock: self.broker.clock.clone(),
+            rate_limit: self
+                .broker
+                .quotas
+                .max_provider_stream_rate
+                .map(|rate| RateLimit::new(rate, self.broker.clock.elapsed())),
+            events: self.broker.events.clone(),
+            client,
+        })
     }
 
     pub async fn with_read_lock<T>(&self, f: impl FnOnce(&DatabaseReadAccess) -> T) -> T {
@@ -1506,6 +3022,71 @@ impl AuthorizedAccess<'_, '_> {
             .map(|entry| entry.datapoint.clone())
     }
 
+    /// The kept past values of the entry `id` since `since` (or all),
+    /// oldest first. Empty if the entry has no history.
+    pub async fn get_history(
+        &self,
+        id: i32,
+        since: Option<SystemTime>,
+    ) -> Result<Vec<Datapoint>, ReadError> {
+        let db = self.broker.database.read().await;
+        db.authorized_read_access(self.permissions)
+            .get_entry_by_id(id)?;
+        Ok(db.history.samples(id, since))
+    }
+
+    /// Define the signal group `name`, replacing any group of that name, or
+    /// remove it if `paths` is empty. Fails with NotFound if any of the
+    /// signals doesn't exist.
+    pub async fn define_signal_group(
+        &self,
+        name: &str,
+        paths: Vec<String>,
+    ) -> Result<(), ReadError> {
+        let paths = {
+            let db = self.broker.database.read().await;
+            let db_read = db.authorized_read_access(self.permissions);
+            paths
+                .iter()
+                .map(|path| {
+                    db_read
+                        .get_metadata_by_path(path)
+                        .map(|metadata| metadata.path.clone())
+                        .ok_or(ReadError::NotFound)
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let mut groups = self
+            .broker
+            .signal_groups
+            .lock()
+            .expect("signal groups should not be poisoned");
+        if paths.is_empty() {
+            groups.remove(name);
+        } else {
+            groups.insert(name.to_owned(), paths);
+        }
+        Ok(())
+    }
+
+    /// The paths and values of the signals of group `name`, all read from
+    /// the same state.
+    pub async fn get_signal_group(
+        &self,
+        name: &str,
+    ) -> Result<Vec<(String, Datapoint)>, ReadError> {
+        let paths = self.broker.signal_group(name).ok_or(ReadError::NotFound)?;
+        let db = self.broker.database.read().await;
+        let db_read = db.authorized_read_access(self.permissions);
+        paths
+            .into_iter()
+            .map(|path| {
+                let datapoint = db_read.get_entry_by_path(&path)?.datapoint.clone();
+                Ok((path, datapoint))
+            })
+            .collect()
+    }
+
     pub async fn get_datapoint_by_path(&self, name: &str) -> Result<Datapoint, ReadError> {
         self.broker
             .database
@@ -1598,18 +3179,147 @@ impl AuthorizedAccess<'_, '_> {
         &self,
         updates: impl IntoIterator<Item = (i32, EntryUpdate)>,
     ) -> Result<(), Vec<(i32, UpdateError)>> {
+        self.update_entries_impl(updates, false, &[]).await
+    }
+
+    /// Like [`update_entries`](Self::update_entries), but all updates are
+    /// validated before any of them is applied. If any update fails, none
+    /// is applied and the failing ones are returned.
+    pub async fn update_entries_atomic(
+        &self,
+        updates: impl IntoIterator<Item = (i32, EntryUpdate)>,
+    ) -> Result<(), Vec<(i32, UpdateError)>> {
+        self.update_entries_impl(updates, true, &[]).await
+    }
+
+    /// Apply `update` only if `condition` holds for the entry at the time
+    /// of the write, otherwise fail with [`UpdateError::Conflict`]. Lets
+    /// several writers coordinate on the same entry without overwriting
+    /// each other's changes unknowingly.
+    pub async fn compare_and_update(
+        &self,
+        id: i32,
+        condition: UpdateCondition,
+        update: EntryUpdate,
+    ) -> Result<(), UpdateError> {
+        self.update_entries_impl([(id, update)], true, &[(id, condition)])
+            .await
+            .map_err(|mut errors| match errors.pop() {
+                Some((_, err)) => err,
+                None => UpdateError::NotFound,
+            })
+    }
+
+    /// Replace the elements at `indices` of the array entry `id` with
+    /// `values`, an array of the same type, leaving the other elements as
+    /// they are. The resulting array is validated like a full update.
+    ///
+    /// Fails with [`UpdateError::OutOfBoundsIndex`] if an index is beyond
+    /// the end of the array (or the array has no value yet) or the number
+    /// of indices and values differ, and with [`UpdateError::WrongType`] if
+    /// `values` doesn't match the type of the entry.
+    pub async fn update_array_elements(
+        &self,
+        id: i32,
+        indices: &[usize],
+        values: &DataValue,
+        source_ts: Option<SystemTime>,
+    ) -> Result<(), UpdateError> {
+        const ATTEMPTS: usize = 8;
+        // The elements are applied to the array as read, so an update of the
+        // array in between fails the comparison and is patched again
+        for _ in 0..ATTEMPTS {
+            let current = self.get_datapoint(id).await.map_err(|err| match err {
+                ReadError::NotFound => UpdateError::NotFound,
+                ReadError::PermissionDenied => UpdateError::PermissionDenied,
+                ReadError::PermissionExpired => UpdateError::PermissionExpired,
+            })?;
+            let value = patch_array(&current.value, indices, values)?;
+            let update = EntryUpdate {
+                datapoint: Some(Datapoint {
+                    ts: self.broker.clock.now(),
+                    source_ts,
+                    value,
+                }),
+                ..Default::default()
+            };
+            match self
+                .compare_and_update(id, UpdateCondition::Value(current.value), update)
+                .await
+            {
+                Err(UpdateError::Conflict) => continue,
+                result => return result,
+            }
+        }
+        Err(UpdateError::Conflict)
+    }
+
+    async fn update_entries_impl(
+        &self,
+        updates: impl IntoIterator<Item = (i32, EntryUpdate)>,
+        atomic: bool,
+        conditions: &[(i32, UpdateCondition)],
+    ) -> Result<(), Vec<(i32, UpdateError)>> {
+        #[cfg(feature = "chaos")]
+        self.broker.chaos.delay().await;
+
+        let mut stopwatch = Stopwatch::start(self.broker.latency.as_deref());
         let mut errors = Vec::new();
         let mut db = self.broker.database.write().await;
+        stopwatch.lap(Stage::Lock);
         let mut db_write = db.authorized_write_access(self.permissions);
         let mut lag_updates: HashMap<String, ()> = HashMap::new();
 
+        let updates: Vec<(i32, EntryUpdate)> = updates.into_iter().collect();
+        let no_update = EntryUpdate::default();
+        for (id, condition) in conditions {
+            let update = updates
+                .iter()
+                .find(|(update_id, _)| update_id == id)
+                .map_or(&no_update, |(_, update)| update);
+            if let Err(err) = db_write.check_condition(*id, condition, update) {
+                errors.push((*id, err));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        if atomic {
+            // Nothing is applied while holding the write lock, so all
+            // updates are checked against the same state
+            for (id, update) in &updates {
+                if let Err(err) = db_write.check_update(*id, update.clone()) {
+                    errors.push((*id, err));
+                }
+            }
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+        }
+
+        let events;
         let cleanup_needed = {
+            let mut updated = Vec::new();
+            let mut suppressed = 0;
             let changed = {
                 let mut changed = HashMap::<i32, HashSet<Field>>::new();
                 for (id, update) in updates {
                     debug!("setting id {} to {:?}", id, update);
-                    match db_write.update(id, update) {
+                    let has_datapoint = update.datapoint.is_some();
+                    stopwatch.lap(Stage::Validate);
+                    let checked = db_write.check_update(id, update);
+                    stopwatch.lap(Stage::Validate);
+                    let applied = checked.and_then(|update| db_write.apply(id, update));
+                    stopwatch.lap(Stage::Store);
+                    match applied {
                         Ok(changed_fields) => {
+                            if has_datapoint {
+                                updated.push(id);
+                                // Dropped as unchanged, see Entry::diff
+                                if !changed_fields.contains(&Field::Datapoint) {
+                                    suppressed += 1;
+                                }
+                            }
                             if !changed_fields.is_empty() {
                                 changed.insert(id, changed_fields);
                             }
@@ -1621,18 +3331,28 @@ impl AuthorizedAccess<'_, '_> {
                 }
                 changed
             };
+            db.track_target_deadlines(&changed, self.broker.clock.elapsed());
+            db.count_updates(&updated, self.broker.clock.elapsed());
+            self.broker
+                .suppressed_updates
+                .fetch_add(suppressed, Ordering::Relaxed);
+            db.record_history(&changed);
+            events = db.changed_events(&changed);
             // Downgrade to reader (to allow other readers) while holding on
             // to a read lock in order to ensure a consistent state while
             // notifying subscribers (no writes in between)
             let db = db.downgrade();
 
+            self.broker.publish_changes(&changed, &db);
+            stopwatch.lap(Stage::Store);
+
             // Notify
             match self
                 .broker
                 .subscriptions
                 .read()
                 .await
-                .notify(Some(&changed), &db)
+                .notify(Some(&changed), &db, &mut stopwatch)
                 .await
             {
                 Ok(None) => false,
@@ -1652,6 +3372,11 @@ impl AuthorizedAccess<'_, '_> {
             }
         }
 
+        // Events are only kept until the subscribers got them
+        if !events.is_empty() {
+            self.broker.database.write().await.expire_events(&events);
+        }
+
         // Cleanup closed subscriptions
         if cleanup_needed {
             self.broker.subscriptions.write().await.cleanup();
@@ -1670,6 +3395,36 @@ impl AuthorizedAccess<'_, '_> {
         &self,
         valid_entries: HashMap<i32, HashSet<Field>>,
         buffer_size: Option<usize>,
+    ) -> Result<impl Stream<Item = EntryUpdates>, SubscriptionError> {
+        self.subscribe_with_snapshot(valid_entries, buffer_size, InitialSnapshot::Send)
+            .await
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but `initial_snapshot` decides
+    /// whether the current state is sent first.
+    pub async fn subscribe_with_snapshot(
+        &self,
+        valid_entries: HashMap<i32, HashSet<Field>>,
+        buffer_size: Option<usize>,
+        initial_snapshot: InitialSnapshot,
+    ) -> Result<impl Stream<Item = EntryUpdates>, SubscriptionError> {
+        self.subscribe_with_filter(
+            valid_entries,
+            buffer_size,
+            initial_snapshot,
+            SubscriptionFilter::default(),
+        )
+        .await
+    }
+
+    /// Like [`subscribe_with_snapshot`](Self::subscribe_with_snapshot), but
+    /// the values sent are limited by `filter`.
+    pub async fn subscribe_with_filter(
+        &self,
+        valid_entries: HashMap<i32, HashSet<Field>>,
+        buffer_size: Option<usize>,
+        initial_snapshot: InitialSnapshot,
+        filter: SubscriptionFilter,
     ) -> Result<impl Stream<Item = EntryUpdates>, SubscriptionError> {
         if valid_entries.is_empty() {
             return Err(SubscriptionError::InvalidInput);
@@ -1687,42 +3442,188 @@ impl AuthorizedAccess<'_, '_> {
         };
 
         let (sender, receiver) = broadcast::channel(channel_capacity);
+        let backfill_ids: Vec<i32> = valid_entries
+            .iter()
+            .filter(|(_, fields)| fields.contains(&Field::Datapoint))
+            .map(|(id, _)| *id)
+            .collect();
+        let now = self.broker.clock.elapsed();
+        let mut budgets = Vec::new();
+        if let Some(rate) = filter.max_bytes_per_sec {
+            budgets.push(Arc::new(Mutex::new(RateLimit::new(rate, now))));
+        }
+        if let Some(rate) = self.broker.quotas.max_subscriber_bytes_per_sec {
+            let client = self.permissions.subject().map(str::to_owned);
+            budgets.push(
+                self.broker
+                    .subscriber_budgets
+                    .lock()
+                    .expect("subscriber budgets should not be poisoned")
+                    .entry(client)
+                    .or_insert_with(|| Arc::new(Mutex::new(RateLimit::new(rate, now))))
+                    .clone(),
+            );
+        }
+        let dispatch_budget = self.broker.quotas.max_dispatch_bytes_per_sec.map(|rate| {
+            self.broker
+                .dispatch_budget
+                .lock()
+                .expect("dispatch budget should not be poisoned")
+                .get_or_insert_with(|| Arc::new(Mutex::new(RateLimit::new(rate, now))))
+                .clone()
+        });
         let subscription = ChangeSubscription {
             entries: valid_entries,
             sender,
             permissions: self.permissions.clone(),
+            filter,
+            filter_state: Mutex::new(FilterState::default()),
+            budgets,
+            dispatch_budget,
+            priority: self.permissions.subscription_priority(filter.priority),
+            counters: self.broker.accounting.client(self.permissions.subject()),
+            clock: self.broker.clock(),
         };
 
-        {
+        if initial_snapshot == InitialSnapshot::Send {
             // Send everything subscribed to in an initial notification
             let db = self.broker.database.read().await;
-            if subscription.notify(None, &db).await.is_err() {
+            if subscription
+                .notify(None, &db, &mut Stopwatch::default())
+                .await
+                .is_err()
+            {
                 warn!("Failed to create initial notification");
             }
         }
 
-        self.broker
-            .subscriptions
-            .write()
+        if !self
+            .add_subscription(|subscriptions| subscriptions.add_change_subscription(subscription))
             .await
-            .add_change_subscription(subscription);
+        {
+            return Err(SubscriptionError::QuotaExceeded);
+        }
+
+        // The backfill is read after subscribing, so that no value is lost
+        // in between. Values that also went to the subscription are only
+        // sent once, as part of the backfill.
+        let backfill = match filter.backfill_since {
+            Some(since) => self.backfill(&backfill_ids, since).await,
+            None => Vec::new(),
+        };
+        let mut backfilled: HashMap<i32, SystemTime> = HashMap::new();
+        for notification in backfill.iter().flat_map(|updates| &updates.updates) {
+            if let Some(datapoint) = &notification.update.datapoint {
+                backfilled.insert(notification.id, datapoint.ts);
+            }
+        }
 
+        let latency = self.broker.latency.clone();
+        let lagged_notifications = self.broker.lagged_notifications.clone();
         let stream = BroadcastStream::new(receiver).filter_map(move |result| match result {
-            Ok(message) => Some(message),
+            Ok(message) => {
+                if let (Some(trace), Some(latency)) = (message.trace, &latency) {
+                    trace.record(latency);
+                }
+                Some(message)
+            }
             Err(err) => {
                 warn!(
                     "Slow subscriber with capacity {} lagged and missed signal updates: {}",
                     channel_capacity, err
                 );
+                let BroadcastStreamRecvError::Lagged(missed) = err;
+                lagged_notifications.fetch_add(missed, Ordering::Relaxed);
                 None
             }
         });
+        let stream = stream.filter_map(move |mut message: EntryUpdates| {
+            if backfilled.is_empty() {
+                return Some(message);
+            }
+            for notification in &mut message.updates {
+                let sent = match (
+                    &notification.update.datapoint,
+                    backfilled.get(&notification.id),
+                ) {
+                    (Some(datapoint), Some(ts)) => datapoint.ts <= *ts,
+                    _ => false,
+                };
+                if sent {
+                    notification.update.datapoint = None;
+                    notification.fields.remove(&Field::Datapoint);
+                }
+            }
+            message
+                .updates
+                .retain(|notification| !notification.fields.is_empty());
+            (!message.updates.is_empty()).then_some(message)
+        });
+        let stream = tokio_stream::iter(backfill).chain(stream);
+        let end: std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + Sync>> =
+            match filter.duration {
+                Some(duration) => Box::pin(tokio::time::sleep(duration)),
+                None => Box::pin(futures::future::pending()),
+            };
+        let stream = futures::StreamExt::take_until(stream, end);
+        #[cfg(feature = "chaos")]
+        let stream = self.broker.chaos.wrap_stream(stream);
         Ok(stream)
     }
 
+    /// The values kept in history after `since` of the entries `ids`, one
+    /// notification per value, ordered by timestamp.
+    async fn backfill(&self, ids: &[i32], since: SystemTime) -> Vec<EntryUpdates> {
+        let db = self.broker.database.read().await;
+        let db_read = db.authorized_read_access(self.permissions);
+        let mut samples = Vec::new();
+        for id in ids {
+            let Ok(entry) = db_read.get_entry_by_id(*id) else {
+                continue;
+            };
+            samples.extend(
+                db.history
+                    .samples(*id, Some(since))
+                    .into_iter()
+                    .filter(|datapoint| datapoint.ts > since)
+                    .map(|datapoint| (*id, entry.metadata.path.clone(), datapoint)),
+            );
+        }
+        // Stable, i.e. the values of an entry stay in order
+        samples.sort_by_key(|(_, _, datapoint)| datapoint.ts);
+        samples
+            .into_iter()
+            .map(|(id, path, datapoint)| EntryUpdates {
+                updates: vec![ChangeNotification {
+                    id,
+                    update: EntryUpdate {
+                        path: Some(path),
+                        datapoint: Some(datapoint),
+                        ..Default::default()
+                    },
+                    fields: HashSet::from([Field::Datapoint]),
+                }],
+                reasons: vec![UpdateReason::Backfill],
+                shaped: false,
+                trace: None,
+            })
+            .collect()
+    }
+
     pub async fn subscribe_query(
         &self,
         query: &str,
+    ) -> Result<impl Stream<Item = QueryResponse>, QueryError> {
+        self.subscribe_query_with_snapshot(query, InitialSnapshot::Send)
+            .await
+    }
+
+    /// Like [`subscribe_query`](Self::subscribe_query), but
+    /// `initial_snapshot` decides whether the query is executed right away.
+    pub async fn subscribe_query_with_snapshot(
+        &self,
+        query: &str,
+        initial_snapshot: InitialSnapshot,
     ) -> Result<impl Stream<Item = QueryResponse>, QueryError> {
         let db_read = self.broker.database.read().await;
         let db_read_access = db_read.authorized_read_access(self.permissions);
@@ -1740,17 +3641,23 @@ impl AuthorizedAccess<'_, '_> {
                 };
 
                 // Send the initial execution of query
-                match subscription.notify(None, &db_read).await {
-                    Ok(_) => self
-                        .broker
-                        .subscriptions
-                        .write()
-                        .await
-                        .add_query_subscription(subscription),
-                    Err(_) => return Err(QueryError::InternalError),
-                };
+                if initial_snapshot == InitialSnapshot::Send
+                    && subscription.notify(None, &db_read).await.is_err()
+                {
+                    return Err(QueryError::InternalError);
+                }
+                if !self
+                    .add_subscription(|subscriptions| {
+                        subscriptions.add_query_subscription(subscription)
+                    })
+                    .await
+                {
+                    return Err(QueryError::QuotaExceeded);
+                }
 
                 let stream = ReceiverStream::new(receiver);
+                #[cfg(feature = "chaos")]
+                let stream = self.broker.chaos.wrap_stream(stream);
                 Ok(stream)
             }
             Err(e) => Err(QueryError::CompilationError(format!("{e:?}"))),
@@ -1801,6 +3708,29 @@ impl AuthorizedAccess<'_, '_> {
         Ok(())
     }
 
+    /// Handle the actuation of `vss_ids` with `actuation_provider` while no
+    /// provider claimed them, see [`crate::simulator`].
+    pub async fn simulate_actuation(
+        &self,
+        vss_ids: Vec<i32>,
+        actuation_provider: Box<dyn ActuationProvider + Send + Sync + 'static>,
+    ) -> Result<(), (ActuationError, String)> {
+        for vss_id in &vss_ids {
+            self.can_write_actuator_target(vss_id).await?;
+        }
+        self.broker
+            .subscriptions
+            .write()
+            .await
+            .simulated_actuations
+            .push(ActuationSubscription {
+                vss_ids,
+                actuation_provider,
+                permissions: self.permissions.clone(),
+            });
+        Ok(())
+    }
+
     async fn map_actuation_changes_by_vss_id(
         &self,
         actuation_changes: Vec<ActuationChange>,
@@ -1824,19 +3754,43 @@ impl AuthorizedAccess<'_, '_> {
         actuation_changes_per_vss_id
     }
 
+    /// Forward `actuation_changes` to the providers of the actuators.
+    /// Returns the ids the actuations are tracked with, in the order of
+    /// `actuation_changes`.
     pub async fn batch_actuate(
         &self,
-        actuation_changes: Vec<ActuationChange>,
-    ) -> Result<(), (ActuationError, String)> {
+        mut actuation_changes: Vec<ActuationChange>,
+    ) -> Result<Vec<u64>, (ActuationError, String)> {
         let read_subscription_guard = self.broker.subscriptions.read().await;
-        let actuation_subscriptions = &read_subscription_guard.actuation_subscriptions;
 
         for actuation_change in &actuation_changes {
             let vss_id = actuation_change.id;
             self.can_write_actuator_target(&vss_id).await?;
+            self.check_safety_gate(&vss_id).await?;
             self.validate_actuator_update(&vss_id, &actuation_change.data_value)
                 .await?;
+            self.check_policies(&vss_id).await?;
+        }
+
+        let now = self.broker.clock.elapsed();
+        for actuation_change in &mut actuation_changes {
+            actuation_change.actuation_id = self.broker.actuations.add(actuation_change.id, now);
         }
+        let actuation_ids: Vec<u64> = actuation_changes
+            .iter()
+            .map(|actuation_change| actuation_change.actuation_id)
+            .collect();
+        // The actuations not delivered yet fail along with the batch
+        let fail = |error: (ActuationError, String)| {
+            let pending = actuation_ids.iter().copied().filter(|id| {
+                self.broker
+                    .actuations
+                    .get(*id)
+                    .is_some_and(|actuation| actuation.status == ActuationStatus::Pending)
+            });
+            self.track_delivery(pending, Some(&error.1));
+            error
+        };
 
         let actuation_changes_per_vss_id = &self
             .map_actuation_changes_by_vss_id(actuation_changes)
@@ -1845,9 +3799,7 @@ impl AuthorizedAccess<'_, '_> {
             let vss_id = *actuation_change_per_vss_id.0;
             let actuation_changes = actuation_change_per_vss_id.1.clone();
 
-            let opt_actuation_subscription = actuation_subscriptions
-                .iter()
-                .find(|subscription| subscription.vss_ids.contains(&vss_id));
+            let opt_actuation_subscription = read_subscription_guard.actuation_subscription(vss_id);
             match opt_actuation_subscription {
                 Some(actuation_subscription) => {
                     let is_expired = actuation_subscription.permissions.is_expired();
@@ -1856,45 +3808,56 @@ impl AuthorizedAccess<'_, '_> {
                             "Permission for vss_ids {:?} expired",
                             actuation_subscription.vss_ids
                         );
-                        return Err((ActuationError::PermissionExpired, message));
+                        return Err(fail((ActuationError::PermissionExpired, message)));
                     }
 
                     if !actuation_subscription.actuation_provider.is_available() {
                         let message = format!("Provider for vss_id {} does not exist", vss_id);
-                        return Err((ActuationError::ProviderNotAvailable, message));
+                        return Err(fail((ActuationError::ProviderNotAvailable, message)));
                     }
 
+                    let delivered: Vec<u64> = actuation_changes
+                        .iter()
+                        .map(|actuation_change| actuation_change.actuation_id)
+                        .collect();
                     actuation_subscription
                         .actuation_provider
                         .actuate(actuation_changes)
-                        .await?
+                        .await
+                        .map_err(fail)?;
+                    self.track_delivery(delivered, None);
                 }
                 None => {
                     let message = format!("Provider for vss_id {} not available", vss_id);
-                    return Err((ActuationError::ProviderNotAvailable, message));
+                    return Err(fail((ActuationError::ProviderNotAvailable, message)));
                 }
             }
         }
 
-        Ok(())
+        Ok(actuation_ids)
     }
 
+    /// Forward the actuation of `vss_id` to its provider. Returns the id
+    /// the actuation is tracked with.
     pub async fn actuate(
         &self,
         vss_id: &i32,
         data_value: &DataValue,
-    ) -> Result<(), (ActuationError, String)> {
+    ) -> Result<u64, (ActuationError, String)> {
         let vss_id = *vss_id;
 
         self.can_write_actuator_target(&vss_id).await?;
+        self.check_safety_gate(&vss_id).await?;
         self.validate_actuator_update(&vss_id, data_value).await?;
+        self.check_policies(&vss_id).await?;
 
+        let actuation_id = self
+            .broker
+            .actuations
+            .add(vss_id, self.broker.clock.elapsed());
         let read_subscription_guard = self.broker.subscriptions.read().await;
-        let opt_actuation_subscription = &read_subscription_guard
-            .actuation_subscriptions
-            .iter()
-            .find(|subscription| subscription.vss_ids.contains(&vss_id));
-        match opt_actuation_subscription {
+        let opt_actuation_subscription = read_subscription_guard.actuation_subscription(vss_id);
+        let result = match opt_actuation_subscription {
             Some(actuation_subscription) => {
                 let is_expired = actuation_subscription.permissions.is_expired();
                 if is_expired {
@@ -1915,6 +3878,7 @@ impl AuthorizedAccess<'_, '_> {
                     .actuate(vec![ActuationChange {
                         id: vss_id,
                         data_value: data_value.clone(),
+                        actuation_id,
                     }])
                     .await
             }
@@ -1922,9 +3886,157 @@ impl AuthorizedAccess<'_, '_> {
                 let message = format!("Provider for vss_id {} does not exist", vss_id);
                 Err((ActuationError::ProviderNotAvailable, message))
             }
+        };
+        let failure = result.as_ref().err().map(|(_, message)| message.as_str());
+        self.track_delivery([actuation_id], failure);
+        result.map(|()| actuation_id)
+    }
+
+    /// Mark the actuations `ids` as delivered, or as failed with the
+    /// message of `failure` if forwarding them to their provider failed.
+    fn track_delivery(&self, ids: impl IntoIterator<Item = u64>, failure: Option<&str>) {
+        let now = self.broker.clock.elapsed();
+        let status = match failure {
+            Some(_) => ActuationStatus::Failed,
+            None => ActuationStatus::Delivered,
+        };
+        for id in ids {
+            self.broker
+                .actuations
+                .set_status(id, status, failure.map(str::to_owned), now);
+        }
+    }
+
+    /// The status of actuation `id`, if the actuator may be read.
+    pub async fn get_actuation(&self, id: u64) -> Result<ActuationInfo, ReadError> {
+        let actuation = self.broker.actuations.get(id).ok_or(ReadError::NotFound)?;
+        let entry = self.get_entry_by_id(actuation.vss_id).await?;
+        match self.permissions.can_read(&entry.metadata.path) {
+            Ok(()) => Ok(actuation),
+            Err(PermissionError::Denied) => Err(ReadError::PermissionDenied),
+            Err(PermissionError::Expired) => Err(ReadError::PermissionExpired),
         }
     }
 
+    /// Report the outcome of actuation `id` as its provider.
+    pub async fn report_actuation(
+        &self,
+        id: u64,
+        status: ActuationStatus,
+        message: Option<String>,
+    ) -> Result<(), (ActuationError, String)> {
+        let Some(actuation) = self.broker.actuations.get(id) else {
+            return Err((
+                ActuationError::NotFound,
+                format!("Unknown actuation id {id}"),
+            ));
+        };
+        self.can_write_actuator_target(&actuation.vss_id).await?;
+        self.broker
+            .actuations
+            .set_status(id, status, message, self.broker.clock.elapsed());
+        Ok(())
+    }
+
+    async fn check_safety_gate(&self, vss_id: &i32) -> Result<(), (ActuationError, String)> {
+        let db = self.broker.database.read().await;
+        let Some(entry) = db.entries.get(vss_id) else {
+            return Err((
+                ActuationError::NotFound,
+                format!("Could not resolve vss_path of vss_id {}", vss_id),
+            ));
+        };
+        match db.check_safety_gate(&entry.metadata, self.permissions) {
+            Ok(()) => Ok(()),
+            Err(PermissionError::Denied) => Err((
+                ActuationError::PermissionDenied,
+                format!(
+                    "Actuation of vss_path {} is restricted by its safety level",
+                    entry.metadata.path
+                ),
+            )),
+            Err(PermissionError::Expired) => Err((
+                ActuationError::PermissionExpired,
+                "Permission expired".to_string(),
+            )),
+        }
+    }
+
+    async fn check_policies(&self, vss_id: &i32) -> Result<(), (ActuationError, String)> {
+        let db = self.broker.database.read().await;
+        let Some(entry) = db.entries.get(vss_id) else {
+            return Err((
+                ActuationError::NotFound,
+                format!("Could not resolve vss_path of vss_id {}", vss_id),
+            ));
+        };
+        db.check_policies(&entry.metadata.path)
+            .map_err(|condition| {
+                (
+                    ActuationError::PolicyViolation,
+                    format!(
+                        "Actuation of vss_path {} denied while {}",
+                        entry.metadata.path, condition
+                    ),
+                )
+            })
+    }
+
+    /// Set the safety level of an entry, e.g. from a VSS overlay.
+    pub async fn set_safety_level(
+        &self,
+        id: i32,
+        safety_level: Option<SafetyLevel>,
+    ) -> Result<(), UpdateError> {
+        let mut db = self.broker.database.write().await;
+        let entry = db.entries.get_mut(&id).ok_or(UpdateError::NotFound)?;
+        // Same permission as registering the entry
+        self.permissions
+            .can_create(&entry.metadata.path)
+            .map_err(|err| match err {
+                PermissionError::Denied => UpdateError::PermissionDenied,
+                PermissionError::Expired => UpdateError::PermissionExpired,
+            })?;
+        entry.metadata.safety_level = safety_level;
+        Ok(())
+    }
+
+    /// Set the time the provider of an actuator has to confirm its target,
+    /// overriding the broker-wide default.
+    pub async fn set_target_ttl(
+        &self,
+        id: i32,
+        target_ttl: Option<Duration>,
+    ) -> Result<(), UpdateError> {
+        let mut db = self.broker.database.write().await;
+        let entry = db.entries.get_mut(&id).ok_or(UpdateError::NotFound)?;
+        // Same permission as registering the entry
+        self.permissions
+            .can_create(&entry.metadata.path)
+            .map_err(|err| match err {
+                PermissionError::Denied => UpdateError::PermissionDenied,
+                PermissionError::Expired => UpdateError::PermissionExpired,
+            })?;
+        entry.metadata.target_ttl = target_ttl;
+        Ok(())
+    }
+
+    /// Set the number of decimals float values of the entry are rounded to
+    /// when stored, None to store them as they are.
+    pub async fn set_precision(&self, id: i32, precision: Option<u32>) -> Result<(), UpdateError> {
+        let mut db = self.broker.database.write().await;
+        let entry = db.entries.get_mut(&id).ok_or(UpdateError::NotFound)?;
+        // Same permission as registering the entry
+        self.permissions
+            .can_create(&entry.metadata.path)
+            .map_err(|err| match err {
+                PermissionError::Denied => UpdateError::PermissionDenied,
+                PermissionError::Expired => UpdateError::PermissionExpired,
+            })?;
+        entry.metadata.precision = precision;
+        Ok(())
+    }
+
     async fn can_write_actuator_target(
         &self,
         vss_id: &i32,
@@ -2032,6 +4144,27 @@ impl AuthorizedAccess<'_, '_> {
                         ActuationError::PermissionExpired,
                         "Permission expired".to_string(),
                     )),
+                    Err(UpdateError::OutOfBoundsIndex) => {
+                        let message =
+                            format!("Array index out of bounds for vss_path {}", vss_path);
+                        Err((ActuationError::OutOfBounds, message))
+                    }
+                    Err(UpdateError::Conflict) => {
+                        let message = format!("Conflicting update for vss_path {}", vss_path);
+                        Err((ActuationError::TransmissionFailure, message))
+                    }
+                    Err(UpdateError::Implausible(reason)) => {
+                        let message =
+                            format!("Implausible value for vss_path {}: {}", vss_path, reason);
+                        Err((ActuationError::OutOfBounds, message))
+                    }
+                    Err(UpdateError::PolicyViolation(condition)) => {
+                        let message = format!(
+                            "Actuation of vss_path {} denied while {}",
+                            vss_path, condition
+                        );
+                        Err((ActuationError::PolicyViolation, message))
+                    }
                 }
             }
             Err(ReadError::NotFound) => {
@@ -2060,196 +4193,2150 @@ impl DataBroker {
             version: version.into(),
             commit_sha: commit_sha.into(),
             shutdown_trigger,
+            clock: clock::system_clock(),
+            case_insensitive_paths: false,
+            quotas: Default::default(),
+            deadlines: Default::default(),
+            provider_streams: Default::default(),
+            provider_ingress: Default::default(),
+            quota_rejections: Default::default(),
+            suppressed_updates: Default::default(),
+            lagged_notifications: Default::default(),
+            authorization_failures: Default::default(),
+            call_stats: Default::default(),
+            events: Default::default(),
+            served_apis: Default::default(),
+            api_usage: Default::default(),
+            provider_enrollment: None,
+            token_exchange: None,
+            subscriber_budgets: Default::default(),
+            dispatch_budget: Default::default(),
+            accounting: Default::default(),
+            sessions: Default::default(),
+            actuations: Default::default(),
+            log_filter: None,
+            latency: None,
+            signal_groups: Default::default(),
+            #[cfg(feature = "chaos")]
+            chaos: Default::default(),
         }
     }
 
-    #[cfg_attr(feature="otel", tracing::instrument(name="data_broker_authorized_access",skip(self, permissions), fields(timestamp=chrono::Utc::now().to_string())))]
-    pub fn authorized_access<'a, 'b>(
-        &'a self,
-        permissions: &'b Permissions,
-    ) -> AuthorizedAccess<'a, 'b> {
-        AuthorizedAccess {
-            broker: self,
-            permissions,
-        }
+    /// Use `clock` as the source of time, e.g. a [`clock::VirtualClock`]
+    /// to test time dependent behavior without waiting.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
-    pub fn start_housekeeping_task(&self) {
-        info!("Starting housekeeping task");
-        let subscriptions = self.subscriptions.clone();
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
 
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+    /// Resolve paths regardless of their case, e.g. `Vehicle.isMoving` to
+    /// `Vehicle.IsMoving`, unless several paths only differ in case. Must
+    /// be enabled before the broker is cloned.
+    pub fn with_case_insensitive_paths(mut self) -> Self {
+        self.case_insensitive_paths = true;
+        Arc::get_mut(&mut self.database)
+            .expect("case insensitive paths must be enabled before cloning the broker")
+            .get_mut()
+            .case_insensitive_paths = true;
+        self
+    }
 
-            loop {
-                interval.tick().await;
+    pub fn case_insensitive_paths(&self) -> bool {
+        self.case_insensitive_paths
+    }
 
-                subscriptions.write().await.cleanup(); // Cleanup dropped subscriptions
-            }
-        });
+    /// Limit the resources clients can use. Must be set before the broker
+    /// is cloned.
+    pub fn with_quotas(mut self, quotas: Quotas) -> Self {
+        Arc::get_mut(&mut self.database)
+            .expect("quotas must be set before cloning the broker")
+            .get_mut()
+            .max_entries = quotas.max_entries;
+        self.quotas = quotas;
+        self
     }
 
-    pub async fn shutdown(&self) {
-        // Drain subscriptions
-        let mut subscriptions = self.subscriptions.write().await;
-        subscriptions.clear();
+    pub fn with_deadlines(mut self, deadlines: Deadlines) -> Self {
+        self.deadlines = deadlines;
+        self
+    }
 
-        // Signal shutdown
-        let _ = self.shutdown_trigger.send(());
+    pub fn deadlines(&self) -> &Deadlines {
+        &self.deadlines
     }
 
-    pub fn get_shutdown_trigger(&self) -> broadcast::Receiver<()> {
-        self.shutdown_trigger.subscribe()
+    /// Let administrators change the log filter at runtime.
+    pub fn with_log_filter(mut self, log_filter: Arc<LogFilter>) -> Self {
+        self.log_filter = Some(log_filter);
+        self
     }
 
-    pub fn get_version(&self) -> &str {
-        &self.version
+    pub fn log_filter(&self) -> Option<&Arc<LogFilter>> {
+        self.log_filter.as_ref()
     }
 
-    pub fn get_commit_sha(&self) -> &str {
-        &self.commit_sha
+    /// Restrict the actuation of entries above the safety level of `gate`.
+    /// Must be set before the broker is cloned.
+    pub fn with_safety_gate(mut self, gate: SafetyGate) -> Self {
+        Arc::get_mut(&mut self.database)
+            .expect("the safety gate must be set before cloning the broker")
+            .get_mut()
+            .safety_gate = Some(gate);
+        self
     }
-}
 
-impl Default for DataBroker {
-    fn default() -> Self {
-        Self::new("", "")
+    /// Check new values with `validators` before accepting them.
+    /// Must be set before the broker is cloned.
+    pub fn with_validators(mut self, validators: Validators) -> Self {
+        Arc::get_mut(&mut self.database)
+            .expect("the validators must be set before cloning the broker")
+            .get_mut()
+            .validators = validators;
+        self
     }
-}
 
-#[cfg(test)]
-/// Public test module to allow other files to reuse helper functions
-pub mod tests {
-    use crate::permissions;
+    /// Keep the past values of entries as `policies` say.
+    /// Must be set before the broker is cloned.
+    pub fn with_history(mut self, policies: RetentionPolicies) -> Self {
+        Arc::get_mut(&mut self.database)
+            .expect("the history must be set before cloning the broker")
+            .get_mut()
+            .history = History::new(policies);
+        self
+    }
 
-    use super::*;
-    use tokio_stream::StreamExt;
+    /// Deny actuation requests as `policies` say.
+    /// Must be set before the broker is cloned.
+    pub fn with_policies(mut self, policies: Policies) -> Self {
+        Arc::get_mut(&mut self.database)
+            .expect("the policies must be set before cloning the broker")
+            .get_mut()
+            .policies = policies;
+        self
+    }
 
-    #[tokio::test]
-    async fn test_databroker_version_and_commit_sha() {
-        let version = "1.1.1";
-        let commit_sha = "3a3c332f5427f2db7a0b8582262c9f5089036c23";
-        let databroker = DataBroker::new(version, commit_sha);
-        assert_eq!(databroker.get_version(), version);
-        assert_eq!(databroker.get_commit_sha(), commit_sha);
+    /// Clear actuator targets that the provider doesn't confirm within
+    /// `ttl`, unless the entry has a TTL of its own.
+    /// Must be set before the broker is cloned.
+    pub fn with_target_ttl(mut self, ttl: Duration) -> Self {
+        Arc::get_mut(&mut self.database)
+            .expect("the target TTL must be set before cloning the broker")
+            .get_mut()
+            .target_ttl = Some(ttl);
+        self
     }
 
-    #[tokio::test]
-    async fn test_register_datapoint() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+    pub async fn get_usage(&self) -> Usage {
+        let subscriptions = self.subscriptions.read().await;
+        let provider_stats = self.get_provider_stats();
+        Usage {
+            entries: self.database.read().await.entries.len(),
+            subscriptions: subscriptions.count(|_| true),
+            provider_streams: self
+                .provider_streams
+                .lock()
+                .expect("provider stream counts should not be poisoned")
+                .values()
+                .sum(),
+            quota_rejections: self.quota_rejections.load(Ordering::Relaxed),
+            removed_subscriptions: subscriptions.removed,
+            ingress_updates: provider_stats.values().map(|stats| stats.updates).sum(),
+            ingress_bytes: provider_stats.values().map(|stats| stats.bytes).sum(),
+            throttled_updates: provider_stats.values().map(|stats| stats.throttled).sum(),
+            rejected_updates: provider_stats.values().map(|stats| stats.rejected).sum(),
+            suppressed_updates: self.suppressed_updates.load(Ordering::Relaxed),
+            deprecated_api_calls: self
+                .api_usage
+                .lock()
+                .expect("api usage should not be poisoned")
+                .iter()
+                .filter(|usage| usage.deprecated)
+                .map(|usage| usage.calls)
+                .sum(),
+            lagged_notifications: self.lagged_notifications.load(Ordering::Relaxed),
+            authorization_failures: self.authorization_failures.load(Ordering::Relaxed),
+        }
+    }
 
-        let id1 = broker
-            .add_entry(
-                "test.datapoint1".to_owned(),
-                DataType::Bool,
-                ChangeType::OnChange,
-                EntryType::Sensor,
-                "Test datapoint 1".to_owned(),
-                None, // min
-                None, // max
-                Some(DataValue::BoolArray(Vec::from([true]))),
-                Some("kg".to_string()),
-            )
-            .await
-            .expect("Register datapoint should succeed");
+    /// Count a call rejected for its access token.
+    pub fn record_authorization_failure(&self) {
+        self.authorization_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The calls to the gRPC APIs per method.
+    pub fn call_stats(&self) -> &Arc<CallStats> {
+        &self.call_stats
+    }
 
+    /// Count a call of `client` to `api`. Returns the number of calls of
+    /// the client to the API so far.
+    pub fn record_api_call(&self, api: &str, deprecated: bool, client: Option<&str>) -> u64 {
+        self.accounting.client(client).record_request();
+        let mut api_usage = self
+            .api_usage
+            .lock()
+            .expect("api usage should not be poisoned");
+        match api_usage
+            .iter_mut()
+            .find(|usage| usage.api == api && usage.client.as_deref() == client)
         {
-            match broker.get_entry_by_id(id1).await {
-                Ok(entry) => {
-                    assert_eq!(entry.metadata.id, id1);
-                    assert_eq!(entry.metadata.path, "test.datapoint1");
-                    assert_eq!(entry.metadata.data_type, DataType::Bool);
-                    assert_eq!(entry.metadata.description, "Test datapoint 1");
-                    assert_eq!(
-                        entry.metadata.allowed,
-                        Some(DataValue::BoolArray(Vec::from([true])))
-                    );
-                    assert_eq!(entry.metadata.unit, Some("kg".to_string()));
-                }
-                Err(_) => {
-                    panic!("datapoint should exist");
-                }
+            Some(usage) => {
+                usage.calls += 1;
+                usage.calls
+            }
+            None => {
+                api_usage.push(ApiUsage {
+                    api: api.to_owned(),
+                    client: client.map(str::to_owned),
+                    deprecated,
+                    calls: 1,
+                });
+                1
             }
         }
+    }
 
-        let id2 = broker
-            .add_entry(
-                "test.datapoint2".to_owned(),
-                DataType::String,
-                ChangeType::OnChange,
-                EntryType::Sensor,
-                "Test datapoint 2".to_owned(),
-                None, // min
-                None, // max
-                None,
-                Some("km".to_string()),
-            )
-            .await
-            .expect("Register datapoint should succeed");
+    /// The calls to each API per client, ordered by API and client.
+    pub fn get_api_usage(&self) -> Vec<ApiUsage> {
+        let mut api_usage: Vec<ApiUsage> = self
+            .api_usage
+            .lock()
+            .expect("api usage should not be poisoned")
+            .clone();
+        api_usage.sort_by(|a, b| (&a.api, &a.client).cmp(&(&b.api, &b.client)));
+        api_usage
+    }
+
+    /// The requests, subscriptions and streamed bytes of each client,
+    /// ordered by client.
+    pub async fn get_client_usage(&self) -> Vec<ClientUsage> {
+        let subscriptions = self.subscriptions.read().await.count_by_client();
+        self.accounting.usage(&subscriptions)
+    }
+
+    /// The registry of the connections to the gRPC APIs.
+    pub fn sessions(&self) -> &Arc<Sessions> {
+        &self.sessions
+    }
+
+    /// The open sessions with their number of subscriptions, ordered by id.
+    pub async fn get_sessions(&self) -> Vec<SessionInfo> {
+        let subscriptions = self.subscriptions.read().await.count_by_session();
+        self.sessions.list(&subscriptions)
+    }
+
+    /// All entries with their values, targets and providers, and all
+    /// subscriptions.
+    pub async fn get_state(&self) -> BrokerState {
+        let db = self.database.read().await;
+        let subscriptions = self.subscriptions.read().await;
+
+        let providers = |id: i32| {
+            let real = subscriptions
+                .actuation_subscriptions
+                .iter()
+                .map(|subscription| (subscription, false));
+            let simulated = subscriptions
+                .simulated_actuations
+                .iter()
+                .map(|subscription| (subscription, true));
+            real.chain(simulated)
+                .filter(|(subscription, _)| subscription.vss_ids.contains(&id))
+                .map(|(subscription, simulated)| ProviderState {
+                    client: subscription.permissions.subject().map(str::to_owned),
+                    session: subscription.permissions.session(),
+                    available: subscription.actuation_provider.is_available(),
+                    simulated,
+                })
+                .collect()
+        };
+        let mut entries: Vec<EntryState> = db
+            .entries
+            .values()
+            .map(|entry| EntryState {
+                metadata: entry.metadata.clone(),
+                datapoint: entry.datapoint.clone(),
+                actuator_target: entry.actuator_target.clone(),
+                providers: providers(entry.metadata.id),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.metadata.path.cmp(&b.metadata.path));
+
+        let path = |id: &i32| db.entries.get(id).map(|entry| entry.metadata.path.clone());
+        let changes = subscriptions
+            .change_subscriptions
+            .iter()
+            .map(|subscription| SubscriptionState {
+                kind: SubscriptionKind::Change,
+                client: subscription.permissions.subject().map(str::to_owned),
+                session: subscription.permissions.session(),
+                paths: subscription.entries.keys().filter_map(path).collect(),
+                active: subscription.sender.receiver_count() > 0,
+            });
+        let queries = subscriptions
+            .query_subscriptions
+            .iter()
+            .map(|subscription| SubscriptionState {
+                kind: SubscriptionKind::Query,
+                client: subscription.permissions.subject().map(str::to_owned),
+                session: subscription.permissions.session(),
+                paths: subscription.query.input_spec.iter().cloned().collect(),
+                active: !subscription.sender.is_closed(),
+            });
+        let subscriptions = changes
+            .chain(queries)
+            .map(|mut subscription| {
+                subscription.paths.sort();
+                subscription
+            })
+            .collect();
+
+        BrokerState {
+            entries,
+            subscriptions,
+        }
+    }
+
+    /// Update statistics of the signals, ordered by path. With `depth`, the
+    /// signals are grouped by the branch of that depth, e.g. `Vehicle.Cabin`
+    /// for a depth of 2, to bound the number of results.
+    pub async fn get_update_stats(&self, depth: Option<usize>) -> Vec<SignalStats> {
+        let db = self.database.read().await;
+        let subscriptions = self.subscriptions.read().await;
+        let now = self.clock.elapsed();
+
+        // The subscriptions (by index) including each entry
+        let mut subscribers: HashMap<i32, Vec<usize>> = HashMap::new();
+        for (index, subscription) in subscriptions.change_subscriptions.iter().enumerate() {
+            for id in subscription.entries.keys() {
+                subscribers.entry(*id).or_default().push(index);
+            }
+        }
+        let offset = subscriptions.change_subscriptions.len();
+        for (index, subscription) in subscriptions.query_subscriptions.iter().enumerate() {
+            for path in &subscription.query.input_spec {
+                if let Some(id) = db.path_to_id.get(path) {
+                    subscribers.entry(*id).or_default().push(offset + index);
+                }
+            }
+        }
+
+        let mut groups: BTreeMap<String, (SignalStats, HashSet<usize>)> = BTreeMap::new();
+        for entry in db.entries.values() {
+            let path = &entry.metadata.path;
+            let group = match depth {
+                Some(depth) => path.split('.').take(depth).collect::<Vec<_>>().join("."),
+                None => path.clone(),
+            };
+            let (stats, group_subscribers) = groups.entry(group.clone()).or_insert_with(|| {
+                (
+                    SignalStats {
+                        path: group,
+                        signals: 0,
+                        updates: 0,
+                        updates_per_second: 0.0,
+                        last_update_age: None,
+                        subscribers: 0,
+                    },
+                    HashSet::new(),
+                )
+            });
+            stats.signals += 1;
+            if let Some(update_stats) = db.update_stats.get(&entry.metadata.id) {
+                stats.updates += update_stats.updates;
+                stats.updates_per_second += update_stats.rate(now);
+                let age = update_stats
+                    .last_update
+                    .map(|last_update| now.saturating_sub(last_update));
+                stats.last_update_age = match (stats.last_update_age, age) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            if let Some(indexes) = subscribers.get(&entry.metadata.id) {
+                group_subscribers.extend(indexes);
+            }
+        }
+        groups
+            .into_values()
+            .map(|(mut stats, group_subscribers)| {
+                stats.subscribers = group_subscribers.len();
+                stats
+            })
+            .collect()
+    }
+
+    /// Ingress statistics of each provider, identified by the subject of
+    /// its access token.
+    pub fn get_provider_stats(&self) -> HashMap<Option<String>, ProviderStats> {
+        let now = self.clock.elapsed();
+        self.provider_ingress
+            .lock()
+            .expect("provider statistics should not be poisoned")
+            .iter_mut()
+            .map(|(client, ingress)| {
+                ingress.roll_window(now);
+                (client.clone(), ingress.stats.clone())
+            })
+            .collect()
+    }
+
+    fn reject_quota_exceeded(&self, permissions: &Permissions, resource: &str) {
+        self.quota_rejections.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            "Client {} exceeded the quota of {}",
+            permissions.subject().unwrap_or("<anonymous>"),
+            resource
+        );
+    }
+
+    /// A matcher for `pattern`, ignoring case if the broker does.
+    pub fn path_matcher(&self, pattern: &str) -> Result<glob::Matcher, glob::MatchError> {
+        let matcher = glob::Matcher::new(pattern)?;
+        if self.case_insensitive_paths {
+            Ok(matcher.ignore_case())
+        } else {
+            Ok(matcher)
+        }
+    }
+
+    /// Inject the faults described by `config` into writes and
+    /// subscriptions. Only meant for testing client robustness.
+    /// Require provider streams to enroll with the credentials in
+    /// `enrollment` before providing anything.
+    pub fn with_provider_enrollment(mut self, enrollment: ProviderEnrollment) -> Self {
+        self.provider_enrollment = Some(Arc::new(enrollment));
+        self
+    }
+
+    pub fn provider_enrollment(&self) -> Option<Arc<ProviderEnrollment>> {
+        self.provider_enrollment.clone()
+    }
+
+    /// Let clients exchange their access token for narrower ones minted by
+    /// `token_exchange`.
+    pub fn with_token_exchange(mut self, token_exchange: TokenExchange) -> Self {
+        self.token_exchange = Some(Arc::new(token_exchange));
+        self
+    }
+
+    pub fn token_exchange(&self) -> Option<Arc<TokenExchange>> {
+        self.token_exchange.clone()
+    }
+
+    /// Measure the latency of updates through each stage of the broker.
+    pub fn with_latency_stats(mut self) -> Self {
+        self.latency = Some(Arc::default());
+        self
+    }
+
+    pub fn latency_stats(&self) -> Option<Arc<LatencyStats>> {
+        self.latency.clone()
+    }
+
+    /// Start with `groups` defined. Must be set before the broker is
+    /// cloned.
+    pub fn with_signal_groups(mut self, groups: SignalGroups) -> Self {
+        self.signal_groups = Arc::new(Mutex::new(groups));
+        self
+    }
+
+    /// The paths of the signals of group `name`, None if there is no such
+    /// group.
+    pub fn signal_group(&self, name: &str) -> Option<Vec<String>> {
+        self.signal_groups
+            .lock()
+            .expect("signal groups should not be poisoned")
+            .get(name)
+            .cloned()
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, config: ChaosConfig) -> Self {
+        self.chaos = Arc::new(Chaos::new(config));
+        self
+    }
+
+    #[cfg_attr(feature="otel", tracing::instrument(name="data_broker_authorized_access",skip(self, permissions), fields(timestamp=chrono::Utc::now().to_string())))]
+    pub fn authorized_access<'a, 'b>(
+        &'a self,
+        permissions: &'b Permissions,
+    ) -> AuthorizedAccess<'a, 'b> {
+        AuthorizedAccess {
+            broker: self,
+            permissions,
+        }
+    }
+
+    pub fn start_housekeeping_task(&self) {
+        info!("Starting housekeeping task");
+        let broker = self.clone();
+        let subscriptions = self.subscriptions.clone();
+        let database = self.database.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+            let mut ticks: u64 = 0;
+
+            loop {
+                interval.tick().await;
+                ticks += 1;
+
+                broker.expire_targets().await;
+                broker.expire_history().await;
+                broker.expire_actuations();
+
+                {
+                    // Same lock order as when updating entries
+                    let db = database.read().await;
+                    subscriptions.read().await.notify_held(&db).await;
+                }
+
+                let mut subscriptions = subscriptions.write().await;
+                subscriptions.cleanup(); // Cleanup dropped subscriptions
+                if ticks.is_multiple_of(COMPACTION_INTERVAL_SECS) {
+                    subscriptions.compact();
+                }
+            }
+        });
+    }
+
+    pub async fn shutdown(&self) {
+        // Drain subscriptions
+        let mut subscriptions = self.subscriptions.write().await;
+        subscriptions.clear();
+
+        // Signal shutdown
+        let _ = self.shutdown_trigger.send(());
+    }
+
+    /// Register paths with the given ids instead of assigning new ones,
+    /// e.g. to keep the ids of a previous run. New ids are assigned above
+    /// the reserved ones.
+    pub async fn reserve_ids(&self, ids: impl IntoIterator<Item = (String, i32)>) {
+        let mut db = self.database.write().await;
+        for (path, id) in ids {
+            db.next_id.fetch_max(id.saturating_add(1), Ordering::SeqCst);
+            db.reserved_ids.insert(path, id);
+        }
+    }
+
+    /// The ids of all registered paths.
+    pub async fn get_ids(&self) -> HashMap<String, i32> {
+        self.database.read().await.path_to_id.clone()
+    }
+
+    /// The ids of the actuators claimed by a connected provider.
+    pub async fn get_provided_actuators(&self) -> Vec<i32> {
+        self.subscriptions
+            .read()
+            .await
+            .actuation_subscriptions
+            .iter()
+            .filter(|subscription| subscription.actuation_provider.is_available())
+            .flat_map(|subscription| subscription.vss_ids.iter().copied())
+            .collect()
+    }
+
+    pub fn get_shutdown_trigger(&self) -> broadcast::Receiver<()> {
+        self.shutdown_trigger.subscribe()
+    }
+
+    /// Listen to the events of the broker, see [`crate::events`].
+    pub fn subscribe_events(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Expire the actuations whose providers didn't report an outcome in
+    /// time, see [`crate::actuations`].
+    pub fn expire_actuations(&self) {
+        self.actuations.expire(self.clock.elapsed());
+    }
+
+    /// Drop the past values that are out of their retention.
+    pub async fn expire_history(&self) {
+        let mut db = self.database.write().await;
+        if db.history.is_enabled() {
+            db.history.expire(self.clock.now());
+        }
+    }
+
+    /// Clear the actuator targets that their providers didn't confirm in
+    /// time and notify the subscribers of the targets.
+    pub async fn expire_targets(&self) {
+        let mut db = self.database.write().await;
+        let expired = db.expire_targets(self.clock.elapsed());
+        if expired.is_empty() {
+            return;
+        }
+        let db = db.downgrade();
+
+        self.publish_changes(&expired, &db);
+        for id in expired.keys() {
+            if let Some(entry) = db.entries.get(id) {
+                self.events.publish(Event::TargetExpired {
+                    id: *id,
+                    path: entry.metadata.path.clone(),
+                });
+            }
+        }
+        // Closed subscriptions are cleaned up by the housekeeping task
+        let _ = self
+            .subscriptions
+            .read()
+            .await
+            .notify(Some(&expired), &db, &mut Stopwatch::default())
+            .await;
+    }
+
+    fn publish_changes(&self, changed: &HashMap<i32, HashSet<Field>>, db: &Database) {
+        if !self.events.has_listeners() {
+            return;
+        }
+        for (id, fields) in changed {
+            let Some(entry) = db.entries.get(id) else {
+                continue;
+            };
+            if fields.contains(&Field::Datapoint) {
+                self.events.publish(Event::ValueChanged {
+                    id: *id,
+                    path: entry.metadata.path.clone(),
+                    datapoint: entry.datapoint.clone(),
+                });
+            }
+            if fields.contains(&Field::ActuatorTarget) {
+                self.events.publish(Event::TargetSet {
+                    id: *id,
+                    path: entry.metadata.path.clone(),
+                    target: entry.actuator_target.clone(),
+                });
+            }
+        }
+    }
+
+    pub fn get_version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn get_commit_sha(&self) -> &str {
+        &self.commit_sha
+    }
+
+    /// Announce that the broker is served with `api`, e.g. `kuksa.val.v2`.
+    pub fn add_served_api(&self, api: impl Into<String>) {
+        self.served_apis
+            .lock()
+            .expect("served apis lock poisoned")
+            .insert(api.into());
+    }
+
+    pub async fn get_capabilities(&self) -> Capabilities {
+        let db = self.database.read().await;
+        let version_part = |name: &str| {
+            db.path_to_id
+                .get(&format!("Vehicle.VersionVSS.{name}"))
+                .and_then(|id| db.entries.get(id))
+                .and_then(|entry| match entry.datapoint.value {
+                    DataValue::Uint32(value) => Some(value),
+                    _ => None,
+                })
+        };
+        let vss_version = match (
+            version_part("Major"),
+            version_part("Minor"),
+            version_part("Patch"),
+        ) {
+            (Some(major), Some(minor), Some(patch)) => Some(format!("{major}.{minor}.{patch}")),
+            (Some(major), Some(minor), None) => Some(format!("{major}.{minor}")),
+            _ => None,
+        };
+        Capabilities {
+            apis: self
+                .served_apis
+                .lock()
+                .expect("served apis lock poisoned")
+                .iter()
+                .cloned()
+                .collect(),
+            wildcards: true,
+            history: db.history.is_enabled(),
+            vss_version,
+        }
+    }
+}
+
+impl Default for DataBroker {
+    fn default() -> Self {
+        Self::new("", "")
+    }
+}
+
+#[cfg(test)]
+/// Public test module to allow other files to reuse helper functions
+pub mod tests {
+    use crate::permissions;
+
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_databroker_version_and_commit_sha() {
+        let version = "1.1.1";
+        let commit_sha = "3a3c332f5427f2db7a0b8582262c9f5089036c23";
+        let databroker = DataBroker::new(version, commit_sha);
+        assert_eq!(databroker.get_version(), version);
+        assert_eq!(databroker.get_commit_sha(), commit_sha);
+    }
+
+    #[tokio::test]
+    async fn test_api_usage() {
+        let databroker = DataBroker::default();
+        assert_eq!(
+            databroker.record_api_call("kuksa.val.v2", false, Some("app")),
+            1
+        );
+        assert_eq!(
+            databroker.record_api_call("sdv.databroker.v1", true, Some("app")),
+            1
+        );
+        assert_eq!(
+            databroker.record_api_call("sdv.databroker.v1", true, None),
+            1
+        );
+        assert_eq!(
+            databroker.record_api_call("sdv.databroker.v1", true, Some("app")),
+            2
+        );
+
+        assert_eq!(
+            databroker.get_api_usage(),
+            vec![
+                ApiUsage {
+                    api: "kuksa.val.v2".to_owned(),
+                    client: Some("app".to_owned()),
+                    deprecated: false,
+                    calls: 1,
+                },
+                ApiUsage {
+                    api: "sdv.databroker.v1".to_owned(),
+                    client: None,
+                    deprecated: true,
+                    calls: 1,
+                },
+                ApiUsage {
+                    api: "sdv.databroker.v1".to_owned(),
+                    client: Some("app".to_owned()),
+                    deprecated: true,
+                    calls: 2,
+                },
+            ]
+        );
+        assert_eq!(databroker.get_usage().await.deprecated_api_calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_capabilities() {
+        let databroker = DataBroker::default();
+        let capabilities = databroker.get_capabilities().await;
+        assert!(capabilities.apis.is_empty());
+        assert!(capabilities.wildcards);
+        assert_eq!(capabilities.vss_version, None);
+
+        databroker.add_served_api("kuksa.val.v2");
+        databroker.add_served_api("kuksa.val.v1");
+        databroker.add_served_api("kuksa.val.v2");
+        let broker = databroker.authorized_access(&permissions::ALLOW_ALL);
+        for (name, value) in [("Major", 4), ("Minor", 0), ("Patch", 1)] {
+            let id = broker
+                .add_entry(
+                    format!("Vehicle.VersionVSS.{name}"),
+                    DataType::Uint32,
+                    ChangeType::Static,
+                    EntryType::Attribute,
+                    "Supported Version of VSS".to_owned(),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            broker
+                .update_entries([(
+                    id,
+                    EntryUpdate {
+                        datapoint: Some(Datapoint {
+                            ts: SystemTime::now(),
+                            source_ts: None,
+                            value: DataValue::Uint32(value),
+                        }),
+                        ..Default::default()
+                    },
+                )])
+                .await
+                .unwrap();
+        }
+
+        let capabilities = databroker.get_capabilities().await;
+        assert_eq!(capabilities.apis, vec!["kuksa.val.v1", "kuksa.val.v2"]);
+        assert_eq!(capabilities.vss_version.as_deref(), Some("4.0.1"));
+    }
+
+    #[tokio::test]
+    async fn test_register_datapoint() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id1 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                Some(DataValue::BoolArray(Vec::from([true]))),
+                Some("kg".to_string()),
+            )
+            .await
+            .expect("Register datapoint should succeed");
 
         {
-            match broker.get_entry_by_id(id2).await {
+            match broker.get_entry_by_id(id1).await {
                 Ok(entry) => {
-                    assert_eq!(entry.metadata.id, id2);
-                    assert_eq!(entry.metadata.path, "test.datapoint2");
-                    assert_eq!(entry.metadata.data_type, DataType::String);
-                    assert_eq!(entry.metadata.description, "Test datapoint 2");
-                    assert_eq!(entry.metadata.allowed, None);
-                    assert_eq!(entry.metadata.unit, Some("km".to_string()));
+                    assert_eq!(entry.metadata.id, id1);
+                    assert_eq!(entry.metadata.path, "test.datapoint1");
+                    assert_eq!(entry.metadata.data_type, DataType::Bool);
+                    assert_eq!(entry.metadata.description, "Test datapoint 1");
+                    assert_eq!(
+                        entry.metadata.allowed,
+                        Some(DataValue::BoolArray(Vec::from([true])))
+                    );
+                    assert_eq!(entry.metadata.unit, Some("kg".to_string()));
                 }
                 Err(_) => {
-                    panic!("no metadata returned");
+                    panic!("datapoint should exist");
                 }
             }
         }
 
-        let id3 = broker
+        let id2 = broker
+            .add_entry(
+                "test.datapoint2".to_owned(),
+                DataType::String,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 2".to_owned(),
+                None, // min
+                None, // max
+                None,
+                Some("km".to_string()),
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        {
+            match broker.get_entry_by_id(id2).await {
+                Ok(entry) => {
+                    assert_eq!(entry.metadata.id, id2);
+                    assert_eq!(entry.metadata.path, "test.datapoint2");
+                    assert_eq!(entry.metadata.data_type, DataType::String);
+                    assert_eq!(entry.metadata.description, "Test datapoint 2");
+                    assert_eq!(entry.metadata.allowed, None);
+                    assert_eq!(entry.metadata.unit, Some("km".to_string()));
+                }
+                Err(_) => {
+                    panic!("no metadata returned");
+                }
+            }
+        }
+
+        let id3 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1 (modified)".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        assert_eq!(id3, id1);
+    }
+
+    #[tokio::test]
+    async fn test_register_invalid_type() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        if broker
+            .add_entry(
+                "test.signal3".to_owned(),
+                DataType::String,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test signal 3".to_owned(),
+                None, // min
+                None, // max
+                Some(DataValue::Int32Array(Vec::from([1, 2, 3, 4]))),
+                None,
+            )
+            .await
+            .is_ok()
+        {
+            panic!("Entry should not register successfully");
+        } else {
+            // everything fine, should not succed to register because allowed is Int32Array and data_type is String
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_set_datapoint() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id1 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let id2 = broker
+            .add_entry(
+                "test.datapoint2".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Actuator,
+                "Test datapoint 2".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        // Data point exists with value NotAvailable
+        match broker.get_datapoint(id1).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.value, DataValue::NotAvailable);
+            }
+            Err(_) => {
+                panic!("data point expected to exist");
+            }
+        }
+
+        match broker.get_entry_by_id(id2).await {
+            Ok(entry) => {
+                assert_eq!(entry.datapoint.value, DataValue::NotAvailable);
+                assert_eq!(entry.actuator_target, None)
+            }
+            Err(_) => {
+                panic!("data point expected to exist");
+            }
+        }
+
+        let time1 = SystemTime::now();
+
+        match broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: time1,
+                        source_ts: None,
+                        value: DataValue::Bool(true),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => {
+                panic!("should not have been able to set int32 typed datapoint to boolean value")
+            }
+            Err(e) => match e[0] {
+                (id, UpdateError::WrongType) => assert_eq!(id, id1),
+                _ => panic!(
+                    "should have reported wrong type but got error of type {:?}",
+                    e
+                ),
+            },
+        }
+
+        broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: time1,
+                        source_ts: None,
+                        value: DataValue::Int32(100),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+            .expect("setting datapoint #1");
+
+        let time2 = SystemTime::now();
+        broker
+            .update_entries([(
+                id2,
+                EntryUpdate {
+                    path: None,
+                    datapoint: None,
+                    actuator_target: Some(Some(Datapoint {
+                        ts: time2,
+                        source_ts: None,
+                        value: DataValue::Bool(true),
+                    })),
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+            .expect("setting datapoint #2");
+
+        // Data point exists with value 100
+        match broker.get_datapoint(id1).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.value, DataValue::Int32(100));
+                assert_eq!(datapoint.ts, time1);
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint1 to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
+            }
+        }
+
+        match broker.get_entry_by_id(id2).await {
+            Ok(entry) => match entry.actuator_target {
+                Some(datapoint) => {
+                    assert_eq!(datapoint.value, DataValue::Bool(true));
+                    assert_eq!(datapoint.ts, time2);
+                }
+                None => {
+                    panic!("data point expected to exist");
+                }
+            },
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint2 to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_set_allowed_values() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id1 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                Some(DataValue::Int32Array(vec![100])),
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        if broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: SystemTime::now(),
+                        source_ts: None,
+                        value: DataValue::Int32(1),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: Some(Some(DataValue::Int32Array(vec![100]))),
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+            .is_ok()
+        {
+            panic!("Setting int32 value of 1 should fail because it is not in the allowed values");
+        } else {
+            // everything fine should fail because trying to set a value which is not in allowed values
+        }
+
+        if broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    path: None,
+                    datapoint: None,
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: Some(Some(DataValue::BoolArray(vec![true]))),
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+            .is_ok()
+        {
+            panic!("Setting allowed to a BoolArray should fail because data_type is int32");
+        } else {
+            // everything fine should fail because trying to set array of type Bool does not match data_type
+        }
+
+        broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    path: None,
+                    datapoint: None,
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: Some(None),
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+            .expect("setting allowed for entry #1");
+
+        let time1 = SystemTime::now();
+
+        broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: time1,
+                        source_ts: None,
+                        value: DataValue::Int32(1),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+            .expect("setting datapoint #1");
+
+        match broker.get_datapoint(id1).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.value, DataValue::Int32(1));
+                assert_eq!(datapoint.ts, time1);
+            }
+            Err(ReadError::NotFound) => {
+                panic!("data point 1 expected to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to have access to data point 1");
+            }
+        }
+    }
+
+    // Helper for adding an int8 signal and adding value
+    async fn helper_add_int8(
+        broker: &DataBroker,
+        name: &str,
+        value: i32,
+        timestamp: std::time::SystemTime,
+    ) -> Result<i32, Vec<(i32, UpdateError)>> {
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
+            .add_entry(
+                name.to_owned(),
+                DataType::Int8,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Some Description That Does Not Matter".to_owned(),
+                Some(types::DataValue::Int32(-5)), // min
+                Some(types::DataValue::Int32(10)), // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        match authorized_access
+            .update_entries([(
+                entry_id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: timestamp,
+                        source_ts: None,
+                        value: types::DataValue::Int32(value),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => Ok(entry_id),
+            Err(details) => Err(details),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_max_int8() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        match helper_add_int8(&broker, "test.datapoint1", -6, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_int8(&broker, "test.datapoint2", -5, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+
+        match helper_add_int8(&broker, "test.datapoint3", 11, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_int8(&broker, "test.datapoint4", 10, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    // Helper for adding an int8 signal and adding value
+    async fn helper_add_int16(
+        broker: &DataBroker,
+        name: &str,
+        value: i32,
+        timestamp: std::time::SystemTime,
+    ) -> Result<i32, Vec<(i32, UpdateError)>> {
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
+            .add_entry(
+                name.to_owned(),
+                DataType::Int16,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Some Description That Does Not Matter".to_owned(),
+                Some(types::DataValue::Int32(-5)), // min
+                Some(types::DataValue::Int32(10)), // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        match authorized_access
+            .update_entries([(
+                entry_id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: timestamp,
+                        source_ts: None,
+                        value: types::DataValue::Int32(value),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => Ok(entry_id),
+            Err(details) => Err(details),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_max_int16() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        match helper_add_int16(&broker, "test.datapoint1", -6, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_int16(&broker, "test.datapoint2", -5, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+
+        match helper_add_int16(&broker, "test.datapoint3", 11, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_int16(&broker, "test.datapoint4", 10, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    // Helper for adding an int32 signal and adding value
+    pub async fn helper_add_int32(
+        broker: &DataBroker,
+        name: &str,
+        value: i32,
+        timestamp: std::time::SystemTime,
+    ) -> Result<i32, Vec<(i32, UpdateError)>> {
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
+            .add_entry(
+                name.to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Some Description That Does Not Matter".to_owned(),
+                Some(types::DataValue::Int32(-500)), // min
+                Some(types::DataValue::Int32(1000)), // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        match authorized_access
+            .update_entries([(
+                entry_id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: timestamp,
+                        source_ts: None,
+                        value: types::DataValue::Int32(value),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => Ok(entry_id),
+            Err(details) => Err(details),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_exceeded() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        match helper_add_int32(&broker, "test.datapoint1", -501, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_equal() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        if helper_add_int32(&broker, "test.datapoint1", -500, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_max_exceeded() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        match helper_add_int32(&broker, "test.datapoint1", 1001, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_atomic() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let timestamp = std::time::SystemTime::now();
+        let id1 = helper_add_int32(&broker, "test.datapoint1", 1, timestamp)
+            .await
+            .expect("Success expected");
+        let id2 = helper_add_int32(&broker, "test.datapoint2", 1, timestamp)
+            .await
+            .expect("Success expected");
+
+        let update = |value| EntryUpdate {
+            datapoint: Some(Datapoint {
+                ts: timestamp,
+                source_ts: None,
+                value: types::DataValue::Int32(value),
+            }),
+            ..Default::default()
+        };
+
+        // The second value is out of range, so neither is applied
+        match authorized_access
+            .update_entries_atomic([(id1, update(5)), (id2, update(1001))])
+            .await
+        {
+            Err(errors) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0], (id2, UpdateError::OutOfBoundsMinMax));
+            }
+            Ok(_) => panic!("Failure expected"),
+        }
+        for id in [id1, id2] {
+            let datapoint = authorized_access.get_datapoint(id).await.unwrap();
+            assert_eq!(datapoint.value, types::DataValue::Int32(1));
+        }
+
+        authorized_access
+            .update_entries_atomic([(id1, update(5)), (id2, update(6))])
+            .await
+            .expect("Success expected");
+        let datapoint = authorized_access.get_datapoint(id1).await.unwrap();
+        assert_eq!(datapoint.value, types::DataValue::Int32(5));
+        let datapoint = authorized_access.get_datapoint(id2).await.unwrap();
+        assert_eq!(datapoint.value, types::DataValue::Int32(6));
+    }
+
+    #[tokio::test]
+    async fn test_precision() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = broker
+            .add_entry(
+                "test.temperature".to_owned(),
+                DataType::Double,
+                ChangeType::Continuous,
+                EntryType::Sensor,
+                "Test sensor".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        broker.set_precision(id, Some(2)).await.unwrap();
+
+        let mut stream = broker
+            .subscribe(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                None,
+            )
+            .await
+            .unwrap();
+        // Initial notification
+        stream.next().await.unwrap();
+
+        let update = |value| EntryUpdate {
+            datapoint: Some(Datapoint {
+                ts: SystemTime::now(),
+                source_ts: None,
+                value: DataValue::Double(value),
+            }),
+            ..Default::default()
+        };
+        let notified = |notification: Option<EntryUpdates>| {
+            notification.unwrap().updates[0]
+                .update
+                .datapoint
+                .as_ref()
+                .unwrap()
+                .value
+                .clone()
+        };
+
+        broker
+            .update_entries([(id, update(21.123456))])
+            .await
+            .unwrap();
+        assert_eq!(notified(stream.next().await), DataValue::Double(21.12));
+
+        // The same once rounded, neither stored nor notified
+        broker
+            .update_entries([(id, update(21.1249))])
+            .await
+            .unwrap();
+        assert_eq!(
+            broker.get_datapoint(id).await.unwrap().value,
+            DataValue::Double(21.12)
+        );
+
+        broker.update_entries([(id, update(21.126))]).await.unwrap();
+        assert_eq!(notified(stream.next().await), DataValue::Double(21.13));
+
+        // Without precision every value of a continuous sensor is notified
+        broker.set_precision(id, None).await.unwrap();
+        broker.update_entries([(id, update(21.13))]).await.unwrap();
+        assert_eq!(notified(stream.next().await), DataValue::Double(21.13));
+    }
+
+    #[tokio::test]
+    async fn test_suppressed_updates() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let timestamp = std::time::SystemTime::now();
+        let id = helper_add_int32(&broker, "test.datapoint1", 5, timestamp)
+            .await
+            .expect("Success expected");
+        let mut stream = authorized_access
+            .subscribe(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                None,
+            )
+            .await
+            .unwrap();
+        // Initial notification
+        stream.next().await.unwrap();
+
+        let update = |value| EntryUpdate {
+            datapoint: Some(Datapoint {
+                ts: std::time::SystemTime::now(),
+                source_ts: None,
+                value: types::DataValue::Int32(value),
+            }),
+            ..Default::default()
+        };
+        for value in [5, 5, 6] {
+            authorized_access
+                .update_entries([(id, update(value))])
+                .await
+                .expect("Success expected");
+        }
+        // Only the change is notified
+        let notification = stream.next().await.unwrap();
+        assert_eq!(
+            notification.updates[0]
+                .update
+                .datapoint
+                .as_ref()
+                .unwrap()
+                .value,
+            types::DataValue::Int32(6)
+        );
+        assert_eq!(broker.get_usage().await.suppressed_updates, 2);
+    }
+
+    #[tokio::test]
+    async fn test_compare_and_update_actuator_target() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Actuator,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let timestamp = std::time::SystemTime::now();
+        let set_target = |value| EntryUpdate {
+            actuator_target: Some(Some(Datapoint {
+                ts: timestamp,
+                source_ts: None,
+                value: DataValue::Int32(value),
+            })),
+            ..Default::default()
+        };
+
+        // Target was never set
+        broker
+            .compare_and_update(
+                id,
+                UpdateCondition::Value(DataValue::NotAvailable),
+                set_target(10),
+            )
+            .await
+            .expect("Condition should hold");
+
+        assert_eq!(
+            broker
+                .compare_and_update(
+                    id,
+                    UpdateCondition::Value(DataValue::NotAvailable),
+                    set_target(20),
+                )
+                .await,
+            Err(UpdateError::Conflict)
+        );
+        assert_eq!(
+            broker
+                .compare_and_update(
+                    id,
+                    UpdateCondition::Timestamp(timestamp - std::time::Duration::from_secs(1)),
+                    set_target(20),
+                )
+                .await,
+            Err(UpdateError::Conflict)
+        );
+
+        broker
+            .compare_and_update(id, UpdateCondition::Timestamp(timestamp), set_target(20))
+            .await
+            .expect("Condition should hold");
+
+        let entry = broker.get_entry_by_id(id).await.unwrap();
+        assert_eq!(
+            entry.actuator_target.map(|target| target.value),
+            Some(DataValue::Int32(20))
+        );
+        // The condition applies to the target, not the current value
+        assert_eq!(entry.datapoint.value, DataValue::NotAvailable);
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_max_equal() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        if helper_add_int32(&broker, "test.datapoint1", 1000, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    /// Helper for adding an int64 signal and adding value
+    async fn helper_add_int64(
+        broker: &DataBroker,
+        name: &str,
+        value: i64,
+        timestamp: std::time::SystemTime,
+    ) -> Result<i32, Vec<(i32, UpdateError)>> {
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
+            .add_entry(
+                name.to_owned(),
+                DataType::Int64,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Some Description That Does Not Matter".to_owned(),
+                Some(types::DataValue::Int64(-500000)),  // min
+                Some(types::DataValue::Int64(10000000)), // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        match authorized_access
+            .update_entries([(
+                entry_id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: timestamp,
+                        source_ts: None,
+                        value: types::DataValue::Int64(value),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => Ok(entry_id),
+            Err(details) => Err(details),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_max_int64() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        match helper_add_int64(&broker, "test.datapoint1", -500001, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_int64(&broker, "test.datapoint2", -500000, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+
+        match helper_add_int64(&broker, "test.datapoint3", 10000001, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_int64(&broker, "test.datapoint4", 10000000, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    /// Helper for adding an uint8 signal and adding value
+    async fn helper_add_uint8(
+        broker: &DataBroker,
+        name: &str,
+        value: u32,
+        timestamp: std::time::SystemTime,
+    ) -> Result<i32, Vec<(i32, UpdateError)>> {
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
+            .add_entry(
+                name.to_owned(),
+                DataType::Uint8,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Some Description That Does Not Matter".to_owned(),
+                Some(types::DataValue::Uint32(3)),  // min
+                Some(types::DataValue::Uint32(26)), // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        match authorized_access
+            .update_entries([(
+                entry_id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: timestamp,
+                        source_ts: None,
+                        value: types::DataValue::Uint32(value),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => Ok(entry_id),
+            Err(details) => Err(details),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_max_uint8() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        match helper_add_uint8(&broker, "test.datapoint1", 2, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_uint8(&broker, "test.datapoint2", 3, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+
+        match helper_add_uint8(&broker, "test.datapoint3", 27, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        if helper_add_uint8(&broker, "test.datapoint4", 26, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    // Helper for adding an int32 signal and adding value
+    async fn helper_add_int32array(
+        broker: &DataBroker,
+        name: &str,
+        value1: i32,
+        value2: i32,
+        timestamp: std::time::SystemTime,
+    ) -> Result<i32, Vec<(i32, UpdateError)>> {
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
+            .add_entry(
+                name.to_owned(),
+                DataType::Int32Array,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Some Description That Does Not Matter".to_owned(),
+                Some(types::DataValue::Int32(-500)), // min
+                Some(types::DataValue::Int32(1000)), // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        match authorized_access
+            .update_entries([(
+                entry_id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: timestamp,
+                        source_ts: None,
+                        value: types::DataValue::Int32Array(Vec::from([value1, value2])),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => Ok(entry_id),
+            Err(details) => Err(details),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_exceeded_int32array() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        // First item out of bound
+        match helper_add_int32array(&broker, "test.datapoint1", -501, -500, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+        // Second item out of bound
+        match helper_add_int32array(&broker, "test.datapoint2", -500, -501, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_min_equal_int32array() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        if helper_add_int32array(&broker, "test.datapoint1", -500, -500, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_max_exceeded_int32array() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        match helper_add_int32array(&broker, "test.datapoint1", 1001, 1000, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+        match helper_add_int32array(&broker, "test.datapoint2", 100, 1001, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_max_equal_int32array() {
+        let broker = DataBroker::default();
+
+        let timestamp = std::time::SystemTime::now();
+
+        if helper_add_int32array(&broker, "test.datapoint1", 1000, 1000, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
+        }
+    }
+
+    // Helper for adding an double array signal and adding value
+    async fn helper_add_doublearray(
+        broker: &DataBroker,
+        name: &str,
+        value1: f64,
+        value2: f64,
+        timestamp: std::time::SystemTime,
+    ) -> Result<i32, Vec<(i32, UpdateError)>> {
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
             .add_entry(
-                "test.datapoint1".to_owned(),
-                DataType::Bool,
+                name.to_owned(),
+                DataType::DoubleArray,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Test datapoint 1 (modified)".to_owned(),
-                None, // min
-                None, // max
+                "Some Description That Does Not Matter".to_owned(),
+                Some(types::DataValue::Double(-500.2)), // min
+                Some(types::DataValue::Double(1000.2)), // max
                 None,
                 None,
             )
             .await
-            .expect("Register datapoint should succeed");
+            .unwrap();
 
-        assert_eq!(id3, id1);
+        match authorized_access
+            .update_entries([(
+                entry_id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: timestamp,
+                        source_ts: None,
+                        value: types::DataValue::DoubleArray(Vec::from([value1, value2])),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
+            .await
+        {
+            Ok(_) => Ok(entry_id),
+            Err(details) => Err(details),
+        }
     }
 
     #[tokio::test]
-    async fn test_register_invalid_type() {
+    async fn test_update_entries_min_max_doublearray() {
         let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
 
-        if broker
-            .add_entry(
-                "test.signal3".to_owned(),
-                DataType::String,
-                ChangeType::OnChange,
-                EntryType::Sensor,
-                "Test signal 3".to_owned(),
-                None, // min
-                None, // max
-                Some(DataValue::Int32Array(Vec::from([1, 2, 3, 4]))),
-                None,
-            )
+        let timestamp = std::time::SystemTime::now();
+
+        // First item out of bound
+        match helper_add_doublearray(&broker, "test.datapoint1", -500.3, -500.0, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+        // Second item out of bound
+        match helper_add_doublearray(&broker, "test.datapoint2", -500.0, -500.3, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        // Both on min
+        if helper_add_doublearray(&broker, "test.datapoint3", -500.2, -500.2, timestamp)
             .await
-            .is_ok()
+            .is_err()
         {
-            panic!("Entry should not register successfully");
-        } else {
-            // everything fine, should not succed to register because allowed is Int32Array and data_type is String
+            panic!("Success expected")
+        }
+
+        // First tto large
+        match helper_add_doublearray(&broker, "test.datapoint4", 1000.3, 1000.0, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        // Second too large
+        match helper_add_doublearray(&broker, "test.datapoint5", 1000.0, 1000.3, timestamp).await {
+            Err(err_vec) => {
+                assert_eq!(err_vec.len(), 1);
+                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            }
+            _ => panic!("Failure expected"),
+        }
+
+        // Both on max
+        if helper_add_doublearray(&broker, "test.datapoint6", 1000.2, 1000.2, timestamp)
+            .await
+            .is_err()
+        {
+            panic!("Success expected")
         }
     }
 
     #[tokio::test]
-    async fn test_get_set_datapoint() {
+    async fn test_subscribe_query_and_get() {
         let broker = DataBroker::default();
         let broker = broker.authorized_access(&permissions::ALLOW_ALL);
 
@@ -2268,52 +6355,34 @@ pub mod tests {
             .await
             .expect("Register datapoint should succeed");
 
-        let id2 = broker
-            .add_entry(
-                "test.datapoint2".to_owned(),
-                DataType::Bool,
-                ChangeType::OnChange,
-                EntryType::Actuator,
-                "Test datapoint 2".to_owned(),
-                None, // min
-                None, // max
-                None,
-                None,
-            )
+        let mut stream = broker
+            .subscribe_query("SELECT test.datapoint1")
             .await
-            .expect("Register datapoint should succeed");
-
-        // Data point exists with value NotAvailable
-        match broker.get_datapoint(id1).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.value, DataValue::NotAvailable);
-            }
-            Err(_) => {
-                panic!("data point expected to exist");
-            }
-        }
+            .expect("Setup subscription");
 
-        match broker.get_entry_by_id(id2).await {
-            Ok(entry) => {
-                assert_eq!(entry.datapoint.value, DataValue::NotAvailable);
-                assert_eq!(entry.actuator_target, None)
+        // Expect an initial query response
+        // No value has been set yet, so value should be NotAvailable
+        match stream.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.reason, UpdateReason::Initial);
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
             }
-            Err(_) => {
-                panic!("data point expected to exist");
+            None => {
+                panic!("did not expect stream end")
             }
         }
 
-        let time1 = SystemTime::now();
-
-        match broker
+        broker
             .update_entries([(
                 id1,
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: time1,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::Bool(true),
+                        value: DataValue::Int32(101),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -2326,101 +6395,143 @@ pub mod tests {
                 },
             )])
             .await
-        {
-            Ok(_) => {
-                panic!("should not have been able to set int32 typed datapoint to boolean value")
+            .expect("setting datapoint #1");
+
+        // Value has been set, expect the next item in stream to match.
+        match stream.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.reason, UpdateReason::ValueChanged);
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::Int32(101));
+            }
+            None => {
+                panic!("did not expect stream end")
             }
-            Err(e) => match e[0] {
-                (id, UpdateError::WrongType) => assert_eq!(id, id1),
-                _ => panic!(
-                    "should have reported wrong type but got error of type {:?}",
-                    e
-                ),
-            },
         }
 
-        broker
-            .update_entries([(
-                id1,
-                EntryUpdate {
-                    path: None,
-                    datapoint: Some(Datapoint {
-                        ts: time1,
-                        source_ts: None,
-                        value: DataValue::Int32(100),
-                    }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
-                },
-            )])
-            .await
-            .expect("setting datapoint #1");
+        // Check that the data point has been stored as well
+        match broker.get_datapoint(id1).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.value, DataValue::Int32(101));
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
+            }
+        }
+    }
 
-        let time2 = SystemTime::now();
-        broker
-            .update_entries([(
-                id2,
-                EntryUpdate {
-                    path: None,
-                    datapoint: None,
-                    actuator_target: Some(Some(Datapoint {
-                        ts: time2,
-                        source_ts: None,
-                        value: DataValue::Bool(true),
-                    })),
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
-                },
-            )])
+    #[tokio::test]
+    async fn test_multi_subscribe() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id1 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
             .await
-            .expect("setting datapoint #2");
+            .expect("Register datapoint should succeed");
 
-        // Data point exists with value 100
-        match broker.get_datapoint(id1).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.value, DataValue::Int32(100));
-                assert_eq!(datapoint.ts, time1);
+        let mut subscription1 = broker
+            .subscribe_query("SELECT test.datapoint1")
+            .await
+            .expect("setup first subscription");
+
+        let mut subscription2 = broker
+            .subscribe_query("SELECT test.datapoint1")
+            .await
+            .expect("setup second subscription");
+
+        // Expect an initial query response
+        // No value has been set yet, so value should be NotAvailable
+        match subscription1.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
             }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint1 to exist");
+            None => {
+                panic!("did not expect stream end")
             }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
+        }
+
+        // Expect an initial query response
+        // No value has been set yet, so value should be NotAvailable
+        match subscription2.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
+            }
+            None => {
+                panic!("did not expect stream end")
             }
         }
 
-        match broker.get_entry_by_id(id2).await {
-            Ok(entry) => match entry.actuator_target {
-                Some(datapoint) => {
-                    assert_eq!(datapoint.value, DataValue::Bool(true));
-                    assert_eq!(datapoint.ts, time2);
-                }
-                None => {
-                    panic!("data point expected to exist");
+        for i in 0..100 {
+            broker
+                .update_entries([(
+                    id1,
+                    EntryUpdate {
+                        path: None,
+                        datapoint: Some(Datapoint {
+                            ts: SystemTime::now(),
+                            source_ts: None,
+                            value: DataValue::Int32(i),
+                        }),
+                        actuator_target: None,
+                        entry_type: None,
+                        data_type: None,
+                        description: None,
+                        allowed: None,
+                        min: None,
+                        max: None,
+                        unit: None,
+                    },
+                )])
+                .await
+                .expect("setting datapoint #1");
+
+            if let Some(query_resp) = subscription1.next().await {
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                if let DataValue::Int32(value) = query_resp.fields[0].value {
+                    assert_eq!(value, i);
+                } else {
+                    panic!("expected test.datapoint1 to contain a value");
                 }
-            },
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint2 to exist");
+            } else {
+                panic!("did not expect end of stream");
             }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
+
+            if let Some(query_resp) = subscription2.next().await {
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                if let DataValue::Int32(value) = query_resp.fields[0].value {
+                    assert_eq!(value, i);
+                } else {
+                    panic!("expected test.datapoint1 to contain a value");
+                }
+            } else {
+                panic!("did not expect end of stream");
             }
         }
     }
 
     #[tokio::test]
-    async fn test_get_set_allowed_values() {
+    async fn test_subscribe_after_new_registration() {
         let broker = DataBroker::default();
         let broker = broker.authorized_access(&permissions::ALLOW_ALL);
 
@@ -2433,13 +6544,31 @@ pub mod tests {
                 "Test datapoint 1".to_owned(),
                 None, // min
                 None, // max
-                Some(DataValue::Int32Array(vec![100])),
+                None,
                 None,
             )
             .await
             .expect("Register datapoint should succeed");
 
-        if broker
+        let mut subscription = broker
+            .subscribe_query("SELECT test.datapoint1")
+            .await
+            .expect("Setup subscription");
+
+        // Expect an initial query response
+        // No value has been set yet, so value should be NotAvailable
+        match subscription.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
+            }
+            None => {
+                panic!("did not expect stream end")
+            }
+        }
+
+        broker
             .update_entries([(
                 id1,
                 EntryUpdate {
@@ -2447,70 +6576,60 @@ pub mod tests {
                     datapoint: Some(Datapoint {
                         ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::Int32(1),
+                        value: DataValue::Int32(200),
                     }),
                     actuator_target: None,
                     entry_type: None,
                     data_type: None,
                     description: None,
-                    allowed: Some(Some(DataValue::Int32Array(vec![100]))),
+                    allowed: None,
                     min: None,
                     max: None,
                     unit: None,
                 },
             )])
             .await
-            .is_ok()
-        {
-            panic!("Setting int32 value of 1 should fail because it is not in the allowed values");
-        } else {
-            // everything fine should fail because trying to set a value which is not in allowed values
+            .expect("setting datapoint #1");
+
+        match subscription.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::Int32(200));
+            }
+            None => {
+                panic!("did not expect stream end")
+            }
         }
 
-        if broker
-            .update_entries([(
-                id1,
-                EntryUpdate {
-                    path: None,
-                    datapoint: None,
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: Some(Some(DataValue::BoolArray(vec![true]))),
-                    min: None,
-                    max: None,
-                    unit: None,
-                },
-            )])
-            .await
-            .is_ok()
-        {
-            panic!("Setting allowed to a BoolArray should fail because data_type is int32");
-        } else {
-            // everything fine should fail because trying to set array of type Bool does not match data_type
+        match broker.get_datapoint(id1).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.value, DataValue::Int32(200));
+            }
+            Err(ReadError::NotFound) => {
+                panic!("datapoint expected to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
+            }
         }
 
-        broker
-            .update_entries([(
-                id1,
-                EntryUpdate {
-                    path: None,
-                    datapoint: None,
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: Some(None),
-                    min: None,
-                    max: None,
-                    unit: None,
-                },
-            )])
+        let id2 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1 (new description)".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
             .await
-            .expect("setting allowed for entry #1");
+            .expect("Registration should succeed");
 
-        let time1 = SystemTime::now();
+        assert_eq!(id1, id2, "Re-registration should result in the same id");
 
         broker
             .update_entries([(
@@ -2518,9 +6637,9 @@ pub mod tests {
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: time1,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::Int32(1),
+                        value: DataValue::Int32(102),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -2533,141 +6652,184 @@ pub mod tests {
                 },
             )])
             .await
-            .expect("setting datapoint #1");
+            .expect("setting datapoint #1 (second time)");
+
+        match subscription.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.fields.len(), 1);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::Int32(102));
+            }
+            None => {
+                panic!("did not expect stream end")
+            }
+        }
 
         match broker.get_datapoint(id1).await {
             Ok(datapoint) => {
-                assert_eq!(datapoint.value, DataValue::Int32(1));
-                assert_eq!(datapoint.ts, time1);
+                assert_eq!(datapoint.value, DataValue::Int32(102));
             }
             Err(ReadError::NotFound) => {
-                panic!("data point 1 expected to exist");
+                panic!("expected datapoint to exist");
             }
             Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to have access to data point 1");
+                panic!("expected to be authorized");
             }
         }
     }
 
-    // Helper for adding an int8 signal and adding value
-    async fn helper_add_int8(
-        broker: &DataBroker,
-        name: &str,
-        value: i32,
-        timestamp: std::time::SystemTime,
-    ) -> Result<i32, Vec<(i32, UpdateError)>> {
-        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
-        let entry_id = authorized_access
+    #[tokio::test]
+    async fn test_subscribe_set_multiple() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id1 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let id2 = broker
             .add_entry(
-                name.to_owned(),
-                DataType::Int8,
+                "test.datapoint2".to_owned(),
+                DataType::Int32,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Some Description That Does Not Matter".to_owned(),
-                Some(types::DataValue::Int32(-5)), // min
-                Some(types::DataValue::Int32(10)), // max
+                "Test datapoint 2".to_owned(),
+                None, // min
+                None, // max
                 None,
                 None,
             )
             .await
-            .unwrap();
+            .expect("Register datapoint should succeed");
 
-        match authorized_access
-            .update_entries([(
-                entry_id,
-                EntryUpdate {
-                    path: None,
-                    datapoint: Some(Datapoint {
-                        ts: timestamp,
-                        source_ts: None,
-                        value: types::DataValue::Int32(value),
-                    }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
-                },
-            )])
+        let mut subscription = broker
+            .subscribe_query("SELECT test.datapoint1, test.datapoint2")
             .await
-        {
-            Ok(_) => Ok(entry_id),
-            Err(details) => Err(details),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_max_int8() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
+            .expect("setup first subscription");
 
-        match helper_add_int8(&broker, "test.datapoint1", -6, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        // Expect an initial query response
+        // No value has been set yet, so value should be NotAvailable
+        match subscription.next().await {
+            Some(query_resp) => {
+                assert_eq!(query_resp.fields.len(), 2);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
+                assert_eq!(query_resp.fields[1].name, "test.datapoint2");
+                assert_eq!(query_resp.fields[1].value, DataValue::NotAvailable);
+            }
+            None => {
+                panic!("did not expect stream end")
             }
-            _ => panic!("Failure expected"),
         }
 
-        if helper_add_int8(&broker, "test.datapoint2", -5, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
+        for i in 0..10 {
+            broker
+                .update_entries([
+                    (
+                        id1,
+                        EntryUpdate {
+                            path: None,
+                            datapoint: Some(Datapoint {
+                                ts: SystemTime::now(),
+                                source_ts: None,
+                                value: DataValue::Int32(-i),
+                            }),
+                            actuator_target: None,
+                            entry_type: None,
+                            data_type: None,
+                            description: None,
+                            allowed: None,
+                            min: None,
+                            max: None,
+                            unit: None,
+                        },
+                    ),
+                    (
+                        id2,
+                        EntryUpdate {
+                            path: None,
+                            datapoint: Some(Datapoint {
+                                ts: SystemTime::now(),
+                                source_ts: None,
+                                value: DataValue::Int32(i),
+                            }),
+                            actuator_target: None,
+                            entry_type: None,
+                            data_type: None,
+                            description: None,
+                            allowed: None,
+                            min: None,
+                            max: None,
+                            unit: None,
+                        },
+                    ),
+                ])
+                .await
+                .expect("setting datapoint #1");
         }
 
-        match helper_add_int8(&broker, "test.datapoint3", 11, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        for i in 0..10 {
+            if let Some(query_resp) = subscription.next().await {
+                assert_eq!(query_resp.fields.len(), 2);
+                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
+                if let DataValue::Int32(value) = query_resp.fields[0].value {
+                    assert_eq!(value, -i);
+                } else {
+                    panic!("expected test.datapoint1 to contain a values");
+                }
+                assert_eq!(query_resp.fields[1].name, "test.datapoint2");
+                if let DataValue::Int32(value) = query_resp.fields[1].value {
+                    assert_eq!(value, i);
+                } else {
+                    panic!("expected test.datapoint2 to contain a values");
+                }
+            } else {
+                panic!("did not expect end of stream");
             }
-            _ => panic!("Failure expected"),
-        }
-
-        if helper_add_int8(&broker, "test.datapoint4", 10, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
         }
     }
 
-    // Helper for adding an int8 signal and adding value
-    async fn helper_add_int16(
-        broker: &DataBroker,
-        name: &str,
-        value: i32,
-        timestamp: std::time::SystemTime,
-    ) -> Result<i32, Vec<(i32, UpdateError)>> {
-        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
-        let entry_id = authorized_access
+    #[tokio::test]
+    async fn test_bool_array() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = broker
             .add_entry(
-                name.to_owned(),
-                DataType::Int16,
+                "Vehicle.TestArray".to_owned(),
+                DataType::BoolArray,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Some Description That Does Not Matter".to_owned(),
-                Some(types::DataValue::Int32(-5)), // min
-                Some(types::DataValue::Int32(10)), // max
+                "Run of the mill test array".to_owned(),
+                None, // min
+                None, // max
                 None,
                 None,
             )
             .await
             .unwrap();
 
-        match authorized_access
+        let ts = std::time::SystemTime::now();
+        match broker
             .update_entries([(
-                entry_id,
+                id,
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: timestamp,
+                        ts,
                         source_ts: None,
-                        value: types::DataValue::Int32(value),
+                        value: DataValue::BoolArray(vec![true, true, false, true]),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -2681,80 +6843,67 @@ pub mod tests {
             )])
             .await
         {
-            Ok(_) => Ok(entry_id),
-            Err(details) => Err(details),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_max_int16() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
-
-        match helper_add_int16(&broker, "test.datapoint1", -6, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            Ok(()) => {}
+            Err(e) => {
+                panic!(
+                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
+                    e
+                )
             }
-            _ => panic!("Failure expected"),
-        }
-
-        if helper_add_int16(&broker, "test.datapoint2", -5, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
         }
 
-        match helper_add_int16(&broker, "test.datapoint3", 11, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        match broker.get_datapoint(id).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.ts, ts);
+                assert_eq!(
+                    datapoint.value,
+                    DataValue::BoolArray(vec![true, true, false, true])
+                );
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
             }
-            _ => panic!("Failure expected"),
-        }
-
-        if helper_add_int16(&broker, "test.datapoint4", 10, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
         }
     }
 
-    // Helper for adding an int32 signal and adding value
-    pub async fn helper_add_int32(
-        broker: &DataBroker,
-        name: &str,
-        value: i32,
-        timestamp: std::time::SystemTime,
-    ) -> Result<i32, Vec<(i32, UpdateError)>> {
-        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
-        let entry_id = authorized_access
+    #[tokio::test]
+    async fn test_string_array() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = broker
             .add_entry(
-                name.to_owned(),
-                DataType::Int32,
+                "Vehicle.TestArray".to_owned(),
+                DataType::StringArray,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Some Description That Does Not Matter".to_owned(),
-                Some(types::DataValue::Int32(-500)), // min
-                Some(types::DataValue::Int32(1000)), // max
+                "Run of the mill test array".to_owned(),
+                None, // min
+                None, // max
                 None,
                 None,
             )
             .await
             .unwrap();
 
-        match authorized_access
+        let ts = std::time::SystemTime::now();
+        match broker
             .update_entries([(
-                entry_id,
+                id,
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: timestamp,
+                        ts,
                         source_ts: None,
-                        value: types::DataValue::Int32(value),
+                        value: DataValue::StringArray(vec![
+                            String::from("yes"),
+                            String::from("no"),
+                            String::from("maybe"),
+                            String::from("nah"),
+                        ]),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -2768,101 +6917,77 @@ pub mod tests {
             )])
             .await
         {
-            Ok(_) => Ok(entry_id),
-            Err(details) => Err(details),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_exceeded() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
-
-        match helper_add_int32(&broker, "test.datapoint1", -501, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            Ok(_) => {}
+            Err(e) => {
+                panic!(
+                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
+                    e
+                )
             }
-            _ => panic!("Failure expected"),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_equal() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
-
-        if helper_add_int32(&broker, "test.datapoint1", -500, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
         }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_max_exceeded() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
 
-        match helper_add_int32(&broker, "test.datapoint1", 1001, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        match broker.get_datapoint(id).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.ts, ts);
+                assert_eq!(
+                    datapoint.value,
+                    DataValue::StringArray(vec![
+                        String::from("yes"),
+                        String::from("no"),
+                        String::from("maybe"),
+                        String::from("nah"),
+                    ])
+                );
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
             }
-            _ => panic!("Failure expected"),
         }
     }
 
     #[tokio::test]
-    async fn test_update_entries_max_equal() {
+    async fn test_string_array_allowed_values() {
         let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
 
-        let timestamp = std::time::SystemTime::now();
-
-        if helper_add_int32(&broker, "test.datapoint1", 1000, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
-        }
-    }
-
-    /// Helper for adding an int64 signal and adding value
-    async fn helper_add_int64(
-        broker: &DataBroker,
-        name: &str,
-        value: i64,
-        timestamp: std::time::SystemTime,
-    ) -> Result<i32, Vec<(i32, UpdateError)>> {
-        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
-        let entry_id = authorized_access
+        let id = broker
             .add_entry(
-                name.to_owned(),
-                DataType::Int64,
+                "Vehicle.TestArray".to_owned(),
+                DataType::StringArray,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Some Description That Does Not Matter".to_owned(),
-                Some(types::DataValue::Int64(-500000)),  // min
-                Some(types::DataValue::Int64(10000000)), // max
-                None,
+                "Run of the mill test array".to_owned(),
+                None, // min
+                None, // max
+                Some(DataValue::StringArray(vec![
+                    String::from("yes"),
+                    String::from("no"),
+                    String::from("maybe"),
+                    String::from("nah"),
+                ])),
                 None,
             )
             .await
             .unwrap();
 
-        match authorized_access
+        let ts = std::time::SystemTime::now();
+        match broker
             .update_entries([(
-                entry_id,
+                id,
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: timestamp,
+                        ts,
                         source_ts: None,
-                        value: types::DataValue::Int64(value),
+                        value: DataValue::StringArray(vec![
+                            String::from("yes"),
+                            String::from("no"),
+                            String::from("maybe"),
+                            String::from("nah"),
+                        ]),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -2876,80 +7001,159 @@ pub mod tests {
             )])
             .await
         {
-            Ok(_) => Ok(entry_id),
-            Err(details) => Err(details),
+            Ok(_) => {}
+            Err(e) => {
+                panic!(
+                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
+                    e
+                )
+            }
         }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_max_int64() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
 
-        match helper_add_int64(&broker, "test.datapoint1", -500001, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        match broker.get_datapoint(id).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.ts, ts);
+                assert_eq!(
+                    datapoint.value,
+                    DataValue::StringArray(vec![
+                        String::from("yes"),
+                        String::from("no"),
+                        String::from("maybe"),
+                        String::from("nah"),
+                    ])
+                );
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
             }
-            _ => panic!("Failure expected"),
         }
 
-        if helper_add_int64(&broker, "test.datapoint2", -500000, timestamp)
+        // check if duplicate is working
+        match broker
+            .update_entries([(
+                id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts,
+                        source_ts: None,
+                        value: DataValue::StringArray(vec![
+                            String::from("yes"),
+                            String::from("no"),
+                            String::from("maybe"),
+                            String::from("nah"),
+                            String::from("yes"),
+                        ]),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
             .await
-            .is_err()
         {
-            panic!("Success expected")
+            Ok(_) => {}
+            Err(e) => {
+                panic!(
+                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
+                    e
+                )
+            }
         }
 
-        match helper_add_int64(&broker, "test.datapoint3", 10000001, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        match broker.get_datapoint(id).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.ts, ts);
+                assert_eq!(
+                    datapoint.value,
+                    DataValue::StringArray(vec![
+                        String::from("yes"),
+                        String::from("no"),
+                        String::from("maybe"),
+                        String::from("nah"),
+                        String::from("yes"),
+                    ])
+                );
+            }
+            Err(ReadError::NotFound) => panic!("Expected datapoint to exist"),
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("Expected to have access to datapoint")
             }
-            _ => panic!("Failure expected"),
         }
 
-        if helper_add_int64(&broker, "test.datapoint4", 10000000, timestamp)
+        if broker
+            .update_entries([(
+                id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts,
+                        source_ts: None,
+                        value: DataValue::StringArray(vec![
+                            String::from("yes"),
+                            String::from("no"),
+                            String::from("maybe"),
+                            String::from("nah"),
+                            String::from("true"),
+                        ]),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
             .await
-            .is_err()
+            .is_ok()
         {
-            panic!("Success expected")
+            panic!("Expected set_datapoints to fail because string(true) not in allowed values")
+        } else {
+            // everything fine vlaue string(true) not in the allowed values
         }
     }
 
-    /// Helper for adding an uint8 signal and adding value
-    async fn helper_add_uint8(
-        broker: &DataBroker,
-        name: &str,
-        value: u32,
-        timestamp: std::time::SystemTime,
-    ) -> Result<i32, Vec<(i32, UpdateError)>> {
-        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
-        let entry_id = authorized_access
+    #[tokio::test]
+    async fn test_int8_array() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = broker
             .add_entry(
-                name.to_owned(),
-                DataType::Uint8,
+                "Vehicle.TestArray".to_owned(),
+                DataType::Int8Array,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Some Description That Does Not Matter".to_owned(),
-                Some(types::DataValue::Uint32(3)),  // min
-                Some(types::DataValue::Uint32(26)), // max
+                "Run of the mill test array".to_owned(),
+                None, // min
+                None, // max
                 None,
                 None,
             )
             .await
             .unwrap();
 
-        match authorized_access
+        let ts = std::time::SystemTime::now();
+        match broker
             .update_entries([(
-                entry_id,
+                id,
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: timestamp,
+                        ts,
                         source_ts: None,
-                        value: types::DataValue::Uint32(value),
+                        value: DataValue::Int32Array(vec![10, 20, 30, 40]),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -2963,81 +7167,85 @@ pub mod tests {
             )])
             .await
         {
-            Ok(_) => Ok(entry_id),
-            Err(details) => Err(details),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_max_uint8() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
-
-        match helper_add_uint8(&broker, "test.datapoint1", 2, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            Ok(()) => {}
+            Err(e) => {
+                panic!(
+                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
+                    e
+                )
             }
-            _ => panic!("Failure expected"),
         }
 
-        if helper_add_uint8(&broker, "test.datapoint2", 3, timestamp)
+        if broker
+            .update_entries([(
+                id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts,
+                        source_ts: None,
+                        value: DataValue::Int32Array(vec![100, 200, 300, 400]),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
             .await
-            .is_err()
+            .is_ok()
         {
-            panic!("Success expected")
+            panic!("Expected set_datapoints to fail ( with Err() ), instead got: Ok(())",)
         }
 
-        match helper_add_uint8(&broker, "test.datapoint3", 27, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        match broker.get_datapoint(id).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.ts, ts);
+                assert_eq!(datapoint.value, DataValue::Int32Array(vec![10, 20, 30, 40]));
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
             }
-            _ => panic!("Failure expected"),
-        }
-
-        if helper_add_uint8(&broker, "test.datapoint4", 26, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
         }
     }
 
-    // Helper for adding an int32 signal and adding value
-    async fn helper_add_int32array(
-        broker: &DataBroker,
-        name: &str,
-        value1: i32,
-        value2: i32,
-        timestamp: std::time::SystemTime,
-    ) -> Result<i32, Vec<(i32, UpdateError)>> {
-        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
-        let entry_id = authorized_access
+    #[tokio::test]
+    async fn test_uint8_array() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = broker
             .add_entry(
-                name.to_owned(),
-                DataType::Int32Array,
+                "Vehicle.TestArray".to_owned(),
+                DataType::Uint8Array,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Some Description That Does Not Matter".to_owned(),
-                Some(types::DataValue::Int32(-500)), // min
-                Some(types::DataValue::Int32(1000)), // max
+                "Run of the mill test array".to_owned(),
+                None, // min
+                None, // max
                 None,
                 None,
             )
             .await
             .unwrap();
 
-        match authorized_access
+        let ts = std::time::SystemTime::now();
+        match broker
             .update_entries([(
-                entry_id,
+                id,
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: timestamp,
+                        ts,
                         source_ts: None,
-                        value: types::DataValue::Int32Array(Vec::from([value1, value2])),
+                        value: DataValue::Uint32Array(vec![10, 20, 30, 40]),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -3051,118 +7259,88 @@ pub mod tests {
             )])
             .await
         {
-            Ok(_) => Ok(entry_id),
-            Err(details) => Err(details),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_exceeded_int32array() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
-
-        // First item out of bound
-        match helper_add_int32array(&broker, "test.datapoint1", -501, -500, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
-            }
-            _ => panic!("Failure expected"),
-        }
-        // Second item out of bound
-        match helper_add_int32array(&broker, "test.datapoint2", -500, -501, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            Ok(()) => {}
+            Err(e) => {
+                panic!(
+                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
+                    e
+                )
             }
-            _ => panic!("Failure expected"),
         }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_min_equal_int32array() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
 
-        if helper_add_int32array(&broker, "test.datapoint1", -500, -500, timestamp)
+        if broker
+            .update_entries([(
+                id,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts,
+                        source_ts: None,
+                        value: DataValue::Uint32Array(vec![100, 200, 300, 400]),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
             .await
-            .is_err()
+            .is_ok()
         {
-            panic!("Success expected")
+            panic!("Expected set_datapoints to fail ( with Err() ), instead got: Ok(())",)
         }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_max_exceeded_int32array() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
 
-        match helper_add_int32array(&broker, "test.datapoint1", 1001, 1000, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        match broker.get_datapoint(id).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.ts, ts);
+                assert_eq!(
+                    datapoint.value,
+                    DataValue::Uint32Array(vec![10, 20, 30, 40])
+                );
             }
-            _ => panic!("Failure expected"),
-        }
-        match helper_add_int32array(&broker, "test.datapoint2", 100, 1001, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
             }
-            _ => panic!("Failure expected"),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_update_entries_max_equal_int32array() {
-        let broker = DataBroker::default();
-
-        let timestamp = std::time::SystemTime::now();
-
-        if helper_add_int32array(&broker, "test.datapoint1", 1000, 1000, timestamp)
-            .await
-            .is_err()
-        {
-            panic!("Success expected")
         }
     }
 
-    // Helper for adding an double array signal and adding value
-    async fn helper_add_doublearray(
-        broker: &DataBroker,
-        name: &str,
-        value1: f64,
-        value2: f64,
-        timestamp: std::time::SystemTime,
-    ) -> Result<i32, Vec<(i32, UpdateError)>> {
-        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
-        let entry_id = authorized_access
+    #[tokio::test]
+    async fn test_float_array() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = broker
             .add_entry(
-                name.to_owned(),
-                DataType::DoubleArray,
+                "Vehicle.TestArray".to_owned(),
+                DataType::FloatArray,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Some Description That Does Not Matter".to_owned(),
-                Some(types::DataValue::Double(-500.2)), // min
-                Some(types::DataValue::Double(1000.2)), // max
+                "Run of the mill test array".to_owned(),
+                None, // min
+                None, // max
                 None,
                 None,
             )
             .await
             .unwrap();
 
-        match authorized_access
+        let ts = std::time::SystemTime::now();
+        match broker
             .update_entries([(
-                entry_id,
+                id,
                 EntryUpdate {
                     path: None,
                     datapoint: Some(Datapoint {
-                        ts: timestamp,
+                        ts,
                         source_ts: None,
-                        value: types::DataValue::DoubleArray(Vec::from([value1, value2])),
+                        value: DataValue::FloatArray(vec![10.0, 20.0, 30.0, 40.0]),
                     }),
                     actuator_target: None,
                     entry_type: None,
@@ -3176,71 +7354,278 @@ pub mod tests {
             )])
             .await
         {
-            Ok(_) => Ok(entry_id),
-            Err(details) => Err(details),
+            Ok(()) => {}
+            Err(e) => {
+                panic!(
+                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
+                    e
+                )
+            }
+        }
+
+        match broker.get_datapoint(id).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.ts, ts);
+                assert_eq!(
+                    datapoint.value,
+                    DataValue::FloatArray(vec![10.0, 20.0, 30.0, 40.0])
+                );
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
+            }
         }
     }
 
-    #[tokio::test]
-    async fn test_update_entries_min_max_doublearray() {
+    async fn test_subscribe_and_get_buffer_size(buffer_size: Option<usize>) {
         let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
 
-        let timestamp = std::time::SystemTime::now();
+        let id1 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
 
-        // First item out of bound
-        match helper_add_doublearray(&broker, "test.datapoint1", -500.3, -500.0, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        let mut stream = broker
+            .subscribe(
+                HashMap::from([(id1, HashSet::from([Field::Datapoint]))]),
+                buffer_size,
+            )
+            .await
+            .expect("subscription should succeed");
+
+        // Stream should yield initial notification with current values i.e. NotAvailable
+        match stream.next().await {
+            Some(entry) => {
+                assert_eq!(entry.updates.len(), 1);
+                assert_eq!(
+                    entry.updates[0].update.path,
+                    Some("test.datapoint1".to_string())
+                );
+                assert_eq!(
+                    entry.updates[0].update.datapoint.as_ref().unwrap().value,
+                    DataValue::NotAvailable
+                );
             }
-            _ => panic!("Failure expected"),
-        }
-        // Second item out of bound
-        match helper_add_doublearray(&broker, "test.datapoint2", -500.0, -500.3, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+            None => {
+                panic!("did not expect stream end")
             }
-            _ => panic!("Failure expected"),
         }
 
-        // Both on min
-        if helper_add_doublearray(&broker, "test.datapoint3", -500.2, -500.2, timestamp)
+        broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    path: None,
+                    datapoint: Some(Datapoint {
+                        ts: SystemTime::now(),
+                        source_ts: None,
+                        value: DataValue::Int32(101),
+                    }),
+                    actuator_target: None,
+                    entry_type: None,
+                    data_type: None,
+                    description: None,
+                    allowed: None,
+                    min: None,
+                    max: None,
+                    unit: None,
+                },
+            )])
             .await
-            .is_err()
-        {
-            panic!("Success expected")
-        }
+            .expect("setting datapoint #1");
 
-        // First tto large
-        match helper_add_doublearray(&broker, "test.datapoint4", 1000.3, 1000.0, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        // Value has been set, expect the next item in stream to match.
+        match stream.next().await {
+            Some(entry) => {
+                assert_eq!(entry.updates.len(), 1);
+                assert_eq!(
+                    entry.updates[0].update.path,
+                    Some("test.datapoint1".to_string())
+                );
+                assert_eq!(
+                    entry.updates[0].update.datapoint.as_ref().unwrap().value,
+                    DataValue::Int32(101)
+                );
+            }
+            None => {
+                panic!("did not expect stream end")
             }
-            _ => panic!("Failure expected"),
         }
 
-        // Second too large
-        match helper_add_doublearray(&broker, "test.datapoint5", 1000.0, 1000.3, timestamp).await {
-            Err(err_vec) => {
-                assert_eq!(err_vec.len(), 1);
-                assert_eq!(err_vec.first().expect("").1, UpdateError::OutOfBoundsMinMax)
+        // Check that the data point has been stored as well
+        match broker.get_datapoint(id1).await {
+            Ok(datapoint) => {
+                assert_eq!(datapoint.value, DataValue::Int32(101));
+            }
+            Err(ReadError::NotFound) => {
+                panic!("expected datapoint to exist");
+            }
+            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
+                panic!("expected to be authorized");
             }
-            _ => panic!("Failure expected"),
         }
+    }
 
-        // Both on max
-        if helper_add_doublearray(&broker, "test.datapoint6", 1000.2, 1000.2, timestamp)
+    #[tokio::test]
+    async fn test_subscribe_and_get() {
+        // None and 0-1000 is valid range
+        test_subscribe_and_get_buffer_size(None).await;
+        test_subscribe_and_get_buffer_size(Some(0)).await;
+        test_subscribe_and_get_buffer_size(Some(1000)).await;
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_metadata_changes() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id1 = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
             .await
-            .is_err()
-        {
-            panic!("Success expected")
-        }
+            .expect("Register datapoint should succeed");
+
+        let mut metadata_stream = broker
+            .subscribe(
+                HashMap::from([(id1, HashSet::from([Field::Metadata]))]),
+                None,
+            )
+            .await
+            .expect("subscription should succeed");
+        let mut value_stream = broker
+            .subscribe(
+                HashMap::from([(id1, HashSet::from([Field::Datapoint]))]),
+                None,
+            )
+            .await
+            .expect("subscription should succeed");
+
+        // Initial notifications
+        let initial = metadata_stream.next().await.expect("initial notification");
+        assert_eq!(initial.reasons, vec![UpdateReason::Initial]);
+        assert_eq!(
+            initial.updates[0].update.description,
+            Some("Test datapoint 1".to_owned())
+        );
+        value_stream.next().await.expect("initial notification");
+
+        broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    description: Some("Renamed".to_owned()),
+                    min: Some(Some(DataValue::Int32(-5))),
+                    unit: Some("km/h".to_owned()),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .expect("changing metadata should succeed");
+
+        let notification = metadata_stream.next().await.expect("metadata notification");
+        assert_eq!(notification.updates.len(), 1);
+        assert_eq!(notification.reasons, vec![UpdateReason::MetadataChanged]);
+        let update = &notification.updates[0];
+        assert_eq!(update.fields, HashSet::from([Field::Metadata]));
+        assert_eq!(update.update.description, Some("Renamed".to_owned()));
+        assert_eq!(update.update.min, Some(Some(DataValue::Int32(-5))));
+        assert_eq!(update.update.max, Some(None));
+        assert_eq!(update.update.unit, Some("km/h".to_owned()));
+        assert_eq!(update.update.data_type, Some(DataType::Int32));
+        assert!(update.update.datapoint.is_none());
+
+        // Setting the same metadata again is not a change
+        broker
+            .update_entries([(
+                id1,
+                EntryUpdate {
+                    description: Some("Renamed".to_owned()),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .expect("changing metadata should succeed");
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), metadata_stream.next())
+                .await
+                .is_err()
+        );
+        // Value subscribers are not notified about metadata changes
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), value_stream.next())
+                .await
+                .is_err()
+        );
+
+        let metadata = broker.get_metadata(id1).await.unwrap();
+        assert_eq!(metadata.description, "Renamed");
+        assert_eq!(metadata.min, Some(DataValue::Int32(-5)));
+        assert_eq!(metadata.unit, Some("km/h".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_change_metadata_requires_create_permission() {
+        let broker = DataBroker::default();
+        let id1 = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let permissions = Permissions::builder()
+            .add_provide_permission(permissions::Permission::All)
+            .build()
+            .unwrap();
+        let broker = broker.authorized_access(&permissions);
+        assert_eq!(
+            broker
+                .update_entries([(
+                    id1,
+                    EntryUpdate {
+                        max: Some(Some(DataValue::Int32(5))),
+                        ..Default::default()
+                    },
+                )])
+                .await,
+            Err(vec![(id1, UpdateError::PermissionDenied)])
+        );
     }
 
     #[tokio::test]
-    async fn test_subscribe_query_and_get() {
+    async fn test_subscribe_buffersize_out_of_range() {
         let broker = DataBroker::default();
         let broker = broker.authorized_access(&permissions::ALLOW_ALL);
 
@@ -3259,185 +7644,328 @@ pub mod tests {
             .await
             .expect("Register datapoint should succeed");
 
-        let mut stream = broker
-            .subscribe_query("SELECT test.datapoint1")
+        match broker
+            .subscribe(
+                HashMap::from([(id1, HashSet::from([Field::Datapoint]))]),
+                // 1001 is just outside valid range 0-1000
+                Some(1001),
+            )
             .await
-            .expect("Setup subscription");
-
-        // Expect an initial query response
-        // No value has been set yet, so value should be NotAvailable
-        match stream.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
-            }
-            None => {
-                panic!("did not expect stream end")
+        {
+            Err(SubscriptionError::InvalidBufferSize) => {}
+            _ => {
+                panic!("expected it to fail with InvalidBufferSize");
             }
         }
+    }
 
-        broker
-            .update_entries([(
-                id1,
-                EntryUpdate {
-                    path: None,
-                    datapoint: Some(Datapoint {
-                        ts: SystemTime::now(),
-                        source_ts: None,
-                        value: DataValue::Int32(101),
-                    }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
-                },
-            )])
-            .await
-            .expect("setting datapoint #1");
+    #[tokio::test]
+    async fn test_metadata_for_each() {
+        let db = DataBroker::default();
+        let broker = db.authorized_access(&permissions::ALLOW_ALL);
 
-        // Value has been set, expect the next item in stream to match.
-        match stream.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::Int32(101));
-            }
-            None => {
-                panic!("did not expect stream end")
-            }
-        }
+        let id1 = broker
+            .add_entry(
+                "Vehicle.Test1".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Run of the mill test signal".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let id2 = broker
+            .add_entry(
+                "Vehicle.Test2".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Run of the mill test signal".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
 
-        // Check that the data point has been stored as well
-        match broker.get_datapoint(id1).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.value, DataValue::Int32(101));
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
+        // No permissions
+        let permissions = Permissions::builder().build().unwrap();
+        let broker = db.authorized_access(&permissions);
+        let metadata = broker.map_entries(|entry| entry.metadata().clone()).await;
+        for entry in metadata {
+            match entry.path.as_str() {
+                "Vehicle.Test1" => assert_eq!(entry.id, id1),
+                "Vehicle.Test2" => assert_eq!(entry.id, id2),
+                _ => panic!("Unexpected metadata entry"),
             }
         }
     }
 
     #[tokio::test]
-    async fn test_multi_subscribe() {
+    async fn test_register_invalid_and_valid_path() {
         let broker = DataBroker::default();
         let broker = broker.authorized_access(&permissions::ALLOW_ALL);
 
-        let id1 = broker
+        let error = broker
             .add_entry(
-                "test.datapoint1".to_owned(),
-                DataType::Int32,
+                "test. signal:3".to_owned(),
+                DataType::String,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Test datapoint 1".to_owned(),
+                "Test signal 3".to_owned(),
                 None, // min
                 None, // max
-                None,
+                Some(DataValue::Int32Array(Vec::from([1, 2, 3, 4]))),
                 None,
             )
             .await
-            .expect("Register datapoint should succeed");
-
-        let mut subscription1 = broker
-            .subscribe_query("SELECT test.datapoint1")
-            .await
-            .expect("setup first subscription");
+            .unwrap_err();
+        assert_eq!(error, RegistrationError::ValidationError);
 
-        let mut subscription2 = broker
-            .subscribe_query("SELECT test.datapoint1")
+        let id = broker
+            .add_entry(
+                "Vehicle._kuksa.databroker.GitVersion.Do_you_not_like_smörgåstårta.tschö_mit_ö.東京_Москва_r#true".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None, // min
+                None, // max
+                Some(DataValue::BoolArray(Vec::from([true]))),
+                None,
+            )
             .await
-            .expect("setup second subscription");
-
-        // Expect an initial query response
-        // No value has been set yet, so value should be NotAvailable
-        match subscription1.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
-            }
-            None => {
-                panic!("did not expect stream end")
+            .expect("Register datapoint should succeed");
+        {
+            match broker.get_entry_by_id(id).await {
+                Ok(entry) => {
+                    assert_eq!(entry.metadata.id, id);
+                    assert_eq!(entry.metadata.path, "Vehicle._kuksa.databroker.GitVersion.Do_you_not_like_smörgåstårta.tschö_mit_ö.東京_Москва_r#true");
+                    assert_eq!(entry.metadata.data_type, DataType::Bool);
+                    assert_eq!(entry.metadata.description, "Test datapoint");
+                    assert_eq!(
+                        entry.metadata.allowed,
+                        Some(DataValue::BoolArray(Vec::from([true])))
+                    );
+                }
+                Err(_) => {
+                    panic!("no metadata returned");
+                }
             }
         }
+    }
 
-        // Expect an initial query response
-        // No value has been set yet, so value should be NotAvailable
-        match subscription2.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
-            }
-            None => {
-                panic!("did not expect stream end")
-            }
+    #[tokio::test]
+    async fn test_reserved_ids() {
+        let broker = DataBroker::default();
+        broker
+            .reserve_ids([("test.b".to_owned(), 7), ("test.a".to_owned(), 3)])
+            .await;
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let mut ids = Vec::new();
+        for path in ["test.a", "test.c", "test.b"] {
+            let id = authorized_access
+                .add_entry(
+                    path.to_owned(),
+                    DataType::Int32,
+                    ChangeType::OnChange,
+                    EntryType::Sensor,
+                    "Test datapoint".to_owned(),
+                    None, // min
+                    None, // max
+                    None,
+                    None,
+                )
+                .await
+                .expect("Register datapoint should succeed");
+            ids.push(id);
         }
+        // New paths get ids above the reserved ones
+        assert_eq!(ids, vec![3, 8, 7]);
+        assert_eq!(broker.get_ids().await.get("test.c"), Some(&8));
+    }
 
-        for i in 0..100 {
-            broker
-                .update_entries([(
-                    id1,
-                    EntryUpdate {
-                        path: None,
-                        datapoint: Some(Datapoint {
-                            ts: SystemTime::now(),
-                            source_ts: None,
-                            value: DataValue::Int32(i),
-                        }),
-                        actuator_target: None,
-                        entry_type: None,
-                        data_type: None,
-                        description: None,
-                        allowed: None,
-                        min: None,
-                        max: None,
-                        unit: None,
-                    },
-                )])
+    #[tokio::test]
+    async fn test_case_insensitive_paths() {
+        let broker = DataBroker::default().with_case_insensitive_paths();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let mut ids = Vec::new();
+        for path in ["Vehicle.IsMoving", "Vehicle.Speed"] {
+            let id = authorized_access
+                .add_entry(
+                    path.to_owned(),
+                    DataType::Bool,
+                    ChangeType::OnChange,
+                    EntryType::Sensor,
+                    "Test datapoint".to_owned(),
+                    None, // min
+                    None, // max
+                    None,
+                    None,
+                )
                 .await
-                .expect("setting datapoint #1");
+                .expect("Register datapoint should succeed");
+            ids.push(id);
+        }
+
+        assert_eq!(
+            authorized_access.get_id_by_path("vehicle.ismoving").await,
+            Some(ids[0])
+        );
+        let metadata = authorized_access
+            .get_metadata_by_path("Vehicle.isMoving")
+            .await
+            .unwrap();
+        // The canonical casing
+        assert_eq!(metadata.path, "Vehicle.IsMoving");
+
+        // Registering with another casing returns the existing entry
+        let id = authorized_access
+            .add_entry(
+                "vehicle.ISMOVING".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, ids[0]);
+
+        // Case sensitive by default
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        authorized_access
+            .add_entry(
+                "Vehicle.IsMoving".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            authorized_access.get_id_by_path("Vehicle.isMoving").await,
+            None
+        );
+    }
 
-            if let Some(query_resp) = subscription1.next().await {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                if let DataValue::Int32(value) = query_resp.fields[0].value {
-                    assert_eq!(value, i);
-                } else {
-                    panic!("expected test.datapoint1 to contain a value");
-                }
-            } else {
-                panic!("did not expect end of stream");
-            }
+    #[tokio::test]
+    async fn test_quotas() {
+        let broker = DataBroker::default().with_quotas(Quotas {
+            max_entries: Some(2),
+            max_subscriptions_per_client: Some(1),
+            max_provider_streams_per_client: Some(1),
+            max_provider_stream_rate: None,
+            max_subscriber_bytes_per_sec: None,
+            max_dispatch_bytes_per_sec: None,
+        });
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
 
-            if let Some(query_resp) = subscription2.next().await {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                if let DataValue::Int32(value) = query_resp.fields[0].value {
-                    assert_eq!(value, i);
-                } else {
-                    panic!("expected test.datapoint1 to contain a value");
-                }
-            } else {
-                panic!("did not expect end of stream");
-            }
+        let mut ids = Vec::new();
+        for path in ["test.a", "test.b", "test.c"] {
+            ids.push(
+                authorized_access
+                    .add_entry(
+                        path.to_owned(),
+                        DataType::Int32,
+                        ChangeType::OnChange,
+                        EntryType::Sensor,
+                        "Test datapoint".to_owned(),
+                        None, // min
+                        None, // max
+                        None,
+                        None,
+                    )
+                    .await,
+            );
         }
+        assert!(ids[0].is_ok());
+        assert!(ids[1].is_ok());
+        assert_eq!(ids[2], Err(RegistrationError::QuotaExceeded));
+        let id = *ids[0].as_ref().unwrap();
+
+        let client_a = Permissions::builder()
+            .subject("a")
+            .add_read_permission(permissions::Permission::All)
+            .build()
+            .unwrap();
+        let client_b = Permissions::builder()
+            .subject("b")
+            .add_read_permission(permissions::Permission::All)
+            .build()
+            .unwrap();
+        let entries = HashMap::from([(id, HashSet::from([Field::Datapoint]))]);
+
+        let subscription = broker
+            .authorized_access(&client_a)
+            .subscribe(entries.clone(), None)
+            .await
+            .expect("first subscription should succeed");
+        assert!(matches!(
+            broker
+                .authorized_access(&client_a)
+                .subscribe(entries.clone(), None)
+                .await,
+            Err(SubscriptionError::QuotaExceeded)
+        ));
+        // Other clients have their own quota
+        let _other = broker
+            .authorized_access(&client_b)
+            .subscribe(entries.clone(), None)
+            .await
+            .expect("subscription of another client should succeed");
+        // Closed subscriptions don't count
+        drop(subscription);
+        let _subscription = broker
+            .authorized_access(&client_a)
+            .subscribe(entries, None)
+            .await
+            .expect("subscription should succeed after closing the first one");
+
+        let stream = broker
+            .authorized_access(&client_a)
+            .open_provider_stream()
+            .expect("first provider stream should be accepted");
+        assert!(broker
+            .authorized_access(&client_a)
+            .open_provider_stream()
+            .is_err());
+        assert_eq!(broker.get_usage().await.provider_streams, 1);
+        drop(stream);
+        assert_eq!(broker.get_usage().await.provider_streams, 0);
+        assert!(broker
+            .authorized_access(&client_a)
+            .open_provider_stream()
+            .is_ok());
+
+        let usage = broker.get_usage().await;
+        assert_eq!(usage.entries, 2);
+        assert_eq!(usage.subscriptions, 2);
+        assert_eq!(usage.quota_rejections, 3);
     }
 
     #[tokio::test]
-    async fn test_subscribe_after_new_registration() {
+    async fn test_cleanup_and_compact_subscriptions() {
         let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
-        let id1 = broker
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
             .add_entry(
                 "test.datapoint1".to_owned(),
                 DataType::Int32,
@@ -3450,142 +7978,247 @@ pub mod tests {
                 None,
             )
             .await
-            .expect("Register datapoint should succeed");
+            .unwrap();
+        let entries = HashMap::from([(id, HashSet::from([Field::Datapoint]))]);
+
+        // Clients reconnecting over and over
+        let mut streams = Vec::new();
+        for _ in 0..100 {
+            streams.push(
+                authorized_access
+                    .subscribe(entries.clone(), None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let kept = streams.pop();
+        drop(streams);
+
+        let mut subscriptions = broker.subscriptions.write().await;
+        assert_eq!(subscriptions.cleanup(), 99);
+        assert_eq!(subscriptions.change_subscriptions.len(), 1);
+        assert!(subscriptions.change_subscriptions.capacity() >= 100);
+        subscriptions.compact();
+        assert!(subscriptions.change_subscriptions.capacity() < 100);
+        drop(subscriptions);
+
+        let usage = broker.get_usage().await;
+        assert_eq!(usage.subscriptions, 1);
+        assert_eq!(usage.removed_subscriptions, 99);
+        drop(kept);
+    }
 
-        let mut subscription = broker
-            .subscribe_query("SELECT test.datapoint1")
-            .await
-            .expect("Setup subscription");
+    #[tokio::test]
+    async fn test_provider_stream_rate() {
+        let clock = clock::VirtualClock::default();
+        let broker = DataBroker::default()
+            .with_clock(Arc::new(clock.clone()))
+            .with_quotas(Quotas {
+                max_provider_stream_rate: Some(10),
+                ..Default::default()
+            });
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
 
-        // Expect an initial query response
-        // No value has been set yet, so value should be NotAvailable
-        match subscription.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
-            }
-            None => {
-                panic!("did not expect stream end")
+        let mut stream = authorized_access.open_provider_stream().unwrap();
+        // A burst of one second worth of updates
+        for _ in 0..10 {
+            assert_eq!(stream.admit(1, 100), Ok(()));
+        }
+        assert_eq!(stream.admit(1, 100), Err(ProviderStreamError::Throttled));
+
+        // Refilled at the configured rate
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(stream.admit(5, 500), Ok(()));
+        assert_eq!(stream.admit(1, 100), Err(ProviderStreamError::Throttled));
+
+        // An oversized message is admitted, but has to be paid off
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(stream.admit(20, 2000), Ok(()));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(stream.admit(1, 100), Err(ProviderStreamError::Throttled));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(stream.admit(1, 100), Ok(()));
+
+        // Other streams have their own budget
+        let mut other_stream = authorized_access.open_provider_stream().unwrap();
+        assert_eq!(other_stream.admit(10, 1000), Ok(()));
+
+        let stats = broker.get_provider_stats();
+        let stats = stats.get(&None).unwrap();
+        assert_eq!(stats.updates, 10 + 5 + 20 + 1 + 10);
+        assert_eq!(stats.throttled, 3);
+        assert_eq!(stats.bytes, 4900);
+        // Only the second stream updated values during the last second
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(broker.get_provider_stats()[&None].updates_per_second, 11);
+
+        let usage = broker.get_usage().await;
+        assert_eq!(usage.ingress_updates, 46);
+        assert_eq!(usage.ingress_bytes, 4900);
+        assert_eq!(usage.throttled_updates, 3);
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let mut events = broker.subscribe_events();
+
+        let id = authorized_access
+            .add_entry(
+                "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Actuator,
+                "Test actuator".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            Event::EntryAdded {
+                id,
+                path: "Vehicle.Cabin.Light.IsDomeOn".to_owned()
             }
-        }
+        );
 
-        broker
+        // Registering it again adds nothing
+        authorized_access
+            .add_entry(
+                "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Actuator,
+                "Test actuator".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+
+        let datapoint = Datapoint {
+            ts: SystemTime::now(),
+            source_ts: None,
+            value: DataValue::Bool(true),
+        };
+        authorized_access
             .update_entries([(
-                id1,
+                id,
                 EntryUpdate {
-                    path: None,
-                    datapoint: Some(Datapoint {
-                        ts: SystemTime::now(),
-                        source_ts: None,
-                        value: DataValue::Int32(200),
-                    }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    datapoint: Some(datapoint.clone()),
+                    actuator_target: Some(Some(datapoint.clone())),
+                    ..Default::default()
                 },
             )])
             .await
-            .expect("setting datapoint #1");
-
-        match subscription.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::Int32(200));
-            }
-            None => {
-                panic!("did not expect stream end")
-            }
-        }
-
-        match broker.get_datapoint(id1).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.value, DataValue::Int32(200));
-            }
-            Err(ReadError::NotFound) => {
-                panic!("datapoint expected to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
-        }
+            .unwrap();
+        let mut received = vec![events.recv().await.unwrap(), events.recv().await.unwrap()];
+        received.sort_by_key(|event| matches!(event, Event::TargetSet { .. }));
+        assert_eq!(
+            received,
+            vec![
+                Event::ValueChanged {
+                    id,
+                    path: "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                    datapoint: datapoint.clone(),
+                },
+                Event::TargetSet {
+                    id,
+                    path: "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                    target: Some(datapoint),
+                },
+            ]
+        );
+
+        let stream = authorized_access.open_provider_stream().unwrap();
+        assert_eq!(
+            events.recv().await.unwrap(),
+            Event::ProviderConnected { client: None }
+        );
+        drop(stream);
+        assert_eq!(
+            events.recv().await.unwrap(),
+            Event::ProviderDisconnected { client: None }
+        );
+    }
 
-        let id2 = broker
+    #[tokio::test]
+    async fn test_update_reasons() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = broker
             .add_entry(
-                "test.datapoint1".to_owned(),
-                DataType::Int32,
+                "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                DataType::Bool,
                 ChangeType::OnChange,
-                EntryType::Sensor,
-                "Test datapoint 1 (new description)".to_owned(),
+                EntryType::Actuator,
+                "Test actuator".to_owned(),
                 None, // min
                 None, // max
                 None,
                 None,
             )
             .await
-            .expect("Registration should succeed");
+            .unwrap();
 
-        assert_eq!(id1, id2, "Re-registration should result in the same id");
+        let mut stream = broker
+            .subscribe(
+                HashMap::from([(id, HashSet::from([Field::Datapoint, Field::ActuatorTarget]))]),
+                None,
+            )
+            .await
+            .unwrap();
+        let initial = stream.next().await.unwrap();
+        assert_eq!(initial.reasons, vec![UpdateReason::Initial]);
 
+        let datapoint = Datapoint {
+            ts: SystemTime::now(),
+            source_ts: None,
+            value: DataValue::Bool(true),
+        };
         broker
             .update_entries([(
-                id1,
+                id,
                 EntryUpdate {
-                    path: None,
-                    datapoint: Some(Datapoint {
-                        ts: SystemTime::now(),
-                        source_ts: None,
-                        value: DataValue::Int32(102),
-                    }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    actuator_target: Some(Some(datapoint.clone())),
+                    ..Default::default()
                 },
             )])
             .await
-            .expect("setting datapoint #1 (second time)");
-
-        match subscription.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 1);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::Int32(102));
-            }
-            None => {
-                panic!("did not expect stream end")
-            }
-        }
+            .unwrap();
+        let notification = stream.next().await.unwrap();
+        assert_eq!(notification.reasons, vec![UpdateReason::TargetChanged]);
 
-        match broker.get_datapoint(id1).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.value, DataValue::Int32(102));
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
-        }
+        broker
+            .update_entries([(
+                id,
+                EntryUpdate {
+                    datapoint: Some(datapoint.clone()),
+                    actuator_target: Some(None),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+        let notification = stream.next().await.unwrap();
+        assert_eq!(
+            notification.reasons,
+            vec![UpdateReason::ValueChanged, UpdateReason::TargetChanged]
+        );
     }
 
     #[tokio::test]
-    async fn test_subscribe_set_multiple() {
+    async fn test_subscribe_without_initial_snapshot() {
         let broker = DataBroker::default();
         let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
-        let id1 = broker
+        let id = broker
             .add_entry(
                 "test.datapoint1".to_owned(),
                 DataType::Int32,
@@ -3598,122 +8231,124 @@ pub mod tests {
                 None,
             )
             .await
-            .expect("Register datapoint should succeed");
+            .unwrap();
 
-        let id2 = broker
+        let mut stream = broker
+            .subscribe_with_snapshot(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                None,
+                InitialSnapshot::Skip,
+            )
+            .await
+            .unwrap();
+        let mut query_stream = broker
+            .subscribe_query_with_snapshot("SELECT test.datapoint1", InitialSnapshot::Skip)
+            .await
+            .unwrap();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), stream.next())
+                .await
+                .is_err()
+        );
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), query_stream.next())
+                .await
+                .is_err()
+        );
+
+        broker
+            .update_entries([(
+                id,
+                EntryUpdate {
+                    datapoint: Some(Datapoint {
+                        ts: SystemTime::now(),
+                        source_ts: None,
+                        value: DataValue::Int32(1),
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+        let notification = stream.next().await.unwrap();
+        assert_eq!(notification.reasons, vec![UpdateReason::ValueChanged]);
+        let query_response = query_stream.next().await.unwrap();
+        assert_eq!(query_response.reason, UpdateReason::ValueChanged);
+        assert_eq!(query_response.fields[0].value, DataValue::Int32(1));
+    }
+
+    #[tokio::test]
+    async fn test_event_signals() {
+        let broker = DataBroker::default();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = broker
             .add_entry(
-                "test.datapoint2".to_owned(),
-                DataType::Int32,
-                ChangeType::OnChange,
+                "Vehicle.Cabin.DoorSlammed".to_owned(),
+                DataType::Bool,
+                ChangeType::Event,
                 EntryType::Sensor,
-                "Test datapoint 2".to_owned(),
+                "Door slammed".to_owned(),
                 None, // min
                 None, // max
                 None,
                 None,
             )
             .await
-            .expect("Register datapoint should succeed");
-
-        let mut subscription = broker
-            .subscribe_query("SELECT test.datapoint1, test.datapoint2")
+            .unwrap();
+        let mut stream = broker
+            .subscribe_with_snapshot(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                None,
+                InitialSnapshot::Skip,
+            )
             .await
-            .expect("setup first subscription");
-
-        // Expect an initial query response
-        // No value has been set yet, so value should be NotAvailable
-        match subscription.next().await {
-            Some(query_resp) => {
-                assert_eq!(query_resp.fields.len(), 2);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                assert_eq!(query_resp.fields[0].value, DataValue::NotAvailable);
-                assert_eq!(query_resp.fields[1].name, "test.datapoint2");
-                assert_eq!(query_resp.fields[1].value, DataValue::NotAvailable);
-            }
-            None => {
-                panic!("did not expect stream end")
-            }
-        }
+            .unwrap();
 
-        for i in 0..10 {
+        // Repeated occurrences are all delivered
+        for _ in 0..2 {
             broker
-                .update_entries([
-                    (
-                        id1,
-                        EntryUpdate {
-                            path: None,
-                            datapoint: Some(Datapoint {
-                                ts: SystemTime::now(),
-                                source_ts: None,
-                                value: DataValue::Int32(-i),
-                            }),
-                            actuator_target: None,
-                            entry_type: None,
-                            data_type: None,
-                            description: None,
-                            allowed: None,
-                            min: None,
-                            max: None,
-                            unit: None,
-                        },
-                    ),
-                    (
-                        id2,
-                        EntryUpdate {
-                            path: None,
-                            datapoint: Some(Datapoint {
-                                ts: SystemTime::now(),
-                                source_ts: None,
-                                value: DataValue::Int32(i),
-                            }),
-                            actuator_target: None,
-                            entry_type: None,
-                            data_type: None,
-                            description: None,
-                            allowed: None,
-                            min: None,
-                            max: None,
-                            unit: None,
-                        },
-                    ),
-                ])
+                .update_entries([(
+                    id,
+                    EntryUpdate {
+                        datapoint: Some(Datapoint {
+                            ts: SystemTime::now(),
+                            source_ts: None,
+                            value: DataValue::Bool(true),
+                        }),
+                        ..Default::default()
+                    },
+                )])
                 .await
-                .expect("setting datapoint #1");
+                .unwrap();
+            let notification = stream.next().await.unwrap();
+            assert_eq!(
+                notification.updates[0]
+                    .update
+                    .datapoint
+                    .as_ref()
+                    .unwrap()
+                    .value,
+                DataValue::Bool(true)
+            );
         }
 
-        for i in 0..10 {
-            if let Some(query_resp) = subscription.next().await {
-                assert_eq!(query_resp.fields.len(), 2);
-                assert_eq!(query_resp.fields[0].name, "test.datapoint1");
-                if let DataValue::Int32(value) = query_resp.fields[0].value {
-                    assert_eq!(value, -i);
-                } else {
-                    panic!("expected test.datapoint1 to contain a values");
-                }
-                assert_eq!(query_resp.fields[1].name, "test.datapoint2");
-                if let DataValue::Int32(value) = query_resp.fields[1].value {
-                    assert_eq!(value, i);
-                } else {
-                    panic!("expected test.datapoint2 to contain a values");
-                }
-            } else {
-                panic!("did not expect end of stream");
-            }
-        }
+        // But not kept as current value
+        let datapoint = broker.get_datapoint(id).await.unwrap();
+        assert_eq!(datapoint.value, DataValue::NotAvailable);
     }
 
     #[tokio::test]
-    async fn test_bool_array() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
+    async fn test_subscription_filter() {
+        let clock = clock::VirtualClock::default();
+        let data_broker = DataBroker::default().with_clock(Arc::new(clock.clone()));
+        let broker = data_broker.authorized_access(&permissions::ALLOW_ALL);
         let id = broker
             .add_entry(
-                "Vehicle.TestArray".to_owned(),
-                DataType::BoolArray,
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Run of the mill test array".to_owned(),
+                "Test datapoint 1".to_owned(),
                 None, // min
                 None, // max
                 None,
@@ -3722,67 +8357,131 @@ pub mod tests {
             .await
             .unwrap();
 
-        let ts = std::time::SystemTime::now();
-        match broker
-            .update_entries([(
+        let mut stream = broker
+            .subscribe_with_filter(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                Some(10),
+                InitialSnapshot::Skip,
+                SubscriptionFilter {
+                    min_interval: Some(Duration::from_millis(100)),
+                    min_change: Some(5.0),
+                    duration: None,
+                    max_bytes_per_sec: None,
+                    backfill_since: None,
+                    priority: SubscriptionPriority::Telemetry,
+                    snapshots: false,
+                },
+            )
+            .await
+            .unwrap();
+
+        let update = |value| {
+            (
                 id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::BoolArray(vec![true, true, false, true]),
+                        value: DataValue::Int32(value),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
-            )])
-            .await
+            )
+        };
+        let next_value = |notification: EntryUpdates| {
+            notification.updates[0]
+                .update
+                .datapoint
+                .as_ref()
+                .unwrap()
+                .value
+                .clone()
+        };
+
+        broker.update_entries([update(10)]).await.unwrap();
+        assert_eq!(
+            next_value(stream.next().await.unwrap()),
+            DataValue::Int32(10)
+        );
+
+        // Too small a change
+        broker.update_entries([update(12)]).await.unwrap();
+        // Big enough, but too soon
+        broker.update_entries([update(16)]).await.unwrap();
+        clock.advance(Duration::from_millis(50));
+        broker.update_entries([update(20)]).await.unwrap();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), stream.next())
+                .await
+                .is_err()
+        );
+
+        // The latest value is sent once the interval has passed
+        clock.advance(Duration::from_millis(50));
         {
-            Ok(()) => {}
-            Err(e) => {
-                panic!(
-                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
-                    e
-                )
-            }
+            let db = data_broker.database.read().await;
+            data_broker
+                .subscriptions
+                .read()
+                .await
+                .notify_held(&db)
+                .await;
         }
+        let notification = stream.next().await.unwrap();
+        assert_eq!(notification.reasons, vec![UpdateReason::ValueChanged]);
+        assert_eq!(next_value(notification), DataValue::Int32(20));
 
-        match broker.get_datapoint(id).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.ts, ts);
-                assert_eq!(
-                    datapoint.value,
-                    DataValue::BoolArray(vec![true, true, false, true])
-                );
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
+        // A change held back is dropped when the value returns
+        broker.update_entries([update(30)]).await.unwrap();
+        broker.update_entries([update(21)]).await.unwrap();
+        clock.advance(Duration::from_millis(100));
+        {
+            let db = data_broker.database.read().await;
+            data_broker
+                .subscriptions
+                .read()
+                .await
+                .notify_held(&db)
+                .await;
         }
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), stream.next())
+                .await
+                .is_err()
+        );
+
+        // The subscription ends after the duration
+        let mut stream = broker
+            .subscribe_with_filter(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                None,
+                InitialSnapshot::Send,
+                SubscriptionFilter {
+                    duration: Some(Duration::from_millis(50)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            next_value(stream.next().await.unwrap()),
+            DataValue::Int32(21)
+        );
+        assert!(stream.next().await.is_none());
     }
 
     #[tokio::test]
-    async fn test_string_array() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
+    async fn test_subscription_bandwidth_budget() {
+        let clock = clock::VirtualClock::default();
+        let data_broker = DataBroker::default().with_clock(Arc::new(clock.clone()));
+        let broker = data_broker.authorized_access(&permissions::ALLOW_ALL);
         let id = broker
             .add_entry(
-                "Vehicle.TestArray".to_owned(),
-                DataType::StringArray,
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Run of the mill test array".to_owned(),
+                "Test datapoint 1".to_owned(),
                 None, // min
                 None, // max
                 None,
@@ -3791,738 +8490,1207 @@ pub mod tests {
             .await
             .unwrap();
 
-        let ts = std::time::SystemTime::now();
-        match broker
-            .update_entries([(
+        // Each notification is estimated at 35 bytes (path, value and overhead)
+        let mut stream = broker
+            .subscribe_with_filter(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                Some(10),
+                InitialSnapshot::Skip,
+                SubscriptionFilter {
+                    max_bytes_per_sec: Some(100),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let update = |value| {
+            (
                 id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::StringArray(vec![
-                            String::from("yes"),
-                            String::from("no"),
-                            String::from("maybe"),
-                            String::from("nah"),
-                        ]),
+                        value: DataValue::Int32(value),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
-            )])
-            .await
-        {
-            Ok(_) => {}
-            Err(e) => {
-                panic!(
-                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
-                    e
-                )
-            }
+            )
+        };
+        let next_value = |notification: &EntryUpdates| {
+            notification.updates[0]
+                .update
+                .datapoint
+                .as_ref()
+                .unwrap()
+                .value
+                .clone()
+        };
+
+        // Within the budget
+        for value in 1..=3 {
+            broker.update_entries([update(value)]).await.unwrap();
+            let notification = stream.next().await.unwrap();
+            assert_eq!(next_value(&notification), DataValue::Int32(value));
+            assert!(!notification.shaped);
         }
 
-        match broker.get_datapoint(id).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.ts, ts);
-                assert_eq!(
-                    datapoint.value,
-                    DataValue::StringArray(vec![
-                        String::from("yes"),
-                        String::from("no"),
-                        String::from("maybe"),
-                        String::from("nah"),
-                    ])
-                );
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
+        // Over the budget, the values are conflated
+        broker.update_entries([update(4)]).await.unwrap();
+        broker.update_entries([update(5)]).await.unwrap();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), stream.next())
+                .await
+                .is_err()
+        );
+
+        // The latest value is sent once the budget allows
+        clock.advance(Duration::from_secs(1));
+        {
+            let db = data_broker.database.read().await;
+            data_broker
+                .subscriptions
+                .read()
+                .await
+                .notify_held(&db)
+                .await;
         }
+        let notification = stream.next().await.unwrap();
+        assert_eq!(next_value(&notification), DataValue::Int32(5));
+        assert!(notification.shaped);
+
+        broker.update_entries([update(6)]).await.unwrap();
+        let notification = stream.next().await.unwrap();
+        assert_eq!(next_value(&notification), DataValue::Int32(6));
+        assert!(!notification.shaped);
     }
 
     #[tokio::test]
-    async fn test_string_array_allowed_values() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
+    async fn test_client_bandwidth_budget() {
+        let clock = clock::VirtualClock::default();
+        let data_broker = DataBroker::default()
+            .with_clock(Arc::new(clock.clone()))
+            .with_quotas(Quotas {
+                max_subscriber_bytes_per_sec: Some(100),
+                ..Default::default()
+            });
+        let broker = data_broker.authorized_access(&permissions::ALLOW_ALL);
         let id = broker
             .add_entry(
-                "Vehicle.TestArray".to_owned(),
-                DataType::StringArray,
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Run of the mill test array".to_owned(),
+                "Test datapoint 1".to_owned(),
                 None, // min
                 None, // max
-                Some(DataValue::StringArray(vec![
-                    String::from("yes"),
-                    String::from("no"),
-                    String::from("maybe"),
-                    String::from("nah"),
-                ])),
+                None,
                 None,
             )
             .await
             .unwrap();
-
-        let ts = std::time::SystemTime::now();
-        match broker
-            .update_entries([(
+        let update = |value| {
+            (
                 id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::StringArray(vec![
-                            String::from("yes"),
-                            String::from("no"),
-                            String::from("maybe"),
-                            String::from("nah"),
-                        ]),
+                        value: DataValue::Int32(value),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
-            )])
-            .await
-        {
-            Ok(_) => {}
-            Err(e) => {
-                panic!(
-                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
-                    e
-                )
+            )
+        };
+
+        // The subscriptions of the client share its budget of 100 bytes,
+        // each notification is estimated at 35 bytes
+        let mut streams = Vec::new();
+        for _ in 0..2 {
+            streams.push(
+                broker
+                    .subscribe_with_snapshot(
+                        HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                        Some(10),
+                        InitialSnapshot::Skip,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+        fn received(streams: &mut [impl Stream<Item = EntryUpdates> + Unpin]) -> Vec<EntryUpdates> {
+            let mut received = Vec::new();
+            for stream in streams {
+                if let Some(Some(notification)) = futures::FutureExt::now_or_never(stream.next()) {
+                    received.push(notification);
+                }
             }
+            received
         }
 
-        match broker.get_datapoint(id).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.ts, ts);
-                assert_eq!(
-                    datapoint.value,
-                    DataValue::StringArray(vec![
-                        String::from("yes"),
-                        String::from("no"),
-                        String::from("maybe"),
-                        String::from("nah"),
-                    ])
-                );
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
+        broker.update_entries([update(1)]).await.unwrap();
+        assert_eq!(received(&mut streams).len(), 2);
+
+        // Only one of them fits in the rest of the budget
+        broker.update_entries([update(2)]).await.unwrap();
+        let notifications = received(&mut streams);
+        assert_eq!(notifications.len(), 1);
+        assert!(!notifications[0].shaped);
+
+        clock.advance(Duration::from_secs(1));
+        {
+            let db = data_broker.database.read().await;
+            data_broker
+                .subscriptions
+                .read()
+                .await
+                .notify_held(&db)
+                .await;
         }
+        let notifications = received(&mut streams);
+        assert_eq!(notifications.len(), 1);
+        assert!(notifications[0].shaped);
+    }
 
-        // check if duplicate is working
-        match broker
-            .update_entries([(
+    #[tokio::test]
+    async fn test_subscription_priority() {
+        let clock = clock::VirtualClock::default();
+        let data_broker = DataBroker::default()
+            .with_clock(Arc::new(clock.clone()))
+            .with_quotas(Quotas {
+                max_dispatch_bytes_per_sec: Some(200),
+                ..Default::default()
+            });
+        let broker = data_broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let update = |value| {
+            (
                 id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::StringArray(vec![
-                            String::from("yes"),
-                            String::from("no"),
-                            String::from("maybe"),
-                            String::from("nah"),
-                            String::from("yes"),
-                        ]),
+                        value: DataValue::Int32(value),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
-            )])
-            .await
-        {
-            Ok(_) => {}
-            Err(e) => {
-                panic!(
-                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
-                    e
-                )
+            )
+        };
+
+        // Lowest class first, each notification is estimated at 35 bytes
+        let mut streams = Vec::new();
+        for priority in [
+            SubscriptionPriority::Telemetry,
+            SubscriptionPriority::Control,
+            SubscriptionPriority::Safety,
+        ] {
+            streams.push(Box::pin(
+                broker
+                    .subscribe_with_filter(
+                        HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                        Some(10),
+                        InitialSnapshot::Skip,
+                        SubscriptionFilter {
+                            priority,
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                    .unwrap(),
+            ));
+        }
+        fn received(streams: &mut [impl Stream<Item = EntryUpdates> + Unpin]) -> Vec<usize> {
+            let mut received = Vec::new();
+            for (i, stream) in streams.iter_mut().enumerate() {
+                if let Some(Some(_)) = futures::FutureExt::now_or_never(stream.next()) {
+                    received.push(i);
+                }
             }
+            received
         }
 
-        match broker.get_datapoint(id).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.ts, ts);
-                assert_eq!(
-                    datapoint.value,
-                    DataValue::StringArray(vec![
-                        String::from("yes"),
-                        String::from("no"),
-                        String::from("maybe"),
-                        String::from("nah"),
-                        String::from("yes"),
-                    ])
-                );
-            }
-            Err(ReadError::NotFound) => panic!("Expected datapoint to exist"),
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("Expected to have access to datapoint")
-            }
+        broker.update_entries([update(1)]).await.unwrap();
+        assert_eq!(received(&mut streams), vec![0, 1, 2]);
+
+        // Telemetry is shed once half of the budget is used
+        broker.update_entries([update(2)]).await.unwrap();
+        assert_eq!(received(&mut streams), vec![1, 2]);
+
+        // Control once all of it is, safety never
+        broker.update_entries([update(3)]).await.unwrap();
+        assert_eq!(received(&mut streams), vec![2]);
+
+        clock.advance(Duration::from_secs(1));
+        {
+            let db = data_broker.database.read().await;
+            data_broker
+                .subscriptions
+                .read()
+                .await
+                .notify_held(&db)
+                .await;
         }
+        assert_eq!(received(&mut streams), vec![0, 1]);
 
-        if broker
+        // Capped by the permissions
+        let permissions = Permissions::builder()
+            .add_read_permission(permissions::Permission::All)
+            .add_subscription_priority(SubscriptionPriority::Control)
+            .build()
+            .unwrap();
+        assert_eq!(
+            permissions.subscription_priority(SubscriptionPriority::Safety),
+            SubscriptionPriority::Control
+        );
+        assert_eq!(
+            permissions.subscription_priority(SubscriptionPriority::Telemetry),
+            SubscriptionPriority::Telemetry
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_usage() {
+        let data_broker = DataBroker::default();
+        let broker = data_broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let client_a = Permissions::builder()
+            .subject("a")
+            .add_read_permission(permissions::Permission::All)
+            .build()
+            .unwrap();
+
+        data_broker.record_api_call("kuksa.val.v2", false, Some("a"));
+        data_broker.record_api_call("kuksa.val.v1", false, Some("a"));
+        let mut stream = data_broker
+            .authorized_access(&client_a)
+            .subscribe_with_snapshot(
+                HashMap::from([(id, HashSet::from([Field::Datapoint]))]),
+                None,
+                InitialSnapshot::Send,
+            )
+            .await
+            .unwrap();
+        let initial = stream.next().await.unwrap();
+        broker
             .update_entries([(
                 id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::StringArray(vec![
-                            String::from("yes"),
-                            String::from("no"),
-                            String::from("maybe"),
-                            String::from("nah"),
-                            String::from("true"),
-                        ]),
+                        value: DataValue::Int32(1),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
             )])
             .await
-            .is_ok()
-        {
-            panic!("Expected set_datapoints to fail because string(true) not in allowed values")
-        } else {
-            // everything fine vlaue string(true) not in the allowed values
-        }
+            .unwrap();
+        let update = stream.next().await.unwrap();
+
+        let usage = data_broker.get_client_usage().await;
+        assert_eq!(
+            usage,
+            vec![ClientUsage {
+                client: Some("a".to_owned()),
+                requests: 2,
+                subscriptions: 1,
+                streamed_bytes: (initial.size() + update.size()) as u64,
+            }]
+        );
+
+        drop(stream);
+        assert_eq!(data_broker.get_client_usage().await[0].subscriptions, 0);
     }
 
     #[tokio::test]
-    async fn test_int8_array() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+    async fn test_safety_gate() {
+        let gate = SafetyGate {
+            max_level: SafetyLevel::AsilA,
+            mode: SafetyGateMode::RequireScope,
+        };
+        let broker = DataBroker::default().with_safety_gate(gate);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let mut ids = Vec::new();
+        for (path, safety_level) in [
+            ("test.qm", Some(SafetyLevel::Qm)),
+            ("test.asil_b", Some(SafetyLevel::AsilB)),
+        ] {
+            let id = authorized_access
+                .add_entry(
+                    path.to_owned(),
+                    DataType::Bool,
+                    ChangeType::OnChange,
+                    EntryType::Actuator,
+                    "Test actuator".to_owned(),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            authorized_access
+                .set_safety_level(id, safety_level)
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        let (qm_id, asil_b_id) = (ids[0], ids[1]);
 
-        let id = broker
+        let set_target = |id| {
+            [(
+                id,
+                EntryUpdate {
+                    actuator_target: Some(Some(Datapoint {
+                        ts: SystemTime::now(),
+                        source_ts: None,
+                        value: DataValue::Bool(true),
+                    })),
+                    ..Default::default()
+                },
+            )]
+        };
+
+        let actuate = Permissions::builder()
+            .add_actuate_permission(permissions::Permission::All)
+            .build()
+            .unwrap();
+        let authorized_access = broker.authorized_access(&actuate);
+        assert!(authorized_access
+            .update_entries(set_target(qm_id))
+            .await
+            .is_ok());
+        assert_eq!(
+            authorized_access
+                .update_entries(set_target(asil_b_id))
+                .await
+                .unwrap_err(),
+            vec![(asil_b_id, UpdateError::PermissionDenied)]
+        );
+        assert!(matches!(
+            authorized_access
+                .actuate(&asil_b_id, &DataValue::Bool(true))
+                .await,
+            Err((ActuationError::PermissionDenied, _))
+        ));
+
+        let actuate_safety = Permissions::builder()
+            .add_actuate_permission(permissions::Permission::All)
+            .add_actuate_safety_permission(permissions::Permission::Glob("test.asil_b".to_owned()))
+            .build()
+            .unwrap();
+        let authorized_access = broker.authorized_access(&actuate_safety);
+        assert!(authorized_access
+            .update_entries(set_target(asil_b_id))
+            .await
+            .is_ok());
+        // Passes the gate, but nobody provides the actuator
+        assert!(matches!(
+            authorized_access
+                .actuate(&asil_b_id, &DataValue::Bool(true))
+                .await,
+            Err((ActuationError::ProviderNotAvailable, _))
+        ));
+
+        let broker = DataBroker::default().with_safety_gate(SafetyGate {
+            mode: SafetyGateMode::Reject,
+            ..gate
+        });
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
             .add_entry(
-                "Vehicle.TestArray".to_owned(),
-                DataType::Int8Array,
+                "test.asil_b".to_owned(),
+                DataType::Bool,
                 ChangeType::OnChange,
-                EntryType::Sensor,
-                "Run of the mill test array".to_owned(),
-                None, // min
-                None, // max
+                EntryType::Actuator,
+                "Test actuator".to_owned(),
+                None,
+                None,
                 None,
                 None,
             )
             .await
             .unwrap();
+        authorized_access
+            .set_safety_level(id, Some(SafetyLevel::AsilB))
+            .await
+            .unwrap();
+        assert_eq!(
+            authorized_access
+                .update_entries(set_target(id))
+                .await
+                .unwrap_err(),
+            vec![(id, UpdateError::PermissionDenied)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_target_expiry() {
+        let clock = clock::VirtualClock::default();
+        let broker = DataBroker::default()
+            .with_clock(Arc::new(clock.clone()))
+            .with_target_ttl(Duration::from_secs(1));
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let mut ids = Vec::new();
+        for path in ["test.default_ttl", "test.own_ttl"] {
+            let id = authorized_access
+                .add_entry(
+                    path.to_owned(),
+                    DataType::Int32,
+                    ChangeType::OnChange,
+                    EntryType::Actuator,
+                    "Test actuator".to_owned(),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        let (default_id, own_id) = (ids[0], ids[1]);
+        authorized_access
+            .set_target_ttl(own_id, Some(Duration::from_millis(200)))
+            .await
+            .unwrap();
 
-        let ts = std::time::SystemTime::now();
-        match broker
-            .update_entries([(
+        let set_target = |id| {
+            (
                 id,
-                EntryUpdate {
-                    path: None,
-                    datapoint: Some(Datapoint {
-                        ts,
+                EntryUpdate {
+                    actuator_target: Some(Some(Datapoint {
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::Int32Array(vec![10, 20, 30, 40]),
-                    }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                        value: DataValue::Int32(5),
+                    })),
+                    ..Default::default()
                 },
-            )])
+            )
+        };
+        let target = |id| {
+            let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+            async move {
+                authorized_access
+                    .get_entry_by_id(id)
+                    .await
+                    .unwrap()
+                    .actuator_target
+            }
+        };
+
+        let mut events = broker.subscribe_events();
+        authorized_access
+            .update_entries([set_target(default_id), set_target(own_id)])
             .await
-        {
-            Ok(()) => {}
-            Err(e) => {
-                panic!(
-                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
-                    e
-                )
+            .unwrap();
+
+        clock.advance(Duration::from_millis(500));
+        broker.expire_targets().await;
+        assert!(target(default_id).await.is_some());
+        assert!(target(own_id).await.is_none());
+
+        clock.advance(Duration::from_millis(500));
+        broker.expire_targets().await;
+        assert!(target(default_id).await.is_none());
+
+        let mut expired = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let Event::TargetExpired { path, .. } = event {
+                expired.push(path);
             }
         }
+        assert_eq!(expired, vec!["test.own_ttl", "test.default_ttl"]);
 
-        if broker
+        // The provider confirms the target by reaching it
+        authorized_access
+            .update_entries([set_target(default_id)])
+            .await
+            .unwrap();
+        authorized_access
             .update_entries([(
-                id,
+                default_id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::Int32Array(vec![100, 200, 300, 400]),
+                        value: DataValue::Int32(5),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
             )])
             .await
-            .is_ok()
-        {
-            panic!("Expected set_datapoints to fail ( with Err() ), instead got: Ok(())",)
+            .unwrap();
+        clock.advance(Duration::from_secs(2));
+        broker.expire_targets().await;
+        assert!(target(default_id).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_signal_stats() {
+        let clock = clock::VirtualClock::default();
+        let broker = DataBroker::default().with_clock(Arc::new(clock.clone()));
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let now = SystemTime::now();
+        let speed = helper_add_int32(&broker, "Vehicle.Speed", 1, now)
+            .await
+            .unwrap();
+        let dome = helper_add_int32(&broker, "Vehicle.Cabin.Dome", 1, now)
+            .await
+            .unwrap();
+        helper_add_int32(&broker, "Vehicle.Cabin.Door", 1, now)
+            .await
+            .unwrap();
+        let entries = HashMap::from([
+            (speed, HashSet::from([Field::Datapoint])),
+            (dome, HashSet::from([Field::Datapoint])),
+        ]);
+        let _subscription = authorized_access.subscribe(entries, None).await.unwrap();
+
+        // Updates not changing the value count as well
+        for _ in 0..20 {
+            clock.advance(Duration::from_millis(500));
+            authorized_access
+                .update_entries([(
+                    speed,
+                    EntryUpdate {
+                        datapoint: Some(Datapoint {
+                            ts: now,
+                            source_ts: None,
+                            value: DataValue::Int32(1),
+                        }),
+                        ..Default::default()
+                    },
+                )])
+                .await
+                .unwrap();
+        }
+        clock.advance(Duration::from_secs(1));
+
+        let stats = broker.get_update_stats(None).await;
+        let paths: Vec<_> = stats.iter().map(|stats| stats.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["Vehicle.Cabin.Dome", "Vehicle.Cabin.Door", "Vehicle.Speed"]
+        );
+        let speed_stats = &stats[2];
+        assert_eq!(speed_stats.updates, 21);
+        assert_eq!(speed_stats.updates_per_second, 2.0);
+        assert_eq!(speed_stats.last_update_age, Some(Duration::from_secs(1)));
+        assert_eq!(speed_stats.subscribers, 1);
+        assert_eq!(stats[1].updates, 1);
+        assert_eq!(stats[1].subscribers, 0);
+
+        let stats = broker.get_update_stats(Some(2)).await;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].path, "Vehicle.Cabin");
+        assert_eq!(stats[0].signals, 2);
+        assert_eq!(stats[0].updates, 2);
+        assert_eq!(stats[0].last_update_age, Some(Duration::from_secs(11)));
+        // The same subscription is only counted once
+        assert_eq!(stats[0].subscribers, 1);
+
+        // A silent feeder is noticed
+        clock.advance(Duration::from_secs(20));
+        let stats = broker.get_update_stats(Some(1)).await;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].signals, 3);
+        assert_eq!(stats[0].updates_per_second, 0.0);
+        assert_eq!(stats[0].subscribers, 1);
+    }
+
+    #[tokio::test]
+    async fn test_history() {
+        let mut policies = RetentionPolicies::default();
+        policies
+            .register(
+                "test.counter",
+                vec![crate::history::Tier {
+                    keep: Duration::from_secs(60),
+                    interval: None,
+                    max_samples: Some(2),
+                }],
+            )
+            .unwrap();
+        let broker = DataBroker::default().with_history(policies);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let mut ids = Vec::new();
+        for path in ["test.counter", "test.other"] {
+            ids.push(
+                authorized_access
+                    .add_entry(
+                        path.to_owned(),
+                        DataType::Uint32,
+                        ChangeType::OnChange,
+                        EntryType::Sensor,
+                        "Test counter".to_owned(),
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap(),
+            );
         }
 
-        match broker.get_datapoint(id).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.ts, ts);
-                assert_eq!(datapoint.value, DataValue::Int32Array(vec![10, 20, 30, 40]));
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
+        for value in 1..=3 {
+            authorized_access
+                .update_entries(ids.iter().map(|id| {
+                    (
+                        *id,
+                        EntryUpdate {
+                            datapoint: Some(Datapoint {
+                                ts: SystemTime::now(),
+                                source_ts: None,
+                                value: DataValue::Uint32(value),
+                            }),
+                            ..Default::default()
+                        },
+                    )
+                }))
+                .await
+                .unwrap();
         }
+
+        let history = authorized_access.get_history(ids[0], None).await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|datapoint| datapoint.value.clone())
+                .collect::<Vec<_>>(),
+            vec![DataValue::Uint32(2), DataValue::Uint32(3)]
+        );
+        assert!(authorized_access
+            .get_history(ids[1], None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            authorized_access.get_history(1000, None).await,
+            Err(ReadError::NotFound)
+        ));
     }
 
     #[tokio::test]
-    async fn test_uint8_array() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
-        let id = broker
+    async fn test_update_array_elements() {
+        let clock = clock::VirtualClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1000));
+        let broker = DataBroker::default().with_clock(Arc::new(clock.clone()));
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
             .add_entry(
-                "Vehicle.TestArray".to_owned(),
-                DataType::Uint8Array,
+                "test.array".to_owned(),
+                DataType::Uint32Array,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Run of the mill test array".to_owned(),
-                None, // min
-                None, // max
+                "Test array".to_owned(),
+                None,
+                None,
                 None,
                 None,
             )
             .await
             .unwrap();
-
-        let ts = std::time::SystemTime::now();
-        match broker
+        authorized_access
             .update_entries([(
                 id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: clock.now(),
                         source_ts: None,
-                        value: DataValue::Uint32Array(vec![10, 20, 30, 40]),
+                        value: DataValue::Uint32Array(vec![1, 2, 3]),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
             )])
             .await
-        {
-            Ok(()) => {}
-            Err(e) => {
-                panic!(
-                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
-                    e
-                )
-            }
-        }
+            .unwrap();
 
-        if broker
-            .update_entries([(
-                id,
-                EntryUpdate {
-                    path: None,
-                    datapoint: Some(Datapoint {
-                        ts,
-                        source_ts: None,
-                        value: DataValue::Uint32Array(vec![100, 200, 300, 400]),
-                    }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
-                },
-            )])
+        clock.advance(Duration::from_secs(5));
+        authorized_access
+            .update_array_elements(id, &[0, 2], &DataValue::Uint32Array(vec![4, 6]), None)
             .await
-            .is_ok()
-        {
-            panic!("Expected set_datapoints to fail ( with Err() ), instead got: Ok(())",)
-        }
-
-        match broker.get_datapoint(id).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.ts, ts);
-                assert_eq!(
-                    datapoint.value,
-                    DataValue::Uint32Array(vec![10, 20, 30, 40])
-                );
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
-        }
+            .unwrap();
+        let datapoint = authorized_access.get_datapoint(id).await.unwrap();
+        assert_eq!(datapoint.value, DataValue::Uint32Array(vec![4, 2, 6]));
+        // Stamped by the broker clock
+        assert_eq!(datapoint.ts, clock.now());
+
+        assert!(matches!(
+            authorized_access
+                .update_array_elements(id, &[3], &DataValue::Uint32Array(vec![7]), None)
+                .await,
+            Err(UpdateError::OutOfBoundsIndex)
+        ));
     }
 
     #[tokio::test]
-    async fn test_float_array() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
-        let id = broker
+    async fn test_history_expires() {
+        let mut policies = RetentionPolicies::default();
+        policies
+            .register(
+                "test.counter",
+                vec![crate::history::Tier {
+                    keep: Duration::from_secs(60),
+                    interval: None,
+                    max_samples: None,
+                }],
+            )
+            .unwrap();
+        let clock = clock::VirtualClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1000));
+        let broker = DataBroker::default()
+            .with_clock(Arc::new(clock.clone()))
+            .with_history(policies);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
             .add_entry(
-                "Vehicle.TestArray".to_owned(),
-                DataType::FloatArray,
+                "test.counter".to_owned(),
+                DataType::Uint32,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Run of the mill test array".to_owned(),
-                None, // min
-                None, // max
+                "Test counter".to_owned(),
+                None,
+                None,
                 None,
                 None,
             )
             .await
             .unwrap();
-
-        let ts = std::time::SystemTime::now();
-        match broker
+        authorized_access
             .update_entries([(
                 id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
-                        ts,
+                        ts: clock.now(),
                         source_ts: None,
-                        value: DataValue::FloatArray(vec![10.0, 20.0, 30.0, 40.0]),
+                        value: DataValue::Uint32(1),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
             )])
             .await
-        {
-            Ok(()) => {}
-            Err(e) => {
-                panic!(
-                    "Expected set_datapoints to succeed ( with Ok(()) ), instead got: Err({:?})",
-                    e
-                )
-            }
-        }
+            .unwrap();
 
-        match broker.get_datapoint(id).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.ts, ts);
-                assert_eq!(
-                    datapoint.value,
-                    DataValue::FloatArray(vec![10.0, 20.0, 30.0, 40.0])
-                );
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
+        clock.advance(Duration::from_secs(30));
+        broker.expire_history().await;
+        assert_eq!(
+            authorized_access.get_history(id, None).await.unwrap().len(),
+            1
+        );
+
+        clock.advance(Duration::from_secs(31));
+        broker.expire_history().await;
+        assert!(authorized_access
+            .get_history(id, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_backfill() {
+        let mut policies = RetentionPolicies::default();
+        policies
+            .register(
+                "test.counter",
+                vec![crate::history::Tier {
+                    keep: Duration::from_secs(60),
+                    interval: None,
+                    max_samples: None,
+                }],
+            )
+            .unwrap();
+        let broker = DataBroker::default().with_history(policies);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let mut ids = Vec::new();
+        for path in ["test.counter", "test.other"] {
+            ids.push(
+                authorized_access
+                    .add_entry(
+                        path.to_owned(),
+                        DataType::Uint32,
+                        ChangeType::OnChange,
+                        EntryType::Sensor,
+                        "Test counter".to_owned(),
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+        let start = SystemTime::now();
+        let update = |value: u32| {
+            ids.iter()
+                .map(|id| {
+                    (
+                        *id,
+                        EntryUpdate {
+                            datapoint: Some(Datapoint {
+                                ts: start + Duration::from_secs(value.into()),
+                                source_ts: None,
+                                value: DataValue::Uint32(value),
+                            }),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        for value in 1..=3 {
+            authorized_access
+                .update_entries(update(value))
+                .await
+                .unwrap();
+        }
+
+        let mut stream = authorized_access
+            .subscribe_with_filter(
+                ids.iter()
+                    .map(|id| (*id, HashSet::from([Field::Datapoint])))
+                    .collect(),
+                Some(10),
+                InitialSnapshot::Send,
+                SubscriptionFilter {
+                    backfill_since: Some(start + Duration::from_secs(1)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        // Values after the last one seen, oldest first
+        for value in [2, 3] {
+            let notification = stream.next().await.unwrap();
+            assert_eq!(notification.reasons, vec![UpdateReason::Backfill]);
+            assert_eq!(notification.updates.len(), 1);
+            assert_eq!(notification.updates[0].id, ids[0]);
+            assert_eq!(
+                notification.updates[0]
+                    .update
+                    .datapoint
+                    .as_ref()
+                    .unwrap()
+                    .value,
+                DataValue::Uint32(value)
+            );
         }
+        // The current value was part of the backfill, only the entry without
+        // history is left of the initial notification
+        let initial = stream.next().await.unwrap();
+        assert_eq!(initial.reasons, vec![UpdateReason::Initial]);
+        assert_eq!(initial.updates.len(), 1);
+        assert_eq!(initial.updates[0].id, ids[1]);
+
+        authorized_access.update_entries(update(4)).await.unwrap();
+        let notification = stream.next().await.unwrap();
+        assert_eq!(notification.reasons, vec![UpdateReason::ValueChanged]);
+        assert_eq!(notification.updates.len(), 2);
     }
 
-    async fn test_subscribe_and_get_buffer_size(buffer_size: Option<usize>) {
+    #[tokio::test]
+    async fn test_signal_group() {
         let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let ts = SystemTime::now();
+        let left = helper_add_int32(&broker, "Vehicle.Wheel.Left.Speed", 10, ts)
+            .await
+            .unwrap();
+        let right = helper_add_int32(&broker, "Vehicle.Wheel.Right.Speed", 10, ts)
+            .await
+            .unwrap();
+        helper_add_int32(&broker, "Vehicle.Speed", 10, ts)
+            .await
+            .unwrap();
 
-        let id1 = broker
-            .add_entry(
-                "test.datapoint1".to_owned(),
-                DataType::Int32,
-                ChangeType::OnChange,
-                EntryType::Sensor,
-                "Test datapoint 1".to_owned(),
-                None, // min
-                None, // max
-                None,
-                None,
+        assert!(matches!(
+            authorized_access
+                .define_signal_group(
+                    "WheelSpeeds",
+                    vec![
+                        "Vehicle.Wheel.Left.Speed".to_owned(),
+                        "Vehicle.Nope".to_owned()
+                    ],
+                )
+                .await,
+            Err(ReadError::NotFound)
+        ));
+        authorized_access
+            .define_signal_group(
+                "WheelSpeeds",
+                vec![
+                    "Vehicle.Wheel.Left.Speed".to_owned(),
+                    "Vehicle.Wheel.Right.Speed".to_owned(),
+                ],
             )
             .await
-            .expect("Register datapoint should succeed");
-
-        let mut stream = broker
-            .subscribe(
-                HashMap::from([(id1, HashSet::from([Field::Datapoint]))]),
-                buffer_size,
+            .unwrap();
+        let values = authorized_access
+            .get_signal_group("WheelSpeeds")
+            .await
+            .unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].0, "Vehicle.Wheel.Left.Speed");
+        assert_eq!(values[1].1.value, DataValue::Int32(10));
+
+        let mut stream = authorized_access
+            .subscribe_with_filter(
+                HashMap::from([
+                    (left, HashSet::from([Field::Datapoint])),
+                    (right, HashSet::from([Field::Datapoint])),
+                ]),
+                Some(10),
+                InitialSnapshot::Skip,
+                SubscriptionFilter {
+                    snapshots: true,
+                    ..Default::default()
+                },
             )
             .await
-            .expect("subscription should succeed");
-
-        // Stream should yield initial notification with current values i.e. NotAvailable
-        match stream.next().await {
-            Some(entry) => {
-                assert_eq!(entry.updates.len(), 1);
-                assert_eq!(
-                    entry.updates[0].update.path,
-                    Some("test.datapoint1".to_string())
-                );
-                assert_eq!(
-                    entry.updates[0].update.datapoint.as_ref().unwrap().value,
-                    DataValue::NotAvailable
-                );
-            }
-            None => {
-                panic!("did not expect stream end")
-            }
-        }
-
-        broker
-            .update_entries([(
-                id1,
+            .unwrap();
+        let update = |id, value| {
+            (
+                id,
                 EntryUpdate {
-                    path: None,
                     datapoint: Some(Datapoint {
                         ts: SystemTime::now(),
                         source_ts: None,
-                        value: DataValue::Int32(101),
+                        value: DataValue::Int32(value),
                     }),
-                    actuator_target: None,
-                    entry_type: None,
-                    data_type: None,
-                    description: None,
-                    allowed: None,
-                    min: None,
-                    max: None,
-                    unit: None,
+                    ..Default::default()
                 },
-            )])
+            )
+        };
+        // Both values of a batch in one notification, and all values when
+        // only one changes
+        authorized_access
+            .update_entries([update(left, 20), update(right, 21)])
             .await
-            .expect("setting datapoint #1");
-
-        // Value has been set, expect the next item in stream to match.
-        match stream.next().await {
-            Some(entry) => {
-                assert_eq!(entry.updates.len(), 1);
-                assert_eq!(
-                    entry.updates[0].update.path,
-                    Some("test.datapoint1".to_string())
-                );
-                assert_eq!(
-                    entry.updates[0].update.datapoint.as_ref().unwrap().value,
-                    DataValue::Int32(101)
-                );
-            }
-            None => {
-                panic!("did not expect stream end")
-            }
-        }
-
-        // Check that the data point has been stored as well
-        match broker.get_datapoint(id1).await {
-            Ok(datapoint) => {
-                assert_eq!(datapoint.value, DataValue::Int32(101));
-            }
-            Err(ReadError::NotFound) => {
-                panic!("expected datapoint to exist");
-            }
-            Err(ReadError::PermissionDenied | ReadError::PermissionExpired) => {
-                panic!("expected to be authorized");
-            }
+            .unwrap();
+        authorized_access
+            .update_entries([update(right, 31)])
+            .await
+            .unwrap();
+        for (left_value, right_value) in [(20, 21), (20, 31)] {
+            let notification = stream.next().await.unwrap();
+            assert_eq!(notification.reasons, vec![UpdateReason::ValueChanged]);
+            let mut values: Vec<_> = notification
+                .updates
+                .iter()
+                .map(|notification| {
+                    (
+                        notification.id,
+                        notification
+                            .update
+                            .datapoint
+                            .as_ref()
+                            .unwrap()
+                            .value
+                            .clone(),
+                    )
+                })
+                .collect();
+            values.sort_by_key(|(id, _)| *id);
+            assert_eq!(
+                values,
+                [
+                    (left, DataValue::Int32(left_value)),
+                    (right, DataValue::Int32(right_value))
+                ]
+            );
         }
-    }
 
-    #[tokio::test]
-    async fn test_subscribe_and_get() {
-        // None and 0-1000 is valid range
-        test_subscribe_and_get_buffer_size(None).await;
-        test_subscribe_and_get_buffer_size(Some(0)).await;
-        test_subscribe_and_get_buffer_size(Some(1000)).await;
+        authorized_access
+            .define_signal_group("WheelSpeeds", Vec::new())
+            .await
+            .unwrap();
+        assert!(matches!(
+            authorized_access.get_signal_group("WheelSpeeds").await,
+            Err(ReadError::NotFound)
+        ));
     }
 
     #[tokio::test]
-    async fn test_subscribe_buffersize_out_of_range() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
-
-        let id1 = broker
+    async fn test_validators() {
+        let mut validators = Validators::default();
+        validators
+            .register("test.counter", Arc::new(crate::validation::Monotonic))
+            .unwrap();
+        let broker = DataBroker::default().with_validators(validators);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
             .add_entry(
-                "test.datapoint1".to_owned(),
-                DataType::Int32,
+                "test.counter".to_owned(),
+                DataType::Uint32,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Test datapoint 1".to_owned(),
-                None, // min
-                None, // max
+                "Test counter".to_owned(),
+                None,
+                None,
                 None,
                 None,
             )
             .await
-            .expect("Register datapoint should succeed");
+            .unwrap();
 
-        match broker
-            .subscribe(
-                HashMap::from([(id1, HashSet::from([Field::Datapoint]))]),
-                // 1001 is just outside valid range 0-1000
-                Some(1001),
-            )
+        let update = |value| {
+            [(
+                id,
+                EntryUpdate {
+                    datapoint: Some(Datapoint {
+                        ts: SystemTime::now(),
+                        source_ts: None,
+                        value: DataValue::Uint32(value),
+                    }),
+                    ..Default::default()
+                },
+            )]
+        };
+        authorized_access.update_entries(update(5)).await.unwrap();
+        authorized_access.update_entries(update(6)).await.unwrap();
+        let errors = authorized_access
+            .update_entries(update(4))
             .await
-        {
-            Err(SubscriptionError::InvalidBufferSize) => {}
-            _ => {
-                panic!("expected it to fail with InvalidBufferSize");
-            }
-        }
+            .unwrap_err();
+        assert!(matches!(errors[..], [(err_id, UpdateError::Implausible(_))] if err_id == id));
+        assert_eq!(
+            authorized_access
+                .get_entry_by_id(id)
+                .await
+                .unwrap()
+                .datapoint
+                .value,
+            DataValue::Uint32(6)
+        );
     }
 
     #[tokio::test]
-    async fn test_metadata_for_each() {
-        let db = DataBroker::default();
-        let broker = db.authorized_access(&permissions::ALLOW_ALL);
-
-        let id1 = broker
+    async fn test_policies() {
+        let mut policies = Policies::default();
+        policies
+            .deny_actuate("test.trunk.IsOpen", "test.speed > 5")
+            .unwrap();
+        let broker = DataBroker::default().with_policies(policies);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let speed = authorized_access
             .add_entry(
-                "Vehicle.Test1".to_owned(),
-                DataType::Bool,
+                "test.speed".to_owned(),
+                DataType::Float,
                 ChangeType::OnChange,
                 EntryType::Sensor,
-                "Run of the mill test signal".to_owned(),
-                None, // min
-                None, // max
+                "Test speed".to_owned(),
+                None,
+                None,
                 None,
                 None,
             )
             .await
             .unwrap();
-        let id2 = broker
+        let trunk = authorized_access
             .add_entry(
-                "Vehicle.Test2".to_owned(),
+                "test.trunk.IsOpen".to_owned(),
                 DataType::Bool,
                 ChangeType::OnChange,
-                EntryType::Sensor,
-                "Run of the mill test signal".to_owned(),
-                None, // min
-                None, // max
+                EntryType::Actuator,
+                "Test trunk".to_owned(),
+                None,
+                None,
                 None,
                 None,
             )
             .await
             .unwrap();
 
-        // No permissions
-        let permissions = Permissions::builder().build().unwrap();
-        let broker = db.authorized_access(&permissions);
-        let metadata = broker.map_entries(|entry| entry.metadata().clone()).await;
-        for entry in metadata {
-            match entry.path.as_str() {
-                "Vehicle.Test1" => assert_eq!(entry.id, id1),
-                "Vehicle.Test2" => assert_eq!(entry.id, id2),
-                _ => panic!("Unexpected metadata entry"),
-            }
-        }
-    }
+        let set_speed = |value| {
+            [(
+                speed,
+                EntryUpdate {
+                    datapoint: Some(Datapoint {
+                        ts: SystemTime::now(),
+                        source_ts: None,
+                        value: DataValue::Float(value),
+                    }),
+                    ..Default::default()
+                },
+            )]
+        };
+        let open_trunk = [(
+            trunk,
+            EntryUpdate {
+                actuator_target: Some(Some(Datapoint {
+                    ts: SystemTime::now(),
+                    source_ts: None,
+                    value: DataValue::Bool(true),
+                })),
+                ..Default::default()
+            },
+        )];
 
-    #[tokio::test]
-    async fn test_register_invalid_and_valid_path() {
-        let broker = DataBroker::default();
-        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+        // The speed is not available yet, which is just as bad
+        assert!(authorized_access
+            .update_entries(open_trunk.clone())
+            .await
+            .is_err());
 
-        let error = broker
-            .add_entry(
-                "test. signal:3".to_owned(),
-                DataType::String,
-                ChangeType::OnChange,
-                EntryType::Sensor,
-                "Test signal 3".to_owned(),
-                None, // min
-                None, // max
-                Some(DataValue::Int32Array(Vec::from([1, 2, 3, 4]))),
-                None,
-            )
+        authorized_access
+            .update_entries(set_speed(30.0))
+            .await
+            .unwrap();
+        let errors = authorized_access
+            .update_entries(open_trunk.clone())
             .await
             .unwrap_err();
-        assert_eq!(error, RegistrationError::ValidationError);
+        assert!(matches!(
+            &errors[..],
+            [(err_id, UpdateError::PolicyViolation(condition))]
+                if *err_id == trunk && condition == "test.speed > 5"
+        ));
+        let (error, _) = authorized_access
+            .actuate(&trunk, &DataValue::Bool(true))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ActuationError::PolicyViolation));
 
-        let id = broker
-            .add_entry(
-                "Vehicle._kuksa.databroker.GitVersion.Do_you_not_like_smörgåstårta.tschö_mit_ö.東京_Москва_r#true".to_owned(),
-                DataType::Bool,
-                ChangeType::OnChange,
-                EntryType::Sensor,
-                "Test datapoint".to_owned(),
-                None, // min
-                None, // max
-                Some(DataValue::BoolArray(Vec::from([true]))),
-                None,
-            )
+        // Other requests are not affected
+        authorized_access
+            .update_entries(set_speed(3.0))
             .await
-            .expect("Register datapoint should succeed");
-        {
-            match broker.get_entry_by_id(id).await {
-                Ok(entry) => {
-                    assert_eq!(entry.metadata.id, id);
-                    assert_eq!(entry.metadata.path, "Vehicle._kuksa.databroker.GitVersion.Do_you_not_like_smörgåstårta.tschö_mit_ö.東京_Москва_r#true");
-                    assert_eq!(entry.metadata.data_type, DataType::Bool);
-                    assert_eq!(entry.metadata.description, "Test datapoint");
-                    assert_eq!(
-                        entry.metadata.allowed,
-                        Some(DataValue::BoolArray(Vec::from([true])))
-                    );
-                }
-                Err(_) => {
-                    panic!("no metadata returned");
-                }
-            }
-        }
+            .unwrap();
+        authorized_access
+            .update_entries(open_trunk.clone())
+            .await
+            .unwrap();
+        let (error, _) = authorized_access
+            .actuate(&trunk, &DataValue::Bool(true))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ActuationError::ProviderNotAvailable));
     }
 }
@@ -0,0 +1,135 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Number of gRPC calls per method and status code, and how long they
+//! took, to monitor error rates and slow calls. The duration of a stream is
+//! the time until it was opened, not how long it stayed open.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the buckets, in microseconds. Durations above the last
+/// bound go to an extra bucket.
+const BUCKETS_US: [u64; 12] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 100_000, 250_000, 1_000_000, 5_000_000,
+];
+
+#[derive(Debug, Default)]
+struct Calls {
+    codes: BTreeMap<i32, u64>,
+    buckets: [u64; BUCKETS_US.len() + 1],
+    count: u64,
+    sum_us: u64,
+}
+
+/// The calls of a method recorded so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodStats {
+    /// Path of the method, e.g. `/kuksa.val.v2.VAL/GetValue`
+    pub method: String,
+    /// Number of calls that ended with each status code
+    pub codes: Vec<(tonic::Code, u64)>,
+    pub count: u64,
+    pub sum: Duration,
+    /// Upper bound of each bucket, None for the last one, and the number of
+    /// calls in it
+    pub buckets: Vec<(Option<Duration>, u64)>,
+}
+
+/// The calls of each method.
+#[derive(Debug, Default)]
+pub struct CallStats {
+    methods: Mutex<BTreeMap<String, Calls>>,
+}
+
+impl CallStats {
+    pub fn record(&self, method: &str, code: tonic::Code, duration: Duration) {
+        let us: u64 = duration.as_micros().try_into().unwrap_or(u64::MAX);
+        let mut methods = self
+            .methods
+            .lock()
+            .expect("call stats should not be poisoned");
+        if !methods.contains_key(method) {
+            methods.insert(method.to_owned(), Calls::default());
+        }
+        let calls = methods
+            .get_mut(method)
+            .expect("calls of the method were just inserted");
+        *calls.codes.entry(code as i32).or_default() += 1;
+        calls.buckets[BUCKETS_US.partition_point(|bound| *bound < us)] += 1;
+        calls.count += 1;
+        calls.sum_us = calls.sum_us.saturating_add(us);
+    }
+
+    /// The stats of every method called so far, ordered by method.
+    pub fn snapshot(&self) -> Vec<MethodStats> {
+        self.methods
+            .lock()
+            .expect("call stats should not be poisoned")
+            .iter()
+            .map(|(method, calls)| MethodStats {
+                method: method.clone(),
+                codes: calls
+                    .codes
+                    .iter()
+                    .map(|(code, count)| (tonic::Code::from_i32(*code), *count))
+                    .collect(),
+                count: calls.count,
+                sum: Duration::from_micros(calls.sum_us),
+                buckets: calls
+                    .buckets
+                    .iter()
+                    .enumerate()
+                    .map(|(index, count)| {
+                        (
+                            BUCKETS_US.get(index).copied().map(Duration::from_micros),
+                            *count,
+                        )
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_stats() {
+        let stats = CallStats::default();
+        let method = "/kuksa.val.v2.VAL/GetValue";
+        stats.record(method, tonic::Code::Ok, Duration::from_micros(80));
+        stats.record(method, tonic::Code::Ok, Duration::from_millis(3));
+        stats.record(method, tonic::Code::NotFound, Duration::from_secs(10));
+        stats.record("/kuksa.val.v1.VAL/Get", tonic::Code::Ok, Duration::ZERO);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        // Ordered by method
+        assert_eq!(snapshot[0].method, "/kuksa.val.v1.VAL/Get");
+        let get_value = &snapshot[1];
+        assert_eq!(
+            get_value.codes,
+            vec![(tonic::Code::Ok, 2), (tonic::Code::NotFound, 1)]
+        );
+        assert_eq!(get_value.count, 3);
+        assert_eq!(get_value.sum, Duration::from_micros(10_003_080));
+        assert_eq!(get_value.buckets.len(), BUCKETS_US.len() + 1);
+        assert_eq!(get_value.buckets[0], (Some(Duration::from_micros(100)), 1));
+        assert_eq!(get_value.buckets[5], (Some(Duration::from_millis(5)), 1));
+        assert_eq!(get_value.buckets[BUCKETS_US.len()], (None, 1));
+    }
+}
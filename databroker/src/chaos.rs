@@ -0,0 +1,298 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Fault injection for robustness testing of clients.
+//!
+//! Only available with the `chaos` feature. Never enable it in production
+//! builds. Example configuration:
+//!
+//! ```json
+//! {
+//!   "latency_ms": 50,
+//!   "latency_jitter_ms": 20,
+//!   "drop_notification_probability": 0.1,
+//!   "stream_reset_after": 100
+//! }
+//! ```
+//!
+//! * `latency_ms` / `latency_jitter_ms`: delay added to every write and to
+//!   every notification delivered to a subscriber.
+//! * `drop_notification_probability`: chance (0.0 - 1.0) that a
+//!   notification is silently dropped.
+//! * `stream_reset_after`: close subscription streams after this many
+//!   notifications, forcing clients to resubscribe.
+
+use std::fmt;
+use std::io::Read;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+
+use crate::simulator::XorShift;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChaosConfig {
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub latency_jitter_ms: u64,
+    #[serde(default)]
+    pub drop_notification_probability: f64,
+    #[serde(default)]
+    pub stream_reset_after: Option<u64>,
+    /// Seed for the random decisions, to make a run reproducible.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse chaos config: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid chaos config: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub fn parse_config_from_str(data: &str) -> Result<ChaosConfig, Error> {
+    let config: ChaosConfig =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    if !(0.0..=1.0).contains(&config.drop_notification_probability) {
+        return Err(Error::InvalidConfig(
+            "drop_notification_probability must be between 0.0 and 1.0".to_owned(),
+        ));
+    }
+    if config.stream_reset_after == Some(0) {
+        return Err(Error::InvalidConfig(
+            "stream_reset_after must be greater than 0".to_owned(),
+        ));
+    }
+    Ok(config)
+}
+
+pub fn parse_config_from_reader<R: Read>(mut reader: R) -> Result<ChaosConfig, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_config_from_str(&data)
+}
+
+pub struct Chaos {
+    config: ChaosConfig,
+    rng: Mutex<XorShift>,
+}
+
+impl fmt::Debug for Chaos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chaos")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Default for Chaos {
+    fn default() -> Self {
+        Self::new(ChaosConfig::default())
+    }
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        Self {
+            config,
+            rng: Mutex::new(XorShift::new(seed)),
+        }
+    }
+
+    fn next_unit(&self) -> f64 {
+        self.rng
+            .lock()
+            .expect("chaos rng lock poisoned")
+            .next_unit()
+    }
+
+    /// The latency to inject for the next operation.
+    pub fn latency(&self) -> Duration {
+        let jitter = if self.config.latency_jitter_ms > 0 {
+            (self.next_unit() * self.config.latency_jitter_ms as f64) as u64
+        } else {
+            0
+        };
+        Duration::from_millis(self.config.latency_ms + jitter)
+    }
+
+    pub async fn delay(&self) {
+        let latency = self.latency();
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+    }
+
+    pub fn drop_notification(&self) -> bool {
+        self.config.drop_notification_probability > 0.0
+            && self.next_unit() < self.config.drop_notification_probability
+    }
+
+    /// Apply the configured faults to a subscription stream.
+    pub fn wrap_stream<S>(
+        self: &Arc<Self>,
+        stream: S,
+    ) -> Pin<Box<dyn Stream<Item = S::Item> + Send + Sync>>
+    where
+        S: Stream + Send + Sync + 'static,
+        S::Item: Send + Sync,
+    {
+        let chaos = self.clone();
+        let reset_after = self.config.stream_reset_after.unwrap_or(u64::MAX);
+        let stream = stream
+            .filter_map(move |item| {
+                let chaos = chaos.clone();
+                async move {
+                    if chaos.drop_notification() {
+                        return None;
+                    }
+                    chaos.delay().await;
+                    Some(item)
+                }
+            })
+            .take(usize::try_from(reset_after).unwrap_or(usize::MAX));
+        Box::pin(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config_from_str(
+            r#"{ "latency_ms": 5, "drop_notification_probability": 0.5, "stream_reset_after": 3 }"#,
+        )
+        .unwrap();
+        assert_eq!(config.latency_ms, 5);
+        assert_eq!(config.latency_jitter_ms, 0);
+        assert_eq!(config.stream_reset_after, Some(3));
+
+        assert!(matches!(
+            parse_config_from_str(r#"{ "drop_notification_probability": 1.5 }"#),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_config_from_str(r#"{ "stream_reset_after": 0 }"#),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_latency_within_jitter() {
+        let chaos = Chaos::new(ChaosConfig {
+            latency_ms: 10,
+            latency_jitter_ms: 5,
+            seed: Some(1),
+            ..Default::default()
+        });
+        for _ in 0..100 {
+            let latency = chaos.latency();
+            assert!(latency >= Duration::from_millis(10));
+            assert!(latency < Duration::from_millis(15));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_reset() {
+        let chaos = Arc::new(Chaos::new(ChaosConfig {
+            stream_reset_after: Some(3),
+            ..Default::default()
+        }));
+        let items: Vec<_> = chaos
+            .wrap_stream(futures::stream::iter(0..10))
+            .collect()
+            .await;
+        assert_eq!(items, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_drop_notifications() {
+        let drop_all = Arc::new(Chaos::new(ChaosConfig {
+            drop_notification_probability: 1.0,
+            ..Default::default()
+        }));
+        let items: Vec<_> = drop_all
+            .wrap_stream(futures::stream::iter(0..10))
+            .collect()
+            .await;
+        assert!(items.is_empty());
+
+        let drop_some = Arc::new(Chaos::new(ChaosConfig {
+            drop_notification_probability: 0.5,
+            seed: Some(42),
+            ..Default::default()
+        }));
+        let items: Vec<_> = drop_some
+            .wrap_stream(futures::stream::iter(0..1000))
+            .collect()
+            .await;
+        assert!(items.len() > 300 && items.len() < 700);
+    }
+
+    #[tokio::test]
+    async fn test_broker_resets_query_stream() {
+        let broker = crate::broker::DataBroker::default().with_chaos(ChaosConfig {
+            stream_reset_after: Some(1),
+            ..Default::default()
+        });
+        let broker = broker.authorized_access(&crate::permissions::ALLOW_ALL);
+        broker
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                crate::types::DataType::Int32,
+                crate::types::ChangeType::OnChange,
+                crate::types::EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let mut subscription = broker
+            .subscribe_query("SELECT test.datapoint1")
+            .await
+            .expect("subscription should succeed");
+
+        // The initial response is delivered, then the stream is closed
+        assert!(subscription.next().await.is_some());
+        assert!(subscription.next().await.is_none());
+    }
+}
@@ -0,0 +1,453 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Validation of the files a broker would be started with, without starting
+//! it, so that deployment pipelines can catch misconfiguration before it
+//! reaches a vehicle. Used by `databroker --check`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::authorization::jwt;
+use crate::{
+    enrollment, federation, history, policy, seed, signal_groups, simulator, validation, vss,
+};
+
+/// The files to check, named after the command line options that set them.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// VSS files, later ones overlaying earlier ones
+    pub vss_files: Vec<String>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_private_key: Option<PathBuf>,
+    pub jwt_public_key: Option<PathBuf>,
+    pub jwt_claim_mapping: Option<PathBuf>,
+    pub validators: Option<PathBuf>,
+    pub history: Option<PathBuf>,
+    pub signal_groups: Option<PathBuf>,
+    pub policies: Option<PathBuf>,
+    pub provider_credentials: Option<PathBuf>,
+    pub simulation_config: Option<PathBuf>,
+    pub seed_file: Option<PathBuf>,
+    pub feeder_plugins: Option<PathBuf>,
+    pub federation: Option<PathBuf>,
+    pub chaos_config: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The broker would fail to start, or start without part of its
+    /// configuration
+    Error,
+    /// The broker would start, but likely not as intended
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The command line option the problem is about, e.g. `vss`
+    pub option: &'static str,
+    /// The file the problem was found in, if any
+    pub file: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(option: &'static str, file: Option<&Path>, message: impl fmt::Display) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            option,
+            file: file.map(|file| file.display().to_string()),
+            message: message.to_string(),
+        }
+    }
+
+    fn warning(option: &'static str, file: Option<&Path>, message: impl fmt::Display) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Self::error(option, file, message)
+        }
+    }
+}
+
+// Parses one of the config files, only the result matters
+type Parse = fn(File) -> Result<(), String>;
+
+/// Check all files of `config`. Returns the problems found, none if the
+/// broker would start with them.
+pub fn check(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_vss(&config.vss_files, &mut diagnostics);
+    check_tls(
+        config.tls_cert.as_deref(),
+        config.tls_private_key.as_deref(),
+        &mut diagnostics,
+    );
+    if let Some(file) = &config.jwt_public_key {
+        if let Err(message) = check_jwt_public_key(file) {
+            diagnostics.push(Diagnostic::error("jwt-public-key", Some(file), message));
+        }
+    }
+
+    let config_files: [(&'static str, &Option<PathBuf>, Parse); 9] = [
+        ("jwt-claim-mapping", &config.jwt_claim_mapping, |file| {
+            jwt::ClaimMapping::from_reader(std::io::BufReader::new(file))
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+        ("validators", &config.validators, |file| {
+            validation::parse_config_from_reader(file)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+        ("history", &config.history, |file| {
+            history::parse_config_from_reader(file)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+        ("signal-groups", &config.signal_groups, |file| {
+            signal_groups::parse_signal_groups_from_reader(file)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+        ("policies", &config.policies, |file| {
+            policy::parse_config_from_reader(file)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+        (
+            "provider-credentials",
+            &config.provider_credentials,
+            |file| {
+                enrollment::parse_credentials_from_reader(file)
+                    .map(drop)
+                    .map_err(|err| err.to_string())
+            },
+        ),
+        ("simulation-config", &config.simulation_config, |file| {
+            simulator::parse_config_from_reader(file)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+        ("seed-file", &config.seed_file, |file| {
+            seed::parse_seed_from_reader(file)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+        ("federation", &config.federation, |file| {
+            federation::parse_config_from_reader(file)
+                .map(drop)
+                .map_err(|err| err.to_string())
+        }),
+    ];
+    for (option, path, parse) in config_files {
+        if let Some(path) = path {
+            if let Err(message) = File::open(path)
+                .map_err(|err| err.to_string())
+                .and_then(parse)
+            {
+                diagnostics.push(Diagnostic::error(option, Some(path), message));
+            }
+        }
+    }
+
+    if let Some(file) = &config.feeder_plugins {
+        #[cfg(feature = "plugins")]
+        let result = File::open(file)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                crate::plugin::parse_config_from_reader(file)
+                    .map(drop)
+                    .map_err(|err| err.to_string())
+            });
+        #[cfg(not(feature = "plugins"))]
+        let result: Result<(), _> = Err("databroker was built without the plugins feature");
+        if let Err(message) = result {
+            diagnostics.push(Diagnostic::error("feeder-plugins", Some(file), message));
+        }
+    }
+
+    if let Some(file) = &config.chaos_config {
+        #[cfg(feature = "chaos")]
+        let result = File::open(file)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                crate::chaos::parse_config_from_reader(file)
+                    .map(drop)
+                    .map_err(|err| err.to_string())
+            });
+        #[cfg(not(feature = "chaos"))]
+        let result: Result<(), _> = Err("databroker was built without the chaos feature");
+        if let Err(message) = result {
+            diagnostics.push(Diagnostic::error("chaos-config", Some(file), message));
+        }
+    }
+    diagnostics
+}
+
+/// Parse the VSS files and look for overlays changing the type of an
+/// entry, which the broker ignores as the entry is registered already.
+fn check_vss(files: &[String], diagnostics: &mut Vec<Diagnostic>) {
+    let mut defined: HashMap<String, (vss::DataEntry, &Path)> = HashMap::new();
+    for filename in files {
+        let file = Path::new(filename.trim());
+        if filename.starts_with("http://") || filename.starts_with("https://") {
+            diagnostics.push(Diagnostic::warning(
+                "vss",
+                Some(file),
+                "URLs aren't fetched when checking",
+            ));
+            continue;
+        }
+        let entries = match File::open(file)
+            .map_err(|err| err.to_string())
+            .and_then(|reader| {
+                vss::parse_vss_from_reader(std::io::BufReader::new(reader))
+                    .map_err(|err| err.to_string())
+            }) {
+            Ok(entries) => entries,
+            Err(message) => {
+                diagnostics.push(Diagnostic::error("vss", Some(file), message));
+                continue;
+            }
+        };
+        if entries.is_empty() {
+            diagnostics.push(Diagnostic::warning("vss", Some(file), "No entries defined"));
+        }
+        for (path, entry) in entries {
+            match defined.get(&path) {
+                Some((first, first_file))
+                    if first.data_type != entry.data_type
+                        || first.entry_type != entry.entry_type =>
+                {
+                    diagnostics.push(Diagnostic::warning(
+                        "vss",
+                        Some(file),
+                        format!(
+                            "{path} is defined as {:?} {:?}, but already as {:?} {:?} in {}, \
+                            which is kept",
+                            entry.entry_type,
+                            entry.data_type,
+                            first.entry_type,
+                            first.data_type,
+                            first_file.display()
+                        ),
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    defined.insert(path, (entry, file));
+                }
+            }
+        }
+    }
+}
+
+fn check_tls(cert: Option<&Path>, key: Option<&Path>, diagnostics: &mut Vec<Diagnostic>) {
+    match (cert, key) {
+        (Some(cert), Some(key)) => {
+            if let Err(message) = check_certificates(cert) {
+                diagnostics.push(Diagnostic::error("tls-cert", Some(cert), message));
+            }
+            if let Err(message) = check_private_key(key) {
+                diagnostics.push(Diagnostic::error("tls-private-key", Some(key), message));
+            }
+        }
+        (Some(cert), None) => diagnostics.push(Diagnostic::error(
+            "tls-private-key",
+            None,
+            format!("Must be set as --tls-cert is ({})", cert.display()),
+        )),
+        (None, Some(key)) => diagnostics.push(Diagnostic::error(
+            "tls-cert",
+            None,
+            format!("Must be set as --tls-private-key is ({})", key.display()),
+        )),
+        (None, None) => {}
+    }
+}
+
+#[cfg(feature = "tls")]
+fn check_certificates(file: &Path) -> Result<(), String> {
+    let mut reader = std::io::BufReader::new(File::open(file).map_err(|err| err.to_string())?);
+    let certs = rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("Invalid PEM: {err}"))?;
+    match certs.is_empty() {
+        true => Err("No certificate found".to_owned()),
+        false => Ok(()),
+    }
+}
+
+#[cfg(feature = "tls")]
+fn check_private_key(file: &Path) -> Result<(), String> {
+    let mut reader = std::io::BufReader::new(File::open(file).map_err(|err| err.to_string())?);
+    match rustls_pemfile::private_key(&mut reader) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err("No private key found".to_owned()),
+        Err(err) => Err(format!("Invalid PEM: {err}")),
+    }
+}
+
+#[cfg(not(feature = "tls"))]
+fn check_certificates(_file: &Path) -> Result<(), String> {
+    Err("databroker was built without the tls feature".to_owned())
+}
+
+#[cfg(not(feature = "tls"))]
+fn check_private_key(_file: &Path) -> Result<(), String> {
+    Err("databroker was built without the tls feature".to_owned())
+}
+
+fn check_jwt_public_key(file: &Path) -> Result<(), String> {
+    let key = std::fs::read_to_string(file).map_err(|err| err.to_string())?;
+    match jwt::Decoder::new(key) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("Invalid public key: {err:?}")),
+    }
+}
+
+/// The diagnostics as JSON document, e.g.
+/// `{"valid": false, "diagnostics": [{"severity": "error", "option": "vss",
+/// "file": "vss.json", "message": "..."}]}`. Only errors make the
+/// configuration invalid.
+pub fn to_json(diagnostics: &[Diagnostic]) -> Value {
+    json!({
+        "valid": !diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error),
+        "diagnostics": diagnostics.iter().map(|diagnostic| json!({
+            "severity": diagnostic.severity.to_string(),
+            "option": diagnostic.option,
+            "file": diagnostic.file,
+            "message": diagnostic.message,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_file(path: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(path)
+    }
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("databroker-check-{name}"));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let config = Config {
+            vss_files: vec![repo_file("data/vss-core/vss_release_4.0.json")
+                .display()
+                .to_string()],
+            tls_cert: Some(repo_file("certificates/Server.pem")),
+            tls_private_key: Some(repo_file("certificates/Server.key")),
+            jwt_public_key: Some(repo_file("certificates/jwt/jwt.key.pub")),
+            ..Default::default()
+        };
+        let diagnostics = check(&config);
+        assert_eq!(diagnostics, vec![]);
+        assert_eq!(
+            to_json(&diagnostics),
+            json!({"valid": true, "diagnostics": []})
+        );
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let vss = temp_file("vss.json", "{ \"Vehicle\": ");
+        let config = Config {
+            vss_files: vec![vss.display().to_string()],
+            tls_cert: Some(repo_file("certificates/jwt/jwt.key.pub")),
+            tls_private_key: Some(repo_file("certificates/Server.pem")),
+            jwt_public_key: Some(vss.clone()),
+            validators: Some(PathBuf::from("/nonexistent/validators.json")),
+            ..Default::default()
+        };
+        let diagnostics = check(&config);
+        std::fs::remove_file(vss).unwrap();
+
+        let options: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.option))
+            .collect();
+        assert_eq!(
+            options,
+            vec![
+                (Severity::Error, "vss"),
+                (Severity::Error, "tls-cert"),
+                (Severity::Error, "tls-private-key"),
+                (Severity::Error, "jwt-public-key"),
+                (Severity::Error, "validators"),
+            ]
+        );
+        assert_eq!(to_json(&diagnostics)["valid"], false);
+
+        let config = Config {
+            tls_cert: Some(repo_file("certificates/Server.pem")),
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&config)
+                .iter()
+                .map(|diagnostic| diagnostic.option)
+                .collect::<Vec<_>>(),
+            vec!["tls-private-key"]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_overlay() {
+        let base = temp_file(
+            "base.json",
+            r#"{"Vehicle": {"type": "branch", "description": "", "children": {
+                "Speed": {"type": "sensor", "datatype": "float", "description": ""}}}}"#,
+        );
+        let overlay = temp_file(
+            "overlay.json",
+            r#"{"Vehicle": {"type": "branch", "description": "", "children": {
+                "Speed": {"type": "sensor", "datatype": "uint16", "description": ""}}}}"#,
+        );
+        let config = Config {
+            vss_files: vec![base.display().to_string(), overlay.display().to_string()],
+            ..Default::default()
+        };
+        let diagnostics = check(&config);
+        std::fs::remove_file(base).unwrap();
+        std::fs::remove_file(overlay).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0]
+            .message
+            .starts_with("Vehicle.Speed is defined"));
+        assert_eq!(to_json(&diagnostics)["valid"], true);
+    }
+}
@@ -0,0 +1,155 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of time for everything in the broker that depends on it.
+///
+/// `now` is the wall clock used for timestamps, `elapsed` is a monotonic
+/// time used to measure intervals (e.g. for throttling or expiry).
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> SystemTime;
+
+    /// Monotonic time since the clock was created.
+    fn elapsed(&self) -> Duration;
+}
+
+/// The real clock.
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time,
+/// so a test can keep a handle while the broker uses another one.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    state: Arc<Mutex<VirtualTime>>,
+}
+
+#[derive(Debug)]
+struct VirtualTime {
+    now: SystemTime,
+    elapsed: Duration,
+}
+
+impl VirtualClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(VirtualTime {
+                now,
+                elapsed: Duration::ZERO,
+            })),
+        }
+    }
+
+    /// Move both the wall clock and the monotonic time forward.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().expect("clock lock poisoned");
+        state.now += duration;
+        state.elapsed += duration;
+    }
+
+    /// Set the wall clock. The monotonic time is not affected, just like
+    /// when the system time is adjusted.
+    pub fn set(&self, now: SystemTime) {
+        self.state.lock().expect("clock lock poisoned").now = now;
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> SystemTime {
+        self.state.lock().expect("clock lock poisoned").now
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.state.lock().expect("clock lock poisoned").elapsed
+    }
+}
+
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock_advance() {
+        let clock = VirtualClock::default();
+        let handle = clock.clone();
+
+        handle.advance(Duration::from_secs(5));
+        assert_eq!(clock.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(5));
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_virtual_clock_set() {
+        let clock = VirtualClock::default();
+        clock.advance(Duration::from_secs(1));
+        clock.set(SystemTime::UNIX_EPOCH + Duration::from_secs(100));
+
+        assert_eq!(
+            clock.now(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(100)
+        );
+        assert_eq!(clock.elapsed(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_broker_uses_clock() {
+        let clock = VirtualClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(42));
+        let broker = crate::broker::DataBroker::default().with_clock(Arc::new(clock.clone()));
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(
+            broker.clock().now(),
+            SystemTime::UNIX_EPOCH + Duration::from_millis(42_500)
+        );
+        assert_eq!(broker.clock().elapsed(), Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,205 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Dumps of the complete broker state as JSON, for post-mortem analysis of
+//! stuck systems: all entries with their values, targets and providers, and
+//! all subscriptions. A dump is requested with `DumpState` of
+//! `kuksa.val.v2` or, written to a file, by sending SIGUSR1 to the broker.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde_json::{json, Value};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info};
+
+use crate::broker::{
+    BrokerState, DataBroker, Datapoint, EntryState, ProviderState, SubscriptionKind,
+    SubscriptionState,
+};
+
+pub(crate) fn millis(ts: SystemTime) -> u128 {
+    ts.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default()
+}
+
+pub(crate) fn datapoint(datapoint: &Datapoint) -> Value {
+    json!({
+        "value": Value::from(datapoint.value.clone()),
+        "ts": millis(datapoint.ts),
+        "source_ts": datapoint.source_ts.map(millis),
+    })
+}
+
+fn provider(provider: &ProviderState) -> Value {
+    json!({
+        "client": provider.client,
+        "session": provider.session,
+        "available": provider.available,
+        "simulated": provider.simulated,
+    })
+}
+
+fn entry(entry: &EntryState) -> Value {
+    let metadata = &entry.metadata;
+    json!({
+        "id": metadata.id,
+        "path": metadata.path,
+        "entry_type": format!("{:?}", metadata.entry_type),
+        "data_type": format!("{:?}", metadata.data_type),
+        "datapoint": datapoint(&entry.datapoint),
+        "actuator_target": entry.actuator_target.as_ref().map(datapoint),
+        "providers": entry.providers.iter().map(provider).collect::<Vec<_>>(),
+    })
+}
+
+fn subscription(subscription: &SubscriptionState) -> Value {
+    json!({
+        "kind": match subscription.kind {
+            SubscriptionKind::Change => "change",
+            SubscriptionKind::Query => "query",
+        },
+        "client": subscription.client,
+        "session": subscription.session,
+        "paths": subscription.paths,
+        "active": subscription.active,
+    })
+}
+
+/// The state as a JSON document. Timestamps are in milliseconds since the
+/// Unix epoch.
+pub fn to_json(state: &BrokerState, version: &str) -> Value {
+    json!({
+        "version": version,
+        "ts": millis(SystemTime::now()),
+        "entries": state.entries.iter().map(entry).collect::<Vec<_>>(),
+        "subscriptions": state.subscriptions.iter().map(subscription).collect::<Vec<_>>(),
+    })
+}
+
+/// The current state of `broker` as a JSON document.
+pub async fn dump(broker: &DataBroker) -> Value {
+    to_json(&broker.get_state().await, broker.get_version())
+}
+
+/// Write the current state of `broker` to a new file in `dir`. Returns the
+/// path of the file.
+pub async fn write(broker: &DataBroker, dir: &Path) -> std::io::Result<PathBuf> {
+    let dump = dump(broker).await;
+    let path = dir.join(format!(
+        "databroker-dump-{}.json",
+        millis(SystemTime::now())
+    ));
+    std::fs::write(&path, serde_json::to_vec_pretty(&dump)?)?;
+    Ok(path)
+}
+
+/// Write a dump to `dir` whenever the broker receives SIGUSR1, until it
+/// shuts down.
+pub fn on_signal(broker: DataBroker, dir: PathBuf) -> std::io::Result<()> {
+    let mut sigusr1 = signal(SignalKind::user_defined1())?;
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = sigusr1.recv() => {}
+                _ = shutdown_trigger.recv() => break,
+            }
+            match write(&broker, &dir).await {
+                Ok(path) => info!("Dumped the broker state to {}", path.display()),
+                Err(err) => error!("Failed to dump the broker state: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::EntryUpdate;
+    use crate::permissions;
+    use crate::types::{ChangeType, DataType, DataValue, EntryType};
+
+    #[tokio::test]
+    async fn test_dump() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Speed".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        authorized_access
+            .update_entries([(
+                id,
+                EntryUpdate {
+                    datapoint: Some(Datapoint {
+                        ts: SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1500),
+                        source_ts: None,
+                        value: DataValue::Float(50.0),
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+        let _stream = authorized_access
+            .subscribe(
+                [(id, [crate::broker::Field::Datapoint].into())].into(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let dump = dump(&broker).await;
+        assert_eq!(
+            dump["entries"][0],
+            json!({
+                "id": id,
+                "path": "Vehicle.Speed",
+                "entry_type": "Sensor",
+                "data_type": "Float",
+                "datapoint": { "value": 50.0, "ts": 1500, "source_ts": null },
+                "actuator_target": null,
+                "providers": [],
+            })
+        );
+        assert_eq!(
+            dump["subscriptions"],
+            json!([{
+                "kind": "change",
+                "client": null,
+                "session": null,
+                "paths": ["Vehicle.Speed"],
+                "active": true,
+            }])
+        );
+
+        let dir = std::env::temp_dir();
+        let path = write(&broker, &dir).await.unwrap();
+        let written: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(written["entries"], dump["entries"]);
+    }
+}
@@ -0,0 +1,448 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Encryption of the files written by [persistence](crate::persistence),
+//! [recording](crate::recording) and the [journal](crate::journal).
+//!
+//! An encrypted file starts with a header (magic and format version)
+//! followed by chunks, each sealed with ChaCha20-Poly1305:
+//!
+//! ```text
+//! <len: u32 le> <nonce: 12 bytes> <ciphertext and tag: len bytes>
+//! ```
+//!
+//! A chunk is written whenever the writer is flushed, so a flushed (and
+//! synced) write is on disk as a complete chunk. The index of a chunk is
+//! authenticated along with it, so chunks can't be reordered or dropped.
+//! Like a cut off frame of a plain file, a chunk cut off at the end of a
+//! file is ignored.
+//!
+//! The 256 bit key is given as 64 hex digits, read from a file or printed
+//! by a program, see [`KeyProvider`].
+
+use std::fmt;
+use std::io::{self, Cursor, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+pub const KEY_LEN: usize = 32;
+
+const MAGIC: &[u8; 4] = b"KDBE";
+const FORMAT_VERSION: u8 = 1;
+
+// Plaintext bytes buffered before a chunk is sealed without a flush
+const MAX_CHUNK_LEN: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidKey(String),
+    KeyUnavailable(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidKey(msg) => write!(f, "invalid encryption key: {msg}"),
+            Error::KeyUnavailable(msg) => write!(f, "encryption key unavailable: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Source of the encryption key, e.g. a hardware security module.
+pub trait KeyProvider {
+    fn key(&self) -> Result<[u8; KEY_LEN], Error>;
+}
+
+/// Reads the key from a file.
+pub struct KeyFile(pub PathBuf);
+
+impl KeyProvider for KeyFile {
+    fn key(&self) -> Result<[u8; KEY_LEN], Error> {
+        let text = std::fs::read_to_string(&self.0)
+            .map_err(|err| Error::KeyUnavailable(format!("{}: {err}", self.0.display())))?;
+        parse_key(&text)
+    }
+}
+
+/// Runs a program printing the key, e.g. to unseal it from a TPM.
+pub struct KeyCommand(pub PathBuf);
+
+impl KeyProvider for KeyCommand {
+    fn key(&self) -> Result<[u8; KEY_LEN], Error> {
+        let output = std::process::Command::new(&self.0)
+            .output()
+            .map_err(|err| Error::KeyUnavailable(format!("{}: {err}", self.0.display())))?;
+        if !output.status.success() {
+            return Err(Error::KeyUnavailable(format!(
+                "{} exited with {}",
+                self.0.display(),
+                output.status
+            )));
+        }
+        let text = String::from_utf8(output.stdout)
+            .map_err(|_| Error::InvalidKey("not hex encoded".to_owned()))?;
+        parse_key(&text)
+    }
+}
+
+/// Parse a key given as 64 hex digits, surrounding whitespace is ignored.
+pub fn parse_key(text: &str) -> Result<[u8; KEY_LEN], Error> {
+    let text = text.trim();
+    if text.len() != 2 * KEY_LEN || !text.is_ascii() {
+        return Err(Error::InvalidKey(format!(
+            "expected {} hex digits",
+            2 * KEY_LEN
+        )));
+    }
+    let mut key = [0; KEY_LEN];
+    for (byte, digits) in key.iter_mut().zip(text.as_bytes().chunks(2)) {
+        let digits = std::str::from_utf8(digits).expect("checked to be ascii");
+        *byte = u8::from_str_radix(digits, 16)
+            .map_err(|_| Error::InvalidKey("not hex encoded".to_owned()))?;
+    }
+    Ok(key)
+}
+
+#[derive(Clone)]
+pub struct Encryption {
+    key: Arc<LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Encryption")
+    }
+}
+
+impl Encryption {
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        let key = UnboundKey::new(&CHACHA20_POLY1305, key).expect("key has the right length");
+        Self {
+            key: Arc::new(LessSafeKey::new(key)),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    pub fn from_provider(provider: &dyn KeyProvider) -> Result<Self, Error> {
+        Ok(Self::new(&provider.key()?))
+    }
+}
+
+/// Encrypts everything written to it, see the [module](self) docs.
+pub struct EncryptedWriter<W: Write> {
+    // Only `None` while dropping
+    inner: Option<W>,
+    encryption: Encryption,
+    buffer: Vec<u8>,
+    index: u64,
+}
+
+impl<W: Write> EncryptedWriter<W> {
+    pub fn new(mut inner: W, encryption: &Encryption) -> io::Result<Self> {
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[FORMAT_VERSION])?;
+        Ok(Self {
+            inner: Some(inner),
+            encryption: encryption.clone(),
+            buffer: Vec::new(),
+            index: 0,
+        })
+    }
+
+    fn seal(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let inner = self.inner.as_mut().expect("only taken when dropped");
+        let mut nonce = [0; NONCE_LEN];
+        self.encryption
+            .rng
+            .fill(&mut nonce)
+            .map_err(|_| io::Error::other("failed to generate a nonce"))?;
+        let mut chunk = std::mem::take(&mut self.buffer);
+        self.encryption
+            .key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(self.index.to_le_bytes()),
+                &mut chunk,
+            )
+            .map_err(|_| io::Error::other("failed to encrypt"))?;
+        inner.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        inner.write_all(&nonce)?;
+        inner.write_all(&chunk)?;
+        self.index += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncryptedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Never more than a chunk, the reader rejects longer ones
+        let len = buf.len().min(MAX_CHUNK_LEN - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        if self.buffer.len() >= MAX_CHUNK_LEN {
+            self.seal()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.seal()?;
+        self.inner
+            .as_mut()
+            .expect("only taken when dropped")
+            .flush()
+    }
+}
+
+impl<W: Write> Drop for EncryptedWriter<W> {
+    fn drop(&mut self) {
+        // Like a BufWriter, don't lose what was written without a flush
+        let _ = self.flush();
+        self.inner.take();
+    }
+}
+
+/// Decrypts a file written by an [`EncryptedWriter`].
+pub struct EncryptedReader<R: Read> {
+    inner: R,
+    encryption: Encryption,
+    chunk: Cursor<Vec<u8>>,
+    index: u64,
+}
+
+impl<R: Read> EncryptedReader<R> {
+    /// Read the next chunk into `self.chunk`, false at the end of the file.
+    fn open_chunk(&mut self) -> io::Result<bool> {
+        let mut len = [0; 4];
+        if !read_complete(&mut self.inner, &mut len)? {
+            return Ok(false);
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_CHUNK_LEN + CHACHA20_POLY1305.tag_len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "encrypted chunk too long",
+            ));
+        }
+        let mut nonce = [0; NONCE_LEN];
+        let mut chunk = vec![0; len];
+        if !read_complete(&mut self.inner, &mut nonce)?
+            || !read_complete(&mut self.inner, &mut chunk)?
+        {
+            return Ok(false);
+        }
+        let plaintext_len = self
+            .encryption
+            .key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(self.index.to_le_bytes()),
+                &mut chunk,
+            )
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "failed to decrypt, wrong key or corrupted file",
+                )
+            })?
+            .len();
+        chunk.truncate(plaintext_len);
+        self.chunk = Cursor::new(chunk);
+        self.index += 1;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for EncryptedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.chunk.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            if !self.open_chunk()? {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+/// Fill `buf`, false if the reader ends before.
+fn read_complete<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(true)
+}
+
+/// Write through `encryption`, if any.
+pub fn writer<'a, W>(
+    writer: W,
+    encryption: Option<&Encryption>,
+) -> io::Result<Box<dyn Write + Send + 'a>>
+where
+    W: Write + Send + 'a,
+{
+    match encryption {
+        Some(encryption) => Ok(Box::new(EncryptedWriter::new(writer, encryption)?)),
+        None => Ok(Box::new(writer)),
+    }
+}
+
+/// Read a file that may be encrypted. Encrypted files need `encryption`,
+/// plain files are read as they are, e.g. those written before
+/// encryption was enabled.
+pub fn reader<'a, R>(
+    mut reader: R,
+    encryption: Option<&Encryption>,
+) -> io::Result<Box<dyn Read + Send + 'a>>
+where
+    R: Read + Send + 'a,
+{
+    let mut magic = Vec::with_capacity(MAGIC.len());
+    (&mut reader)
+        .take(MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    if magic != MAGIC {
+        return Ok(Box::new(Cursor::new(magic).chain(reader)));
+    }
+    let Some(encryption) = encryption else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the file is encrypted, but no key is configured",
+        ));
+    };
+    let mut version = [0; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported encryption format version {}", version[0]),
+        ));
+    }
+    Ok(Box::new(EncryptedReader {
+        inner: reader,
+        encryption: encryption.clone(),
+        chunk: Cursor::new(Vec::new()),
+        index: 0,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    fn encrypt(encryption: &Encryption, chunks: &[&[u8]]) -> Vec<u8> {
+        let mut file = Vec::new();
+        {
+            let mut writer = EncryptedWriter::new(&mut file, encryption).unwrap();
+            for chunk in chunks {
+                writer.write_all(chunk).unwrap();
+                writer.flush().unwrap();
+            }
+        }
+        file
+    }
+
+    fn decrypt(file: &[u8], encryption: Option<&Encryption>) -> io::Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        reader(file, encryption)?.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let key = parse_key(&format!("{KEY}\n")).unwrap();
+        assert_eq!(key[1], 1);
+        assert_eq!(key[31], 0x1f);
+        assert!(parse_key("0011").is_err());
+        assert!(parse_key(&KEY.replace('0', "x")).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let encryption = Encryption::new(&parse_key(KEY).unwrap());
+        let file = encrypt(&encryption, &[b"VIN WVWZZZ", b"", b"1234"]);
+        assert!(!file
+            .windows(b"WVWZZZ".len())
+            .any(|window| window == b"WVWZZZ"));
+        assert_eq!(
+            decrypt(&file, Some(&encryption)).unwrap(),
+            b"VIN WVWZZZ1234"
+        );
+
+        // Plain files are read as they are
+        assert_eq!(
+            decrypt(b"KDBP plain", Some(&encryption)).unwrap(),
+            b"KDBP plain"
+        );
+        assert_eq!(decrypt(b"KD", None).unwrap(), b"KD");
+    }
+
+    #[test]
+    fn test_large_write() {
+        let encryption = Encryption::new(&parse_key(KEY).unwrap());
+        let plaintext: Vec<u8> = (0..3 * MAX_CHUNK_LEN + 10).map(|i| i as u8).collect();
+        let mut file = Vec::new();
+        {
+            // More than a chunk in one call
+            let mut writer = EncryptedWriter::new(&mut file, &encryption).unwrap();
+            writer.write_all(&plaintext).unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(decrypt(&file, Some(&encryption)).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_truncated_chunk_is_ignored() {
+        let encryption = Encryption::new(&parse_key(KEY).unwrap());
+        let file = encrypt(&encryption, &[b"first", b"second"]);
+        assert_eq!(
+            decrypt(&file[..file.len() - 3], Some(&encryption)).unwrap(),
+            b"first"
+        );
+    }
+
+    #[test]
+    fn test_rejects_wrong_key_and_tampering() {
+        let encryption = Encryption::new(&parse_key(KEY).unwrap());
+        let mut file = encrypt(&encryption, &[b"first", b"second"]);
+
+        assert!(decrypt(&file, None).is_err());
+        let other = Encryption::new(&[7; KEY_LEN]);
+        assert_eq!(
+            decrypt(&file, Some(&other)).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let last = file.len() - 1;
+        file[last] ^= 1;
+        assert!(decrypt(&file, Some(&encryption)).is_err());
+    }
+}
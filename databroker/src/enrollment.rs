@@ -0,0 +1,286 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Enrollment of providers by challenge-response.
+//!
+//! Each provider shares a secret with the broker and is confined to a set
+//! of paths:
+//!
+//! ```json
+//! [
+//!   { "provider_id": "wheel-speed", "secret": "...", "paths": ["Vehicle.Speed", "Vehicle.Chassis.**"] }
+//! ]
+//! ```
+//!
+//! A provider stream enrolls by sending the provider id, the broker
+//! answers with a random nonce and the provider proves its identity with
+//! the HMAC-SHA256 of the nonce, keyed with its secret. The secret itself
+//! is never sent. The resulting [`ProviderIdentity`] is bound to the stream
+//! and confines the operations on it to the paths of the provider.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Deserialize;
+
+use crate::permissions::{Permissions, PermissionsBuildError};
+
+const NONCE_LEN: usize = 32;
+const SESSION_ID_LEN: usize = 16;
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse provider credentials: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid provider credentials: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, PartialEq)]
+pub enum EnrollmentError {
+    // Unknown provider or wrong signature, deliberately not told apart
+    Rejected,
+    // The random number generator failed
+    Unavailable,
+}
+
+#[derive(Deserialize)]
+struct Credential {
+    provider_id: String,
+    secret: String,
+    paths: Vec<String>,
+}
+
+struct Provider {
+    key: hmac::Key,
+    paths: Vec<String>,
+}
+
+/// The credentials of the providers allowed to enroll.
+pub struct ProviderEnrollment {
+    providers: HashMap<String, Provider>,
+    rng: SystemRandom,
+}
+
+impl fmt::Debug for ProviderEnrollment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.providers.keys()).finish()
+    }
+}
+
+/// A challenge issued to a provider, to be answered on the same stream.
+#[derive(Debug)]
+pub struct Challenge {
+    provider_id: String,
+    nonce: [u8; NONCE_LEN],
+}
+
+impl Challenge {
+    pub fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+}
+
+/// The identity of an enrolled provider, valid for one stream.
+#[derive(Debug, Clone)]
+pub struct ProviderIdentity {
+    pub provider_id: String,
+    pub session_id: String,
+    pub paths: Vec<String>,
+}
+
+impl ProviderIdentity {
+    /// `permissions` confined to the paths of the provider.
+    pub fn restrict(
+        &self,
+        permissions: &Permissions,
+    ) -> Result<Permissions, PermissionsBuildError> {
+        permissions.restricted_to(&self.paths)
+    }
+}
+
+impl ProviderEnrollment {
+    /// Issue a challenge to `provider_id`. Unknown providers get one as
+    /// well, so they can't be told apart from known ones.
+    pub fn challenge(&self, provider_id: &str) -> Result<Challenge, EnrollmentError> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| EnrollmentError::Unavailable)?;
+        Ok(Challenge {
+            provider_id: provider_id.to_owned(),
+            nonce,
+        })
+    }
+
+    /// Check the `signature` answering `challenge`.
+    pub fn verify(
+        &self,
+        challenge: &Challenge,
+        signature: &[u8],
+    ) -> Result<ProviderIdentity, EnrollmentError> {
+        let provider = self
+            .providers
+            .get(&challenge.provider_id)
+            .ok_or(EnrollmentError::Rejected)?;
+        hmac::verify(&provider.key, &challenge.nonce, signature)
+            .map_err(|_| EnrollmentError::Rejected)?;
+
+        let mut session_id = [0; SESSION_ID_LEN];
+        self.rng
+            .fill(&mut session_id)
+            .map_err(|_| EnrollmentError::Unavailable)?;
+        Ok(ProviderIdentity {
+            provider_id: challenge.provider_id.clone(),
+            session_id: session_id
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+            paths: provider.paths.clone(),
+        })
+    }
+}
+
+pub fn parse_credentials_from_str(data: &str) -> Result<ProviderEnrollment, Error> {
+    let credentials: Vec<Credential> =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    let mut providers = HashMap::new();
+    for credential in credentials {
+        if credential.secret.len() < 16 {
+            return Err(Error::InvalidConfig(format!(
+                "the secret of '{}' must be at least 16 characters long",
+                credential.provider_id
+            )));
+        }
+        // Rejects invalid patterns up front
+        Permissions::builder()
+            .build()
+            .and_then(|permissions| permissions.restricted_to(&credential.paths))
+            .map_err(|_| {
+                Error::InvalidConfig(format!(
+                    "invalid path pattern for '{}'",
+                    credential.provider_id
+                ))
+            })?;
+        let provider = Provider {
+            key: hmac::Key::new(hmac::HMAC_SHA256, credential.secret.as_bytes()),
+            paths: credential.paths,
+        };
+        if providers
+            .insert(credential.provider_id.clone(), provider)
+            .is_some()
+        {
+            return Err(Error::InvalidConfig(format!(
+                "'{}' is listed more than once",
+                credential.provider_id
+            )));
+        }
+    }
+    Ok(ProviderEnrollment {
+        providers,
+        rng: SystemRandom::new(),
+    })
+}
+
+pub fn parse_credentials_from_reader<R: Read>(mut reader: R) -> Result<ProviderEnrollment, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_credentials_from_str(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::{self, Permission};
+
+    const CREDENTIALS: &str = r#"[
+        { "provider_id": "speed", "secret": "0123456789abcdef", "paths": ["Vehicle.Speed"] }
+    ]"#;
+
+    fn sign(secret: &str, nonce: &[u8]) -> Vec<u8> {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        hmac::sign(&key, nonce).as_ref().to_vec()
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        assert!(parse_credentials_from_str(CREDENTIALS).is_ok());
+        assert!(matches!(
+            parse_credentials_from_str(
+                r#"[{ "provider_id": "speed", "secret": "short", "paths": [] }]"#
+            ),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_credentials_from_str(r#"[{ "provider_id": "speed" }]"#),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_enroll() {
+        let enrollment = parse_credentials_from_str(CREDENTIALS).unwrap();
+
+        let challenge = enrollment.challenge("speed").unwrap();
+        let identity = enrollment
+            .verify(&challenge, &sign("0123456789abcdef", challenge.nonce()))
+            .unwrap();
+        assert_eq!(identity.provider_id, "speed");
+        assert_eq!(identity.session_id.len(), 2 * SESSION_ID_LEN);
+
+        // A proof is only good for its own challenge
+        let other = enrollment.challenge("speed").unwrap();
+        assert_ne!(other.nonce(), challenge.nonce());
+        assert_eq!(
+            enrollment
+                .verify(&other, &sign("0123456789abcdef", challenge.nonce()))
+                .unwrap_err(),
+            EnrollmentError::Rejected
+        );
+
+        let challenge = enrollment.challenge("unknown").unwrap();
+        assert_eq!(
+            enrollment
+                .verify(&challenge, &sign("0123456789abcdef", challenge.nonce()))
+                .unwrap_err(),
+            EnrollmentError::Rejected
+        );
+
+        let provide = Permissions::builder()
+            .add_provide_permission(Permission::All)
+            .build()
+            .unwrap();
+        let restricted = identity.restrict(&provide).unwrap();
+        assert!(restricted.can_write_datapoint("Vehicle.Speed").is_ok());
+        assert!(restricted
+            .can_write_datapoint("Vehicle.Body.Lights.IsOn")
+            .is_err());
+        // Restricting never widens the permissions
+        let restricted = identity.restrict(&permissions::ALLOW_NONE).unwrap();
+        assert!(restricted.can_write_datapoint("Vehicle.Speed").is_err());
+    }
+}
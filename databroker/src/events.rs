@@ -0,0 +1,101 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Internal event bus.
+//!
+//! The broker publishes what happens to its entries and providers on the
+//! bus, optional subsystems (persistence, recording, exporters, bridges,
+//! ...) listen to it with [`DataBroker::subscribe_events`] instead of
+//! hooking into the update path or subscribing to a fixed set of entries.
+//!
+//! Events are published in the order the changes were applied, while the
+//! database is still locked. Every listener gets every event, unless it
+//! falls more than [`EVENT_BUS_CAPACITY`] events behind, in which case it
+//! receives [`broadcast::error::RecvError::Lagged`] and should resync from
+//! the current state of the broker.
+//!
+//! [`DataBroker::subscribe_events`]: crate::broker::DataBroker::subscribe_events
+
+use tokio::sync::broadcast;
+
+use crate::broker::{Datapoint, RejectedUpdate};
+
+/// Number of events a listener may fall behind before missing events.
+pub const EVENT_BUS_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A new entry was registered.
+    EntryAdded { id: i32, path: String },
+    /// An entry was removed. Entries can't be removed yet, so this is
+    /// never published for now.
+    EntryRemoved { id: i32, path: String },
+    /// The current value of an entry changed.
+    ValueChanged {
+        id: i32,
+        path: String,
+        datapoint: Datapoint,
+    },
+    /// The target value of an actuator was set or cleared.
+    TargetSet {
+        id: i32,
+        path: String,
+        target: Option<Datapoint>,
+    },
+    /// The target of an actuator was cleared as its provider didn't
+    /// confirm it in time. Follows the [`Event::TargetSet`] clearing it.
+    TargetExpired { id: i32, path: String },
+    /// A provider opened a provider stream. `client` is the subject of
+    /// its access token.
+    ProviderConnected { client: Option<String> },
+    /// A provider stream was closed.
+    ProviderDisconnected { client: Option<String> },
+    /// A value published on a provider stream was rejected, e.g. as out
+    /// of bounds.
+    UpdateRejected {
+        client: Option<String>,
+        update: RejectedUpdate,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Whether anyone listens, to avoid building events for nobody.
+    pub fn has_listeners(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub fn publish(&self, event: Event) {
+        // Fails only if nobody listens
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
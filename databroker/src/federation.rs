@@ -0,0 +1,615 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Federation of zone brokers into a central broker.
+//!
+//! In a zonal E/E architecture, each zone runs a broker of its own. The
+//! central broker mounts the subtrees served by them, so applications see
+//! a single VSS tree:
+//!
+//! ```json
+//! [
+//!   { "prefix": "Vehicle.Body", "address": "http://zone-front:55555" },
+//!   { "prefix": "Vehicle.Chassis", "address": "https://zone-rear:55555", "token_file": "zone-rear.token", "ca_cert": "ca.pem" }
+//! ]
+//! ```
+//!
+//! For each mount, the central broker registers the entries the zone
+//! broker lists below the prefix, mirrors their values and claims their
+//! actuators, forwarding actuation requests to the zone broker
+//! (kuksa.val.v2). Getting and subscribing are served from the mirrored
+//! values. While a zone broker is unreachable, the values of its entries
+//! are not available and actuating them fails, until it's reconnected.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use databroker_proto::kuksa::val::v2 as proto;
+use proto::val_client::ValClient;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use tonic::transport::{Channel, Endpoint};
+use tracing::{debug, info, warn};
+
+use crate::broker::{
+    self, ActuationChange, ActuationError, ActuationProvider, AuthorizedAccess, DataBroker,
+    Datapoint, EntryUpdate,
+};
+use crate::glob;
+use crate::permissions;
+use crate::types::DataValue;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse federation config: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid federation config: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A subtree served by a zone broker.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mount {
+    /// Path of the subtree, e.g. `Vehicle.Body`.
+    pub prefix: String,
+    /// Address of the zone broker, e.g. `http://zone-front:55555`.
+    pub address: String,
+    /// File holding the access token presented to the zone broker.
+    #[serde(default)]
+    pub token_file: Option<PathBuf>,
+    /// CA certificate (PEM) to verify an `https` zone broker with.
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+}
+
+/// Whether `path` is `prefix` or below it.
+fn is_below(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+pub fn parse_config_from_str(data: &str) -> Result<Vec<Mount>, Error> {
+    let mounts: Vec<Mount> =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    for (index, mount) in mounts.iter().enumerate() {
+        if !glob::is_valid_path(&mount.prefix) {
+            return Err(Error::InvalidConfig(format!(
+                "invalid prefix '{}'",
+                mount.prefix
+            )));
+        }
+        if Endpoint::from_shared(mount.address.clone()).is_err() {
+            return Err(Error::InvalidConfig(format!(
+                "invalid address '{}'",
+                mount.address
+            )));
+        }
+        #[cfg(not(feature = "tls"))]
+        if mount.ca_cert.is_some() {
+            return Err(Error::InvalidConfig(
+                "ca_cert needs a databroker built with TLS support".to_owned(),
+            ));
+        }
+        if let Some(other) = mounts[index + 1..].iter().find(|other| {
+            is_below(&other.prefix, &mount.prefix) || is_below(&mount.prefix, &other.prefix)
+        }) {
+            return Err(Error::InvalidConfig(format!(
+                "'{}' and '{}' overlap",
+                mount.prefix, other.prefix
+            )));
+        }
+    }
+    Ok(mounts)
+}
+
+pub fn parse_config_from_reader<R: Read>(mut reader: R) -> Result<Vec<Mount>, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_config_from_str(&data)
+}
+
+#[derive(Clone)]
+struct Connection {
+    client: ValClient<Channel>,
+    token: Option<String>,
+}
+
+impl Connection {
+    fn request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        if let Some(token) = &self.token {
+            if let Ok(value) = format!("Bearer {token}").parse() {
+                request.metadata_mut().insert("authorization", value);
+            }
+        }
+        request
+    }
+}
+
+/// The state of a mount, shared with the provider of its actuators.
+#[derive(Clone, Default)]
+struct Zone {
+    connection: Arc<RwLock<Option<Connection>>>,
+    // Id -> path of the mirrored entries
+    paths: Arc<RwLock<HashMap<i32, String>>>,
+}
+
+/// Forwards actuation requests to the zone broker.
+struct ZoneActuators {
+    zone: Zone,
+}
+
+#[async_trait::async_trait]
+impl ActuationProvider for ZoneActuators {
+    async fn actuate(
+        &self,
+        actuation_changes: Vec<ActuationChange>,
+    ) -> Result<(), (ActuationError, String)> {
+        let Some(connection) = self.zone.connection.read().await.clone() else {
+            return Err((
+                ActuationError::ProviderNotAvailable,
+                "zone broker not connected".to_owned(),
+            ));
+        };
+        let paths = self.zone.paths.read().await;
+        let actuate_requests = actuation_changes
+            .into_iter()
+            .filter_map(|change| {
+                Some(proto::ActuateRequest {
+                    signal_id: Some(proto::SignalId {
+                        signal: Some(proto::signal_id::Signal::Path(
+                            paths.get(&change.id)?.clone(),
+                        )),
+                    }),
+                    value: Some(change.data_value.into()),
+                })
+            })
+            .collect();
+        drop(paths);
+
+        let mut client = connection.client.clone();
+        client
+            .batch_actuate(connection.request(proto::BatchActuateRequest { actuate_requests }))
+            .await
+            .map(|_| ())
+            .map_err(|status| {
+                (
+                    ActuationError::from_tonic_status(&status),
+                    status.message().to_owned(),
+                )
+            })
+    }
+
+    // Claimed for good, a lost zone broker fails the actuation instead
+    fn is_available(&self) -> bool {
+        true
+    }
+}
+
+/// Mount the subtrees served by zone brokers and keep them mounted until
+/// the broker shuts down.
+pub fn start(broker: DataBroker, mounts: Vec<Mount>) {
+    for mount in mounts {
+        info!("Mounting {} served by {}", mount.prefix, mount.address);
+        tokio::spawn(run(broker.clone(), mount));
+    }
+}
+
+async fn run(broker: DataBroker, mount: Mount) {
+    let zone = Zone::default();
+    let mut claimed = HashSet::new();
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    loop {
+        tokio::select! {
+            result = follow(&broker, &mount, &zone, &mut claimed) => {
+                if let Err(err) = result {
+                    warn!("Lost zone broker {} serving {}: {}", mount.address, mount.prefix, err);
+                }
+            }
+            _ = shutdown_trigger.recv() => break,
+        }
+        *zone.connection.write().await = None;
+        set_unavailable(&broker, &zone).await;
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+async fn connect(mount: &Mount) -> Result<Connection, BoxError> {
+    #[allow(unused_mut)]
+    let mut endpoint =
+        Endpoint::from_shared(mount.address.clone())?.connect_timeout(CONNECT_TIMEOUT);
+    #[cfg(feature = "tls")]
+    if let Some(ca_cert) = &mount.ca_cert {
+        let pem = std::fs::read(ca_cert)?;
+        endpoint = endpoint.tls_config(
+            tonic::transport::ClientTlsConfig::new()
+                .ca_certificate(tonic::transport::Certificate::from_pem(pem)),
+        )?;
+    }
+    // Read on every connect, tokens get renewed
+    let token = match &mount.token_file {
+        Some(token_file) => Some(std::fs::read_to_string(token_file)?.trim().to_owned()),
+        None => None,
+    };
+    Ok(Connection {
+        client: ValClient::new(endpoint.connect().await?),
+        token,
+    })
+}
+
+/// Mirror the entries of the zone broker until the connection is lost.
+async fn follow(
+    broker: &DataBroker,
+    mount: &Mount,
+    zone: &Zone,
+    claimed: &mut HashSet<i32>,
+) -> Result<(), BoxError> {
+    let connection = connect(mount).await?;
+    let mut client = connection.client.clone();
+
+    let mut metadata = Vec::new();
+    let mut page_token = String::new();
+    loop {
+        let response = client
+            .list_metadata(connection.request(proto::ListMetadataRequest {
+                root: mount.prefix.clone(),
+                page_token,
+                ..Default::default()
+            }))
+            .await?
+            .into_inner();
+        metadata.extend(response.metadata);
+        if response.next_page_token.is_empty() {
+            break;
+        }
+        page_token = response.next_page_token;
+    }
+
+    let access = broker.authorized_access(&permissions::ALLOW_ALL);
+    let mut ids = HashMap::new();
+    let mut actuators = Vec::new();
+    for metadata in metadata {
+        if !is_below(&metadata.path, &mount.prefix) {
+            continue;
+        }
+        let path = metadata.path.clone();
+        match add_entry(&access, metadata).await {
+            Ok((id, entry_type)) => {
+                if entry_type == broker::EntryType::Actuator && !claimed.contains(&id) {
+                    actuators.push(id);
+                }
+                ids.insert(path, id);
+            }
+            Err(err) => warn!("Failed to mount {}: {}", path, err),
+        }
+    }
+    if ids.is_empty() {
+        return Err(format!("no entries below {}", mount.prefix).into());
+    }
+
+    *zone.paths.write().await = ids.iter().map(|(path, id)| (*id, path.clone())).collect();
+    *zone.connection.write().await = Some(connection.clone());
+    if !actuators.is_empty() {
+        access
+            .provide_actuation(
+                actuators.clone(),
+                Box::new(ZoneActuators { zone: zone.clone() }),
+            )
+            .await
+            .map_err(|(_, message)| message)?;
+        claimed.extend(actuators);
+    }
+    info!(
+        "Mounted {} entries of {} served by {}",
+        ids.len(),
+        mount.prefix,
+        mount.address
+    );
+
+    let mut stream = client
+        .subscribe(connection.request(proto::SubscribeRequest {
+            signal_paths: ids.keys().cloned().collect(),
+            ..Default::default()
+        }))
+        .await?
+        .into_inner();
+    while let Some(response) = stream.message().await? {
+        let updates: Vec<_> = response
+            .entries
+            .iter()
+            .filter_map(|(path, datapoint)| {
+                let update = EntryUpdate {
+                    datapoint: Some(Datapoint::from(datapoint)),
+                    ..Default::default()
+                };
+                Some((*ids.get(path)?, update))
+            })
+            .collect();
+        if let Err(errors) = access.update_entries(updates).await {
+            for (id, error) in errors {
+                debug!("Failed to mirror value of id {}: {:?}", id, error);
+            }
+        }
+    }
+    Err("subscription closed by the zone broker".into())
+}
+
+async fn add_entry(
+    access: &AuthorizedAccess<'_, '_>,
+    metadata: proto::Metadata,
+) -> Result<(i32, broker::EntryType), BoxError> {
+    let data_type = proto::DataType::try_from(metadata.data_type)
+        .ok()
+        .and_then(|data_type| broker::DataType::try_from(data_type).ok())
+        .ok_or("unsupported data type")?;
+    let entry_type = proto::EntryType::try_from(metadata.entry_type)
+        .ok()
+        .and_then(|entry_type| broker::EntryType::try_from(entry_type).ok())
+        .ok_or("unsupported entry type")?;
+    let id = access
+        .add_entry(
+            metadata.path,
+            data_type,
+            broker::ChangeType::OnChange,
+            entry_type.clone(),
+            metadata.description,
+            metadata.min.map(DataValue::from),
+            metadata.max.map(DataValue::from),
+            metadata.allowed_values.map(DataValue::from),
+            Some(metadata.unit).filter(|unit| !unit.is_empty()),
+        )
+        .await
+        .map_err(|err| format!("{err:?}"))?;
+    Ok((id, entry_type))
+}
+
+async fn set_unavailable(broker: &DataBroker, zone: &Zone) {
+    let updates: Vec<_> = zone
+        .paths
+        .read()
+        .await
+        .keys()
+        .map(|id| {
+            let update = EntryUpdate {
+                datapoint: Some(Datapoint {
+                    ts: SystemTime::now(),
+                    source_ts: None,
+                    value: DataValue::NotAvailable,
+                }),
+                ..Default::default()
+            };
+            (*id, update)
+        })
+        .collect();
+    let _ = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .update_entries(updates)
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    use super::*;
+    use crate::authorization::Authorization;
+    use crate::grpc::server::{self, Api};
+
+    #[derive(Default, Clone)]
+    struct Provider {
+        changes: Arc<Mutex<Vec<ActuationChange>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ActuationProvider for Provider {
+        async fn actuate(
+            &self,
+            actuation_changes: Vec<ActuationChange>,
+        ) -> Result<(), (ActuationError, String)> {
+            self.changes.lock().unwrap().extend(actuation_changes);
+            Ok(())
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    async fn add_entry(
+        broker: &DataBroker,
+        path: &str,
+        data_type: broker::DataType,
+        entry_type: broker::EntryType,
+    ) -> i32 {
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                path.to_owned(),
+                data_type,
+                broker::ChangeType::OnChange,
+                entry_type,
+                "Test".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn wait_for_value(broker: &DataBroker, path: &str, value: &DataValue) {
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        for _ in 0..100 {
+            if let Ok(datapoint) = access.get_datapoint_by_path(path).await {
+                if &datapoint.value == value {
+                    return;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("{path} never became {value:?}");
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let mounts = parse_config_from_str(
+            r#"[
+                { "prefix": "Vehicle.Body", "address": "http://zone-front:55555" },
+                { "prefix": "Vehicle.Bodywork", "address": "http://zone-rear:55555", "token_file": "rear.token" }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[1].token_file, Some(PathBuf::from("rear.token")));
+
+        assert!(matches!(
+            parse_config_from_str(
+                r#"[
+                    { "prefix": "Vehicle.Body", "address": "http://zone-front:55555" },
+                    { "prefix": "Vehicle.Body.Lights", "address": "http://zone-rear:55555" }
+                ]"#
+            ),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_config_from_str(r#"[{ "prefix": "Vehicle..Body", "address": "http://zone" }]"#),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_config_from_str(r#"[{ "prefix": "Vehicle.Body" }]"#),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mount_zone_broker() {
+        let zone = DataBroker::default();
+        add_entry(
+            &zone,
+            "Vehicle.Body.Temperature",
+            broker::DataType::Float,
+            broker::EntryType::Sensor,
+        )
+        .await;
+        let lights = add_entry(
+            &zone,
+            "Vehicle.Body.Lights.IsOn",
+            broker::DataType::Bool,
+            broker::EntryType::Actuator,
+        )
+        .await;
+        add_entry(
+            &zone,
+            "Vehicle.Speed",
+            broker::DataType::Float,
+            broker::EntryType::Sensor,
+        )
+        .await;
+        let provider = Provider::default();
+        zone.authorized_access(&permissions::ALLOW_ALL)
+            .provide_actuation(vec![lights], Box::new(provider.clone()))
+            .await
+            .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        {
+            let zone = zone.clone();
+            tokio::spawn(async move {
+                let _ = server::serve_with_incoming_shutdown(
+                    TcpListenerStream::new(listener),
+                    zone,
+                    #[cfg(feature = "tls")]
+                    server::ServerTLS::Disabled,
+                    &[Api::KuksaValV2],
+                    Authorization::Disabled,
+                    std::future::pending(),
+                )
+                .await;
+            });
+        }
+
+        let central = DataBroker::default();
+        start(
+            central.clone(),
+            vec![Mount {
+                prefix: "Vehicle.Body".to_owned(),
+                address,
+                token_file: None,
+                ca_cert: None,
+            }],
+        );
+
+        let update = EntryUpdate {
+            datapoint: Some(Datapoint {
+                ts: SystemTime::now(),
+                source_ts: None,
+                value: DataValue::Float(21.5),
+            }),
+            ..Default::default()
+        };
+        let temperature = zone
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_id_by_path("Vehicle.Body.Temperature")
+            .await
+            .unwrap();
+        zone.authorized_access(&permissions::ALLOW_ALL)
+            .update_entries([(temperature, update)])
+            .await
+            .unwrap();
+        wait_for_value(
+            &central,
+            "Vehicle.Body.Temperature",
+            &DataValue::Float(21.5),
+        )
+        .await;
+
+        let access = central.authorized_access(&permissions::ALLOW_ALL);
+        // Only the mounted subtree
+        assert!(access.get_id_by_path("Vehicle.Speed").await.is_none());
+        let lights = access
+            .get_id_by_path("Vehicle.Body.Lights.IsOn")
+            .await
+            .unwrap();
+        access
+            .actuate(&lights, &DataValue::Bool(true))
+            .await
+            .unwrap();
+        let changes = provider.changes.lock().unwrap().clone();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].data_value, DataValue::Bool(true));
+    }
+}
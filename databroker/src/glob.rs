@@ -11,7 +11,6 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
-use glob_match::glob_match;
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -25,9 +24,195 @@ pub enum MatchError {
     MatchError,
 }
 
+/// A path pattern compiled into its segments, see
+/// [wildcard matching](https://github.com/eclipse-kuksa/kuksa-databroker/blob/main/doc/wildcard_matching.md).
+///
+/// Besides `*` (any single segment) and `**` (any number of segments), a
+/// segment may contain wildcards itself, e.g. `Row*` to match all
+/// instances of a row. A pattern without any wildcard matches the path
+/// itself and everything below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+    ignore_case: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    // `*`
+    Any,
+    // `**`
+    AnyDepth,
+    // A segment containing `*`, split at the wildcards
+    Wildcard(Vec<String>),
+}
+
+impl Segment {
+    fn parse(segment: &str) -> Self {
+        match segment {
+            "*" => Segment::Any,
+            "**" => Segment::AnyDepth,
+            _ if segment.contains('*') => {
+                Segment::Wildcard(segment.split('*').map(str::to_owned).collect())
+            }
+            _ => Segment::Literal(segment.to_owned()),
+        }
+    }
+
+    fn is_match(&self, segment: &str) -> bool {
+        match self {
+            Segment::Literal(literal) => literal == segment,
+            Segment::Any | Segment::AnyDepth => true,
+            Segment::Wildcard(parts) => {
+                // parts has at least two elements, the text before the first
+                // and after the last wildcard
+                let (first, rest) = parts.split_first().expect("wildcard has parts");
+                let (last, middle) = rest.split_last().expect("wildcard has parts");
+                let Some(mut remaining) = segment.strip_prefix(first.as_str()) else {
+                    return false;
+                };
+                for part in middle {
+                    match remaining.find(part.as_str()) {
+                        Some(pos) => remaining = &remaining[pos + part.len()..],
+                        None => return false,
+                    }
+                }
+                remaining.ends_with(last.as_str())
+            }
+        }
+    }
+}
+
+impl Pattern {
+    /// Compile a pattern of `.` separated segments. The empty pattern
+    /// matches everything.
+    pub fn new(pattern: &str) -> Result<Self, MatchError> {
+        if pattern.is_empty() {
+            return Ok(Pattern {
+                segments: vec![Segment::AnyDepth],
+                ignore_case: false,
+            });
+        }
+        if !is_valid_pattern(pattern) {
+            return Err(MatchError::MatchError);
+        }
+
+        let mut segments: Vec<Segment> = Vec::new();
+        for segment in pattern.split('.').map(Segment::parse) {
+            // Consecutive `**` are the same as one
+            if segment == Segment::AnyDepth && segments.last() == Some(&Segment::AnyDepth) {
+                continue;
+            }
+            segments.push(segment);
+        }
+        if !pattern.contains('*') {
+            // Match the branch as well as the signal
+            segments.push(Segment::AnyDepth);
+        }
+        Ok(Pattern {
+            segments,
+            ignore_case: false,
+        })
+    }
+
+    /// Match paths regardless of their case.
+    pub fn ignore_case(mut self) -> Self {
+        for segment in &mut self.segments {
+            match segment {
+                Segment::Literal(literal) => *literal = literal.to_lowercase(),
+                Segment::Wildcard(parts) => {
+                    for part in parts {
+                        *part = part.to_lowercase();
+                    }
+                }
+                Segment::Any | Segment::AnyDepth => {}
+            }
+        }
+        self.ignore_case = true;
+        self
+    }
+
+    /// Whether every path matched by `other` is matched by this pattern as
+    /// well. Conservative, segments with wildcards of their own only
+    /// include themselves and literal segments they match.
+    pub fn includes(&self, other: &Pattern) -> bool {
+        if self.ignore_case != other.ignore_case {
+            return false;
+        }
+        includes_segments(&self.segments, &other.segments)
+    }
+
+    /// Whether `path` (`.` separated) matches the pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.is_match_separated_by(path, '.')
+    }
+
+    /// Whether `path` with segments separated by `separator` matches the
+    /// pattern.
+    pub fn is_match_separated_by(&self, path: &str, separator: char) -> bool {
+        if self.ignore_case {
+            match_segments(&self.segments, Some(&path.to_lowercase()), separator)
+        } else {
+            match_segments(&self.segments, Some(path), separator)
+        }
+    }
+}
+
+fn includes_segments(segments: &[Segment], other: &[Segment]) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return other.is_empty();
+    };
+    if *segment == Segment::AnyDepth {
+        // Consume zero or more segments of the other pattern, whatever
+        // they match
+        return includes_segments(rest, other)
+            || (!other.is_empty() && includes_segments(segments, &other[1..]));
+    }
+    let Some((other_segment, other_rest)) = other.split_first() else {
+        return false;
+    };
+    let included = match (segment, other_segment) {
+        (_, Segment::AnyDepth) => false,
+        (Segment::Any, _) => true,
+        (_, Segment::Literal(literal)) => segment.is_match(literal),
+        _ => segment == other_segment,
+    };
+    included && includes_segments(rest, other_rest)
+}
+
+// `path` is None when all of its segments have been consumed
+fn match_segments(segments: &[Segment], path: Option<&str>, separator: char) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return path.is_none();
+    };
+    if *segment == Segment::AnyDepth {
+        // Consume zero or more segments
+        let mut remaining = path;
+        loop {
+            if match_segments(rest, remaining, separator) {
+                return true;
+            }
+            match remaining {
+                Some(path) => remaining = path.split_once(separator).map(|(_, tail)| tail),
+                None => return false,
+            }
+        }
+    }
+    let Some(path) = path else {
+        return false;
+    };
+    let (first, tail) = match path.split_once(separator) {
+        Some((first, tail)) => (first, Some(tail)),
+        None => (path, None),
+    };
+    segment.is_match(first) && match_segments(rest, tail, separator)
+}
+
 #[derive(Debug)]
 pub struct Matcher {
     glob_pattern: String,
+    pattern: Pattern,
 }
 
 impl Matcher {
@@ -44,25 +229,41 @@ impl Matcher {
             glob_pattern.to_string()
         };
 
-        if is_valid_pattern(&glob_pattern_modified) {
-            Ok(Matcher {
-                glob_pattern: Matcher::to_glob_string(&glob_pattern_modified),
-            })
-        } else {
-            Err(MatchError::MatchError)
-        }
+        Ok(Matcher {
+            pattern: Pattern::new(&glob_pattern_modified)?,
+            glob_pattern: Matcher::to_glob_string(&glob_pattern_modified),
+        })
     }
 
-    pub fn is_match(&self, path: &str) -> bool {
-        let mut res: bool = glob_match(&self.glob_pattern, path);
-        // Special case - if we have something like A.B.C as input we want to both match
-        // the explicit signal A.B.C if existing but also
-        // sub-signals if A.B.C is a branch
-        if !res && self.glob_pattern.ends_with("/**") {
-            let new_pattern = self.glob_pattern.strip_suffix("/**").unwrap();
-            res = glob_match(new_pattern, path);
+    /// Whether `glob_path`, i.e. a path with `/` as separator, matches.
+    pub fn is_match(&self, glob_path: &str) -> bool {
+        self.pattern.is_match_separated_by(glob_path, '/')
+    }
+
+    /// Whether a VSS path, i.e. with `.` as separator, matches.
+    pub fn is_match_vss_path(&self, path: &str) -> bool {
+        self.pattern.is_match(path)
+    }
+
+    /// A matcher for everything below the branch(es) matched by this one.
+    pub fn branch_matcher(&self) -> Matcher {
+        let mut segments = self.pattern.segments.clone();
+        if segments.last() != Some(&Segment::AnyDepth) {
+            segments.push(Segment::AnyDepth);
+        }
+        Matcher {
+            glob_pattern: format!("{}/**", self.glob_pattern),
+            pattern: Pattern {
+                segments,
+                ignore_case: self.pattern.ignore_case,
+            },
         }
-        res
+    }
+
+    /// Match paths regardless of their case.
+    pub fn ignore_case(mut self) -> Self {
+        self.pattern = self.pattern.ignore_case();
+        self
     }
 
     pub fn as_string(&self) -> String {
@@ -138,10 +339,6 @@ pub fn to_regex(glob: &str) -> Result<Regex, Error> {
     Regex::new(&re).map_err(|_err| Error::RegexError)
 }
 
-pub fn glob_string_match(glob: &str, path: &str) -> bool {
-    glob_match(glob, path)
-}
-
 lazy_static! {
     static ref REGEX_VALID_PATTERN: regex::Regex = regex::Regex::new(
         r"(?x)
@@ -2817,15 +3014,6 @@ mod tests_glob_matching {
     #[ignore] // Ignored due to comment below
     #[test]
     fn test_matches_combination_of_multiple_wildcard_and_single_wildcard() {
-        /*
-            This is the only case that can not be supported by the new glob match lib used.
-            Known problem, see e.g.
-            https://github.com/devongovett/glob-match/issues/8
-            https://github.com/devongovett/glob-match/issues/9
-            https://github.com/devongovett/glob-match/pull/18
-
-            But repo seems to be abandoned!
-        */
         assert!(using_glob_matching("**.*.*.*.Position")
             .with_signals(ALL_SIGNALS_WITH_SLASH_SEPARATORS)
             .should_match_signals(&[
@@ -2845,11 +3033,8 @@ mod tests_glob_matching {
                 "Vehicle/Cabin/Seat/Row2/DriverSide/Position",
                 "Vehicle/Cabin/Sunroof/Shade/Position",
             ]));
-        /*
-               It doesn't match for example:
-                   "Vehicle.Cabin.RearShade.Position",
-                   "Vehicle.Cabin.Sunroof.Position",
-        */
+        // See test_pattern_double_wildcard_with_single_wildcards for paths
+        // like Vehicle.Cabin.RearShade.Position (not part of the signals)
     }
 
     #[test]
@@ -2994,4 +3179,107 @@ mod tests_glob_matching {
             "*/String/String/String"
         );
     }
+
+    #[test]
+    fn test_pattern_instance_wildcard() {
+        let pattern = Pattern::new("Vehicle.Cabin.Door.Row*.*.IsOpen").unwrap();
+        assert!(pattern.is_match("Vehicle.Cabin.Door.Row1.DriverSide.IsOpen"));
+        assert!(pattern.is_match("Vehicle.Cabin.Door.Row2.PassengerSide.IsOpen"));
+        assert!(!pattern.is_match("Vehicle.Cabin.Door.Column1.DriverSide.IsOpen"));
+        assert!(!pattern.is_match("Vehicle.Cabin.Door.Row1.IsOpen"));
+
+        let pattern = Pattern::new("Vehicle.Cabin.Seat.*1.*Side*").unwrap();
+        assert!(pattern.is_match("Vehicle.Cabin.Seat.Row1.DriverSide"));
+        assert!(pattern.is_match("Vehicle.Cabin.Seat.Row1.PassengerSideShade"));
+        assert!(!pattern.is_match("Vehicle.Cabin.Seat.Row2.DriverSide"));
+        assert!(!pattern.is_match("Vehicle.Cabin.Seat.Row1.Middle"));
+    }
+
+    #[test]
+    fn test_pattern_double_wildcard_with_single_wildcards() {
+        let pattern = Pattern::new("**.*.*.*.Position").unwrap();
+        assert!(pattern.is_match("Vehicle.Cabin.Door.Row2.PassengerSide.Shade.Position"));
+        assert!(pattern.is_match("Vehicle.Cabin.Seat.Row1.Middle.Position"));
+        assert!(pattern.is_match("Vehicle.Cabin.RearShade.Position"));
+        assert!(!pattern.is_match("Vehicle.Cabin.Position"));
+    }
+
+    #[test]
+    fn test_pattern_same_semantics_for_both_separators() {
+        let pattern = Pattern::new("Vehicle.Cabin.Sunroof").unwrap();
+        let matcher = Matcher::new("Vehicle.Cabin.Sunroof").unwrap();
+        for path in [
+            "Vehicle.Cabin.Sunroof",
+            "Vehicle.Cabin.Sunroof.Position",
+            "Vehicle.Cabin.Sunroof.Shade.Position",
+            "Vehicle.Cabin.SunroofPosition",
+            "Vehicle.Cabin",
+        ] {
+            assert_eq!(pattern.is_match(path), matcher.is_match_vss_path(path));
+            assert_eq!(
+                pattern.is_match(path),
+                matcher.is_match(&path.replace('.', "/"))
+            );
+        }
+        assert!(pattern.is_match("Vehicle.Cabin.Sunroof"));
+        assert!(!pattern.is_match("Vehicle.Cabin.SunroofPosition"));
+    }
+
+    #[test]
+    fn test_pattern_includes() {
+        let includes = |pattern: &str, other: &str| {
+            Pattern::new(pattern)
+                .unwrap()
+                .includes(&Pattern::new(other).unwrap())
+        };
+        assert!(includes("", "Vehicle.Cabin.*"));
+        assert!(includes("Vehicle", "Vehicle.Cabin"));
+        assert!(includes("Vehicle.Cabin", "Vehicle.Cabin"));
+        assert!(includes("Vehicle.Cabin", "Vehicle.Cabin.Door.*"));
+        assert!(includes(
+            "Vehicle.*.Door.*.IsOpen",
+            "Vehicle.Cabin.Door.*.IsOpen"
+        ));
+        assert!(includes("Vehicle.**", "Vehicle.**.Position"));
+        assert!(includes(
+            "Vehicle.Cabin.Door.Row*.*",
+            "Vehicle.Cabin.Door.Row1.*"
+        ));
+        assert!(includes("**.Position", "Vehicle.Cabin.*.Position"));
+
+        assert!(!includes("Vehicle.Cabin", "Vehicle"));
+        assert!(!includes("Vehicle.Cabin", "Vehicle.*"));
+        // Vehicle.Cabin includes everything below it, not just a level
+        assert!(!includes("Vehicle.*", "Vehicle.Cabin"));
+        assert!(!includes("Vehicle.*.*", "Vehicle.Cabin"));
+        assert!(!includes("Vehicle.Cabin.*", "Vehicle.Cabin.Door.*"));
+        assert!(!includes("Vehicle.*.Door", "Vehicle.Cabin.*"));
+        assert!(!includes("Vehicle.Cabin.*", "Vehicle.Cabin.**"));
+        assert!(!includes("Vehicle.Cabin.Door.Row*", "Vehicle.Cabin.Door.*"));
+        assert!(!includes("Vehicle.Speed", ""));
+    }
+
+    #[test]
+    fn test_branch_matcher() {
+        let matcher = Matcher::new("Vehicle.*.Sunroof").unwrap();
+        assert!(!matcher.is_match("Vehicle/Cabin/Sunroof/Position"));
+        let branch_matcher = matcher.branch_matcher();
+        assert_eq!(branch_matcher.as_string(), "Vehicle/*/Sunroof/**");
+        assert!(branch_matcher.is_match("Vehicle/Cabin/Sunroof/Position"));
+        assert!(branch_matcher.is_match("Vehicle/Cabin/Sunroof/Shade/Position"));
+    }
+
+    #[test]
+    fn test_matcher_ignore_case() {
+        let matcher = Matcher::new("vehicle.cabin.door.row*.*.isopen")
+            .unwrap()
+            .ignore_case();
+        assert!(matcher.is_match("Vehicle/Cabin/Door/Row1/DriverSide/IsOpen"));
+        assert!(matcher.is_match_vss_path("Vehicle.Cabin.Door.Row2.PassengerSide.IsOpen"));
+        assert!(!matcher.is_match_vss_path("Vehicle.Cabin.Door.Row2.PassengerSide.IsLocked"));
+
+        let matcher = Matcher::new("Vehicle.Speed").unwrap();
+        assert!(!matcher.is_match_vss_path("Vehicle.speed"));
+        assert!(matcher.ignore_case().is_match_vss_path("Vehicle.speed"));
+    }
 }
@@ -0,0 +1,748 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! GraphQL API with a schema generated from the VSS tree.
+//!
+//! Every branch becomes an object type (`Vehicle_Cabin` for
+//! `Vehicle.Cabin`) and every signal a field of one of the signal types,
+//! which hold the metadata and the current value:
+//!
+//! ```graphql
+//! {
+//!   Vehicle {
+//!     Speed { value ts unit }
+//!     Cabin { Infotainment { Media { Volume { value target } } } }
+//!   }
+//!   signal(path: "Vehicle.Speed") { description ... on FloatSignal { value } }
+//! }
+//! ```
+//!
+//! Queries are sent with `POST /graphql`, subscriptions over a WebSocket
+//! at the same path (`graphql-transport-ws` or `graphql-ws` protocol):
+//!
+//! ```graphql
+//! subscription {
+//!   signals(paths: ["Vehicle.Speed"]) { path ts ... on FloatSignal { value } }
+//! }
+//! ```
+//!
+//! The schema is generated once on startup, entries registered later
+//! aren't part of it.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_graphql::dynamic::{
+    Enum, Field, FieldFuture, FieldValue, InputValue, Interface, InterfaceField, Object,
+    ResolverContext, Scalar, Schema, SchemaError, Subscription, SubscriptionField,
+    SubscriptionFieldFuture, TypeRef,
+};
+use async_graphql::http::{WebSocket as GraphQLWebSocket, WsMessage, ALL_WEBSOCKET_PROTOCOLS};
+use async_graphql::{Data, Value};
+use axum::{
+    extract::{
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use futures::{SinkExt, StreamExt};
+use tracing::{debug, error, info};
+
+use crate::authorization::{self, Authorization};
+use crate::broker::{self, DataBroker, EntryType, Metadata};
+use crate::permissions::{self, Permissions};
+use crate::types::{DataType, DataValue};
+
+/// 64-bit integers, which don't fit the 32-bit `Int` of GraphQL.
+const LONG: &str = "Long";
+const SIGNAL: &str = "Signal";
+const ENTRY_TYPE: &str = "EntryType";
+
+/// GraphQL types of the values of the signal types.
+const VALUE_TYPES: [(&str, &str); 5] = [
+    ("Boolean", TypeRef::BOOLEAN),
+    ("String", TypeRef::STRING),
+    ("Int", TypeRef::INT),
+    ("Long", LONG),
+    ("Float", TypeRef::FLOAT),
+];
+
+#[derive(Clone)]
+struct AppState {
+    broker: DataBroker,
+    schema: Schema,
+    authorization: Authorization,
+}
+
+/// A signal resolved in a query. Subscriptions resolve it with the value
+/// that changed instead of reading the current one.
+struct SignalRef {
+    metadata: Arc<Metadata>,
+    datapoint: Option<broker::Datapoint>,
+}
+
+pub async fn serve(
+    addr: impl Into<SocketAddr>,
+    broker: DataBroker,
+    authorization: Authorization,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let schema = build_schema(broker.clone()).await?;
+    broker.add_served_api("graphql");
+    let app = Router::new()
+        .route("/graphql", get(handle_upgrade).post(handle_query))
+        .with_state(AppState {
+            broker,
+            schema,
+            authorization,
+        });
+
+    let addr = addr.into();
+    let builder = axum::Server::try_bind(&addr).map_err(|err| {
+        error!("Failed to bind address {addr}: {err}");
+        err
+    })?;
+
+    info!("GraphQL service listening on {}", addr);
+    builder
+        .serve(app.into_make_service())
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn handle_query(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Result<Json<async_graphql::Response>, (StatusCode, String)> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+    let mut request = request;
+    if let Some(permissions) = resolve_permissions(&state.broker, &state.authorization, token)
+        .map_err(|err| (StatusCode::UNAUTHORIZED, err))?
+    {
+        request = request.data(permissions);
+    }
+    Ok(Json(state.schema.execute(request).await))
+}
+
+async fn handle_upgrade(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(str::to_owned);
+    ws.protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| handle_websocket(socket, state, token))
+}
+
+async fn handle_websocket(socket: WebSocket, state: AppState, token: Option<String>) {
+    let protocol = match socket
+        .protocol()
+        .and_then(|protocol| protocol.to_str().ok())
+        .map(str::parse)
+    {
+        Some(Ok(protocol)) => protocol,
+        _ => {
+            debug!("Unsupported GraphQL websocket subprotocol");
+            return;
+        }
+    };
+
+    let (mut sink, stream) = socket.split();
+    let stream = stream
+        .take_while(|message| futures::future::ready(message.is_ok()))
+        .filter_map(|message| {
+            futures::future::ready(match message {
+                Ok(Message::Text(text)) => Some(text.into_bytes()),
+                Ok(Message::Binary(bytes)) => Some(bytes),
+                _ => None,
+            })
+        });
+
+    let broker = state.broker.clone();
+    let authorization = state.authorization.clone();
+    let mut messages = GraphQLWebSocket::new(state.schema.clone(), stream, protocol)
+        .on_connection_init(move |payload| async move {
+            // Browsers can't set headers on websockets, the token is
+            // passed in the payload of the init message instead.
+            let token = payload
+                .get("token")
+                .or_else(|| payload.get("Authorization"))
+                .and_then(|token| token.as_str())
+                .map(|token| token.strip_prefix("Bearer ").unwrap_or(token).to_owned())
+                .or(token);
+            let mut data = Data::default();
+            if let Some(permissions) =
+                resolve_permissions(&broker, &authorization, token.as_deref())
+                    .map_err(async_graphql::Error::new)?
+            {
+                data.insert(permissions);
+            }
+            Ok(data)
+        });
+
+    while let Some(message) = messages.next().await {
+        let message = match message {
+            WsMessage::Text(text) => Message::Text(text),
+            WsMessage::Close(code, reason) => Message::Close(Some(CloseFrame {
+                code,
+                reason: reason.into(),
+            })),
+        };
+        if sink.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Permissions of the token, `None` if authorization is enabled but no
+/// token was given. Metadata can still be queried then.
+fn resolve_permissions(
+    broker: &DataBroker,
+    authorization: &Authorization,
+    token: Option<&str>,
+) -> Result<Option<Permissions>, String> {
+    if token.is_none() && matches!(authorization, Authorization::Enabled { .. }) {
+        return Ok(None);
+    }
+    authorization::permissions_from_token(authorization, token)
+        .map(Some)
+        .map_err(|err| {
+            broker.record_authorization_failure();
+            err.to_string()
+        })
+}
+
+#[derive(Default)]
+struct Branch {
+    branches: BTreeMap<String, Branch>,
+    signals: BTreeMap<String, Arc<Metadata>>,
+}
+
+impl Branch {
+    fn insert(&mut self, path: &[&str], metadata: Arc<Metadata>) {
+        match path {
+            [] => {}
+            [name] => {
+                self.signals.insert(field_name(name), metadata);
+            }
+            [name, rest @ ..] => self
+                .branches
+                .entry(field_name(name))
+                .or_default()
+                .insert(rest, metadata),
+        }
+    }
+
+    /// Register the object types of this branch and its children, named
+    /// after their path.
+    fn register(&self, type_name: &str, types: &mut Vec<Object>) {
+        let mut object = Object::new(type_name);
+        for (name, branch) in &self.branches {
+            let child_type = format!("{type_name}_{name}");
+            branch.register(&child_type, types);
+            object = object.field(Field::new(name, TypeRef::named_nn(&child_type), |_| {
+                FieldFuture::new(async { Ok(Some(FieldValue::owned_any(()))) })
+            }));
+        }
+        for (name, metadata) in &self.signals {
+            let description = metadata.description.clone();
+            let type_name = signal_type(&metadata.data_type);
+            let metadata = metadata.clone();
+            let field = Field::new(name, TypeRef::named_nn(type_name), move |_| {
+                let metadata = metadata.clone();
+                FieldFuture::new(async move {
+                    Ok(Some(FieldValue::owned_any(SignalRef {
+                        metadata,
+                        datapoint: None,
+                    })))
+                })
+            });
+            object = object.field(field.description(description));
+        }
+        types.push(object);
+    }
+}
+
+/// Generate the schema from the entries of the broker.
+pub async fn build_schema(broker: DataBroker) -> Result<Schema, SchemaError> {
+    let entries = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .map_entries(|entry| Arc::new(entry.metadata().clone()))
+        .await;
+
+    let mut root = Branch::default();
+    let mut signals = BTreeMap::new();
+    for metadata in entries {
+        let path: Vec<&str> = metadata.path.split('.').collect();
+        root.insert(&path, metadata.clone());
+        signals.insert(metadata.path.clone(), metadata);
+    }
+
+    let mut query = Object::new("Query");
+    let mut types = Vec::new();
+    for (name, branch) in &root.branches {
+        branch.register(name, &mut types);
+        query = query.field(Field::new(name, TypeRef::named_nn(name), |_| {
+            FieldFuture::new(async { Ok(Some(FieldValue::owned_any(()))) })
+        }));
+    }
+    let signals = Arc::new(signals);
+    {
+        let signals = signals.clone();
+        query = query.field(
+            Field::new("signal", TypeRef::named(SIGNAL), move |ctx| {
+                let signals = signals.clone();
+                FieldFuture::new(async move {
+                    let path = ctx.args.try_get("path")?.string()?;
+                    Ok(signals.get(path).map(|metadata| {
+                        FieldValue::owned_any(SignalRef {
+                            metadata: metadata.clone(),
+                            datapoint: None,
+                        })
+                        .with_type(signal_type(&metadata.data_type))
+                    }))
+                })
+            })
+            .description("The signal at `path`")
+            .argument(InputValue::new("path", TypeRef::named_nn(TypeRef::STRING))),
+        );
+    }
+
+    let subscription = Subscription::new("Subscription").field(
+        SubscriptionField::new("signals", TypeRef::named_nn(SIGNAL), move |ctx| {
+            let signals = signals.clone();
+            SubscriptionFieldFuture::new(async move {
+                let broker = ctx.data::<DataBroker>()?;
+                let permissions = permissions(&ctx)?;
+                let access = broker.authorized_access(permissions);
+                let mut entries = std::collections::HashMap::new();
+                for path in ctx.args.try_get("paths")?.list()?.iter() {
+                    let path = path.string()?;
+                    let Some(metadata) = signals.get(path) else {
+                        return Err(format!("{path}: not found").into());
+                    };
+                    permissions
+                        .can_read(path)
+                        .map_err(|_| format!("{path}: permission denied"))?;
+                    entries.insert(metadata.id, [broker::Field::Datapoint].into());
+                }
+                let stream = access
+                    .subscribe(entries, None)
+                    .await
+                    .map_err(|err| format!("failed to subscribe: {err:?}"))?;
+                Ok(stream
+                    .flat_map(move |notification| {
+                        let signals = notification
+                            .updates
+                            .into_iter()
+                            .filter_map(|change| {
+                                let metadata = signals.get(&change.update.path?)?.clone();
+                                let signal = SignalRef {
+                                    datapoint: Some(change.update.datapoint?),
+                                    metadata,
+                                };
+                                let type_name = signal_type(&signal.metadata.data_type);
+                                Some(Ok(FieldValue::owned_any(signal).with_type(type_name)))
+                            })
+                            .collect::<Vec<_>>();
+                        futures::stream::iter(signals)
+                    })
+                    .boxed())
+            })
+        })
+        .description("Changes of the signals at `paths`, starting with their current values")
+        .argument(InputValue::new(
+            "paths",
+            TypeRef::named_nn_list_nn(TypeRef::STRING),
+        )),
+    );
+
+    let mut builder = Schema::build("Query", None, Some("Subscription"))
+        .register(query)
+        .register(subscription)
+        .register(Scalar::new(LONG).description("64-bit integer"))
+        .register(
+            Enum::new(ENTRY_TYPE)
+                .item("SENSOR")
+                .item("ATTRIBUTE")
+                .item("ACTUATOR"),
+        )
+        .register(signal_interface())
+        .data(broker);
+    for (name, value_type) in VALUE_TYPES {
+        builder = builder
+            .register(signal_object(
+                &format!("{name}Signal"),
+                TypeRef::named(value_type),
+            ))
+            .register(signal_object(
+                &format!("{name}ArraySignal"),
+                TypeRef::named_nn_list(value_type),
+            ));
+    }
+    for object in types {
+        builder = builder.register(object);
+    }
+    builder.finish()
+}
+
+/// Name of the signal type for a data type.
+fn signal_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Bool => "BooleanSignal",
+        DataType::String => "StringSignal",
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Uint8 | DataType::Uint16 => {
+            "IntSignal"
+        }
+        DataType::Int64 | DataType::Uint32 | DataType::Uint64 => "LongSignal",
+        DataType::Float | DataType::Double => "FloatSignal",
+        DataType::BoolArray => "BooleanArraySignal",
+        DataType::StringArray => "StringArraySignal",
+        DataType::Int8Array
+        | DataType::Int16Array
+        | DataType::Int32Array
+        | DataType::Uint8Array
+        | DataType::Uint16Array => "IntArraySignal",
+        DataType::Int64Array | DataType::Uint32Array | DataType::Uint64Array => "LongArraySignal",
+        DataType::FloatArray | DataType::DoubleArray => "FloatArraySignal",
+    }
+}
+
+/// Fields common to all signal types.
+fn signal_fields() -> Vec<(&'static str, TypeRef, &'static str)> {
+    vec![
+        (
+            "path",
+            TypeRef::named_nn(TypeRef::STRING),
+            "Path in the VSS tree",
+        ),
+        (
+            "description",
+            TypeRef::named_nn(TypeRef::STRING),
+            "Description",
+        ),
+        ("unit", TypeRef::named(TypeRef::STRING), "Unit of the value"),
+        (
+            "dataType",
+            TypeRef::named_nn(TypeRef::STRING),
+            "VSS data type",
+        ),
+        (
+            "entryType",
+            TypeRef::named_nn(ENTRY_TYPE),
+            "Sensor, attribute or actuator",
+        ),
+        (
+            "ts",
+            TypeRef::named(LONG),
+            "Time of the value in milliseconds since the UNIX epoch",
+        ),
+    ]
+}
+
+fn signal_interface() -> Interface {
+    signal_fields().into_iter().fold(
+        Interface::new(SIGNAL),
+        |interface, (name, ty, description)| {
+            interface.field(InterfaceField::new(name, ty).description(description))
+        },
+    )
+}
+
+fn signal_object(type_name: &str, value_type: TypeRef) -> Object {
+    let object = signal_fields().into_iter().fold(
+        Object::new(type_name).implement(SIGNAL),
+        |object, (name, ty, description)| {
+            object.field(
+                Field::new(name, ty, move |ctx| {
+                    FieldFuture::new(async move {
+                        let signal = ctx.parent_value.try_downcast_ref::<SignalRef>()?;
+                        let metadata = &signal.metadata;
+                        Ok(match name {
+                            "path" => Some(Value::from(metadata.path.clone())),
+                            "description" => Some(Value::from(metadata.description.clone())),
+                            "unit" => metadata.unit.clone().map(Value::from),
+                            "dataType" => Some(Value::from(metadata.data_type.to_string())),
+                            "entryType" => Some(Value::Enum(async_graphql::Name::new(
+                                match metadata.entry_type {
+                                    EntryType::Sensor => "SENSOR",
+                                    EntryType::Attribute => "ATTRIBUTE",
+                                    EntryType::Actuator => "ACTUATOR",
+                                },
+                            ))),
+                            _ => datapoint(&ctx, signal).await?.map(|datapoint| {
+                                let ts = datapoint
+                                    .ts
+                                    .duration_since(SystemTime::UNIX_EPOCH)
+                                    .unwrap_or_default();
+                                Value::from(ts.as_millis() as u64)
+                            }),
+                        })
+                    })
+                })
+                .description(description),
+            )
+        },
+    );
+    object
+        .field(
+            Field::new("value", value_type.clone(), |ctx| {
+                FieldFuture::new(async move {
+                    let signal = ctx.parent_value.try_downcast_ref::<SignalRef>()?;
+                    Ok(datapoint(&ctx, signal)
+                        .await?
+                        .and_then(|datapoint| to_value(datapoint.value)))
+                })
+            })
+            .description("Current value, null if not available"),
+        )
+        .field(
+            Field::new("target", value_type, |ctx| {
+                FieldFuture::new(async move {
+                    let signal = ctx.parent_value.try_downcast_ref::<SignalRef>()?;
+                    let broker = ctx.data::<DataBroker>()?;
+                    let entry = broker
+                        .authorized_access(permissions(&ctx)?)
+                        .get_entry_by_id(signal.metadata.id)
+                        .await
+                        .map_err(|err| read_error(&signal.metadata.path, err))?;
+                    Ok(entry
+                        .actuator_target
+                        .and_then(|target| to_value(target.value)))
+                })
+            })
+            .description("Target value of an actuator, null if not set"),
+        )
+}
+
+/// The datapoint of the signal, read from the broker unless the signal
+/// was resolved with one.
+async fn datapoint(
+    ctx: &ResolverContext<'_>,
+    signal: &SignalRef,
+) -> async_graphql::Result<Option<broker::Datapoint>> {
+    if let Some(datapoint) = &signal.datapoint {
+        return Ok(Some(datapoint.clone()));
+    }
+    let broker = ctx.data::<DataBroker>()?;
+    broker
+        .authorized_access(permissions(ctx)?)
+        .get_datapoint(signal.metadata.id)
+        .await
+        .map(Some)
+        .map_err(|err| read_error(&signal.metadata.path, err))
+}
+
+fn permissions<'a>(ctx: &ResolverContext<'a>) -> async_graphql::Result<&'a Permissions> {
+    ctx.data_opt::<Permissions>()
+        .ok_or_else(|| "no access token provided".into())
+}
+
+fn read_error(path: &str, err: broker::ReadError) -> async_graphql::Error {
+    match err {
+        broker::ReadError::NotFound => format!("{path}: not found").into(),
+        broker::ReadError::PermissionDenied => format!("{path}: permission denied").into(),
+        broker::ReadError::PermissionExpired => "access token expired".into(),
+    }
+}
+
+fn to_value(value: DataValue) -> Option<Value> {
+    match value {
+        DataValue::NotAvailable => None,
+        value => Value::from_json(value.into()).ok(),
+    }
+}
+
+/// Turn a VSS name into a valid GraphQL name.
+fn field_name(name: &str) -> String {
+    let mut field_name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if field_name.starts_with(|c: char| c.is_ascii_digit()) {
+        field_name.insert(0, '_');
+    }
+    field_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization::jwt;
+    use std::time::Duration;
+
+    async fn test_schema() -> (DataBroker, Schema) {
+        let broker = DataBroker::default();
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        for (path, data_type, entry_type) in [
+            ("Vehicle.Speed", DataType::Float, EntryType::Sensor),
+            (
+                "Vehicle.Cabin.Infotainment.Media.Volume",
+                DataType::Uint8,
+                EntryType::Actuator,
+            ),
+            (
+                "Vehicle.VehicleIdentification.VIN",
+                DataType::String,
+                EntryType::Attribute,
+            ),
+        ] {
+            access
+                .add_entry(
+                    path.to_owned(),
+                    data_type,
+                    broker::ChangeType::OnChange,
+                    entry_type,
+                    format!("{path} description"),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let schema = build_schema(broker.clone()).await.unwrap();
+        (broker, schema)
+    }
+
+    async fn set_value(broker: &DataBroker, path: &str, value: DataValue) {
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = access.get_id_by_path(path).await.unwrap();
+        access
+            .update_entries([(
+                id,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint {
+                        ts: SystemTime::UNIX_EPOCH + Duration::from_millis(1500),
+                        source_ts: None,
+                        value,
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_field_name() {
+        assert_eq!(field_name("Row1"), "Row1");
+        assert_eq!(field_name("2ndRow"), "_2ndRow");
+        assert_eq!(field_name("Left-Side"), "Left_Side");
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let (broker, schema) = test_schema().await;
+        set_value(&broker, "Vehicle.Speed", DataValue::Float(12.5)).await;
+
+        let request = async_graphql::Request::new(
+            r#"{
+                Vehicle {
+                    Speed { path value ts entryType }
+                    Cabin { Infotainment { Media { Volume { dataType value } } } }
+                }
+                signal(path: "Vehicle.VehicleIdentification.VIN") {
+                    description
+                    ... on StringSignal { value }
+                }
+            }"#,
+        )
+        .data(permissions::ALLOW_ALL.clone());
+        let response = schema.execute(request).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({
+                "Vehicle": {
+                    "Speed": { "path": "Vehicle.Speed", "value": 12.5, "ts": 1500, "entryType": "SENSOR" },
+                    "Cabin": { "Infotainment": { "Media": { "Volume": { "dataType": "Uint8", "value": null } } } }
+                },
+                "signal": {
+                    "description": "Vehicle.VehicleIdentification.VIN description",
+                    "value": null
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_permissions() {
+        let (broker, schema) = test_schema().await;
+
+        // Metadata doesn't need a token, values do
+        let response = schema
+            .execute("{ Vehicle { Speed { description } } }")
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let response = schema.execute("{ Vehicle { Speed { value } } }").await;
+        assert_eq!(response.errors.len(), 1);
+
+        let authorization = Authorization::new(jwt::DEV_PUBLIC_KEY.to_owned()).unwrap();
+        let token = jwt::Encoder::dev()
+            .mint("dashboard", "read:Vehicle.Cabin", Duration::from_secs(60))
+            .unwrap();
+        let permissions = resolve_permissions(&broker, &authorization, Some(&token))
+            .unwrap()
+            .unwrap();
+        let request = async_graphql::Request::new(
+            "{ Vehicle { Speed { value } Cabin { Infotainment { Media { Volume { value } } } } } }",
+        )
+        .data(permissions);
+        let response = schema.execute(request).await;
+        assert_eq!(response.errors.len(), 1);
+        assert!(response.errors[0].message.contains("permission denied"));
+        assert!(resolve_permissions(&broker, &authorization, Some("not-a-token")).is_err());
+        assert_eq!(broker.get_usage().await.authorization_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_subscription() {
+        let (broker, schema) = test_schema().await;
+        let request = async_graphql::Request::new(
+            r#"subscription {
+                signals(paths: ["Vehicle.Speed"]) { path ... on FloatSignal { value } }
+            }"#,
+        )
+        .data(permissions::ALLOW_ALL.clone());
+        let mut stream = schema.execute_stream(request);
+
+        // Current value
+        let response = stream.next().await.unwrap();
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({ "signals": { "path": "Vehicle.Speed", "value": null } })
+        );
+
+        set_value(&broker, "Vehicle.Speed", DataValue::Float(20.0)).await;
+        let response = stream.next().await.unwrap();
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({ "signals": { "path": "Vehicle.Speed", "value": 20.0 } })
+        );
+    }
+}
@@ -0,0 +1,200 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Ending response streams that exceeded their maximum lifetime or were
+//! idle for too long, see [`Deadlines`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::time::{Instant, Sleep};
+use tokio_stream::Stream;
+use tracing::debug;
+
+use crate::broker::Deadlines;
+
+/// When the last message was sent or received on a stream. Handlers of
+/// bidirectional streams touch it for every received message.
+#[derive(Clone)]
+pub struct Activity(Arc<Mutex<Instant>>);
+
+impl Activity {
+    pub fn touch(&self) {
+        *self.0.lock().unwrap() = Instant::now();
+    }
+
+    fn last(&self) -> Instant {
+        *self.0.lock().unwrap()
+    }
+}
+
+pub struct DeadlineStream<S> {
+    stream: Pin<Box<S>>,
+    lifetime: Option<(Duration, Pin<Box<Sleep>>)>,
+    idle: Option<(Duration, Pin<Box<Sleep>>)>,
+    activity: Activity,
+    done: bool,
+}
+
+impl<S> DeadlineStream<S> {
+    pub fn new(stream: S, deadlines: &Deadlines) -> Self {
+        let now = Instant::now();
+        let sleep =
+            |timeout: Duration| (timeout, Box::pin(tokio::time::sleep_until(now + timeout)));
+        DeadlineStream {
+            stream: Box::pin(stream),
+            lifetime: deadlines.max_stream_lifetime.map(sleep),
+            idle: deadlines.stream_idle_timeout.map(sleep),
+            activity: Activity(Arc::new(Mutex::new(now))),
+            done: false,
+        }
+    }
+
+    pub fn activity(&self) -> Activity {
+        self.activity.clone()
+    }
+
+    /// The wrapped stream, without the deadlines.
+    pub fn into_inner(self) -> S
+    where
+        S: Unpin,
+    {
+        *Pin::into_inner(self.stream)
+    }
+
+    fn expire<T>(&mut self, message: String) -> Poll<Option<Result<T, tonic::Status>>> {
+        debug!("Closing stream: {message}");
+        self.done = true;
+        Poll::Ready(Some(Err(tonic::Status::deadline_exceeded(message))))
+    }
+}
+
+impl<S, T> Stream for DeadlineStream<S>
+where
+    S: Stream<Item = Result<T, tonic::Status>>,
+{
+    type Item = Result<T, tonic::Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        if let Some((lifetime, sleep)) = &mut this.lifetime {
+            if sleep.as_mut().poll(cx).is_ready() {
+                let message = format!("Stream exceeded its maximum lifetime of {lifetime:?}");
+                return this.expire(message);
+            }
+        }
+        match this.stream.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                this.activity.touch();
+                return Poll::Ready(Some(item));
+            }
+            Poll::Ready(None) => {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {}
+        }
+        if let Some((timeout, sleep)) = &mut this.idle {
+            // Only the last activity counts, the timer is moved up to it
+            // when it fires
+            while sleep.as_mut().poll(cx).is_ready() {
+                let deadline = this.activity.last() + *timeout;
+                if deadline <= Instant::now() {
+                    let message = format!("Stream was idle for {timeout:?}");
+                    return this.expire(message);
+                }
+                sleep.as_mut().reset(deadline);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+
+    type Item = Result<i32, tonic::Status>;
+
+    fn channel() -> (mpsc::Sender<Item>, ReceiverStream<Item>) {
+        let (sender, receiver) = mpsc::channel(10);
+        (sender, ReceiverStream::new(receiver))
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let deadlines = Deadlines {
+            stream_idle_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let (sender, receiver) = channel();
+        let mut stream = DeadlineStream::new(receiver, &deadlines);
+        let activity = stream.activity();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            sender.send(Ok(1)).await.unwrap();
+            // Received messages count as well
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            activity.touch();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+
+        let start = Instant::now();
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        let status = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+        assert!(start.elapsed() >= Duration::from_millis(220));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_max_lifetime() {
+        let deadlines = Deadlines {
+            max_stream_lifetime: Some(Duration::from_millis(200)),
+            stream_idle_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let (sender, receiver) = channel();
+        let mut stream = DeadlineStream::new(receiver, &deadlines);
+
+        tokio::spawn(async move {
+            for value in 0.. {
+                if sender.send(Ok(value)).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        let start = Instant::now();
+        let status = loop {
+            match stream.next().await {
+                Some(Ok(_)) => {}
+                Some(Err(status)) => break status,
+                None => panic!("stream should end with an error"),
+            }
+        };
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(status.message().contains("lifetime"));
+        assert!(stream.next().await.is_none());
+    }
+}
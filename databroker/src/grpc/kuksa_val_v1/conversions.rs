@@ -17,6 +17,7 @@ use crate::broker;
 
 use std::convert::TryFrom;
 use std::time::SystemTime;
+use tracing::debug;
 
 impl From<&broker::EntryType> for proto::EntryType {
     fn from(from: &broker::EntryType) -> Self {
@@ -284,6 +285,20 @@ impl From<&broker::Field> for proto::Field {
             broker::Field::Datapoint => proto::Field::Value,
             broker::Field::ActuatorTarget => proto::Field::ActuatorTarget,
             broker::Field::MetadataUnit => proto::Field::MetadataUnit,
+            broker::Field::Metadata => proto::Field::Metadata,
+        }
+    }
+}
+
+impl From<broker::UpdateReason> for proto::UpdateReason {
+    fn from(from: broker::UpdateReason) -> Self {
+        match from {
+            broker::UpdateReason::Initial => proto::UpdateReason::Initial,
+            // Not sent over this API
+            broker::UpdateReason::Backfill => proto::UpdateReason::ValueChanged,
+            broker::UpdateReason::ValueChanged => proto::UpdateReason::ValueChanged,
+            broker::UpdateReason::TargetChanged => proto::UpdateReason::TargetChanged,
+            broker::UpdateReason::MetadataChanged => proto::UpdateReason::MetadataChanged,
         }
     }
 }
@@ -331,8 +346,27 @@ impl From<broker::EntryUpdate> for proto::DataEntry {
                 None => None,
             },
             metadata: {
+                // The data type is only set if all metadata is
+                let value_restriction = from.data_type.as_ref().and_then(|data_type| {
+                    value_restriction(
+                        data_type,
+                        &from.min.flatten(),
+                        &from.max.flatten(),
+                        &from.allowed.flatten(),
+                    )
+                });
                 let metadata = proto::Metadata {
+                    data_type: from
+                        .data_type
+                        .map(|data_type| proto::DataType::from(data_type) as i32)
+                        .unwrap_or_default(),
+                    entry_type: from
+                        .entry_type
+                        .map(|entry_type| proto::EntryType::from(&entry_type) as i32)
+                        .unwrap_or_default(),
+                    description: from.description,
                     unit: from.unit,
+                    value_restriction,
                     ..Default::default()
                 };
                 Some(metadata)
@@ -340,3 +374,155 @@ impl From<broker::EntryUpdate> for proto::DataEntry {
         }
     }
 }
+
+/// Value restriction of an entry of type `data_type` with the given min,
+/// max and allowed values, if it has any.
+pub fn value_restriction(
+    data_type: &broker::DataType,
+    min: &Option<broker::DataValue>,
+    max: &Option<broker::DataValue>,
+    allowed: &Option<broker::DataValue>,
+) -> Option<proto::ValueRestriction> {
+    match data_type {
+        broker::DataType::String | broker::DataType::StringArray => {
+            let allowed = match allowed.as_ref() {
+                Some(broker::DataValue::StringArray(vec)) => vec.clone(),
+                _ => Vec::new(),
+            };
+
+            if !allowed.is_empty() {
+                return Some(proto::ValueRestriction {
+                    r#type: Some(proto::value_restriction::Type::String(
+                        proto::ValueRestrictionString {
+                            allowed_values: allowed,
+                        },
+                    )),
+                });
+            };
+        }
+        broker::DataType::Int8
+        | broker::DataType::Int16
+        | broker::DataType::Int32
+        | broker::DataType::Int64
+        | broker::DataType::Int8Array
+        | broker::DataType::Int16Array
+        | broker::DataType::Int32Array
+        | broker::DataType::Int64Array => {
+            let min_value = match *min {
+                Some(broker::DataValue::Int32(value)) => Some(i64::from(value)),
+                Some(broker::DataValue::Int64(value)) => Some(value),
+                _ => None,
+            };
+            let max_value = match *max {
+                Some(broker::DataValue::Int32(value)) => Some(i64::from(value)),
+                Some(broker::DataValue::Int64(value)) => Some(value),
+                _ => None,
+            };
+            let allowed = match allowed.as_ref() {
+                Some(allowed) => match allowed {
+                    broker::DataValue::Int32Array(vec) => {
+                        vec.iter().cloned().map(i64::from).collect()
+                    }
+                    broker::DataValue::Int64Array(vec) => vec.to_vec(),
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
+            };
+
+            if min_value.is_some() | max_value.is_some() | !allowed.is_empty() {
+                return Some(proto::ValueRestriction {
+                    r#type: Some(proto::value_restriction::Type::Signed(
+                        proto::ValueRestrictionInt {
+                            allowed_values: allowed,
+                            min: min_value,
+                            max: max_value,
+                        },
+                    )),
+                });
+            };
+        }
+        broker::DataType::Uint8
+        | broker::DataType::Uint16
+        | broker::DataType::Uint32
+        | broker::DataType::Uint64
+        | broker::DataType::Uint8Array
+        | broker::DataType::Uint16Array
+        | broker::DataType::Uint32Array
+        | broker::DataType::Uint64Array => {
+            let min_value = match *min {
+                Some(broker::DataValue::Uint32(value)) => Some(u64::from(value)),
+                Some(broker::DataValue::Uint64(value)) => Some(value),
+                _ => None,
+            };
+            let max_value = match *max {
+                Some(broker::DataValue::Uint32(value)) => Some(u64::from(value)),
+                Some(broker::DataValue::Uint64(value)) => Some(value),
+                _ => None,
+            };
+            let allowed = match allowed.as_ref() {
+                Some(allowed) => match allowed {
+                    broker::DataValue::Uint32Array(vec) => {
+                        vec.iter().cloned().map(u64::from).collect()
+                    }
+                    broker::DataValue::Uint64Array(vec) => vec.to_vec(),
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
+            };
+
+            if min_value.is_some() | max_value.is_some() | !allowed.is_empty() {
+                return Some(proto::ValueRestriction {
+                    r#type: Some(proto::value_restriction::Type::Unsigned(
+                        proto::ValueRestrictionUint {
+                            allowed_values: allowed,
+                            min: min_value,
+                            max: max_value,
+                        },
+                    )),
+                });
+            };
+        }
+        broker::DataType::Float
+        | broker::DataType::Double
+        | broker::DataType::FloatArray
+        | broker::DataType::DoubleArray => {
+            let min_value = match *min {
+                Some(broker::DataValue::Float(value)) => Some(f64::from(value)),
+                Some(broker::DataValue::Double(value)) => Some(value),
+                _ => None,
+            };
+            let max_value = match *max {
+                Some(broker::DataValue::Float(value)) => Some(f64::from(value)),
+                Some(broker::DataValue::Double(value)) => Some(value),
+                _ => None,
+            };
+            let allowed = match allowed.as_ref() {
+                Some(allowed) => match allowed {
+                    broker::DataValue::FloatArray(vec) => {
+                        vec.iter().cloned().map(f64::from).collect()
+                    }
+                    broker::DataValue::DoubleArray(vec) => vec.to_vec(),
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
+            };
+
+            if min_value.is_some() | max_value.is_some() | !allowed.is_empty() {
+                return Some(proto::ValueRestriction {
+                    r#type: Some(proto::value_restriction::Type::FloatingPoint(
+                        proto::ValueRestrictionFloat {
+                            allowed_values: allowed,
+                            min: min_value,
+                            max: max_value,
+                        },
+                    )),
+                });
+            };
+        }
+
+        _ => {
+            debug!("Datatype {:?} not yet handled", data_type);
+        }
+    }
+    None
+}
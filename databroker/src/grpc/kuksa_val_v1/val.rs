@@ -20,6 +20,7 @@ use tokio::sync::mpsc;
 
 use databroker_proto::kuksa::val::v1 as proto;
 use databroker_proto::kuksa::val::v1::{DataEntryError, EntryUpdate};
+use prost::Message;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::Stream;
 use tokio_stream::StreamExt;
@@ -27,13 +28,14 @@ use tonic::{Response, Status, Streaming};
 use tracing::debug;
 use tracing::info;
 
+use super::conversions::value_restriction;
 use crate::broker;
 use crate::broker::ReadError;
 use crate::broker::SubscriptionError;
 use crate::broker::{AuthorizedAccess, EntryReadAccess};
 use crate::glob::Matcher;
+use crate::grpc::deadlines::DeadlineStream;
 use crate::permissions::Permissions;
-use crate::types::{DataType, DataValue};
 
 const MAX_REQUEST_PATH_LENGTH: usize = 1000;
 
@@ -92,7 +94,7 @@ impl proto::val_server::Val for broker::DataBroker {
                     continue;
                 }
 
-                match Matcher::new(&request.path) {
+                match self.path_matcher(&request.path) {
                     Ok(matcher) => {
                         let view = proto::View::try_from(request.view).map_err(|_| {
                             tonic::Status::invalid_argument(format!(
@@ -165,43 +167,42 @@ impl proto::val_server::Val for broker::DataBroker {
                         && !matcher.as_string().ends_with("/**")
                         && !(*is_match)
                     {
-                        if let Ok(branch_matcher) = Matcher::new(&(matcher.as_string() + "/**")) {
-                            broker
-                                .for_each_entry(|entry| {
-                                    let mut result_fields: HashSet<proto::Field> = HashSet::new();
-                                    let glob_path = &entry.metadata().glob_path;
-                                    if branch_matcher.is_match(glob_path) {
-                                        // Update the `is_match` to indicate a valid and used request path.
-                                        *is_match = true;
-                                        if view_fields.contains(&proto::Field::Metadata) {
-                                            result_fields.extend(view_fields.clone());
-                                        }
-                                        if view_fields.contains(&proto::Field::ActuatorTarget)
-                                            || view_fields.contains(&proto::Field::Value)
-                                        {
-                                            match entry.datapoint() {
-                                                Ok(_) => {
-                                                    // If the entry's path matches the regex and there is access permission,
-                                                    // add the result fields to the current entry.
-                                                    result_fields.extend(view_fields.clone());
-                                                }
-                                                Err(error) => {
-                                                    //Propagate the error
-                                                    *op_error = Some(error);
-                                                }
+                        let branch_matcher = matcher.branch_matcher();
+                        broker
+                            .for_each_entry(|entry| {
+                                let mut result_fields: HashSet<proto::Field> = HashSet::new();
+                                let glob_path = &entry.metadata().glob_path;
+                                if branch_matcher.is_match(glob_path) {
+                                    // Update the `is_match` to indicate a valid and used request path.
+                                    *is_match = true;
+                                    if view_fields.contains(&proto::Field::Metadata) {
+                                        result_fields.extend(view_fields.clone());
+                                    }
+                                    if view_fields.contains(&proto::Field::ActuatorTarget)
+                                        || view_fields.contains(&proto::Field::Value)
+                                    {
+                                        match entry.datapoint() {
+                                            Ok(_) => {
+                                                // If the entry's path matches the regex and there is access permission,
+                                                // add the result fields to the current entry.
+                                                result_fields.extend(view_fields.clone());
+                                            }
+                                            Err(error) => {
+                                                //Propagate the error
+                                                *op_error = Some(error);
                                             }
                                         }
                                     }
-                                    // If there are result fields, add them to the entries list.
-                                    if !result_fields.is_empty() {
-                                        let proto_entry =
-                                            proto_entry_from_entry_and_fields(entry, result_fields);
-                                        debug!("Getting datapoint: {:?}", proto_entry);
-                                        entries.push(proto_entry);
-                                    }
-                                })
-                                .await;
-                        }
+                                }
+                                // If there are result fields, add them to the entries list.
+                                if !result_fields.is_empty() {
+                                    let proto_entry =
+                                        proto_entry_from_entry_and_fields(entry, result_fields);
+                                    debug!("Getting datapoint: {:?}", proto_entry);
+                                    entries.push(proto_entry);
+                                }
+                            })
+                            .await;
                     }
                 }
             }
@@ -289,9 +290,10 @@ impl proto::val_server::Val for broker::DataBroker {
         for request in entry_updates {
             match &request.entry {
                 Some(entry) => match broker.get_id_by_path(&entry.path).await {
+                    // An invalid update doesn't keep the others from being set
                     Some(id) => match validate_entry_update(&broker, &request, id).await {
                         Ok(result) => updates.push(result),
-                        Err(e) => return Err(e),
+                        Err(status) => errors.push(invalid_data_error(&entry.path, &status)),
                     },
                     None => {
                         let message = format!("{} not found", entry.path);
@@ -334,7 +336,7 @@ impl proto::val_server::Val for broker::DataBroker {
     }
 
     type StreamedUpdateStream =
-        ReceiverStream<Result<proto::StreamedUpdateResponse, tonic::Status>>;
+        DeadlineStream<ReceiverStream<Result<proto::StreamedUpdateResponse, tonic::Status>>>;
 
     async fn streamed_update(
         &self,
@@ -348,6 +350,12 @@ impl proto::val_server::Val for broker::DataBroker {
             }
             None => return Err(tonic::Status::unauthenticated("Unauthenticated")),
         };
+        let provider_stream_guard = self
+            .authorized_access(&permissions)
+            .open_provider_stream()
+            .map_err(|_| {
+                tonic::Status::resource_exhausted("Maximum number of provider streams reached")
+            })?;
         let mut stream = request.into_inner();
 
         let mut shutdown_trigger = self.get_shutdown_trigger();
@@ -357,8 +365,11 @@ impl proto::val_server::Val for broker::DataBroker {
 
         // Create stream (to be returned); when changing buffer size, throughput should be measured
         let (sender, receiver) = mpsc::channel(10);
+        let response_stream = DeadlineStream::new(ReceiverStream::new(receiver), self.deadlines());
+        let activity = response_stream.activity();
         // Listening on stream
         tokio::spawn(async move {
+            let mut provider_stream_guard = provider_stream_guard;
             info!("Update Stream opened");
             let permissions = permissions;
             let broker = broker.authorized_access(&permissions);
@@ -367,8 +378,30 @@ impl proto::val_server::Val for broker::DataBroker {
                     message = stream.message() => {
                         match message {
                             Ok(request) => {
+                                activity.touch();
                                 match request {
                                     Some(req) => {
+                                        if provider_stream_guard.admit(req.updates.len(), req.encoded_len()).is_err() {
+                                            let error = proto::Error {
+                                                code: 429,
+                                                reason: "too_many_requests".to_string(),
+                                                message: "Provider stream rate exceeded".to_string(),
+                                            };
+                                            if let Err(err) = sender.send(
+                                                Ok(proto::StreamedUpdateResponse {
+                                                    errors: req.updates.iter().filter_map(|update| {
+                                                        update.entry.as_ref().map(|entry| proto::DataEntryError {
+                                                            path: entry.path.clone(),
+                                                            error: Some(error.clone()),
+                                                        })
+                                                    }).collect(),
+                                                    error: Some(error),
+                                                })
+                                            ).await {
+                                                debug!("Failed to send errors: {}", err);
+                                            }
+                                            continue;
+                                        }
                                         let entry_updates = req.updates;
 
                                         // Collect errors encountered
@@ -383,16 +416,8 @@ impl proto::val_server::Val for broker::DataBroker {
                                                             Ok(result) => {
                                                                 updates.push(result);
                                                             }
-                                                            Err(e) => {
-                                                                let message = format!("Data present in the request is invalid: {}", e.message());
-                                                                errors.push(proto::DataEntryError {
-                                                                    path: entry.path.clone(),
-                                                                    error: Some(proto::Error {
-                                                                        code: 400,
-                                                                        reason: "invalid_data".to_string(),
-                                                                        message,
-                                                                    })
-                                                                })
+                                                            Err(status) => {
+                                                                errors.push(invalid_data_error(&entry.path, &status));
                                                             }
                                                         }
                                                     }
@@ -469,7 +494,7 @@ impl proto::val_server::Val for broker::DataBroker {
         });
 
         // Return the stream
-        Ok(Response::new(ReceiverStream::new(receiver)))
+        Ok(Response::new(response_stream))
     }
 
     type SubscribeStream = Pin<
@@ -515,26 +540,34 @@ impl proto::val_server::Val for broker::DataBroker {
                 continue;
             }
 
-            match Matcher::new(&entry.path) {
+            match self.path_matcher(&entry.path) {
                 Ok(matcher) => {
                     let mut fields = HashSet::new();
-                    for id in &entry.fields {
-                        if let Ok(field) = proto::Field::try_from(*id) {
-                            match field {
-                                proto::Field::Value => {
-                                    fields.insert(broker::Field::Datapoint);
-                                }
-                                proto::Field::ActuatorTarget => {
-                                    fields.insert(broker::Field::ActuatorTarget);
-                                }
-                                proto::Field::MetadataUnit => {
-                                    fields.insert(broker::Field::MetadataUnit);
-                                }
-                                _ => {
-                                    // Just ignore other fields for now
-                                }
+                    let view = proto::View::try_from(entry.view).unwrap_or_default();
+                    let requested = entry
+                        .fields
+                        .iter()
+                        .filter_map(|id| proto::Field::try_from(*id).ok());
+                    for field in combine_view_and_fields(view, requested) {
+                        match field {
+                            proto::Field::Value => {
+                                fields.insert(broker::Field::Datapoint);
+                            }
+                            proto::Field::ActuatorTarget => {
+                                fields.insert(broker::Field::ActuatorTarget);
+                            }
+                            proto::Field::MetadataUnit => {
+                                fields.insert(broker::Field::MetadataUnit);
+                            }
+                            proto::Field::Metadata
+                            | proto::Field::MetadataDescription
+                            | proto::Field::MetadataValueRestriction => {
+                                fields.insert(broker::Field::Metadata);
+                            }
+                            _ => {
+                                // Just ignore other fields for now
                             }
-                        };
+                        }
                     }
                     valid_requests.insert(entry.path.clone(), (matcher, fields));
                 }
@@ -576,27 +609,26 @@ impl proto::val_server::Val for broker::DataBroker {
                     if !matcher.as_string().starts_with("**")
                         && !matcher.as_string().ends_with("/**")
                     {
-                        if let Ok(branch_matcher) = Matcher::new(&(matcher.as_string() + "/**")) {
-                            broker
-                                .for_each_entry(|entry| {
-                                    let glob_path = &entry.metadata().glob_path;
-                                    if branch_matcher.is_match(glob_path) {
-                                        requested_path_found = true;
-                                        entries
-                                            .entry(entry.metadata().id)
-                                            .and_modify(|existing_fields| {
-                                                existing_fields.extend(fields.clone());
-                                            })
-                                            .or_insert(fields.clone());
+                        let branch_matcher = matcher.branch_matcher();
+                        broker
+                            .for_each_entry(|entry| {
+                                let glob_path = &entry.metadata().glob_path;
+                                if branch_matcher.is_match(glob_path) {
+                                    requested_path_found = true;
+                                    entries
+                                        .entry(entry.metadata().id)
+                                        .and_modify(|existing_fields| {
+                                            existing_fields.extend(fields.clone());
+                                        })
+                                        .or_insert(fields.clone());
 
-                                        match entry.datapoint() {
-                                            Ok(_) => {}
-                                            Err(_) => permission_error = true,
-                                        }
+                                    match entry.datapoint() {
+                                        Ok(_) => {}
+                                        Err(_) => permission_error = true,
                                     }
-                                })
-                                .await;
-                        }
+                                }
+                            })
+                            .await;
                     }
                     if !requested_path_found {
                         let message = format!("No entries found for the provided. Path: {}", path);
@@ -610,9 +642,18 @@ impl proto::val_server::Val for broker::DataBroker {
             }
         }
 
-        match broker.subscribe(entries, None).await {
+        let initial_snapshot = if request.changes_only {
+            broker::InitialSnapshot::Skip
+        } else {
+            broker::InitialSnapshot::Send
+        };
+        match broker
+            .subscribe_with_snapshot(entries, None, initial_snapshot)
+            .await
+        {
             Ok(stream) => {
                 let stream = convert_to_proto_stream(stream);
+                let stream = DeadlineStream::new(stream, self.deadlines());
                 Ok(tonic::Response::new(Box::pin(stream)))
             }
             Err(SubscriptionError::NotFound) => {
@@ -629,6 +670,10 @@ impl proto::val_server::Val for broker::DataBroker {
                 tonic::Code::InvalidArgument,
                 "Subscription buffer_size max allowed value is 1000",
             )),
+            Err(SubscriptionError::QuotaExceeded) => Err(tonic::Status::new(
+                tonic::Code::ResourceExhausted,
+                "Maximum number of subscriptions reached",
+            )),
         }
     }
 
@@ -676,6 +721,20 @@ async fn validate_entry_update(
     Ok((id, update))
 }
 
+fn invalid_data_error(path: &str, status: &Status) -> DataEntryError {
+    DataEntryError {
+        path: path.to_owned(),
+        error: Some(proto::Error {
+            code: 400,
+            reason: "invalid_data".to_string(),
+            message: format!(
+                "Data present in the request is invalid: {}",
+                status.message()
+            ),
+        }),
+    }
+}
+
 #[cfg_attr(feature="otel", tracing::instrument(name="kuksa_val_v1_convert_to_data_entry_error", skip(path, error), fields(timestamp=chrono::Utc::now().to_string())))]
 fn convert_to_data_entry_error(path: &String, error: &broker::UpdateError) -> DataEntryError {
     match error {
@@ -727,6 +786,14 @@ fn convert_to_data_entry_error(path: &String, error: &broker::UpdateError) -> Da
                 message: String::from("given value exceeds type's boundaries"),
             }),
         },
+        broker::UpdateError::OutOfBoundsIndex => DataEntryError {
+            path: path.clone(),
+            error: Some(proto::Error {
+                code: 400,
+                reason: String::from("index out of bounds"),
+                message: String::from("given index exceeds the array's length"),
+            }),
+        },
         broker::UpdateError::PermissionDenied => DataEntryError {
             path: path.clone(),
             error: Some(proto::Error {
@@ -743,6 +810,30 @@ fn convert_to_data_entry_error(path: &String, error: &broker::UpdateError) -> Da
                 message: String::from("Unauthorized"),
             }),
         },
+        broker::UpdateError::Conflict => DataEntryError {
+            path: path.clone(),
+            error: Some(proto::Error {
+                code: 409,
+                reason: String::from("conflict"),
+                message: format!("value of {path} has changed"),
+            }),
+        },
+        broker::UpdateError::Implausible(reason) => DataEntryError {
+            path: path.clone(),
+            error: Some(proto::Error {
+                code: 400,
+                reason: String::from("implausible value"),
+                message: reason.clone(),
+            }),
+        },
+        broker::UpdateError::PolicyViolation(condition) => DataEntryError {
+            path: path.clone(),
+            error: Some(proto::Error {
+                code: 403,
+                reason: String::from("policy violation"),
+                message: format!("actuating {path} is denied while {condition}"),
+            }),
+        },
     }
 }
 
@@ -762,7 +853,14 @@ fn convert_to_proto_stream(
                     .collect(),
             });
         }
-        let response = proto::SubscribeResponse { updates };
+        let response = proto::SubscribeResponse {
+            updates,
+            reasons: item
+                .reasons
+                .into_iter()
+                .map(|reason| proto::UpdateReason::from(reason) as i32)
+                .collect(),
+        };
         Ok(response)
     })
 }
@@ -825,148 +923,13 @@ fn proto_entry_from_entry_and_fields(
         }
         if all || fields.contains(&proto::Field::MetadataValueRestriction) {
             metadata_is_set = true;
-            debug!("Datatype {:?} to be handled", entry.metadata().data_type);
-            match entry.metadata().data_type {
-                DataType::String | DataType::StringArray => {
-                    let allowed = match entry.metadata().allowed.as_ref() {
-                        Some(broker::DataValue::StringArray(vec)) => vec.clone(),
-                        _ => Vec::new(),
-                    };
-
-                    if !allowed.is_empty() {
-                        metadata.value_restriction = Some(proto::ValueRestriction {
-                            r#type: Some(proto::value_restriction::Type::String(
-                                proto::ValueRestrictionString {
-                                    allowed_values: allowed,
-                                },
-                            )),
-                        });
-                    };
-                }
-                DataType::Int8
-                | DataType::Int16
-                | DataType::Int32
-                | DataType::Int64
-                | DataType::Int8Array
-                | DataType::Int16Array
-                | DataType::Int32Array
-                | DataType::Int64Array => {
-                    let min_value = match entry.metadata().min {
-                        Some(DataValue::Int32(value)) => Some(i64::from(value)),
-                        Some(DataValue::Int64(value)) => Some(value),
-                        _ => None,
-                    };
-                    let max_value = match entry.metadata().max {
-                        Some(DataValue::Int32(value)) => Some(i64::from(value)),
-                        Some(DataValue::Int64(value)) => Some(value),
-                        _ => None,
-                    };
-                    let allowed = match entry.metadata().allowed.as_ref() {
-                        Some(allowed) => match allowed {
-                            broker::DataValue::Int32Array(vec) => {
-                                vec.iter().cloned().map(i64::from).collect()
-                            }
-                            broker::DataValue::Int64Array(vec) => vec.to_vec(),
-                            _ => Vec::new(),
-                        },
-                        _ => Vec::new(),
-                    };
-
-                    if min_value.is_some() | max_value.is_some() | !allowed.is_empty() {
-                        metadata.value_restriction = Some(proto::ValueRestriction {
-                            r#type: Some(proto::value_restriction::Type::Signed(
-                                proto::ValueRestrictionInt {
-                                    allowed_values: allowed,
-                                    min: min_value,
-                                    max: max_value,
-                                },
-                            )),
-                        });
-                    };
-                }
-                DataType::Uint8
-                | DataType::Uint16
-                | DataType::Uint32
-                | DataType::Uint64
-                | DataType::Uint8Array
-                | DataType::Uint16Array
-                | DataType::Uint32Array
-                | DataType::Uint64Array => {
-                    let min_value = match entry.metadata().min {
-                        Some(DataValue::Uint32(value)) => Some(u64::from(value)),
-                        Some(DataValue::Uint64(value)) => Some(value),
-                        _ => None,
-                    };
-                    let max_value = match entry.metadata().max {
-                        Some(DataValue::Uint32(value)) => Some(u64::from(value)),
-                        Some(DataValue::Uint64(value)) => Some(value),
-                        _ => None,
-                    };
-                    let allowed = match entry.metadata().allowed.as_ref() {
-                        Some(allowed) => match allowed {
-                            broker::DataValue::Uint32Array(vec) => {
-                                vec.iter().cloned().map(u64::from).collect()
-                            }
-                            broker::DataValue::Uint64Array(vec) => vec.to_vec(),
-                            _ => Vec::new(),
-                        },
-                        _ => Vec::new(),
-                    };
-
-                    if min_value.is_some() | max_value.is_some() | !allowed.is_empty() {
-                        metadata.value_restriction = Some(proto::ValueRestriction {
-                            r#type: Some(proto::value_restriction::Type::Unsigned(
-                                proto::ValueRestrictionUint {
-                                    allowed_values: allowed,
-                                    min: min_value,
-                                    max: max_value,
-                                },
-                            )),
-                        });
-                    };
-                }
-                DataType::Float
-                | DataType::Double
-                | DataType::FloatArray
-                | DataType::DoubleArray => {
-                    let min_value = match entry.metadata().min {
-                        Some(DataValue::Float(value)) => Some(f64::from(value)),
-                        Some(DataValue::Double(value)) => Some(value),
-                        _ => None,
-                    };
-                    let max_value = match entry.metadata().max {
-                        Some(DataValue::Float(value)) => Some(f64::from(value)),
-                        Some(DataValue::Double(value)) => Some(value),
-                        _ => None,
-                    };
-                    let allowed = match entry.metadata().allowed.as_ref() {
-                        Some(allowed) => match allowed {
-                            broker::DataValue::FloatArray(vec) => {
-                                vec.iter().cloned().map(f64::from).collect()
-                            }
-                            broker::DataValue::DoubleArray(vec) => vec.to_vec(),
-                            _ => Vec::new(),
-                        },
-                        _ => Vec::new(),
-                    };
-
-                    if min_value.is_some() | max_value.is_some() | !allowed.is_empty() {
-                        metadata.value_restriction = Some(proto::ValueRestriction {
-                            r#type: Some(proto::value_restriction::Type::FloatingPoint(
-                                proto::ValueRestrictionFloat {
-                                    allowed_values: allowed,
-                                    min: min_value,
-                                    max: max_value,
-                                },
-                            )),
-                        });
-                    };
-                }
-
-                _ => {
-                    debug!("Datatype {:?} not yet handled", entry.metadata().data_type);
-                }
-            }
+            let entry_metadata = entry.metadata();
+            metadata.value_restriction = value_restriction(
+                &entry_metadata.data_type,
+                &entry_metadata.min,
+                &entry_metadata.max,
+                &entry_metadata.allowed,
+            );
         }
         if all || fields.contains(&proto::Field::MetadataActuator) {
             metadata_is_set = true;
@@ -1178,6 +1141,72 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_set_with_invalid_entry_sets_the_others() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let mut ids = Vec::new();
+        for path in ["test.sensor", "test.other"] {
+            ids.push(
+                authorized_access
+                    .add_entry(
+                        path.to_owned(),
+                        broker::DataType::Int32,
+                        broker::ChangeType::OnChange,
+                        broker::EntryType::Sensor,
+                        "Test sensor".to_owned(),
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap(),
+            );
+        }
+        let datapoint = Some(proto::Datapoint {
+            timestamp: None,
+            value: Some(proto::datapoint::Value::Int32(7)),
+        });
+
+        // Sensors have no target value
+        let mut req = tonic::Request::new(proto::SetRequest {
+            updates: vec![
+                proto::EntryUpdate {
+                    fields: vec![proto::Field::ActuatorTarget as i32],
+                    entry: Some(proto::DataEntry {
+                        path: "test.sensor".to_owned(),
+                        value: None,
+                        metadata: None,
+                        actuator_target: datapoint.clone(),
+                    }),
+                },
+                proto::EntryUpdate {
+                    fields: vec![proto::Field::Value as i32],
+                    entry: Some(proto::DataEntry {
+                        path: "test.other".to_owned(),
+                        value: datapoint,
+                        metadata: None,
+                        actuator_target: None,
+                    }),
+                },
+            ],
+        });
+        req.extensions_mut().insert(permissions::ALLOW_ALL.clone());
+
+        let response = proto::val_server::Val::set(&broker, req)
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].path, "test.sensor");
+        assert_eq!(response.errors[0].error.as_ref().unwrap().code, 400);
+        assert_eq!(
+            authorized_access.get_datapoint(ids[1]).await.unwrap().value,
+            broker::DataValue::Int32(7)
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_streamed_update_with_valid_datapoint() {
         let broker = DataBroker::default();
@@ -1221,7 +1250,7 @@ mod tests {
             Ok(response) => {
                 tokio::spawn(async move {
                     let stream = response.into_inner();
-                    let mut receiver = stream.into_inner();
+                    let mut receiver = stream.into_inner().into_inner();
                     let option = receiver.recv();
                     assert!(option.await.is_none()) // no errors should occur and no ack is delivered
                 });
@@ -1259,7 +1288,7 @@ mod tests {
             Ok(response) => {
                 tokio::spawn(async move {
                     let stream = response.into_inner();
-                    let mut receiver = stream.into_inner();
+                    let mut receiver = stream.into_inner().into_inner();
                     let option = receiver.recv().await;
                     assert!(option.is_some());
                     let result = option.unwrap();
@@ -1425,4 +1454,83 @@ mod tests {
             Err(_status) => panic!("failed to execute get request"),
         }
     }
+
+    #[tokio::test]
+    async fn test_subscribe_metadata_view() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let id = authorized_access
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                broker::DataType::Int32,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let mut request = tonic::Request::new(proto::SubscribeRequest {
+            entries: vec![proto::SubscribeEntry {
+                path: "test.datapoint1".to_owned(),
+                view: proto::View::Metadata as i32,
+                fields: vec![],
+            }],
+            changes_only: false,
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let mut stream = proto::val_server::Val::subscribe(&broker, request)
+            .await
+            .expect("subscribe should succeed")
+            .into_inner();
+
+        // Initial notification
+        let response = stream.next().await.unwrap().unwrap();
+        let metadata = response.updates[0]
+            .entry
+            .as_ref()
+            .and_then(|entry| entry.metadata.clone())
+            .unwrap();
+        assert_eq!(metadata.description, Some("Test datapoint 1".to_owned()));
+
+        authorized_access
+            .update_entries([(
+                id,
+                broker::EntryUpdate {
+                    max: Some(Some(broker::DataValue::Int32(10))),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .expect("changing metadata should succeed");
+
+        let response = stream.next().await.unwrap().unwrap();
+        assert_eq!(response.updates.len(), 1);
+        let update = &response.updates[0];
+        assert_eq!(update.fields, vec![proto::Field::Metadata as i32]);
+        let entry = update.entry.as_ref().unwrap();
+        assert_eq!(entry.path, "test.datapoint1");
+        assert!(entry.value.is_none());
+        let metadata = entry.metadata.as_ref().unwrap();
+        assert_eq!(metadata.data_type, proto::DataType::Int32 as i32);
+        assert_eq!(
+            metadata.value_restriction,
+            Some(proto::ValueRestriction {
+                r#type: Some(proto::value_restriction::Type::Signed(
+                    proto::ValueRestrictionInt {
+                        allowed_values: vec![],
+                        min: None,
+                        max: Some(10),
+                    }
+                )),
+            })
+        );
+    }
 }
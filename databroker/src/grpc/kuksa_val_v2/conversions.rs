@@ -10,6 +10,7 @@
 // *
 // * SPDX-License-Identifier: Apache-2.0
 // ********************************************************************************/
+use crate::actuations::ActuationStatus;
 use crate::broker;
 use crate::types::DataValue;
 use databroker_proto::kuksa::val::v2 as proto;
@@ -18,7 +19,7 @@ use kuksa::proto::v2::{
     Uint64Array,
 };
 
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tracing::debug;
 
 impl From<&proto::Datapoint> for broker::Datapoint {
@@ -297,6 +298,10 @@ impl From<&broker::UpdateError> for proto::Error {
                 code: proto::ErrorCode::InvalidArgument.into(),
                 message: "Out of Bounds Type".to_string(),
             },
+            broker::UpdateError::OutOfBoundsIndex => proto::Error {
+                code: proto::ErrorCode::InvalidArgument.into(),
+                message: "Out of Bounds Index".to_string(),
+            },
             broker::UpdateError::UnsupportedType => proto::Error {
                 code: proto::ErrorCode::InvalidArgument.into(),
                 message: "Unsupported Type".to_string(),
@@ -309,6 +314,18 @@ impl From<&broker::UpdateError> for proto::Error {
                 code: proto::ErrorCode::PermissionDenied.into(),
                 message: "Permission Expired".to_string(),
             },
+            broker::UpdateError::Conflict => proto::Error {
+                code: proto::ErrorCode::Conflict.into(),
+                message: "Conflict".to_string(),
+            },
+            broker::UpdateError::Implausible(reason) => proto::Error {
+                code: proto::ErrorCode::InvalidArgument.into(),
+                message: format!("Implausible value: {reason}"),
+            },
+            broker::UpdateError::PolicyViolation(condition) => proto::Error {
+                code: proto::ErrorCode::PolicyViolation.into(),
+                message: format!("Denied by policy while {condition}"),
+            },
         }
     }
 }
@@ -344,6 +361,40 @@ impl From<broker::DataType> for proto::DataType {
     }
 }
 
+impl TryFrom<proto::DataType> for broker::DataType {
+    type Error = ();
+
+    fn try_from(from: proto::DataType) -> Result<Self, Self::Error> {
+        match from {
+            proto::DataType::String => Ok(broker::DataType::String),
+            proto::DataType::Boolean => Ok(broker::DataType::Bool),
+            proto::DataType::Int8 => Ok(broker::DataType::Int8),
+            proto::DataType::Int16 => Ok(broker::DataType::Int16),
+            proto::DataType::Int32 => Ok(broker::DataType::Int32),
+            proto::DataType::Int64 => Ok(broker::DataType::Int64),
+            proto::DataType::Uint8 => Ok(broker::DataType::Uint8),
+            proto::DataType::Uint16 => Ok(broker::DataType::Uint16),
+            proto::DataType::Uint32 => Ok(broker::DataType::Uint32),
+            proto::DataType::Uint64 => Ok(broker::DataType::Uint64),
+            proto::DataType::Float => Ok(broker::DataType::Float),
+            proto::DataType::Double => Ok(broker::DataType::Double),
+            proto::DataType::StringArray => Ok(broker::DataType::StringArray),
+            proto::DataType::BooleanArray => Ok(broker::DataType::BoolArray),
+            proto::DataType::Int8Array => Ok(broker::DataType::Int8Array),
+            proto::DataType::Int16Array => Ok(broker::DataType::Int16Array),
+            proto::DataType::Int32Array => Ok(broker::DataType::Int32Array),
+            proto::DataType::Int64Array => Ok(broker::DataType::Int64Array),
+            proto::DataType::Uint8Array => Ok(broker::DataType::Uint8Array),
+            proto::DataType::Uint16Array => Ok(broker::DataType::Uint16Array),
+            proto::DataType::Uint32Array => Ok(broker::DataType::Uint32Array),
+            proto::DataType::Uint64Array => Ok(broker::DataType::Uint64Array),
+            proto::DataType::FloatArray => Ok(broker::DataType::FloatArray),
+            proto::DataType::DoubleArray => Ok(broker::DataType::DoubleArray),
+            _ => Err(()),
+        }
+    }
+}
+
 impl From<broker::EntryType> for proto::EntryType {
     fn from(from: broker::EntryType) -> Self {
         match from {
@@ -354,6 +405,87 @@ impl From<broker::EntryType> for proto::EntryType {
     }
 }
 
+impl TryFrom<proto::EntryType> for broker::EntryType {
+    type Error = ();
+
+    fn try_from(from: proto::EntryType) -> Result<Self, Self::Error> {
+        match from {
+            proto::EntryType::Sensor => Ok(broker::EntryType::Sensor),
+            proto::EntryType::Attribute => Ok(broker::EntryType::Attribute),
+            proto::EntryType::Actuator => Ok(broker::EntryType::Actuator),
+            proto::EntryType::Unspecified => Err(()),
+        }
+    }
+}
+
+impl From<broker::UpdateReason> for proto::UpdateReason {
+    fn from(from: broker::UpdateReason) -> Self {
+        match from {
+            broker::UpdateReason::Initial => proto::UpdateReason::Initial,
+            broker::UpdateReason::Backfill => proto::UpdateReason::Backfill,
+            broker::UpdateReason::ValueChanged => proto::UpdateReason::ValueChanged,
+            broker::UpdateReason::TargetChanged => proto::UpdateReason::TargetChanged,
+            broker::UpdateReason::MetadataChanged => proto::UpdateReason::MetadataChanged,
+        }
+    }
+}
+
+impl From<ActuationStatus> for proto::ActuationStatus {
+    fn from(from: ActuationStatus) -> Self {
+        match from {
+            ActuationStatus::Pending => proto::ActuationStatus::Pending,
+            ActuationStatus::Delivered => proto::ActuationStatus::Delivered,
+            ActuationStatus::Executed => proto::ActuationStatus::Executed,
+            ActuationStatus::Failed => proto::ActuationStatus::Failed,
+            ActuationStatus::Expired => proto::ActuationStatus::Expired,
+        }
+    }
+}
+
+impl From<broker::Capabilities> for proto::ServerCapabilities {
+    fn from(from: broker::Capabilities) -> Self {
+        proto::ServerCapabilities {
+            apis: from.apis,
+            wildcards: from.wildcards,
+            history: from.history,
+            max_message_size: 0,
+            vss_version: from.vss_version.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<proto::SubscriptionPriority> for broker::SubscriptionPriority {
+    fn from(from: proto::SubscriptionPriority) -> Self {
+        match from {
+            proto::SubscriptionPriority::Unspecified | proto::SubscriptionPriority::Telemetry => {
+                broker::SubscriptionPriority::Telemetry
+            }
+            proto::SubscriptionPriority::Control => broker::SubscriptionPriority::Control,
+            proto::SubscriptionPriority::Safety => broker::SubscriptionPriority::Safety,
+        }
+    }
+}
+
+impl From<&proto::Filter> for broker::SubscriptionFilter {
+    fn from(from: &proto::Filter) -> Self {
+        broker::SubscriptionFilter {
+            min_interval: from
+                .min_sample_interval
+                .as_ref()
+                .filter(|interval| interval.interval_ms > 0)
+                .map(|interval| Duration::from_millis(interval.interval_ms.into())),
+            min_change: (from.min_change > 0.0).then_some(from.min_change),
+            duration: (from.duration_ms > 0)
+                .then(|| Duration::from_millis(from.duration_ms.into())),
+            max_bytes_per_sec: (from.max_bytes_per_sec > 0).then_some(from.max_bytes_per_sec),
+            // Part of the subscribe request, not of the filter
+            backfill_since: None,
+            priority: broker::SubscriptionPriority::default(),
+            snapshots: false,
+        }
+    }
+}
+
 impl broker::UpdateError {
     pub fn to_status_with_code(&self, id: &i32) -> tonic::Status {
         match self {
@@ -377,6 +509,10 @@ impl broker::UpdateError {
                 tonic::Code::InvalidArgument,
                 format!("Value out of type bounds (id: {})", id),
             ),
+            broker::UpdateError::OutOfBoundsIndex => tonic::Status::new(
+                tonic::Code::InvalidArgument,
+                format!("Array index out of bounds (id: {})", id),
+            ),
             broker::UpdateError::UnsupportedType => tonic::Status::new(
                 tonic::Code::InvalidArgument,
                 format!("Unsupported type (id: {})", id),
@@ -389,6 +525,18 @@ impl broker::UpdateError {
                 tonic::Code::Unauthenticated,
                 format!("Permission expired (id: {})", id),
             ),
+            broker::UpdateError::Conflict => tonic::Status::new(
+                tonic::Code::Aborted,
+                format!("Value has changed (id: {})", id),
+            ),
+            broker::UpdateError::Implausible(reason) => tonic::Status::new(
+                tonic::Code::InvalidArgument,
+                format!("Implausible value (id: {}): {}", id, reason),
+            ),
+            broker::UpdateError::PolicyViolation(condition) => tonic::Status::new(
+                tonic::Code::FailedPrecondition,
+                format!("Denied by policy (id: {}) while {}", id, condition),
+            ),
         }
     }
 }
@@ -535,6 +683,21 @@ impl broker::ActuationError {
             broker::ActuationError::ProviderNotAvailable => tonic::Status::unavailable(message),
             broker::ActuationError::ProviderAlreadyExists => tonic::Status::already_exists(message),
             broker::ActuationError::TransmissionFailure => tonic::Status::data_loss(message),
+            broker::ActuationError::PolicyViolation => tonic::Status::failed_precondition(message),
+        }
+    }
+
+    /// The error reported by another broker, e.g. a federated zone broker.
+    pub fn from_tonic_status(status: &tonic::Status) -> Self {
+        match status.code() {
+            tonic::Code::NotFound => broker::ActuationError::NotFound,
+            tonic::Code::InvalidArgument => broker::ActuationError::WrongType,
+            tonic::Code::PermissionDenied => broker::ActuationError::PermissionDenied,
+            tonic::Code::Unauthenticated => broker::ActuationError::PermissionExpired,
+            tonic::Code::Unavailable => broker::ActuationError::ProviderNotAvailable,
+            tonic::Code::AlreadyExists => broker::ActuationError::ProviderAlreadyExists,
+            tonic::Code::FailedPrecondition => broker::ActuationError::PolicyViolation,
+            _ => broker::ActuationError::TransmissionFailure,
         }
     }
 }
@@ -0,0 +1,168 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use databroker_proto::kuksa::val::v2 as proto;
+
+use crate::broker;
+use crate::types::DataValue;
+
+const DEFAULT_FULL_VALUE_INTERVAL: u32 = 100;
+
+/// Encodes the array values of a subscription stream as the elements
+/// changed since the value previously sent for the same signal.
+pub struct DeltaEncoder<K> {
+    full_value_interval: u32,
+    // The value last sent per signal and the number of deltas since it
+    // was last sent whole
+    sent: HashMap<K, (DataValue, u32)>,
+}
+
+impl<K: Hash + Eq + Clone> DeltaEncoder<K> {
+    pub fn new(config: &proto::DeltaEncoding) -> Self {
+        Self {
+            full_value_interval: match config.full_value_interval {
+                0 => DEFAULT_FULL_VALUE_INTERVAL,
+                interval => interval,
+            },
+            sent: HashMap::new(),
+        }
+    }
+
+    /// The delta to send instead of `datapoint`, if it is smaller than the
+    /// whole value.
+    pub fn encode(&mut self, key: &K, datapoint: &broker::Datapoint) -> Option<proto::ArrayDelta> {
+        if let Some((previous, deltas)) = self.sent.get_mut(key) {
+            if *deltas + 1 < self.full_value_interval {
+                if let Some((indices, values)) = changed_elements(previous, &datapoint.value) {
+                    *previous = datapoint.value.clone();
+                    *deltas += 1;
+                    return Some(proto::ArrayDelta {
+                        timestamp: Some(datapoint.ts.into()),
+                        indices,
+                        values: Some(values.into()),
+                    });
+                }
+            }
+        }
+        self.sent.insert(key.clone(), (datapoint.value.clone(), 0));
+        None
+    }
+}
+
+/// The indices and values of the elements of `next` that differ from
+/// `previous`, if both are arrays of the same type and length and less
+/// than half of the elements changed.
+fn changed_elements(previous: &DataValue, next: &DataValue) -> Option<(Vec<u32>, DataValue)> {
+    match (previous, next) {
+        (DataValue::BoolArray(previous), DataValue::BoolArray(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::BoolArray(values)))
+        }
+        (DataValue::StringArray(previous), DataValue::StringArray(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::StringArray(values)))
+        }
+        (DataValue::Int32Array(previous), DataValue::Int32Array(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::Int32Array(values)))
+        }
+        (DataValue::Int64Array(previous), DataValue::Int64Array(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::Int64Array(values)))
+        }
+        (DataValue::Uint32Array(previous), DataValue::Uint32Array(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::Uint32Array(values)))
+        }
+        (DataValue::Uint64Array(previous), DataValue::Uint64Array(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::Uint64Array(values)))
+        }
+        (DataValue::FloatArray(previous), DataValue::FloatArray(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::FloatArray(values)))
+        }
+        (DataValue::DoubleArray(previous), DataValue::DoubleArray(next)) => {
+            diff(previous, next).map(|(indices, values)| (indices, DataValue::DoubleArray(values)))
+        }
+        _ => None,
+    }
+}
+
+fn diff<T: PartialEq + Clone>(previous: &[T], next: &[T]) -> Option<(Vec<u32>, Vec<T>)> {
+    if previous.len() != next.len() {
+        return None;
+    }
+    let len = next.len();
+    let mut indices = Vec::new();
+    let mut values = Vec::new();
+    for (index, (previous, next)) in previous.iter().zip(next).enumerate() {
+        if previous != next {
+            if (indices.len() + 1) * 2 >= len {
+                return None;
+            }
+            indices.push(index as u32);
+            values.push(next.clone());
+        }
+    }
+    Some((indices, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+
+    fn datapoint(values: Vec<i32>) -> broker::Datapoint {
+        broker::Datapoint {
+            ts: SystemTime::now(),
+            source_ts: None,
+            value: DataValue::Int32Array(values),
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut encoder = DeltaEncoder::new(&proto::DeltaEncoding {
+            full_value_interval: 3,
+        });
+        let key = "Vehicle.Seats".to_owned();
+
+        // The first value is sent whole
+        assert!(encoder.encode(&key, &datapoint(vec![1, 2, 3, 4])).is_none());
+
+        let delta = encoder.encode(&key, &datapoint(vec![1, 5, 3, 4])).unwrap();
+        assert_eq!(delta.indices, vec![1]);
+        assert_eq!(
+            delta.values,
+            Some(proto::Value::from(DataValue::Int32Array(vec![5])))
+        );
+
+        // Relative to the value sent last
+        let delta = encoder.encode(&key, &datapoint(vec![1, 5, 3, 6])).unwrap();
+        assert_eq!(delta.indices, vec![3]);
+
+        // Periodically sent whole
+        assert!(encoder.encode(&key, &datapoint(vec![1, 5, 3, 7])).is_none());
+        assert!(encoder.encode(&key, &datapoint(vec![1, 5, 3, 8])).is_some());
+
+        // Not when half of the elements or the length changed
+        assert!(encoder.encode(&key, &datapoint(vec![0, 0, 3, 8])).is_none());
+        assert!(encoder.encode(&key, &datapoint(vec![0, 0, 3])).is_none());
+        // Nor for other values
+        let speed = broker::Datapoint {
+            ts: SystemTime::now(),
+            source_ts: None,
+            value: DataValue::Float(50.0),
+        };
+        assert!(encoder.encode(&key, &speed).is_none());
+        assert!(encoder.encode(&key, &speed).is_none());
+    }
+}
@@ -12,4 +12,5 @@
 ********************************************************************************/
 
 mod conversions;
+mod delta;
 mod val;
@@ -11,22 +11,32 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
-use std::{collections::HashMap, pin::Pin};
+use std::{collections::HashMap, pin::Pin, time::Duration};
 
 use crate::{
+    actuations::ActuationStatus,
+    authorization::jwt::ExchangeError,
     broker::{
-        self, ActuationChange, ActuationProvider, AuthorizedAccess, ReadError, SubscriptionError,
+        self, ActuationChange, ActuationProvider, AuthorizedAccess, ProviderStreamGuard, ReadError,
+        SubscriptionError,
     },
-    glob::Matcher,
-    permissions::Permissions,
+    dump,
+    enrollment::{Challenge, EnrollmentError, ProviderEnrollment, ProviderIdentity},
+    events::Event,
+    grpc::{
+        deadlines::DeadlineStream, kuksa_val_v2::delta::DeltaEncoder, server::MAX_MESSAGE_SIZE,
+    },
+    log_filter::LogFilterError,
+    permissions::{PermissionError, Permissions},
     types::DataValue,
 };
 
 use databroker_proto::kuksa::val::v2::{
     self as proto,
     open_provider_stream_request::Action::{
-        BatchActuateStreamResponse, GetProviderValueResponse, ProvideActuationRequest,
-        ProvideSignalRequest, ProviderErrorIndication, PublishValuesRequest, UpdateFilterResponse,
+        BatchActuateStreamResponse, EnrollProviderProof, EnrollProviderRequest,
+        GetProviderValueResponse, ProvideActuationRequest, ProvideSignalRequest,
+        ProviderErrorIndication, PublishValuesRequest, UpdateFilterResponse,
     },
     open_provider_stream_response, OpenProviderStreamResponse, PublishValuesResponse,
 };
@@ -35,12 +45,22 @@ use kuksa::proto::v2::{
     signal_id, ActuateRequest, ActuateResponse, BatchActuateStreamRequest, ErrorCode,
     ListMetadataResponse, ProvideActuationResponse,
 };
+use prost::Message;
 use std::collections::HashSet;
 use tokio::{select, sync::mpsc};
-use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
-use tracing::debug;
+use tokio_stream::{
+    wrappers::{BroadcastStream, ReceiverStream},
+    Stream, StreamExt,
+};
+use tracing::{debug, info, warn};
 
 const MAX_REQUEST_PATH_LENGTH: usize = 1000;
+// Size of the parts a state dump is sent in, well below the max message size
+const DUMP_CHUNK_SIZE: usize = 64 * 1024;
+// How long a changed log filter stays in effect unless the client says
+const DEFAULT_LOG_FILTER_TIMEOUT: Duration = Duration::from_secs(600);
+// Lifetime of an exchanged token unless the client says
+const DEFAULT_EXCHANGED_TOKEN_LIFETIME: Duration = Duration::from_secs(300);
 
 pub struct Provider {
     sender: mpsc::Sender<Result<OpenProviderStreamResponse, tonic::Status>>,
@@ -53,7 +73,9 @@ impl ActuationProvider for Provider {
         actuation_changes: Vec<broker::ActuationChange>,
     ) -> Result<(), (broker::ActuationError, String)> {
         let mut actuation_requests: Vec<ActuateRequest> = vec![];
+        let mut actuation_ids = Vec::with_capacity(actuation_changes.len());
         for actuation_change in actuation_changes {
+            actuation_ids.push(actuation_change.actuation_id);
             let data_value = actuation_change.data_value;
             actuation_requests.push(ActuateRequest {
                 signal_id: Some(proto::SignalId {
@@ -67,6 +89,7 @@ impl ActuationProvider for Provider {
             open_provider_stream_response::Action::BatchActuateStreamRequest(
                 BatchActuateStreamRequest {
                     actuate_requests: actuation_requests,
+                    actuation_ids,
                 },
             );
 
@@ -248,12 +271,42 @@ impl proto::val_server::Val for broker::DataBroker {
             );
         }
 
+        let initial_snapshot = if request.changes_only {
+            broker::InitialSnapshot::Skip
+        } else {
+            broker::InitialSnapshot::Send
+        };
+        let mut filter = request
+            .filter
+            .as_ref()
+            .map(broker::SubscriptionFilter::from)
+            .unwrap_or_default();
+        if let Some(since) = request.backfill_since {
+            match since.try_into() {
+                Ok(since) => filter.backfill_since = Some(since),
+                Err(_) => {
+                    return Err(tonic::Status::invalid_argument(
+                        "Invalid backfill_since timestamp",
+                    ))
+                }
+            }
+        }
+        // Unknown classes count as telemetry
+        filter.priority = proto::SubscriptionPriority::try_from(request.priority)
+            .unwrap_or_default()
+            .into();
         match broker
-            .subscribe(valid_requests, Some(request.buffer_size as usize))
+            .subscribe_with_filter(
+                valid_requests,
+                Some(request.buffer_size as usize),
+                initial_snapshot,
+                filter,
+            )
             .await
         {
             Ok(stream) => {
-                let stream = convert_to_proto_stream(stream, size);
+                let stream = convert_to_proto_stream(stream, size, request.delta_encoding);
+                let stream = DeadlineStream::new(stream, self.deadlines());
                 Ok(tonic::Response::new(Box::pin(stream)))
             }
             Err(SubscriptionError::NotFound) => Err(tonic::Status::not_found("Path not found")),
@@ -265,6 +318,9 @@ impl proto::val_server::Val for broker::DataBroker {
                 tonic::Code::InvalidArgument,
                 "Subscription buffer_size max allowed value is 1000",
             )),
+            Err(SubscriptionError::QuotaExceeded) => Err(tonic::Status::resource_exhausted(
+                "Maximum number of subscriptions reached",
+            )),
         }
     }
 
@@ -321,12 +377,42 @@ impl proto::val_server::Val for broker::DataBroker {
             );
         }
 
+        let initial_snapshot = if request.changes_only {
+            broker::InitialSnapshot::Skip
+        } else {
+            broker::InitialSnapshot::Send
+        };
+        let mut filter = request
+            .filter
+            .as_ref()
+            .map(broker::SubscriptionFilter::from)
+            .unwrap_or_default();
+        if let Some(since) = request.backfill_since {
+            match since.try_into() {
+                Ok(since) => filter.backfill_since = Some(since),
+                Err(_) => {
+                    return Err(tonic::Status::invalid_argument(
+                        "Invalid backfill_since timestamp",
+                    ))
+                }
+            }
+        }
+        // Unknown classes count as telemetry
+        filter.priority = proto::SubscriptionPriority::try_from(request.priority)
+            .unwrap_or_default()
+            .into();
         match broker
-            .subscribe(valid_requests, Some(request.buffer_size as usize))
+            .subscribe_with_filter(
+                valid_requests,
+                Some(request.buffer_size as usize),
+                initial_snapshot,
+                filter,
+            )
             .await
         {
             Ok(stream) => {
-                let stream = convert_to_proto_stream_id(stream, size);
+                let stream = convert_to_proto_stream_id(stream, size, request.delta_encoding);
+                let stream = DeadlineStream::new(stream, self.deadlines());
                 Ok(tonic::Response::new(Box::pin(stream)))
             }
             Err(SubscriptionError::NotFound) => {
@@ -343,6 +429,167 @@ impl proto::val_server::Val for broker::DataBroker {
                 tonic::Code::InvalidArgument,
                 "Subscription buffer_size max allowed value is 1000",
             )),
+            Err(SubscriptionError::QuotaExceeded) => Err(tonic::Status::resource_exhausted(
+                "Maximum number of subscriptions reached",
+            )),
+        }
+    }
+
+    // Returns (GRPC error code):
+    //   INVALID_ARGUMENT if the name is empty.
+    //   NOT_FOUND if any of the signals are non-existant.
+    //   PERMISSION_DENIED if the client lacks the `admin` scope.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn define_signal_group(
+        &self,
+        request: tonic::Request<proto::DefineSignalGroupRequest>,
+    ) -> Result<tonic::Response<proto::DefineSignalGroupResponse>, tonic::Status> {
+        debug!(?request);
+        check_admin_permission(&request)?;
+        let permissions = request
+            .extensions()
+            .get::<Permissions>()
+            .ok_or(tonic::Status::unauthenticated("Unauthenticated"))?
+            .clone();
+
+        let request = request.into_inner();
+        if request.name.is_empty() {
+            return Err(tonic::Status::invalid_argument("No group name provided"));
+        }
+        match self
+            .authorized_access(&permissions)
+            .define_signal_group(&request.name, request.signal_paths)
+            .await
+        {
+            Ok(()) => Ok(tonic::Response::new(proto::DefineSignalGroupResponse {})),
+            Err(ReadError::NotFound) => Err(tonic::Status::not_found("Path not found")),
+            Err(ReadError::PermissionDenied) => {
+                Err(tonic::Status::permission_denied("Permission denied"))
+            }
+            Err(ReadError::PermissionExpired) => {
+                Err(tonic::Status::unauthenticated("Permission expired"))
+            }
+        }
+    }
+
+    // Returns (GRPC error code):
+    //   NOT_FOUND if the group or any of its signals are non-existant.
+    //   PERMISSION_DENIED if access is denied for any of the signals.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn get_signal_group(
+        &self,
+        request: tonic::Request<proto::GetSignalGroupRequest>,
+    ) -> Result<tonic::Response<proto::GetSignalGroupResponse>, tonic::Status> {
+        debug!(?request);
+        let permissions = request
+            .extensions()
+            .get::<Permissions>()
+            .ok_or(tonic::Status::unauthenticated("Unauthenticated"))?
+            .clone();
+
+        let name = request.into_inner().name;
+        match self
+            .authorized_access(&permissions)
+            .get_signal_group(&name)
+            .await
+        {
+            Ok(values) => Ok(tonic::Response::new(proto::GetSignalGroupResponse {
+                entries: values
+                    .into_iter()
+                    .filter_map(|(path, datapoint)| {
+                        Option::<proto::Datapoint>::from(datapoint)
+                            .map(|datapoint| (path, datapoint))
+                    })
+                    .collect(),
+            })),
+            Err(ReadError::NotFound) => Err(tonic::Status::not_found(format!(
+                "Group '{name}' or any of its signals not found"
+            ))),
+            Err(ReadError::PermissionDenied) => Err(tonic::Status::permission_denied(format!(
+                "Permission denied for a signal of group '{name}'"
+            ))),
+            Err(ReadError::PermissionExpired) => {
+                Err(tonic::Status::unauthenticated("Permission expired"))
+            }
+        }
+    }
+
+    type SubscribeSignalGroupStream = Pin<
+        Box<
+            dyn Stream<Item = Result<proto::SubscribeResponse, tonic::Status>>
+                + Send
+                + Sync
+                + 'static,
+        >,
+    >;
+    // Returns (GRPC error code):
+    //   NOT_FOUND if the group or any of its signals are non-existant.
+    //   PERMISSION_DENIED if access is denied for any of the signals.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //   INVALID_ARGUMENT if buffer_size exceeds the maximum permitted
+    //
+    async fn subscribe_signal_group(
+        &self,
+        request: tonic::Request<proto::SubscribeSignalGroupRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeSignalGroupStream>, tonic::Status> {
+        debug!(?request);
+        let permissions = request
+            .extensions()
+            .get::<Permissions>()
+            .ok_or(tonic::Status::unauthenticated("Unauthenticated"))?
+            .clone();
+
+        let request = request.into_inner();
+        let broker = self.authorized_access(&permissions);
+        let Some(paths) = self.signal_group(&request.name) else {
+            return Err(tonic::Status::not_found(format!(
+                "Group '{}' not found",
+                request.name
+            )));
+        };
+        let size = paths.len();
+        let mut valid_requests: HashMap<i32, HashSet<broker::Field>> = HashMap::with_capacity(size);
+        for path in paths {
+            let id = get_signal(
+                Some(proto::SignalId {
+                    signal: Some(proto::signal_id::Signal::Path(path)),
+                }),
+                &broker,
+            )
+            .await?;
+            valid_requests.insert(id, HashSet::from([broker::Field::Datapoint]));
+        }
+
+        match broker
+            .subscribe_with_filter(
+                valid_requests,
+                Some(request.buffer_size as usize),
+                broker::InitialSnapshot::Send,
+                broker::SubscriptionFilter {
+                    snapshots: true,
+                    ..Default::default()
+                },
+            )
+            .await
+        {
+            Ok(stream) => {
+                let stream = convert_to_proto_stream(stream, size, None);
+                let stream = DeadlineStream::new(stream, self.deadlines());
+                Ok(tonic::Response::new(Box::pin(stream)))
+            }
+            Err(SubscriptionError::NotFound) => Err(tonic::Status::not_found("Path not found")),
+            Err(SubscriptionError::InvalidInput) => {
+                Err(tonic::Status::invalid_argument("Group has no signals"))
+            }
+            Err(SubscriptionError::InternalError) => Err(tonic::Status::internal("Internal Error")),
+            Err(SubscriptionError::InvalidBufferSize) => Err(tonic::Status::invalid_argument(
+                "Subscription buffer_size max allowed value is 1000",
+            )),
+            Err(SubscriptionError::QuotaExceeded) => Err(tonic::Status::resource_exhausted(
+                "Maximum number of subscriptions reached",
+            )),
         }
     }
 
@@ -370,6 +617,7 @@ impl proto::val_server::Val for broker::DataBroker {
         let join_handle = tokio::spawn(async move {
             let permissions = permissions;
             let broker = broker.authorized_access(&permissions);
+            let mut actuation_id = 0;
             loop {
                 select! {
                     message = stream.message() => {
@@ -390,12 +638,12 @@ impl proto::val_server::Val for broker::DataBroker {
                                                 let id = broker.get_id_by_path(path).await
                                                     .ok_or_else(|| tonic::Status::not_found(format!("Invalid path: {}", path)))?;
                                                 // Propagate error from actuate.
-                                                broker.actuate(&id, &DataValue::from(value))
+                                                actuation_id = broker.actuate(&id, &DataValue::from(value))
                                                     .await
                                                     .map_err(|(error, code)| error.to_tonic_status(code))?;
                                             }
                                             Some(proto::signal_id::Signal::Id(ref id)) => {
-                                                broker.actuate(id, &DataValue::from(value))
+                                                actuation_id = broker.actuate(id, &DataValue::from(value))
                                                     .await
                                                     .map_err(|(error, code)| error.to_tonic_status(code))?;
                                             }
@@ -422,11 +670,13 @@ impl proto::val_server::Val for broker::DataBroker {
                     }
                 }
             }
-            Ok::<(), tonic::Status>(())
+            Ok::<u64, tonic::Status>(actuation_id)
         });
 
         match join_handle.await {
-            Ok(Ok(())) => Ok(tonic::Response::new(proto::ActuateResponse {})),
+            Ok(Ok(actuation_id)) => Ok(tonic::Response::new(proto::ActuateResponse {
+                actuation_id,
+            })),
             Ok(Err(status)) => Err(status),
             Err(join_error) => Err(tonic::Status::internal(format!(
                 "Actuate stream error: {:?}",
@@ -482,13 +732,13 @@ impl proto::val_server::Val for broker::DataBroker {
                     )))?;
 
                 match broker.actuate(&id, &DataValue::from(value)).await {
-                    Ok(()) => Ok(tonic::Response::new(ActuateResponse {})),
+                    Ok(actuation_id) => Ok(tonic::Response::new(ActuateResponse { actuation_id })),
                     Err(error) => Err(error.0.to_tonic_status(error.1)),
                 }
             }
             Some(proto::signal_id::Signal::Id(id)) => {
                 match broker.actuate(id, &DataValue::from(value)).await {
-                    Ok(()) => Ok(tonic::Response::new(ActuateResponse {})),
+                    Ok(actuation_id) => Ok(tonic::Response::new(ActuateResponse { actuation_id })),
                     Err(error) => Err(error.0.to_tonic_status(error.1)),
                 }
             }
@@ -557,13 +807,17 @@ impl proto::val_server::Val for broker::DataBroker {
             let actuation_change = ActuationChange {
                 id: vss_id,
                 data_value,
+                // Assigned by the broker
+                actuation_id: 0,
             };
             actuation_changes.push(actuation_change);
         }
 
         let result = broker.batch_actuate(actuation_changes).await;
         match result {
-            Ok(_) => Ok(tonic::Response::new(proto::BatchActuateResponse {})),
+            Ok(actuation_ids) => Ok(tonic::Response::new(proto::BatchActuateResponse {
+                actuation_ids,
+            })),
             Err(error) => return Err(error.0.to_tonic_status(error.1)),
         }
     }
@@ -589,7 +843,17 @@ impl proto::val_server::Val for broker::DataBroker {
 
         let metadata_request = request.into_inner();
 
-        match Matcher::new(&metadata_request.root) {
+        // The page token is the id of the last entry of the previous page
+        let page_start = if metadata_request.page_token.is_empty() {
+            None
+        } else {
+            match metadata_request.page_token.parse::<i32>() {
+                Ok(last_id) => Some(last_id),
+                Err(_) => return Err(tonic::Status::invalid_argument("Invalid page token")),
+            }
+        };
+
+        match self.path_matcher(&metadata_request.root) {
             Ok(matcher) => {
                 let mut metadata_response = Vec::new();
                 broker
@@ -605,8 +869,24 @@ impl proto::val_server::Val for broker::DataBroker {
                         "Specified root branch does not exist",
                     ))
                 } else {
+                    // Order by id, so pages stay stable when entries are added
+                    metadata_response.sort_by_key(|metadata| metadata.id);
+                    if let Some(last_id) = page_start {
+                        metadata_response.retain(|metadata| metadata.id > last_id);
+                    }
+                    let page_size = metadata_request.page_size as usize;
+                    let next_page_token = if page_size > 0 && metadata_response.len() > page_size {
+                        metadata_response.truncate(page_size);
+                        metadata_response
+                            .last()
+                            .map(|metadata| metadata.id.to_string())
+                            .unwrap_or_default()
+                    } else {
+                        String::new()
+                    };
                     Ok(tonic::Response::new(ListMetadataResponse {
                         metadata: metadata_response,
+                        next_page_token,
                     }))
                 }
             }
@@ -680,8 +960,205 @@ impl proto::val_server::Val for broker::DataBroker {
         }
     }
 
+    // Publish multiple signal values at once.
+    // All values are validated before any of them is published. If any error
+    // occurs, the entire operation will be aborted and no value will be published.
+    //
+    // Returns (GRPC error code):
+    //   NOT_FOUND if any of the signals are non-existant.
+    //   PERMISSION_DENIED
+    //       - if access is denied for any of the signals.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //   INVALID_ARGUMENT
+    //       - if a request is missing its data point
+    //       - if the data type used in the request does not match
+    //            the data type of the addressed signal
+    //       - if any of the published values is not accepted,
+    //            e.g. if sending an unsupported enum value
+    //       - if any of the published values is out of the min/max range specified
+    //
+    async fn batch_publish_values(
+        &self,
+        request: tonic::Request<proto::BatchPublishValuesRequest>,
+    ) -> Result<tonic::Response<proto::BatchPublishValuesResponse>, tonic::Status> {
+        debug!(?request);
+        let permissions = match request.extensions().get::<Permissions>() {
+            Some(permissions) => {
+                debug!(?permissions);
+                permissions.clone()
+            }
+            None => return Err(tonic::Status::unauthenticated("Unauthenticated")),
+        };
+
+        let broker = self.authorized_access(&permissions);
+
+        let publish_requests = request.into_inner().publish_requests;
+
+        let mut updates: Vec<(i32, broker::EntryUpdate)> =
+            Vec::with_capacity(publish_requests.len());
+        for publish_request in publish_requests {
+            let signal_id = get_signal(publish_request.signal_id, &broker).await?;
+            let data_point = match publish_request.data_point {
+                Some(data_point) => data_point,
+                None => {
+                    return Err(tonic::Status::invalid_argument(format!(
+                        "No data point provided (id: {})",
+                        signal_id
+                    )))
+                }
+            };
+            updates.push((
+                signal_id,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint::from(&data_point)),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        match broker.update_entries_atomic(updates).await {
+            Ok(()) => Ok(tonic::Response::new(proto::BatchPublishValuesResponse {})),
+            Err(errors) => match errors.first() {
+                Some((id, err)) => Err(err.to_status_with_code(id)),
+                None => Err(tonic::Status::internal(
+                    "There is no error provided for the entry",
+                )),
+            },
+        }
+    }
+
+    // Publish a signal value only if the signal still has the value (or the
+    // timestamp) the caller expects.
+    //
+    // Returns (GRPC error code):
+    //   NOT_FOUND if the signal is non-existant.
+    //   PERMISSION_DENIED
+    //       - if read or write access is denied for the signal.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //   ABORTED if the signal does not have the expected value or timestamp.
+    //   INVALID_ARGUMENT
+    //       - if the data point or the expected value/timestamp is missing
+    //       - if the data type used in the request does not match
+    //            the data type of the addressed signal
+    //       - if the published value is not accepted,
+    //            e.g. if sending an unsupported enum value
+    //       - if the published value is out of the min/max range specified
+    //
+    async fn compare_and_publish_value(
+        &self,
+        request: tonic::Request<proto::CompareAndPublishValueRequest>,
+    ) -> Result<tonic::Response<proto::CompareAndPublishValueResponse>, tonic::Status> {
+        debug!(?request);
+        let permissions = match request.extensions().get::<Permissions>() {
+            Some(permissions) => {
+                debug!(?permissions);
+                permissions.clone()
+            }
+            None => return Err(tonic::Status::unauthenticated("Unauthenticated")),
+        };
+
+        let broker = self.authorized_access(&permissions);
+
+        let request = request.into_inner();
+
+        let signal_id = get_signal(request.signal_id, &broker).await?;
+        let data_point = match request.data_point {
+            Some(data_point) => data_point,
+            None => {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "No data point provided (id: {})",
+                    signal_id
+                )))
+            }
+        };
+        let condition = match request.expected {
+            Some(proto::compare_and_publish_value_request::Expected::ExpectedValue(value)) => {
+                broker::UpdateCondition::Value(match value.typed_value {
+                    Some(_) => broker::DataValue::from(value),
+                    None => broker::DataValue::NotAvailable,
+                })
+            }
+            Some(proto::compare_and_publish_value_request::Expected::ExpectedTimestamp(
+                timestamp,
+            )) => match timestamp.try_into() {
+                Ok(timestamp) => broker::UpdateCondition::Timestamp(timestamp),
+                Err(_) => {
+                    return Err(tonic::Status::invalid_argument(format!(
+                        "Invalid expected timestamp (id: {})",
+                        signal_id
+                    )))
+                }
+            },
+            None => {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "No expected value or timestamp provided (id: {})",
+                    signal_id
+                )))
+            }
+        };
+
+        match broker
+            .compare_and_update(
+                signal_id,
+                condition,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint::from(&data_point)),
+                    ..Default::default()
+                },
+            )
+            .await
+        {
+            Ok(()) => Ok(tonic::Response::new(
+                proto::CompareAndPublishValueResponse {},
+            )),
+            Err(err) => Err(err.to_status_with_code(&signal_id)),
+        }
+    }
+
+    async fn publish_array_delta(
+        &self,
+        request: tonic::Request<proto::PublishArrayDeltaRequest>,
+    ) -> Result<tonic::Response<proto::PublishArrayDeltaResponse>, tonic::Status> {
+        debug!(?request);
+        let permissions = match request.extensions().get::<Permissions>() {
+            Some(permissions) => {
+                debug!(?permissions);
+                permissions.clone()
+            }
+            None => return Err(tonic::Status::unauthenticated("Unauthenticated")),
+        };
+
+        let broker = self.authorized_access(&permissions);
+
+        let request = request.into_inner();
+
+        let signal_id = get_signal(request.signal_id, &broker).await?;
+        let Some(delta) = request.delta else {
+            return Err(tonic::Status::invalid_argument(format!(
+                "No delta provided (id: {})",
+                signal_id
+            )));
+        };
+        let indices: Vec<usize> = delta.indices.iter().map(|index| *index as usize).collect();
+        let values = match delta.values {
+            Some(values) if values.typed_value.is_some() => broker::DataValue::from(values),
+            _ => broker::DataValue::NotAvailable,
+        };
+        let source_ts = delta
+            .timestamp
+            .and_then(|timestamp| timestamp.try_into().ok());
+
+        match broker
+            .update_array_elements(signal_id, &indices, &values, source_ts)
+            .await
+        {
+            Ok(()) => Ok(tonic::Response::new(proto::PublishArrayDeltaResponse {})),
+            Err(err) => Err(err.to_status_with_code(&signal_id)),
+        }
+    }
+
     type OpenProviderStreamStream =
-        ReceiverStream<Result<proto::OpenProviderStreamResponse, tonic::Status>>;
+        DeadlineStream<ReceiverStream<Result<proto::OpenProviderStreamResponse, tonic::Status>>>;
 
     // Errors:
     //    - Provider sends ProvideActuationRequest -> Databroker returns ProvideActuationResponse
@@ -706,8 +1183,9 @@ impl proto::val_server::Val for broker::DataBroker {
     //
     //    - Databroker sends BatchActuateStreamRequest -> Provider shall return a BatchActuateStreamResponse,
     //        for every signal requested to indicate if the request was accepted or not.
-    //        It is up to the provider to decide if the stream shall be closed,
-    //        as of today Databroker will not react on the received error message.
+    //        It is up to the provider to decide if the stream shall be closed.
+    //        An error fails the actuation, see GetActuationStatus. The provider may
+    //        send another response with the actuation_id once the actuation was executed.
     //
     async fn open_provider_stream(
         &self,
@@ -722,27 +1200,84 @@ impl proto::val_server::Val for broker::DataBroker {
             None => return Err(tonic::Status::unauthenticated("Unauthenticated")),
         };
 
+        let provider_stream_guard = self
+            .authorized_access(&permissions)
+            .open_provider_stream()
+            .map_err(|_| {
+                tonic::Status::resource_exhausted("Maximum number of provider streams reached")
+            })?;
+
         let mut stream = request.into_inner();
 
         let mut shutdown_trigger = self.get_shutdown_trigger();
 
         // Copy (to move into task below)
-        let broker = self.clone();
+        let data_broker = self.clone();
+        let provider_enrollment = self.provider_enrollment();
         // Create stream (to be returned)
         let (response_stream_sender, response_stream_receiver) = mpsc::channel(10);
+        let response_stream = DeadlineStream::new(
+            ReceiverStream::new(response_stream_receiver),
+            self.deadlines(),
+        );
+        let activity = response_stream.activity();
 
         // Listening on stream
         tokio::spawn(async move {
-            let permissions = permissions;
-            let broker = broker.authorized_access(&permissions);
+            let mut provider_stream_guard = provider_stream_guard;
+            let mut permissions = permissions;
+            // Challenge issued to the provider while enrolling
+            let mut challenge = None;
+            let mut enrolled = false;
             loop {
                 select! {
                     message = stream.message() => {
                         match message {
                             Ok(request) => {
+                                activity.touch();
                                 match request {
                                     Some(req) => {
-                                        match req.action {
+                                        let bytes = req.encoded_len();
+                                        let action = match req.action {
+                                            Some(EnrollProviderRequest(enroll_provider_request)) => {
+                                                let response = enroll_provider(provider_enrollment.as_deref(), &enroll_provider_request, &mut challenge).await;
+                                                if let Err(err) = response_stream_sender.send(response).await {
+                                                    debug!("Failed to send response: {}", err)
+                                                }
+                                                continue;
+                                            }
+                                            Some(EnrollProviderProof(enroll_provider_proof)) => {
+                                                let response = match verify_provider(provider_enrollment.as_deref(), challenge.take(), &enroll_provider_proof).await {
+                                                    Ok(identity) => match identity.restrict(&permissions) {
+                                                        Ok(restricted) => {
+                                                            info!("Provider {} enrolled (session {})", identity.provider_id, identity.session_id);
+                                                            permissions = restricted;
+                                                            enrolled = true;
+                                                            Ok(enrolled_response(identity))
+                                                        }
+                                                        Err(_) => Err(tonic::Status::internal("Failed to restrict permissions")),
+                                                    },
+                                                    Err(status) => Err(status),
+                                                };
+                                                if let Err(err) = response_stream_sender.send(response).await {
+                                                    debug!("Failed to send response: {}", err)
+                                                }
+                                                continue;
+                                            }
+                                            action => action,
+                                        };
+                                        if provider_enrollment.is_some()
+                                            && !enrolled
+                                            && matches!(action, Some(ProvideActuationRequest(_)) | Some(PublishValuesRequest(_)))
+                                        {
+                                            let response = Err(tonic::Status::permission_denied("Provider must enroll first"));
+                                            if let Err(err) = response_stream_sender.send(response).await {
+                                                debug!("Failed to send response: {}", err)
+                                            }
+                                            continue;
+                                        }
+                                        let broker = data_broker.authorized_access(&permissions);
+                                        match action {
                                             Some(ProvideActuationRequest(provided_actuation)) => {
                                                 let response = provide_actuation(&broker, &provided_actuation, response_stream_sender.clone()).await;
                                                 if let Err(err) = response_stream_sender.send(response).await
@@ -751,7 +1286,10 @@ impl proto::val_server::Val for broker::DataBroker {
                                                 }
                                             },
                                             Some(PublishValuesRequest(publish_values_request)) => {
-                                                let response = publish_values(&broker, &publish_values_request).await;
+                                                let response = match provider_stream_guard.admit(publish_values_request.data_points.len(), bytes) {
+                                                    Ok(()) => publish_values(&broker, &provider_stream_guard, &publish_values_request).await,
+                                                    Err(_) => Some(throttled(&publish_values_request)),
+                                                };
                                                 if let Some(value) = response {
                                                     if let Err(err) = response_stream_sender.send(Ok(value)).await {
                                                         debug!("Failed to send error response: {}", err);
@@ -759,7 +1297,9 @@ impl proto::val_server::Val for broker::DataBroker {
                                                 }
                                             },
                                             Some(BatchActuateStreamResponse(batch_actuate_stream_response)) => {
-
+                                                let actuation_id = batch_actuate_stream_response.actuation_id;
+                                                let status = batch_actuate_stream_response.status();
+                                                let mut failure = None;
                                                 if let Some(error) = batch_actuate_stream_response.error {
                                                     match error.code() {
                                                         ErrorCode::Ok  => {},
@@ -777,11 +1317,14 @@ impl proto::val_server::Val for broker::DataBroker {
                                                                 }
                                                             }
                                                             msg = format!("{}, error code: {}, error message: {}", msg, &error.code.to_string(), &error.message);
-                                                            debug!(msg)
+                                                            debug!(msg);
+                                                            failure = Some(error.message);
                                                         }
                                                     }
                                                 }
-
+                                                if actuation_id != 0 {
+                                                    report_actuation(&broker, actuation_id, status, failure).await;
+                                                }
                                             },
                                             Some(ProvideSignalRequest(_provide_signal_request)) => {
                                                 todo!();
@@ -795,6 +1338,9 @@ impl proto::val_server::Val for broker::DataBroker {
                                             Some(ProviderErrorIndication(_provide_error_indication)) => {
                                                 todo!();
                                             }
+                                            Some(EnrollProviderRequest(_)) | Some(EnrollProviderProof(_)) => {
+                                                // Handled above
+                                            }
                                             None => {
 
                                             },
@@ -820,22 +1366,435 @@ impl proto::val_server::Val for broker::DataBroker {
             }
         });
 
-        Ok(tonic::Response::new(ReceiverStream::new(
-            response_stream_receiver,
-        )))
+        Ok(tonic::Response::new(response_stream))
     }
 
-    async fn get_server_info(
+    type SubscribeRejectionsStream = Pin<
+        Box<
+            dyn Stream<Item = Result<proto::SubscribeRejectionsResponse, tonic::Status>>
+                + Send
+                + Sync
+                + 'static,
+        >,
+    >;
+    // Returns (GRPC error code):
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn subscribe_rejections(
         &self,
-        _request: tonic::Request<proto::GetServerInfoRequest>,
-    ) -> Result<tonic::Response<proto::GetServerInfoResponse>, tonic::Status> {
-        let server_info = proto::GetServerInfoResponse {
-            name: "databroker".to_owned(),
+        request: tonic::Request<proto::SubscribeRejectionsRequest>,
+    ) -> Result<tonic::Response<Self::SubscribeRejectionsStream>, tonic::Status> {
+        debug!(?request);
+        let permissions = request
+            .extensions()
+            .get::<Permissions>()
+            .ok_or(tonic::Status::unauthenticated("Unauthenticated"))?;
+        if permissions.is_expired() {
+            return Err(tonic::Status::unauthenticated("Permission expired"));
+        }
+        // Administrators get the rejections of all providers
+        let all = permissions.can_administer().is_ok();
+        let subject = permissions.subject().map(str::to_owned);
+
+        let stream =
+            BroadcastStream::new(self.subscribe_events()).filter_map(move |event| match event {
+                Ok(Event::UpdateRejected { client, update }) if all || client == subject => {
+                    Some(Ok(proto::SubscribeRejectionsResponse {
+                        client: client.unwrap_or_default(),
+                        signal_id: update.id,
+                        path: update.path.unwrap_or_default(),
+                        data_point: update.datapoint.into(),
+                        error: Some(proto::Error::from(&update.error)),
+                    }))
+                }
+                Ok(_) => None,
+                Err(err) => {
+                    warn!("Slow subscriber missed rejected updates: {}", err);
+                    None
+                }
+            });
+        let stream = DeadlineStream::new(stream, self.deadlines());
+        Ok(tonic::Response::new(Box::pin(stream)))
+    }
+
+    async fn get_server_info(
+        &self,
+        _request: tonic::Request<proto::GetServerInfoRequest>,
+    ) -> Result<tonic::Response<proto::GetServerInfoResponse>, tonic::Status> {
+        let mut capabilities = proto::ServerCapabilities::from(self.get_capabilities().await);
+        capabilities.max_message_size = MAX_MESSAGE_SIZE as u64;
+        let server_info = proto::GetServerInfoResponse {
+            name: "databroker".to_owned(),
             version: self.get_version().to_owned(),
             commit_hash: self.get_commit_sha().to_owned(),
+            capabilities: Some(capabilities),
         };
         Ok(tonic::Response::new(server_info))
     }
+
+    type DumpStateStream =
+        Pin<Box<dyn Stream<Item = Result<proto::DumpStateResponse, tonic::Status>> + Send + Sync>>;
+
+    // Returns (GRPC error code):
+    //   PERMISSION_DENIED if the client lacks the `admin` scope.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn dump_state(
+        &self,
+        request: tonic::Request<proto::DumpStateRequest>,
+    ) -> Result<tonic::Response<Self::DumpStateStream>, tonic::Status> {
+        debug!(?request);
+        check_admin_permission(&request)?;
+
+        let dump = serde_json::to_vec(&dump::dump(self).await)
+            .map_err(|err| tonic::Status::internal(format!("Failed to dump the state: {err}")))?;
+        let responses: Vec<_> = dump
+            .chunks(DUMP_CHUNK_SIZE)
+            .map(|chunk| proto::DumpStateResponse {
+                chunk: chunk.to_vec(),
+            })
+            .collect();
+        Ok(tonic::Response::new(Box::pin(tokio_stream::iter(
+            responses.into_iter().map(Ok),
+        ))))
+    }
+
+    // Returns (GRPC error code):
+    //   INVALID_ARGUMENT if the filter can't be parsed.
+    //   FAILED_PRECONDITION if the log filter of the broker can't be changed.
+    //   PERMISSION_DENIED if the client lacks the `admin` scope.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn set_log_filter(
+        &self,
+        request: tonic::Request<proto::SetLogFilterRequest>,
+    ) -> Result<tonic::Response<proto::SetLogFilterResponse>, tonic::Status> {
+        debug!(?request);
+        check_admin_permission(&request)?;
+        let Some(log_filter) = self.log_filter() else {
+            return Err(tonic::Status::failed_precondition(
+                "The log filter can't be changed",
+            ));
+        };
+
+        let request = request.into_inner();
+        let result = if request.filter.is_empty() {
+            log_filter.reset()
+        } else {
+            let timeout = match request.timeout_seconds {
+                0 => DEFAULT_LOG_FILTER_TIMEOUT,
+                seconds => Duration::from_secs(seconds.into()),
+            };
+            log_filter.set(&request.filter, timeout)
+        };
+        match result {
+            Ok(filter) => Ok(tonic::Response::new(proto::SetLogFilterResponse { filter })),
+            Err(LogFilterError::InvalidFilter(err)) => Err(tonic::Status::invalid_argument(
+                format!("Invalid filter: {err}"),
+            )),
+            Err(LogFilterError::ReloadFailed(err)) => Err(tonic::Status::internal(format!(
+                "Failed to change the log filter: {err}"
+            ))),
+        }
+    }
+
+    // Returns (GRPC error code):
+    //   PERMISSION_DENIED if the client lacks the `admin` scope.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn list_sessions(
+        &self,
+        request: tonic::Request<proto::ListSessionsRequest>,
+    ) -> Result<tonic::Response<proto::ListSessionsResponse>, tonic::Status> {
+        debug!(?request);
+        check_admin_permission(&request)?;
+
+        let sessions = self
+            .get_sessions()
+            .await
+            .into_iter()
+            .map(|session| proto::Session {
+                id: session.id,
+                peer: session.peer.unwrap_or_default(),
+                connected_at: Some(session.connected_at.into()),
+                client_name: session.client_name.unwrap_or_default(),
+                client_version: session.client_version.unwrap_or_default(),
+                subject: session.subject.unwrap_or_default(),
+                subscriptions: session.subscriptions.try_into().unwrap_or(u32::MAX),
+            })
+            .collect();
+        Ok(tonic::Response::new(proto::ListSessionsResponse {
+            sessions,
+        }))
+    }
+
+    // Returns (GRPC error code):
+    //   PERMISSION_DENIED if the client lacks the `admin` scope.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn get_signal_stats(
+        &self,
+        request: tonic::Request<proto::GetSignalStatsRequest>,
+    ) -> Result<tonic::Response<proto::GetSignalStatsResponse>, tonic::Status> {
+        debug!(?request);
+        check_admin_permission(&request)?;
+
+        let request = request.into_inner();
+        let depth = match request.depth {
+            0 => None,
+            depth => Some(depth as usize),
+        };
+        let root = request.root.trim_end_matches('.');
+        let stats = self
+            .get_update_stats(depth)
+            .await
+            .into_iter()
+            .filter(|stats| {
+                root.is_empty()
+                    || stats
+                        .path
+                        .strip_prefix(root)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+            .map(|stats| proto::SignalStats {
+                path: stats.path,
+                signals: stats.signals.try_into().unwrap_or(u32::MAX),
+                updates: stats.updates,
+                updates_per_second: stats.updates_per_second,
+                last_update_age_ms: stats
+                    .last_update_age
+                    .map(|age| age.as_millis().try_into().unwrap_or(u64::MAX))
+                    .unwrap_or_default(),
+                subscribers: stats.subscribers.try_into().unwrap_or(u32::MAX),
+            })
+            .collect();
+        Ok(tonic::Response::new(proto::GetSignalStatsResponse {
+            stats,
+        }))
+    }
+
+    // Returns (GRPC error code):
+    //   PERMISSION_DENIED if the client lacks the `admin` scope.
+    //   FAILED_PRECONDITION if not enabled with --latency-stats.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn get_latency_stats(
+        &self,
+        request: tonic::Request<proto::GetLatencyStatsRequest>,
+    ) -> Result<tonic::Response<proto::GetLatencyStatsResponse>, tonic::Status> {
+        debug!(?request);
+        check_admin_permission(&request)?;
+        let Some(latency_stats) = self.latency_stats() else {
+            return Err(tonic::Status::failed_precondition(
+                "Latency stats are not enabled",
+            ));
+        };
+
+        fn micros(duration: Duration) -> u64 {
+            duration.as_micros().try_into().unwrap_or(u64::MAX)
+        }
+        let stages = latency_stats
+            .snapshot()
+            .into_iter()
+            .map(|stats| proto::LatencyStats {
+                stage: stats.stage.name().to_owned(),
+                count: stats.count,
+                sum_us: micros(stats.sum),
+                max_us: micros(stats.max),
+                p50_us: stats.quantile(0.5).map(micros).unwrap_or_default(),
+                p99_us: stats.quantile(0.99).map(micros).unwrap_or_default(),
+                buckets: stats
+                    .buckets
+                    .iter()
+                    .map(|(upper_bound, count)| proto::LatencyBucket {
+                        upper_bound_us: upper_bound.map(micros).unwrap_or_default(),
+                        count: *count,
+                    })
+                    .collect(),
+            })
+            .collect();
+        if request.into_inner().reset {
+            latency_stats.reset();
+        }
+        Ok(tonic::Response::new(proto::GetLatencyStatsResponse {
+            stages,
+        }))
+    }
+
+    // Returns (GRPC error code):
+    //   INVALID_ARGUMENT if the scope is malformed.
+    //   PERMISSION_DENIED if the presented token doesn't grant the scope.
+    //   FAILED_PRECONDITION if token exchange is not enabled.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn exchange_token(
+        &self,
+        request: tonic::Request<proto::ExchangeTokenRequest>,
+    ) -> Result<tonic::Response<proto::ExchangeTokenResponse>, tonic::Status> {
+        debug!(?request);
+        let permissions = request
+            .extensions()
+            .get::<Permissions>()
+            .ok_or(tonic::Status::unauthenticated("Unauthenticated"))?
+            .clone();
+        let Some(token_exchange) = self.token_exchange() else {
+            return Err(tonic::Status::failed_precondition(
+                "Token exchange is not enabled",
+            ));
+        };
+
+        let request = request.into_inner();
+        let valid_for = match request.valid_for_seconds {
+            0 => DEFAULT_EXCHANGED_TOKEN_LIFETIME,
+            seconds => Duration::from_secs(seconds.into()),
+        };
+        match token_exchange.exchange(&permissions, &request.scope, valid_for) {
+            Ok((token, expires_at)) => Ok(tonic::Response::new(proto::ExchangeTokenResponse {
+                token,
+                expires_at: Some(expires_at.into()),
+            })),
+            Err(ExchangeError::InvalidScope) => Err(tonic::Status::invalid_argument(format!(
+                "Invalid scope '{}'",
+                request.scope
+            ))),
+            Err(ExchangeError::NotGranted(scope)) => Err(tonic::Status::permission_denied(
+                format!("'{scope}' is not granted by the presented token"),
+            )),
+            Err(ExchangeError::PresentedExpired) => {
+                Err(tonic::Status::unauthenticated("Permission expired"))
+            }
+            Err(ExchangeError::EncodeError(err)) => Err(tonic::Status::internal(err)),
+        }
+    }
+
+    // Returns (GRPC error code):
+    //   NOT_FOUND if the actuation is unknown, or no longer tracked.
+    //   PERMISSION_DENIED if the client may not read the actuator.
+    //   UNAUTHENTICATED if no credentials provided or credentials has expired
+    //
+    async fn get_actuation_status(
+        &self,
+        request: tonic::Request<proto::GetActuationStatusRequest>,
+    ) -> Result<tonic::Response<proto::GetActuationStatusResponse>, tonic::Status> {
+        debug!(?request);
+        let permissions = request
+            .extensions()
+            .get::<Permissions>()
+            .ok_or(tonic::Status::unauthenticated("Unauthenticated"))?
+            .clone();
+        let broker = self.authorized_access(&permissions);
+
+        let actuation_id = request.into_inner().actuation_id;
+        match broker.get_actuation(actuation_id).await {
+            Ok(actuation) => Ok(tonic::Response::new(proto::GetActuationStatusResponse {
+                status: proto::ActuationStatus::from(actuation.status).into(),
+                signal_id: Some(proto::SignalId {
+                    signal: Some(signal_id::Signal::Id(actuation.vss_id)),
+                }),
+                message: actuation.message.unwrap_or_default(),
+            })),
+            Err(ReadError::NotFound) => Err(tonic::Status::not_found(format!(
+                "Unknown actuation id {actuation_id}"
+            ))),
+            Err(ReadError::PermissionDenied) => {
+                Err(tonic::Status::permission_denied("Permission denied"))
+            }
+            Err(ReadError::PermissionExpired) => {
+                Err(tonic::Status::unauthenticated("Permission expired"))
+            }
+        }
+    }
+}
+
+/// Record the outcome of an actuation reported by its provider. An error
+/// fails the actuation, a response without status just acknowledges it.
+async fn report_actuation(
+    broker: &AuthorizedAccess<'_, '_>,
+    actuation_id: u64,
+    status: proto::ActuationStatus,
+    failure: Option<String>,
+) {
+    let status = match (&failure, status) {
+        (Some(_), _) | (None, proto::ActuationStatus::Failed) => ActuationStatus::Failed,
+        (None, proto::ActuationStatus::Executed) => ActuationStatus::Executed,
+        (None, _) => ActuationStatus::Delivered,
+    };
+    if let Err((_, message)) = broker.report_actuation(actuation_id, status, failure).await {
+        debug!(
+            "Ignoring the outcome of actuation {}: {}",
+            actuation_id, message
+        );
+    }
+}
+
+/// Fails unless the client of `request` may administer the broker.
+#[allow(clippy::result_large_err)]
+fn check_admin_permission<T>(request: &tonic::Request<T>) -> Result<(), tonic::Status> {
+    let Some(permissions) = request.extensions().get::<Permissions>() else {
+        return Err(tonic::Status::unauthenticated("Unauthenticated"));
+    };
+    debug!(?permissions);
+    permissions.can_administer().map_err(|err| match err {
+        PermissionError::Denied => tonic::Status::permission_denied("Permission denied"),
+        PermissionError::Expired => tonic::Status::unauthenticated("Permission expired"),
+    })
+}
+
+async fn enroll_provider(
+    enrollment: Option<&ProviderEnrollment>,
+    request: &proto::EnrollProviderRequest,
+    challenge: &mut Option<Challenge>,
+) -> Result<OpenProviderStreamResponse, tonic::Status> {
+    let Some(enrollment) = enrollment else {
+        return Err(tonic::Status::failed_precondition(
+            "Provider enrollment is not enabled",
+        ));
+    };
+    let issued = enrollment
+        .challenge(&request.provider_id)
+        .map_err(|_| tonic::Status::unavailable("Failed to issue a challenge"))?;
+    let response = OpenProviderStreamResponse {
+        action: Some(
+            open_provider_stream_response::Action::EnrollProviderChallenge(
+                proto::EnrollProviderChallenge {
+                    nonce: issued.nonce().to_vec(),
+                },
+            ),
+        ),
+    };
+    *challenge = Some(issued);
+    Ok(response)
+}
+
+async fn verify_provider(
+    enrollment: Option<&ProviderEnrollment>,
+    challenge: Option<Challenge>,
+    proof: &proto::EnrollProviderProof,
+) -> Result<ProviderIdentity, tonic::Status> {
+    let (Some(enrollment), Some(challenge)) = (enrollment, challenge) else {
+        return Err(tonic::Status::failed_precondition(
+            "No enrollment challenge to answer",
+        ));
+    };
+    enrollment
+        .verify(&challenge, &proof.signature)
+        .map_err(|err| match err {
+            EnrollmentError::Rejected => tonic::Status::permission_denied("Enrollment rejected"),
+            EnrollmentError::Unavailable => tonic::Status::unavailable("Failed to issue a session"),
+        })
+}
+
+fn enrolled_response(identity: ProviderIdentity) -> OpenProviderStreamResponse {
+    OpenProviderStreamResponse {
+        action: Some(
+            open_provider_stream_response::Action::EnrollProviderResponse(
+                proto::EnrollProviderResponse {
+                    session_id: identity.session_id,
+                    paths: identity.paths,
+                },
+            ),
+        ),
+    }
 }
 
 async fn provide_actuation(
@@ -908,6 +1867,7 @@ async fn provide_actuation(
 
 async fn publish_values(
     broker: &AuthorizedAccess<'_, '_>,
+    provider_stream_guard: &ProviderStreamGuard,
     request: &databroker_proto::kuksa::val::v2::PublishValuesRequest,
 ) -> Option<OpenProviderStreamResponse> {
     let ids: Vec<(i32, broker::EntryUpdate)> = request
@@ -935,19 +1895,58 @@ async fn publish_values(
     // TODO check if provider is allowed to update the entries for the provided signals?
     match broker.update_entries(ids).await {
         Ok(_) => None,
-        Err(err) => Some(OpenProviderStreamResponse {
-            action: Some(
-                open_provider_stream_response::Action::PublishValuesResponse(
-                    PublishValuesResponse {
-                        request_id: request.request_id,
-                        status: err
-                            .iter()
-                            .map(|(id, error)| (*id, proto::Error::from(error)))
-                            .collect(),
-                    },
+        Err(err) => {
+            let mut rejected = Vec::with_capacity(err.len());
+            for (id, error) in &err {
+                if let Some(datapoint) = request.data_points.get(id) {
+                    rejected.push(broker::RejectedUpdate {
+                        id: *id,
+                        path: broker.get_metadata(*id).await.map(|metadata| metadata.path),
+                        datapoint: broker::Datapoint::from(datapoint),
+                        error: error.clone(),
+                    });
+                }
+            }
+            provider_stream_guard.reject(rejected);
+            Some(OpenProviderStreamResponse {
+                action: Some(
+                    open_provider_stream_response::Action::PublishValuesResponse(
+                        PublishValuesResponse {
+                            request_id: request.request_id,
+                            status: err
+                                .iter()
+                                .map(|(id, error)| (*id, proto::Error::from(error)))
+                                .collect(),
+                        },
+                    ),
                 ),
-            ),
-        }),
+            })
+        }
+    }
+}
+
+fn throttled(
+    request: &databroker_proto::kuksa::val::v2::PublishValuesRequest,
+) -> OpenProviderStreamResponse {
+    OpenProviderStreamResponse {
+        action: Some(
+            open_provider_stream_response::Action::PublishValuesResponse(PublishValuesResponse {
+                request_id: request.request_id,
+                status: request
+                    .data_points
+                    .keys()
+                    .map(|id| {
+                        (
+                            *id,
+                            proto::Error {
+                                code: ErrorCode::ResourceExhausted.into(),
+                                message: "Provider stream rate exceeded".to_string(),
+                            },
+                        )
+                    })
+                    .collect(),
+            }),
+        ),
     }
 }
 
@@ -985,25 +1984,43 @@ async fn get_signal(
 fn convert_to_proto_stream(
     input: impl Stream<Item = broker::EntryUpdates>,
     size: usize,
+    delta_encoding: Option<proto::DeltaEncoding>,
 ) -> impl Stream<Item = Result<proto::SubscribeResponse, tonic::Status>> {
+    let mut delta_encoder = delta_encoding.as_ref().map(DeltaEncoder::new);
     input.map(move |item| {
         let mut entries: HashMap<String, proto::Datapoint> = HashMap::with_capacity(size);
+        let mut deltas = HashMap::new();
         for update in item.updates {
+            let path = update
+                .update
+                .path
+                .expect("Something wrong with update path of subscriptions!");
+            if let (Some(encoder), Some(datapoint)) =
+                (delta_encoder.as_mut(), &update.update.datapoint)
+            {
+                if let Some(delta) = encoder.encode(&path, datapoint) {
+                    deltas.insert(path, delta);
+                    continue;
+                }
+            }
             let update_datapoint: Option<proto::Datapoint> = match update.update.datapoint {
                 Some(datapoint) => datapoint.into(),
                 None => None,
             };
             if let Some(dp) = update_datapoint {
-                entries.insert(
-                    update
-                        .update
-                        .path
-                        .expect("Something wrong with update path of subscriptions!"),
-                    dp,
-                );
+                entries.insert(path, dp);
             }
         }
-        let response = proto::SubscribeResponse { entries };
+        let response = proto::SubscribeResponse {
+            entries,
+            deltas,
+            reasons: item
+                .reasons
+                .into_iter()
+                .map(|reason| proto::UpdateReason::from(reason) as i32)
+                .collect(),
+            shaped: item.shaped,
+        };
         Ok(response)
     })
 }
@@ -1011,10 +2028,21 @@ fn convert_to_proto_stream(
 fn convert_to_proto_stream_id(
     input: impl Stream<Item = broker::EntryUpdates>,
     size: usize,
+    delta_encoding: Option<proto::DeltaEncoding>,
 ) -> impl Stream<Item = Result<proto::SubscribeByIdResponse, tonic::Status>> {
+    let mut delta_encoder = delta_encoding.as_ref().map(DeltaEncoder::new);
     input.map(move |item| {
         let mut entries: HashMap<i32, proto::Datapoint> = HashMap::with_capacity(size);
+        let mut deltas = HashMap::new();
         for update in item.updates {
+            if let (Some(encoder), Some(datapoint)) =
+                (delta_encoder.as_mut(), &update.update.datapoint)
+            {
+                if let Some(delta) = encoder.encode(&update.id, datapoint) {
+                    deltas.insert(update.id, delta);
+                    continue;
+                }
+            }
             let update_datapoint: Option<proto::Datapoint> = match update.update.datapoint {
                 Some(datapoint) => datapoint.into(),
                 None => None,
@@ -1023,7 +2051,16 @@ fn convert_to_proto_stream_id(
                 entries.insert(update.id, dp);
             }
         }
-        let response = proto::SubscribeByIdResponse { entries };
+        let response = proto::SubscribeByIdResponse {
+            entries,
+            deltas,
+            reasons: item
+                .reasons
+                .into_iter()
+                .map(|reason| proto::UpdateReason::from(reason) as i32)
+                .collect(),
+            shaped: item.shaped,
+        };
         Ok(response)
     })
 }
@@ -1034,8 +2071,9 @@ mod tests {
     use crate::{broker::DataBroker, permissions};
     use databroker_proto::kuksa::val::v2::val_server::Val;
     use proto::open_provider_stream_response::Action::{
-        BatchActuateStreamRequest, GetProviderValueRequest, ProvideActuationResponse,
-        ProvideSignalResponse, PublishValuesResponse, UpdateFilterRequest,
+        BatchActuateStreamRequest, EnrollProviderChallenge, EnrollProviderResponse,
+        GetProviderValueRequest, ProvideActuationResponse, ProvideSignalResponse,
+        PublishValuesResponse, UpdateFilterRequest,
     };
     use proto::{
         open_provider_stream_request, BatchActuateRequest, OpenProviderStreamRequest,
@@ -1877,77 +2915,349 @@ mod tests {
         }
     }
 
-    async fn publish_value(
-        broker: &DataBroker,
-        entry_id: i32,
-        input_value: Option<bool>,
-        input_timestamp: Option<std::time::SystemTime>,
-    ) {
-        let timestamp = input_timestamp.map(|input_timestamp| input_timestamp.into());
-
-        let mut request = tonic::Request::new(proto::PublishValueRequest {
-            signal_id: Some(proto::SignalId {
-                signal: Some(proto::signal_id::Signal::Id(entry_id)),
-            }),
-            data_point: Some(proto::Datapoint {
-                timestamp,
+    #[tokio::test]
+    async fn test_batch_publish_values_all_or_nothing() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
 
-                value: match input_value {
-                    Some(true) => Some(proto::Value {
-                        typed_value: Some(proto::value::TypedValue::Bool(true)),
-                    }),
-                    Some(false) => Some(proto::Value {
-                        typed_value: Some(proto::value::TypedValue::Bool(false)),
-                    }),
-                    None => None,
-                },
-            }),
-        });
+        let mut entry_ids = Vec::new();
+        for name in ["test.gear", "test.clutch"] {
+            let entry_id = authorized_access
+                .add_entry(
+                    name.to_owned(),
+                    broker::DataType::Uint8,
+                    broker::ChangeType::OnChange,
+                    broker::EntryType::Sensor,
+                    "Test datapoint".to_owned(),
+                    Some(broker::types::DataValue::Uint32(0)), // min
+                    Some(broker::types::DataValue::Uint32(6)), // max
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            entry_ids.push(entry_id);
+        }
 
-        request
-            .extensions_mut()
-            .insert(permissions::ALLOW_ALL.clone());
-        match broker.publish_value(request).await {
-            Ok(response) => {
-                // Handle the successful response
-                let publish_response = response.into_inner();
+        let batch_request = |values: [u32; 2]| {
+            let mut request = tonic::Request::new(proto::BatchPublishValuesRequest {
+                publish_requests: entry_ids
+                    .iter()
+                    .zip(values)
+                    .map(|(entry_id, value)| proto::PublishValueRequest {
+                        signal_id: Some(proto::SignalId {
+                            signal: Some(proto::signal_id::Signal::Id(*entry_id)),
+                        }),
+                        data_point: Some(proto::Datapoint {
+                            timestamp: None,
+                            value: Some(proto::Value {
+                                typed_value: Some(proto::value::TypedValue::Uint32(value)),
+                            }),
+                        }),
+                    })
+                    .collect(),
+            });
+            request
+                .extensions_mut()
+                .insert(permissions::ALLOW_ALL.clone());
+            request
+        };
 
-                // Check if there is an error in the response
-                assert_eq!(publish_response, proto::PublishValueResponse {});
-            }
+        // The clutch value is out of range, so the gear is not published either
+        match broker.batch_publish_values(batch_request([3, 7])).await {
+            Ok(_) => panic!("Should not happen!"),
             Err(status) => {
-                // Handle the error from the publish_value function
-                panic!("Publish failed with status: {:?}", status);
+                assert_eq!(status.code(), tonic::Code::InvalidArgument);
+                assert_eq!(
+                    status.message(),
+                    format!("Value out of min/max bounds (id: {})", entry_ids[1])
+                );
             }
         }
+        for entry_id in &entry_ids {
+            let datapoint = authorized_access.get_datapoint(*entry_id).await.unwrap();
+            assert_eq!(datapoint.value, broker::types::DataValue::NotAvailable);
+        }
+
+        broker
+            .batch_publish_values(batch_request([3, 1]))
+            .await
+            .expect("batch publish should succeed");
+        for (entry_id, value) in entry_ids.iter().zip([3, 1]) {
+            let datapoint = authorized_access.get_datapoint(*entry_id).await.unwrap();
+            assert_eq!(datapoint.value, broker::types::DataValue::Uint32(value));
+        }
     }
 
-    /*
-        Test subscribe service method
-    */
-    async fn test_subscribe_case(has_value: bool) {
-        async fn check_stream_next(
-            item: &Result<proto::SubscribeResponse, tonic::Status>,
-            input_value: Option<bool>,
-        ) {
-            // Create Datapoint
-            let mut expected_response: HashMap<String, proto::Datapoint> = HashMap::new();
-            // We expect to get an empty response first
-            expected_response.insert(
-                "test.datapoint1".to_string(),
-                proto::Datapoint {
-                    timestamp: None,
-                    value: match input_value {
-                        Some(true) => Some(proto::Value {
-                            typed_value: Some(proto::value::TypedValue::Bool(true)),
-                        }),
-                        Some(false) => Some(proto::Value {
-                            typed_value: Some(proto::value::TypedValue::Bool(false)),
-                        }),
-                        None => None,
-                    },
-                },
-            );
+    #[tokio::test]
+    async fn test_compare_and_publish_value() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let entry_id = authorized_access
+            .add_entry(
+                "test.gear".to_owned(),
+                broker::DataType::Uint8,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let compare_and_publish_request =
+            |expected: proto::compare_and_publish_value_request::Expected, value: u32| {
+                let mut request = tonic::Request::new(proto::CompareAndPublishValueRequest {
+                    signal_id: Some(proto::SignalId {
+                        signal: Some(proto::signal_id::Signal::Id(entry_id)),
+                    }),
+                    data_point: Some(proto::Datapoint {
+                        timestamp: None,
+                        value: Some(proto::Value {
+                            typed_value: Some(proto::value::TypedValue::Uint32(value)),
+                        }),
+                    }),
+                    expected: Some(expected),
+                });
+                request
+                    .extensions_mut()
+                    .insert(permissions::ALLOW_ALL.clone());
+                request
+            };
+        let expected_value = |value: Option<u32>| {
+            proto::compare_and_publish_value_request::Expected::ExpectedValue(proto::Value {
+                typed_value: value.map(proto::value::TypedValue::Uint32),
+            })
+        };
+
+        // Nothing published yet
+        broker
+            .compare_and_publish_value(compare_and_publish_request(expected_value(None), 1))
+            .await
+            .expect("publish should succeed");
+
+        // Another coordinator still expects no value
+        match broker
+            .compare_and_publish_value(compare_and_publish_request(expected_value(None), 2))
+            .await
+        {
+            Ok(_) => panic!("Should not happen!"),
+            Err(status) => {
+                assert_eq!(status.code(), tonic::Code::Aborted);
+                assert_eq!(
+                    status.message(),
+                    format!("Value has changed (id: {})", entry_id)
+                );
+            }
+        }
+
+        broker
+            .compare_and_publish_value(compare_and_publish_request(expected_value(Some(1)), 2))
+            .await
+            .expect("publish should succeed");
+
+        let datapoint = authorized_access.get_datapoint(entry_id).await.unwrap();
+        assert_eq!(datapoint.value, broker::types::DataValue::Uint32(2));
+
+        // Timestamps as returned when reading the value
+        let stale = proto::compare_and_publish_value_request::Expected::ExpectedTimestamp(
+            (datapoint.ts - std::time::Duration::from_millis(1)).into(),
+        );
+        match broker
+            .compare_and_publish_value(compare_and_publish_request(stale, 3))
+            .await
+        {
+            Ok(_) => panic!("Should not happen!"),
+            Err(status) => assert_eq!(status.code(), tonic::Code::Aborted),
+        }
+
+        let current = proto::compare_and_publish_value_request::Expected::ExpectedTimestamp(
+            datapoint.ts.into(),
+        );
+        broker
+            .compare_and_publish_value(compare_and_publish_request(current, 3))
+            .await
+            .expect("publish should succeed");
+
+        let datapoint = authorized_access.get_datapoint(entry_id).await.unwrap();
+        assert_eq!(datapoint.value, broker::types::DataValue::Uint32(3));
+    }
+
+    #[tokio::test]
+    async fn test_publish_array_delta() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        let entry_id = authorized_access
+            .add_entry(
+                "test.cells".to_owned(),
+                broker::DataType::Uint32Array,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None,
+                Some(broker::types::DataValue::Uint32(10)),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let publish_request = |indices: Vec<u32>, values: proto::value::TypedValue| {
+            let mut request = tonic::Request::new(proto::PublishArrayDeltaRequest {
+                signal_id: Some(proto::SignalId {
+                    signal: Some(proto::signal_id::Signal::Id(entry_id)),
+                }),
+                delta: Some(proto::ArrayDelta {
+                    timestamp: None,
+                    indices,
+                    values: Some(proto::Value {
+                        typed_value: Some(values),
+                    }),
+                }),
+            });
+            request
+                .extensions_mut()
+                .insert(permissions::ALLOW_ALL.clone());
+            request
+        };
+        let uint32s =
+            |values: Vec<u32>| proto::value::TypedValue::Uint32Array(proto::Uint32Array { values });
+
+        // No elements to update yet
+        match broker
+            .publish_array_delta(publish_request(vec![0], uint32s(vec![1])))
+            .await
+        {
+            Ok(_) => panic!("Should not happen!"),
+            Err(status) => assert_eq!(status.code(), tonic::Code::InvalidArgument),
+        }
+
+        authorized_access
+            .update_entries([(
+                entry_id,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint {
+                        ts: std::time::SystemTime::now(),
+                        source_ts: None,
+                        value: broker::types::DataValue::Uint32Array(vec![1, 2, 3]),
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+
+        broker
+            .publish_array_delta(publish_request(vec![0, 2], uint32s(vec![5, 7])))
+            .await
+            .expect("publish should succeed");
+        let datapoint = authorized_access.get_datapoint(entry_id).await.unwrap();
+        assert_eq!(
+            datapoint.value,
+            broker::types::DataValue::Uint32Array(vec![5, 2, 7])
+        );
+
+        // Nothing is published if any of the elements is rejected
+        for (indices, values) in [
+            (vec![1, 3], uint32s(vec![4, 4])),
+            (vec![1, 2], uint32s(vec![4])),
+            (
+                vec![1],
+                proto::value::TypedValue::Int32Array(proto::Int32Array { values: vec![4] }),
+            ),
+            (vec![1, 2], uint32s(vec![4, 11])),
+        ] {
+            match broker
+                .publish_array_delta(publish_request(indices, values))
+                .await
+            {
+                Ok(_) => panic!("Should not happen!"),
+                Err(status) => assert_eq!(status.code(), tonic::Code::InvalidArgument),
+            }
+        }
+        let datapoint = authorized_access.get_datapoint(entry_id).await.unwrap();
+        assert_eq!(
+            datapoint.value,
+            broker::types::DataValue::Uint32Array(vec![5, 2, 7])
+        );
+    }
+
+    async fn publish_value(
+        broker: &DataBroker,
+        entry_id: i32,
+        input_value: Option<bool>,
+        input_timestamp: Option<std::time::SystemTime>,
+    ) {
+        let timestamp = input_timestamp.map(|input_timestamp| input_timestamp.into());
+
+        let mut request = tonic::Request::new(proto::PublishValueRequest {
+            signal_id: Some(proto::SignalId {
+                signal: Some(proto::signal_id::Signal::Id(entry_id)),
+            }),
+            data_point: Some(proto::Datapoint {
+                timestamp,
+
+                value: match input_value {
+                    Some(true) => Some(proto::Value {
+                        typed_value: Some(proto::value::TypedValue::Bool(true)),
+                    }),
+                    Some(false) => Some(proto::Value {
+                        typed_value: Some(proto::value::TypedValue::Bool(false)),
+                    }),
+                    None => None,
+                },
+            }),
+        });
+
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        match broker.publish_value(request).await {
+            Ok(response) => {
+                // Handle the successful response
+                let publish_response = response.into_inner();
+
+                // Check if there is an error in the response
+                assert_eq!(publish_response, proto::PublishValueResponse {});
+            }
+            Err(status) => {
+                // Handle the error from the publish_value function
+                panic!("Publish failed with status: {:?}", status);
+            }
+        }
+    }
+
+    /*
+        Test subscribe service method
+    */
+    async fn test_subscribe_case(has_value: bool) {
+        async fn check_stream_next(
+            item: &Result<proto::SubscribeResponse, tonic::Status>,
+            input_value: Option<bool>,
+        ) {
+            // Create Datapoint
+            let mut expected_response: HashMap<String, proto::Datapoint> = HashMap::new();
+            // We expect to get an empty response first
+            expected_response.insert(
+                "test.datapoint1".to_string(),
+                proto::Datapoint {
+                    timestamp: None,
+                    value: match input_value {
+                        Some(true) => Some(proto::Value {
+                            typed_value: Some(proto::value::TypedValue::Bool(true)),
+                        }),
+                        Some(false) => Some(proto::Value {
+                            typed_value: Some(proto::value::TypedValue::Bool(false)),
+                        }),
+                        None => None,
+                    },
+                },
+            );
 
             match item {
                 Ok(subscribe_response) => {
@@ -2005,6 +3315,10 @@ mod tests {
             signal_paths: vec!["test.datapoint1".to_string()],
             buffer_size: 5,
             filter: None,
+            changes_only: false,
+            delta_encoding: None,
+            backfill_since: None,
+            priority: 0,
         });
 
         request
@@ -2045,9 +3359,17 @@ mod tests {
                 match item_count {
                     0 => {
                         check_stream_next(&item, if has_value { Some(false) } else { None }).await;
+                        assert_eq!(
+                            item.as_ref().unwrap().reasons,
+                            vec![proto::UpdateReason::Initial as i32]
+                        );
                     }
                     1 => {
                         check_stream_next(&item, Some(true)).await;
+                        assert_eq!(
+                            item.as_ref().unwrap().reasons,
+                            vec![proto::UpdateReason::ValueChanged as i32]
+                        );
                     }
                     2 => {
                         // As long as value stays as false we do not get anything new, so prepare for None
@@ -2153,6 +3475,10 @@ mod tests {
             signal_ids: vec![entry_id],
             buffer_size: 5,
             filter: None,
+            changes_only: false,
+            delta_encoding: None,
+            backfill_since: None,
+            priority: 0,
         });
 
         request
@@ -2297,7 +3623,7 @@ mod tests {
                 tokio::spawn(async move {
                     std::thread::sleep(std::time::Duration::from_secs(3));
                     let stream = response.into_inner();
-                    let mut receiver = stream.into_inner();
+                    let mut receiver = stream.into_inner().into_inner();
                     while let Some(value) = receiver.recv().await {
                         match value {
                             Ok(value) => match value.action {
@@ -2329,6 +3655,10 @@ mod tests {
                                 Some(GetProviderValueRequest(_)) => {
                                     panic!("Should not happen")
                                 }
+                                Some(EnrollProviderChallenge(_))
+                                | Some(EnrollProviderResponse(_)) => {
+                                    panic!("Should not happen")
+                                }
                                 None => {
                                     panic!("Should not happen")
                                 }
@@ -2369,6 +3699,7 @@ mod tests {
         let mut data_req = tonic::Request::new(proto::ListMetadataRequest {
             root: "test.datapoint1".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         // Manually insert permissions
@@ -2440,26 +3771,31 @@ mod tests {
         let mut wildcard_req_two_asteriks = tonic::Request::new(proto::ListMetadataRequest {
             root: "test.**".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         let mut wildcard_req_one_asterik = tonic::Request::new(proto::ListMetadataRequest {
             root: "test.*".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         let mut no_wildcard_req_root = tonic::Request::new(proto::ListMetadataRequest {
             root: "test".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         let mut no_wildcard_req_branch = tonic::Request::new(proto::ListMetadataRequest {
             root: "test.branch".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         let mut empty_req = tonic::Request::new(proto::ListMetadataRequest {
             root: "".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         // Manually insert permissions
@@ -2539,6 +3875,84 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_list_metadata_paged() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+
+        for index in 0..5 {
+            authorized_access
+                .add_entry(
+                    format!("test.datapoint{index}"),
+                    broker::DataType::Bool,
+                    broker::ChangeType::OnChange,
+                    broker::EntryType::Sensor,
+                    "Test datapoint".to_owned(),
+                    None, // min
+                    None, // max
+                    None,
+                    None,
+                )
+                .await
+                .expect("Register datapoint should succeed");
+        }
+
+        let mut ids = Vec::new();
+        let mut page_token = String::new();
+        let mut pages = 0;
+        loop {
+            let mut request = tonic::Request::new(proto::ListMetadataRequest {
+                root: "test.**".to_owned(),
+                filter: "".to_owned(),
+                page_size: 2,
+                page_token: page_token.clone(),
+            });
+            request
+                .extensions_mut()
+                .insert(permissions::ALLOW_ALL.clone());
+
+            let response = proto::val_server::Val::list_metadata(&broker, request)
+                .await
+                .expect("list metadata should succeed")
+                .into_inner();
+            assert!(response.metadata.len() <= 2);
+            ids.extend(response.metadata.iter().map(|metadata| metadata.id));
+            pages += 1;
+            if response.next_page_token.is_empty() {
+                break;
+            }
+            page_token = response.next_page_token;
+        }
+
+        assert_eq!(pages, 3);
+        let mut sorted_ids = ids.clone();
+        sorted_ids.sort();
+        sorted_ids.dedup();
+        assert_eq!(ids, sorted_ids);
+        assert_eq!(ids.len(), 5);
+
+        let mut invalid_token_req = tonic::Request::new(proto::ListMetadataRequest {
+            root: "test.**".to_owned(),
+            filter: "".to_owned(),
+            page_size: 2,
+            page_token: "not a token".to_owned(),
+        });
+        invalid_token_req
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+
+        match proto::val_server::Val::list_metadata(&broker, invalid_token_req).await {
+            Ok(_) => panic!("Success not expected!"),
+            Err(error) => {
+                assert_eq!(
+                    error.code(),
+                    tonic::Code::InvalidArgument,
+                    "unexpected error code"
+                );
+            }
+        }
+    }
+
     #[tokio::test]
     async fn test_list_metadata_bad_request_pattern_or_not_found() {
         let broker = DataBroker::default();
@@ -2562,6 +3976,7 @@ mod tests {
         let mut wildcard_req = tonic::Request::new(proto::ListMetadataRequest {
             root: "test. **".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         // Manually insert permissions
@@ -2593,6 +4008,7 @@ mod tests {
         let mut not_found_req = tonic::Request::new(proto::ListMetadataRequest {
             root: "test.notfound".to_owned(),
             filter: "".to_owned(),
+            ..Default::default()
         });
 
         // Manually insert permissions
@@ -2798,6 +4214,98 @@ mod tests {
         result_response.expect("Result should be Ok");
     }
 
+    #[tokio::test]
+    async fn test_get_actuation_status() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let vss_id = authorized_access
+            .add_entry(
+                "Vehicle.ADAS.ABS.IsEnabled".to_owned(),
+                broker::DataType::Bool,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Actuator,
+                "Some funny description".to_owned(),
+                None, // min
+                None, // max
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let (sender, mut receiver) = mpsc::channel(10);
+        authorized_access
+            .provide_actuation(vec![vss_id], Box::new(Provider { sender }))
+            .await
+            .expect("Registering a new Actuation Provider should succeed");
+
+        let mut request = tonic::Request::new(ActuateRequest {
+            signal_id: Some(SignalId {
+                signal: Some(proto::signal_id::Signal::Id(vss_id)),
+            }),
+            value: Some(Value {
+                typed_value: Some(proto::value::TypedValue::Bool(true)),
+            }),
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let actuation_id = proto::val_server::Val::actuate(&broker, request)
+            .await
+            .expect("Actuation should succeed")
+            .into_inner()
+            .actuation_id;
+
+        // The provider gets the id along with the request
+        match receiver.recv().await.unwrap().unwrap().action {
+            Some(open_provider_stream_response::Action::BatchActuateStreamRequest(request)) => {
+                assert_eq!(request.actuation_ids, vec![actuation_id]);
+            }
+            other => panic!("Unexpected response {other:?}"),
+        }
+
+        let get_status = |actuation_id| {
+            let mut request =
+                tonic::Request::new(proto::GetActuationStatusRequest { actuation_id });
+            request
+                .extensions_mut()
+                .insert(permissions::ALLOW_ALL.clone());
+            proto::val_server::Val::get_actuation_status(&broker, request)
+        };
+        let response = get_status(actuation_id).await.unwrap().into_inner();
+        assert_eq!(response.status(), proto::ActuationStatus::Delivered);
+        assert_eq!(
+            response.signal_id.unwrap().signal,
+            Some(proto::signal_id::Signal::Id(vss_id))
+        );
+
+        report_actuation(
+            &authorized_access,
+            actuation_id,
+            proto::ActuationStatus::Executed,
+            None,
+        )
+        .await;
+        let response = get_status(actuation_id).await.unwrap().into_inner();
+        assert_eq!(response.status(), proto::ActuationStatus::Executed);
+
+        let err = get_status(actuation_id + 1).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+
+        // Only for clients that may read the actuator
+        let mut request = tonic::Request::new(proto::GetActuationStatusRequest { actuation_id });
+        request.extensions_mut().insert(
+            permissions::Permissions::builder()
+                .add_read_permission(permissions::Permission::Glob("Vehicle.Speed".to_owned()))
+                .build()
+                .unwrap(),
+        );
+        let err = proto::val_server::Val::get_actuation_status(&broker, request)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+
     #[tokio::test]
     async fn test_batch_actuate_out_of_range() {
         let broker = DataBroker::default();
@@ -3176,7 +4684,7 @@ mod tests {
         match proto::val_server::Val::open_provider_stream(&broker, streaming_request).await {
             Ok(response) => {
                 let stream = response.into_inner();
-                let mut receiver = stream.into_inner();
+                let mut receiver = stream.into_inner().into_inner();
                 let result_response = receiver
                     .recv()
                     .await
@@ -3232,7 +4740,7 @@ mod tests {
         match proto::val_server::Val::open_provider_stream(&broker, streaming_request).await {
             Ok(response) => {
                 let stream = response.into_inner();
-                let mut receiver = stream.into_inner();
+                let mut receiver = stream.into_inner().into_inner();
                 let result_response = receiver
                     .recv()
                     .await
@@ -3434,7 +4942,9 @@ mod tests {
 
         match proto::val_server::Val::actuate_stream(&broker, streaming_request).await {
             Ok(response) => {
-                assert!(response.into_inner().eq(&ActuateResponse {}));
+                assert!(response
+                    .into_inner()
+                    .eq(&ActuateResponse { actuation_id: 1 }));
             }
             Err(_) => {
                 panic!("Should not happen")
@@ -3447,6 +4957,7 @@ mod tests {
         let version = "1.1.1";
         let commit_hash = "3a3c332f5427f2db7a0b8582262c9f5089036c23";
         let broker = DataBroker::new(version, commit_hash);
+        broker.add_served_api("kuksa.val.v2");
 
         let request = tonic::Request::new(proto::GetServerInfoRequest {});
 
@@ -3458,10 +4969,737 @@ mod tests {
                 assert_eq!(response.name, "databroker");
                 assert_eq!(response.version, version);
                 assert_eq!(response.commit_hash, commit_hash);
+                let capabilities = response.capabilities.unwrap();
+                assert_eq!(capabilities.apis, vec!["kuksa.val.v2"]);
+                assert!(capabilities.wildcards);
+                assert_eq!(capabilities.max_message_size, MAX_MESSAGE_SIZE as u64);
+                assert_eq!(capabilities.vss_version, "");
+                assert!(!capabilities.history);
             }
             Err(_) => {
                 panic!("Should not happen")
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_get_server_info_with_history() {
+        let mut policies = crate::history::RetentionPolicies::default();
+        policies
+            .register(
+                "Vehicle.Speed",
+                vec![crate::history::Tier {
+                    keep: std::time::Duration::from_secs(60),
+                    interval: None,
+                    max_samples: None,
+                }],
+            )
+            .unwrap();
+        let broker = DataBroker::default().with_history(policies);
+
+        let request = tonic::Request::new(proto::GetServerInfoRequest {});
+        let response = proto::val_server::Val::get_server_info(&broker, request)
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.capabilities.unwrap().history);
+    }
+
+    #[tokio::test]
+    async fn test_dump_state() {
+        let broker = DataBroker::default();
+        broker::tests::helper_add_int32(
+            &broker,
+            "test.datapoint1",
+            -64,
+            std::time::SystemTime::now(),
+        )
+        .await
+        .expect("Shall succeed");
+
+        // Reading everything isn't enough
+        let reader = Permissions::builder()
+            .add_read_permission(permissions::Permission::All)
+            .build()
+            .unwrap();
+        let mut request = tonic::Request::new(proto::DumpStateRequest {});
+        request.extensions_mut().insert(reader);
+        match broker.dump_state(request).await {
+            Ok(_) => panic!("Did not expect success"),
+            Err(status) => assert_eq!(status.code(), tonic::Code::PermissionDenied),
+        }
+
+        let admin = Permissions::builder()
+            .add_admin_permission()
+            .build()
+            .unwrap();
+        let mut request = tonic::Request::new(proto::DumpStateRequest {});
+        request.extensions_mut().insert(admin);
+        let mut stream = broker.dump_state(request).await.unwrap().into_inner();
+        let mut dump = Vec::new();
+        while let Some(response) = stream.next().await {
+            dump.extend(response.unwrap().chunk);
+        }
+        let dump: serde_json::Value = serde_json::from_slice(&dump).unwrap();
+        assert_eq!(dump["entries"][0]["path"], "test.datapoint1");
+        assert_eq!(dump["entries"][0]["datapoint"]["value"], -64);
+    }
+
+    #[tokio::test]
+    async fn test_set_log_filter() {
+        let admin = Permissions::builder()
+            .add_admin_permission()
+            .build()
+            .unwrap();
+        let request = |filter: &str| {
+            let mut request = tonic::Request::new(proto::SetLogFilterRequest {
+                filter: filter.to_owned(),
+                timeout_seconds: 10,
+            });
+            request.extensions_mut().insert(admin.clone());
+            request
+        };
+
+        // Without a log filter to change
+        let broker = DataBroker::default();
+        let status = broker.set_log_filter(request("trace")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let log_filter =
+            crate::log_filter::LogFilter::new(&tracing_subscriber::EnvFilter::new("info"), |_| {
+                Ok(())
+            });
+        let broker = DataBroker::default().with_log_filter(log_filter.clone());
+        let response = broker.set_log_filter(request("trace")).await.unwrap();
+        assert_eq!(response.into_inner().filter, "trace");
+        assert_eq!(log_filter.current(), "trace");
+
+        let status = broker.set_log_filter(request("=loud")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let response = broker.set_log_filter(request("")).await.unwrap();
+        assert_eq!(response.into_inner().filter, "info");
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions() {
+        let broker = DataBroker::default();
+        broker::tests::helper_add_int32(&broker, "Vehicle.Speed", 50, std::time::SystemTime::now())
+            .await
+            .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        {
+            let broker = broker.clone();
+            tokio::spawn(async move {
+                let _ = crate::grpc::server::serve_with_incoming_shutdown(
+                    tokio_stream::wrappers::TcpListenerStream::new(listener),
+                    broker,
+                    #[cfg(feature = "tls")]
+                    crate::grpc::server::ServerTLS::Disabled,
+                    &[crate::grpc::server::Api::KuksaValV2],
+                    crate::authorization::Authorization::Disabled,
+                    std::future::pending(),
+                )
+                .await;
+            });
+        }
+
+        let mut client = proto::val_client::ValClient::connect(address)
+            .await
+            .unwrap();
+        let mut request = tonic::Request::new(proto::SubscribeRequest {
+            signal_paths: vec!["Vehicle.Speed".to_owned()],
+            ..Default::default()
+        });
+        request
+            .metadata_mut()
+            .insert("kuksa-client", "speedometer/1.0".parse().unwrap());
+        let _stream = client.subscribe(request).await.unwrap();
+
+        let sessions = client
+            .list_sessions(proto::ListSessionsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .sessions;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].client_name, "speedometer");
+        assert_eq!(sessions[0].client_version, "1.0");
+        assert!(sessions[0].peer.starts_with("127.0.0.1:"));
+        assert_eq!(sessions[0].subscriptions, 1);
+
+        // The subscription can be traced back to the session
+        let state = broker.get_state().await;
+        assert_eq!(state.subscriptions[0].session, Some(sessions[0].id));
+
+        let mut request = tonic::Request::new(proto::ListSessionsRequest {});
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_NONE.clone());
+        let status = broker.list_sessions(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_get_signal_stats() {
+        let broker = DataBroker::default();
+        for path in [
+            "Vehicle.Speed",
+            "Vehicle.Cabin.Dome",
+            "Vehicle.CabinTemperature",
+        ] {
+            broker::tests::helper_add_int32(&broker, path, 50, std::time::SystemTime::now())
+                .await
+                .unwrap();
+        }
+
+        let mut request = tonic::Request::new(proto::GetSignalStatsRequest {
+            root: "Vehicle.Cabin".to_owned(),
+            depth: 0,
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let stats = broker
+            .get_signal_stats(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .stats;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].path, "Vehicle.Cabin.Dome");
+        assert_eq!(stats[0].signals, 1);
+        assert_eq!(stats[0].updates, 1);
+
+        let mut request = tonic::Request::new(proto::GetSignalStatsRequest {
+            root: "".to_owned(),
+            depth: 1,
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let stats = broker
+            .get_signal_stats(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .stats;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].path, "Vehicle");
+        assert_eq!(stats[0].signals, 3);
+        assert_eq!(stats[0].updates, 3);
+
+        let mut request = tonic::Request::new(proto::GetSignalStatsRequest::default());
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_NONE.clone());
+        let status = broker.get_signal_stats(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_backfill() {
+        let mut policies = crate::history::RetentionPolicies::default();
+        policies
+            .register(
+                "Vehicle.Speed",
+                vec![crate::history::Tier {
+                    keep: std::time::Duration::from_secs(60),
+                    interval: None,
+                    max_samples: None,
+                }],
+            )
+            .unwrap();
+        let broker = DataBroker::default().with_history(policies);
+        let start = std::time::SystemTime::now();
+        let speed = broker::tests::helper_add_int32(&broker, "Vehicle.Speed", 10, start)
+            .await
+            .unwrap();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        for (value, secs) in [(20, 1), (30, 2)] {
+            authorized_access
+                .update_entries([(
+                    speed,
+                    broker::EntryUpdate {
+                        datapoint: Some(broker::Datapoint {
+                            ts: start + std::time::Duration::from_secs(secs),
+                            source_ts: None,
+                            value: broker::DataValue::Int32(value),
+                        }),
+                        ..Default::default()
+                    },
+                )])
+                .await
+                .unwrap();
+        }
+
+        let mut request = tonic::Request::new(proto::SubscribeRequest {
+            signal_paths: vec!["Vehicle.Speed".to_owned()],
+            buffer_size: 5,
+            changes_only: true,
+            backfill_since: Some(start.into()),
+            ..Default::default()
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let mut stream = broker.subscribe(request).await.unwrap().into_inner();
+        for value in [20, 30] {
+            let response = stream.next().await.unwrap().unwrap();
+            assert_eq!(response.reasons, vec![proto::UpdateReason::Backfill as i32]);
+            assert_eq!(
+                response.entries["Vehicle.Speed"].value,
+                Some(proto::Value {
+                    typed_value: Some(proto::value::TypedValue::Int32(value))
+                })
+            );
+        }
+
+        let mut request = tonic::Request::new(proto::SubscribeRequest {
+            signal_paths: vec!["Vehicle.Speed".to_owned()],
+            backfill_since: Some(prost_types::Timestamp {
+                seconds: i64::MIN,
+                nanos: 0,
+            }),
+            ..Default::default()
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let status = broker.subscribe(request).await.err().unwrap();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_get_latency_stats() {
+        let broker = DataBroker::default().with_latency_stats();
+        let speed = broker::tests::helper_add_int32(
+            &broker,
+            "Vehicle.Speed",
+            10,
+            std::time::SystemTime::now(),
+        )
+        .await
+        .unwrap();
+
+        let mut request = tonic::Request::new(proto::SubscribeRequest {
+            signal_paths: vec!["Vehicle.Speed".to_owned()],
+            changes_only: true,
+            ..Default::default()
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let mut stream = broker.subscribe(request).await.unwrap().into_inner();
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .update_entries([(
+                speed,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint {
+                        ts: std::time::SystemTime::now(),
+                        source_ts: None,
+                        value: broker::DataValue::Int32(20),
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+        stream.next().await.unwrap().unwrap();
+
+        let mut request = tonic::Request::new(proto::GetLatencyStatsRequest { reset: true });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let stages = broker
+            .get_latency_stats(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .stages;
+        let names: Vec<&str> = stages.iter().map(|stats| stats.stage.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "lock",
+                "validate",
+                "store",
+                "match",
+                "dispatch",
+                "deliver",
+                "end_to_end"
+            ]
+        );
+        for stats in &stages {
+            assert!(stats.count >= 1, "{}", stats.stage);
+            assert_eq!(
+                stats.buckets.iter().map(|bucket| bucket.count).sum::<u64>(),
+                stats.count
+            );
+            assert_eq!(stats.buckets.last().unwrap().upper_bound_us, 0);
+        }
+
+        // Reset
+        let mut request = tonic::Request::new(proto::GetLatencyStatsRequest::default());
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let stages = broker
+            .get_latency_stats(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .stages;
+        assert!(stages.iter().all(|stats| stats.count == 0));
+
+        let mut request = tonic::Request::new(proto::GetLatencyStatsRequest::default());
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_NONE.clone());
+        let status = broker.get_latency_stats(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let mut request = tonic::Request::new(proto::GetLatencyStatsRequest::default());
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let status = DataBroker::default()
+            .get_latency_stats(request)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_signal_group() {
+        let broker = DataBroker::default();
+        let ts = std::time::SystemTime::now();
+        let left = broker::tests::helper_add_int32(&broker, "Vehicle.Wheel.Left.Speed", 10, ts)
+            .await
+            .unwrap();
+        broker::tests::helper_add_int32(&broker, "Vehicle.Wheel.Right.Speed", 11, ts)
+            .await
+            .unwrap();
+
+        let define = |permissions: &Permissions, paths: &[&str]| {
+            let mut request = tonic::Request::new(proto::DefineSignalGroupRequest {
+                name: "WheelSpeeds".to_owned(),
+                signal_paths: paths.iter().map(|path| path.to_string()).collect(),
+            });
+            request.extensions_mut().insert(permissions.clone());
+            request
+        };
+        let paths = ["Vehicle.Wheel.Left.Speed", "Vehicle.Wheel.Right.Speed"];
+        let status = broker
+            .define_signal_group(define(&permissions::ALLOW_NONE, &paths))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let status = broker
+            .define_signal_group(define(&permissions::ALLOW_ALL, &["Vehicle.Nope"]))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        broker
+            .define_signal_group(define(&permissions::ALLOW_ALL, &paths))
+            .await
+            .unwrap();
+
+        let mut request = tonic::Request::new(proto::GetSignalGroupRequest {
+            name: "WheelSpeeds".to_owned(),
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let entries = broker
+            .get_signal_group(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .entries;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries["Vehicle.Wheel.Right.Speed"].value,
+            Some(proto::Value {
+                typed_value: Some(proto::value::TypedValue::Int32(11))
+            })
+        );
+
+        let mut request = tonic::Request::new(proto::SubscribeSignalGroupRequest {
+            name: "WheelSpeeds".to_owned(),
+            buffer_size: 5,
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let mut stream = broker
+            .subscribe_signal_group(request)
+            .await
+            .unwrap()
+            .into_inner();
+        let initial = stream.next().await.unwrap().unwrap();
+        assert_eq!(initial.entries.len(), 2);
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .update_entries([(
+                left,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint {
+                        ts: std::time::SystemTime::now(),
+                        source_ts: None,
+                        value: broker::DataValue::Int32(20),
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+        // All signals of the group, not just the changed one
+        let response = stream.next().await.unwrap().unwrap();
+        assert_eq!(response.entries.len(), 2);
+        assert_eq!(
+            response.entries["Vehicle.Wheel.Left.Speed"].value,
+            Some(proto::Value {
+                typed_value: Some(proto::value::TypedValue::Int32(20))
+            })
+        );
+
+        let mut request = tonic::Request::new(proto::GetSignalGroupRequest {
+            name: "Nope".to_owned(),
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+        let status = broker.get_signal_group(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_rejections() {
+        let broker = DataBroker::default();
+        let speed = broker::tests::helper_add_int32(
+            &broker,
+            "Vehicle.Speed",
+            10,
+            std::time::SystemTime::now(),
+        )
+        .await
+        .unwrap();
+        let feeder = Permissions::builder()
+            .subject("feeder")
+            .add_read_permission(permissions::Permission::Glob("Vehicle".to_owned()))
+            .add_provide_permission(permissions::Permission::Glob("Vehicle".to_owned()))
+            .build()
+            .unwrap();
+        let other = Permissions::builder().subject("other").build().unwrap();
+
+        let subscribe = |permissions: &Permissions| {
+            let mut request = tonic::Request::new(proto::SubscribeRejectionsRequest {});
+            request.extensions_mut().insert(permissions.clone());
+            broker.subscribe_rejections(request)
+        };
+        let mut own = subscribe(&feeder).await.unwrap().into_inner();
+        let mut all = subscribe(&permissions::ALLOW_ALL)
+            .await
+            .unwrap()
+            .into_inner();
+        let mut others = subscribe(&other).await.unwrap().into_inner();
+
+        let request = OpenProviderStreamRequest {
+            action: Some(open_provider_stream_request::Action::PublishValuesRequest(
+                PublishValuesRequest {
+                    request_id: 1,
+                    data_points: HashMap::from([(
+                        speed,
+                        proto::Datapoint {
+                            timestamp: None,
+                            value: Some(proto::Value {
+                                typed_value: Some(proto::value::TypedValue::Int32(2000)),
+                            }),
+                        },
+                    )]),
+                },
+            )),
+        };
+        let mut streaming_request = tonic_mock::streaming_request(vec![request]);
+        streaming_request.extensions_mut().insert(feeder.clone());
+        let mut responses = broker
+            .open_provider_stream(streaming_request)
+            .await
+            .unwrap()
+            .into_inner();
+        // The error response on the provider stream itself
+        responses.next().await.unwrap().unwrap();
+
+        for stream in [&mut own, &mut all] {
+            let rejection = stream.next().await.unwrap().unwrap();
+            assert_eq!(rejection.client, "feeder");
+            assert_eq!(rejection.signal_id, speed);
+            assert_eq!(rejection.path, "Vehicle.Speed");
+            assert_eq!(
+                rejection.data_point.unwrap().value.unwrap().typed_value,
+                Some(proto::value::TypedValue::Int32(2000))
+            );
+            assert_eq!(rejection.error.unwrap().message, "Out of Bounds MinMax");
+        }
+        // Only administrators get the rejections of other clients
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), others.next())
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            broker.get_provider_stats()[&Some("feeder".to_owned())].rejected,
+            1
+        );
+        assert_eq!(broker.get_usage().await.rejected_updates, 1);
+    }
+
+    #[tokio::test]
+    async fn test_exchange_token() {
+        let gateway = Permissions::builder()
+            .subject("gateway")
+            .add_read_permission(permissions::Permission::Glob("Vehicle".to_owned()))
+            .expires_at(std::time::SystemTime::now() + Duration::from_secs(600))
+            .build()
+            .unwrap();
+        let exchange_request = |scope: &str| {
+            let mut request = tonic::Request::new(proto::ExchangeTokenRequest {
+                scope: scope.to_owned(),
+                valid_for_seconds: 60,
+            });
+            request.extensions_mut().insert(gateway.clone());
+            request
+        };
+
+        let status = DataBroker::default()
+            .exchange_token(exchange_request("read:Vehicle.Speed"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        let token_exchange = crate::authorization::jwt::TokenExchange::generate().unwrap();
+        let broker = DataBroker::default().with_token_exchange(token_exchange.clone());
+        let response = broker
+            .exchange_token(exchange_request("read:Vehicle.Speed"))
+            .await
+            .unwrap()
+            .into_inner();
+        let claims =
+            crate::authorization::jwt::Decoder::new(crate::authorization::jwt::DEV_PUBLIC_KEY)
+                .unwrap()
+                .with_token_exchange(token_exchange)
+                .decode(&response.token)
+                .unwrap();
+        assert_eq!(claims.sub, "gateway");
+        assert_eq!(claims.scope, "read:Vehicle.Speed");
+        assert_eq!(response.expires_at.unwrap().seconds, claims.exp as i64);
+
+        let status = broker
+            .exchange_token(exchange_request("actuate:Vehicle.Speed"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        let status = broker
+            .exchange_token(exchange_request("read:vehicle"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    const PROVIDER_CREDENTIALS: &str = r#"[
+        { "provider_id": "speed", "secret": "0123456789abcdef", "paths": ["Vehicle.Speed"] }
+    ]"#;
+
+    #[tokio::test]
+    async fn test_enroll_provider() {
+        let enrollment =
+            crate::enrollment::parse_credentials_from_str(PROVIDER_CREDENTIALS).unwrap();
+
+        let mut challenge = None;
+        let response = enroll_provider(
+            Some(&enrollment),
+            &proto::EnrollProviderRequest {
+                provider_id: "speed".to_owned(),
+            },
+            &mut challenge,
+        )
+        .await
+        .unwrap();
+        let Some(EnrollProviderChallenge(issued)) = response.action else {
+            panic!("Expected a challenge");
+        };
+
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"0123456789abcdef");
+        let proof = proto::EnrollProviderProof {
+            signature: ring::hmac::sign(&key, &issued.nonce).as_ref().to_vec(),
+        };
+        let identity = verify_provider(Some(&enrollment), challenge.take(), &proof)
+            .await
+            .unwrap();
+        let permissions = identity.restrict(&permissions::ALLOW_ALL).unwrap();
+        assert!(permissions.can_write_datapoint("Vehicle.Speed").is_ok());
+        assert!(permissions.can_write_datapoint("Vehicle.Width").is_err());
+        let Some(EnrollProviderResponse(response)) = enrolled_response(identity).action else {
+            panic!("Expected an enrollment response");
+        };
+        assert_eq!(response.paths, vec!["Vehicle.Speed"]);
+
+        // The challenge is used up
+        assert_eq!(
+            verify_provider(Some(&enrollment), challenge.take(), &proof)
+                .await
+                .unwrap_err()
+                .code(),
+            tonic::Code::FailedPrecondition
+        );
+        // Enrollment isn't enabled
+        assert_eq!(
+            enroll_provider(
+                None,
+                &proto::EnrollProviderRequest {
+                    provider_id: "speed".to_owned(),
+                },
+                &mut challenge,
+            )
+            .await
+            .unwrap_err()
+            .code(),
+            tonic::Code::FailedPrecondition
+        );
+    }
+
+    #[tokio::test]
+    async fn test_provider_must_enroll() {
+        let enrollment =
+            crate::enrollment::parse_credentials_from_str(PROVIDER_CREDENTIALS).unwrap();
+        let broker = DataBroker::default().with_provider_enrollment(enrollment);
+
+        let request = OpenProviderStreamRequest {
+            action: Some(open_provider_stream_request::Action::PublishValuesRequest(
+                PublishValuesRequest {
+                    request_id: 1,
+                    data_points: HashMap::new(),
+                },
+            )),
+        };
+        let mut streaming_request = tonic_mock::streaming_request(vec![request]);
+        streaming_request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+
+        let mut receiver = broker
+            .open_provider_stream(streaming_request)
+            .await
+            .unwrap()
+            .into_inner()
+            .into_inner()
+            .into_inner();
+        match receiver.recv().await {
+            Some(Err(status)) => assert_eq!(status.code(), tonic::Code::PermissionDenied),
+            other => panic!("Expected the publish to be denied, got {:?}", other),
+        }
+    }
 }
@@ -13,6 +13,7 @@
 
 pub mod server;
 
+mod deadlines;
 mod kuksa_val_v1;
 mod kuksa_val_v2;
 mod sdv_databroker_v1;
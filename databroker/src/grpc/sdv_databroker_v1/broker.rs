@@ -19,10 +19,14 @@ use tokio_stream::{Stream, StreamExt};
 
 use std::collections::HashMap;
 use std::pin::Pin;
+use std::time::SystemTime;
 
-use crate::broker::{self, ReadError};
+use crate::broker::{self, QueryError, ReadError};
+use crate::grpc::deadlines::DeadlineStream;
 use crate::permissions::Permissions;
 
+use super::deprecated;
+
 use tracing::debug;
 
 #[tonic::async_trait]
@@ -81,7 +85,7 @@ impl proto::broker_server::Broker for broker::DataBroker {
 
             let reply = proto::GetDatapointsReply { datapoints };
 
-            Ok(Response::new(reply))
+            Ok(deprecated(Response::new(reply)))
         }
     }
 
@@ -161,7 +165,9 @@ impl proto::broker_server::Broker for broker::DataBroker {
             }
         }
 
-        Ok(Response::new(proto::SetDatapointsReply { errors }))
+        Ok(deprecated(Response::new(proto::SetDatapointsReply {
+            errors,
+        })))
     }
 
     type SubscribeStream =
@@ -181,13 +187,26 @@ impl proto::broker_server::Broker for broker::DataBroker {
         };
         let broker = self.authorized_access(&permissions);
 
-        let query = request.into_inner().query;
-        match broker.subscribe_query(&query).await {
+        let request = request.into_inner();
+        let initial_snapshot = if request.changes_only {
+            broker::InitialSnapshot::Skip
+        } else {
+            broker::InitialSnapshot::Send
+        };
+        match broker
+            .subscribe_query_with_snapshot(&request.query, initial_snapshot)
+            .await
+        {
             Ok(stream) => {
                 let stream = convert_to_proto_stream(stream);
                 debug!("Subscribed to new query");
-                Ok(Response::new(Box::pin(stream)))
+                let stream = DeadlineStream::new(stream, self.deadlines());
+                Ok(deprecated(Response::new(Box::pin(stream))))
             }
+            Err(QueryError::QuotaExceeded) => Err(Status::new(
+                Code::ResourceExhausted,
+                "Maximum number of subscriptions reached",
+            )),
             Err(e) => Err(Status::new(Code::InvalidArgument, format!("{e:?}"))),
         }
     }
@@ -226,7 +245,58 @@ impl proto::broker_server::Broker for broker::DataBroker {
                 .await
         };
         let reply = proto::GetMetadataReply { list };
-        Ok(Response::new(reply))
+        Ok(deprecated(Response::new(reply)))
+    }
+
+    async fn get_history(
+        &self,
+        request: tonic::Request<proto::GetHistoryRequest>,
+    ) -> Result<tonic::Response<proto::GetHistoryReply>, tonic::Status> {
+        debug!(?request);
+        let permissions = match request.extensions().get::<Permissions>() {
+            Some(permissions) => {
+                debug!(?permissions);
+                permissions.clone()
+            }
+            None => return Err(tonic::Status::unauthenticated("Unauthenticated")),
+        };
+        let broker = self.authorized_access(&permissions);
+
+        let request = request.into_inner();
+        let since = match request.since.map(SystemTime::try_from).transpose() {
+            Ok(since) => since,
+            Err(err) => {
+                return Err(Status::invalid_argument(format!("Invalid since: {err}")));
+            }
+        };
+        let Some(metadata) = broker.get_metadata_by_path(&request.name).await else {
+            return Err(Status::not_found(format!("{} not found", request.name)));
+        };
+        let history = match broker.get_history(metadata.id, since).await {
+            Ok(history) => history,
+            Err(ReadError::NotFound) => {
+                return Err(Status::not_found(format!("{} not found", request.name)))
+            }
+            Err(ReadError::PermissionDenied) => {
+                return Err(Status::permission_denied("Permission denied"))
+            }
+            Err(ReadError::PermissionExpired) => {
+                return Err(Status::unauthenticated("Permission expired"))
+            }
+        };
+        // The most recent values if limited
+        let skip = match request.limit {
+            0 => 0,
+            limit => history.len().saturating_sub(limit as usize),
+        };
+        let datapoints = history
+            .iter()
+            .skip(skip)
+            .map(proto::Datapoint::from)
+            .collect();
+        Ok(deprecated(Response::new(proto::GetHistoryReply {
+            datapoints,
+        })))
     }
 }
 
@@ -240,7 +310,121 @@ fn convert_to_proto_stream(
             let value = proto::Datapoint::from(&field);
             datapoints.insert(field.name, value);
         }
-        let notification = proto::SubscribeReply { fields: datapoints };
+        let notification = proto::SubscribeReply {
+            fields: datapoints,
+            reasons: vec![proto::UpdateReason::from(item.reason) as i32],
+        };
         Ok(notification)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::history::{RetentionPolicies, Tier};
+    use crate::permissions;
+    use crate::types::DataValue;
+    use proto::broker_server::Broker;
+
+    #[tokio::test]
+    async fn test_get_history() {
+        let mut policies = RetentionPolicies::default();
+        policies
+            .register(
+                "test.counter",
+                vec![Tier {
+                    keep: Duration::from_secs(60),
+                    interval: None,
+                    max_samples: None,
+                }],
+            )
+            .unwrap();
+        let broker = broker::DataBroker::default().with_history(policies);
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
+            .add_entry(
+                "test.counter".to_owned(),
+                broker::DataType::Uint32,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Sensor,
+                "Test counter".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let start = SystemTime::now();
+        for value in 1..=4 {
+            authorized_access
+                .update_entries([(
+                    id,
+                    broker::EntryUpdate {
+                        datapoint: Some(broker::Datapoint {
+                            ts: start + Duration::from_secs(value.into()),
+                            source_ts: None,
+                            value: DataValue::Uint32(value),
+                        }),
+                        ..Default::default()
+                    },
+                )])
+                .await
+                .unwrap();
+        }
+
+        let get_history = |name: &str, since: Option<SystemTime>, limit| {
+            let mut request = tonic::Request::new(proto::GetHistoryRequest {
+                name: name.to_owned(),
+                since: since.map(Into::into),
+                limit,
+            });
+            request
+                .extensions_mut()
+                .insert(permissions::ALLOW_ALL.clone());
+            request
+        };
+        let values = |reply: proto::GetHistoryReply| {
+            reply
+                .datapoints
+                .into_iter()
+                .map(|datapoint| datapoint.value)
+                .collect::<Vec<_>>()
+        };
+        let uint32 = |value| Some(proto::datapoint::Value::Uint32Value(value));
+
+        let reply = broker
+            .get_history(get_history("test.counter", None, 0))
+            .await
+            .unwrap();
+        assert_eq!(
+            values(reply.into_inner()),
+            vec![uint32(1), uint32(2), uint32(3), uint32(4)]
+        );
+
+        // The most recent values
+        let reply = broker
+            .get_history(get_history("test.counter", None, 2))
+            .await
+            .unwrap();
+        assert_eq!(values(reply.into_inner()), vec![uint32(3), uint32(4)]);
+
+        let since = Some(start + Duration::from_secs(2));
+        let reply = broker
+            .get_history(get_history("test.counter", since, 0))
+            .await
+            .unwrap();
+        assert_eq!(
+            values(reply.into_inner()),
+            vec![uint32(2), uint32(3), uint32(4)]
+        );
+
+        let status = broker
+            .get_history(get_history("test.unknown", None, 0))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+}
@@ -15,6 +15,7 @@ use std::collections::HashMap;
 
 use databroker_proto::sdv::databroker::v1 as proto;
 
+use prost::Message;
 use tokio::select;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
@@ -23,9 +24,12 @@ use tracing::debug;
 
 use crate::{
     broker::{self, RegistrationError},
+    grpc::deadlines::DeadlineStream,
     permissions::Permissions,
 };
 
+use super::deprecated;
+
 #[tonic::async_trait]
 impl proto::collector_server::Collector for broker::DataBroker {
     async fn update_datapoints(
@@ -79,10 +83,13 @@ impl proto::collector_server::Collector for broker::DataBroker {
             }
         }
 
-        Ok(Response::new(proto::UpdateDatapointsReply { errors }))
+        Ok(deprecated(Response::new(proto::UpdateDatapointsReply {
+            errors,
+        })))
     }
 
-    type StreamDatapointsStream = ReceiverStream<Result<proto::StreamDatapointsReply, Status>>;
+    type StreamDatapointsStream =
+        DeadlineStream<ReceiverStream<Result<proto::StreamDatapointsReply, Status>>>;
 
     async fn stream_datapoints(
         &self,
@@ -97,6 +104,13 @@ impl proto::collector_server::Collector for broker::DataBroker {
             None => return Err(tonic::Status::unauthenticated("Unauthenticated")),
         };
 
+        let provider_stream_guard = self
+            .authorized_access(&permissions)
+            .open_provider_stream()
+            .map_err(|_| {
+                tonic::Status::resource_exhausted("Maximum number of provider streams reached")
+            })?;
+
         let mut stream = request.into_inner();
 
         let mut shutdown_trigger = self.get_shutdown_trigger();
@@ -106,9 +120,13 @@ impl proto::collector_server::Collector for broker::DataBroker {
 
         // Create error stream (to be returned)
         let (error_sender, error_receiver) = mpsc::channel(10);
+        let error_stream =
+            DeadlineStream::new(ReceiverStream::new(error_receiver), self.deadlines());
+        let activity = error_stream.activity();
 
         // Listening on stream
         tokio::spawn(async move {
+            let mut provider_stream_guard = provider_stream_guard;
             let permissions = permissions;
             let broker = broker.authorized_access(&permissions);
             loop {
@@ -116,8 +134,22 @@ impl proto::collector_server::Collector for broker::DataBroker {
                     message = stream.message() => {
                         match message {
                             Ok(request) => {
+                                activity.touch();
                                 match request {
                                     Some(req) => {
+                                        if provider_stream_guard.admit(req.datapoints.len(), req.encoded_len()).is_err() {
+                                            if let Err(err) = error_sender.send(
+                                                Ok(proto::StreamDatapointsReply {
+                                                    errors: req.datapoints.keys().map(|id| {
+                                                        (*id, proto::DatapointError::Throttled as i32)
+                                                    }).collect(),
+                                                    sequence_number: req.sequence_number,
+                                                })
+                                            ).await {
+                                                debug!("Failed to send errors: {}", err);
+                                            }
+                                            continue;
+                                        }
                                         let ids: Vec<(i32, broker::EntryUpdate)> = req.datapoints
                                             .iter()
                                             .map(|(id, datapoint)|
@@ -139,21 +171,23 @@ impl proto::collector_server::Collector for broker::DataBroker {
                                             )
                                             .collect();
                                         // TODO: Check if sender is allowed to provide datapoint with this id
-                                        match broker
+                                        let errors = match broker
                                             .update_entries(ids)
                                             .await
                                         {
-                                            Ok(_) => {}
-                                            Err(err) => {
-                                                if let Err(err) = error_sender.send(
-                                                    Ok(proto::StreamDatapointsReply {
-                                                        errors: err.iter().map(|(id, error)| {
-                                                            (*id, proto::DatapointError::from(error) as i32)
-                                                        }).collect(),
-                                                    })
-                                                ).await {
-                                                    debug!("Failed to send errors: {}", err);
-                                                }
+                                            Ok(_) => HashMap::new(),
+                                            Err(err) => err.iter().map(|(id, error)| {
+                                                (*id, proto::DatapointError::from(error) as i32)
+                                            }).collect(),
+                                        };
+                                        if req.ack || !errors.is_empty() {
+                                            if let Err(err) = error_sender.send(
+                                                Ok(proto::StreamDatapointsReply {
+                                                    errors,
+                                                    sequence_number: req.sequence_number,
+                                                })
+                                            ).await {
+                                                debug!("Failed to send errors: {}", err);
                                             }
                                         }
                                     },
@@ -178,7 +212,7 @@ impl proto::collector_server::Collector for broker::DataBroker {
         });
 
         // Return the error stream
-        Ok(Response::new(ReceiverStream::new(error_receiver)))
+        Ok(deprecated(Response::new(error_stream)))
     }
 
     async fn register_datapoints(
@@ -243,6 +277,16 @@ impl proto::collector_server::Collector for broker::DataBroker {
                             ));
                             break;
                         }
+                        Err(RegistrationError::QuotaExceeded) => {
+                            error = Some(Status::new(
+                                Code::ResourceExhausted,
+                                format!(
+                                    "Failed to register {}, maximum number of entries reached",
+                                    metadata.name
+                                ),
+                            ));
+                            break;
+                        }
                     };
                 }
                 (Err(_), _) => {
@@ -266,7 +310,9 @@ impl proto::collector_server::Collector for broker::DataBroker {
 
         match error {
             Some(error) => Err(error),
-            None => Ok(Response::new(proto::RegisterDatapointsReply { results })),
+            None => Ok(deprecated(Response::new(proto::RegisterDatapointsReply {
+                results,
+            }))),
         }
     }
 }
@@ -351,4 +397,95 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_stream_datapoints_ack() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let entry_id = authorized_access
+            .add_entry(
+                "test.datapoint1".to_owned(),
+                broker::DataType::Int32,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Sensor,
+                "Test datapoint 1".to_owned(),
+                None,                                      // min
+                Some(broker::types::DataValue::Int32(10)), // max
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let request = |sequence_number, value, ack| proto::StreamDatapointsRequest {
+            datapoints: HashMap::from([(
+                entry_id,
+                proto::Datapoint {
+                    timestamp: None,
+                    value: Some(proto::datapoint::Value::Int32Value(value)),
+                },
+            )]),
+            sequence_number,
+            ack,
+        };
+        let mut streaming_request = tonic_mock::streaming_request(vec![
+            request(1, 1, true),
+            // Without ack, only errors are replied to
+            request(2, 2, false),
+            request(3, 100, false),
+            request(4, 100, true),
+            request(5, 5, true),
+        ]);
+        streaming_request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+
+        let mut replies = broker
+            .stream_datapoints(streaming_request)
+            .await
+            .unwrap()
+            .into_inner()
+            .into_inner()
+            .into_inner();
+        let mut received = Vec::new();
+        while let Some(reply) = replies.recv().await {
+            let reply = reply.unwrap();
+            received.push((reply.sequence_number, reply.errors));
+        }
+        let out_of_bounds = HashMap::from([(entry_id, proto::DatapointError::OutOfBounds as i32)]);
+        assert_eq!(
+            received,
+            vec![
+                (1, HashMap::new()),
+                (3, out_of_bounds.clone()),
+                (4, out_of_bounds),
+                (5, HashMap::new()),
+            ]
+        );
+        assert_eq!(
+            authorized_access
+                .get_datapoint(entry_id)
+                .await
+                .unwrap()
+                .value,
+            broker::types::DataValue::Int32(5)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deprecation_header() {
+        let broker = DataBroker::default();
+        let mut request = tonic::Request::new(proto::UpdateDatapointsRequest {
+            datapoints: HashMap::new(),
+        });
+        request
+            .extensions_mut()
+            .insert(permissions::ALLOW_ALL.clone());
+
+        let response = broker.update_datapoints(request).await.unwrap();
+        assert_eq!(
+            response.metadata().get("deprecation").unwrap(),
+            crate::grpc::sdv_databroker_v1::DEPRECATION_NOTICE
+        );
+    }
 }
@@ -293,12 +293,26 @@ impl From<&broker::EntryType> for proto::EntryType {
     }
 }
 
+impl From<broker::UpdateReason> for proto::UpdateReason {
+    fn from(from: broker::UpdateReason) -> Self {
+        match from {
+            broker::UpdateReason::Initial => proto::UpdateReason::Initial,
+            // Not sent over this API
+            broker::UpdateReason::Backfill => proto::UpdateReason::ValueChanged,
+            broker::UpdateReason::ValueChanged => proto::UpdateReason::ValueChanged,
+            broker::UpdateReason::TargetChanged => proto::UpdateReason::TargetChanged,
+            broker::UpdateReason::MetadataChanged => proto::UpdateReason::MetadataChanged,
+        }
+    }
+}
+
 impl From<&proto::ChangeType> for broker::ChangeType {
     fn from(change_type: &proto::ChangeType) -> Self {
         match change_type {
             proto::ChangeType::OnChange => broker::ChangeType::OnChange,
             proto::ChangeType::Continuous => broker::ChangeType::Continuous,
             proto::ChangeType::Static => broker::ChangeType::Static,
+            proto::ChangeType::Event => broker::ChangeType::Event,
         }
     }
 }
@@ -414,8 +428,12 @@ impl From<&broker::UpdateError> for proto::DatapointError {
             broker::UpdateError::OutOfBoundsAllowed => proto::DatapointError::OutOfBounds,
             broker::UpdateError::OutOfBoundsMinMax => proto::DatapointError::OutOfBounds,
             broker::UpdateError::OutOfBoundsType => proto::DatapointError::OutOfBounds,
+            broker::UpdateError::OutOfBoundsIndex => proto::DatapointError::OutOfBounds,
             broker::UpdateError::PermissionDenied => proto::DatapointError::AccessDenied,
             broker::UpdateError::PermissionExpired => proto::DatapointError::AccessDenied,
+            broker::UpdateError::Conflict => proto::DatapointError::InternalError,
+            broker::UpdateError::Implausible(_) => proto::DatapointError::OutOfBounds,
+            broker::UpdateError::PolicyViolation(_) => proto::DatapointError::AccessDenied,
         }
     }
 }
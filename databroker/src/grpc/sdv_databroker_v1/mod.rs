@@ -14,3 +14,15 @@
 mod broker;
 mod collector;
 mod conversions;
+
+/// Attached to every response as `deprecation` header.
+const DEPRECATION_NOTICE: &str =
+    "sdv.databroker.v1 is deprecated and will be removed, migrate to kuksa.val.v2";
+
+fn deprecated<T>(mut response: tonic::Response<T>) -> tonic::Response<T> {
+    response.metadata_mut().insert(
+        "deprecation",
+        tonic::metadata::MetadataValue::from_static(DEPRECATION_NOTICE),
+    );
+    response
+}
@@ -11,44 +11,189 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
-use std::{convert::TryFrom, future::Future};
+use std::{
+    convert::TryFrom,
+    future::Future,
+    io,
+    os::unix::fs::PermissionsExt,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
 
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use socket2::{Domain, Protocol, Socket, Type};
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
-    net::UnixListener,
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpStream, UnixListener, UnixStream},
 };
 use tokio_stream::wrappers::{TcpListenerStream, UnixListenerStream};
-#[cfg(feature = "tls")]
-use tonic::transport::ServerTlsConfig;
+use tonic::codegen::http;
 use tonic::transport::{server::Connected, Server};
-use tracing::{debug, info};
+#[cfg(feature = "tls")]
+use tonic::transport::{server::TlsConnectInfo, ServerTlsConfig};
+use tracing::{debug, info, warn};
 
 use databroker_proto::{kuksa, sdv};
 
 use crate::{
     authorization::Authorization,
     broker,
+    call_stats::CallStats,
     permissions::{self, Permissions},
+    sessions::{Session, CLIENT_HEADER},
 };
 
 // https://www.linuxjournal.com/files/linuxjournal.com/linuxjournal/articles/023/2333/2333s2.html
 const MAX_ACCEPT_QUEUE_SIZE: i32 = 128;
 
+/// Max size of a received message in bytes, tonic's default.
+pub const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
 #[cfg(feature = "tls")]
 pub enum ServerTLS {
     Disabled,
     Enabled { tls_config: ServerTlsConfig },
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum Api {
     KuksaValV1,
     KuksaValV2,
     SdvDatabrokerV1,
 }
 
+impl Api {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Api::KuksaValV1 => "kuksa.val.v1",
+            Api::KuksaValV2 => "kuksa.val.v2",
+            Api::SdvDatabrokerV1 => "sdv.databroker.v1",
+        }
+    }
+
+    /// Deprecated APIs are still served, but will be removed.
+    pub fn is_deprecated(&self) -> bool {
+        matches!(self, Api::SdvDatabrokerV1)
+    }
+}
+
+/// Connections that can tell who is on the other end, for the session
+/// registry.
+pub trait Peer {
+    fn peer(&self) -> Option<String>;
+}
+
+impl Peer for TcpStream {
+    fn peer(&self) -> Option<String> {
+        self.peer_addr().ok().map(|addr| addr.to_string())
+    }
+}
+
+impl Peer for UnixStream {
+    fn peer(&self) -> Option<String> {
+        self.peer_cred()
+            .ok()
+            .and_then(|cred| cred.pid())
+            .map(|pid| format!("pid {pid}"))
+    }
+}
+
+/// A connection registered as session for as long as it is open.
+pub struct SessionIo<IO> {
+    io: IO,
+    session: Session,
+}
+
+/// The connect info of a [`SessionIo`], available to the interceptors.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionConnectInfo {
+    pub session: u64,
+}
+
+impl<IO> Connected for SessionIo<IO> {
+    type ConnectInfo = SessionConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        SessionConnectInfo {
+            session: self.session.id(),
+        }
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for SessionIo<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for SessionIo<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+/// The session of the connection `request` was received on.
+fn session_of<T>(request: &tonic::Request<T>) -> Option<u64> {
+    let extensions = request.extensions();
+    let info = extensions.get::<SessionConnectInfo>().copied();
+    #[cfg(feature = "tls")]
+    let info = info.or_else(|| {
+        extensions
+            .get::<TlsConnectInfo<SessionConnectInfo>>()
+            .map(|info| *info.get_ref())
+    });
+    info.map(|info| info.session)
+}
+
+/// Authorizes the calls to an API and counts them per client.
+#[derive(Clone)]
+struct ApiInterceptor {
+    api: Api,
+    authorization: Authorization,
+    broker: broker::DataBroker,
+}
+
+impl ApiInterceptor {
+    fn new(api: Api, authorization: &Authorization, broker: &broker::DataBroker) -> Self {
+        broker.add_served_api(api.name());
+        Self {
+            api,
+            authorization: authorization.clone(),
+            broker: broker.clone(),
+        }
+    }
+}
+
 impl tonic::service::Interceptor for Authorization {
     fn call(
         &mut self,
@@ -93,6 +238,130 @@ impl tonic::service::Interceptor for Authorization {
     }
 }
 
+impl tonic::service::Interceptor for ApiInterceptor {
+    fn call(&mut self, request: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        let mut request = tonic::service::Interceptor::call(&mut self.authorization, request)
+            .inspect_err(|_| self.broker.record_authorization_failure())?;
+        if let Some(session) = session_of(&request) {
+            let declared = request
+                .metadata()
+                .get(CLIENT_HEADER)
+                .and_then(|value| value.to_str().ok());
+            let subject = request
+                .extensions()
+                .get::<Permissions>()
+                .and_then(|permissions| permissions.subject());
+            self.broker.sessions().identify(session, declared, subject);
+            if let Some(permissions) = request.extensions_mut().get_mut::<Permissions>() {
+                permissions.set_session(session);
+            }
+        }
+        let client = request
+            .extensions()
+            .get::<Permissions>()
+            .and_then(|permissions| permissions.subject());
+        let calls = self
+            .broker
+            .record_api_call(self.api.name(), self.api.is_deprecated(), client);
+        if calls == 1 && self.api.is_deprecated() {
+            warn!(
+                "Client {} uses the deprecated {} API",
+                client.unwrap_or("<anonymous>"),
+                self.api.name()
+            );
+        }
+        Ok(request)
+    }
+}
+
+/// Records the status code and duration of every call in the
+/// [`CallStats`] of the broker.
+#[derive(Clone)]
+struct CallStatsLayer {
+    stats: Arc<CallStats>,
+}
+
+impl<S> tower::Layer<S> for CallStatsLayer {
+    type Service = CallStatsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CallStatsService {
+            inner,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CallStatsService<S> {
+    inner: S,
+    stats: Arc<CallStats>,
+}
+
+/// A call in progress, recorded when dropped. Calls dropped before they
+/// were answered, e.g. as they timed out, count as cancelled.
+struct PendingCall {
+    stats: Arc<CallStats>,
+    method: String,
+    start: Instant,
+    code: tonic::Code,
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        // Unknown methods aren't told apart, as clients choose their names
+        let method = match self.code {
+            tonic::Code::Unimplemented => "other",
+            _ => &self.method,
+        };
+        self.stats.record(method, self.code, self.start.elapsed());
+    }
+}
+
+/// The status code of a response. Calls that fail before responding carry
+/// it in the headers, the others are counted as successful.
+fn status_code(headers: &http::HeaderMap) -> tonic::Code {
+    headers
+        .get("grpc-status")
+        .and_then(|status| status.to_str().ok())
+        .and_then(|status| status.parse::<i32>().ok())
+        .map_or(tonic::Code::Ok, tonic::Code::from_i32)
+}
+
+impl<S, ReqBody, ResBody> tower::Service<http::Request<ReqBody>> for CallStatsService<S>
+where
+    S: tower::Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let call = PendingCall {
+            stats: self.stats.clone(),
+            method: request.uri().path().to_owned(),
+            start: Instant::now(),
+            code: tonic::Code::Cancelled,
+        };
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            // Moved as a whole, to be dropped when the future is
+            let mut call = call;
+            let response = response.await;
+            call.code = match &response {
+                Ok(response) => status_code(response.headers()),
+                Err(_) => tonic::Code::Internal,
+            };
+            response
+        })
+    }
+}
+
 async fn shutdown<F>(databroker: broker::DataBroker, signal: F)
 where
     F: Future<Output = ()>,
@@ -155,8 +424,20 @@ where
     .await
 }
 
+/// Who may connect to the unix socket, besides the owner. Unset fields are
+/// left as the socket is created, i.e. by the umask and the group of the
+/// process.
+#[derive(Clone, Debug, Default)]
+pub struct UnixSocketPermissions {
+    /// Permission bits of the socket, e.g. 0o660 to let the group connect
+    pub mode: Option<u32>,
+    /// Group owning the socket
+    pub group: Option<u32>,
+}
+
 pub async fn serve_uds<F>(
     path: impl AsRef<std::path::Path>,
+    permissions: &UnixSocketPermissions,
     broker: broker::DataBroker,
     apis: &[Api],
     authorization: Authorization,
@@ -165,7 +446,13 @@ pub async fn serve_uds<F>(
 where
     F: Future<Output = ()>,
 {
-    let listener = UnixListener::bind(path)?;
+    let listener = UnixListener::bind(&path)?;
+    if let Some(group) = permissions.group {
+        std::os::unix::fs::chown(&path, None, Some(group))?;
+    }
+    if let Some(mode) = permissions.mode {
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+    }
 
     if let Ok(addr) = listener.local_addr() {
         match addr.as_pathname() {
@@ -199,17 +486,31 @@ pub async fn serve_with_incoming_shutdown<F, I, IO, IE>(
 where
     F: Future<Output = ()>,
     I: Stream<Item = Result<IO, IE>>,
-    IO: AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static,
-    IO::ConnectInfo: Clone + Send + Sync + 'static,
+    IO: AsyncRead + AsyncWrite + Peer + Unpin + Send + 'static,
     IE: Into<Box<dyn std::error::Error + Send + Sync>>,
 {
     broker.start_housekeeping_task();
 
+    let sessions = broker.sessions().clone();
+    let incoming = incoming.map(move |io| {
+        io.map(|io| SessionIo {
+            session: sessions.open(io.peer()),
+            io,
+        })
+    });
+
     let mut server = Server::builder()
         .http2_adaptive_window(Some(true))
         .http2_keepalive_interval(None)
         .http2_keepalive_timeout(None);
 
+    if let Some(timeout) = broker.deadlines().request_timeout {
+        server = server.timeout(timeout);
+    }
+    let mut server = server.layer(CallStatsLayer {
+        stats: broker.call_stats().clone(),
+    });
+
     #[cfg(feature = "tls")]
     match server_tls {
         ServerTLS::Enabled { tls_config } => {
@@ -229,7 +530,7 @@ where
         if apis.contains(&Api::KuksaValV1) {
             Some(kuksa::val::v1::val_server::ValServer::with_interceptor(
                 broker.clone(),
-                authorization.clone(),
+                ApiInterceptor::new(Api::KuksaValV1, &authorization, &broker),
             ))
         } else {
             None
@@ -247,7 +548,7 @@ where
         router = router.add_optional_service(Some(
             kuksa::val::v2::val_server::ValServer::with_interceptor(
                 broker.clone(),
-                authorization.clone(),
+                ApiInterceptor::new(Api::KuksaValV2, &authorization, &broker),
             ),
         ));
     }
@@ -256,16 +557,17 @@ where
         reflection_builder = reflection_builder
             .register_encoded_file_descriptor_set(sdv::databroker::v1::FILE_DESCRIPTOR_SET);
 
+        let interceptor = ApiInterceptor::new(Api::SdvDatabrokerV1, &authorization, &broker);
         router = router.add_optional_service(Some(
             sdv::databroker::v1::broker_server::BrokerServer::with_interceptor(
                 broker.clone(),
-                authorization.clone(),
+                interceptor.clone(),
             ),
         ));
         router = router.add_optional_service(Some(
             sdv::databroker::v1::collector_server::CollectorServer::with_interceptor(
                 broker.clone(),
-                authorization,
+                interceptor,
             ),
         ));
     }
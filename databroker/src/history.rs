@@ -0,0 +1,442 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Past values of entries, kept in memory for diagnostics.
+//!
+//! Which entries keep a history, and for how long, is decided by retention
+//! policies per path pattern. A policy consists of tiers, each keeping
+//! samples for a while: the raw values, or one sample per interval (the
+//! latest one) to cover a longer time with bounded memory:
+//!
+//! ```json
+//! [
+//!   { "path": "Vehicle.Speed",
+//!     "tiers": [ { "keep_ms": 600000, "max_samples": 10000 },
+//!                { "keep_ms": 86400000, "interval_ms": 60000 } ] },
+//!   { "path": "Vehicle.Powertrain.**", "tiers": [ { "keep_ms": 3600000, "interval_ms": 1000 } ] }
+//! ]
+//! ```
+//!
+//! The first policy matching a path applies. Every tier is bounded by
+//! `max_samples` if given; a downsampled tier holds at most one sample per
+//! interval anyway.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+
+use crate::broker::Datapoint;
+use crate::glob;
+
+/// How long samples are kept, at which resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tier {
+    pub keep: Duration,
+    /// Keep only the latest sample of each interval, `None` to keep all.
+    pub interval: Option<Duration>,
+    pub max_samples: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse history config: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid history config: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The tiers of history to keep, by path pattern.
+#[derive(Default)]
+pub struct RetentionPolicies {
+    policies: Vec<(glob::Matcher, Arc<[Tier]>)>,
+}
+
+impl fmt::Debug for RetentionPolicies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.policies.iter().map(|(matcher, _)| matcher.as_string()))
+            .finish()
+    }
+}
+
+impl RetentionPolicies {
+    /// Keep the history of the paths matching `pattern` (and no earlier
+    /// registered pattern) in `tiers`.
+    pub fn register(&mut self, pattern: &str, tiers: Vec<Tier>) -> Result<(), Error> {
+        let matcher = glob::Matcher::new(pattern)
+            .map_err(|_| Error::InvalidConfig(format!("invalid path pattern '{pattern}'")))?;
+        if tiers.is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "no tiers given for '{pattern}'"
+            )));
+        }
+        self.policies.push((matcher, tiers.into()));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    fn tiers(&self, path: &str) -> Option<&Arc<[Tier]>> {
+        self.policies
+            .iter()
+            .find(|(matcher, _)| matcher.is_match_vss_path(path))
+            .map(|(_, tiers)| tiers)
+    }
+}
+
+struct Buffer {
+    tier: Tier,
+    // Oldest first
+    samples: VecDeque<Datapoint>,
+}
+
+impl Buffer {
+    fn push(&mut self, datapoint: &Datapoint) {
+        if let (Some(interval), Some(last)) = (self.tier.interval, self.samples.back_mut()) {
+            if bucket(last.ts, interval) == bucket(datapoint.ts, interval) {
+                *last = datapoint.clone();
+                return;
+            }
+        }
+        self.samples.push_back(datapoint.clone());
+        if let Some(max_samples) = self.tier.max_samples {
+            while self.samples.len() > max_samples {
+                self.samples.pop_front();
+            }
+        }
+    }
+
+    fn expire(&mut self, now: SystemTime) {
+        let Some(oldest) = now.checked_sub(self.tier.keep) else {
+            return;
+        };
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| sample.ts < oldest)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+fn bucket(ts: SystemTime, interval: Duration) -> u128 {
+    ts.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        / interval.as_nanos().max(1)
+}
+
+/// The past values of the entries with a retention policy.
+#[derive(Default)]
+pub struct History {
+    policies: RetentionPolicies,
+    // Finest tier first, per entry id
+    buffers: HashMap<i32, Vec<Buffer>>,
+}
+
+impl History {
+    pub fn new(policies: RetentionPolicies) -> Self {
+        History {
+            policies,
+            buffers: HashMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.policies.is_empty()
+    }
+
+    /// Add a new value of the entry `id` at `path`, and drop the samples
+    /// of it that are past their retention.
+    pub fn record(&mut self, id: i32, path: &str, datapoint: &Datapoint) {
+        if !self.buffers.contains_key(&id) {
+            let Some(tiers) = self.policies.tiers(path) else {
+                return;
+            };
+            let mut tiers = tiers.to_vec();
+            tiers.sort_by_key(|tier| tier.interval.unwrap_or_default());
+            let buffers = tiers
+                .into_iter()
+                .map(|tier| Buffer {
+                    tier,
+                    samples: VecDeque::new(),
+                })
+                .collect();
+            self.buffers.insert(id, buffers);
+        }
+        if let Some(buffers) = self.buffers.get_mut(&id) {
+            for buffer in buffers {
+                buffer.push(datapoint);
+                buffer.expire(datapoint.ts);
+            }
+        }
+    }
+
+    /// Drop the samples of all entries past their retention at `now`.
+    pub fn expire(&mut self, now: SystemTime) {
+        for buffers in self.buffers.values_mut() {
+            for buffer in buffers {
+                buffer.expire(now);
+            }
+        }
+    }
+
+    /// The kept values of the entry `id` since `since` (or all), oldest
+    /// first. Recent values come from the finest tier, older ones from the
+    /// coarser tiers covering the time before.
+    pub fn samples(&self, id: i32, since: Option<SystemTime>) -> Vec<Datapoint> {
+        let Some(buffers) = self.buffers.get(&id) else {
+            return Vec::new();
+        };
+        let mut samples: Vec<Datapoint> = Vec::new();
+        let mut covered_from: Option<SystemTime> = None;
+        for buffer in buffers {
+            let older = buffer
+                .samples
+                .iter()
+                .filter(|sample| covered_from.is_none_or(|from| sample.ts < from))
+                .filter(|sample| since.is_none_or(|since| sample.ts >= since));
+            let mut older: Vec<Datapoint> = older.cloned().collect();
+            if let Some(first) = buffer.samples.front() {
+                covered_from = Some(covered_from.map_or(first.ts, |from| from.min(first.ts)));
+            }
+            older.append(&mut samples);
+            samples = older;
+        }
+        samples
+    }
+
+    /// Number of samples kept over all entries.
+    pub fn len(&self) -> usize {
+        self.buffers
+            .values()
+            .flatten()
+            .map(|buffer| buffer.samples.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TierConfig {
+    keep_ms: u64,
+    #[serde(default)]
+    interval_ms: Option<u64>,
+    #[serde(default)]
+    max_samples: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+    path: String,
+    tiers: Vec<TierConfig>,
+}
+
+pub fn parse_config_from_str(data: &str) -> Result<RetentionPolicies, Error> {
+    let rules: Vec<Rule> =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    let mut policies = RetentionPolicies::default();
+    for rule in rules {
+        let mut tiers = Vec::with_capacity(rule.tiers.len());
+        for tier in rule.tiers {
+            if tier.keep_ms == 0 {
+                return Err(Error::InvalidConfig(
+                    "keep_ms must be greater than 0".to_owned(),
+                ));
+            }
+            if tier.interval_ms == Some(0) {
+                return Err(Error::InvalidConfig(
+                    "interval_ms must be greater than 0".to_owned(),
+                ));
+            }
+            if tier.max_samples == Some(0) {
+                return Err(Error::InvalidConfig(
+                    "max_samples must be greater than 0".to_owned(),
+                ));
+            }
+            tiers.push(Tier {
+                keep: Duration::from_millis(tier.keep_ms),
+                interval: tier.interval_ms.map(Duration::from_millis),
+                max_samples: tier.max_samples,
+            });
+        }
+        policies.register(&rule.path, tiers)?;
+    }
+    Ok(policies)
+}
+
+pub fn parse_config_from_reader<R: Read>(mut reader: R) -> Result<RetentionPolicies, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_config_from_str(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DataValue;
+
+    fn sample(value: i32, ts: SystemTime) -> Datapoint {
+        Datapoint {
+            ts,
+            source_ts: None,
+            value: DataValue::Int32(value),
+        }
+    }
+
+    fn values(samples: &[Datapoint]) -> Vec<DataValue> {
+        samples.iter().map(|sample| sample.value.clone()).collect()
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let policies = parse_config_from_str(
+            r#"[
+                { "path": "Vehicle.Speed",
+                  "tiers": [ { "keep_ms": 600000 }, { "keep_ms": 86400000, "interval_ms": 60000 } ] },
+                { "path": "Vehicle.**", "tiers": [ { "keep_ms": 1000, "max_samples": 10 } ] }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(policies.tiers("Vehicle.Speed").unwrap().len(), 2);
+        assert_eq!(
+            policies
+                .tiers("Vehicle.Cabin.Door.Row1.Left.IsOpen")
+                .unwrap()[0]
+                .max_samples,
+            Some(10)
+        );
+        assert!(policies.tiers("Other.Speed").is_none());
+
+        for invalid in [
+            r#"[{ "path": "Vehicle.Speed", "tiers": [] }]"#,
+            r#"[{ "path": "Vehicle.Speed", "tiers": [ { "keep_ms": 0 } ] }]"#,
+            r#"[{ "path": "Vehicle.Speed", "tiers": [ { "keep_ms": 1, "interval_ms": 0 } ] }]"#,
+        ] {
+            assert!(matches!(
+                parse_config_from_str(invalid),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+        assert!(matches!(
+            parse_config_from_str(r#"[{ "path": "Vehicle.Speed", "tiers": [ { "keep": 1 } ] }]"#),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_retention_and_downsampling() {
+        let mut policies = RetentionPolicies::default();
+        policies
+            .register(
+                "Vehicle.Speed",
+                vec![
+                    Tier {
+                        keep: Duration::from_secs(10),
+                        interval: None,
+                        max_samples: None,
+                    },
+                    Tier {
+                        keep: Duration::from_secs(3600),
+                        interval: Some(Duration::from_secs(60)),
+                        max_samples: None,
+                    },
+                ],
+            )
+            .unwrap();
+        let mut history = History::new(policies);
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 * 60);
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // One sample per second for three minutes
+        for secs in 0..180 {
+            history.record(1, "Vehicle.Speed", &sample(secs as i32, at(secs)));
+        }
+        history.record(2, "Vehicle.Other", &sample(0, at(0)));
+
+        // The last 10 seconds raw, before that the latest of each minute
+        let samples = history.samples(1, None);
+        assert_eq!(
+            values(&samples),
+            [59, 119]
+                .into_iter()
+                .chain(169..180)
+                .map(DataValue::Int32)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            values(&history.samples(1, Some(at(100)))),
+            [119]
+                .into_iter()
+                .chain(169..180)
+                .map(DataValue::Int32)
+                .collect::<Vec<_>>()
+        );
+        assert!(history.samples(2, None).is_empty());
+
+        // The raw samples expire, the downsampled ones stay
+        history.expire(at(600));
+        assert_eq!(
+            values(&history.samples(1, None)),
+            [59, 119, 179].map(DataValue::Int32)
+        );
+        history.expire(at(2 * 3600));
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_max_samples() {
+        let mut policies = RetentionPolicies::default();
+        policies
+            .register(
+                "Vehicle.Speed",
+                vec![Tier {
+                    keep: Duration::from_secs(3600),
+                    interval: None,
+                    max_samples: Some(3),
+                }],
+            )
+            .unwrap();
+        let mut history = History::new(policies);
+        let now = SystemTime::now();
+        for value in 0..10 {
+            history.record(1, "Vehicle.Speed", &sample(value, now));
+        }
+        assert_eq!(
+            values(&history.samples(1, None)),
+            [7, 8, 9].map(DataValue::Int32)
+        );
+    }
+}
@@ -0,0 +1,206 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Entry ids that stay the same across restarts.
+//!
+//! Ids are otherwise assigned in registration order, which changes with
+//! the VSS files loaded and the order providers register in. The mapping
+//! from path to id is kept in a JSON file:
+//!
+//! ```json
+//! { "Vehicle.Speed": 0, "Vehicle.Cabin.Door.Row1.PassengerSide.IsOpen": 1 }
+//! ```
+//!
+//! The ids in the file are reserved before any entries are registered.
+//! Paths not found in the file get new ids, which are added to the file.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::broker::DataBroker;
+
+// How often to check for new registrations to add to the file
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    InvalidFormat(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::InvalidFormat(msg) => write!(f, "invalid id map: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Read the mapping from path to id from `file`. A missing file is an
+/// empty mapping.
+pub fn load(file: &Path) -> Result<HashMap<String, i32>, Error> {
+    let content = match fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let ids: HashMap<String, i32> =
+        serde_json::from_str(&content).map_err(|err| Error::InvalidFormat(err.to_string()))?;
+
+    let mut seen = HashSet::with_capacity(ids.len());
+    for (path, id) in &ids {
+        if *id < 0 {
+            return Err(Error::InvalidFormat(format!(
+                "negative id {id} of '{path}'"
+            )));
+        }
+        if !seen.insert(*id) {
+            return Err(Error::InvalidFormat(format!("id {id} used more than once")));
+        }
+    }
+    Ok(ids)
+}
+
+/// Write the mapping to `file`, replacing it atomically.
+pub fn save(file: &Path, ids: &HashMap<String, i32>) -> Result<(), Error> {
+    // Sorted by path to keep the file readable
+    let sorted: BTreeMap<_, _> = ids.iter().collect();
+    let content = serde_json::to_string_pretty(&sorted)
+        .map_err(|err| Error::InvalidFormat(err.to_string()))?;
+
+    let mut tmp_file = file.as_os_str().to_owned();
+    tmp_file.push(".tmp");
+    let tmp_file = PathBuf::from(tmp_file);
+    fs::write(&tmp_file, content)?;
+    fs::rename(&tmp_file, file)?;
+    Ok(())
+}
+
+/// Reserve the ids found in `file`. Call before registering any entries.
+pub async fn restore(broker: &DataBroker, file: &Path) -> Result<usize, Error> {
+    let ids = load(file)?;
+    let count = ids.len();
+    broker.reserve_ids(ids).await;
+    Ok(count)
+}
+
+/// Write the ids of all registered entries to `file` and keep adding the
+/// ids of entries registered later on, until the broker shuts down.
+pub async fn start(broker: DataBroker, file: PathBuf) -> Result<(), Error> {
+    let mut saved = broker.get_ids().await;
+    save(&file, &saved)?;
+
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAVE_INTERVAL);
+        loop {
+            let shutdown = tokio::select! {
+                _ = interval.tick() => false,
+                _ = shutdown_trigger.recv() => true,
+            };
+            let ids = broker.get_ids().await;
+            if ids != saved {
+                match save(&file, &ids) {
+                    Ok(()) => {
+                        debug!("Saved {} ids", ids.len());
+                        saved = ids;
+                    }
+                    Err(err) => warn!("Failed to save ids: {}", err),
+                }
+            }
+            if shutdown {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions;
+    use crate::types::{ChangeType, DataType, EntryType};
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "databroker-id-map-{}-{}.json",
+            name,
+            std::process::id()
+        ))
+    }
+
+    async fn register(broker: &DataBroker, path: &str) -> i32 {
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                path.to_owned(),
+                DataType::Int32,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed")
+    }
+
+    #[tokio::test]
+    async fn test_ids_stable_across_restarts() {
+        let file = temp_file("restart");
+        let _ = fs::remove_file(&file);
+
+        let broker = DataBroker::default();
+        restore(&broker, &file).await.unwrap();
+        let id_a = register(&broker, "test.a").await;
+        let id_b = register(&broker, "test.b").await;
+        save(&file, &broker.get_ids().await).unwrap();
+
+        // Register in a different order, with a new path in between
+        let broker = DataBroker::default();
+        assert_eq!(restore(&broker, &file).await.unwrap(), 2);
+        let id_c = register(&broker, "test.c").await;
+        assert_eq!(register(&broker, "test.b").await, id_b);
+        assert_eq!(register(&broker, "test.a").await, id_a);
+        assert_ne!(id_c, id_a);
+        assert_ne!(id_c, id_b);
+
+        let _ = fs::remove_file(&file);
+    }
+
+    #[test]
+    fn test_load_rejects_duplicate_ids() {
+        let file = temp_file("duplicate");
+        fs::write(&file, r#"{"test.a": 1, "test.b": 1}"#).unwrap();
+        assert!(matches!(load(&file), Err(Error::InvalidFormat(_))));
+        let _ = fs::remove_file(&file);
+    }
+}
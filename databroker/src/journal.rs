@@ -0,0 +1,568 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Change journal, a flight recorder of all value changes.
+//!
+//! Unlike a [recording](crate::recording), the journal is meant to run all
+//! the time: it is written to a directory of segment files with a bounded
+//! total size, and the oldest segment is deleted whenever a new one is
+//! started. Segments are named `journal-<seq>.kdbj` and each of them can be
+//! read on its own:
+//!
+//! ```text
+//! "KDBJ" <version: u8>                                header
+//! 0x01 <id: varint> <len: varint> <path: utf8>        path definition
+//! 0x02 <id: varint> <delta_us: zigzag> <value>        value change
+//! ```
+//!
+//! The timestamp of a change is stored as the (signed) delta to the
+//! previous change of the segment, the first one as the delta to the Unix
+//! epoch. A change cut off at the end of a segment, e.g. by a power loss,
+//! is read as an error after the complete changes before it.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+use crate::broker::DataBroker;
+use crate::encryption::{self, Encryption};
+use crate::events::Event;
+use crate::recording::{
+    self, read_bytes, read_u8, read_value, read_varint, write_bytes, write_value, write_varint,
+};
+use crate::types::DataValue;
+
+const MAGIC: &[u8; 4] = b"KDBJ";
+const FORMAT_VERSION: u8 = 1;
+
+const FRAME_PATH: u8 = 0x01;
+const FRAME_CHANGE: u8 = 0x02;
+
+const SEGMENT_PREFIX: &str = "journal-";
+const SEGMENT_SUFFIX: &str = ".kdbj";
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    InvalidFormat(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::InvalidFormat(msg) => write!(f, "invalid journal: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid journal configuration: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<recording::Error> for Error {
+    fn from(err: recording::Error) -> Self {
+        match err {
+            recording::Error::Io(err) => Error::Io(err),
+            recording::Error::InvalidFormat(msg) => Error::InvalidFormat(msg),
+            other => Error::InvalidFormat(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub ts: SystemTime,
+    pub path: String,
+    pub value: DataValue,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub dir: PathBuf,
+    /// Upper bound of the total size of all segments in bytes.
+    pub max_size: u64,
+    /// Number of segments the journal is split into. The oldest segment is
+    /// deleted as a whole, so more segments keep the journal closer to
+    /// `max_size`.
+    pub segments: usize,
+}
+
+impl Config {
+    fn segment_size(&self) -> u64 {
+        self.max_size / self.segments as u64
+    }
+}
+
+fn micros(ts: SystemTime) -> i64 {
+    match ts.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(duration) => duration.as_micros() as i64,
+        Err(err) => -(err.duration().as_micros() as i64),
+    }
+}
+
+fn from_micros(micros: i64) -> SystemTime {
+    if micros >= 0 {
+        SystemTime::UNIX_EPOCH + Duration::from_micros(micros as u64)
+    } else {
+        SystemTime::UNIX_EPOCH - Duration::from_micros(micros.unsigned_abs())
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+pub struct SegmentWriter<W: Write> {
+    writer: W,
+    paths: HashMap<String, u64>,
+    last_ts: i64,
+    size: u64,
+    frame: Vec<u8>,
+}
+
+impl<W: Write> SegmentWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self, Error> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        Ok(Self {
+            writer,
+            paths: HashMap::new(),
+            last_ts: 0,
+            size: (MAGIC.len() + 1) as u64,
+            frame: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, change: &Change) -> Result<(), Error> {
+        self.frame.clear();
+        let id = match self.paths.get(&change.path) {
+            Some(id) => *id,
+            None => {
+                let id = self.paths.len() as u64;
+                self.frame.push(FRAME_PATH);
+                write_varint(&mut self.frame, id)?;
+                write_bytes(&mut self.frame, change.path.as_bytes())?;
+                self.paths.insert(change.path.clone(), id);
+                id
+            }
+        };
+
+        let ts = micros(change.ts);
+        self.frame.push(FRAME_CHANGE);
+        write_varint(&mut self.frame, id)?;
+        write_varint(&mut self.frame, zigzag(ts.wrapping_sub(self.last_ts)))?;
+        write_value(&mut self.frame, &change.value)?;
+        self.writer.write_all(&self.frame)?;
+        self.last_ts = ts;
+        self.size += self.frame.len() as u64;
+        Ok(())
+    }
+
+    /// Number of bytes written to the segment so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+pub struct SegmentReader<R: Read> {
+    reader: R,
+    paths: Vec<String>,
+    last_ts: i64,
+}
+
+impl<R: Read> SegmentReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidFormat(
+                "not a databroker journal segment".to_owned(),
+            ));
+        }
+        let version = read_u8(&mut reader)?;
+        if version != FORMAT_VERSION {
+            return Err(Error::InvalidFormat(format!(
+                "unsupported format version {version}"
+            )));
+        }
+        Ok(Self {
+            reader,
+            paths: Vec::new(),
+            last_ts: 0,
+        })
+    }
+
+    fn read_change(&mut self) -> Result<Option<Change>, Error> {
+        loop {
+            let mut frame = [0u8; 1];
+            if self.reader.read(&mut frame)? == 0 {
+                return Ok(None);
+            }
+            match frame[0] {
+                FRAME_PATH => {
+                    let id = read_varint(&mut self.reader)?;
+                    if id != self.paths.len() as u64 {
+                        return Err(Error::InvalidFormat(format!("unexpected path id {id}")));
+                    }
+                    let path = String::from_utf8(read_bytes(&mut self.reader)?)
+                        .map_err(|_| Error::InvalidFormat("path is not utf-8".to_owned()))?;
+                    self.paths.push(path);
+                }
+                FRAME_CHANGE => {
+                    let id = read_varint(&mut self.reader)?;
+                    let path = self
+                        .paths
+                        .get(id as usize)
+                        .ok_or_else(|| Error::InvalidFormat(format!("unknown path id {id}")))?
+                        .clone();
+                    let delta = unzigzag(read_varint(&mut self.reader)?);
+                    let value = read_value(&mut self.reader)?;
+                    self.last_ts = self.last_ts.wrapping_add(delta);
+                    return Ok(Some(Change {
+                        ts: from_micros(self.last_ts),
+                        path,
+                        value,
+                    }));
+                }
+                other => {
+                    return Err(Error::InvalidFormat(format!(
+                        "unknown frame type {other:#04x}"
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for SegmentReader<R> {
+    type Item = Result<Change, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_change().transpose()
+    }
+}
+
+fn segment_seq(path: &Path) -> Option<u64> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix(SEGMENT_PREFIX)?
+        .strip_suffix(SEGMENT_SUFFIX)?
+        .parse()
+        .ok()
+}
+
+/// The segments of the journal in `dir`, oldest first.
+pub fn segments(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut segments = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(seq) = segment_seq(&path) {
+            segments.push((seq, path));
+        }
+    }
+    segments.sort();
+    Ok(segments.into_iter().map(|(_, path)| path).collect())
+}
+
+type BoxedSegmentWriter = SegmentWriter<BufWriter<Box<dyn Write + Send>>>;
+
+/// Writer of the segments in a journal directory.
+pub struct Journal {
+    config: Config,
+    encryption: Option<Encryption>,
+    segments: VecDeque<PathBuf>,
+    next_seq: u64,
+    writer: BoxedSegmentWriter,
+}
+
+impl Journal {
+    /// Open the journal in `config.dir`, creating the directory if needed.
+    /// Existing segments are kept and a new segment is started after them.
+    pub fn open(config: Config, encryption: Option<Encryption>) -> Result<Self, Error> {
+        if config.segments == 0 {
+            return Err(Error::InvalidConfig(
+                "the journal needs at least one segment".to_owned(),
+            ));
+        }
+        if config.segment_size() == 0 {
+            return Err(Error::InvalidConfig(format!(
+                "a size of {} bytes is too small for {} segments",
+                config.max_size, config.segments
+            )));
+        }
+        std::fs::create_dir_all(&config.dir)?;
+        let segments: VecDeque<PathBuf> = segments(&config.dir)?.into();
+        let next_seq = segments
+            .back()
+            .and_then(|path| segment_seq(path))
+            .map_or(0, |seq| seq + 1);
+        let (writer, path) = Self::create_segment(&config.dir, next_seq, encryption.as_ref())?;
+        let mut journal = Self {
+            config,
+            encryption,
+            segments,
+            next_seq: next_seq + 1,
+            writer,
+        };
+        journal.segments.push_back(path);
+        journal.prune();
+        Ok(journal)
+    }
+
+    fn create_segment(
+        dir: &Path,
+        seq: u64,
+        encryption: Option<&Encryption>,
+    ) -> Result<(BoxedSegmentWriter, PathBuf), Error> {
+        let path = dir.join(format!("{SEGMENT_PREFIX}{seq:010}{SEGMENT_SUFFIX}"));
+        let file = File::create(&path)?;
+        let writer = SegmentWriter::new(BufWriter::new(encryption::writer(file, encryption)?))?;
+        Ok((writer, path))
+    }
+
+    /// Delete the oldest segments beyond the configured number.
+    fn prune(&mut self) {
+        while self.segments.len() > self.config.segments {
+            if let Some(path) = self.segments.pop_front() {
+                if let Err(err) = std::fs::remove_file(&path) {
+                    warn!(
+                        "Failed to delete journal segment {}: {}",
+                        path.display(),
+                        err
+                    );
+                }
+            }
+        }
+    }
+
+    /// Append a change, starting a new segment once the current one is full.
+    pub fn append(&mut self, change: &Change) -> Result<(), Error> {
+        self.writer.write(change)?;
+        if self.writer.size() >= self.config.segment_size() {
+            self.writer.flush()?;
+            let (writer, path) =
+                Self::create_segment(&self.config.dir, self.next_seq, self.encryption.as_ref())?;
+            self.writer = writer;
+            self.next_seq += 1;
+            self.segments.push_back(path);
+            self.prune();
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()
+    }
+}
+
+/// Read a journal segment, which may be encrypted.
+pub fn open_segment(
+    path: &Path,
+    encryption: Option<&Encryption>,
+) -> Result<SegmentReader<Box<dyn Read + Send>>, Error> {
+    let reader = encryption::reader(BufReader::new(File::open(path)?), encryption)?;
+    SegmentReader::new(reader)
+}
+
+/// Write all value changes to `journal` until the broker shuts down.
+pub fn start(broker: DataBroker, mut journal: Journal) {
+    let mut events = broker.subscribe_events();
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    info!(
+        "Writing value changes to the journal in {}",
+        journal.config.dir.display()
+    );
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Journal missed {} changes", missed);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = shutdown_trigger.recv() => break,
+            };
+            let Event::ValueChanged {
+                path, datapoint, ..
+            } = event
+            else {
+                continue;
+            };
+            let change = Change {
+                ts: datapoint.ts,
+                path,
+                value: datapoint.value,
+            };
+            if let Err(err) = journal.append(&change) {
+                warn!("Journal stopped: {}", err);
+                return;
+            }
+            // Flush once the events received so far are written
+            if events.is_empty() {
+                if let Err(err) = journal.flush() {
+                    warn!("Journal stopped: {}", err);
+                    return;
+                }
+            }
+        }
+        debug!("Journal finished");
+        let _ = journal.flush();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(ts_ms: u64, path: &str, value: DataValue) -> Change {
+        Change {
+            ts: SystemTime::UNIX_EPOCH + Duration::from_millis(ts_ms),
+            path: path.to_owned(),
+            value,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "databroker-journal-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn read_all(dir: &Path) -> Vec<Change> {
+        segments(dir)
+            .unwrap()
+            .iter()
+            .flat_map(|path| open_segment(path, None).unwrap())
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let changes = vec![
+            change(1_700_000_000_000, "Vehicle.Speed", DataValue::Float(1.0)),
+            change(1_700_000_000_010, "Vehicle.IsMoving", DataValue::Bool(true)),
+            // Clocks may go backwards
+            change(1_699_999_999_000, "Vehicle.Speed", DataValue::Float(2.0)),
+            change(1_700_000_001_000, "Vehicle.Speed", DataValue::NotAvailable),
+        ];
+        let mut writer = SegmentWriter::new(Vec::new()).unwrap();
+        for change in &changes {
+            writer.write(change).unwrap();
+        }
+        assert_eq!(writer.size(), writer.writer.len() as u64);
+
+        let mut data = writer.writer;
+        let read: Vec<_> = SegmentReader::new(&data[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, changes);
+
+        // The changes before one that is cut off are read
+        data.truncate(data.len() - 1);
+        let read: Vec<_> = SegmentReader::new(&data[..]).unwrap().collect();
+        assert_eq!(read.len(), 4);
+        assert!(read[..3]
+            .iter()
+            .zip(&changes)
+            .all(|(read, change)| read.as_ref().unwrap() == change));
+        assert!(read[3].is_err());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = temp_dir("rotation");
+        let config = Config {
+            dir: dir.clone(),
+            max_size: 300,
+            segments: 3,
+        };
+        let mut journal = Journal::open(config.clone(), None).unwrap();
+        for i in 0..100 {
+            journal
+                .append(&change(i, "Vehicle.Speed", DataValue::Uint32(i as u32)))
+                .unwrap();
+        }
+        journal.flush().unwrap();
+        drop(journal);
+
+        let files = segments(&dir).unwrap();
+        assert_eq!(files.len(), 3);
+        let size: u64 = files
+            .iter()
+            .map(|path| std::fs::metadata(path).unwrap().len())
+            .sum();
+        assert!(size <= 300 + 100, "journal grew to {size} bytes");
+
+        // Only the latest changes are kept, in order
+        let changes = read_all(&dir);
+        assert!(!changes.is_empty() && changes.len() < 100);
+        assert_eq!(changes.last().unwrap().value, DataValue::Uint32(99));
+        assert!(changes.windows(2).all(|pair| pair[0].ts < pair[1].ts));
+
+        // Reopening continues after the existing segments
+        let mut journal = Journal::open(config, None).unwrap();
+        journal
+            .append(&change(100, "Vehicle.Speed", DataValue::Uint32(100)))
+            .unwrap();
+        journal.flush().unwrap();
+        let changes = read_all(&dir);
+        assert_eq!(changes.last().unwrap().value, DataValue::Uint32(100));
+        assert_eq!(segments(&dir).unwrap().len(), 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = Config {
+            dir: temp_dir("invalid"),
+            max_size: 2,
+            segments: 4,
+        };
+        assert!(matches!(
+            Journal::open(config, None),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
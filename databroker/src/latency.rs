@@ -0,0 +1,310 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Latency of updates through the stages of the broker, from the call
+//! setting a value to its notification being picked up by a subscriber, to
+//! find where the time goes on the target. Measured with the monotonic
+//! system clock, and only if enabled, as reading the clock at every stage
+//! isn't free.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A stage of the path of an update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Waiting for the database write lock
+    Lock,
+    /// Checking conditions, permissions, types and bounds
+    Validate,
+    /// Applying the values, recording history and events
+    Store,
+    /// Finding the subscriptions to notify and building the notifications
+    Match,
+    /// Queueing the notifications of the subscriptions
+    Dispatch,
+    /// Waiting in the queue of a subscription until it is picked up
+    Deliver,
+    /// From the call to the notification being picked up
+    EndToEnd,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::Lock,
+        Stage::Validate,
+        Stage::Store,
+        Stage::Match,
+        Stage::Dispatch,
+        Stage::Deliver,
+        Stage::EndToEnd,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Lock => "lock",
+            Stage::Validate => "validate",
+            Stage::Store => "store",
+            Stage::Match => "match",
+            Stage::Dispatch => "dispatch",
+            Stage::Deliver => "deliver",
+            Stage::EndToEnd => "end_to_end",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Upper bounds of the buckets, in microseconds. Values above the last
+/// bound go to an extra bucket.
+const BUCKETS_US: [u64; 14] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000,
+];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS_US.len() + 1],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+    max_us: AtomicU64,
+}
+
+impl Histogram {
+    fn record(&self, duration: Duration) {
+        let us = duration.as_micros().try_into().unwrap_or(u64::MAX);
+        let bucket = BUCKETS_US.partition_point(|bound| *bound < us);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum_us.store(0, Ordering::Relaxed);
+        self.max_us.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The latencies of a stage recorded so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageStats {
+    pub stage: Stage,
+    pub count: u64,
+    pub sum: Duration,
+    pub max: Duration,
+    /// Upper bound of each bucket, None for the last one, and the number of
+    /// latencies in it
+    pub buckets: Vec<(Option<Duration>, u64)>,
+}
+
+impl StageStats {
+    /// Estimate of the `quantile` (0.0 to 1.0), the upper bound of the
+    /// bucket it falls in, or the max if in the last one. None if nothing
+    /// was recorded.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bound, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                return Some(bound.map_or(self.max, |bound| bound.min(self.max)));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Histograms of the latency of each stage.
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    histograms: [Histogram; Stage::ALL.len()],
+}
+
+impl LatencyStats {
+    pub fn record(&self, stage: Stage, duration: Duration) {
+        self.histograms[stage.index()].record(duration);
+    }
+
+    /// The stats of every stage, in the order of the stages.
+    pub fn snapshot(&self) -> Vec<StageStats> {
+        Stage::ALL
+            .iter()
+            .map(|stage| {
+                let histogram = &self.histograms[stage.index()];
+                let buckets = histogram
+                    .buckets
+                    .iter()
+                    .enumerate()
+                    .map(|(index, count)| {
+                        (
+                            BUCKETS_US.get(index).copied().map(Duration::from_micros),
+                            count.load(Ordering::Relaxed),
+                        )
+                    })
+                    .collect();
+                StageStats {
+                    stage: *stage,
+                    count: histogram.count.load(Ordering::Relaxed),
+                    sum: Duration::from_micros(histogram.sum_us.load(Ordering::Relaxed)),
+                    max: Duration::from_micros(histogram.max_us.load(Ordering::Relaxed)),
+                    buckets,
+                }
+            })
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        for histogram in &self.histograms {
+            histogram.reset();
+        }
+    }
+}
+
+/// When a notification was queued, for the latency of its delivery.
+#[derive(Debug, Clone, Copy)]
+pub struct Trace {
+    /// When the update causing it came in
+    pub ingress: Instant,
+    /// When it was queued
+    pub sent: Instant,
+}
+
+impl Trace {
+    /// Record the latency of the notification, now that it's picked up.
+    pub fn record(&self, stats: &LatencyStats) {
+        let now = Instant::now();
+        stats.record(Stage::Deliver, now.duration_since(self.sent));
+        stats.record(Stage::EndToEnd, now.duration_since(self.ingress));
+    }
+}
+
+/// Measures the stages of one update, which may be passed several times,
+/// e.g. validating and storing every value in turn. The time spent in each
+/// stage is recorded when dropped. Does nothing if `stats` is None.
+#[derive(Debug, Default)]
+pub struct Stopwatch<'a> {
+    stats: Option<&'a LatencyStats>,
+    ingress: Option<Instant>,
+    lap: Option<Instant>,
+    stages: [Option<Duration>; Stage::ALL.len()],
+}
+
+impl<'a> Stopwatch<'a> {
+    pub fn start(stats: Option<&'a LatencyStats>) -> Self {
+        let now = stats.map(|_| Instant::now());
+        Self {
+            stats,
+            ingress: now,
+            lap: now,
+            stages: Default::default(),
+        }
+    }
+
+    /// Add the time since the previous lap to `stage`.
+    pub fn lap(&mut self, stage: Stage) {
+        if let Some(lap) = self.lap {
+            let now = Instant::now();
+            let spent = self.stages[stage.index()].get_or_insert(Duration::ZERO);
+            *spent += now.duration_since(lap);
+            self.lap = Some(now);
+        }
+    }
+
+    /// The trace of a notification queued now.
+    pub fn trace(&self) -> Option<Trace> {
+        self.ingress.map(|ingress| Trace {
+            ingress,
+            sent: Instant::now(),
+        })
+    }
+}
+
+impl Drop for Stopwatch<'_> {
+    fn drop(&mut self) {
+        if let Some(stats) = self.stats {
+            for (stage, spent) in Stage::ALL.iter().zip(self.stages) {
+                if let Some(spent) = spent {
+                    stats.record(*stage, spent);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats() {
+        let stats = LatencyStats::default();
+        for us in [5, 20, 20, 80, 3_000, 400_000] {
+            stats.record(Stage::Store, Duration::from_micros(us));
+        }
+        stats.record(Stage::Lock, Duration::from_micros(1));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), Stage::ALL.len());
+        let store = &snapshot[Stage::Store.index()];
+        assert_eq!(store.stage, Stage::Store);
+        assert_eq!(store.count, 6);
+        assert_eq!(store.sum, Duration::from_micros(403_125));
+        assert_eq!(store.max, Duration::from_micros(400_000));
+        assert_eq!(store.buckets[0], (Some(Duration::from_micros(10)), 1));
+        assert_eq!(store.buckets[1], (Some(Duration::from_micros(25)), 2));
+        assert_eq!(store.buckets[BUCKETS_US.len()], (None, 1));
+        assert_eq!(store.quantile(0.5), Some(Duration::from_micros(25)));
+        assert_eq!(store.quantile(0.8), Some(Duration::from_micros(5_000)));
+        assert_eq!(store.quantile(1.0), Some(Duration::from_micros(400_000)));
+        // Not above the max
+        assert_eq!(
+            snapshot[Stage::Lock.index()].quantile(0.5),
+            Some(Duration::from_micros(1))
+        );
+        assert_eq!(snapshot[Stage::Match.index()].quantile(0.5), None);
+
+        stats.reset();
+        assert_eq!(stats.snapshot()[Stage::Store.index()].count, 0);
+    }
+
+    #[test]
+    fn test_stopwatch() {
+        let stats = LatencyStats::default();
+        {
+            let mut stopwatch = Stopwatch::start(Some(&stats));
+            stopwatch.lap(Stage::Validate);
+            stopwatch.lap(Stage::Store);
+            // Passed again, recorded once
+            stopwatch.lap(Stage::Validate);
+            assert!(stopwatch.trace().is_some());
+        }
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot[Stage::Validate.index()].count, 1);
+        assert_eq!(snapshot[Stage::Store.index()].count, 1);
+        assert_eq!(snapshot[Stage::Lock.index()].count, 0);
+
+        // Disabled
+        let mut stopwatch = Stopwatch::start(None);
+        stopwatch.lap(Stage::Validate);
+        assert!(stopwatch.trace().is_none());
+    }
+}
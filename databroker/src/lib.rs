@@ -11,20 +11,68 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
+pub mod accounting;
+pub mod actuations;
 pub mod authorization;
 pub mod broker;
+pub mod call_stats;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod check;
+pub mod clock;
+pub mod dump;
+pub mod encryption;
+pub mod enrollment;
+pub mod events;
+pub mod federation;
 pub mod glob;
 pub mod grpc;
+pub mod history;
+pub mod id_map;
+pub mod journal;
+pub mod latency;
+pub mod log_filter;
+pub mod metrics;
 pub mod open_telemetry;
 pub mod permissions;
+pub mod persistence;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod policy;
 pub mod query;
+pub mod recording;
+pub mod replication;
+pub mod seed;
+pub mod sessions;
+pub mod signal_groups;
+pub mod simulator;
 pub mod types;
+pub mod validation;
 pub mod vss;
+#[cfg(feature = "vss-fetch")]
+pub mod vss_fetch;
 
 #[cfg(feature = "viss")]
 pub mod viss;
 
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+#[cfg(feature = "sse")]
+pub mod sse;
+
+#[cfg(feature = "graphql")]
+pub mod graphql;
+
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
 use std::fmt::Write;
+#[cfg(not(feature = "otel"))]
+use std::sync::Arc;
 
 use tracing::info;
 use tracing_subscriber::filter::EnvFilter;
@@ -36,24 +84,35 @@ use {
     tracing_subscriber::layer::SubscriberExt,
 };
 
+/// Install the logging subscriber. Returns the handle to change its filter
+/// at runtime.
 #[cfg(not(feature = "otel"))]
-pub fn init_logging() {
+pub fn init_logging() -> Option<Arc<log_filter::LogFilter>> {
     let mut output = String::from("Init logging from RUST_LOG");
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|err| {
         output.write_fmt(format_args!(" ({err})")).unwrap();
         // If no environment variable set, this is the default
         EnvFilter::new("info")
     });
-    tracing_subscriber::fmt::Subscriber::builder()
+    let log_filter = filter.to_string();
+    let builder = tracing_subscriber::fmt::Subscriber::builder()
         .with_env_filter(filter)
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    builder
         .try_init()
         .expect("Unable to install global logging subscriber");
 
     info!("{}", output);
+    Some(log_filter::LogFilter::new(
+        &EnvFilter::new(log_filter),
+        move |filter| handle.reload(filter).map_err(|err| err.to_string()),
+    ))
 }
 
+/// Install the logging subscriber. Its filter can't be changed at runtime.
 #[cfg(feature = "otel")]
-pub fn init_logging() {
+pub fn init_logging() -> Option<std::sync::Arc<log_filter::LogFilter>> {
     let output = String::from("Init logging from RUST_LOG");
 
     // Set OpenTelemetry trace propagator
@@ -75,4 +134,5 @@ pub fn init_logging() {
         .expect("Unable to install global logging subscriber");
 
     info!("{}", output);
+    None
 }
@@ -0,0 +1,197 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Changing the log filter of a running broker, e.g. to trace an
+//! intermittent issue without restarting and losing the faulty state. A
+//! changed filter is reverted to the one the broker was started with after
+//! a timeout, so elevated logging isn't left on by accident.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tracing::info;
+use tracing_subscriber::filter::EnvFilter;
+
+#[derive(Debug, PartialEq)]
+pub enum LogFilterError {
+    /// The directives couldn't be parsed
+    InvalidFilter(String),
+    /// The filter couldn't be replaced, e.g. because the logging subscriber
+    /// is gone
+    ReloadFailed(String),
+}
+
+type Reload = dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync;
+
+pub struct LogFilter {
+    reload: Box<Reload>,
+    initial: String,
+    state: Mutex<State>,
+}
+
+struct State {
+    current: String,
+    // Counts the changes, so that a revert doesn't undo a later change
+    generation: u64,
+    // Reverts the current filter to the initial one
+    revert: Option<JoinHandle<()>>,
+}
+
+impl LogFilter {
+    /// A log filter starting out as `initial`, changed with `reload`.
+    pub fn new(
+        initial: &EnvFilter,
+        reload: impl Fn(EnvFilter) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Arc<Self> {
+        Arc::new(LogFilter {
+            reload: Box::new(reload),
+            initial: initial.to_string(),
+            state: Mutex::new(State {
+                current: initial.to_string(),
+                generation: 0,
+                revert: None,
+            }),
+        })
+    }
+
+    /// The directives of the filter in effect.
+    pub fn current(&self) -> String {
+        self.state
+            .lock()
+            .expect("log filter should not be poisoned")
+            .current
+            .clone()
+    }
+
+    /// Replace the filter with `directives` (e.g. `databroker::broker=trace`)
+    /// for `timeout`, after which the initial filter is back in effect. An
+    /// earlier change is superseded, including its timeout. Returns the
+    /// directives now in effect.
+    pub fn set(
+        self: &Arc<Self>,
+        directives: &str,
+        timeout: Duration,
+    ) -> Result<String, LogFilterError> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|err| LogFilterError::InvalidFilter(err.to_string()))?;
+        let mut state = self
+            .state
+            .lock()
+            .expect("log filter should not be poisoned");
+        self.apply(&mut state, filter)?;
+        info!("Log filter set to '{}' for {:?}", state.current, timeout);
+
+        let log_filter = Arc::downgrade(self);
+        let generation = state.generation;
+        state.revert = Some(tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            if let Some(log_filter) = log_filter.upgrade() {
+                let mut state = log_filter
+                    .state
+                    .lock()
+                    .expect("log filter should not be poisoned");
+                if state.generation != generation {
+                    return;
+                }
+                state.revert = None;
+                match log_filter.apply(&mut state, EnvFilter::new(&log_filter.initial)) {
+                    Ok(()) => info!("Log filter reverted to '{}'", state.current),
+                    Err(err) => info!("Failed to revert the log filter: {:?}", err),
+                }
+            }
+        }));
+        Ok(state.current.clone())
+    }
+
+    /// Put the initial filter back in effect right away.
+    pub fn reset(&self) -> Result<String, LogFilterError> {
+        let mut state = self
+            .state
+            .lock()
+            .expect("log filter should not be poisoned");
+        self.apply(&mut state, EnvFilter::new(&self.initial))?;
+        info!("Log filter reset to '{}'", state.current);
+        Ok(state.current.clone())
+    }
+
+    fn apply(&self, state: &mut State, filter: EnvFilter) -> Result<(), LogFilterError> {
+        let directives = filter.to_string();
+        (self.reload)(filter).map_err(LogFilterError::ReloadFailed)?;
+        if let Some(revert) = state.revert.take() {
+            revert.abort();
+        }
+        state.current = directives;
+        state.generation += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_filter() -> (Arc<LogFilter>, Arc<Mutex<Vec<String>>>) {
+        let reloaded = Arc::new(Mutex::new(Vec::new()));
+        let log_filter = LogFilter::new(&EnvFilter::new("info"), {
+            let reloaded = reloaded.clone();
+            move |filter| {
+                reloaded.lock().unwrap().push(filter.to_string());
+                Ok(())
+            }
+        });
+        (log_filter, reloaded)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_and_revert() {
+        let (log_filter, reloaded) = log_filter();
+        let current = log_filter
+            .set("info,databroker::broker=trace", Duration::from_millis(50))
+            .unwrap();
+        assert_eq!(current, "databroker::broker=trace,info");
+        assert_eq!(log_filter.current(), current);
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(log_filter.current(), "info");
+        assert_eq!(
+            *reloaded.lock().unwrap(),
+            vec!["databroker::broker=trace,info", "info"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_set_supersedes() {
+        let (log_filter, reloaded) = log_filter();
+        log_filter.set("debug", Duration::from_millis(50)).unwrap();
+        log_filter.set("trace", Duration::from_secs(10)).unwrap();
+
+        // The first timeout doesn't revert the second filter
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(log_filter.current(), "trace");
+
+        assert_eq!(log_filter.reset().unwrap(), "info");
+        assert_eq!(*reloaded.lock().unwrap(), vec!["debug", "trace", "info"]);
+    }
+
+    #[test]
+    fn test_invalid_filter() {
+        let (log_filter, reloaded) = log_filter();
+        assert!(matches!(
+            log_filter.set("databroker=loud", Duration::from_secs(1)),
+            Err(LogFilterError::InvalidFilter(_))
+        ));
+        assert_eq!(log_filter.current(), "info");
+        assert!(reloaded.lock().unwrap().is_empty());
+    }
+}
@@ -16,12 +16,15 @@
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
 static DEFAULT_UNIX_SOCKET_PATH: &str = "/run/kuksa/databroker.sock";
+// How often to update the published metrics
+const METRICS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
+use std::collections::BTreeMap;
 use std::io;
 use std::os::unix::fs::FileTypeExt;
 use std::path::Path;
 
-use databroker::authorization::Authorization;
+use databroker::authorization::{jwt, Authorization};
 use databroker::broker::RegistrationError;
 
 #[cfg(feature = "tls")]
@@ -33,9 +36,27 @@ use tokio::select;
 use tokio::signal::unix::{signal, SignalKind};
 use tracing::{debug, error, info, warn};
 
+#[cfg(feature = "graphql")]
+use databroker::graphql;
+#[cfg(feature = "sse")]
+use databroker::sse;
+
+#[cfg(feature = "plugins")]
+use databroker::plugin;
+#[cfg(feature = "prometheus")]
+use databroker::prometheus;
+#[cfg(feature = "snapshot")]
+use databroker::snapshot;
 #[cfg(feature = "viss")]
 use databroker::viss;
-use databroker::{broker, grpc, permissions, vss};
+#[cfg(feature = "vss-fetch")]
+use databroker::vss_fetch;
+#[cfg(feature = "websocket")]
+use databroker::websocket;
+use databroker::{
+    broker, check, dump, encryption, federation, glob, grpc, id_map, journal, metrics, permissions,
+    persistence, recording, replication, seed, simulator, vss,
+};
 
 async fn shutdown_handler() {
     let mut sigint =
@@ -109,18 +130,50 @@ async fn add_kuksa_attribute(
         Err(RegistrationError::ValidationError) => {
             error!("Failed to add entry {attribute}: Validation failed")
         }
+        Err(RegistrationError::QuotaExceeded) => {
+            error!("Failed to add entry {attribute}: Maximum number of entries reached")
+        }
     }
 }
 
+fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+#[cfg(feature = "vss-fetch")]
+async fn fetch_metadata_file(
+    url: &str,
+    cache_dir: Option<&String>,
+) -> Result<BTreeMap<String, vss::DataEntry>, Box<dyn std::error::Error>> {
+    info!("Populating metadata from '{}'", url);
+    let fetcher = vss_fetch::Fetcher::new(cache_dir.map(std::path::PathBuf::from));
+    // Display rather than Debug the reason startup failed
+    let contents = fetcher.fetch(url).await.map_err(|err| err.to_string())?;
+    Ok(vss::parse_vss_from_reader(contents.as_slice())?)
+}
+
+#[cfg(not(feature = "vss-fetch"))]
+async fn fetch_metadata_file(
+    url: &str,
+    _cache_dir: Option<&String>,
+) -> Result<BTreeMap<String, vss::DataEntry>, Box<dyn std::error::Error>> {
+    Err(format!("Can't fetch '{url}', databroker was built without the vss-fetch feature").into())
+}
+
 async fn read_metadata_file(
     database: &broker::AuthorizedAccess<'_, '_>,
     filename: &str,
+    cache_dir: Option<&String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let path = filename.trim();
-    info!("Populating metadata from file '{}'", path);
-    let metadata_file = std::fs::OpenOptions::new().read(true).open(filename)?;
-    let buffered = std::io::BufReader::new(metadata_file);
-    let entries = vss::parse_vss_from_reader(buffered)?;
+    let entries = if is_url(path) {
+        fetch_metadata_file(path, cache_dir).await?
+    } else {
+        info!("Populating metadata from file '{}'", path);
+        let metadata_file = std::fs::OpenOptions::new().read(true).open(filename)?;
+        let buffered = std::io::BufReader::new(metadata_file);
+        vss::parse_vss_from_reader(buffered)?
+    };
 
     for (path, entry) in entries {
         debug!("Adding VSS datapoint {}", path);
@@ -140,6 +193,21 @@ async fn read_metadata_file(
             .await
         {
             Ok(id) => {
+                if entry.safety_level.is_some() {
+                    if let Err(error) = database.set_safety_level(id, entry.safety_level).await {
+                        info!("Failed to set safety level for {}: {:?}", path, error);
+                    }
+                }
+                if entry.target_ttl.is_some() {
+                    if let Err(error) = database.set_target_ttl(id, entry.target_ttl).await {
+                        info!("Failed to set target TTL for {}: {:?}", path, error);
+                    }
+                }
+                if entry.precision.is_some() {
+                    if let Err(error) = database.set_precision(id, entry.precision).await {
+                        info!("Failed to set precision for {}: {:?}", path, error);
+                    }
+                }
                 if let Some(default) = entry.default {
                     let ids = [(
                         id,
@@ -177,6 +245,9 @@ async fn read_metadata_file(
             Err(RegistrationError::ValidationError) => {
                 error!("Failed to add entry {path}: Validation failed")
             }
+            Err(RegistrationError::QuotaExceeded) => {
+                error!("Failed to add entry {path}: Maximum number of entries reached")
+            }
         }
     }
     Ok(())
@@ -191,6 +262,109 @@ fn unlink_unix_domain_socket(path: impl AsRef<Path>) -> Result<(), io::Error> {
     Ok(())
 }
 
+/// Permission bits given in octal, e.g. `660`.
+fn parse_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("{mode} is not an octal file mode"))
+}
+
+/// The files `--check` validates.
+fn check_config(args: &clap::ArgMatches) -> check::Config {
+    let path = |id: &str| args.get_one::<String>(id).map(std::path::PathBuf::from);
+    check::Config {
+        vss_files: args
+            .get_many::<String>("vss-file")
+            .map(|files| files.cloned().collect())
+            .unwrap_or_default(),
+        #[cfg(feature = "tls")]
+        tls_cert: path("tls-cert"),
+        #[cfg(not(feature = "tls"))]
+        tls_cert: None,
+        #[cfg(feature = "tls")]
+        tls_private_key: path("tls-private-key"),
+        #[cfg(not(feature = "tls"))]
+        tls_private_key: None,
+        jwt_public_key: path("jwt-public-key"),
+        jwt_claim_mapping: path("jwt-claim-mapping"),
+        validators: path("validators"),
+        history: path("history"),
+        signal_groups: path("signal-groups"),
+        policies: path("policies"),
+        provider_credentials: path("provider-credentials"),
+        simulation_config: path("simulation-config"),
+        seed_file: path("seed-file"),
+        #[cfg(feature = "plugins")]
+        feeder_plugins: path("feeder-plugins"),
+        #[cfg(not(feature = "plugins"))]
+        feeder_plugins: None,
+        federation: path("federation"),
+        #[cfg(feature = "chaos")]
+        chaos_config: path("chaos-config"),
+        #[cfg(not(feature = "chaos"))]
+        chaos_config: None,
+    }
+}
+
+/// The shared secret and TLS configuration to replicate with, None if the
+/// broker neither accepts nor is a standby.
+fn replication_security(
+    args: &clap::ArgMatches,
+) -> Result<Option<replication::Security>, Box<dyn std::error::Error>> {
+    let serves = args.contains_id("replication-address");
+    if !serves && !args.contains_id("standby-of") {
+        return Ok(None);
+    }
+    let Some(secret_file) = args.get_one::<String>("replication-secret-file") else {
+        return Err(
+            "Replication secret (--replication-secret-file) must be set to replicate.".into(),
+        );
+    };
+    let secret = std::fs::read_to_string(secret_file)?;
+    let secret = secret.trim();
+    if secret.len() < 16 {
+        return Err("Replication secret must be at least 16 characters long.".into());
+    }
+    let security = replication::Security::new(secret.as_bytes());
+
+    #[cfg(not(feature = "tls"))]
+    let encrypted = false;
+    #[cfg(feature = "tls")]
+    let (security, encrypted) = {
+        let cert_file = args.get_one::<String>("tls-cert");
+        let key_file = args.get_one::<String>("tls-private-key");
+        let (security, encrypted) = match (cert_file, key_file) {
+            (Some(cert_file), Some(key_file)) if serves && !args.get_flag("insecure") => (
+                security
+                    .with_tls_identity(&std::fs::read(cert_file)?, &std::fs::read(key_file)?)?,
+                true,
+            ),
+            _ => (security, false),
+        };
+        let primary = args.get_one::<String>("standby-of");
+        match (primary, args.get_one::<String>("standby-ca-cert")) {
+            (Some(primary), Some(ca_cert)) => {
+                // The HOST of HOST:PORT, without the brackets of an IPv6 address
+                let host = primary
+                    .rsplit_once(':')
+                    .map_or(primary.as_str(), |(host, _)| host)
+                    .trim_start_matches('[')
+                    .trim_end_matches(']');
+                (
+                    security.with_tls_ca(&std::fs::read(ca_cert)?, host)?,
+                    encrypted,
+                )
+            }
+            _ => (security, encrypted),
+        }
+    };
+    if serves && !encrypted {
+        warn!("Replication is not encrypted, replicate over TLS with --tls-cert and --tls-private-key.");
+    }
+    Ok(Some(security))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let version = option_env!("CARGO_PKG_VERSION").unwrap_or_default();
     let commit_sha = option_env!("VERGEN_GIT_SHA").unwrap_or_default();
@@ -257,12 +431,34 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .required(false)
                 .env("KUKSA_DATABROKER_UNIX_SOCKET"),
         )
+        .arg(
+            Arg::new("unix-socket-mode")
+                .display_order(4)
+                .long("unix-socket-mode")
+                .help("Permissions of the unix socket (octal), e.g. 660 to let the group connect")
+                .action(ArgAction::Set)
+                .value_name("MODE")
+                .value_parser(parse_mode)
+                .required(false)
+                .env("KUKSA_DATABROKER_UNIX_SOCKET_MODE"),
+        )
+        .arg(
+            Arg::new("unix-socket-group")
+                .display_order(4)
+                .long("unix-socket-group")
+                .help("Group (id) owning the unix socket")
+                .action(ArgAction::Set)
+                .value_name("GID")
+                .value_parser(clap::value_parser!(u32))
+                .required(false)
+                .env("KUKSA_DATABROKER_UNIX_SOCKET_GROUP"),
+        )
         .arg(
             Arg::new("vss-file")
                 .display_order(5)
                 .alias("metadata")
                 .long("vss")
-                .help("Populate data broker with VSS metadata from (comma-separated) list of files")
+                .help("Populate data broker with VSS metadata from (comma-separated) list of files or URLs")
                 .action(ArgAction::Set)
                 .value_delimiter(',')
                 .value_name("FILE")
@@ -279,6 +475,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .value_name("FILE")
                 .required(false),
         )
+        .arg(
+            Arg::new("jwt-claim-mapping")
+                .display_order(6)
+                .long("jwt-claim-mapping")
+                .help("Read the scopes of access tokens from the claims listed in FILE (JSON) instead of 'scope'")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .requires("jwt-public-key")
+                .required(false)
+                .env("KUKSA_DATABROKER_JWT_CLAIM_MAPPING"),
+        )
+        .arg(
+            Arg::new("enable-token-exchange")
+                .display_order(6)
+                .long("enable-token-exchange")
+                .help("Let clients exchange their access token for narrower, short-lived ones")
+                .action(ArgAction::SetTrue)
+                .requires("jwt-public-key")
+                .env("KUKSA_DATABROKER_ENABLE_TOKEN_EXCHANGE"),
+        )
         .arg(
             Arg::new("disable-authorization")
                 .display_order(7)
@@ -302,6 +518,454 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .required(false)
                 .env("KUKSA_WORKER_THREADS")
                 .value_parser(clap::value_parser!(usize))
+        )
+        .arg(
+            Arg::new("simulation-config")
+                .display_order(35)
+                .long("simulation-config")
+                .help("Simulate signal values and actuators as described in FILE (JSON)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_SIMULATION_CONFIG"),
+        )
+        .arg(
+            Arg::new("seed-file")
+                .display_order(35)
+                .long("seed-file")
+                .help("Set initial signal values from FILE (JSON object of path to value)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_SEED_FILE"),
+        )
+        .arg(
+            Arg::new("record")
+                .display_order(36)
+                .long("record")
+                .help("Record datapoint updates to FILE")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_RECORD"),
+        )
+        .arg(
+            Arg::new("record-filter")
+                .display_order(37)
+                .long("record-filter")
+                .help("Only record paths matching the (comma-separated) list of patterns")
+                .action(ArgAction::Set)
+                .value_delimiter(',')
+                .value_name("PATTERN")
+                .requires("record")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .required(false),
+        )
+        .arg(
+            Arg::new("replay")
+                .display_order(38)
+                .long("replay")
+                .help("Replay datapoint updates recorded in FILE")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_REPLAY"),
+        )
+        .arg(
+            Arg::new("replay-speed")
+                .display_order(39)
+                .long("replay-speed")
+                .help("Playback speed factor used by --replay")
+                .action(ArgAction::Set)
+                .value_name("FACTOR")
+                .requires("replay")
+                .default_value("1.0")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("journal-dir")
+                .display_order(40)
+                .long("journal-dir")
+                .help("Keep a size-bounded journal of all value changes in DIR")
+                .action(ArgAction::Set)
+                .value_name("DIR")
+                .required(false)
+                .env("KUKSA_DATABROKER_JOURNAL_DIR"),
+        )
+        .arg(
+            Arg::new("journal-size")
+                .display_order(40)
+                .long("journal-size")
+                .help("Maximum size of the journal in MiB")
+                .action(ArgAction::Set)
+                .value_name("MIB")
+                .requires("journal-dir")
+                .default_value("16")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("journal-segments")
+                .display_order(40)
+                .long("journal-segments")
+                .help("Number of files the journal is split into")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .requires("journal-dir")
+                .default_value("8")
+                .value_parser(clap::value_parser!(u64).range(1..1000)),
+        )
+        .arg(
+            Arg::new("persistence-dir")
+                .display_order(40)
+                .long("persistence-dir")
+                .help("Persist datapoint values in DIR and restore them on startup")
+                .action(ArgAction::Set)
+                .value_name("DIR")
+                .required(false)
+                .env("KUKSA_DATABROKER_PERSISTENCE_DIR"),
+        )
+        .arg(
+            Arg::new("persist")
+                .display_order(41)
+                .long("persist")
+                .help("Only persist paths matching the (comma-separated) list of patterns")
+                .action(ArgAction::Set)
+                .value_delimiter(',')
+                .value_name("PATTERN")
+                .requires("persistence-dir")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .required(false),
+        )
+        .arg(
+            Arg::new("persist-entry-types")
+                .display_order(41)
+                .long("persist-entry-types")
+                .help("Only persist entries of the (comma-separated) list of types")
+                .action(ArgAction::Set)
+                .value_delimiter(',')
+                .value_name("TYPE")
+                .requires("persistence-dir")
+                .value_parser(["sensor", "actuator", "attribute"])
+                .required(false),
+        )
+        .arg(
+            Arg::new("snapshot-interval")
+                .display_order(42)
+                .long("snapshot-interval")
+                .help("Seconds between snapshots of the persisted values")
+                .action(ArgAction::Set)
+                .value_name("SECONDS")
+                .requires("persistence-dir")
+                .default_value("60")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("id-map")
+                .display_order(43)
+                .long("id-map")
+                .help("Keep entry ids stable across restarts by storing them in FILE")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_ID_MAP"),
+        )
+        .arg(
+            Arg::new("case-insensitive-paths")
+                .display_order(44)
+                .long("case-insensitive-paths")
+                .help("Resolve paths regardless of their case, e.g. Vehicle.isMoving to Vehicle.IsMoving")
+                .action(ArgAction::SetTrue)
+                .env("KUKSA_DATABROKER_CASE_INSENSITIVE_PATHS"),
+        )
+        .arg(
+            Arg::new("max-entries")
+                .display_order(45)
+                .long("max-entries")
+                .help("Maximum number of registered entries")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .required(false)
+                .env("KUKSA_DATABROKER_MAX_ENTRIES")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("max-subscriptions-per-client")
+                .display_order(46)
+                .long("max-subscriptions-per-client")
+                .help("Maximum number of subscriptions of a client")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .required(false)
+                .env("KUKSA_DATABROKER_MAX_SUBSCRIPTIONS_PER_CLIENT")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("max-provider-streams-per-client")
+                .display_order(47)
+                .long("max-provider-streams-per-client")
+                .help("Maximum number of provider streams of a client")
+                .action(ArgAction::Set)
+                .value_name("COUNT")
+                .required(false)
+                .env("KUKSA_DATABROKER_MAX_PROVIDER_STREAMS_PER_CLIENT")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("max-provider-stream-rate")
+                .display_order(48)
+                .long("max-provider-stream-rate")
+                .help("Maximum number of values a provider stream may update per second")
+                .action(ArgAction::Set)
+                .value_name("UPDATES")
+                .required(false)
+                .env("KUKSA_DATABROKER_MAX_PROVIDER_STREAM_RATE")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("request-timeout")
+                .display_order(48)
+                .long("request-timeout")
+                .help("Fail calls not handled within this many milliseconds")
+                .action(ArgAction::Set)
+                .value_name("MILLISECONDS")
+                .required(false)
+                .env("KUKSA_DATABROKER_REQUEST_TIMEOUT")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("max-stream-lifetime")
+                .display_order(48)
+                .long("max-stream-lifetime")
+                .help("Close streams after this many seconds")
+                .action(ArgAction::Set)
+                .value_name("SECONDS")
+                .required(false)
+                .env("KUKSA_DATABROKER_MAX_STREAM_LIFETIME")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("stream-idle-timeout")
+                .display_order(48)
+                .long("stream-idle-timeout")
+                .help("Close streams without messages sent or received for this many seconds")
+                .action(ArgAction::Set)
+                .value_name("SECONDS")
+                .required(false)
+                .env("KUKSA_DATABROKER_STREAM_IDLE_TIMEOUT")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("dump-dir")
+                .display_order(49)
+                .long("dump-dir")
+                .help("Write a dump of the broker state to DIR on SIGUSR1 [default: the temporary directory]")
+                .action(ArgAction::Set)
+                .value_name("DIR")
+                .required(false)
+                .env("KUKSA_DATABROKER_DUMP_DIR"),
+        )
+        .arg(
+            Arg::new("metrics")
+                .display_order(49)
+                .long("metrics")
+                .help("Publish broker metrics as Kuksa.Databroker.Metrics.* signals")
+                .action(ArgAction::SetTrue)
+                .env("KUKSA_DATABROKER_METRICS"),
+        )
+        .arg(
+            Arg::new("actuation-safety-level")
+                .display_order(50)
+                .long("actuation-safety-level")
+                .help("Restrict the actuation of entries with a higher safety level (QM, ASIL-A to ASIL-D)")
+                .action(ArgAction::Set)
+                .value_name("LEVEL")
+                .required(false)
+                .env("KUKSA_DATABROKER_ACTUATION_SAFETY_LEVEL")
+                .value_parser(clap::value_parser!(broker::SafetyLevel)),
+        )
+        .arg(
+            Arg::new("actuation-safety-mode")
+                .display_order(51)
+                .long("actuation-safety-mode")
+                .help("How actuation above the safety level is restricted: require the actuate_safety scope, or reject it")
+                .action(ArgAction::Set)
+                .value_name("MODE")
+                .required(false)
+                .env("KUKSA_DATABROKER_ACTUATION_SAFETY_MODE")
+                .value_parser(["require-scope", "reject"])
+                .default_value("require-scope"),
+        )
+        .arg(
+            Arg::new("actuator-target-ttl")
+                .display_order(52)
+                .long("actuator-target-ttl")
+                .help("Clear actuator targets not confirmed by their provider within this many milliseconds")
+                .action(ArgAction::Set)
+                .value_name("MILLISECONDS")
+                .required(false)
+                .env("KUKSA_DATABROKER_ACTUATOR_TARGET_TTL")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("validators")
+                .display_order(53)
+                .long("validators")
+                .help("Reject implausible values as described in FILE (JSON)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_VALIDATORS"),
+        )
+        .arg(
+            Arg::new("provider-credentials")
+                .display_order(54)
+                .long("provider-credentials")
+                .help("Require providers to enroll with the credentials in FILE (JSON)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_PROVIDER_CREDENTIALS"),
+        )
+        .arg(
+            Arg::new("encryption-key-file")
+                .display_order(55)
+                .long("encryption-key-file")
+                .help("Encrypt persisted values and recordings with the key in FILE (64 hex digits)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_ENCRYPTION_KEY_FILE"),
+        )
+        .arg(
+            Arg::new("encryption-key-command")
+                .display_order(56)
+                .long("encryption-key-command")
+                .help("Encrypt persisted values and recordings with the key printed by PROGRAM (64 hex digits)")
+                .action(ArgAction::Set)
+                .value_name("PROGRAM")
+                .required(false)
+                .env("KUKSA_DATABROKER_ENCRYPTION_KEY_COMMAND")
+                .conflicts_with("encryption-key-file"),
+        )
+        .arg(
+            Arg::new("replication-address")
+                .display_order(57)
+                .long("replication-address")
+                .help("Accept hot-standby brokers replicating this broker on ADDR (IP:PORT)")
+                .action(ArgAction::Set)
+                .value_name("ADDR")
+                .required(false)
+                .env("KUKSA_DATABROKER_REPLICATION_ADDRESS"),
+        )
+        .arg(
+            Arg::new("standby-of")
+                .display_order(58)
+                .long("standby-of")
+                .help("Run as hot standby of the primary broker replicating on ADDR (HOST:PORT) and take over when it is gone")
+                .action(ArgAction::Set)
+                .value_name("ADDR")
+                .required(false)
+                .env("KUKSA_DATABROKER_STANDBY_OF"),
+        )
+        .arg(
+            Arg::new("takeover-timeout")
+                .display_order(59)
+                .long("takeover-timeout")
+                .help("Take over when the primary wasn't heard of for this many milliseconds")
+                .action(ArgAction::Set)
+                .value_name("MILLISECONDS")
+                .required(false)
+                .env("KUKSA_DATABROKER_TAKEOVER_TIMEOUT")
+                .requires("standby-of")
+                .value_parser(clap::value_parser!(u64).range(300..))
+                .default_value("1000"),
+        )
+        .arg(
+            Arg::new("replication-secret-file")
+                .display_order(59)
+                .long("replication-secret-file")
+                .help("Authenticate the primary and its standby brokers with the shared secret in FILE (at least 16 characters)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_REPLICATION_SECRET_FILE"),
+        )
+        .arg(
+            Arg::new("federation")
+                .display_order(60)
+                .long("federation")
+                .help("Mount subtrees served by zone brokers as described in FILE (JSON)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_FEDERATION"),
+        )
+        .arg(
+            Arg::new("max-subscriber-bandwidth")
+                .display_order(61)
+                .long("max-subscriber-bandwidth")
+                .help("Maximum size of the notifications sent to the subscriptions of a client per second")
+                .action(ArgAction::Set)
+                .value_name("BYTES")
+                .required(false)
+                .env("KUKSA_DATABROKER_MAX_SUBSCRIBER_BANDWIDTH")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("max-dispatch-bandwidth")
+                .display_order(61)
+                .long("max-dispatch-bandwidth")
+                .help("Maximum size of the notifications sent to all subscriptions per second, shedding telemetry first")
+                .action(ArgAction::Set)
+                .value_name("BYTES")
+                .required(false)
+                .env("KUKSA_DATABROKER_MAX_DISPATCH_BANDWIDTH")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("latency-stats")
+                .display_order(61)
+                .long("latency-stats")
+                .help("Measure the latency of updates through each stage of the broker, see GetLatencyStats")
+                .action(ArgAction::SetTrue)
+                .env("KUKSA_DATABROKER_LATENCY_STATS"),
+        )
+        .arg(
+            Arg::new("policies")
+                .display_order(62)
+                .long("policies")
+                .help("Deny actuation requests under the conditions in FILE (JSON)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_POLICIES"),
+        )
+        .arg(
+            Arg::new("history")
+                .display_order(64)
+                .long("history")
+                .help("Keep past values in memory as described in FILE (JSON)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_HISTORY"),
+        )
+        .arg(
+            Arg::new("signal-groups")
+                .display_order(64)
+                .long("signal-groups")
+                .help("Define the groups of signals in FILE (JSON), read and subscribed to as a whole")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_SIGNAL_GROUPS"),
+        )
+        .arg(
+            Arg::new("check")
+                .display_order(64)
+                .long("check")
+                .help("Validate the configured files (VSS, TLS, JWT and config files) without starting, print the diagnostics as JSON and exit")
+                .action(ArgAction::SetTrue),
         );
 
     #[cfg(feature = "tls")]
@@ -331,6 +995,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .action(ArgAction::Set)
                     .value_name("FILE")
                     .conflicts_with("insecure"),
+            )
+            .arg(
+                Arg::new("standby-ca-cert")
+                    .display_order(23)
+                    .long("standby-ca-cert")
+                    .help("Follow the primary over TLS, verifying its certificate with the CA certificate in FILE (.pem)")
+                    .action(ArgAction::Set)
+                    .value_name("FILE")
+                    .env("KUKSA_DATABROKER_STANDBY_CA_CERT")
+                    .requires("standby-of"),
             );
     }
 
@@ -368,8 +1042,226 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             );
     }
 
+    #[cfg(feature = "websocket")]
+    {
+        parser = parser
+            .arg(
+                Arg::new("enable-websocket")
+                    .display_order(64)
+                    .long("enable-websocket")
+                    .help("Enable the lightweight WebSocket JSON API")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("websocket-address")
+                    .display_order(65)
+                    .long("websocket-address")
+                    .help("Bind address for the WebSocket JSON API, if argument is not provided, the value of --address is used")
+                    .action(ArgAction::Set)
+                    .value_name("IP")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_WEBSOCKET_ADDR")
+            )
+            .arg(
+                Arg::new("websocket-port")
+                    .display_order(66)
+                    .long("websocket-port")
+                    .help("WebSocket JSON API port")
+                    .action(ArgAction::Set)
+                    .value_name("PORT")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_WEBSOCKET_PORT")
+                    .value_parser(clap::value_parser!(u16))
+                    .default_value("8091"),
+            );
+    }
+
+    #[cfg(feature = "sse")]
+    {
+        parser = parser
+            .arg(
+                Arg::new("enable-sse")
+                    .display_order(67)
+                    .long("enable-sse")
+                    .help("Enable the Server-Sent Events endpoint for subscriptions")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("sse-address")
+                    .display_order(68)
+                    .long("sse-address")
+                    .help("Bind address for the Server-Sent Events endpoint, if argument is not provided, the value of --address is used")
+                    .action(ArgAction::Set)
+                    .value_name("IP")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_SSE_ADDR")
+            )
+            .arg(
+                Arg::new("sse-port")
+                    .display_order(69)
+                    .long("sse-port")
+                    .help("Server-Sent Events port")
+                    .action(ArgAction::Set)
+                    .value_name("PORT")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_SSE_PORT")
+                    .value_parser(clap::value_parser!(u16))
+                    .default_value("8092"),
+            );
+    }
+
+    #[cfg(feature = "graphql")]
+    {
+        parser = parser
+            .arg(
+                Arg::new("enable-graphql")
+                    .display_order(70)
+                    .long("enable-graphql")
+                    .help("Enable the GraphQL API")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("graphql-address")
+                    .display_order(71)
+                    .long("graphql-address")
+                    .help("Bind address for the GraphQL API, if argument is not provided, the value of --address is used")
+                    .action(ArgAction::Set)
+                    .value_name("IP")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_GRAPHQL_ADDR")
+            )
+            .arg(
+                Arg::new("graphql-port")
+                    .display_order(72)
+                    .long("graphql-port")
+                    .help("GraphQL API port")
+                    .action(ArgAction::Set)
+                    .value_name("PORT")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_GRAPHQL_PORT")
+                    .value_parser(clap::value_parser!(u16))
+                    .default_value("8093"),
+            );
+    }
+
+    #[cfg(feature = "snapshot")]
+    {
+        parser = parser
+            .arg(
+                Arg::new("enable-snapshot")
+                    .display_order(73)
+                    .long("enable-snapshot")
+                    .help("Enable the HTTP endpoint exporting all entries and values as JSON")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("snapshot-address")
+                    .display_order(74)
+                    .long("snapshot-address")
+                    .help("Bind address for the snapshot endpoint, if argument is not provided, the value of --address is used")
+                    .action(ArgAction::Set)
+                    .value_name("IP")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_SNAPSHOT_ADDR")
+            )
+            .arg(
+                Arg::new("snapshot-port")
+                    .display_order(75)
+                    .long("snapshot-port")
+                    .help("Snapshot endpoint port")
+                    .action(ArgAction::Set)
+                    .value_name("PORT")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_SNAPSHOT_PORT")
+                    .value_parser(clap::value_parser!(u16))
+                    .default_value("8094"),
+            );
+    }
+
+    #[cfg(feature = "prometheus")]
+    {
+        parser = parser
+            .arg(
+                Arg::new("enable-prometheus")
+                    .display_order(76)
+                    .long("enable-prometheus")
+                    .help("Enable the HTTP endpoint exposing broker metrics in the Prometheus format")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("prometheus-address")
+                    .display_order(77)
+                    .long("prometheus-address")
+                    .help("Bind address for the Prometheus endpoint, if argument is not provided, the value of --address is used")
+                    .action(ArgAction::Set)
+                    .value_name("IP")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_PROMETHEUS_ADDR")
+            )
+            .arg(
+                Arg::new("prometheus-port")
+                    .display_order(78)
+                    .long("prometheus-port")
+                    .help("Prometheus endpoint port")
+                    .action(ArgAction::Set)
+                    .value_name("PORT")
+                    .required(false)
+                    .env("KUKSA_DATABROKER_PROMETHEUS_PORT")
+                    .value_parser(clap::value_parser!(u16))
+                    .default_value("8095"),
+            );
+    }
+
+    #[cfg(feature = "vss-fetch")]
+    {
+        parser = parser.arg(
+            Arg::new("vss-cache-dir")
+                .display_order(5)
+                .long("vss-cache-dir")
+                .help("Keep copies of VSS files fetched from URLs in DIR, used when the server can't be reached")
+                .action(ArgAction::Set)
+                .value_name("DIR")
+                .required(false)
+                .env("KUKSA_DATABROKER_VSS_CACHE_DIR"),
+        );
+    }
+
+    #[cfg(feature = "plugins")]
+    {
+        parser = parser.arg(
+            Arg::new("feeder-plugins")
+                .display_order(63)
+                .long("feeder-plugins")
+                .help("Load the feeder plugins listed in FILE (JSON)")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_FEEDER_PLUGINS"),
+        );
+    }
+
+    #[cfg(feature = "chaos")]
+    {
+        parser = parser.arg(
+            Arg::new("chaos-config")
+                .display_order(40)
+                .long("chaos-config")
+                .help("Inject faults (latency, dropped notifications, stream resets) as described in FILE (JSON). For testing only")
+                .action(ArgAction::Set)
+                .value_name("FILE")
+                .required(false)
+                .env("KUKSA_DATABROKER_CHAOS_CONFIG"),
+        );
+    }
+
     let args = parser.get_matches();
 
+    if args.get_flag("check") {
+        let report = check::to_json(&check::check(&check_config(&args)));
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        std::process::exit(if report["valid"] == true { 0 } else { 1 });
+    }
+
     let cores = available_parallelism().unwrap().get();
     let worker_threads: &usize = args.get_one::<usize>("worker-threads").unwrap_or(&cores);
 
@@ -381,7 +1273,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     runtime.block_on(async {
         // install global collector configured based on RUST_LOG env var.
-        databroker::init_logging();
+        let log_filter = databroker::init_logging();
 
         info!("Starting Kuksa Databroker {}", version);
         info!(
@@ -395,7 +1287,143 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .expect("port should be a number");
         let addr = std::net::SocketAddr::new(ip_addr, *port);
 
-        let broker = broker::DataBroker::new(version, commit_sha);
+        let mut broker = broker::DataBroker::new(version, commit_sha);
+        if let Some(log_filter) = log_filter {
+            broker = broker.with_log_filter(log_filter);
+        }
+        if args.get_flag("case-insensitive-paths") {
+            info!("Resolving paths case insensitively");
+            broker = broker.with_case_insensitive_paths();
+        }
+        if args.get_flag("latency-stats") {
+            info!("Measuring the latency of updates");
+            broker = broker.with_latency_stats();
+        }
+        let quotas = broker::Quotas {
+            max_entries: args.get_one::<usize>("max-entries").copied(),
+            max_subscriptions_per_client: args
+                .get_one::<usize>("max-subscriptions-per-client")
+                .copied(),
+            max_provider_streams_per_client: args
+                .get_one::<usize>("max-provider-streams-per-client")
+                .copied(),
+            max_provider_stream_rate: args.get_one::<u32>("max-provider-stream-rate").copied(),
+            max_subscriber_bytes_per_sec: args.get_one::<u32>("max-subscriber-bandwidth").copied(),
+            max_dispatch_bytes_per_sec: args.get_one::<u32>("max-dispatch-bandwidth").copied(),
+        };
+        let quotas_set = quotas.max_entries.is_some()
+            || quotas.max_subscriptions_per_client.is_some()
+            || quotas.max_provider_streams_per_client.is_some()
+            || quotas.max_provider_stream_rate.is_some()
+            || quotas.max_subscriber_bytes_per_sec.is_some()
+            || quotas.max_dispatch_bytes_per_sec.is_some();
+        if quotas_set {
+            info!("Using quotas {:?}", quotas);
+            broker = broker.with_quotas(quotas);
+        }
+        let deadlines = broker::Deadlines {
+            request_timeout: args
+                .get_one::<u64>("request-timeout")
+                .map(|timeout| std::time::Duration::from_millis(*timeout)),
+            max_stream_lifetime: args
+                .get_one::<u64>("max-stream-lifetime")
+                .map(|lifetime| std::time::Duration::from_secs(*lifetime)),
+            stream_idle_timeout: args
+                .get_one::<u64>("stream-idle-timeout")
+                .map(|timeout| std::time::Duration::from_secs(*timeout)),
+        };
+        if deadlines.request_timeout.is_some()
+            || deadlines.max_stream_lifetime.is_some()
+            || deadlines.stream_idle_timeout.is_some()
+        {
+            info!("Using deadlines {:?}", deadlines);
+            broker = broker.with_deadlines(deadlines);
+        }
+        // Quotas come with metrics on their utilization
+        let enable_metrics = args.get_flag("metrics") || quotas_set;
+
+        if let Some(max_level) = args.get_one::<broker::SafetyLevel>("actuation-safety-level") {
+            let mode = match args
+                .get_one::<String>("actuation-safety-mode")
+                .map(String::as_str)
+            {
+                Some("reject") => broker::SafetyGateMode::Reject,
+                _ => broker::SafetyGateMode::RequireScope,
+            };
+            let gate = broker::SafetyGate {
+                max_level: *max_level,
+                mode,
+            };
+            info!("Restricting actuation with {:?}", gate);
+            broker = broker.with_safety_gate(gate);
+        }
+
+        if let Some(ttl) = args.get_one::<u64>("actuator-target-ttl") {
+            info!("Clearing actuator targets not confirmed within {} ms", ttl);
+            broker = broker.with_target_ttl(std::time::Duration::from_millis(*ttl));
+        }
+
+        if let Some(validators) = args.get_one::<String>("validators") {
+            let file = std::fs::File::open(validators)?;
+            let validators = databroker::validation::parse_config_from_reader(file)?;
+            info!("Checking values with validators for {:?}", validators);
+            broker = broker.with_validators(validators);
+        }
+
+        if let Some(history) = args.get_one::<String>("history") {
+            let file = std::fs::File::open(history)?;
+            let policies = databroker::history::parse_config_from_reader(file)?;
+            info!("Keeping the history of {:?}", policies);
+            broker = broker.with_history(policies);
+        }
+
+        if let Some(signal_groups) = args.get_one::<String>("signal-groups") {
+            let file = std::fs::File::open(signal_groups)?;
+            let groups = databroker::signal_groups::parse_signal_groups_from_reader(file)?;
+            info!("Defining signal groups {:?}", groups.keys());
+            broker = broker.with_signal_groups(groups);
+        }
+
+        if let Some(policies) = args.get_one::<String>("policies") {
+            let file = std::fs::File::open(policies)?;
+            let policies = databroker::policy::parse_config_from_reader(file)?;
+            info!("Denying actuation by policies {:?}", policies);
+            broker = broker.with_policies(policies);
+        }
+
+        if let Some(credentials) = args.get_one::<String>("provider-credentials") {
+            let file = std::fs::File::open(credentials)?;
+            let enrollment = databroker::enrollment::parse_credentials_from_reader(file)?;
+            info!(
+                "Providers need to enroll, known providers: {:?}",
+                enrollment
+            );
+            broker = broker.with_provider_enrollment(enrollment);
+        }
+
+        let token_exchange = if args.get_flag("enable-token-exchange") {
+            let token_exchange = jwt::TokenExchange::generate()?;
+            broker = broker.with_token_exchange(token_exchange.clone());
+            Some(token_exchange)
+        } else {
+            None
+        };
+
+        #[cfg(feature = "chaos")]
+        let broker = match args.get_one::<String>("chaos-config") {
+            Some(chaos_config) => {
+                warn!("Fault injection enabled by '{}'", chaos_config);
+                let file = std::fs::File::open(chaos_config)?;
+                broker.with_chaos(databroker::chaos::parse_config_from_reader(file)?)
+            }
+            None => broker,
+        };
+        let id_map_file = args.get_one::<String>("id-map");
+        if let Some(id_map_file) = id_map_file {
+            let reserved = id_map::restore(&broker, id_map_file.as_ref()).await?;
+            info!("Reserved {} ids found in '{}'", reserved, id_map_file);
+        }
+
         let database = broker.authorized_access(&permissions::ALLOW_ALL);
 
         add_kuksa_attribute(
@@ -425,11 +1453,163 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await;
 
         if let Some(metadata_filenames) = args.get_many::<String>("vss-file") {
+            #[cfg(feature = "vss-fetch")]
+            let cache_dir = args.get_one::<String>("vss-cache-dir");
+            #[cfg(not(feature = "vss-fetch"))]
+            let cache_dir = None;
             for filename in metadata_filenames {
-                read_metadata_file(&database, filename).await?;
+                read_metadata_file(&database, filename, cache_dir).await?;
             }
         }
 
+        if let Some(id_map_file) = id_map_file {
+            id_map::start(broker.clone(), id_map_file.into()).await?;
+        }
+
+        if let Some(seed_file) = args.get_one::<String>("seed-file") {
+            info!("Reading initial values from '{}'", seed_file);
+            let file = std::fs::File::open(seed_file)?;
+            let report = seed::apply(&broker, seed::parse_seed_from_reader(file)?).await;
+            for rejected in &report.rejected {
+                warn!(
+                    "Rejected initial value of {}: {}",
+                    rejected.path, rejected.reason
+                );
+            }
+            info!(
+                "Applied {} initial values, rejected {}",
+                report.applied,
+                report.rejected.len()
+            );
+        }
+
+        if enable_metrics {
+            if let Err(err) = metrics::start(broker.clone(), METRICS_INTERVAL).await {
+                warn!("Failed to publish metrics: {:?}", err);
+            }
+        }
+
+        let dump_dir = args
+            .get_one::<String>("dump-dir")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        if let Err(err) = dump::on_signal(broker.clone(), dump_dir) {
+            warn!("Failed to set up the SIGUSR1 handler: {}", err);
+        }
+
+        let encryption = if let Some(key_file) = args.get_one::<String>("encryption-key-file") {
+            info!("Reading encryption key from '{}'", key_file);
+            Some(encryption::Encryption::from_provider(
+                &encryption::KeyFile(key_file.into()),
+            )?)
+        } else if let Some(program) = args.get_one::<String>("encryption-key-command") {
+            info!("Getting encryption key from '{}'", program);
+            Some(encryption::Encryption::from_provider(
+                &encryption::KeyCommand(program.into()),
+            )?)
+        } else {
+            None
+        };
+
+        if let Some(persistence_dir) = args.get_one::<String>("persistence-dir") {
+            let filter = match args.get_many::<String>("persist") {
+                Some(patterns) => patterns
+                    .map(|pattern| {
+                        glob::Matcher::new(pattern)
+                            .map_err(|_| format!("Invalid persist pattern '{pattern}'"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+            };
+            let entry_types = args
+                .get_many::<String>("persist-entry-types")
+                .into_iter()
+                .flatten()
+                .map(|entry_type| match entry_type.as_str() {
+                    "sensor" => broker::EntryType::Sensor,
+                    "actuator" => broker::EntryType::Actuator,
+                    _ => broker::EntryType::Attribute,
+                })
+                .collect();
+            let snapshot_interval = *args
+                .get_one::<u64>("snapshot-interval")
+                .expect("snapshot-interval should have a default");
+            let config = persistence::Config {
+                dir: persistence_dir.into(),
+                filter,
+                entry_types,
+                snapshot_interval: std::time::Duration::from_secs(snapshot_interval),
+                encryption: encryption.clone(),
+            };
+            let restored = persistence::recover(&broker, &config).await?;
+            info!(
+                "Restored {} values persisted in '{}'",
+                restored, persistence_dir
+            );
+            persistence::start(broker.clone(), config).await?;
+        }
+
+        if let Some(simulation_config) = args.get_one::<String>("simulation-config") {
+            info!("Reading simulation config from '{}'", simulation_config);
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .open(simulation_config)?;
+            let config = simulator::parse_config_from_reader(file)?;
+            simulator::start(broker.clone(), config).await;
+        }
+
+        #[cfg(feature = "plugins")]
+        if let Some(plugins_config) = args.get_one::<String>("feeder-plugins") {
+            info!("Reading feeder plugins from '{}'", plugins_config);
+            let file = std::fs::File::open(plugins_config)?;
+            let plugins = plugin::parse_config_from_reader(file)?;
+            plugin::start(broker.clone(), plugins).await?;
+        }
+
+        if let Some(record_file) = args.get_one::<String>("record") {
+            let filter = match args.get_many::<String>("record-filter") {
+                Some(patterns) => patterns
+                    .map(|pattern| {
+                        glob::Matcher::new(pattern)
+                            .map_err(|_| format!("Invalid record filter '{pattern}'"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+            };
+            info!("Recording datapoint updates to '{}'", record_file);
+            let file = std::fs::File::create(record_file)?;
+            let writer = encryption::writer(file, encryption.as_ref())?;
+            recording::start_recording(broker.clone(), filter, writer).await?;
+        }
+
+        if let Some(journal_dir) = args.get_one::<String>("journal-dir") {
+            let max_size = *args
+                .get_one::<u64>("journal-size")
+                .expect("journal-size should have a default");
+            let segments = *args
+                .get_one::<u64>("journal-segments")
+                .expect("journal-segments should have a default");
+            let config = journal::Config {
+                dir: journal_dir.into(),
+                max_size: max_size * 1024 * 1024,
+                segments: segments as usize,
+            };
+            journal::start(
+                broker.clone(),
+                journal::Journal::open(config, encryption.clone())?,
+            );
+        }
+
+        if let Some(replay_file) = args.get_one::<String>("replay") {
+            let speed = *args
+                .get_one::<f64>("replay-speed")
+                .expect("replay-speed should have a default");
+            info!("Replaying datapoint updates from '{}'", replay_file);
+            let file = std::fs::File::open(replay_file)?;
+            let reader = encryption::reader(file, encryption.as_ref())?;
+            recording::start_replay(broker.clone(), reader, speed).await?;
+        }
+
         #[cfg(feature = "tls")]
         let tls_config = if args.get_flag("insecure") {
             ServerTLS::Disabled
@@ -481,8 +1661,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             None => Ok(None),
         }?;
 
+        let claim_mapping = match args.get_one::<String>("jwt-claim-mapping") {
+            Some(filename) => {
+                info!("Reading JWT claim mapping from '{filename}'");
+                let file = std::fs::File::open(filename)?;
+                jwt::ClaimMapping::from_reader(std::io::BufReader::new(file))?
+            }
+            None => jwt::ClaimMapping::default(),
+        };
+
         let authorization = match (enable_authorization, jwt_public_key) {
-            (true, Some(pub_key)) => Authorization::new(pub_key)?,
+            (true, Some(pub_key)) => {
+                let authorization = Authorization::with_claim_mapping(pub_key, claim_mapping)?;
+                match token_exchange {
+                    Some(token_exchange) => {
+                        info!("Token exchange enabled");
+                        authorization.with_token_exchange(token_exchange)
+                    }
+                    None => authorization,
+                }
+            }
             (true, None) => {
                 warn!("Authorization is not enabled.");
                 Authorization::Disabled
@@ -490,6 +1688,44 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             (false, _) => Authorization::Disabled,
         };
 
+        if let Some(federation_config) = args.get_one::<String>("federation") {
+            let file = std::fs::File::open(federation_config)?;
+            let mounts = federation::parse_config_from_reader(file)?;
+            federation::start(broker.clone(), mounts);
+        }
+
+        let replication_security = replication_security(&args)?;
+        if let (Some(primary), Some(security)) = (
+            args.get_one::<String>("standby-of"),
+            replication_security.as_ref(),
+        ) {
+            let takeover_timeout = *args
+                .get_one::<u64>("takeover-timeout")
+                .expect("takeover-timeout should have a default");
+            info!("Standing by for primary {}", primary);
+            let claimed = replication::follow(
+                &broker,
+                primary,
+                security,
+                std::time::Duration::from_millis(takeover_timeout),
+            )
+            .await;
+            info!("Taking over from primary {}", primary);
+            if !claimed.is_empty() {
+                info!(
+                    "Waiting for the providers of {} to reconnect",
+                    claimed.join(", ")
+                );
+            }
+        }
+
+        if let (Some(replication_address), Some(security)) = (
+            args.get_one::<String>("replication-address"),
+            replication_security,
+        ) {
+            replication::serve(broker.clone(), replication_address.as_str(), security).await?;
+        }
+
         #[cfg(feature = "viss")]
         {
             let viss_bind_addr = if args.contains_id("viss-address") {
@@ -514,6 +1750,105 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
+        #[cfg(feature = "websocket")]
+        if args.get_flag("enable-websocket") {
+            let websocket_bind_addr = match args.get_one::<String>("websocket-address") {
+                Some(address) => address.parse()?,
+                None => args.get_one::<String>("address").unwrap().parse()?,
+            };
+            let websocket_port = args
+                .get_one::<u16>("websocket-port")
+                .expect("port should be a number");
+            let websocket_addr = std::net::SocketAddr::new(websocket_bind_addr, *websocket_port);
+
+            let broker = broker.clone();
+            let authorization = authorization.clone();
+            tokio::spawn(async move {
+                if let Err(err) = websocket::serve(websocket_addr, broker, authorization).await {
+                    error!("{err}");
+                }
+            });
+        }
+
+        #[cfg(feature = "sse")]
+        if args.get_flag("enable-sse") {
+            let sse_bind_addr = match args.get_one::<String>("sse-address") {
+                Some(address) => address.parse()?,
+                None => args.get_one::<String>("address").unwrap().parse()?,
+            };
+            let sse_port = args
+                .get_one::<u16>("sse-port")
+                .expect("port should be a number");
+            let sse_addr = std::net::SocketAddr::new(sse_bind_addr, *sse_port);
+
+            let broker = broker.clone();
+            let authorization = authorization.clone();
+            tokio::spawn(async move {
+                if let Err(err) = sse::serve(sse_addr, broker, authorization).await {
+                    error!("{err}");
+                }
+            });
+        }
+
+        #[cfg(feature = "graphql")]
+        if args.get_flag("enable-graphql") {
+            let graphql_bind_addr = match args.get_one::<String>("graphql-address") {
+                Some(address) => address.parse()?,
+                None => args.get_one::<String>("address").unwrap().parse()?,
+            };
+            let graphql_port = args
+                .get_one::<u16>("graphql-port")
+                .expect("port should be a number");
+            let graphql_addr = std::net::SocketAddr::new(graphql_bind_addr, *graphql_port);
+
+            let broker = broker.clone();
+            let authorization = authorization.clone();
+            tokio::spawn(async move {
+                if let Err(err) = graphql::serve(graphql_addr, broker, authorization).await {
+                    error!("{err}");
+                }
+            });
+        }
+
+        #[cfg(feature = "snapshot")]
+        if args.get_flag("enable-snapshot") {
+            let snapshot_bind_addr = match args.get_one::<String>("snapshot-address") {
+                Some(address) => address.parse()?,
+                None => args.get_one::<String>("address").unwrap().parse()?,
+            };
+            let snapshot_port = args
+                .get_one::<u16>("snapshot-port")
+                .expect("port should be a number");
+            let snapshot_addr = std::net::SocketAddr::new(snapshot_bind_addr, *snapshot_port);
+
+            let broker = broker.clone();
+            let authorization = authorization.clone();
+            tokio::spawn(async move {
+                if let Err(err) = snapshot::serve(snapshot_addr, broker, authorization).await {
+                    error!("{err}");
+                }
+            });
+        }
+
+        #[cfg(feature = "prometheus")]
+        if args.get_flag("enable-prometheus") {
+            let prometheus_bind_addr = match args.get_one::<String>("prometheus-address") {
+                Some(address) => address.parse()?,
+                None => args.get_one::<String>("address").unwrap().parse()?,
+            };
+            let prometheus_port = args
+                .get_one::<u16>("prometheus-port")
+                .expect("port should be a number");
+            let prometheus_addr = std::net::SocketAddr::new(prometheus_bind_addr, *prometheus_port);
+
+            let broker = broker.clone();
+            tokio::spawn(async move {
+                if let Err(err) = prometheus::serve(prometheus_addr, broker).await {
+                    error!("{err}");
+                }
+            });
+        }
+
         let mut apis = vec![grpc::server::Api::KuksaValV1, grpc::server::Api::KuksaValV2];
 
         if args.get_flag("enable-databroker-v1") {
@@ -531,6 +1866,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
 
         if let Some(path) = unix_socket_path {
+            let permissions = grpc::server::UnixSocketPermissions {
+                mode: args.get_one::<u32>("unix-socket-mode").copied(),
+                group: args.get_one::<u32>("unix-socket-group").copied(),
+            };
             // We cannot assume that the socket was closed down properly
             // so unlink before we recreate it.
             unlink_unix_domain_socket(&path)?;
@@ -539,9 +1878,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let authorization = authorization.clone();
             let apis = apis.clone();
             tokio::spawn(async move {
-                if let Err(err) =
-                    grpc::server::serve_uds(&path, broker, &apis, authorization, shutdown_handler())
-                        .await
+                if let Err(err) = grpc::server::serve_uds(
+                    &path,
+                    &permissions,
+                    broker,
+                    &apis,
+                    authorization,
+                    shutdown_handler(),
+                )
+                .await
                 {
                     error!("{err}");
                 }
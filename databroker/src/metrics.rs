@@ -0,0 +1,310 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Broker metrics, published as sensors below `Kuksa.Databroker.Metrics`,
+//! so they can be read and subscribed to like any other signal. The usage
+//! of each client is published below `Kuksa.Databroker.Metrics.Clients`.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+
+use tracing::debug;
+
+use crate::accounting::ClientUsage;
+use crate::broker::{
+    self, AuthorizedAccess, DataBroker, Datapoint, ProviderStats, RegistrationError, Usage,
+};
+use crate::permissions;
+use crate::types::{ChangeType, DataType, DataValue, EntryType};
+
+const PREFIX: &str = "Kuksa.Databroker.Metrics";
+
+const METRICS: [(&str, &str); 13] = [
+    ("Entries", "Number of registered entries"),
+    ("Subscriptions", "Number of active subscriptions"),
+    ("ProviderStreams", "Number of open provider streams"),
+    (
+        "QuotaRejections",
+        "Number of requests rejected because a quota was exceeded",
+    ),
+    (
+        "RemovedSubscriptions",
+        "Number of subscriptions removed because the client was gone",
+    ),
+    (
+        "IngressUpdates",
+        "Number of values updated through provider streams",
+    ),
+    (
+        "IngressBytes",
+        "Number of bytes received on provider streams",
+    ),
+    (
+        "ThrottledUpdates",
+        "Number of values rejected because a provider stream exceeded its rate",
+    ),
+    (
+        "RejectedUpdates",
+        "Number of values of provider streams rejected as invalid or not permitted",
+    ),
+    (
+        "SuppressedUpdates",
+        "Number of values dropped because they were the same as the current value",
+    ),
+    ("DeprecatedApiCalls", "Number of calls to deprecated APIs"),
+    (
+        "LaggedNotifications",
+        "Number of notifications missed by subscribers too slow to keep up",
+    ),
+    (
+        "AuthorizationFailures",
+        "Number of calls rejected for a missing, invalid or expired access token",
+    ),
+];
+
+const CLIENT_METRICS: [(&str, &str); 4] = [
+    ("Requests", "Number of calls of the client to any API"),
+    (
+        "Subscriptions",
+        "Number of active subscriptions of the client",
+    ),
+    (
+        "StreamedBytes",
+        "Number of bytes sent to the subscriptions of the client",
+    ),
+    (
+        "RejectedUpdates",
+        "Number of values of the provider streams of the client rejected as invalid or not permitted",
+    ),
+];
+
+fn values(usage: &Usage) -> [u64; METRICS.len()] {
+    [
+        usage.entries as u64,
+        usage.subscriptions as u64,
+        usage.provider_streams as u64,
+        usage.quota_rejections,
+        usage.removed_subscriptions,
+        usage.ingress_updates,
+        usage.ingress_bytes,
+        usage.throttled_updates,
+        usage.rejected_updates,
+        usage.suppressed_updates,
+        usage.deprecated_api_calls,
+        usage.lagged_notifications,
+        usage.authorization_failures,
+    ]
+}
+
+fn client_values(
+    usage: &ClientUsage,
+    provider: Option<&ProviderStats>,
+) -> [u64; CLIENT_METRICS.len()] {
+    [
+        usage.requests,
+        usage.subscriptions as u64,
+        usage.streamed_bytes,
+        provider.map_or(0, |stats| stats.rejected),
+    ]
+}
+
+/// The name of a client in the paths of its metrics: the subject of its
+/// access token with the characters not allowed in paths replaced.
+fn client_name(client: Option<&str>) -> String {
+    match client {
+        Some("") => "_".to_owned(),
+        Some(client) => client
+            .chars()
+            .map(|c| match c {
+                '.' | ':' | '*' => '_',
+                c if c.is_whitespace() => '_',
+                c => c,
+            })
+            .collect(),
+        None => "Anonymous".to_owned(),
+    }
+}
+
+async fn register<const N: usize>(
+    authorized_access: &AuthorizedAccess<'_, '_>,
+    prefix: &str,
+    metrics: [(&str, &str); N],
+) -> Result<Vec<i32>, RegistrationError> {
+    let mut ids = Vec::with_capacity(N);
+    for (name, description) in metrics {
+        let id = authorized_access
+            .add_entry(
+                format!("{prefix}.{name}"),
+                DataType::Uint64,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                description.to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?;
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+/// The metrics of each client, registered when the client is first seen.
+#[derive(Default)]
+struct ClientMetrics {
+    ids: HashMap<Option<String>, Vec<i32>>,
+    names: HashSet<String>,
+}
+
+impl ClientMetrics {
+    async fn ids(
+        &mut self,
+        authorized_access: &AuthorizedAccess<'_, '_>,
+        client: &Option<String>,
+    ) -> Result<&[i32], RegistrationError> {
+        if !self.ids.contains_key(client) {
+            // Subjects may only differ in replaced characters
+            let base = client_name(client.as_deref());
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while self.names.contains(&name) {
+                suffix += 1;
+                name = format!("{base}_{suffix}");
+            }
+            let prefix = format!("{PREFIX}.Clients.{name}");
+            let ids = register(authorized_access, &prefix, CLIENT_METRICS).await?;
+            self.names.insert(name);
+            self.ids.insert(client.clone(), ids);
+        }
+        Ok(&self.ids[client])
+    }
+}
+
+/// Register the metrics and update them every `interval` until the broker
+/// shuts down.
+pub async fn start(broker: DataBroker, interval: Duration) -> Result<(), RegistrationError> {
+    let ids = register(
+        &broker.authorized_access(&permissions::ALLOW_ALL),
+        PREFIX,
+        METRICS,
+    )
+    .await?;
+
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        let mut last_values = HashMap::new();
+        let mut client_metrics = ClientMetrics::default();
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_trigger.recv() => break,
+            }
+            let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+            let mut values: HashMap<i32, u64> = ids
+                .iter()
+                .copied()
+                .zip(values(&broker.get_usage().await))
+                .collect();
+            let provider_stats = broker.get_provider_stats();
+            for usage in broker.get_client_usage().await {
+                let provider = provider_stats.get(&usage.client);
+                match client_metrics.ids(&authorized_access, &usage.client).await {
+                    Ok(ids) => {
+                        values.extend(ids.iter().copied().zip(client_values(&usage, provider)))
+                    }
+                    Err(err) => debug!("Failed to register client metrics: {:?}", err),
+                }
+            }
+
+            let ts = SystemTime::now();
+            let updates: Vec<_> = values
+                .iter()
+                .filter(|(id, value)| last_values.get(*id) != Some(*value))
+                .map(|(id, value)| {
+                    (
+                        *id,
+                        broker::EntryUpdate {
+                            datapoint: Some(Datapoint {
+                                ts,
+                                source_ts: None,
+                                value: DataValue::Uint64(*value),
+                            }),
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect();
+            if updates.is_empty() {
+                continue;
+            }
+            if let Err(errors) = authorized_access.update_entries(updates).await {
+                debug!("Failed to update metrics: {:?}", errors);
+            }
+            last_values = values;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_published() {
+        let broker = DataBroker::default();
+        start(broker.clone(), Duration::from_millis(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let datapoint = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_datapoint_by_path("Kuksa.Databroker.Metrics.Entries")
+            .await
+            .unwrap();
+        assert_eq!(datapoint.value, DataValue::Uint64(METRICS.len() as u64));
+    }
+
+    #[tokio::test]
+    async fn test_client_metrics_published() {
+        let broker = DataBroker::default();
+        broker.record_api_call("kuksa.val.v2", false, Some("team.a"));
+        broker.record_api_call("kuksa.val.v2", false, Some("team.a"));
+        broker.record_api_call("kuksa.val.v2", false, Some("team_a"));
+        start(broker.clone(), Duration::from_millis(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let datapoint = authorized_access
+            .get_datapoint_by_path("Kuksa.Databroker.Metrics.Clients.team_a.Requests")
+            .await
+            .unwrap();
+        assert_eq!(datapoint.value, DataValue::Uint64(2));
+        let datapoint = authorized_access
+            .get_datapoint_by_path("Kuksa.Databroker.Metrics.Clients.team_a_2.Requests")
+            .await
+            .unwrap();
+        assert_eq!(datapoint.value, DataValue::Uint64(1));
+    }
+
+    #[test]
+    fn test_client_name() {
+        assert_eq!(client_name(Some("team a:provider.1")), "team_a_provider_1");
+        assert_eq!(client_name(None), "Anonymous");
+    }
+}
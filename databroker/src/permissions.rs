@@ -14,42 +14,71 @@
 use std::time::SystemTime;
 
 use lazy_static::lazy_static;
-use regex::RegexSet;
 
+use crate::broker::SubscriptionPriority;
 use crate::glob;
 
 lazy_static! {
     pub static ref ALLOW_ALL: Permissions = Permissions {
         expires_at: None,
+        subject: None,
         read: PathMatcher::Everything,
         actuate: PathMatcher::Everything,
+        actuate_safety: PathMatcher::Everything,
         provide: PathMatcher::Everything,
         create: PathMatcher::Everything,
+        admin: true,
+        max_priority: SubscriptionPriority::Safety,
+        scope: Vec::new(),
+        session: None,
     };
     pub static ref ALLOW_NONE: Permissions = Permissions {
         expires_at: None,
+        subject: None,
         read: PathMatcher::Nothing,
         actuate: PathMatcher::Nothing,
+        actuate_safety: PathMatcher::Nothing,
         provide: PathMatcher::Nothing,
         create: PathMatcher::Nothing,
+        admin: false,
+        max_priority: SubscriptionPriority::Telemetry,
+        scope: Vec::new(),
+        session: None,
     };
 }
 
 #[derive(Debug, Clone)]
 pub struct Permissions {
     expires_at: Option<SystemTime>,
+    subject: Option<String>,
     read: PathMatcher,
     actuate: PathMatcher,
+    // Actuation of entries restricted by their safety level
+    actuate_safety: PathMatcher,
     provide: PathMatcher,
     create: PathMatcher,
+    // Administration of the broker itself, e.g. dumping its state
+    admin: bool,
+    // Highest priority class of change subscriptions
+    max_priority: SubscriptionPriority,
+    // Paths all of the above are confined to, e.g. those of an enrolled
+    // provider. Every matcher has to match.
+    scope: Vec<PathMatcher>,
+    // The connection the permissions were presented on, see
+    // `crate::sessions`
+    session: Option<u64>,
 }
 
 pub struct PermissionBuilder {
     expiration: Option<SystemTime>,
+    subject: Option<String>,
     read: PathMatchBuilder,
     actuate: PathMatchBuilder,
+    actuate_safety: PathMatchBuilder,
     provide: PathMatchBuilder,
     create: PathMatchBuilder,
+    admin: bool,
+    max_priority: SubscriptionPriority,
 }
 
 pub enum Permission {
@@ -68,7 +97,7 @@ pub enum PathMatchBuilder {
 pub enum PathMatcher {
     Nothing,
     Everything,
-    Regexps(regex::RegexSet),
+    Patterns(Vec<glob::Pattern>),
 }
 
 #[derive(Debug)]
@@ -92,10 +121,14 @@ impl PermissionBuilder {
     pub fn new() -> Self {
         Self {
             expiration: None,
+            subject: None,
             read: PathMatchBuilder::Nothing,
             actuate: PathMatchBuilder::Nothing,
+            actuate_safety: PathMatchBuilder::Nothing,
             provide: PathMatchBuilder::Nothing,
             create: PathMatchBuilder::Nothing,
+            admin: false,
+            max_priority: SubscriptionPriority::Telemetry,
         }
     }
 
@@ -104,6 +137,13 @@ impl PermissionBuilder {
         self
     }
 
+    /// The client the permissions were granted to, e.g. the subject of
+    /// an access token.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
     pub fn add_read_permission(mut self, permission: Permission) -> Self {
         match permission {
             Permission::Nothing => {
@@ -126,6 +166,19 @@ impl PermissionBuilder {
         self
     }
 
+    pub fn add_actuate_safety_permission(mut self, permission: Permission) -> Self {
+        match permission {
+            Permission::Nothing => {
+                // Adding nothing
+            }
+            Permission::All => self
+                .actuate_safety
+                .extend_with(PathMatchBuilder::Everything),
+            Permission::Glob(path) => self.actuate_safety.extend_with_glob(path),
+        };
+        self
+    }
+
     pub fn add_provide_permission(mut self, permission: Permission) -> Self {
         match permission {
             Permission::Nothing => {
@@ -148,13 +201,30 @@ impl PermissionBuilder {
         self
     }
 
+    pub fn add_admin_permission(mut self) -> Self {
+        self.admin = true;
+        self
+    }
+
+    /// Allow change subscriptions up to `priority`.
+    pub fn add_subscription_priority(mut self, priority: SubscriptionPriority) -> Self {
+        self.max_priority = self.max_priority.max(priority);
+        self
+    }
+
     pub fn build(self) -> Result<Permissions, PermissionsBuildError> {
         Ok(Permissions {
             expires_at: self.expiration,
+            subject: self.subject,
             read: self.read.build()?,
             actuate: self.actuate.build()?,
+            actuate_safety: self.actuate_safety.build()?,
             provide: self.provide.build()?,
             create: self.create.build()?,
+            admin: self.admin,
+            max_priority: self.max_priority,
+            scope: Vec::new(),
+            session: None,
         })
     }
 }
@@ -164,10 +234,45 @@ impl Permissions {
         PermissionBuilder::new()
     }
 
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at
+    }
+
+    /// The session of the connection the permissions were presented on.
+    pub fn session(&self) -> Option<u64> {
+        self.session
+    }
+
+    pub fn set_session(&mut self, session: u64) {
+        self.session = Some(session);
+    }
+
+    /// The same permissions, but confined to `globs`.
+    pub fn restricted_to(&self, globs: &[String]) -> Result<Permissions, PermissionsBuildError> {
+        let mut scope = PathMatchBuilder::Nothing;
+        for glob in globs {
+            scope.extend_with_glob(glob.clone());
+        }
+        let mut permissions = self.clone();
+        permissions.scope.push(scope.build()?);
+        Ok(permissions)
+    }
+
+    fn is_in_scope(&self, path: &str) -> bool {
+        self.scope.iter().all(|scope| scope.is_match(path))
+    }
+
     pub fn can_read(&self, path: &str) -> Result<(), PermissionError> {
         if self.is_expired() {
             return Err(PermissionError::Expired);
         }
+        if !self.is_in_scope(path) {
+            return Err(PermissionError::Denied);
+        }
 
         if self.read.is_match(path) {
             return Ok(());
@@ -193,6 +298,9 @@ impl Permissions {
         if self.is_expired() {
             return Err(PermissionError::Expired);
         }
+        if !self.is_in_scope(path) {
+            return Err(PermissionError::Denied);
+        }
 
         if self.actuate.is_match(path) {
             return Ok(());
@@ -200,11 +308,30 @@ impl Permissions {
         Err(PermissionError::Denied)
     }
 
+    /// Whether entries restricted by their safety level may be actuated,
+    /// in addition to [`can_write_actuator_target`](Self::can_write_actuator_target).
+    pub fn can_actuate_safety_relevant(&self, path: &str) -> Result<(), PermissionError> {
+        if self.is_expired() {
+            return Err(PermissionError::Expired);
+        }
+        if !self.is_in_scope(path) {
+            return Err(PermissionError::Denied);
+        }
+
+        if self.actuate_safety.is_match(path) {
+            return Ok(());
+        }
+        Err(PermissionError::Denied)
+    }
+
     #[cfg_attr(feature="otel", tracing::instrument(name="permissions_can_write_datapoint", skip(self, path), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn can_write_datapoint(&self, path: &str) -> Result<(), PermissionError> {
         if self.is_expired() {
             return Err(PermissionError::Expired);
         }
+        if !self.is_in_scope(path) {
+            return Err(PermissionError::Denied);
+        }
 
         if self.provide.is_match(path) {
             return Ok(());
@@ -212,10 +339,31 @@ impl Permissions {
         Err(PermissionError::Denied)
     }
 
+    /// Whether the broker itself may be administered, e.g. its state be
+    /// dumped. Not confined to any paths.
+    pub fn can_administer(&self) -> Result<(), PermissionError> {
+        if self.is_expired() {
+            return Err(PermissionError::Expired);
+        }
+        if self.admin && self.scope.is_empty() {
+            return Ok(());
+        }
+        Err(PermissionError::Denied)
+    }
+
+    /// The priority class a change subscription requesting `requested`
+    /// gets, i.e. at most the one granted.
+    pub fn subscription_priority(&self, requested: SubscriptionPriority) -> SubscriptionPriority {
+        requested.min(self.max_priority)
+    }
+
     pub fn can_create(&self, path: &str) -> Result<(), PermissionError> {
         if self.is_expired() {
             return Err(PermissionError::Expired);
         }
+        if !self.is_in_scope(path) {
+            return Err(PermissionError::Denied);
+        }
 
         if self.create.is_match(path) {
             return Ok(());
@@ -223,6 +371,41 @@ impl Permissions {
         Err(PermissionError::Denied)
     }
 
+    /// Whether everything `other` permits is permitted by these permissions
+    /// as well, e.g. so that a token derived from another one doesn't grant
+    /// more than it. Conservative, see [`glob::Pattern::includes`].
+    pub fn includes(&self, other: &Permissions) -> Result<bool, PermissionError> {
+        if self.is_expired() {
+            return Err(PermissionError::Expired);
+        }
+        // Read permissions are included in the other permissions
+        let readable = [&self.read, &self.actuate, &self.provide, &self.create];
+        let granted = [
+            (&other.read, &readable[..]),
+            (&other.actuate, &[&self.actuate][..]),
+            (&other.actuate_safety, &[&self.actuate_safety][..]),
+            (&other.provide, &[&self.provide][..]),
+            (&other.create, &[&self.create][..]),
+        ];
+        for (requested, matchers) in granted {
+            for pattern in requested.patterns() {
+                if !matchers.iter().any(|matcher| matcher.includes(&pattern)) {
+                    return Ok(false);
+                }
+                if !self.scope.iter().all(|scope| scope.includes(&pattern)) {
+                    return Ok(false);
+                }
+            }
+        }
+        if other.admin && !(self.admin && self.scope.is_empty()) {
+            return Ok(false);
+        }
+        if other.max_priority > self.max_priority {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
     #[cfg_attr(feature="otel", tracing::instrument(name="permissions_expired", skip(self), fields(timestamp=chrono::Utc::now().to_string())))]
     #[inline]
     pub fn is_expired(&self) -> bool {
@@ -236,12 +419,35 @@ impl Permissions {
 }
 
 impl PathMatcher {
+    // The patterns matched, `Everything` being the empty pattern
+    fn patterns(&self) -> Vec<glob::Pattern> {
+        match self {
+            PathMatcher::Nothing => Vec::new(),
+            PathMatcher::Everything => {
+                vec![glob::Pattern::new("").expect("empty pattern should be valid")]
+            }
+            PathMatcher::Patterns(patterns) => patterns.clone(),
+        }
+    }
+
+    fn includes(&self, pattern: &glob::Pattern) -> bool {
+        match self {
+            PathMatcher::Nothing => false,
+            PathMatcher::Everything => true,
+            PathMatcher::Patterns(patterns) => {
+                patterns.iter().any(|included| included.includes(pattern))
+            }
+        }
+    }
+
     #[cfg_attr(feature="otel", tracing::instrument(name="permissions_is_match", skip(self, path), fields(timestamp=chrono::Utc::now().to_string())))]
     pub fn is_match(&self, path: &str) -> bool {
         match self {
             PathMatcher::Nothing => false,
             PathMatcher::Everything => true,
-            PathMatcher::Regexps(regexps) => regexps.is_match(path),
+            PathMatcher::Patterns(patterns) => {
+                patterns.iter().any(|pattern| pattern.is_match(path))
+            }
         }
     }
 }
@@ -292,15 +498,12 @@ impl PathMatchBuilder {
             PathMatchBuilder::Nothing => Ok(PathMatcher::Nothing),
             PathMatchBuilder::Everything => Ok(PathMatcher::Everything),
             PathMatchBuilder::Globs(globs) => {
-                let regexps = globs.iter().map(|glob| glob::to_regex_string(glob));
-
-                Ok(PathMatcher::Regexps(RegexSet::new(regexps).map_err(
-                    |err| match err {
-                        regex::Error::Syntax(_) => PermissionsBuildError::BuildError,
-                        regex::Error::CompiledTooBig(_) => PermissionsBuildError::BuildError,
-                        _ => PermissionsBuildError::BuildError,
-                    },
-                )?))
+                let patterns = globs
+                    .iter()
+                    .map(|glob| glob::Pattern::new(glob))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| PermissionsBuildError::BuildError)?;
+                Ok(PathMatcher::Patterns(patterns))
             }
         }
     }
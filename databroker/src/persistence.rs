@@ -0,0 +1,862 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Persistence of datapoint values and actuator targets across restarts.
+//!
+//! The state of persisted entries is kept in a directory holding a
+//! snapshot and a write-ahead log (WAL) of the updates since the snapshot
+//! was taken. Each batch of updates is synced to disk when it has been
+//! appended to the WAL. Taking a new snapshot starts a new, empty WAL.
+//!
+//! Both files use the same format, a header (magic and format version)
+//! followed by frames:
+//!
+//! ```text
+//! 0x01 <id: varint> <len: varint> <path: utf8>        path definition
+//! 0x02 <id: varint> <ts_us: varint> <value>           datapoint value
+//! 0x03 <entry id: varint> <metadata>                  entry definition
+//! 0x04 <id: varint> <ts_us: varint> <0|1> [<value>]   actuator target
+//! ```
+//!
+//! Values are encoded like in a [recording](crate::recording), metadata
+//! like in [replication](crate::replication). Entries that don't exist when
+//! recovering, e.g. as they were registered by a provider rather than
+//! loaded from a VSS file, are registered again from their definition.
+//! Afterwards, the value and the target with the latest timestamp of each
+//! path win, whichever file they were found in. That way a crash while
+//! taking a snapshot doesn't lose or revert values. A frame cut off at the
+//! end of a file, e.g. by a power loss, is ignored. Files of format version
+//! 1 only hold values.
+//!
+//! With [`Config::encryption`] set, both files are
+//! [encrypted](crate::encryption). Plain files are still recovered, so
+//! encryption can be enabled for an existing directory.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::broker::{self, DataBroker, Datapoint, Metadata};
+use crate::encryption::{self, Encryption};
+use crate::events::Event;
+use crate::glob::Matcher;
+use crate::permissions;
+use crate::recording::{self, Error};
+use crate::replication;
+use crate::types::{DataValue, EntryType};
+
+const MAGIC: &[u8; 4] = b"KDBP";
+const FORMAT_VERSION: u8 = 2;
+
+const FRAME_PATH: u8 = 0x01;
+const FRAME_VALUE: u8 = 0x02;
+const FRAME_ENTRY: u8 = 0x03;
+const FRAME_TARGET: u8 = 0x04;
+
+const SNAPSHOT_FILE: &str = "snapshot";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.tmp";
+const WAL_FILE: &str = "wal";
+
+#[derive(Debug)]
+pub struct Config {
+    /// Directory holding the snapshot and the WAL.
+    pub dir: PathBuf,
+    /// Only persist entries matching any of these patterns, all entries
+    /// if empty.
+    pub filter: Vec<Matcher>,
+    /// Only persist entries of these types, all entries if empty.
+    pub entry_types: Vec<EntryType>,
+    pub snapshot_interval: Duration,
+    /// Encrypt the snapshot and the WAL.
+    pub encryption: Option<Encryption>,
+}
+
+impl Config {
+    /// Whether the entry described by `metadata` is persisted.
+    fn persists(&self, metadata: &Metadata) -> bool {
+        recording::matches(&self.filter, &metadata.path)
+            && (self.entry_types.is_empty() || self.entry_types.contains(&metadata.entry_type))
+    }
+}
+
+fn micros(ts: SystemTime) -> u64 {
+    ts.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+struct LogWriter {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    // Synced, the writer may be encrypting
+    file: File,
+    paths: HashMap<String, u64>,
+}
+
+impl LogWriter {
+    fn create(path: &Path, encryption: Option<&Encryption>) -> Result<Self, Error> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(encryption::writer(file.try_clone()?, encryption)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        Ok(Self {
+            writer,
+            file,
+            paths: HashMap::new(),
+        })
+    }
+
+    /// The id of `path` in this file, defining it first if needed.
+    fn path_id(&mut self, path: &str) -> Result<u64, Error> {
+        if let Some(id) = self.paths.get(path) {
+            return Ok(*id);
+        }
+        let id = self.paths.len() as u64;
+        self.writer.write_all(&[FRAME_PATH])?;
+        recording::write_varint(&mut self.writer, id)?;
+        recording::write_bytes(&mut self.writer, path.as_bytes())?;
+        self.paths.insert(path.to_owned(), id);
+        Ok(id)
+    }
+
+    fn write(&mut self, path: &str, datapoint: &Datapoint) -> Result<(), Error> {
+        let id = self.path_id(path)?;
+        self.writer.write_all(&[FRAME_VALUE])?;
+        recording::write_varint(&mut self.writer, id)?;
+        recording::write_varint(&mut self.writer, micros(datapoint.ts))?;
+        recording::write_value(&mut self.writer, &datapoint.value)?;
+        Ok(())
+    }
+
+    fn write_entry(&mut self, metadata: &Metadata) -> Result<(), Error> {
+        self.writer.write_all(&[FRAME_ENTRY])?;
+        recording::write_varint(&mut self.writer, metadata.id as u32 as u64)?;
+        replication::write_metadata(&mut self.writer, metadata)?;
+        Ok(())
+    }
+
+    /// Write the target of an actuator, set at `ts` or cleared if `target`
+    /// is None.
+    fn write_target(
+        &mut self,
+        path: &str,
+        ts: SystemTime,
+        target: Option<&DataValue>,
+    ) -> Result<(), Error> {
+        let id = self.path_id(path)?;
+        self.writer.write_all(&[FRAME_TARGET])?;
+        recording::write_varint(&mut self.writer, id)?;
+        recording::write_varint(&mut self.writer, micros(ts))?;
+        self.writer.write_all(&[u8::from(target.is_some())])?;
+        if let Some(target) = target {
+            recording::write_value(&mut self.writer, target)?;
+        }
+        Ok(())
+    }
+
+    /// Flush buffered frames and wait until they are on disk.
+    fn sync(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
+
+/// What was read from the snapshot and the WAL, by path.
+#[derive(Default)]
+struct Persisted {
+    entries: HashMap<String, Metadata>,
+    values: HashMap<String, (SystemTime, DataValue)>,
+    targets: HashMap<String, (SystemTime, Option<DataValue>)>,
+}
+
+/// Insert `value` set at `ts` unless a later one is known already.
+fn keep_latest<T>(
+    latest: &mut HashMap<String, (SystemTime, T)>,
+    path: String,
+    ts: SystemTime,
+    value: T,
+) {
+    match latest.get(&path) {
+        Some((latest_ts, _)) if *latest_ts > ts => {}
+        _ => {
+            latest.insert(path, (ts, value));
+        }
+    }
+}
+
+/// Read the file at `path` into `persisted`, keeping the latest value and
+/// target of each path. A missing file holds nothing.
+fn read_file(
+    path: &Path,
+    persisted: &mut Persisted,
+    encryption: Option<&Encryption>,
+) -> Result<(), Error> {
+    let mut reader = match File::open(path) {
+        Ok(file) => encryption::reader(BufReader::new(file), encryption)?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::InvalidFormat(format!(
+            "{} is not a databroker persistence file",
+            path.display()
+        )));
+    }
+    let version = recording::read_u8(&mut reader)?;
+    if !(1..=FORMAT_VERSION).contains(&version) {
+        return Err(Error::InvalidFormat(format!(
+            "unsupported format version {version}"
+        )));
+    }
+
+    let mut paths = Vec::new();
+    loop {
+        match read_frame(&mut reader, &mut paths) {
+            Ok(Some(Frame::Value { path, ts, value })) => {
+                keep_latest(&mut persisted.values, path, ts, value)
+            }
+            Ok(Some(Frame::Target { path, ts, target })) => {
+                keep_latest(&mut persisted.targets, path, ts, target)
+            }
+            Ok(Some(Frame::Entry(metadata))) => {
+                persisted.entries.insert(metadata.path.clone(), metadata);
+            }
+            Ok(Some(Frame::Path)) => {}
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                warn!("Ignoring the rest of {}: {}", path.display(), err);
+                return Ok(());
+            }
+        }
+    }
+}
+
+enum Frame {
+    Path,
+    Value {
+        path: String,
+        ts: SystemTime,
+        value: DataValue,
+    },
+    Entry(Metadata),
+    Target {
+        path: String,
+        ts: SystemTime,
+        target: Option<DataValue>,
+    },
+}
+
+/// Read the next frame, `None` at the end of the file. Path definitions
+/// are added to `paths`.
+fn read_frame<R: Read>(reader: &mut R, paths: &mut Vec<String>) -> Result<Option<Frame>, Error> {
+    let mut frame = [0u8; 1];
+    if reader.read(&mut frame)? == 0 {
+        return Ok(None);
+    }
+    match frame[0] {
+        FRAME_PATH => {
+            let id = recording::read_varint(reader)?;
+            if id != paths.len() as u64 {
+                return Err(Error::InvalidFormat(format!("unexpected path id {id}")));
+            }
+            let path = String::from_utf8(recording::read_bytes(reader)?)
+                .map_err(|_| Error::InvalidFormat("path is not utf-8".to_owned()))?;
+            paths.push(path);
+            Ok(Some(Frame::Path))
+        }
+        FRAME_VALUE => {
+            let path = read_path(reader, paths)?;
+            let ts = read_ts(reader)?;
+            let value = recording::read_value(reader)?;
+            Ok(Some(Frame::Value { path, ts, value }))
+        }
+        FRAME_ENTRY => {
+            let id = recording::read_varint(reader)? as u32 as i32;
+            let metadata = replication::read_metadata(reader, id)?;
+            Ok(Some(Frame::Entry(metadata)))
+        }
+        FRAME_TARGET => {
+            let path = read_path(reader, paths)?;
+            let ts = read_ts(reader)?;
+            let target = match recording::read_u8(reader)? {
+                0 => None,
+                _ => Some(recording::read_value(reader)?),
+            };
+            Ok(Some(Frame::Target { path, ts, target }))
+        }
+        other => Err(Error::InvalidFormat(format!(
+            "unknown frame type {other:#04x}"
+        ))),
+    }
+}
+
+fn read_path<R: Read>(reader: &mut R, paths: &[String]) -> Result<String, Error> {
+    let id = recording::read_varint(reader)?;
+    paths
+        .get(id as usize)
+        .cloned()
+        .ok_or_else(|| Error::InvalidFormat(format!("unknown path id {id}")))
+}
+
+fn read_ts<R: Read>(reader: &mut R) -> Result<SystemTime, Error> {
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_micros(recording::read_varint(reader)?))
+}
+
+/// Register the persisted entries that don't exist, in the order they were
+/// registered originally. Returns the number of registered entries.
+async fn restore_entries(
+    broker: &broker::AuthorizedAccess<'_, '_>,
+    config: &Config,
+    entries: HashMap<String, Metadata>,
+) -> usize {
+    let mut entries: Vec<_> = entries
+        .into_values()
+        .filter(|metadata| config.persists(metadata))
+        .collect();
+    entries.sort_by_key(|metadata| metadata.id);
+    let mut count = 0;
+    for metadata in entries {
+        if broker.get_id_by_path(&metadata.path).await.is_some() {
+            continue;
+        }
+        let result = broker
+            .add_entry(
+                metadata.path.clone(),
+                metadata.data_type,
+                metadata.change_type,
+                metadata.entry_type,
+                metadata.description,
+                metadata.min,
+                metadata.max,
+                metadata.allowed,
+                metadata.unit,
+            )
+            .await;
+        match result {
+            Ok(id) => {
+                let _ = broker.set_safety_level(id, metadata.safety_level).await;
+                let _ = broker.set_target_ttl(id, metadata.target_ttl).await;
+                let _ = broker.set_precision(id, metadata.precision).await;
+                count += 1;
+            }
+            Err(err) => warn!("Failed to restore entry {}: {:?}", metadata.path, err),
+        }
+    }
+    count
+}
+
+/// Restore the entries, values and actuator targets persisted in
+/// `config.dir` into the broker. Values of entries that don't match the
+/// filter, or that don't exist and weren't persisted with their
+/// definition, are skipped. Returns the number of entries whose value or
+/// target was restored.
+pub async fn recover(broker: &DataBroker, config: &Config) -> Result<usize, Error> {
+    let mut persisted = Persisted::default();
+    for file in [SNAPSHOT_FILE, WAL_FILE] {
+        read_file(
+            &config.dir.join(file),
+            &mut persisted,
+            config.encryption.as_ref(),
+        )?;
+    }
+
+    let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+    let registered = restore_entries(&broker, config, persisted.entries).await;
+    if registered > 0 {
+        info!("Registered {} persisted entries", registered);
+    }
+
+    let mut updates: HashMap<i32, broker::EntryUpdate> = HashMap::new();
+    for (path, (ts, value)) in persisted.values {
+        let Some(id) = persisted_id(&broker, config, &path).await else {
+            continue;
+        };
+        updates.entry(id).or_default().datapoint = Some(Datapoint {
+            ts,
+            source_ts: None,
+            value,
+        });
+    }
+    for (path, (ts, target)) in persisted.targets {
+        let Some(id) = persisted_id(&broker, config, &path).await else {
+            continue;
+        };
+        updates.entry(id).or_default().actuator_target = Some(target.map(|value| Datapoint {
+            ts,
+            source_ts: None,
+            value,
+        }));
+    }
+
+    let mut count = updates.len();
+    if let Err(errors) = broker.update_entries(updates).await {
+        for (id, error) in &errors {
+            warn!("Failed to restore value of id {}: {:?}", id, error);
+        }
+        count -= errors.len();
+    }
+    Ok(count)
+}
+
+/// The id of the entry at `path`, None if it doesn't exist or isn't
+/// persisted.
+async fn persisted_id(
+    broker: &broker::AuthorizedAccess<'_, '_>,
+    config: &Config,
+    path: &str,
+) -> Option<i32> {
+    let Some(metadata) = broker.get_metadata_by_path(path).await else {
+        debug!("Not restoring {}, no such entry", path);
+        return None;
+    };
+    config.persists(&metadata).then_some(metadata.id)
+}
+
+/// Whether the entry with `id` is persisted, remembered in `persisted` as
+/// entries don't change.
+async fn is_persisted(
+    broker: &DataBroker,
+    config: &Config,
+    persisted: &mut HashMap<i32, bool>,
+    id: i32,
+) -> bool {
+    if let Some(persisted) = persisted.get(&id) {
+        return *persisted;
+    }
+    let result = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .get_metadata(id)
+        .await
+        .is_some_and(|metadata| config.persists(&metadata));
+    persisted.insert(id, result);
+    result
+}
+
+/// Write the persisted entries with their current values and targets to a
+/// new snapshot and start a new WAL. The snapshot replaces the old one
+/// atomically, before the old WAL is discarded.
+async fn take_snapshot(broker: &DataBroker, config: &Config) -> Result<LogWriter, Error> {
+    let values = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .filter_map_entries(|entry| {
+            if config.persists(entry.metadata()) {
+                let datapoint = entry.datapoint().ok()?.clone();
+                let target = entry.actuator_target().ok().cloned().flatten();
+                Some((entry.metadata().clone(), datapoint, target))
+            } else {
+                None
+            }
+        })
+        .await;
+
+    let tmp_file = config.dir.join(SNAPSHOT_TMP_FILE);
+    let mut snapshot = LogWriter::create(&tmp_file, config.encryption.as_ref())?;
+    for (metadata, datapoint, target) in &values {
+        snapshot.write_entry(metadata)?;
+        snapshot.write(&metadata.path, datapoint)?;
+        if let Some(target) = target {
+            snapshot.write_target(&metadata.path, target.ts, Some(&target.value))?;
+        }
+    }
+    snapshot.sync()?;
+    fs::rename(&tmp_file, config.dir.join(SNAPSHOT_FILE))?;
+    // Make the rename itself durable
+    #[cfg(unix)]
+    File::open(&config.dir)?.sync_all()?;
+
+    let mut wal = LogWriter::create(&config.dir.join(WAL_FILE), config.encryption.as_ref())?;
+    wal.sync()?;
+    debug!("Snapshot of {} values taken", values.len());
+    Ok(wal)
+}
+
+/// Persist the entries matching `config`, with their datapoint updates and
+/// actuator targets, including entries registered later on, until the
+/// broker shuts down. The returned task finishes after the last write is
+/// synced.
+/// Takes a snapshot right away, so call [`recover`] before.
+pub async fn start(broker: DataBroker, config: Config) -> Result<JoinHandle<()>, Error> {
+    fs::create_dir_all(&config.dir)?;
+
+    let count = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .filter_map_entries(|entry| config.persists(entry.metadata()).then_some(()))
+        .await
+        .len();
+    if count == 0 {
+        return Err(Error::NoMatchingEntries);
+    }
+    info!("Persisting values of {} entries", count);
+
+    // Listen before taking the snapshot, so no update falls in between
+    let mut events = broker.subscribe_events();
+    let mut wal = take_snapshot(&broker, &config).await?;
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+
+    let task = tokio::spawn(async move {
+        let mut persisted = HashMap::new();
+        let mut snapshot_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + config.snapshot_interval,
+            config.snapshot_interval,
+        );
+        loop {
+            let mut event = tokio::select! {
+                // Write the updates received before shutting down
+                biased;
+                event = events.recv() => match event {
+                    Err(RecvError::Closed) => break,
+                    event => event,
+                },
+                _ = snapshot_interval.tick() => {
+                    match take_snapshot(&broker, &config).await {
+                        Ok(new_wal) => wal = new_wal,
+                        Err(err) => warn!("Failed to take snapshot: {}", err),
+                    }
+                    continue;
+                }
+                _ = shutdown_trigger.recv() => break,
+            };
+            // Write everything received so far before syncing
+            let mut result = Ok(());
+            loop {
+                match event {
+                    Ok(Event::ValueChanged {
+                        id,
+                        path,
+                        datapoint,
+                    }) => {
+                        if is_persisted(&broker, &config, &mut persisted, id).await {
+                            result = wal.write(&path, &datapoint);
+                        }
+                    }
+                    Ok(Event::TargetSet { id, path, target }) => {
+                        if is_persisted(&broker, &config, &mut persisted, id).await {
+                            result = match target {
+                                Some(target) => {
+                                    wal.write_target(&path, target.ts, Some(&target.value))
+                                }
+                                None => wal.write_target(&path, SystemTime::now(), None),
+                            };
+                        }
+                    }
+                    Ok(Event::EntryAdded { id, .. }) => {
+                        if is_persisted(&broker, &config, &mut persisted, id).await {
+                            let metadata = broker
+                                .authorized_access(&permissions::ALLOW_ALL)
+                                .get_metadata(id)
+                                .await;
+                            if let Some(metadata) = metadata {
+                                result = wal.write_entry(&metadata);
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(missed)) => {
+                        // The snapshot holds the values of the missed updates
+                        warn!("Persistence missed {} events, taking a snapshot", missed);
+                        result = take_snapshot(&broker, &config).await.map(|new_wal| {
+                            wal = new_wal;
+                        });
+                    }
+                    Err(RecvError::Closed) => {}
+                }
+                if result.is_err() {
+                    break;
+                }
+                event = match events.try_recv() {
+                    Ok(event) => Ok(event),
+                    Err(TryRecvError::Lagged(missed)) => Err(RecvError::Lagged(missed)),
+                    Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+                };
+            }
+            if let Err(err) = result.and_then(|_| wal.sync()) {
+                warn!("Persistence stopped: {}", err);
+                return;
+            }
+        }
+        debug!("Persistence finished");
+        let _ = wal.sync();
+    });
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChangeType, DataType, EntryType};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "databroker-persistence-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    async fn broker_with_speed() -> (DataBroker, i32) {
+        let broker = DataBroker::default();
+        let id = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+        (broker, id)
+    }
+
+    fn datapoint(ts_ms: u64, value: f32) -> Datapoint {
+        Datapoint {
+            ts: SystemTime::UNIX_EPOCH + Duration::from_millis(ts_ms),
+            source_ts: None,
+            value: DataValue::Float(value),
+        }
+    }
+
+    fn config(dir: &Path) -> Config {
+        Config {
+            dir: dir.to_owned(),
+            filter: Vec::new(),
+            entry_types: Vec::new(),
+            snapshot_interval: Duration::from_secs(60),
+            encryption: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recover_latest_value() {
+        let dir = temp_dir("latest");
+        fs::create_dir_all(&dir).unwrap();
+
+        // As if the broker crashed after writing the snapshot, but before
+        // starting a new WAL
+        let mut snapshot = LogWriter::create(&dir.join(SNAPSHOT_FILE), None).unwrap();
+        snapshot
+            .write("Vehicle.Speed", &datapoint(200, 2.0))
+            .unwrap();
+        snapshot.sync().unwrap();
+        let mut wal = LogWriter::create(&dir.join(WAL_FILE), None).unwrap();
+        wal.write("Vehicle.Speed", &datapoint(100, 1.0)).unwrap();
+        wal.write("Vehicle.Unknown", &datapoint(100, 1.0)).unwrap();
+        wal.sync().unwrap();
+
+        let (broker, id) = broker_with_speed().await;
+        assert_eq!(recover(&broker, &config(&dir)).await.unwrap(), 1);
+        let _ = fs::remove_dir_all(&dir);
+
+        let restored = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_datapoint(id)
+            .await
+            .unwrap();
+        assert_eq!(restored.value, DataValue::Float(2.0));
+        assert_eq!(restored.ts, datapoint(200, 2.0).ts);
+    }
+
+    #[tokio::test]
+    async fn test_recover_ignores_truncated_frame() {
+        let dir = temp_dir("truncated");
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut wal = LogWriter::create(&dir.join(WAL_FILE), None).unwrap();
+        wal.write("Vehicle.Speed", &datapoint(100, 1.0)).unwrap();
+        wal.write("Vehicle.Speed", &datapoint(200, 2.0)).unwrap();
+        wal.sync().unwrap();
+        let len = fs::metadata(dir.join(WAL_FILE)).unwrap().len();
+        File::options()
+            .write(true)
+            .open(dir.join(WAL_FILE))
+            .unwrap()
+            .set_len(len - 2)
+            .unwrap();
+
+        let (broker, id) = broker_with_speed().await;
+        assert_eq!(recover(&broker, &config(&dir)).await.unwrap(), 1);
+        let _ = fs::remove_dir_all(&dir);
+
+        let restored = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_datapoint(id)
+            .await
+            .unwrap();
+        assert_eq!(restored.value, DataValue::Float(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_persisted_values_survive_restart() {
+        let dir = temp_dir("restart");
+
+        let (broker, id) = broker_with_speed().await;
+        assert_eq!(recover(&broker, &config(&dir)).await.unwrap(), 0);
+        let task = start(broker.clone(), config(&dir)).await.unwrap();
+
+        let update = broker::EntryUpdate {
+            datapoint: Some(Datapoint {
+                ts: SystemTime::now(),
+                source_ts: None,
+                value: DataValue::Float(42.0),
+            }),
+            ..Default::default()
+        };
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .update_entries([(id, update)])
+            .await
+            .unwrap();
+        broker.shutdown().await;
+        task.await.unwrap();
+
+        let (restarted, id) = broker_with_speed().await;
+        assert_eq!(recover(&restarted, &config(&dir)).await.unwrap(), 1);
+        let _ = fs::remove_dir_all(&dir);
+
+        let restored = restarted
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_datapoint(id)
+            .await
+            .unwrap();
+        assert_eq!(restored.value, DataValue::Float(42.0));
+    }
+
+    #[tokio::test]
+    async fn test_registered_entries_and_targets_survive_restart() {
+        let dir = temp_dir("registered");
+
+        let (broker, _) = broker_with_speed().await;
+        let task = start(broker.clone(), config(&dir)).await.unwrap();
+        // Registered by a provider after startup
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = access
+            .add_entry(
+                "Vehicle.Cabin.Light.Intensity".to_owned(),
+                DataType::Uint8,
+                ChangeType::OnChange,
+                EntryType::Actuator,
+                "Test actuator".to_owned(),
+                None,
+                Some(DataValue::Uint32(100)),
+                None,
+                Some("percent".to_owned()),
+            )
+            .await
+            .unwrap();
+        access.set_precision(id, Some(1)).await.unwrap();
+        let update = broker::EntryUpdate {
+            actuator_target: Some(Some(Datapoint {
+                ts: SystemTime::now(),
+                source_ts: None,
+                value: DataValue::Uint32(80),
+            })),
+            ..Default::default()
+        };
+        access.update_entries([(id, update)]).await.unwrap();
+        broker.shutdown().await;
+        task.await.unwrap();
+
+        let (restarted, _) = broker_with_speed().await;
+        assert_eq!(recover(&restarted, &config(&dir)).await.unwrap(), 2);
+        let _ = fs::remove_dir_all(&dir);
+
+        let access = restarted.authorized_access(&permissions::ALLOW_ALL);
+        let metadata = access
+            .get_metadata_by_path("Vehicle.Cabin.Light.Intensity")
+            .await
+            .unwrap();
+        assert_eq!(metadata.entry_type, EntryType::Actuator);
+        assert_eq!(metadata.max, Some(DataValue::Uint32(100)));
+        assert_eq!(metadata.unit.as_deref(), Some("percent"));
+        assert_eq!(metadata.precision, Some(1));
+        let entry = access.get_entry_by_id(metadata.id).await.unwrap();
+        assert_eq!(
+            entry.actuator_target.map(|target| target.value),
+            Some(DataValue::Uint32(80))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_persist_entry_types() {
+        let dir = temp_dir("entry-types");
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut wal = LogWriter::create(&dir.join(WAL_FILE), None).unwrap();
+        wal.write("Vehicle.Speed", &datapoint(100, 1.0)).unwrap();
+        wal.sync().unwrap();
+
+        let config = Config {
+            entry_types: vec![EntryType::Attribute],
+            ..config(&dir)
+        };
+        let (broker, _) = broker_with_speed().await;
+        assert_eq!(recover(&broker, &config).await.unwrap(), 0);
+        // No attributes to persist
+        assert!(matches!(
+            start(broker, config).await,
+            Err(Error::NoMatchingEntries)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_recover_encrypted() {
+        let dir = temp_dir("encrypted");
+        fs::create_dir_all(&dir).unwrap();
+        let encryption = Encryption::new(&[1; encryption::KEY_LEN]);
+
+        // Written before encryption was enabled
+        let mut snapshot = LogWriter::create(&dir.join(SNAPSHOT_FILE), None).unwrap();
+        snapshot
+            .write("Vehicle.Speed", &datapoint(100, 1.0))
+            .unwrap();
+        snapshot.sync().unwrap();
+        let mut wal = LogWriter::create(&dir.join(WAL_FILE), Some(&encryption)).unwrap();
+        wal.write("Vehicle.Speed", &datapoint(200, 2.0)).unwrap();
+        wal.sync().unwrap();
+
+        let (broker, _) = broker_with_speed().await;
+        assert!(recover(&broker, &config(&dir)).await.is_err());
+
+        let (broker, id) = broker_with_speed().await;
+        let config = Config {
+            encryption: Some(encryption),
+            ..config(&dir)
+        };
+        assert_eq!(recover(&broker, &config).await.unwrap(), 1);
+        let _ = fs::remove_dir_all(&dir);
+
+        let restored = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_datapoint(id)
+            .await
+            .unwrap();
+        assert_eq!(restored.value, DataValue::Float(2.0));
+    }
+}
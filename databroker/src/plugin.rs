@@ -0,0 +1,587 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Feeder plugins loaded into the broker process.
+//!
+//! A feeder of a custom bus (LIN, FlexRay, a proprietary serial protocol)
+//! can be built as a dynamic library and loaded by the broker, publishing
+//! values directly instead of through gRPC:
+//!
+//! ```json
+//! [
+//!   { "library": "/usr/lib/kuksa/liblin_feeder.so", "config": { "device": "/dev/ttyLIN0" } }
+//! ]
+//! ```
+//!
+//! The plugin interface is a C ABI, declared in
+//! `databroker/include/kuksa_feeder_plugin.h`. A plugin exports
+//!
+//! - `uint32_t kuksa_feeder_abi_version(void)`, returning
+//!   [`ABI_VERSION`],
+//! - `int32_t kuksa_feeder_start(const KuksaHostV1 *host, const char *config)`,
+//!   returning 0 once started. `config` is the `config` of the plugin as
+//!   JSON. The plugin keeps `host` and publishes from threads of its own,
+//! - `void kuksa_feeder_stop(void)`, called on shutdown. The plugin must
+//!   not use `host` after returning.
+//!
+//! Signals are resolved to ids by path once, and values are published by
+//! id. Paths registered after the plugins were started can't be resolved.
+//! Published values are queued and applied in batches. When the queue is
+//! full, [`PUBLISH_QUEUE_FULL`] is returned and the value is dropped.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fmt;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use crate::broker::{DataBroker, Datapoint, EntryUpdate};
+use crate::permissions;
+use crate::types::{DataType, DataValue};
+
+/// Version of the plugin interface implemented by the broker.
+pub const ABI_VERSION: u32 = 1;
+
+pub const VALUE_NOT_AVAILABLE: u32 = 0;
+pub const VALUE_BOOL: u32 = 1;
+pub const VALUE_INT: u32 = 2;
+pub const VALUE_UINT: u32 = 3;
+pub const VALUE_FLOAT: u32 = 4;
+pub const VALUE_STRING: u32 = 5;
+
+pub const PUBLISH_OK: i32 = 0;
+pub const PUBLISH_QUEUE_FULL: i32 = 1;
+pub const PUBLISH_UNKNOWN_ID: i32 = -1;
+pub const PUBLISH_WRONG_TYPE: i32 = -2;
+pub const PUBLISH_STOPPED: i32 = -3;
+
+pub const LOG_ERROR: u32 = 0;
+pub const LOG_WARN: u32 = 1;
+pub const LOG_INFO: u32 = 2;
+pub const LOG_DEBUG: u32 = 3;
+
+const QUEUE_SIZE: usize = 10000;
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// A value published by a plugin. `kind` tells which of the fields holds
+/// it, e.g. `int_value` for [`VALUE_INT`].
+#[repr(C)]
+pub struct KuksaValue {
+    pub kind: u32,
+    pub bool_value: bool,
+    pub int_value: i64,
+    pub uint_value: u64,
+    pub float_value: f64,
+    /// NUL terminated UTF-8
+    pub string_value: *const c_char,
+}
+
+/// The functions of the broker available to a plugin. They may be called
+/// from any thread.
+#[repr(C)]
+pub struct KuksaHostV1 {
+    pub abi_version: u32,
+    pub context: *mut c_void,
+    /// The id of the signal at `path`, -1 if there is none. Only signals
+    /// known when the plugin was started can be resolved and published.
+    pub resolve: extern "C" fn(context: *mut c_void, path: *const c_char) -> i32,
+    /// Publish the current value of a signal. `timestamp_ns` is the time
+    /// the value was sampled in nanoseconds since the UNIX epoch, 0 if
+    /// unknown. Returns one of the `PUBLISH_*` codes.
+    pub publish: extern "C" fn(
+        context: *mut c_void,
+        id: i32,
+        value: *const KuksaValue,
+        timestamp_ns: u64,
+    ) -> i32,
+    /// Log `message` at one of the `LOG_*` levels.
+    pub log: extern "C" fn(context: *mut c_void, level: u32, message: *const c_char),
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+    LoadError(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse plugin config: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid plugin config: {msg}"),
+            Error::LoadError(msg) => write!(f, "failed to load plugin: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub library: PathBuf,
+    /// Passed to the plugin as JSON.
+    #[serde(default)]
+    pub config: serde_json::Value,
+}
+
+pub fn parse_config_from_str(data: &str) -> Result<Vec<PluginConfig>, Error> {
+    let plugins: Vec<PluginConfig> =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    for plugin in &plugins {
+        if plugin.library.as_os_str().is_empty() {
+            return Err(Error::InvalidConfig("empty library path".to_owned()));
+        }
+    }
+    Ok(plugins)
+}
+
+pub fn parse_config_from_reader<R: Read>(mut reader: R) -> Result<Vec<PluginConfig>, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_config_from_str(&data)
+}
+
+type StartFn = unsafe extern "C" fn(host: *const KuksaHostV1, config: *const c_char) -> i32;
+type StopFn = unsafe extern "C" fn();
+
+struct EntryPoints {
+    start: StartFn,
+    stop: StopFn,
+}
+
+/// State behind the context of [`KuksaHostV1`].
+struct Host {
+    name: String,
+    // The signals at the start of the plugin, looked up without the broker
+    // as the plugin calls in from threads of its own
+    ids: HashMap<String, i32>,
+    data_types: HashMap<i32, DataType>,
+    sender: mpsc::Sender<(i32, Datapoint)>,
+}
+
+/// A started plugin. Stopped and unloaded when dropped.
+struct Plugin {
+    stop: StopFn,
+    // Referenced by the plugin until stopped
+    _host: Box<KuksaHostV1>,
+    context: Box<Host>,
+    // Unloaded last
+    _library: Option<libloading::Library>,
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        info!("Stopping feeder plugin {}", self.context.name);
+        // Safety: the plugin was started and is stopped once
+        unsafe { (self.stop)() };
+    }
+}
+
+// Safety: the plugin may call the host from any thread, the context is
+// only read through it
+unsafe impl Send for Plugin {}
+
+/// Load the plugins, start them and keep them running until the broker
+/// shuts down.
+pub async fn start(broker: DataBroker, plugins: Vec<PluginConfig>) -> Result<(), Error> {
+    for config in plugins {
+        let name = config.library.display().to_string();
+        info!("Loading feeder plugin {}", name);
+        // Safety: loading runs the library's initializers, the library is
+        // trusted like the broker binary itself
+        let library = unsafe { libloading::Library::new(&config.library) }
+            .map_err(|err| Error::LoadError(format!("{name}: {err}")))?;
+        let entry_points = unsafe { entry_points(&library) }
+            .map_err(|err| Error::LoadError(format!("{name}: {err}")))?;
+        run(&broker, name, Some(library), entry_points, &config.config).await?;
+    }
+    Ok(())
+}
+
+/// Safety: the symbols must have the signatures of the plugin interface.
+unsafe fn entry_points(library: &libloading::Library) -> Result<EntryPoints, String> {
+    let abi_version = library
+        .get::<unsafe extern "C" fn() -> u32>(b"kuksa_feeder_abi_version\0")
+        .map_err(|err| err.to_string())?;
+    let version = abi_version();
+    if version != ABI_VERSION {
+        return Err(format!(
+            "plugin interface version {version}, the broker implements {ABI_VERSION}"
+        ));
+    }
+    let start = *library
+        .get::<StartFn>(b"kuksa_feeder_start\0")
+        .map_err(|err| err.to_string())?;
+    let stop = *library
+        .get::<StopFn>(b"kuksa_feeder_stop\0")
+        .map_err(|err| err.to_string())?;
+    Ok(EntryPoints { start, stop })
+}
+
+/// Start a plugin. The signals it can resolve and publish, and their data
+/// types, are taken from `broker` now and aren't updated afterwards, so
+/// entries registered later are unknown to the plugin.
+async fn run(
+    broker: &DataBroker,
+    name: String,
+    library: Option<libloading::Library>,
+    entry_points: EntryPoints,
+    config: &serde_json::Value,
+) -> Result<(), Error> {
+    let signals = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .map_entries(|entry| {
+            let metadata = entry.metadata();
+            (
+                metadata.path.clone(),
+                metadata.id,
+                metadata.data_type.clone(),
+            )
+        })
+        .await;
+    let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+    let mut context = Box::new(Host {
+        name: name.clone(),
+        ids: signals
+            .iter()
+            .map(|(path, id, _)| (path.clone(), *id))
+            .collect(),
+        data_types: signals
+            .into_iter()
+            .map(|(_, id, data_type)| (id, data_type))
+            .collect(),
+        sender,
+    });
+    let host = Box::new(KuksaHostV1 {
+        abi_version: ABI_VERSION,
+        context: &mut *context as *mut Host as *mut c_void,
+        resolve,
+        publish,
+        log,
+    });
+    let config = CString::new(config.to_string())
+        .map_err(|err| Error::InvalidConfig(format!("{name}: {err}")))?;
+    // Safety: `host` outlives the plugin, which is stopped before it's
+    // dropped
+    let result = unsafe { (entry_points.start)(&*host, config.as_ptr()) };
+    if result != 0 {
+        return Err(Error::LoadError(format!(
+            "{name}: failed to start with code {result}"
+        )));
+    }
+    info!("Started feeder plugin {}", name);
+    let plugin = Plugin {
+        stop: entry_points.stop,
+        _host: host,
+        context,
+        _library: library,
+    };
+    tokio::spawn(apply(broker.clone(), plugin, receiver));
+    Ok(())
+}
+
+/// Apply the values published by `plugin` until the broker shuts down.
+async fn apply(broker: DataBroker, plugin: Plugin, mut receiver: mpsc::Receiver<(i32, Datapoint)>) {
+    let access = broker.authorized_access(&permissions::ALLOW_ALL);
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    loop {
+        let first = tokio::select! {
+            published = receiver.recv() => match published {
+                Some(published) => published,
+                None => break,
+            },
+            _ = shutdown_trigger.recv() => break,
+        };
+        let mut updates = vec![first];
+        while updates.len() < MAX_BATCH_SIZE {
+            match receiver.try_recv() {
+                Ok(published) => updates.push(published),
+                Err(_) => break,
+            }
+        }
+        let updates = updates.into_iter().map(|(id, datapoint)| {
+            let update = EntryUpdate {
+                datapoint: Some(datapoint),
+                ..Default::default()
+            };
+            (id, update)
+        });
+        if let Err(errors) = access.update_entries(updates).await {
+            for (id, error) in errors {
+                debug!(
+                    "Failed to apply value of id {} from feeder plugin {}: {:?}",
+                    id, plugin.context.name, error
+                );
+            }
+        }
+    }
+    drop(plugin);
+}
+
+/// Safety: `context` is the context of a started plugin's host.
+unsafe fn host<'a>(context: *mut c_void) -> Option<&'a Host> {
+    (context as *const Host).as_ref()
+}
+
+extern "C" fn resolve(context: *mut c_void, path: *const c_char) -> i32 {
+    // Safety: called by the plugin with the context it was given
+    let Some(host) = (unsafe { host(context) }) else {
+        return -1;
+    };
+    if path.is_null() {
+        return -1;
+    }
+    // Safety: NUL terminated as of the plugin interface
+    let path = unsafe { CStr::from_ptr(path) };
+    path.to_str()
+        .ok()
+        .and_then(|path| host.ids.get(path))
+        .copied()
+        .unwrap_or(-1)
+}
+
+extern "C" fn publish(
+    context: *mut c_void,
+    id: i32,
+    value: *const KuksaValue,
+    timestamp_ns: u64,
+) -> i32 {
+    // Safety: called by the plugin with the context it was given
+    let Some(host) = (unsafe { host(context) }) else {
+        return PUBLISH_STOPPED;
+    };
+    let Some(data_type) = host.data_types.get(&id) else {
+        return PUBLISH_UNKNOWN_ID;
+    };
+    // Safety: points to a value as of the plugin interface
+    let Some(value) = (unsafe { value.as_ref() }) else {
+        return PUBLISH_WRONG_TYPE;
+    };
+    // Safety: as above
+    let Some(value) = (unsafe { to_data_value(value, data_type) }) else {
+        return PUBLISH_WRONG_TYPE;
+    };
+    let datapoint = Datapoint {
+        ts: SystemTime::now(),
+        source_ts: (timestamp_ns != 0)
+            .then(|| SystemTime::UNIX_EPOCH + Duration::from_nanos(timestamp_ns)),
+        value,
+    };
+    match host.sender.try_send((id, datapoint)) {
+        Ok(()) => PUBLISH_OK,
+        Err(mpsc::error::TrySendError::Full(_)) => PUBLISH_QUEUE_FULL,
+        Err(mpsc::error::TrySendError::Closed(_)) => PUBLISH_STOPPED,
+    }
+}
+
+extern "C" fn log(context: *mut c_void, level: u32, message: *const c_char) {
+    // Safety: called by the plugin with the context it was given
+    let Some(host) = (unsafe { host(context) }) else {
+        return;
+    };
+    if message.is_null() {
+        return;
+    }
+    // Safety: NUL terminated as of the plugin interface
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    match level {
+        LOG_ERROR => error!("{}: {}", host.name, message),
+        LOG_WARN => warn!("{}: {}", host.name, message),
+        LOG_INFO => info!("{}: {}", host.name, message),
+        _ => debug!("{}: {}", host.name, message),
+    }
+}
+
+/// The value of a signal of `data_type` published as `value`, if it can
+/// hold it. Range checks of the narrow types are left to the broker.
+///
+/// Safety: `string_value` is NUL terminated if `kind` is [`VALUE_STRING`].
+unsafe fn to_data_value(value: &KuksaValue, data_type: &DataType) -> Option<DataValue> {
+    let data_value = match (value.kind, data_type) {
+        (VALUE_NOT_AVAILABLE, _) => DataValue::NotAvailable,
+        (VALUE_BOOL, DataType::Bool) => DataValue::Bool(value.bool_value),
+        (VALUE_INT, DataType::Int8 | DataType::Int16 | DataType::Int32) => {
+            DataValue::Int32(i32::try_from(value.int_value).ok()?)
+        }
+        (VALUE_INT, DataType::Int64) => DataValue::Int64(value.int_value),
+        (VALUE_INT, DataType::Uint8 | DataType::Uint16 | DataType::Uint32) => {
+            DataValue::Uint32(u32::try_from(value.int_value).ok()?)
+        }
+        (VALUE_INT, DataType::Uint64) => DataValue::Uint64(u64::try_from(value.int_value).ok()?),
+        (VALUE_UINT, DataType::Int8 | DataType::Int16 | DataType::Int32) => {
+            DataValue::Int32(i32::try_from(value.uint_value).ok()?)
+        }
+        (VALUE_UINT, DataType::Int64) => DataValue::Int64(i64::try_from(value.uint_value).ok()?),
+        (VALUE_UINT, DataType::Uint8 | DataType::Uint16 | DataType::Uint32) => {
+            DataValue::Uint32(u32::try_from(value.uint_value).ok()?)
+        }
+        (VALUE_UINT, DataType::Uint64) => DataValue::Uint64(value.uint_value),
+        (VALUE_FLOAT, DataType::Float) => DataValue::Float(value.float_value as f32),
+        (VALUE_FLOAT, DataType::Double) => DataValue::Double(value.float_value),
+        (VALUE_STRING, DataType::String) if !value.string_value.is_null() => {
+            DataValue::String(CStr::from_ptr(value.string_value).to_str().ok()?.to_owned())
+        }
+        _ => return None,
+    };
+    Some(data_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+    use super::*;
+    use crate::broker;
+
+    static UNKNOWN_ID_RESULT: AtomicI32 = AtomicI32::new(0);
+    static WRONG_TYPE_RESULT: AtomicI32 = AtomicI32::new(0);
+    static STOPPED: AtomicBool = AtomicBool::new(false);
+
+    fn value(kind: u32) -> KuksaValue {
+        KuksaValue {
+            kind,
+            bool_value: false,
+            int_value: 0,
+            uint_value: 0,
+            float_value: 0.0,
+            string_value: std::ptr::null(),
+        }
+    }
+
+    unsafe extern "C" fn start(host: *const KuksaHostV1, config: *const c_char) -> i32 {
+        let config = CStr::from_ptr(config).to_str().unwrap();
+        assert_eq!(config, r#"{"device":"/dev/ttyLIN0"}"#);
+        let host = &*host;
+        let speed = (host.resolve)(host.context, c"Vehicle.Speed".as_ptr());
+        assert_eq!(
+            (host.resolve)(host.context, c"Vehicle.Unknown".as_ptr()),
+            -1
+        );
+        (host.log)(host.context, LOG_INFO, c"started".as_ptr());
+
+        let speed_value = KuksaValue {
+            float_value: 42.5,
+            ..value(VALUE_FLOAT)
+        };
+        assert_eq!(
+            (host.publish)(host.context, speed, &speed_value, 1_000_000_000),
+            PUBLISH_OK
+        );
+        UNKNOWN_ID_RESULT.store(
+            (host.publish)(host.context, 1000, &speed_value, 0),
+            Ordering::SeqCst,
+        );
+        let text = KuksaValue {
+            string_value: c"fast".as_ptr(),
+            ..value(VALUE_STRING)
+        };
+        WRONG_TYPE_RESULT.store(
+            (host.publish)(host.context, speed, &text, 0),
+            Ordering::SeqCst,
+        );
+        0
+    }
+
+    unsafe extern "C" fn stop() {
+        STOPPED.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let plugins = parse_config_from_str(
+            r#"[
+                { "library": "/usr/lib/kuksa/liblin_feeder.so", "config": { "device": "/dev/ttyLIN0" } },
+                { "library": "libserial_feeder.so" }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(plugins.len(), 2);
+        assert_eq!(plugins[1].config, serde_json::Value::Null);
+
+        assert!(matches!(
+            parse_config_from_str(r#"[{ "library": "" }]"#),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_config_from_str(r#"[{ "path": "libfeeder.so" }]"#),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_plugin() {
+        let broker = DataBroker::default();
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Sensor,
+                "Test".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        run(
+            &broker,
+            "test".to_owned(),
+            None,
+            EntryPoints { start, stop },
+            &serde_json::json!({ "device": "/dev/ttyLIN0" }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(UNKNOWN_ID_RESULT.load(Ordering::SeqCst), PUBLISH_UNKNOWN_ID);
+        assert_eq!(WRONG_TYPE_RESULT.load(Ordering::SeqCst), PUBLISH_WRONG_TYPE);
+
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let mut datapoint = None;
+        for _ in 0..100 {
+            let current = access.get_datapoint_by_path("Vehicle.Speed").await.unwrap();
+            if current.value != DataValue::NotAvailable {
+                datapoint = Some(current);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let datapoint = datapoint.expect("value should be published");
+        assert_eq!(datapoint.value, DataValue::Float(42.5));
+        assert_eq!(
+            datapoint.source_ts,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1))
+        );
+
+        broker.shutdown().await;
+        for _ in 0..100 {
+            if STOPPED.load(Ordering::SeqCst) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(STOPPED.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,187 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Conditional actuation rules.
+//!
+//! A rule denies actuating the paths matching a pattern while a condition
+//! on the current values holds, so that basic interlocks are enforced by
+//! the broker instead of by every application:
+//!
+//! ```json
+//! [
+//!   { "deny": "actuate", "path": "Vehicle.Body.Trunk.*.IsOpen", "when": "Vehicle.Speed > 5" },
+//!   { "deny": "actuate", "path": "Vehicle.Cabin.Door.**.IsOpen", "when": "Vehicle.Speed > 5 OR Vehicle.IsMoving = true" }
+//! ]
+//! ```
+//!
+//! `when` uses the syntax of the `WHERE` clause of queries. It is
+//! evaluated on every actuation request, regardless of whether the caller
+//! may read the entries it refers to. A condition that can't be evaluated,
+//! e.g. because it refers to an unknown entry or to a value that is not
+//! available yet, denies the actuation.
+
+use std::fmt;
+use std::io::Read;
+
+use serde::Deserialize;
+
+use crate::glob;
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse policy config: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid policy config: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The rules restricting actuation, by path pattern.
+#[derive(Default)]
+pub struct Policies {
+    deny_actuate: Vec<(glob::Matcher, String)>,
+}
+
+impl fmt::Debug for Policies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.deny_actuate.iter().map(|(matcher, condition)| {
+                    format!("{} when {}", matcher.as_string(), condition)
+                }),
+            )
+            .finish()
+    }
+}
+
+impl Policies {
+    /// Deny actuating the paths matching `pattern` while `condition` holds.
+    pub fn deny_actuate(&mut self, pattern: &str, condition: &str) -> Result<(), Error> {
+        let matcher = glob::Matcher::new(pattern)
+            .map_err(|_| Error::InvalidConfig(format!("invalid path pattern '{pattern}'")))?;
+        // Types are only known once the entries are registered, so only
+        // the syntax can be checked up front
+        let dialect = sqlparser::dialect::GenericDialect {};
+        let sql = format!("SELECT 1 WHERE {condition}");
+        match sqlparser::parser::Parser::parse_sql(&dialect, &sql) {
+            Ok(statements) if statements.len() == 1 => {}
+            _ => {
+                return Err(Error::InvalidConfig(format!(
+                    "invalid condition '{condition}'"
+                )))
+            }
+        }
+        self.deny_actuate.push((matcher, condition.to_owned()));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deny_actuate.is_empty()
+    }
+
+    /// The conditions under which actuating `path` is denied.
+    pub fn actuate_conditions<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a str> {
+        self.deny_actuate
+            .iter()
+            .filter(move |(matcher, _)| matcher.is_match_vss_path(path))
+            .map(|(_, condition)| condition.as_str())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Actuate,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+    deny: Action,
+    path: String,
+    when: String,
+}
+
+pub fn parse_config_from_str(data: &str) -> Result<Policies, Error> {
+    let rules: Vec<Rule> =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    let mut policies = Policies::default();
+    for rule in rules {
+        match rule.deny {
+            Action::Actuate => policies.deny_actuate(&rule.path, &rule.when)?,
+        }
+    }
+    Ok(policies)
+}
+
+pub fn parse_config_from_reader<R: Read>(mut reader: R) -> Result<Policies, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_config_from_str(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let policies = parse_config_from_str(
+            r#"[
+                { "deny": "actuate", "path": "Vehicle.Body.Trunk.*.IsOpen", "when": "Vehicle.Speed > 5" },
+                { "deny": "actuate", "path": "Vehicle.Body.**", "when": "Vehicle.IsMoving = true" }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            policies
+                .actuate_conditions("Vehicle.Body.Trunk.Rear.IsOpen")
+                .collect::<Vec<_>>(),
+            vec!["Vehicle.Speed > 5", "Vehicle.IsMoving = true"]
+        );
+        assert_eq!(
+            policies
+                .actuate_conditions("Vehicle.Cabin.Light.IsOn")
+                .count(),
+            0
+        );
+
+        assert!(matches!(
+            parse_config_from_str(
+                r#"[{ "deny": "read", "path": "Vehicle.Speed", "when": "Vehicle.Speed > 5" }]"#
+            ),
+            Err(Error::ParseError(_))
+        ));
+        assert!(matches!(
+            parse_config_from_str(
+                r#"[{ "deny": "actuate", "path": "Vehicle.Speed", "when": "Vehicle.Speed >" }]"#
+            ),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            parse_config_from_str(
+                r#"[{ "deny": "actuate", "path": "Vehicle.Speed", "when": "1 = 1; SELECT 2" }]"#
+            ),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
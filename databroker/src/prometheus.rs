@@ -0,0 +1,331 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! HTTP endpoint exposing the health of the broker in the Prometheus text
+//! format, to be scraped by a monitoring system:
+//!
+//! ```text
+//! # HELP kuksa_databroker_entries Number of registered entries
+//! # TYPE kuksa_databroker_entries gauge
+//! kuksa_databroker_entries 1224
+//! # HELP kuksa_databroker_grpc_calls_total Number of gRPC calls by method and status code
+//! # TYPE kuksa_databroker_grpc_calls_total counter
+//! kuksa_databroker_grpc_calls_total{method="/kuksa.val.v2.VAL/GetValue",code="Ok"} 42
+//! ```
+//!
+//! Providers and clients are labeled with the subject of their access
+//! token, empty for clients without one.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::net::SocketAddr;
+
+use axum::{extract::State, http::header, routing::get, Router};
+use tracing::{error, info};
+
+use crate::broker::DataBroker;
+
+pub async fn serve(
+    addr: impl Into<SocketAddr>,
+    broker: DataBroker,
+) -> Result<(), Box<dyn std::error::Error>> {
+    broker.add_served_api("prometheus");
+    let app = Router::new()
+        .route("/metrics", get(handle_metrics))
+        .with_state(broker);
+
+    let addr = addr.into();
+    let builder = axum::Server::try_bind(&addr).map_err(|err| {
+        error!("Failed to bind address {addr}: {err}");
+        err
+    })?;
+
+    info!("Prometheus metrics endpoint listening on {}", addr);
+    builder
+        .serve(app.into_make_service())
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn handle_metrics(
+    State(broker): State<DataBroker>,
+) -> ([(header::HeaderName, &'static str); 1], String) {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics(&broker).await,
+    )
+}
+
+/// Writes metrics in the Prometheus text format.
+#[derive(Default)]
+struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    fn describe(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP kuksa_databroker_{name} {help}");
+        let _ = writeln!(self.out, "# TYPE kuksa_databroker_{name} {kind}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        let _ = write!(self.out, "kuksa_databroker_{name}");
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {value}");
+    }
+
+    fn metric(&mut self, name: &str, kind: &str, help: &str, value: impl Display) {
+        self.describe(name, kind, help);
+        self.sample(name, &[], value);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The metrics of `broker` in the Prometheus text format.
+async fn metrics(broker: &DataBroker) -> String {
+    let usage = broker.get_usage().await;
+    let mut exposition = Exposition::default();
+    for (name, help, value) in [
+        ("entries", "Number of registered entries", usage.entries),
+        (
+            "subscriptions",
+            "Number of active subscriptions",
+            usage.subscriptions,
+        ),
+        (
+            "provider_streams",
+            "Number of open provider streams",
+            usage.provider_streams,
+        ),
+    ] {
+        exposition.metric(name, "gauge", help, value);
+    }
+    for (name, help, value) in [
+        (
+            "quota_rejections_total",
+            "Number of requests rejected because a quota was exceeded",
+            usage.quota_rejections,
+        ),
+        (
+            "removed_subscriptions_total",
+            "Number of subscriptions removed because the client was gone",
+            usage.removed_subscriptions,
+        ),
+        (
+            "ingress_updates_total",
+            "Number of values updated through provider streams",
+            usage.ingress_updates,
+        ),
+        (
+            "ingress_bytes_total",
+            "Number of bytes received on provider streams",
+            usage.ingress_bytes,
+        ),
+        (
+            "throttled_updates_total",
+            "Number of values rejected because a provider stream exceeded its rate",
+            usage.throttled_updates,
+        ),
+        (
+            "rejected_updates_total",
+            "Number of values of provider streams rejected as invalid or not permitted",
+            usage.rejected_updates,
+        ),
+        (
+            "suppressed_updates_total",
+            "Number of values dropped because they were the same as the current value",
+            usage.suppressed_updates,
+        ),
+        (
+            "deprecated_api_calls_total",
+            "Number of calls to deprecated APIs",
+            usage.deprecated_api_calls,
+        ),
+        (
+            "lagged_notifications_total",
+            "Number of notifications missed by subscribers too slow to keep up",
+            usage.lagged_notifications,
+        ),
+        (
+            "authorization_failures_total",
+            "Number of calls rejected for a missing, invalid or expired access token",
+            usage.authorization_failures,
+        ),
+    ] {
+        exposition.metric(name, "counter", help, value);
+    }
+
+    let providers: BTreeMap<_, _> = broker.get_provider_stats().into_iter().collect();
+    exposition.describe(
+        "provider_updates_total",
+        "counter",
+        "Number of values updated through the provider streams of a provider",
+    );
+    for (provider, stats) in &providers {
+        let provider = provider.as_deref().unwrap_or_default();
+        exposition.sample(
+            "provider_updates_total",
+            &[("provider", provider)],
+            stats.updates,
+        );
+    }
+    exposition.describe(
+        "provider_rejected_updates_total",
+        "counter",
+        "Number of values of the provider streams of a provider rejected as invalid or not permitted",
+    );
+    for (provider, stats) in &providers {
+        let provider = provider.as_deref().unwrap_or_default();
+        exposition.sample(
+            "provider_rejected_updates_total",
+            &[("provider", provider)],
+            stats.rejected,
+        );
+    }
+
+    let clients = broker.get_client_usage().await;
+    exposition.describe(
+        "client_requests_total",
+        "counter",
+        "Number of calls of a client to any API",
+    );
+    for usage in &clients {
+        let client = usage.client.as_deref().unwrap_or_default();
+        exposition.sample(
+            "client_requests_total",
+            &[("client", client)],
+            usage.requests,
+        );
+    }
+    exposition.describe(
+        "client_subscriptions",
+        "gauge",
+        "Number of active subscriptions of a client",
+    );
+    for usage in &clients {
+        let client = usage.client.as_deref().unwrap_or_default();
+        exposition.sample(
+            "client_subscriptions",
+            &[("client", client)],
+            usage.subscriptions,
+        );
+    }
+
+    let calls = broker.call_stats().snapshot();
+    exposition.describe(
+        "grpc_calls_total",
+        "counter",
+        "Number of gRPC calls by method and status code",
+    );
+    for stats in &calls {
+        for (code, count) in &stats.codes {
+            let code = format!("{code:?}");
+            exposition.sample(
+                "grpc_calls_total",
+                &[("method", &stats.method), ("code", &code)],
+                count,
+            );
+        }
+    }
+    exposition.describe(
+        "grpc_call_duration_seconds",
+        "histogram",
+        "Duration of gRPC calls until answered, for streams until opened",
+    );
+    for stats in &calls {
+        let mut cumulative = 0;
+        for (bound, count) in &stats.buckets {
+            cumulative += count;
+            let le = bound.map_or("+Inf".to_owned(), |bound| bound.as_secs_f64().to_string());
+            exposition.sample(
+                "grpc_call_duration_seconds_bucket",
+                &[("method", &stats.method), ("le", &le)],
+                cumulative,
+            );
+        }
+        exposition.sample(
+            "grpc_call_duration_seconds_sum",
+            &[("method", &stats.method)],
+            stats.sum.as_secs_f64(),
+        );
+        exposition.sample(
+            "grpc_call_duration_seconds_count",
+            &[("method", &stats.method)],
+            stats.count,
+        );
+    }
+    exposition.out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let broker = DataBroker::default();
+        broker.record_api_call("kuksa.val.v2", false, Some("team \"a\""));
+        broker.record_authorization_failure();
+        let method = "/kuksa.val.v2.VAL/GetValue";
+        let stats = broker.call_stats();
+        stats.record(method, tonic::Code::Ok, Duration::from_micros(50));
+        stats.record(method, tonic::Code::NotFound, Duration::from_secs(10));
+
+        let metrics = metrics(&broker).await;
+        assert!(metrics.contains("# TYPE kuksa_databroker_entries gauge\n"));
+        assert!(metrics.contains("\nkuksa_databroker_entries 0\n"));
+        assert!(metrics.contains("\nkuksa_databroker_authorization_failures_total 1\n"));
+        assert!(metrics
+            .contains("\nkuksa_databroker_client_requests_total{client=\"team \\\"a\\\"\"} 1\n"));
+        assert!(metrics.contains(
+            "\nkuksa_databroker_grpc_calls_total{method=\"/kuksa.val.v2.VAL/GetValue\",code=\"NotFound\"} 1\n"
+        ));
+        // Buckets are cumulative
+        assert!(metrics.contains(
+            "\nkuksa_databroker_grpc_call_duration_seconds_bucket{method=\"/kuksa.val.v2.VAL/GetValue\",le=\"0.0001\"} 1\n"
+        ));
+        assert!(metrics.contains(
+            "\nkuksa_databroker_grpc_call_duration_seconds_bucket{method=\"/kuksa.val.v2.VAL/GetValue\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(metrics.contains(
+            "\nkuksa_databroker_grpc_call_duration_seconds_count{method=\"/kuksa.val.v2.VAL/GetValue\"} 2\n"
+        ));
+        // Every sample is described
+        for line in metrics.lines().filter(|line| !line.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            let family = ["_bucket", "_sum", "_count"]
+                .iter()
+                .find_map(|suffix| name.strip_suffix(suffix))
+                .filter(|family| metrics.contains(&format!("# TYPE {family} histogram")))
+                .unwrap_or(name);
+            assert!(
+                metrics.contains(&format!("# TYPE {family} ")),
+                "{name} is not described"
+            );
+        }
+    }
+}
@@ -0,0 +1,816 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Recording and replay of datapoint updates.
+//!
+//! A recording starts with a header (magic, format version and the wall
+//! clock time the recording was started) followed by a sequence of frames:
+//!
+//! ```text
+//! 0x01 <id: varint> <len: varint> <path: utf8>        path definition
+//! 0x02 <id: varint> <delta_us: varint> <value>        datapoint update
+//! ```
+//!
+//! Paths are only written once and referenced by id afterwards. The time
+//! of an update is stored as the delta to the previous update, which keeps
+//! the log small for signals updated at high frequency.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+use crate::broker::{self, DataBroker};
+use crate::events::Event;
+use crate::glob::Matcher;
+use crate::permissions;
+use crate::types::DataValue;
+
+const MAGIC: &[u8; 4] = b"KDBR";
+const FORMAT_VERSION: u8 = 1;
+
+const FRAME_PATH: u8 = 0x01;
+const FRAME_UPDATE: u8 = 0x02;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    InvalidFormat(String),
+    NoMatchingEntries,
+    SubscriptionFailed(String),
+    InvalidSpeed(f64),
+    // The peer of a replication stream doesn't share the secret
+    AuthenticationFailed,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::InvalidFormat(msg) => write!(f, "invalid recording: {msg}"),
+            Error::NoMatchingEntries => write!(f, "no entries match the recording filter"),
+            Error::SubscriptionFailed(msg) => write!(f, "failed to subscribe: {msg}"),
+            Error::InvalidSpeed(speed) => write!(f, "invalid replay speed {speed}"),
+            Error::AuthenticationFailed => write!(f, "authentication failed"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Time since the start of the recording.
+    pub offset: Duration,
+    pub path: String,
+    pub value: DataValue,
+}
+
+pub struct RecordingWriter<W: Write> {
+    writer: W,
+    paths: HashMap<String, u64>,
+    last_offset: Duration,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    pub fn new(mut writer: W, start_time: SystemTime) -> Result<Self, Error> {
+        let start_us = start_time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_micros() as u64)
+            .unwrap_or_default();
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_all(&start_us.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            paths: HashMap::new(),
+            last_offset: Duration::ZERO,
+        })
+    }
+
+    /// Append a record. Records must be written in order of their offset.
+    pub fn write(&mut self, record: &Record) -> Result<(), Error> {
+        if record.offset < self.last_offset {
+            return Err(Error::InvalidFormat(format!(
+                "record for {} is older than the previous record",
+                record.path
+            )));
+        }
+
+        let id = match self.paths.get(&record.path) {
+            Some(id) => *id,
+            None => {
+                let id = self.paths.len() as u64;
+                self.writer.write_all(&[FRAME_PATH])?;
+                write_varint(&mut self.writer, id)?;
+                write_bytes(&mut self.writer, record.path.as_bytes())?;
+                self.paths.insert(record.path.clone(), id);
+                id
+            }
+        };
+
+        let delta = record.offset - self.last_offset;
+        self.writer.write_all(&[FRAME_UPDATE])?;
+        write_varint(&mut self.writer, id)?;
+        write_varint(&mut self.writer, delta.as_micros() as u64)?;
+        write_value(&mut self.writer, &record.value)?;
+        self.last_offset = record.offset;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+pub struct RecordingReader<R: Read> {
+    reader: R,
+    start_time: SystemTime,
+    paths: Vec<String>,
+    last_offset: Duration,
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::InvalidFormat(
+                "not a databroker recording".to_owned(),
+            ));
+        }
+        let version = read_u8(&mut reader)?;
+        if version != FORMAT_VERSION {
+            return Err(Error::InvalidFormat(format!(
+                "unsupported format version {version}"
+            )));
+        }
+        let mut start = [0u8; 8];
+        reader.read_exact(&mut start)?;
+        let start_time = SystemTime::UNIX_EPOCH + Duration::from_micros(u64::from_le_bytes(start));
+        Ok(Self {
+            reader,
+            start_time,
+            paths: Vec::new(),
+            last_offset: Duration::ZERO,
+        })
+    }
+
+    /// Wall clock time at which the recording was started.
+    pub fn start_time(&self) -> SystemTime {
+        self.start_time
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, Error> {
+        loop {
+            let mut frame = [0u8; 1];
+            if self.reader.read(&mut frame)? == 0 {
+                return Ok(None);
+            }
+            match frame[0] {
+                FRAME_PATH => {
+                    let id = read_varint(&mut self.reader)?;
+                    if id != self.paths.len() as u64 {
+                        return Err(Error::InvalidFormat(format!("unexpected path id {id}")));
+                    }
+                    let path = String::from_utf8(read_bytes(&mut self.reader)?)
+                        .map_err(|_| Error::InvalidFormat("path is not utf-8".to_owned()))?;
+                    self.paths.push(path);
+                }
+                FRAME_UPDATE => {
+                    let id = read_varint(&mut self.reader)?;
+                    let path = self
+                        .paths
+                        .get(id as usize)
+                        .ok_or_else(|| Error::InvalidFormat(format!("unknown path id {id}")))?
+                        .clone();
+                    let delta = Duration::from_micros(read_varint(&mut self.reader)?);
+                    let value = read_value(&mut self.reader)?;
+                    self.last_offset += delta;
+                    return Ok(Some(Record {
+                        offset: self.last_offset,
+                        path,
+                        value,
+                    }));
+                }
+                other => {
+                    return Err(Error::InvalidFormat(format!(
+                        "unknown frame type {other:#04x}"
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Returns true if `path` matches any of the patterns in `filter`, or if
+/// `filter` is empty.
+pub fn matches(filter: &[Matcher], path: &str) -> bool {
+    if filter.is_empty() {
+        return true;
+    }
+    filter.iter().any(|matcher| matcher.is_match_vss_path(path))
+}
+
+/// Copy the records between `from` and `to` (relative to the start of the
+/// recording) whose path matches one of `filter` into a new recording.
+/// An empty filter keeps all paths. Returns the number of records written.
+pub fn trim<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    from: Duration,
+    to: Option<Duration>,
+    filter: &[Matcher],
+) -> Result<usize, Error> {
+    let reader = RecordingReader::new(reader)?;
+    let mut writer = RecordingWriter::new(writer, reader.start_time() + from)?;
+    let mut count = 0;
+    for record in reader {
+        let mut record = record?;
+        if record.offset < from {
+            continue;
+        }
+        if to.is_some_and(|to| record.offset > to) {
+            break;
+        }
+        if !matches(filter, &record.path) {
+            continue;
+        }
+        record.offset -= from;
+        writer.write(&record)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Record the datapoint updates of all entries matching `filter` (all
+/// entries if empty) to `writer` until the broker shuts down. The current
+/// values are written as the first records of the recording. Entries
+/// registered later on are recorded as well.
+pub async fn start_recording<W>(
+    broker: DataBroker,
+    filter: Vec<Matcher>,
+    writer: W,
+) -> Result<(), Error>
+where
+    W: Write + Send + 'static,
+{
+    // Listen before reading the current values, so no update falls in
+    // between
+    let mut events = broker.subscribe_events();
+    let values = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .filter_map_entries(|entry| {
+            let path = &entry.metadata().path;
+            if matches(&filter, path) {
+                Some((path.clone(), entry.datapoint().ok()?.value.clone()))
+            } else {
+                None
+            }
+        })
+        .await;
+    if values.is_empty() {
+        return Err(Error::NoMatchingEntries);
+    }
+    info!("Recording updates of {} entries", values.len());
+
+    let clock = broker.clock();
+    let mut writer = RecordingWriter::new(BufWriter::new(writer), clock.now())?;
+    let started = clock.elapsed();
+    for (path, value) in values {
+        writer.write(&Record {
+            offset: Duration::ZERO,
+            path,
+            value,
+        })?;
+    }
+    writer.flush()?;
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Recording missed {} updates", missed);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = shutdown_trigger.recv() => break,
+            };
+            let Event::ValueChanged {
+                path, datapoint, ..
+            } = event
+            else {
+                continue;
+            };
+            if !matches(&filter, &path) {
+                continue;
+            }
+            let record = Record {
+                offset: clock.elapsed().saturating_sub(started),
+                path,
+                value: datapoint.value,
+            };
+            if let Err(err) = writer.write(&record) {
+                warn!("Recording stopped: {}", err);
+                return;
+            }
+            // Flush once the events received so far are written
+            if events.is_empty() {
+                if let Err(err) = writer.flush() {
+                    warn!("Recording stopped: {}", err);
+                    return;
+                }
+            }
+        }
+        debug!("Recording finished");
+        let _ = writer.flush();
+    });
+    Ok(())
+}
+
+/// Replay a recording into the broker, preserving the original timing.
+/// `speed` scales the playback rate, e.g. 2.0 replays twice as fast.
+pub async fn start_replay<R>(broker: DataBroker, reader: R, speed: f64) -> Result<(), Error>
+where
+    R: Read + Send + 'static,
+{
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(Error::InvalidSpeed(speed));
+    }
+    let reader = RecordingReader::new(BufReader::new(reader))?;
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+
+    tokio::spawn(async move {
+        let clock = broker.clock();
+        let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+        let started = tokio::time::Instant::now();
+        let mut ids: HashMap<String, Option<i32>> = HashMap::new();
+        let mut count = 0;
+
+        for record in reader {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    warn!("Replay stopped: {}", err);
+                    return;
+                }
+            };
+
+            let deadline = started + record.offset.div_f64(speed);
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => {}
+                _ = shutdown_trigger.recv() => return,
+            }
+
+            let id = match ids.get(&record.path) {
+                Some(id) => *id,
+                None => {
+                    let id = broker.get_id_by_path(&record.path).await;
+                    if id.is_none() {
+                        warn!("Replay: {} not found, skipping", record.path);
+                    }
+                    ids.insert(record.path.clone(), id);
+                    id
+                }
+            };
+            let Some(id) = id else {
+                continue;
+            };
+
+            let update = broker::EntryUpdate {
+                datapoint: Some(broker::Datapoint {
+                    ts: clock.now(),
+                    source_ts: None,
+                    value: record.value,
+                }),
+                ..Default::default()
+            };
+            if let Err(errors) = broker.update_entries([(id, update)]).await {
+                if let Some((_, error)) = errors.first() {
+                    debug!("Replay failed to set {}: {:?}", record.path, error);
+                }
+            }
+            count += 1;
+        }
+        info!("Replay finished after {} updates", count);
+    });
+    Ok(())
+}
+
+pub(crate) fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+pub(crate) fn read_varint<R: Read>(reader: &mut R) -> Result<u64, Error> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(reader)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::InvalidFormat("varint too long".to_owned()))
+}
+
+pub(crate) fn read_u8<R: Read>(reader: &mut R) -> Result<u8, Error> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+pub(crate) fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_varint(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+pub(crate) fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let len = read_varint(reader)? as usize;
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(Error::InvalidFormat("truncated record".to_owned()));
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn read_string<R: Read>(reader: &mut R) -> Result<String, Error> {
+    String::from_utf8(read_bytes(reader)?)
+        .map_err(|_| Error::InvalidFormat("string is not utf-8".to_owned()))
+}
+
+fn read_array<R: Read, T>(
+    reader: &mut R,
+    mut read: impl FnMut(&mut R) -> Result<T, Error>,
+) -> Result<Vec<T>, Error> {
+    let len = read_varint(reader)?;
+    (0..len).map(|_| read(reader)).collect()
+}
+
+macro_rules! read_le {
+    ($reader:expr, $ty:ty) => {{
+        let mut bytes = [0u8; std::mem::size_of::<$ty>()];
+        $reader
+            .read_exact(&mut bytes)
+            .map(|_| <$ty>::from_le_bytes(bytes))
+    }};
+}
+
+pub(crate) fn write_value<W: Write>(writer: &mut W, value: &DataValue) -> io::Result<()> {
+    match value {
+        DataValue::NotAvailable => writer.write_all(&[0]),
+        DataValue::Bool(value) => writer.write_all(&[1, u8::from(*value)]),
+        DataValue::String(value) => {
+            writer.write_all(&[2])?;
+            write_bytes(writer, value.as_bytes())
+        }
+        DataValue::Int32(value) => {
+            writer.write_all(&[3])?;
+            writer.write_all(&value.to_le_bytes())
+        }
+        DataValue::Int64(value) => {
+            writer.write_all(&[4])?;
+            writer.write_all(&value.to_le_bytes())
+        }
+        DataValue::Uint32(value) => {
+            writer.write_all(&[5])?;
+            writer.write_all(&value.to_le_bytes())
+        }
+        DataValue::Uint64(value) => {
+            writer.write_all(&[6])?;
+            writer.write_all(&value.to_le_bytes())
+        }
+        DataValue::Float(value) => {
+            writer.write_all(&[7])?;
+            writer.write_all(&value.to_le_bytes())
+        }
+        DataValue::Double(value) => {
+            writer.write_all(&[8])?;
+            writer.write_all(&value.to_le_bytes())
+        }
+        DataValue::BoolArray(values) => {
+            writer.write_all(&[9])?;
+            write_varint(writer, values.len() as u64)?;
+            for value in values {
+                writer.write_all(&[u8::from(*value)])?;
+            }
+            Ok(())
+        }
+        DataValue::StringArray(values) => {
+            writer.write_all(&[10])?;
+            write_varint(writer, values.len() as u64)?;
+            for value in values {
+                write_bytes(writer, value.as_bytes())?;
+            }
+            Ok(())
+        }
+        DataValue::Int32Array(values) => {
+            writer.write_all(&[11])?;
+            write_varint(writer, values.len() as u64)?;
+            values
+                .iter()
+                .try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+        }
+        DataValue::Int64Array(values) => {
+            writer.write_all(&[12])?;
+            write_varint(writer, values.len() as u64)?;
+            values
+                .iter()
+                .try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+        }
+        DataValue::Uint32Array(values) => {
+            writer.write_all(&[13])?;
+            write_varint(writer, values.len() as u64)?;
+            values
+                .iter()
+                .try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+        }
+        DataValue::Uint64Array(values) => {
+            writer.write_all(&[14])?;
+            write_varint(writer, values.len() as u64)?;
+            values
+                .iter()
+                .try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+        }
+        DataValue::FloatArray(values) => {
+            writer.write_all(&[15])?;
+            write_varint(writer, values.len() as u64)?;
+            values
+                .iter()
+                .try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+        }
+        DataValue::DoubleArray(values) => {
+            writer.write_all(&[16])?;
+            write_varint(writer, values.len() as u64)?;
+            values
+                .iter()
+                .try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+        }
+    }
+}
+
+pub(crate) fn read_value<R: Read>(reader: &mut R) -> Result<DataValue, Error> {
+    let value = match read_u8(reader)? {
+        0 => DataValue::NotAvailable,
+        1 => DataValue::Bool(read_u8(reader)? != 0),
+        2 => DataValue::String(read_string(reader)?),
+        3 => DataValue::Int32(read_le!(reader, i32)?),
+        4 => DataValue::Int64(read_le!(reader, i64)?),
+        5 => DataValue::Uint32(read_le!(reader, u32)?),
+        6 => DataValue::Uint64(read_le!(reader, u64)?),
+        7 => DataValue::Float(read_le!(reader, f32)?),
+        8 => DataValue::Double(read_le!(reader, f64)?),
+        9 => DataValue::BoolArray(read_array(reader, |r| Ok(read_u8(r)? != 0))?),
+        10 => DataValue::StringArray(read_array(reader, read_string)?),
+        11 => DataValue::Int32Array(read_array(reader, |r| Ok(read_le!(r, i32)?))?),
+        12 => DataValue::Int64Array(read_array(reader, |r| Ok(read_le!(r, i64)?))?),
+        13 => DataValue::Uint32Array(read_array(reader, |r| Ok(read_le!(r, u32)?))?),
+        14 => DataValue::Uint64Array(read_array(reader, |r| Ok(read_le!(r, u64)?))?),
+        15 => DataValue::FloatArray(read_array(reader, |r| Ok(read_le!(r, f32)?))?),
+        16 => DataValue::DoubleArray(read_array(reader, |r| Ok(read_le!(r, f64)?))?),
+        other => return Err(Error::InvalidFormat(format!("unknown value type {other}"))),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChangeType, DataType, EntryType};
+
+    fn record(offset_ms: u64, path: &str, value: DataValue) -> Record {
+        Record {
+            offset: Duration::from_millis(offset_ms),
+            path: path.to_owned(),
+            value,
+        }
+    }
+
+    fn encode(records: &[Record]) -> Vec<u8> {
+        let mut writer = RecordingWriter::new(Vec::new(), SystemTime::UNIX_EPOCH).unwrap();
+        for record in records {
+            writer.write(record).unwrap();
+        }
+        writer.writer
+    }
+
+    fn decode(data: &[u8]) -> Vec<Record> {
+        RecordingReader::new(data)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let records = vec![
+            record(0, "Vehicle.Speed", DataValue::Float(0.0)),
+            record(0, "Vehicle.IsMoving", DataValue::NotAvailable),
+            record(10, "Vehicle.Speed", DataValue::Float(12.5)),
+            record(10, "Vehicle.IsMoving", DataValue::Bool(true)),
+            record(
+                25,
+                "Vehicle.Cabin.Door.Row1.Left.Window.Position",
+                DataValue::Uint32Array(vec![1, 2, 3]),
+            ),
+            record(
+                2500,
+                "Vehicle.VehicleIdentification.VIN",
+                DataValue::String("VIN1234".to_owned()),
+            ),
+            record(
+                2600,
+                "Vehicle.Tags",
+                DataValue::StringArray(vec!["a".to_owned(), "".to_owned()]),
+            ),
+            record(3000, "Vehicle.Odometer", DataValue::DoubleArray(vec![1.5])),
+            record(3000, "Vehicle.Count", DataValue::Int64(-7)),
+        ];
+        assert_eq!(decode(&encode(&records)), records);
+    }
+
+    #[test]
+    fn test_paths_written_once() {
+        let one = encode(&[record(0, "Vehicle.Speed", DataValue::Int32(1))]);
+        let two = encode(&[
+            record(0, "Vehicle.Speed", DataValue::Int32(1)),
+            record(1, "Vehicle.Speed", DataValue::Int32(2)),
+        ]);
+        // frame type + id + delta + value tag + i32
+        assert_eq!(two.len() - one.len(), 1 + 1 + 2 + 1 + 4);
+    }
+
+    #[test]
+    fn test_invalid_recording() {
+        assert!(matches!(
+            RecordingReader::new(&b"nope"[..]),
+            Err(Error::InvalidFormat(_))
+        ));
+
+        let mut data = encode(&[record(0, "Vehicle.Speed", DataValue::Int32(1))]);
+        data.truncate(data.len() - 2);
+        let result: Result<Vec<_>, _> = RecordingReader::new(&data[..]).unwrap().collect();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_trim() {
+        let data = encode(&[
+            record(0, "Vehicle.Speed", DataValue::Int32(1)),
+            record(100, "Vehicle.Speed", DataValue::Int32(2)),
+            record(150, "Vehicle.IsMoving", DataValue::Bool(true)),
+            record(200, "Vehicle.Speed", DataValue::Int32(3)),
+            record(300, "Vehicle.Speed", DataValue::Int32(4)),
+        ]);
+        let mut trimmed = Vec::new();
+        let count = trim(
+            &data[..],
+            &mut trimmed,
+            Duration::from_millis(100),
+            Some(Duration::from_millis(200)),
+            &[Matcher::new("Vehicle.Speed").unwrap()],
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+
+        let reader = RecordingReader::new(&trimmed[..]).unwrap();
+        assert_eq!(
+            reader.start_time(),
+            SystemTime::UNIX_EPOCH + Duration::from_millis(100)
+        );
+        assert_eq!(
+            reader.collect::<Result<Vec<_>, _>>().unwrap(),
+            vec![
+                record(0, "Vehicle.Speed", DataValue::Int32(2)),
+                record(100, "Vehicle.Speed", DataValue::Int32(3)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_updates_broker() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let data = encode(&[
+            record(0, "Vehicle.Speed", DataValue::Float(1.0)),
+            record(0, "Vehicle.Unknown", DataValue::Float(1.0)),
+            record(20, "Vehicle.Speed", DataValue::Float(2.0)),
+        ]);
+        start_replay(broker.clone(), io::Cursor::new(data), 1.0)
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let datapoint = authorized_access.get_datapoint(id).await.unwrap();
+        assert_eq!(datapoint.value, DataValue::Float(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_recording_captures_updates() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let file = std::env::temp_dir().join(format!("databroker-{}.rec", std::process::id()));
+        start_recording(
+            broker.clone(),
+            vec![Matcher::new("Vehicle.**").unwrap()],
+            std::fs::File::create(&file).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let update = broker::EntryUpdate {
+            datapoint: Some(broker::Datapoint {
+                ts: SystemTime::now(),
+                source_ts: None,
+                value: DataValue::Float(42.0),
+            }),
+            ..Default::default()
+        };
+        authorized_access
+            .update_entries([(id, update)])
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let records: Vec<Record> = RecordingReader::new(std::fs::File::open(&file).unwrap())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let _ = std::fs::remove_file(&file);
+        let values: Vec<_> = records.into_iter().map(|record| record.value).collect();
+        assert_eq!(
+            values,
+            vec![DataValue::NotAvailable, DataValue::Float(42.0)]
+        );
+    }
+}
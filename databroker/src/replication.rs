@@ -0,0 +1,1182 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Hot-standby replication between two broker instances.
+//!
+//! A primary broker accepts standby brokers on a dedicated address, see
+//! [`serve`]. A standby mirrors the entries (keeping their ids), values
+//! and actuator targets of the primary as well as which actuators are
+//! claimed by a provider, see [`follow`]. It doesn't serve clients while
+//! following. Once it hasn't heard from the primary for the takeover
+//! timeout, it takes over, i.e. starts its listeners on the same virtual
+//! IP or unix socket path. Clients and providers reconnect like after a
+//! restart of the primary, but find the same entries and values.
+//!
+//! The primary and its standbys share a secret and authenticate each
+//! other before anything is replicated. The primary sends a header (magic
+//! and format version) and a random nonce, the standby answers with a
+//! nonce of its own and the HMAC-SHA256 of both nonces, keyed with the
+//! secret, and the primary proves itself the same way. The secret itself is
+//! never sent. The stream can additionally be wrapped in TLS to keep the
+//! values confidential, see [`Security`].
+//!
+//! Then the primary sends batches of frames, `<len: u32 le> <frames> <mac>`,
+//! each authenticated with the HMAC-SHA256 of its sequence number and
+//! frames, keyed with a key derived from both nonces. So batches can't be
+//! forged, replayed or reordered either:
+//!
+//! ```text
+//! 0x01 <id: varint> <metadata>                  entry
+//! 0x02 <id: varint> <datapoint>                 current value
+//! 0x03 <id: varint> 0x00 | 0x01 <datapoint>     actuator target, cleared or set
+//! 0x04 <count: varint> <id: varint>...          actuators claimed by a provider
+//! ```
+//!
+//! Values are encoded like in a [recording](crate::recording). The claimed
+//! actuators are sent every [`HEARTBEAT_INTERVAL`], which lets the standby
+//! tell a lost primary from one without updates.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Cursor, Read, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::broker::{DataBroker, Datapoint, EntryUpdate, Metadata};
+use crate::events::Event;
+use crate::permissions;
+use crate::recording::{self, Error};
+use crate::types::{ChangeType, DataType, DataValue, EntryType, SafetyLevel};
+
+const MAGIC: &[u8; 4] = b"KDBS";
+const FORMAT_VERSION: u8 = 3;
+
+const FRAME_ENTRY: u8 = 0x01;
+const FRAME_VALUE: u8 = 0x02;
+const FRAME_TARGET: u8 = 0x03;
+const FRAME_CLAIMED: u8 = 0x04;
+
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+const RECONNECT_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_BATCH_LEN: usize = 64 * 1024 * 1024;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const NONCE_LEN: usize = 32;
+const MAC_LEN: usize = 32;
+const SEQ_LEN: usize = 8;
+// Tell the proofs and the session key apart, so none can stand in for
+// another
+const STANDBY_PROOF: &[u8] = b"standby";
+const PRIMARY_PROOF: &[u8] = b"primary";
+const SESSION_KEY: &[u8] = b"session";
+
+// Enums are sent as their index in these tables
+const DATA_TYPES: [DataType; 24] = [
+    DataType::String,
+    DataType::Bool,
+    DataType::Int8,
+    DataType::Int16,
+    DataType::Int32,
+    DataType::Int64,
+    DataType::Uint8,
+    DataType::Uint16,
+    DataType::Uint32,
+    DataType::Uint64,
+    DataType::Float,
+    DataType::Double,
+    DataType::StringArray,
+    DataType::BoolArray,
+    DataType::Int8Array,
+    DataType::Int16Array,
+    DataType::Int32Array,
+    DataType::Int64Array,
+    DataType::Uint8Array,
+    DataType::Uint16Array,
+    DataType::Uint32Array,
+    DataType::Uint64Array,
+    DataType::FloatArray,
+    DataType::DoubleArray,
+];
+const ENTRY_TYPES: [EntryType; 3] = [EntryType::Sensor, EntryType::Attribute, EntryType::Actuator];
+const CHANGE_TYPES: [ChangeType; 4] = [
+    ChangeType::Static,
+    ChangeType::OnChange,
+    ChangeType::Continuous,
+    ChangeType::Event,
+];
+const SAFETY_LEVELS: [SafetyLevel; 5] = [
+    SafetyLevel::Qm,
+    SafetyLevel::AsilA,
+    SafetyLevel::AsilB,
+    SafetyLevel::AsilC,
+    SafetyLevel::AsilD,
+];
+
+#[derive(Debug, Clone)]
+enum Frame {
+    Entry(Metadata),
+    Value { id: i32, datapoint: Datapoint },
+    Target { id: i32, target: Option<Datapoint> },
+    Claimed(Vec<i32>),
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// The secret shared by a primary and its standbys and, if set, the TLS
+/// configuration to replicate over.
+pub struct Security {
+    key: hmac::Key,
+    rng: SystemRandom,
+    #[cfg(feature = "tls")]
+    acceptor: Option<tokio_rustls::TlsAcceptor>,
+    #[cfg(feature = "tls")]
+    connector: Option<(
+        tokio_rustls::TlsConnector,
+        tokio_rustls::rustls::pki_types::ServerName<'static>,
+    )>,
+}
+
+impl Security {
+    pub fn new(secret: &[u8]) -> Self {
+        Security {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+            rng: SystemRandom::new(),
+            #[cfg(feature = "tls")]
+            acceptor: None,
+            #[cfg(feature = "tls")]
+            connector: None,
+        }
+    }
+
+    /// Accept standbys over TLS, with the certificate chain in `cert` and
+    /// the private key in `key` (PEM).
+    #[cfg(feature = "tls")]
+    pub fn with_tls_identity(mut self, cert: &[u8], key: &[u8]) -> io::Result<Self> {
+        use tokio_rustls::rustls::ServerConfig;
+
+        let certs = rustls_pemfile::certs(&mut &cert[..]).collect::<Result<Vec<_>, _>>()?;
+        let key = rustls_pemfile::private_key(&mut &key[..])?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no private key found"))?;
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.acceptor = Some(Arc::new(config).into());
+        Ok(self)
+    }
+
+    /// Follow the primary over TLS, which needs a certificate for
+    /// `server_name` issued by a CA in `ca_cert` (PEM).
+    #[cfg(feature = "tls")]
+    pub fn with_tls_ca(mut self, ca_cert: &[u8], server_name: &str) -> io::Result<Self> {
+        use tokio_rustls::rustls::pki_types::ServerName;
+        use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut &ca_cert[..]) {
+            roots
+                .add(cert?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        }
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_name = ServerName::try_from(server_name)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?
+            .to_owned();
+        self.connector = Some((Arc::new(config).into(), server_name));
+        Ok(self)
+    }
+
+    async fn accept(&self, stream: TcpStream) -> io::Result<Box<dyn Stream>> {
+        stream.set_nodelay(true)?;
+        #[cfg(feature = "tls")]
+        if let Some(acceptor) = &self.acceptor {
+            return Ok(Box::new(acceptor.accept(stream).await?));
+        }
+        Ok(Box::new(stream))
+    }
+
+    async fn connect(&self, stream: TcpStream) -> io::Result<Box<dyn Stream>> {
+        stream.set_nodelay(true)?;
+        #[cfg(feature = "tls")]
+        if let Some((connector, server_name)) = &self.connector {
+            return Ok(Box::new(
+                connector.connect(server_name.clone(), stream).await?,
+            ));
+        }
+        Ok(Box::new(stream))
+    }
+
+    fn nonce(&self) -> io::Result<[u8; NONCE_LEN]> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| io::Error::other("the random number generator failed"))?;
+        Ok(nonce)
+    }
+
+    fn prove(&self, label: &[u8], primary_nonce: &[u8], standby_nonce: &[u8]) -> hmac::Tag {
+        hmac::sign(&self.key, &[label, primary_nonce, standby_nonce].concat())
+    }
+
+    fn verify(
+        &self,
+        label: &[u8],
+        primary_nonce: &[u8],
+        standby_nonce: &[u8],
+        proof: &[u8],
+    ) -> Result<(), Error> {
+        hmac::verify(
+            &self.key,
+            &[label, primary_nonce, standby_nonce].concat(),
+            proof,
+        )
+        .map_err(|_| Error::AuthenticationFailed)
+    }
+
+    fn session(&self, primary_nonce: &[u8], standby_nonce: &[u8]) -> Session {
+        let key = self.prove(SESSION_KEY, primary_nonce, standby_nonce);
+        Session {
+            key: hmac::Key::new(hmac::HMAC_SHA256, key.as_ref()),
+            seq: 0,
+        }
+    }
+}
+
+/// Authenticates the batches of one replication stream.
+struct Session {
+    key: hmac::Key,
+    seq: u64,
+}
+
+impl Session {
+    fn sign(&mut self, batch: &[u8]) -> hmac::Tag {
+        let mut context = hmac::Context::with_key(&self.key);
+        context.update(&self.seq.to_le_bytes());
+        context.update(batch);
+        self.seq += 1;
+        context.sign()
+    }
+
+    /// Check the `mac` of a batch read into `data` after [`SEQ_LEN`] bytes
+    /// left for the sequence number.
+    fn verify(&mut self, data: &mut [u8], mac: &[u8]) -> Result<(), Error> {
+        data[..SEQ_LEN].copy_from_slice(&self.seq.to_le_bytes());
+        hmac::verify(&self.key, data, mac).map_err(|_| Error::AuthenticationFailed)?;
+        self.seq += 1;
+        Ok(())
+    }
+}
+
+/// Accept standby brokers on `addr` and replicate to them until the broker
+/// shuts down, once they proved to share the secret of `security`. Returns
+/// the address listened on.
+pub async fn serve(
+    broker: DataBroker,
+    addr: impl ToSocketAddrs,
+    security: Security,
+) -> Result<SocketAddr, Error> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    info!("Accepting standby brokers on {}", local_addr);
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    let security = Arc::new(security);
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        warn!("Failed to accept standby: {}", err);
+                        continue;
+                    }
+                },
+                _ = shutdown_trigger.recv() => break,
+            };
+            info!("Standby {} connected", peer);
+            let broker = broker.clone();
+            let security = security.clone();
+            tokio::spawn(async move {
+                match replicate(&broker, &security, stream).await {
+                    Err(Error::AuthenticationFailed) => {
+                        warn!("Standby {} failed to authenticate", peer);
+                    }
+                    Err(err) => info!("Standby {} disconnected: {}", peer, err),
+                    Ok(()) => {}
+                }
+            });
+        }
+        debug!("Replication finished");
+    });
+    Ok(local_addr)
+}
+
+async fn replicate(
+    broker: &DataBroker,
+    security: &Security,
+    stream: TcpStream,
+) -> Result<(), Error> {
+    let handshake = async {
+        let mut stream = security.accept(stream).await?;
+        let session = authenticate_standby(security, &mut stream).await?;
+        Ok::<_, Error>((stream, session))
+    };
+    let (mut stream, mut session) = tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no handshake from the standby"))??;
+
+    // Listen before taking the snapshot, so no update falls in between
+    let mut events = broker.subscribe_events();
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+
+    let mut batch = Vec::new();
+    write_snapshot(broker, &mut batch).await?;
+    send(&mut stream, &mut session, &mut batch).await?;
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        let mut event = tokio::select! {
+            event = events.recv() => event,
+            _ = heartbeat.tick() => {
+                write_claimed(broker, &mut batch).await?;
+                send(&mut stream, &mut session, &mut batch).await?;
+                continue;
+            }
+            _ = shutdown_trigger.recv() => return Ok(()),
+        };
+        // Send everything received so far in one batch
+        loop {
+            match event {
+                Ok(event) => write_event(broker, &event, &mut batch).await?,
+                Err(RecvError::Lagged(missed)) => {
+                    warn!(
+                        "Replication missed {} events, resending all entries",
+                        missed
+                    );
+                    write_snapshot(broker, &mut batch).await?;
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+            event = match events.try_recv() {
+                Ok(event) => Ok(event),
+                Err(TryRecvError::Lagged(missed)) => Err(RecvError::Lagged(missed)),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            };
+        }
+        send(&mut stream, &mut session, &mut batch).await?;
+    }
+}
+
+/// Exchange the proofs of sharing the secret with the standby on `stream`,
+/// returning the session to authenticate the batches with.
+async fn authenticate_standby(
+    security: &Security,
+    stream: &mut Box<dyn Stream>,
+) -> Result<Session, Error> {
+    let nonce = security.nonce()?;
+    stream
+        .write_all(&[&MAGIC[..], &[FORMAT_VERSION], &nonce].concat())
+        .await?;
+    stream.flush().await?;
+
+    let mut reply = [0u8; NONCE_LEN + MAC_LEN];
+    stream.read_exact(&mut reply).await?;
+    let (standby_nonce, proof) = reply.split_at(NONCE_LEN);
+    security.verify(STANDBY_PROOF, &nonce, standby_nonce, proof)?;
+
+    let proof = security.prove(PRIMARY_PROOF, &nonce, standby_nonce);
+    stream.write_all(proof.as_ref()).await?;
+    Ok(security.session(&nonce, standby_nonce))
+}
+
+async fn send(
+    stream: &mut Box<dyn Stream>,
+    session: &mut Session,
+    batch: &mut Vec<u8>,
+) -> io::Result<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let mac = session.sign(batch);
+    stream
+        .write_all(&(batch.len() as u32).to_le_bytes())
+        .await?;
+    stream.write_all(batch).await?;
+    stream.write_all(mac.as_ref()).await?;
+    stream.flush().await?;
+    batch.clear();
+    Ok(())
+}
+
+async fn write_snapshot(broker: &DataBroker, batch: &mut Vec<u8>) -> io::Result<()> {
+    let entries = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .map_entries(|entry| {
+            (
+                entry.metadata().clone(),
+                entry.datapoint().ok().cloned(),
+                entry.actuator_target().ok().cloned().flatten(),
+            )
+        })
+        .await;
+    for (metadata, datapoint, target) in entries {
+        let id = metadata.id;
+        let is_actuator = metadata.entry_type == EntryType::Actuator;
+        write_frame(batch, &Frame::Entry(metadata))?;
+        if let Some(datapoint) = datapoint {
+            if datapoint.value != DataValue::NotAvailable {
+                write_frame(batch, &Frame::Value { id, datapoint })?;
+            }
+        }
+        if is_actuator {
+            write_frame(batch, &Frame::Target { id, target })?;
+        }
+    }
+    write_claimed(broker, batch).await
+}
+
+async fn write_claimed(broker: &DataBroker, batch: &mut Vec<u8>) -> io::Result<()> {
+    let claimed = broker.get_provided_actuators().await;
+    write_frame(batch, &Frame::Claimed(claimed))
+}
+
+async fn write_event(broker: &DataBroker, event: &Event, batch: &mut Vec<u8>) -> io::Result<()> {
+    match event {
+        Event::EntryAdded { id, .. } => {
+            let metadata = broker
+                .authorized_access(&permissions::ALLOW_ALL)
+                .get_metadata(*id)
+                .await;
+            if let Some(metadata) = metadata {
+                write_frame(batch, &Frame::Entry(metadata))?;
+            }
+        }
+        Event::ValueChanged { id, datapoint, .. } => write_frame(
+            batch,
+            &Frame::Value {
+                id: *id,
+                datapoint: datapoint.clone(),
+            },
+        )?,
+        Event::TargetSet { id, target, .. } => write_frame(
+            batch,
+            &Frame::Target {
+                id: *id,
+                target: target.clone(),
+            },
+        )?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Mirror the primary replicating on `addr` into `broker` and return once
+/// it has been gone for `takeover_timeout`, i.e. when it's time to take
+/// over. Connection attempts, including ones where the primary fails to
+/// prove it shares the secret of `security`, count as gone, so a standby
+/// also takes over if the primary never shows up. Returns the paths of the
+/// actuators that were claimed by a provider on the primary.
+pub async fn follow(
+    broker: &DataBroker,
+    addr: &str,
+    security: &Security,
+    takeover_timeout: Duration,
+) -> Vec<String> {
+    let mut standby = Standby {
+        broker,
+        ids: HashMap::new(),
+        claimed: HashSet::new(),
+    };
+    let mut last_seen = Instant::now();
+    loop {
+        let deadline = last_seen + takeover_timeout;
+        match tokio::time::timeout_at(deadline, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => {
+                info!("Following primary {}", addr);
+                if let Err(err) = standby
+                    .follow(stream, security, takeover_timeout, &mut last_seen)
+                    .await
+                {
+                    warn!("Lost primary {}: {}", addr, err);
+                }
+            }
+            Ok(Err(err)) => {
+                debug!("Failed to connect to primary {}: {}", addr, err);
+                tokio::time::sleep_until(deadline.min(Instant::now() + RECONNECT_INTERVAL)).await;
+            }
+            Err(_) => {}
+        }
+        if last_seen.elapsed() >= takeover_timeout {
+            break;
+        }
+    }
+
+    let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+    let mut claimed = Vec::with_capacity(standby.claimed.len());
+    for id in standby.claimed {
+        if let Some(metadata) = broker.get_metadata(id).await {
+            claimed.push(metadata.path);
+        }
+    }
+    claimed.sort();
+    claimed
+}
+
+struct Standby<'a> {
+    broker: &'a DataBroker,
+    // Primary id -> id of the standby, which only differ if the standby
+    // registered the path on its own before
+    ids: HashMap<i32, i32>,
+    claimed: HashSet<i32>,
+}
+
+impl Standby<'_> {
+    async fn follow(
+        &mut self,
+        stream: TcpStream,
+        security: &Security,
+        takeover_timeout: Duration,
+        last_seen: &mut Instant,
+    ) -> Result<(), Error> {
+        let deadline = *last_seen + takeover_timeout;
+        let mut stream = tokio::time::timeout_at(deadline, security.connect(stream))
+            .await
+            .map_err(|_| {
+                io::Error::new(io::ErrorKind::TimedOut, "no handshake from the primary")
+            })??;
+        let mut header = [0u8; 5 + NONCE_LEN];
+        read_before(&mut stream, &mut header, deadline).await?;
+        if &header[..4] != MAGIC {
+            return Err(Error::InvalidFormat(
+                "not a databroker replication stream".to_owned(),
+            ));
+        }
+        if header[4] != FORMAT_VERSION {
+            return Err(Error::InvalidFormat(format!(
+                "unsupported replication format version {}",
+                header[4]
+            )));
+        }
+
+        let primary_nonce = &header[5..];
+        let nonce = security.nonce()?;
+        let proof = security.prove(STANDBY_PROOF, primary_nonce, &nonce);
+        stream
+            .write_all(&[&nonce[..], proof.as_ref()].concat())
+            .await?;
+        stream.flush().await?;
+        let mut proof = [0u8; MAC_LEN];
+        read_before(&mut stream, &mut proof, deadline).await?;
+        security.verify(PRIMARY_PROOF, primary_nonce, &nonce, &proof)?;
+        let mut session = security.session(primary_nonce, &nonce);
+
+        loop {
+            let mut len = [0u8; 4];
+            read_before(&mut stream, &mut len, *last_seen + takeover_timeout).await?;
+            let len = u32::from_le_bytes(len) as usize;
+            if len > MAX_BATCH_LEN {
+                return Err(Error::InvalidFormat(format!("batch of {len} bytes")));
+            }
+            // Leaves room for the sequence number the MAC covers
+            let mut data = vec![0; SEQ_LEN + len];
+            let mut mac = [0u8; MAC_LEN];
+            read_before(
+                &mut stream,
+                &mut data[SEQ_LEN..],
+                *last_seen + takeover_timeout,
+            )
+            .await?;
+            read_before(&mut stream, &mut mac, *last_seen + takeover_timeout).await?;
+            session.verify(&mut data, &mac)?;
+            *last_seen = Instant::now();
+            self.apply(&data[SEQ_LEN..]).await?;
+        }
+    }
+
+    async fn apply(&mut self, batch: &[u8]) -> Result<(), Error> {
+        let mut reader = Cursor::new(batch);
+        let mut updates = Vec::new();
+        while (reader.position() as usize) < batch.len() {
+            match read_frame(&mut reader)? {
+                Frame::Entry(metadata) => self.add_entry(metadata).await,
+                Frame::Value { id, datapoint } => {
+                    if let Some(id) = self.ids.get(&id) {
+                        let update = EntryUpdate {
+                            datapoint: Some(datapoint),
+                            ..Default::default()
+                        };
+                        updates.push((*id, update));
+                    }
+                }
+                Frame::Target { id, target } => {
+                    if let Some(id) = self.ids.get(&id) {
+                        let update = EntryUpdate {
+                            actuator_target: Some(target),
+                            ..Default::default()
+                        };
+                        updates.push((*id, update));
+                    }
+                }
+                Frame::Claimed(ids) => {
+                    self.claimed = ids
+                        .iter()
+                        .filter_map(|id| self.ids.get(id).copied())
+                        .collect();
+                }
+            }
+        }
+        if updates.is_empty() {
+            return Ok(());
+        }
+        let broker = self.broker.authorized_access(&permissions::ALLOW_ALL);
+        if let Err(errors) = broker.update_entries(updates).await {
+            for (id, error) in errors {
+                debug!("Failed to mirror update of id {}: {:?}", id, error);
+            }
+        }
+        Ok(())
+    }
+
+    async fn add_entry(&mut self, metadata: Metadata) {
+        let broker = self.broker.authorized_access(&permissions::ALLOW_ALL);
+        if broker.get_id_by_path(&metadata.path).await.is_none() {
+            self.broker
+                .reserve_ids([(metadata.path.clone(), metadata.id)])
+                .await;
+        }
+        let result = broker
+            .add_entry(
+                metadata.path.clone(),
+                metadata.data_type,
+                metadata.change_type,
+                metadata.entry_type,
+                metadata.description,
+                metadata.min,
+                metadata.max,
+                metadata.allowed,
+                metadata.unit,
+            )
+            .await;
+        match result {
+            Ok(id) => {
+                if id != metadata.id {
+                    warn!(
+                        "Mirrored {} with id {} instead of id {}",
+                        metadata.path, id, metadata.id
+                    );
+                }
+                let _ = broker.set_safety_level(id, metadata.safety_level).await;
+                let _ = broker.set_target_ttl(id, metadata.target_ttl).await;
+                let _ = broker.set_precision(id, metadata.precision).await;
+                self.ids.insert(metadata.id, id);
+            }
+            Err(err) => warn!("Failed to mirror {}: {:?}", metadata.path, err),
+        }
+    }
+}
+
+async fn read_before<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    buf: &mut [u8],
+    deadline: Instant,
+) -> io::Result<()> {
+    match tokio::time::timeout_at(deadline, stream.read_exact(buf)).await {
+        Ok(result) => result.map(|_| ()),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "no heartbeat from the primary",
+        )),
+    }
+}
+
+fn encode<T: PartialEq>(table: &[T], value: &T) -> u8 {
+    table
+        .iter()
+        .position(|candidate| candidate == value)
+        .expect("all variants are listed") as u8
+}
+
+fn decode<T: Clone>(table: &[T], index: u8) -> Result<T, Error> {
+    table
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| Error::InvalidFormat(format!("unknown enum value {index}")))
+}
+
+fn write_flag<W: Write>(writer: &mut W, flag: bool) -> io::Result<()> {
+    writer.write_all(&[u8::from(flag)])
+}
+
+fn read_flag<R: Read>(reader: &mut R) -> Result<bool, Error> {
+    match recording::read_u8(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        other => Err(Error::InvalidFormat(format!("invalid flag {other:#04x}"))),
+    }
+}
+
+fn write_ts<W: Write>(writer: &mut W, ts: SystemTime) -> io::Result<()> {
+    let ts_us = ts
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_micros() as u64)
+        .unwrap_or_default();
+    recording::write_varint(writer, ts_us)
+}
+
+fn read_ts<R: Read>(reader: &mut R) -> Result<SystemTime, Error> {
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_micros(recording::read_varint(reader)?))
+}
+
+fn write_datapoint<W: Write>(writer: &mut W, datapoint: &Datapoint) -> io::Result<()> {
+    write_ts(writer, datapoint.ts)?;
+    write_flag(writer, datapoint.source_ts.is_some())?;
+    if let Some(source_ts) = datapoint.source_ts {
+        write_ts(writer, source_ts)?;
+    }
+    recording::write_value(writer, &datapoint.value)
+}
+
+fn read_datapoint<R: Read>(reader: &mut R) -> Result<Datapoint, Error> {
+    let ts = read_ts(reader)?;
+    let source_ts = if read_flag(reader)? {
+        Some(read_ts(reader)?)
+    } else {
+        None
+    };
+    let value = recording::read_value(reader)?;
+    Ok(Datapoint {
+        ts,
+        source_ts,
+        value,
+    })
+}
+
+fn write_optional_value<W: Write>(writer: &mut W, value: &Option<DataValue>) -> io::Result<()> {
+    write_flag(writer, value.is_some())?;
+    match value {
+        Some(value) => recording::write_value(writer, value),
+        None => Ok(()),
+    }
+}
+
+fn read_optional_value<R: Read>(reader: &mut R) -> Result<Option<DataValue>, Error> {
+    if read_flag(reader)? {
+        Ok(Some(recording::read_value(reader)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub(crate) fn write_metadata<W: Write>(writer: &mut W, metadata: &Metadata) -> io::Result<()> {
+    recording::write_bytes(writer, metadata.path.as_bytes())?;
+    writer.write_all(&[
+        encode(&DATA_TYPES, &metadata.data_type),
+        encode(&ENTRY_TYPES, &metadata.entry_type),
+        encode(&CHANGE_TYPES, &metadata.change_type),
+    ])?;
+    recording::write_bytes(writer, metadata.description.as_bytes())?;
+    write_optional_value(writer, &metadata.min)?;
+    write_optional_value(writer, &metadata.max)?;
+    write_optional_value(writer, &metadata.allowed)?;
+    write_flag(writer, metadata.unit.is_some())?;
+    if let Some(unit) = &metadata.unit {
+        recording::write_bytes(writer, unit.as_bytes())?;
+    }
+    write_flag(writer, metadata.safety_level.is_some())?;
+    if let Some(safety_level) = &metadata.safety_level {
+        writer.write_all(&[encode(&SAFETY_LEVELS, safety_level)])?;
+    }
+    write_flag(writer, metadata.target_ttl.is_some())?;
+    if let Some(target_ttl) = metadata.target_ttl {
+        recording::write_varint(writer, target_ttl.as_millis() as u64)?;
+    }
+    write_flag(writer, metadata.precision.is_some())?;
+    if let Some(precision) = metadata.precision {
+        recording::write_varint(writer, u64::from(precision))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_metadata<R: Read>(reader: &mut R, id: i32) -> Result<Metadata, Error> {
+    let path = recording::read_string(reader)?;
+    let data_type = decode(&DATA_TYPES, recording::read_u8(reader)?)?;
+    let entry_type = decode(&ENTRY_TYPES, recording::read_u8(reader)?)?;
+    let change_type = decode(&CHANGE_TYPES, recording::read_u8(reader)?)?;
+    let description = recording::read_string(reader)?;
+    let min = read_optional_value(reader)?;
+    let max = read_optional_value(reader)?;
+    let allowed = read_optional_value(reader)?;
+    let unit = if read_flag(reader)? {
+        Some(recording::read_string(reader)?)
+    } else {
+        None
+    };
+    let safety_level = if read_flag(reader)? {
+        Some(decode(&SAFETY_LEVELS, recording::read_u8(reader)?)?)
+    } else {
+        None
+    };
+    let target_ttl = if read_flag(reader)? {
+        Some(Duration::from_millis(recording::read_varint(reader)?))
+    } else {
+        None
+    };
+    let precision = if read_flag(reader)? {
+        Some(
+            recording::read_varint(reader)?
+                .try_into()
+                .map_err(|_| Error::InvalidFormat("precision out of range".to_owned()))?,
+        )
+    } else {
+        None
+    };
+    Ok(Metadata {
+        id,
+        glob_path: path.replace('.', "/"),
+        path,
+        data_type,
+        entry_type,
+        change_type,
+        description,
+        min,
+        max,
+        allowed,
+        unit,
+        safety_level,
+        target_ttl,
+        precision,
+    })
+}
+
+fn write_id<W: Write>(writer: &mut W, id: i32) -> io::Result<()> {
+    recording::write_varint(writer, id as u32 as u64)
+}
+
+fn read_id<R: Read>(reader: &mut R) -> Result<i32, Error> {
+    Ok(recording::read_varint(reader)? as u32 as i32)
+}
+
+fn write_frame<W: Write>(writer: &mut W, frame: &Frame) -> io::Result<()> {
+    match frame {
+        Frame::Entry(metadata) => {
+            writer.write_all(&[FRAME_ENTRY])?;
+            write_id(writer, metadata.id)?;
+            write_metadata(writer, metadata)
+        }
+        Frame::Value { id, datapoint } => {
+            writer.write_all(&[FRAME_VALUE])?;
+            write_id(writer, *id)?;
+            write_datapoint(writer, datapoint)
+        }
+        Frame::Target { id, target } => {
+            writer.write_all(&[FRAME_TARGET])?;
+            write_id(writer, *id)?;
+            write_flag(writer, target.is_some())?;
+            match target {
+                Some(target) => write_datapoint(writer, target),
+                None => Ok(()),
+            }
+        }
+        Frame::Claimed(ids) => {
+            writer.write_all(&[FRAME_CLAIMED])?;
+            recording::write_varint(writer, ids.len() as u64)?;
+            ids.iter().try_for_each(|id| write_id(writer, *id))
+        }
+    }
+}
+
+fn read_frame<R: Read>(reader: &mut R) -> Result<Frame, Error> {
+    match recording::read_u8(reader)? {
+        FRAME_ENTRY => {
+            let id = read_id(reader)?;
+            Ok(Frame::Entry(read_metadata(reader, id)?))
+        }
+        FRAME_VALUE => {
+            let id = read_id(reader)?;
+            let datapoint = read_datapoint(reader)?;
+            Ok(Frame::Value { id, datapoint })
+        }
+        FRAME_TARGET => {
+            let id = read_id(reader)?;
+            let target = if read_flag(reader)? {
+                Some(read_datapoint(reader)?)
+            } else {
+                None
+            };
+            Ok(Frame::Target { id, target })
+        }
+        FRAME_CLAIMED => {
+            let count = recording::read_varint(reader)?;
+            let ids = (0..count)
+                .map(|_| read_id(reader))
+                .collect::<Result<_, _>>()?;
+            Ok(Frame::Claimed(ids))
+        }
+        other => Err(Error::InvalidFormat(format!(
+            "unknown frame type {other:#04x}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::{ActuationChange, ActuationError, ActuationProvider};
+
+    const SECRET: &[u8] = b"0123456789abcdef";
+
+    struct Provider;
+
+    #[async_trait::async_trait]
+    impl ActuationProvider for Provider {
+        async fn actuate(
+            &self,
+            _actuation_changes: Vec<ActuationChange>,
+        ) -> Result<(), (ActuationError, String)> {
+            Ok(())
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    fn datapoint(value: DataValue) -> Datapoint {
+        Datapoint {
+            ts: SystemTime::UNIX_EPOCH + Duration::from_millis(1500),
+            source_ts: None,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_frames_round_trip() {
+        let metadata = Metadata {
+            id: 7,
+            path: "Vehicle.Cabin.Door.IsOpen".to_owned(),
+            glob_path: "Vehicle/Cabin/Door/IsOpen".to_owned(),
+            data_type: DataType::Bool,
+            entry_type: EntryType::Actuator,
+            change_type: ChangeType::OnChange,
+            description: "Door".to_owned(),
+            min: None,
+            max: None,
+            allowed: None,
+            unit: Some("none".to_owned()),
+            safety_level: Some(SafetyLevel::AsilB),
+            target_ttl: Some(Duration::from_millis(500)),
+            precision: Some(2),
+        };
+        let mut batch = Vec::new();
+        write_frame(&mut batch, &Frame::Entry(metadata)).unwrap();
+        write_frame(
+            &mut batch,
+            &Frame::Target {
+                id: 7,
+                target: Some(datapoint(DataValue::Bool(true))),
+            },
+        )
+        .unwrap();
+        write_frame(&mut batch, &Frame::Claimed(vec![7, 9])).unwrap();
+
+        let mut reader = Cursor::new(&batch);
+        let Frame::Entry(metadata) = read_frame(&mut reader).unwrap() else {
+            panic!("expected an entry");
+        };
+        assert_eq!(metadata.id, 7);
+        assert_eq!(metadata.path, "Vehicle.Cabin.Door.IsOpen");
+        assert_eq!(metadata.glob_path, "Vehicle/Cabin/Door/IsOpen");
+        assert_eq!(metadata.entry_type, EntryType::Actuator);
+        assert_eq!(metadata.unit.as_deref(), Some("none"));
+        assert_eq!(metadata.safety_level, Some(SafetyLevel::AsilB));
+        assert_eq!(metadata.target_ttl, Some(Duration::from_millis(500)));
+        assert_eq!(metadata.precision, Some(2));
+        let Frame::Target { id, target } = read_frame(&mut reader).unwrap() else {
+            panic!("expected a target");
+        };
+        assert_eq!(id, 7);
+        assert_eq!(target, Some(datapoint(DataValue::Bool(true))));
+        assert!(matches!(
+            read_frame(&mut reader).unwrap(),
+            Frame::Claimed(ids) if ids == [7, 9]
+        ));
+        assert_eq!(reader.position() as usize, batch.len());
+    }
+
+    #[test]
+    fn test_batches_are_authenticated() {
+        let primary = Security::new(SECRET);
+        let standby = Security::new(SECRET);
+        let mut sent = primary.session(&[1; NONCE_LEN], &[2; NONCE_LEN]);
+        let mut received = standby.session(&[1; NONCE_LEN], &[2; NONCE_LEN]);
+
+        let batch = [0u8; SEQ_LEN + 3];
+        let first = sent.sign(&batch[SEQ_LEN..]);
+        let second = sent.sign(&batch[SEQ_LEN..]);
+        // Replayed out of order
+        assert!(matches!(
+            received.verify(&mut batch.clone(), second.as_ref()),
+            Err(Error::AuthenticationFailed)
+        ));
+        received.verify(&mut batch.clone(), first.as_ref()).unwrap();
+        let mut tampered = batch;
+        tampered[SEQ_LEN] = 1;
+        assert!(matches!(
+            received.verify(&mut tampered, second.as_ref()),
+            Err(Error::AuthenticationFailed)
+        ));
+
+        // Another secret or other nonces make another session
+        let mut other =
+            Security::new(b"fedcba9876543210").session(&[1; NONCE_LEN], &[2; NONCE_LEN]);
+        assert!(other.verify(&mut batch.clone(), first.as_ref()).is_err());
+        let mut other = standby.session(&[1; NONCE_LEN], &[3; NONCE_LEN]);
+        assert!(other.verify(&mut batch.clone(), first.as_ref()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_standby_needs_the_secret() {
+        let primary = DataBroker::default();
+        primary
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Speed".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let addr = serve(primary.clone(), "127.0.0.1:0", Security::new(SECRET))
+            .await
+            .unwrap();
+
+        // Never authenticated, so the standby takes over without mirroring
+        let standby = DataBroker::default();
+        let security = Security::new(b"fedcba9876543210");
+        let claimed = follow(
+            &standby,
+            &addr.to_string(),
+            &security,
+            Duration::from_millis(300),
+        )
+        .await;
+        assert!(claimed.is_empty());
+        assert!(standby
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_id_by_path("Vehicle.Speed")
+            .await
+            .is_none());
+        primary.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_standby_takes_over() {
+        let primary = DataBroker::default();
+        let access = primary.authorized_access(&permissions::ALLOW_ALL);
+        let speed = access
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                ChangeType::OnChange,
+                EntryType::Sensor,
+                "Speed".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let door = access
+            .add_entry(
+                "Vehicle.Cabin.Door.IsOpen".to_owned(),
+                DataType::Bool,
+                ChangeType::OnChange,
+                EntryType::Actuator,
+                "Door".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        access
+            .set_safety_level(door, Some(SafetyLevel::AsilB))
+            .await
+            .unwrap();
+        let addr = serve(primary.clone(), "127.0.0.1:0", Security::new(SECRET))
+            .await
+            .unwrap();
+
+        let standby = DataBroker::default();
+        let follower = {
+            let standby = standby.clone();
+            tokio::spawn(async move {
+                let security = Security::new(SECRET);
+                follow(
+                    &standby,
+                    &addr.to_string(),
+                    &security,
+                    Duration::from_millis(300),
+                )
+                .await
+            })
+        };
+
+        access
+            .provide_actuation(vec![door], Box::new(Provider))
+            .await
+            .unwrap();
+        let update = EntryUpdate {
+            datapoint: Some(datapoint(DataValue::Float(42.0))),
+            ..Default::default()
+        };
+        access.update_entries([(speed, update)]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(!follower.is_finished());
+
+        let mirrored = standby.authorized_access(&permissions::ALLOW_ALL);
+        assert_eq!(
+            mirrored.get_datapoint(speed).await.unwrap().value,
+            DataValue::Float(42.0)
+        );
+        let metadata = mirrored.get_metadata(door).await.unwrap();
+        assert_eq!(metadata.path, "Vehicle.Cabin.Door.IsOpen");
+        assert_eq!(metadata.safety_level, Some(SafetyLevel::AsilB));
+
+        primary.shutdown().await;
+        let started = Instant::now();
+        let claimed = follower.await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(600));
+        assert_eq!(claimed, ["Vehicle.Cabin.Door.IsOpen"]);
+    }
+}
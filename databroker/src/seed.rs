@@ -0,0 +1,222 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Initial values applied once the VSS tree is loaded, so that bench setups
+//! and demos start with a realistic vehicle state without running feeders
+//! first:
+//!
+//! ```json
+//! {
+//!   "Vehicle.Speed": 0,
+//!   "Vehicle.Cabin.Door.Row1.DriverSide.IsOpen": false,
+//!   "Vehicle.Cabin.Infotainment.Media.Played.Source": "FM"
+//! }
+//! ```
+//!
+//! Values are checked against the data type, bounds and allowed values of
+//! their signal. Rejected ones are reported and left out, the others are
+//! applied regardless.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use std::time::SystemTime;
+
+use crate::broker::{DataBroker, Datapoint, EntryUpdate, UpdateError};
+use crate::permissions;
+use crate::types::DataValue;
+use crate::vss;
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse seed file: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Seed values by path.
+pub type Seed = BTreeMap<String, serde_json::Value>;
+
+pub fn parse_seed_from_str(data: &str) -> Result<Seed, Error> {
+    serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))
+}
+
+pub fn parse_seed_from_reader<R: Read>(mut reader: R) -> Result<Seed, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_seed_from_str(&data)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejected {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Number of values applied
+    pub applied: usize,
+    /// Ordered by path
+    pub rejected: Vec<Rejected>,
+}
+
+fn reason(error: &UpdateError) -> String {
+    match error {
+        UpdateError::NotFound => "no such signal".to_owned(),
+        UpdateError::WrongType => "wrong data type".to_owned(),
+        UpdateError::OutOfBoundsAllowed => "not an allowed value".to_owned(),
+        UpdateError::OutOfBoundsMinMax => "out of min/max bounds".to_owned(),
+        UpdateError::OutOfBoundsType => "out of type bounds".to_owned(),
+        UpdateError::OutOfBoundsIndex => "array index out of bounds".to_owned(),
+        UpdateError::UnsupportedType => "unsupported data type".to_owned(),
+        UpdateError::PermissionDenied | UpdateError::PermissionExpired => {
+            "permission denied".to_owned()
+        }
+        UpdateError::Conflict => "value has changed".to_owned(),
+        UpdateError::Implausible(reason) => format!("implausible: {reason}"),
+        UpdateError::PolicyViolation(condition) => format!("denied by policy: {condition}"),
+    }
+}
+
+/// Set the current values of the signals in `seed`.
+pub async fn apply(broker: &DataBroker, seed: Seed) -> Report {
+    let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+    let mut report = Report::default();
+    let mut paths = BTreeMap::new();
+    let mut updates = Vec::with_capacity(seed.len());
+    let ts = SystemTime::now();
+    for (path, value) in seed {
+        let Some(metadata) = broker.get_metadata_by_path(&path).await else {
+            report.rejected.push(Rejected {
+                path,
+                reason: "no such signal".to_owned(),
+            });
+            continue;
+        };
+        // null leaves the signal without a value
+        let value = match vss::try_from_json_value(
+            Some(value).filter(|value| !value.is_null()),
+            &metadata.data_type,
+        ) {
+            Ok(value) => value.unwrap_or(DataValue::NotAvailable),
+            Err(err) => {
+                report.rejected.push(Rejected {
+                    path,
+                    reason: format!("expected a {}: {err}", metadata.data_type),
+                });
+                continue;
+            }
+        };
+        updates.push((
+            metadata.id,
+            EntryUpdate {
+                datapoint: Some(Datapoint {
+                    ts,
+                    source_ts: None,
+                    value,
+                }),
+                ..Default::default()
+            },
+        ));
+        paths.insert(metadata.id, path);
+    }
+
+    report.applied = updates.len();
+    if let Err(errors) = broker.update_entries(updates).await {
+        report.applied -= errors.len();
+        for (id, error) in errors {
+            report.rejected.push(Rejected {
+                path: paths.remove(&id).unwrap_or_default(),
+                reason: reason(&error),
+            });
+        }
+    }
+    report
+        .rejected
+        .sort_by(|left, right| left.path.cmp(&right.path));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::tests::helper_add_int32;
+
+    #[tokio::test]
+    async fn test_apply() {
+        let broker = DataBroker::default();
+        let ts = SystemTime::now();
+        let speed = helper_add_int32(&broker, "Vehicle.Speed", 1, ts)
+            .await
+            .unwrap();
+        let gear = helper_add_int32(&broker, "Vehicle.Gear", 1, ts)
+            .await
+            .unwrap();
+        helper_add_int32(&broker, "Vehicle.Rpm", 1, ts)
+            .await
+            .unwrap();
+
+        let seed = parse_seed_from_str(
+            r#"{
+                "Vehicle.Speed": 80,
+                "Vehicle.Gear": null,
+                "Vehicle.Rpm": 5000,
+                "Vehicle.Horn": true
+            }"#,
+        )
+        .unwrap();
+        let report = apply(&broker, seed).await;
+        assert_eq!(report.applied, 2);
+        let rejected: Vec<_> = report
+            .rejected
+            .iter()
+            .map(|rejected| (rejected.path.as_str(), rejected.reason.as_str()))
+            .collect();
+        assert_eq!(
+            rejected,
+            [
+                ("Vehicle.Horn", "no such signal"),
+                ("Vehicle.Rpm", "out of min/max bounds"),
+            ]
+        );
+
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let value = |id| {
+            let access = &access;
+            async move { access.get_datapoint(id).await.unwrap().value }
+        };
+        assert_eq!(value(speed).await, DataValue::Int32(80));
+        assert_eq!(value(gear).await, DataValue::NotAvailable);
+
+        // Values of the wrong type are rejected as well
+        let seed = parse_seed_from_str(r#"{"Vehicle.Speed": "fast"}"#).unwrap();
+        let report = apply(&broker, seed).await;
+        assert_eq!(report.applied, 0);
+        assert_eq!(report.rejected.len(), 1);
+        assert!(report.rejected[0].reason.starts_with("expected a Int32"));
+        assert_eq!(value(speed).await, DataValue::Int32(80));
+
+        assert!(parse_seed_from_str("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Registry of the connections to the gRPC APIs, so that subscriptions and
+//! providers can be traced back to the process holding them. Every
+//! connection is a session with an id, the address (or, on the unix socket,
+//! the process id) of the peer, and what the client declares about itself
+//! in the `kuksa-client` header, e.g. `seat-service/1.2.0`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+
+/// Header a client can declare its name and version with, as
+/// `<name>/<version>` or just `<name>`.
+pub const CLIENT_HEADER: &str = "kuksa-client";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+    pub id: u64,
+    /// The address of the peer, or its process id on the unix socket
+    pub peer: Option<String>,
+    pub connected_at: SystemTime,
+    /// As declared by the client in the `kuksa-client` header
+    pub client_name: Option<String>,
+    pub client_version: Option<String>,
+    /// The subject of the access token of the latest call
+    pub subject: Option<String>,
+    /// Currently active subscriptions
+    pub subscriptions: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+    last_id: AtomicU64,
+    sessions: Mutex<BTreeMap<u64, SessionInfo>>,
+}
+
+/// A registered session, removed from the registry when dropped, i.e. when
+/// the connection is closed.
+#[derive(Debug)]
+pub struct Session {
+    id: u64,
+    sessions: Weak<Sessions>,
+}
+
+impl Session {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if let Some(sessions) = self.sessions.upgrade() {
+            sessions
+                .sessions
+                .lock()
+                .expect("sessions should not be poisoned")
+                .remove(&self.id);
+        }
+    }
+}
+
+/// Split the value of the `kuksa-client` header into name and version.
+fn parse_client(header: &str) -> (Option<String>, Option<String>) {
+    let non_empty = |value: &str| Some(value.trim().to_owned()).filter(|value| !value.is_empty());
+    match header.split_once('/') {
+        Some((name, version)) => (non_empty(name), non_empty(version)),
+        None => (non_empty(header), None),
+    }
+}
+
+impl Sessions {
+    /// Register a new connection from `peer`.
+    pub fn open(self: &Arc<Self>, peer: Option<String>) -> Session {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.sessions
+            .lock()
+            .expect("sessions should not be poisoned")
+            .insert(
+                id,
+                SessionInfo {
+                    id,
+                    peer,
+                    connected_at: SystemTime::now(),
+                    client_name: None,
+                    client_version: None,
+                    subject: None,
+                    subscriptions: 0,
+                },
+            );
+        Session {
+            id,
+            sessions: Arc::downgrade(self),
+        }
+    }
+
+    /// Record what is known about the client of session `id` from one of
+    /// its calls: the value of its `kuksa-client` header and the subject of
+    /// its access token. A call without the header keeps what an earlier
+    /// call declared.
+    pub fn identify(&self, id: u64, client: Option<&str>, subject: Option<&str>) {
+        let mut sessions = self
+            .sessions
+            .lock()
+            .expect("sessions should not be poisoned");
+        if let Some(session) = sessions.get_mut(&id) {
+            if let Some(client) = client {
+                (session.client_name, session.client_version) = parse_client(client);
+            }
+            if session.subject.as_deref() != subject {
+                session.subject = subject.map(str::to_owned);
+            }
+        }
+    }
+
+    /// All open sessions, ordered by id. The subscriptions are filled in
+    /// from `subscriptions`, the number of active subscriptions per
+    /// session.
+    pub fn list(&self, subscriptions: &HashMap<u64, usize>) -> Vec<SessionInfo> {
+        self.sessions
+            .lock()
+            .expect("sessions should not be poisoned")
+            .values()
+            .map(|session| SessionInfo {
+                subscriptions: subscriptions.get(&session.id).copied().unwrap_or_default(),
+                ..session.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions() {
+        let sessions = Arc::new(Sessions::default());
+        let first = sessions.open(Some("127.0.0.1:40000".to_owned()));
+        let second = sessions.open(None);
+        assert_eq!((first.id(), second.id()), (1, 2));
+
+        sessions.identify(first.id(), Some("seat-service/1.2.0"), Some("seats"));
+        // Calls without the header don't forget the name
+        sessions.identify(first.id(), None, Some("seats"));
+        sessions.identify(second.id(), Some("dashboard"), None);
+
+        let listed = sessions.list(&HashMap::from([(1, 3)]));
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].peer.as_deref(), Some("127.0.0.1:40000"));
+        assert_eq!(listed[0].client_name.as_deref(), Some("seat-service"));
+        assert_eq!(listed[0].client_version.as_deref(), Some("1.2.0"));
+        assert_eq!(listed[0].subject.as_deref(), Some("seats"));
+        assert_eq!(listed[0].subscriptions, 3);
+        assert_eq!(listed[1].client_name.as_deref(), Some("dashboard"));
+        assert_eq!(listed[1].client_version, None);
+        assert_eq!(listed[1].subscriptions, 0);
+
+        drop(first);
+        let listed = sessions.list(&HashMap::new());
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, 2);
+    }
+}
@@ -0,0 +1,94 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Named groups of related signals, e.g. the wheel speeds, that are read
+//! and subscribed to as a whole. The values of a group are always read from
+//! the same state, so consumers never see part of the values set by a call
+//! and part of those from before. Groups are defined at runtime, or up front
+//! in a file:
+//!
+//! ```json
+//! {
+//!   "WheelSpeeds": [
+//!     "Vehicle.Chassis.Axle.Row1.Wheel.Left.Speed",
+//!     "Vehicle.Chassis.Axle.Row1.Wheel.Right.Speed"
+//!   ]
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse signal groups: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The paths of the signals of each group, by name.
+pub type SignalGroups = BTreeMap<String, Vec<String>>;
+
+pub fn parse_signal_groups_from_str(data: &str) -> Result<SignalGroups, Error> {
+    let groups: SignalGroups =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    for (name, paths) in &groups {
+        if name.is_empty() {
+            return Err(Error::ParseError("group without name".to_owned()));
+        }
+        if paths.is_empty() {
+            return Err(Error::ParseError(format!("group {name} has no signals")));
+        }
+    }
+    Ok(groups)
+}
+
+pub fn parse_signal_groups_from_reader<R: Read>(mut reader: R) -> Result<SignalGroups, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_signal_groups_from_str(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal_groups() {
+        let groups = parse_signal_groups_from_str(
+            r#"{
+                "WheelSpeeds": [
+                    "Vehicle.Chassis.Axle.Row1.Wheel.Left.Speed",
+                    "Vehicle.Chassis.Axle.Row1.Wheel.Right.Speed"
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(groups["WheelSpeeds"].len(), 2);
+
+        assert!(parse_signal_groups_from_str(r#"{"WheelSpeeds": []}"#).is_err());
+        assert!(parse_signal_groups_from_str(r#"{"": ["Vehicle.Speed"]}"#).is_err());
+        assert!(parse_signal_groups_from_str(r#"{"WheelSpeeds": "Vehicle.Speed"}"#).is_err());
+    }
+}
@@ -0,0 +1,952 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Built-in signal simulator.
+//!
+//! Generates values for selected paths according to a configured pattern
+//! so that applications can be tested without any real feeders attached.
+//!
+//! Example configuration:
+//!
+//! ```json
+//! {
+//!   "signals": [
+//!     { "path": "Vehicle.Speed", "interval_ms": 100,
+//!       "pattern": { "type": "sine", "amplitude": 50, "offset": 50, "period_ms": 10000 } },
+//!     { "path": "Vehicle.Powertrain.TractionBattery.StateOfCharge.Current", "interval_ms": 1000,
+//!       "pattern": { "type": "ramp", "start": 100, "end": 0, "step": -0.5 } },
+//!     { "path": "Vehicle.Cabin.HVAC.AmbientAirTemperature", "interval_ms": 500,
+//!       "pattern": { "type": "random_walk", "start": 21, "max_step": 0.2, "min": 15, "max": 30 } },
+//!     { "path": "Vehicle.Body.Lights.Beam.Low.IsOn", "interval_ms": 2000,
+//!       "pattern": { "type": "sequence", "values": [true, false] } }
+//!   ],
+//!   "actuators": [
+//!     { "path": "Vehicle.Body.Trunk.Rear.IsOpen", "delay_ms": 500 },
+//!     { "path": "Vehicle.Cabin.Seat.Row1.DriverSide.Position", "delay_ms": 200,
+//!       "ramp": { "rate": 50, "interval_ms": 100 } }
+//!   ]
+//! }
+//! ```
+//!
+//! The listed actuators act as if a provider moved them: the targets
+//! requested through `Actuate` of the v2 API and the targets set through
+//! the v1 API become their current values after `delay_ms`. With a `ramp`,
+//! numeric values move towards the target by `rate` per second. A new
+//! target replaces the one the actuator is moving to. Once a provider
+//! claims an actuator, its actuation requests go to the provider instead.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+use tokio::sync::watch;
+use tokio_stream::StreamExt;
+use tracing::{debug, info, warn};
+
+use crate::broker::{
+    self, ActuationChange, ActuationError, ActuationProvider, DataBroker, InitialSnapshot,
+};
+use crate::permissions;
+use crate::types::{DataType, DataValue, EntryType};
+use crate::vss;
+
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const DEFAULT_RAMP_INTERVAL_MS: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct SimulationConfig {
+    #[serde(default)]
+    pub signals: Vec<SignalSimulation>,
+    #[serde(default)]
+    pub actuators: Vec<ActuatorSimulation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignalSimulation {
+    pub path: String,
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    pub pattern: Pattern,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActuatorSimulation {
+    pub path: String,
+    /// Time until the actuator starts moving towards a new target
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Move numeric values gradually instead of jumping to the target
+    #[serde(default)]
+    pub ramp: Option<Ramp>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Ramp {
+    /// Change of the value per second
+    pub rate: f64,
+    #[serde(default = "default_ramp_interval_ms")]
+    pub interval_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Pattern {
+    /// offset + amplitude * sin(2π * t / period)
+    Sine {
+        amplitude: f64,
+        #[serde(default)]
+        offset: f64,
+        period_ms: u64,
+    },
+    /// Moves from `start` towards `end` by `step` on every tick and
+    /// starts over once `end` has been passed.
+    Ramp { start: f64, end: f64, step: f64 },
+    /// Moves randomly by at most `max_step` on every tick, bounded by `min` and `max`.
+    RandomWalk {
+        start: f64,
+        max_step: f64,
+        min: f64,
+        max: f64,
+    },
+    /// Emits the given values in order.
+    Sequence {
+        values: Vec<serde_json::Value>,
+        #[serde(default = "default_repeat")]
+        repeat: bool,
+    },
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ParseError(error) => write!(f, "{error}"),
+            Error::InvalidConfig(error) => write!(f, "invalid simulation config: {error}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(from: serde_json::Error) -> Self {
+        Error::ParseError(from.to_string())
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn default_interval_ms() -> u64 {
+    DEFAULT_INTERVAL_MS
+}
+
+fn default_ramp_interval_ms() -> u64 {
+    DEFAULT_RAMP_INTERVAL_MS
+}
+
+fn default_repeat() -> bool {
+    true
+}
+
+/// A single value produced by a pattern, before it is converted
+/// to the data type of the simulated entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sample {
+    Number(f64),
+    Json(serde_json::Value),
+}
+
+pub struct Generator {
+    pattern: Pattern,
+    interval_ms: u64,
+    tick: u64,
+    current: f64,
+    rng: XorShift,
+}
+
+/// Minimal xorshift PRNG, good enough for simulated noise.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        // State must never be zero
+        XorShift(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Uniformly distributed in [0.0, 1.0)
+    pub(crate) fn next_unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed in [-1.0, 1.0]
+    fn next_signed_unit(&mut self) -> f64 {
+        self.next_unit() * 2.0 - 1.0
+    }
+}
+
+impl Pattern {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Pattern::Sine { period_ms, .. } if *period_ms == 0 => {
+                Err("sine period_ms must be greater than 0".to_owned())
+            }
+            Pattern::Ramp { start, end, step } => {
+                if *step == 0.0 || (end - start).signum() * step.signum() < 0.0 {
+                    Err("ramp step must move from start towards end".to_owned())
+                } else {
+                    Ok(())
+                }
+            }
+            Pattern::RandomWalk { min, max, .. } if min > max => {
+                Err("random_walk min must not be greater than max".to_owned())
+            }
+            Pattern::Sequence { values, .. } if values.is_empty() => {
+                Err("sequence must contain at least one value".to_owned())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Generator {
+    pub fn new(pattern: Pattern, interval_ms: u64, seed: u64) -> Self {
+        let current = match &pattern {
+            Pattern::Ramp { start, .. } => *start,
+            Pattern::RandomWalk { start, .. } => *start,
+            _ => 0.0,
+        };
+        Generator {
+            pattern,
+            interval_ms,
+            tick: 0,
+            current,
+            rng: XorShift::new(seed),
+        }
+    }
+
+    /// Produce the next sample, or None if a non-repeating
+    /// sequence has been exhausted.
+    pub fn next_sample(&mut self) -> Option<Sample> {
+        let tick = self.tick;
+        self.tick += 1;
+        match &self.pattern {
+            Pattern::Sine {
+                amplitude,
+                offset,
+                period_ms,
+            } => {
+                let t = (tick * self.interval_ms) as f64 / *period_ms as f64;
+                Some(Sample::Number(
+                    offset + amplitude * (2.0 * std::f64::consts::PI * t).sin(),
+                ))
+            }
+            Pattern::Ramp { start, end, step } => {
+                let value = self.current;
+                let next = value + step;
+                self.current = if (*step > 0.0 && next > *end) || (*step < 0.0 && next < *end) {
+                    *start
+                } else {
+                    next
+                };
+                Some(Sample::Number(value))
+            }
+            Pattern::RandomWalk {
+                max_step, min, max, ..
+            } => {
+                let value = self.current;
+                let delta = self.rng.next_signed_unit() * max_step;
+                self.current = (value + delta).clamp(*min, *max);
+                Some(Sample::Number(value))
+            }
+            Pattern::Sequence { values, repeat } => {
+                let index = tick as usize;
+                if *repeat {
+                    Some(Sample::Json(values[index % values.len()].clone()))
+                } else {
+                    values.get(index).cloned().map(Sample::Json)
+                }
+            }
+        }
+    }
+}
+
+impl Sample {
+    /// Convert the sample to a value matching the data type of the entry.
+    pub fn to_data_value(&self, data_type: &DataType) -> Result<DataValue, String> {
+        match self {
+            Sample::Json(value) => match vss::try_from_json_value(Some(value.clone()), data_type) {
+                Ok(Some(value)) => Ok(value),
+                Ok(None) => Err("no value".to_owned()),
+                Err(err) => Err(err.to_string()),
+            },
+            Sample::Number(value) => match data_type {
+                DataType::Bool => Ok(DataValue::Bool(*value > 0.0)),
+                DataType::String => Ok(DataValue::String(value.to_string())),
+                DataType::Int8 => Ok(DataValue::Int32(value.round() as i8 as i32)),
+                DataType::Int16 => Ok(DataValue::Int32(value.round() as i16 as i32)),
+                DataType::Int32 => Ok(DataValue::Int32(value.round() as i32)),
+                DataType::Int64 => Ok(DataValue::Int64(value.round() as i64)),
+                DataType::Uint8 => Ok(DataValue::Uint32(value.round() as u8 as u32)),
+                DataType::Uint16 => Ok(DataValue::Uint32(value.round() as u16 as u32)),
+                DataType::Uint32 => Ok(DataValue::Uint32(value.round() as u32)),
+                DataType::Uint64 => Ok(DataValue::Uint64(value.round() as u64)),
+                DataType::Float => Ok(DataValue::Float(*value as f32)),
+                DataType::Double => Ok(DataValue::Double(*value)),
+                _ => Err(format!("numeric pattern not supported for {data_type}")),
+            },
+        }
+    }
+}
+
+pub fn parse_config_from_str(data: &str) -> Result<SimulationConfig, Error> {
+    let config: SimulationConfig = serde_json::from_str(data)?;
+    for signal in &config.signals {
+        if signal.interval_ms == 0 {
+            return Err(Error::InvalidConfig(format!(
+                "{}: interval_ms must be greater than 0",
+                signal.path
+            )));
+        }
+        signal
+            .pattern
+            .validate()
+            .map_err(|err| Error::InvalidConfig(format!("{}: {err}", signal.path)))?;
+    }
+    for actuator in &config.actuators {
+        if let Some(ramp) = &actuator.ramp {
+            if !(ramp.rate.is_finite() && ramp.rate > 0.0) {
+                return Err(Error::InvalidConfig(format!(
+                    "{}: ramp rate must be greater than 0",
+                    actuator.path
+                )));
+            }
+            if ramp.interval_ms == 0 {
+                return Err(Error::InvalidConfig(format!(
+                    "{}: ramp interval_ms must be greater than 0",
+                    actuator.path
+                )));
+            }
+        }
+    }
+    Ok(config)
+}
+
+pub fn parse_config_from_reader<R>(reader: R) -> Result<SimulationConfig, Error>
+where
+    R: std::io::Read,
+{
+    let mut data = String::new();
+    std::io::BufReader::new(reader)
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_config_from_str(&data)
+}
+
+/// Spawn one task per simulated signal and actuator. The tasks end when
+/// the broker shuts down.
+pub async fn start(broker: DataBroker, config: SimulationConfig) {
+    simulate_actuators(&broker, config.actuators).await;
+
+    let seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+
+    for (index, signal) in config.signals.into_iter().enumerate() {
+        let metadata = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_metadata_by_path(&signal.path)
+            .await;
+        let Some(metadata) = metadata else {
+            warn!("Simulation: {} not found, skipping", signal.path);
+            continue;
+        };
+
+        info!("Simulating {} every {} ms", signal.path, signal.interval_ms);
+        let broker = broker.clone();
+        let mut generator = Generator::new(
+            signal.pattern,
+            signal.interval_ms,
+            seed.wrapping_add(index as u64),
+        );
+        let mut shutdown_trigger = broker.get_shutdown_trigger();
+
+        tokio::spawn(async move {
+            let clock = broker.clock();
+            let broker = broker.authorized_access(&permissions::ALLOW_ALL);
+            let mut interval = tokio::time::interval(Duration::from_millis(signal.interval_ms));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown_trigger.recv() => break,
+                }
+
+                let Some(sample) = generator.next_sample() else {
+                    debug!("Simulation of {} finished", signal.path);
+                    break;
+                };
+                let value = match sample.to_data_value(&metadata.data_type) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        warn!("Simulation of {} stopped: {}", signal.path, err);
+                        break;
+                    }
+                };
+
+                let update = broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint {
+                        ts: clock.now(),
+                        source_ts: None,
+                        value,
+                    }),
+                    ..Default::default()
+                };
+                if let Err(errors) = broker.update_entries([(metadata.id, update)]).await {
+                    if let Some((_, error)) = errors.first() {
+                        debug!("Simulation failed to set {}: {:?}", signal.path, error);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Passes the requested targets on to the tasks moving the actuators.
+struct SimulatedActuators {
+    targets: Arc<HashMap<i32, watch::Sender<Option<DataValue>>>>,
+}
+
+#[async_trait::async_trait]
+impl ActuationProvider for SimulatedActuators {
+    async fn actuate(
+        &self,
+        actuation_changes: Vec<ActuationChange>,
+    ) -> Result<(), (ActuationError, String)> {
+        for change in actuation_changes {
+            if let Some(target) = self.targets.get(&change.id) {
+                target.send_replace(Some(change.data_value));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+}
+
+async fn simulate_actuators(broker: &DataBroker, actuators: Vec<ActuatorSimulation>) {
+    let access = broker.authorized_access(&permissions::ALLOW_ALL);
+    let mut targets = HashMap::new();
+    for actuator in actuators {
+        let Some(metadata) = access.get_metadata_by_path(&actuator.path).await else {
+            warn!("Simulation: {} not found, skipping", actuator.path);
+            continue;
+        };
+        if metadata.entry_type != EntryType::Actuator {
+            warn!("Simulation: {} is not an actuator, skipping", actuator.path);
+            continue;
+        }
+
+        info!("Simulating actuator {}", actuator.path);
+        let (sender, receiver) = watch::channel(None);
+        targets.insert(metadata.id, sender);
+        tokio::spawn(run_actuator(broker.clone(), metadata, actuator, receiver));
+    }
+    if targets.is_empty() {
+        return;
+    }
+
+    let targets = Arc::new(targets);
+    let ids = targets.keys().copied().collect();
+    let provider = Box::new(SimulatedActuators {
+        targets: targets.clone(),
+    });
+    if let Err((_, message)) = access.simulate_actuation(ids, provider).await {
+        warn!("Simulation of actuators failed: {message}");
+        return;
+    }
+    tokio::spawn(follow_targets(broker.clone(), targets));
+}
+
+/// Pass on the targets set through the v1 API, unless a provider claimed
+/// the actuator.
+async fn follow_targets(
+    broker: DataBroker,
+    targets: Arc<HashMap<i32, watch::Sender<Option<DataValue>>>>,
+) {
+    let entries = targets
+        .keys()
+        .map(|id| (*id, HashSet::from([broker::Field::ActuatorTarget])))
+        .collect();
+    let stream = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .subscribe_with_snapshot(entries, None, InitialSnapshot::Skip)
+        .await;
+    let mut stream = match stream {
+        Ok(stream) => Box::pin(stream),
+        Err(err) => {
+            warn!("Simulation of actuators can't follow targets: {err:?}");
+            return;
+        }
+    };
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    loop {
+        let updates = tokio::select! {
+            updates = stream.next() => match updates {
+                Some(updates) => updates,
+                None => break,
+            },
+            _ = shutdown_trigger.recv() => break,
+        };
+        let provided = broker.get_provided_actuators().await;
+        for notification in updates.updates {
+            let Some(Some(target)) = notification.update.actuator_target else {
+                continue;
+            };
+            if provided.contains(&notification.id) {
+                continue;
+            }
+            if let Some(sender) = targets.get(&notification.id) {
+                sender.send_replace(Some(target.value));
+            }
+        }
+    }
+}
+
+/// Move the actuator to the targets received through `targets`.
+async fn run_actuator(
+    broker: DataBroker,
+    metadata: broker::Metadata,
+    simulation: ActuatorSimulation,
+    mut targets: watch::Receiver<Option<DataValue>>,
+) {
+    let mut shutdown_trigger = broker.get_shutdown_trigger();
+    loop {
+        tokio::select! {
+            changed = targets.changed() => if changed.is_err() { break },
+            _ = shutdown_trigger.recv() => break,
+        }
+        loop {
+            let target = targets.borrow_and_update().clone();
+            let Some(target) = target else {
+                break;
+            };
+            tokio::select! {
+                _ = move_to(&broker, &metadata, &simulation, target) => break,
+                // Start over towards the new target
+                changed = targets.changed() => if changed.is_err() { return },
+                _ = shutdown_trigger.recv() => return,
+            }
+        }
+    }
+}
+
+async fn move_to(
+    broker: &DataBroker,
+    metadata: &broker::Metadata,
+    simulation: &ActuatorSimulation,
+    target: DataValue,
+) {
+    tokio::time::sleep(Duration::from_millis(simulation.delay_ms)).await;
+
+    let access = broker.authorized_access(&permissions::ALLOW_ALL);
+    let current = access
+        .get_datapoint(metadata.id)
+        .await
+        .ok()
+        .and_then(|datapoint| datapoint.value.as_f64());
+    if let (Some(ramp), Some(mut value), Some(end)) = (&simulation.ramp, current, target.as_f64()) {
+        let step = ramp.rate * ramp.interval_ms as f64 / 1000.0;
+        let period = Duration::from_millis(ramp.interval_ms);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            if (end - value).abs() <= step {
+                break;
+            }
+            value += step.copysign(end - value);
+            match Sample::Number(value).to_data_value(&metadata.data_type) {
+                Ok(value) => publish(broker, metadata, value).await,
+                Err(err) => {
+                    debug!("Simulation can't ramp {}: {}", metadata.path, err);
+                    break;
+                }
+            }
+        }
+    }
+    publish(broker, metadata, target).await;
+}
+
+async fn publish(broker: &DataBroker, metadata: &broker::Metadata, value: DataValue) {
+    let update = broker::EntryUpdate {
+        datapoint: Some(broker::Datapoint {
+            ts: broker.clock().now(),
+            source_ts: None,
+            value,
+        }),
+        ..Default::default()
+    };
+    if let Err(errors) = broker
+        .authorized_access(&permissions::ALLOW_ALL)
+        .update_entries([(metadata.id, update)])
+        .await
+    {
+        if let Some((_, error)) = errors.first() {
+            debug!("Simulation failed to set {}: {:?}", metadata.path, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config_from_str(
+            r#"{
+                "signals": [
+                    { "path": "Vehicle.Speed", "interval_ms": 100,
+                      "pattern": { "type": "sine", "amplitude": 10, "period_ms": 1000 } },
+                    { "path": "Vehicle.IsMoving",
+                      "pattern": { "type": "sequence", "values": [true, false], "repeat": false } }
+                ]
+            }"#,
+        )
+        .expect("config should parse");
+
+        assert_eq!(config.signals.len(), 2);
+        assert_eq!(config.signals[0].interval_ms, 100);
+        assert_eq!(config.signals[1].interval_ms, DEFAULT_INTERVAL_MS);
+        match &config.signals[1].pattern {
+            Pattern::Sequence { values, repeat } => {
+                assert_eq!(values.len(), 2);
+                assert!(!repeat);
+            }
+            _ => panic!("expected sequence pattern"),
+        }
+    }
+
+    #[test]
+    fn test_parse_config_invalid() {
+        assert!(parse_config_from_str(
+            r#"{ "signals": [ { "path": "A.B", "pattern": { "type": "ramp", "start": 0, "end": 10, "step": -1 } } ] }"#
+        )
+        .is_err());
+        assert!(parse_config_from_str(
+            r#"{ "signals": [ { "path": "A.B", "pattern": { "type": "sequence", "values": [] } } ] }"#
+        )
+        .is_err());
+        assert!(parse_config_from_str(
+            r#"{ "signals": [ { "path": "A.B", "interval_ms": 0, "pattern": { "type": "sine", "amplitude": 1, "period_ms": 10 } } ] }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_actuator_config() {
+        let config = parse_config_from_str(
+            r#"{
+                "actuators": [
+                    { "path": "Vehicle.Body.Trunk.Rear.IsOpen" },
+                    { "path": "Vehicle.Cabin.Seat.Row1.DriverSide.Position", "delay_ms": 200,
+                      "ramp": { "rate": 50 } }
+                ]
+            }"#,
+        )
+        .expect("config should parse");
+
+        assert!(config.signals.is_empty());
+        assert_eq!(config.actuators[0].delay_ms, 0);
+        assert!(config.actuators[0].ramp.is_none());
+        let ramp = config.actuators[1].ramp.as_ref().unwrap();
+        assert_eq!(ramp.interval_ms, DEFAULT_RAMP_INTERVAL_MS);
+
+        assert!(parse_config_from_str(
+            r#"{ "actuators": [ { "path": "A.B", "ramp": { "rate": 0 } } ] }"#
+        )
+        .is_err());
+        assert!(parse_config_from_str(
+            r#"{ "actuators": [ { "path": "A.B", "ramp": { "rate": 1, "interval_ms": 0 } } ] }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sine() {
+        let mut generator = Generator::new(
+            Pattern::Sine {
+                amplitude: 10.0,
+                offset: 5.0,
+                period_ms: 400,
+            },
+            100,
+            1,
+        );
+        let samples: Vec<f64> = (0..4)
+            .map(|_| match generator.next_sample() {
+                Some(Sample::Number(value)) => value,
+                _ => panic!("expected number"),
+            })
+            .collect();
+        assert!((samples[0] - 5.0).abs() < 1e-9);
+        assert!((samples[1] - 15.0).abs() < 1e-9);
+        assert!((samples[2] - 5.0).abs() < 1e-9);
+        assert!((samples[3] + 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ramp_wraps() {
+        let mut generator = Generator::new(
+            Pattern::Ramp {
+                start: 0.0,
+                end: 2.0,
+                step: 1.0,
+            },
+            100,
+            1,
+        );
+        let samples: Vec<Option<Sample>> = (0..4).map(|_| generator.next_sample()).collect();
+        assert_eq!(
+            samples,
+            vec![
+                Some(Sample::Number(0.0)),
+                Some(Sample::Number(1.0)),
+                Some(Sample::Number(2.0)),
+                Some(Sample::Number(0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_random_walk_bounded() {
+        let mut generator = Generator::new(
+            Pattern::RandomWalk {
+                start: 0.0,
+                max_step: 5.0,
+                min: -1.0,
+                max: 1.0,
+            },
+            100,
+            42,
+        );
+        for _ in 0..1000 {
+            match generator.next_sample() {
+                Some(Sample::Number(value)) => assert!((-1.0..=1.0).contains(&value)),
+                _ => panic!("expected number"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequence_without_repeat_ends() {
+        let mut generator = Generator::new(
+            Pattern::Sequence {
+                values: vec![serde_json::json!(1), serde_json::json!(2)],
+                repeat: false,
+            },
+            100,
+            1,
+        );
+        assert_eq!(
+            generator.next_sample(),
+            Some(Sample::Json(serde_json::json!(1)))
+        );
+        assert_eq!(
+            generator.next_sample(),
+            Some(Sample::Json(serde_json::json!(2)))
+        );
+        assert_eq!(generator.next_sample(), None);
+    }
+
+    #[test]
+    fn test_sample_to_data_value() {
+        assert_eq!(
+            Sample::Number(41.6).to_data_value(&DataType::Uint8),
+            Ok(DataValue::Uint32(42))
+        );
+        assert_eq!(
+            Sample::Number(-1.0).to_data_value(&DataType::Bool),
+            Ok(DataValue::Bool(false))
+        );
+        assert_eq!(
+            Sample::Json(serde_json::json!("on")).to_data_value(&DataType::String),
+            Ok(DataValue::String("on".to_owned()))
+        );
+        assert!(Sample::Number(1.0)
+            .to_data_value(&DataType::Int32Array)
+            .is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulation_updates_broker() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = authorized_access
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                crate::types::ChangeType::OnChange,
+                crate::types::EntryType::Sensor,
+                "Test datapoint".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed");
+
+        let config = parse_config_from_str(
+            r#"{ "signals": [ { "path": "Vehicle.Speed", "interval_ms": 10,
+                 "pattern": { "type": "sequence", "values": [12.5], "repeat": false } } ] }"#,
+        )
+        .unwrap();
+        start(broker.clone(), config).await;
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let datapoint = authorized_access.get_datapoint(id).await.unwrap();
+        assert_eq!(datapoint.value, DataValue::Float(12.5));
+    }
+
+    async fn add_actuator(broker: &DataBroker, path: &str, data_type: DataType) -> i32 {
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                path.to_owned(),
+                data_type,
+                crate::types::ChangeType::OnChange,
+                EntryType::Actuator,
+                "Test actuator".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("Register datapoint should succeed")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_actuation() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = add_actuator(&broker, "Vehicle.Body.Trunk.Rear.IsOpen", DataType::Bool).await;
+
+        // Without simulation nobody provides the actuator
+        assert!(matches!(
+            authorized_access.actuate(&id, &DataValue::Bool(true)).await,
+            Err((ActuationError::ProviderNotAvailable, _))
+        ));
+
+        let config = parse_config_from_str(
+            r#"{ "actuators": [ { "path": "Vehicle.Body.Trunk.Rear.IsOpen", "delay_ms": 10 } ] }"#,
+        )
+        .unwrap();
+        start(broker.clone(), config).await;
+
+        authorized_access
+            .actuate(&id, &DataValue::Bool(true))
+            .await
+            .expect("simulated actuator should accept the target");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let datapoint = authorized_access.get_datapoint(id).await.unwrap();
+        assert_eq!(datapoint.value, DataValue::Bool(true));
+
+        // Targets set through the v1 API
+        let update = broker::EntryUpdate {
+            actuator_target: Some(Some(broker::Datapoint {
+                ts: SystemTime::now(),
+                source_ts: None,
+                value: DataValue::Bool(false),
+            })),
+            ..Default::default()
+        };
+        authorized_access
+            .update_entries([(id, update)])
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let datapoint = authorized_access.get_datapoint(id).await.unwrap();
+        assert_eq!(datapoint.value, DataValue::Bool(false));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_actuation_ramp() {
+        let broker = DataBroker::default();
+        let authorized_access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = add_actuator(&broker, "Vehicle.Cabin.Seat.Position", DataType::Uint16).await;
+        let update = broker::EntryUpdate {
+            datapoint: Some(broker::Datapoint {
+                ts: SystemTime::now(),
+                source_ts: None,
+                value: DataValue::Uint32(0),
+            }),
+            ..Default::default()
+        };
+        authorized_access
+            .update_entries([(id, update)])
+            .await
+            .unwrap();
+
+        let config = parse_config_from_str(
+            r#"{ "actuators": [ { "path": "Vehicle.Cabin.Seat.Position",
+                 "ramp": { "rate": 1000, "interval_ms": 10 } } ] }"#,
+        )
+        .unwrap();
+        start(broker.clone(), config).await;
+        let mut values = authorized_access
+            .subscribe_with_snapshot(
+                HashMap::from([(id, HashSet::from([broker::Field::Datapoint]))]),
+                None,
+                InitialSnapshot::Skip,
+            )
+            .await
+            .unwrap()
+            .map(|updates| updates.updates[0].update.datapoint.clone().unwrap().value);
+
+        authorized_access
+            .actuate(&id, &DataValue::Uint32(45))
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        while let Ok(Some(value)) =
+            tokio::time::timeout(Duration::from_secs(1), values.next()).await
+        {
+            received.push(value);
+            if received.last() == Some(&DataValue::Uint32(45)) {
+                break;
+            }
+        }
+        assert_eq!(
+            received,
+            [10, 20, 30, 40, 45].map(DataValue::Uint32).to_vec()
+        );
+    }
+}
@@ -0,0 +1,350 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Read-only HTTP endpoint exporting all entries, with their metadata and
+//! values, as one JSON document, for analysis tools that don't speak gRPC.
+//!
+//! `GET /snapshot` returns the entries the access token allows reading,
+//! sorted by path and all read from the same state of the broker:
+//!
+//! ```text
+//! {"version":"0.6.0","ts":1700000000000,"entries":[
+//!   {"path":"Vehicle.Speed","entry_type":"Sensor","data_type":"Float",...,
+//!    "datapoint":{"value":42.5,"ts":1700000000000,"source_ts":null},
+//!    "actuator_target":null}]}
+//! ```
+//!
+//! `GET /openapi.json` describes the endpoint as an OpenAPI 3 document.
+//! Timestamps are milliseconds since the UNIX epoch, unavailable values are
+//! `null`.
+
+use std::net::SocketAddr;
+use std::time::SystemTime;
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::authorization::{self, Authorization};
+use crate::broker::{DataBroker, EntryReadAccess};
+use crate::dump::{datapoint, millis};
+use crate::permissions::Permissions;
+
+#[derive(Clone)]
+struct AppState {
+    broker: DataBroker,
+    authorization: Authorization,
+}
+
+pub async fn serve(
+    addr: impl Into<SocketAddr>,
+    broker: DataBroker,
+    authorization: Authorization,
+) -> Result<(), Box<dyn std::error::Error>> {
+    broker.add_served_api("snapshot");
+    let app = Router::new()
+        .route("/snapshot", get(handle_snapshot))
+        .route("/openapi.json", get(handle_openapi))
+        .with_state(AppState {
+            broker,
+            authorization,
+        });
+
+    let addr = addr.into();
+    let builder = axum::Server::try_bind(&addr).map_err(|err| {
+        error!("Failed to bind address {addr}: {err}");
+        err
+    })?;
+
+    info!("Snapshot service listening on {}", addr);
+    builder
+        .serve(app.into_make_service())
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn handle_snapshot(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+    let permissions =
+        authorization::permissions_from_token(&state.authorization, token).map_err(|err| {
+            state.broker.record_authorization_failure();
+            (StatusCode::UNAUTHORIZED, err.to_string())
+        })?;
+    Ok(Json(snapshot(&state.broker, &permissions).await))
+}
+
+async fn handle_openapi(State(state): State<AppState>) -> Json<Value> {
+    Json(openapi(state.broker.get_version()))
+}
+
+fn entry(entry: EntryReadAccess<'_>) -> Option<Value> {
+    // Entries that can't be read are left out
+    let current = entry.datapoint().ok()?;
+    let target = entry.actuator_target().ok().and_then(Option::as_ref);
+    let metadata = entry.metadata();
+    Some(json!({
+        "path": metadata.path,
+        "id": metadata.id,
+        "entry_type": format!("{:?}", metadata.entry_type),
+        "data_type": format!("{:?}", metadata.data_type),
+        "change_type": format!("{:?}", metadata.change_type),
+        "description": metadata.description,
+        "unit": metadata.unit,
+        "min": metadata.min.clone().map(Value::from),
+        "max": metadata.max.clone().map(Value::from),
+        "allowed": metadata.allowed.clone().map(Value::from),
+        "datapoint": datapoint(current),
+        "actuator_target": target.map(datapoint),
+    }))
+}
+
+/// The entries `permissions` allow reading, with their metadata and values.
+async fn snapshot(broker: &DataBroker, permissions: &Permissions) -> Value {
+    let mut entries = broker
+        .authorized_access(permissions)
+        .filter_map_entries(entry)
+        .await;
+    entries.sort_by(|a, b| a["path"].as_str().cmp(&b["path"].as_str()));
+    json!({
+        "version": broker.get_version(),
+        "ts": millis(SystemTime::now()),
+        "entries": entries,
+    })
+}
+
+fn openapi(version: &str) -> Value {
+    let timestamp = json!({
+        "type": "integer",
+        "format": "int64",
+        "description": "Milliseconds since the UNIX epoch",
+    });
+    let mut nullable_timestamp = timestamp.clone();
+    nullable_timestamp["nullable"] = json!(true);
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "KUKSA Databroker snapshot",
+            "description": "All entries the access token allows reading, with their metadata and values, read from the same state of the broker.",
+            "version": version,
+        },
+        "paths": {
+            "/snapshot": {
+                "get": {
+                    "summary": "Export all readable entries",
+                    "security": [{"bearer": []}, {}],
+                    "responses": {
+                        "200": {
+                            "description": "The entries, sorted by path",
+                            "content": {
+                                "application/json": {
+                                    "schema": {"$ref": "#/components/schemas/Snapshot"},
+                                },
+                            },
+                        },
+                        "401": {"description": "Missing, invalid or expired access token"},
+                    },
+                },
+            },
+        },
+        "components": {
+            "securitySchemes": {
+                "bearer": {"type": "http", "scheme": "bearer", "bearerFormat": "JWT"},
+            },
+            "schemas": {
+                "Snapshot": {
+                    "type": "object",
+                    "required": ["version", "ts", "entries"],
+                    "properties": {
+                        "version": {"type": "string", "description": "Version of the broker"},
+                        "ts": {
+                            "type": "integer",
+                            "format": "int64",
+                            "description": "Time of the snapshot, in milliseconds since the UNIX epoch",
+                        },
+                        "entries": {
+                            "type": "array",
+                            "items": {"$ref": "#/components/schemas/Entry"},
+                        },
+                    },
+                },
+                "Entry": {
+                    "type": "object",
+                    "required": ["path", "id", "entry_type", "data_type", "change_type", "description", "datapoint"],
+                    "properties": {
+                        "path": {"type": "string", "example": "Vehicle.Speed"},
+                        "id": {"type": "integer", "format": "int32"},
+                        "entry_type": {"type": "string", "enum": ["Sensor", "Actuator", "Attribute"]},
+                        "data_type": {"type": "string", "example": "Float"},
+                        "change_type": {"type": "string", "enum": ["Static", "OnChange", "Continuous", "Event"]},
+                        "description": {"type": "string"},
+                        "unit": {"type": "string", "nullable": true},
+                        "min": {"$ref": "#/components/schemas/Value"},
+                        "max": {"$ref": "#/components/schemas/Value"},
+                        "allowed": {"$ref": "#/components/schemas/Value"},
+                        "datapoint": {"$ref": "#/components/schemas/Datapoint"},
+                        "actuator_target": {
+                            "allOf": [{"$ref": "#/components/schemas/Datapoint"}],
+                            "nullable": true,
+                            "description": "Target value of actuators, null if there is none",
+                        },
+                    },
+                },
+                "Datapoint": {
+                    "type": "object",
+                    "required": ["value", "ts"],
+                    "properties": {
+                        "value": {"$ref": "#/components/schemas/Value"},
+                        "ts": timestamp,
+                        "source_ts": nullable_timestamp,
+                    },
+                },
+                "Value": {
+                    "nullable": true,
+                    "description": "A boolean, number, string or array of them, null if not available",
+                    "oneOf": [
+                        {"type": "boolean"},
+                        {"type": "number"},
+                        {"type": "string"},
+                        {"type": "array", "items": {}},
+                    ],
+                },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::authorization::jwt;
+    use crate::broker::{ChangeType, Datapoint, EntryType, EntryUpdate};
+    use crate::permissions;
+    use crate::types::{DataType, DataValue};
+
+    async fn test_broker() -> DataBroker {
+        let broker = DataBroker::default();
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        for (path, entry_type) in [
+            ("Vehicle.Speed", EntryType::Sensor),
+            ("Vehicle.Cabin.Light.Intensity", EntryType::Actuator),
+        ] {
+            let id = access
+                .add_entry(
+                    path.to_owned(),
+                    DataType::Float,
+                    ChangeType::OnChange,
+                    entry_type,
+                    "Test".to_owned(),
+                    None,
+                    Some(DataValue::Float(100.0)),
+                    None,
+                    Some("percent".to_owned()),
+                )
+                .await
+                .unwrap();
+            access
+                .update_entries([(
+                    id,
+                    EntryUpdate {
+                        datapoint: Some(Datapoint {
+                            ts: SystemTime::UNIX_EPOCH + Duration::from_millis(1500),
+                            source_ts: None,
+                            value: DataValue::Float(12.5),
+                        }),
+                        ..Default::default()
+                    },
+                )])
+                .await
+                .unwrap();
+        }
+        broker
+    }
+
+    #[tokio::test]
+    async fn test_snapshot() {
+        let broker = test_broker().await;
+        let snapshot = snapshot(&broker, &permissions::ALLOW_ALL).await;
+        let entries = snapshot["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        // Sorted by path
+        assert_eq!(entries[0]["path"], "Vehicle.Cabin.Light.Intensity");
+        assert_eq!(entries[0]["entry_type"], "Actuator");
+        assert_eq!(entries[0]["actuator_target"], Value::Null);
+        let speed = &entries[1];
+        assert_eq!(speed["path"], "Vehicle.Speed");
+        assert_eq!(speed["data_type"], "Float");
+        assert_eq!(speed["unit"], "percent");
+        assert_eq!(speed["min"], Value::Null);
+        assert_eq!(speed["max"], 100.0);
+        assert_eq!(
+            speed["datapoint"],
+            json!({"value": 12.5, "ts": 1500, "source_ts": null})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_authorized() {
+        let broker = test_broker().await;
+        let state = AppState {
+            broker: broker.clone(),
+            authorization: Authorization::new(jwt::DEV_PUBLIC_KEY.to_owned()).unwrap(),
+        };
+        let Err((status, _)) = handle_snapshot(State(state.clone()), HeaderMap::new()).await else {
+            panic!("a snapshot without a token should fail");
+        };
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(broker.get_usage().await.authorization_failures, 1);
+
+        let token = jwt::Encoder::dev()
+            .mint("analysis", "read:Vehicle.Cabin", Duration::from_secs(60))
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+        let Json(snapshot) = handle_snapshot(State(state), headers).await.unwrap();
+        let entries = snapshot["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["path"], "Vehicle.Cabin.Light.Intensity");
+    }
+
+    #[test]
+    fn test_openapi() {
+        let openapi = openapi("1.2.3");
+        assert_eq!(openapi["info"]["version"], "1.2.3");
+        assert!(openapi["paths"]["/snapshot"]["get"].is_object());
+        // Every reference resolves
+        let text = openapi.to_string();
+        for reference in text.split("\"$ref\":\"#/components/schemas/").skip(1) {
+            let name = &reference[..reference.find('"').unwrap()];
+            assert!(
+                openapi["components"]["schemas"][name].is_object(),
+                "{name} is not defined"
+            );
+        }
+    }
+}
@@ -0,0 +1,309 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Server-Sent Events endpoint for following signals over plain HTTP.
+//!
+//! `GET /subscribe?paths=Vehicle.Speed,Vehicle.Cabin.Light.IsDomeOn`
+//! responds with an event stream. The first event holds the current
+//! values, every following one the values that changed:
+//!
+//! ```text
+//! data:{"Vehicle.Speed":{"value":42.5,"ts":1700000000000}}
+//! ```
+//!
+//! Timestamps are milliseconds since the UNIX epoch, unavailable values
+//! are `null`. With authorization enabled, the access token is passed in
+//! the `Authorization: Bearer` header or, for browsers whose `EventSource`
+//! can't set headers, in the `token` query parameter.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info};
+
+use crate::authorization::{self, Authorization};
+use crate::broker::{self, DataBroker};
+use crate::permissions::PermissionError;
+
+/// Interval of comments keeping idle connections open through proxies.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Clone)]
+struct AppState {
+    broker: DataBroker,
+    authorization: Authorization,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscribeParams {
+    /// Comma separated paths
+    paths: String,
+    token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Value {
+    value: serde_json::Value,
+    ts: u128,
+}
+
+pub async fn serve(
+    addr: impl Into<SocketAddr>,
+    broker: DataBroker,
+    authorization: Authorization,
+) -> Result<(), Box<dyn std::error::Error>> {
+    broker.add_served_api("sse");
+    let app = Router::new()
+        .route("/subscribe", get(handle_subscribe))
+        .with_state(AppState {
+            broker,
+            authorization,
+        });
+
+    let addr = addr.into();
+    let builder = axum::Server::try_bind(&addr).map_err(|err| {
+        error!("Failed to bind address {addr}: {err}");
+        err
+    })?;
+
+    info!("Server-Sent Events service listening on {}", addr);
+    builder
+        .serve(app.into_make_service())
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn handle_subscribe(
+    State(state): State<AppState>,
+    Query(params): Query<SubscribeParams>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .or(params.token.as_deref());
+    let paths = params
+        .paths
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .collect::<Vec<_>>();
+
+    let stream = subscribe(&state.broker, &state.authorization, token, &paths).await?;
+    let events = stream.map(|data| Ok(Event::default().data(data)));
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
+
+/// Subscribe to `paths`, returning a stream of serialized value updates.
+async fn subscribe(
+    broker: &DataBroker,
+    authorization: &Authorization,
+    token: Option<&str>,
+    paths: &[&str],
+) -> Result<impl Stream<Item = String>, (StatusCode, String)> {
+    let permissions =
+        authorization::permissions_from_token(authorization, token).map_err(|err| {
+            broker.record_authorization_failure();
+            (StatusCode::UNAUTHORIZED, err.to_string())
+        })?;
+    let access = broker.authorized_access(&permissions);
+
+    let mut entries = HashMap::new();
+    for path in paths {
+        let Some(id) = access.get_id_by_path(path).await else {
+            return Err((StatusCode::NOT_FOUND, format!("{path}: not found")));
+        };
+        permissions.can_read(path).map_err(|err| match err {
+            PermissionError::Denied => {
+                (StatusCode::FORBIDDEN, format!("{path}: permission denied"))
+            }
+            PermissionError::Expired => {
+                (StatusCode::UNAUTHORIZED, "access token expired".to_owned())
+            }
+        })?;
+        entries.insert(id, [broker::Field::Datapoint].into());
+    }
+    if entries.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "no paths given".to_owned()));
+    }
+
+    let stream = access
+        .subscribe(entries, None)
+        .await
+        .map_err(|err| match err {
+            broker::SubscriptionError::NotFound | broker::SubscriptionError::InvalidInput => {
+                (StatusCode::NOT_FOUND, "not found".to_owned())
+            }
+            broker::SubscriptionError::QuotaExceeded => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "subscription quota exceeded".to_owned(),
+            ),
+            broker::SubscriptionError::InternalError
+            | broker::SubscriptionError::InvalidBufferSize => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to subscribe".to_owned(),
+            ),
+        })?;
+    debug!("New SSE subscription");
+
+    Ok(stream.map(|notification| {
+        let values: HashMap<String, Value> = notification
+            .updates
+            .into_iter()
+            .filter_map(|change| Some((change.update.path?, change.update.datapoint?)))
+            .map(|(path, datapoint)| (path, Value::from(datapoint)))
+            .collect();
+        serde_json::to_string(&values).expect("values should be serializable")
+    }))
+}
+
+impl From<broker::Datapoint> for Value {
+    fn from(datapoint: broker::Datapoint) -> Self {
+        Value {
+            value: datapoint.value.into(),
+            ts: datapoint
+                .ts
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.as_millis())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization::jwt;
+    use crate::permissions;
+    use crate::types::{DataType, DataValue};
+
+    async fn test_broker() -> DataBroker {
+        let broker = DataBroker::default();
+        broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .add_entry(
+                "Vehicle.Speed".to_owned(),
+                DataType::Float,
+                broker::ChangeType::OnChange,
+                broker::EntryType::Sensor,
+                "Test".to_owned(),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        broker
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let broker = test_broker().await;
+        let stream = subscribe(&broker, &Authorization::Disabled, None, &["Vehicle.Speed"])
+            .await
+            .unwrap();
+        let mut stream = Box::pin(stream);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&stream.next().await.unwrap()).unwrap()
+                ["Vehicle.Speed"]["value"],
+            serde_json::Value::Null
+        );
+
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = access.get_id_by_path("Vehicle.Speed").await.unwrap();
+        access
+            .update_entries([(
+                id,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint {
+                        ts: SystemTime::UNIX_EPOCH + Duration::from_millis(1500),
+                        source_ts: None,
+                        value: DataValue::Float(12.5),
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+        assert_eq!(
+            stream.next().await.unwrap(),
+            r#"{"Vehicle.Speed":{"value":12.5,"ts":1500}}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_errors() {
+        let broker = test_broker().await;
+        let Err((status, _)) = subscribe(
+            &broker,
+            &Authorization::Disabled,
+            None,
+            &["Vehicle.Unknown"],
+        )
+        .await
+        else {
+            panic!("subscribing to an unknown path should fail");
+        };
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let Err((status, _)) = subscribe(&broker, &Authorization::Disabled, None, &[]).await else {
+            panic!("subscribing to nothing should fail");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_authorized() {
+        let broker = test_broker().await;
+        let authorization = Authorization::new(jwt::DEV_PUBLIC_KEY.to_owned()).unwrap();
+
+        let Err((status, _)) = subscribe(&broker, &authorization, None, &["Vehicle.Speed"]).await
+        else {
+            panic!("subscribing without a token should fail");
+        };
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(broker.get_usage().await.authorization_failures, 1);
+
+        let token = jwt::Encoder::dev()
+            .mint("kiosk", "read:Vehicle.Cabin", Duration::from_secs(60))
+            .unwrap();
+        let Err((status, _)) =
+            subscribe(&broker, &authorization, Some(&token), &["Vehicle.Speed"]).await
+        else {
+            panic!("subscribing without permission should fail");
+        };
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let token = jwt::Encoder::dev()
+            .mint("kiosk", "read:Vehicle.Speed", Duration::from_secs(60))
+            .unwrap();
+        assert!(
+            subscribe(&broker, &authorization, Some(&token), &["Vehicle.Speed"],)
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -84,6 +84,49 @@ pub enum ChangeType {
     Static,
     OnChange,
     Continuous,
+    /// One-shot occurrences, e.g. a door being slammed. Every update is
+    /// delivered to subscribers, but not kept as current value.
+    Event,
+}
+
+/// Safety integrity level of an entry, lowest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SafetyLevel {
+    /// Quality managed, i.e. not safety relevant
+    Qm,
+    AsilA,
+    AsilB,
+    AsilC,
+    AsilD,
+}
+
+impl fmt::Display for SafetyLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SafetyLevel::Qm => write!(f, "QM"),
+            SafetyLevel::AsilA => write!(f, "ASIL-A"),
+            SafetyLevel::AsilB => write!(f, "ASIL-B"),
+            SafetyLevel::AsilC => write!(f, "ASIL-C"),
+            SafetyLevel::AsilD => write!(f, "ASIL-D"),
+        }
+    }
+}
+
+impl std::str::FromStr for SafetyLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "QM" => Ok(SafetyLevel::Qm),
+            "ASIL-A" | "A" => Ok(SafetyLevel::AsilA),
+            "ASIL-B" | "B" => Ok(SafetyLevel::AsilB),
+            "ASIL-C" | "C" => Ok(SafetyLevel::AsilC),
+            "ASIL-D" | "D" => Ok(SafetyLevel::AsilD),
+            _ => Err(format!(
+                "Invalid safety level '{s}', expected QM or ASIL-A to ASIL-D"
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -485,6 +528,93 @@ impl DataValue {
             _ => Err(CastError {}),
         }
     }
+
+    /// The value as a number, for scalar numeric values.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            DataValue::Int32(value) => Some(f64::from(*value)),
+            DataValue::Int64(value) => Some(*value as f64),
+            DataValue::Uint32(value) => Some(f64::from(*value)),
+            DataValue::Uint64(value) => Some(*value as f64),
+            DataValue::Float(value) => Some(f64::from(*value)),
+            DataValue::Double(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value with floats rounded to `decimals` decimal places. Other
+    /// values are returned as they are.
+    pub fn rounded(&self, decimals: u32) -> DataValue {
+        let factor = 10f64.powi(decimals.min(i32::MAX as u32) as i32);
+        let round = |value: f64| {
+            let rounded = (value * factor).round() / factor;
+            if rounded.is_finite() {
+                rounded
+            } else {
+                // Already more precise than the value can be
+                value
+            }
+        };
+        match self {
+            DataValue::Float(value) => DataValue::Float(round(f64::from(*value)) as f32),
+            DataValue::Double(value) => DataValue::Double(round(*value)),
+            DataValue::FloatArray(values) => DataValue::FloatArray(
+                values
+                    .iter()
+                    .map(|value| round(f64::from(*value)) as f32)
+                    .collect(),
+            ),
+            DataValue::DoubleArray(values) => {
+                DataValue::DoubleArray(values.iter().map(|value| round(*value)).collect())
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Approximate size of the value on the wire, in bytes.
+    pub fn encoded_size(&self) -> usize {
+        match self {
+            DataValue::NotAvailable => 0,
+            DataValue::Bool(_) => 1,
+            DataValue::String(value) => value.len(),
+            DataValue::Int32(_) | DataValue::Uint32(_) | DataValue::Float(_) => 4,
+            DataValue::Int64(_) | DataValue::Uint64(_) | DataValue::Double(_) => 8,
+            DataValue::BoolArray(values) => values.len(),
+            DataValue::StringArray(values) => values.iter().map(|value| value.len() + 1).sum(),
+            DataValue::Int32Array(values) => values.len() * 4,
+            DataValue::Uint32Array(values) => values.len() * 4,
+            DataValue::FloatArray(values) => values.len() * 4,
+            DataValue::Int64Array(values) => values.len() * 8,
+            DataValue::Uint64Array(values) => values.len() * 8,
+            DataValue::DoubleArray(values) => values.len() * 8,
+        }
+    }
+}
+
+/// Plain JSON representation, `null` if not available. Non-finite floats
+/// become `null` as well, as JSON has no representation for them.
+impl From<DataValue> for serde_json::Value {
+    fn from(value: DataValue) -> Self {
+        match value {
+            DataValue::NotAvailable => serde_json::Value::Null,
+            DataValue::Bool(value) => value.into(),
+            DataValue::String(value) => value.into(),
+            DataValue::Int32(value) => value.into(),
+            DataValue::Int64(value) => value.into(),
+            DataValue::Uint32(value) => value.into(),
+            DataValue::Uint64(value) => value.into(),
+            DataValue::Float(value) => value.into(),
+            DataValue::Double(value) => value.into(),
+            DataValue::BoolArray(values) => values.into(),
+            DataValue::StringArray(values) => values.into(),
+            DataValue::Int32Array(values) => values.into(),
+            DataValue::Int64Array(values) => values.into(),
+            DataValue::Uint32Array(values) => values.into(),
+            DataValue::Uint64Array(values) => values.into(),
+            DataValue::FloatArray(values) => values.into(),
+            DataValue::DoubleArray(values) => values.into(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -945,6 +1075,29 @@ fn test_float_less_than() {}
 #[test]
 fn test_double_less_than() {}
 
+#[test]
+fn test_rounded() {
+    assert_eq!(
+        DataValue::Double(1.23456).rounded(2),
+        DataValue::Double(1.23)
+    );
+    assert_eq!(
+        DataValue::Float(-1.2351).rounded(3),
+        DataValue::Float(-1.235)
+    );
+    assert_eq!(DataValue::Double(1.5).rounded(0), DataValue::Double(2.0));
+    assert_eq!(
+        DataValue::DoubleArray(vec![0.004, 0.006]).rounded(2),
+        DataValue::DoubleArray(vec![0.0, 0.01])
+    );
+    // Too many decimals to round
+    assert_eq!(
+        DataValue::Double(1e300).rounded(100),
+        DataValue::Double(1e300)
+    );
+    assert_eq!(DataValue::Int32(12345).rounded(2), DataValue::Int32(12345));
+}
+
 #[test]
 fn test_float_equals() {
     assert!(DataValue::Float(5000.0)
@@ -0,0 +1,310 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Plausibility checks of updated values.
+//!
+//! A [`Validator`] is registered for the paths matching a pattern and is
+//! asked to accept every new value of them, given the previous value.
+//! Implausible values are rejected with [`UpdateError::Implausible`].
+//! The built-in validators can be configured without recompiling:
+//!
+//! ```json
+//! [
+//!   { "path": "Vehicle.Speed", "validator": "rate_of_change", "max_change": 100 },
+//!   { "path": "Vehicle.Powertrain.*.Range", "validator": "rate_of_change", "max_change": 10, "per_ms": 1000 },
+//!   { "path": "Vehicle.TraveledDistance", "validator": "monotonic" }
+//! ]
+//! ```
+//!
+//! * `rate_of_change`: the value may change by at most `max_change`
+//!   between two samples or, with `per_ms`, per that many milliseconds
+//!   between their timestamps.
+//! * `monotonic`: the value may not decrease, e.g. for counters.
+//!
+//! [`UpdateError::Implausible`]: crate::broker::UpdateError::Implausible
+
+use std::fmt;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::broker::Datapoint;
+use crate::glob;
+
+/// Checks whether a new value of an entry is plausible.
+pub trait Validator: Send + Sync {
+    /// Accept `next` as the successor of `previous`, or explain why not.
+    /// Not called as long as the entry has no value.
+    fn validate(&self, previous: &Datapoint, next: &Datapoint) -> Result<(), String>;
+}
+
+/// Limits the change of numeric values between samples.
+#[derive(Debug, Clone)]
+pub struct RateOfChange {
+    pub max_change: f64,
+    /// Scale `max_change` by the time between the samples.
+    pub per: Option<Duration>,
+}
+
+impl Validator for RateOfChange {
+    fn validate(&self, previous: &Datapoint, next: &Datapoint) -> Result<(), String> {
+        let (Some(previous_value), Some(next_value)) =
+            (previous.value.as_f64(), next.value.as_f64())
+        else {
+            return Ok(());
+        };
+        let max_change = match self.per {
+            Some(per) => {
+                let elapsed = next
+                    .source_ts
+                    .unwrap_or(next.ts)
+                    .duration_since(previous.source_ts.unwrap_or(previous.ts))
+                    .unwrap_or_default();
+                self.max_change * elapsed.as_secs_f64() / per.as_secs_f64()
+            }
+            None => self.max_change,
+        };
+        let change = (next_value - previous_value).abs();
+        if change > max_change {
+            return Err(format!(
+                "changed by {change} from {previous_value}, at most {max_change} allowed"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects numeric values smaller than the previous one.
+#[derive(Debug, Clone)]
+pub struct Monotonic;
+
+impl Validator for Monotonic {
+    fn validate(&self, previous: &Datapoint, next: &Datapoint) -> Result<(), String> {
+        match (previous.value.as_f64(), next.value.as_f64()) {
+            (Some(previous_value), Some(next_value)) if next_value < previous_value => {
+                Err(format!("decreased from {previous_value} to {next_value}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse validator config: {msg}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid validator config: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The validators to apply, by path pattern.
+#[derive(Default)]
+pub struct Validators {
+    validators: Vec<(glob::Matcher, Arc<dyn Validator>)>,
+}
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.validators
+                    .iter()
+                    .map(|(matcher, _)| matcher.as_string()),
+            )
+            .finish()
+    }
+}
+
+impl Validators {
+    /// Apply `validator` to the values of all paths matching `pattern`.
+    pub fn register(&mut self, pattern: &str, validator: Arc<dyn Validator>) -> Result<(), Error> {
+        let matcher = glob::Matcher::new(pattern)
+            .map_err(|_| Error::InvalidConfig(format!("invalid path pattern '{pattern}'")))?;
+        self.validators.push((matcher, validator));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+
+    /// Check `next` with every validator registered for `path`.
+    pub fn validate(
+        &self,
+        path: &str,
+        previous: &Datapoint,
+        next: &Datapoint,
+    ) -> Result<(), String> {
+        if previous.value == crate::types::DataValue::NotAvailable {
+            return Ok(());
+        }
+        self.validators
+            .iter()
+            .filter(|(matcher, _)| matcher.is_match_vss_path(path))
+            .try_for_each(|(_, validator)| validator.validate(previous, next))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "validator", rename_all = "snake_case")]
+enum ValidatorConfig {
+    RateOfChange {
+        max_change: f64,
+        #[serde(default)]
+        per_ms: Option<u64>,
+    },
+    Monotonic,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+    path: String,
+    #[serde(flatten)]
+    validator: ValidatorConfig,
+}
+
+pub fn parse_config_from_str(data: &str) -> Result<Validators, Error> {
+    let rules: Vec<Rule> =
+        serde_json::from_str(data).map_err(|err| Error::ParseError(err.to_string()))?;
+    let mut validators = Validators::default();
+    for rule in rules {
+        let validator: Arc<dyn Validator> = match rule.validator {
+            ValidatorConfig::RateOfChange { max_change, per_ms } => {
+                if max_change < 0.0 {
+                    return Err(Error::InvalidConfig(
+                        "max_change must not be negative".to_owned(),
+                    ));
+                }
+                if per_ms == Some(0) {
+                    return Err(Error::InvalidConfig(
+                        "per_ms must be greater than 0".to_owned(),
+                    ));
+                }
+                Arc::new(RateOfChange {
+                    max_change,
+                    per: per_ms.map(Duration::from_millis),
+                })
+            }
+            ValidatorConfig::Monotonic => Arc::new(Monotonic),
+        };
+        validators.register(&rule.path, validator)?;
+    }
+    Ok(validators)
+}
+
+pub fn parse_config_from_reader<R: Read>(mut reader: R) -> Result<Validators, Error> {
+    let mut data = String::new();
+    reader
+        .read_to_string(&mut data)
+        .map_err(|err| Error::ParseError(err.to_string()))?;
+    parse_config_from_str(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::types::DataValue;
+
+    fn datapoint(value: DataValue, ts: SystemTime) -> Datapoint {
+        Datapoint {
+            ts,
+            source_ts: None,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let validators = parse_config_from_str(
+            r#"[
+                { "path": "Vehicle.Speed", "validator": "rate_of_change", "max_change": 100 },
+                { "path": "Vehicle.*.Odometer", "validator": "monotonic" }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(validators.validators.len(), 2);
+
+        assert!(matches!(
+            parse_config_from_str(r#"[{ "path": "Vehicle.Speed", "validator": "unknown" }]"#),
+            Err(Error::ParseError(_))
+        ));
+        assert!(matches!(
+            parse_config_from_str(
+                r#"[{ "path": "Vehicle.Speed", "validator": "rate_of_change", "max_change": 1, "per_ms": 0 }]"#
+            ),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let validators = parse_config_from_str(
+            r#"[
+                { "path": "Vehicle.Speed", "validator": "rate_of_change", "max_change": 100 },
+                { "path": "Vehicle.Range", "validator": "rate_of_change", "max_change": 10, "per_ms": 1000 },
+                { "path": "Vehicle.**.Odometer", "validator": "monotonic" }
+            ]"#,
+        )
+        .unwrap();
+        let now = SystemTime::now();
+        let float = |value, ts| datapoint(DataValue::Float(value), ts);
+
+        assert!(validators
+            .validate("Vehicle.Speed", &float(10.0, now), &float(110.0, now))
+            .is_ok());
+        assert!(validators
+            .validate("Vehicle.Speed", &float(10.0, now), &float(110.5, now))
+            .is_err());
+        // The first value is always plausible
+        assert!(validators
+            .validate(
+                "Vehicle.Speed",
+                &datapoint(DataValue::NotAvailable, now),
+                &float(200.0, now)
+            )
+            .is_ok());
+
+        let later = now + Duration::from_secs(2);
+        assert!(validators
+            .validate("Vehicle.Range", &float(100.0, now), &float(80.0, later))
+            .is_ok());
+        assert!(validators
+            .validate("Vehicle.Range", &float(100.0, now), &float(79.0, later))
+            .is_err());
+
+        let uint = |value| datapoint(DataValue::Uint32(value), now);
+        assert!(validators
+            .validate("Vehicle.Cabin.Odometer", &uint(5), &uint(5))
+            .is_ok());
+        assert!(validators
+            .validate("Vehicle.Cabin.Odometer", &uint(5), &uint(4))
+            .is_err());
+        // Not covered by any validator
+        assert!(validators
+            .validate("Vehicle.Cabin.Temperature", &uint(5), &uint(4))
+            .is_ok());
+    }
+}
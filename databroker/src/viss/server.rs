@@ -42,6 +42,7 @@ pub async fn serve(
     authorization: Authorization,
     // signal: F
 ) -> Result<(), Box<dyn std::error::Error>> {
+    broker.add_served_api("viss.v2");
     let app = Router::new()
         .route("/", get(handle_upgrade))
         .with_state(AppState {
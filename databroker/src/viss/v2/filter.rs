@@ -0,0 +1,510 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! The subscription filters of VISS v2 (timebased, change, range and
+//! curvelog), applied to the values of a subscription before they are
+//! sent. Filters can be combined: `timebased` decides when the latest
+//! value is sampled, `range` and `change` which values are sent and
+//! `curvelog` collects them into reduced batches.
+
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::{stream, Stream, StreamExt};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::broker::{self, DataType, DataValue};
+
+use super::types::{Boundary, CombinationOp, Error, Filter, LogicOp};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Filters {
+    period: Option<Duration>,
+    change: Option<(LogicOp, f64)>,
+    range: Option<(Vec<(LogicOp, f64)>, CombinationOp)>,
+    curvelog: Option<(f64, usize)>,
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::BadRequestFilterInvalid {
+        msg: Some(msg.into()),
+    }
+}
+
+fn is_numeric(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::Uint8
+            | DataType::Uint16
+            | DataType::Uint32
+            | DataType::Uint64
+            | DataType::Float
+            | DataType::Double
+    )
+}
+
+fn parse_number(name: &str, value: &str) -> Result<f64, Error> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| invalid(format!("{name} must be a number")))
+}
+
+impl Filters {
+    /// The filters of a subscription to an entry of `data_type`.
+    pub fn new(filters: Vec<Filter>, data_type: &DataType) -> Result<Self, Error> {
+        let mut result = Filters::default();
+        for filter in filters {
+            match filter {
+                Filter::StaticMetadata(_) => {
+                    return Err(invalid("static-metadata can't be used with subscribe"))
+                }
+                Filter::Timebased(filter) => {
+                    let period = parse_number("period", &filter.parameter.period)?;
+                    if period < 1.0 {
+                        return Err(invalid("period must be at least 1 ms"));
+                    }
+                    result.period = Some(Duration::from_millis(period as u64));
+                }
+                Filter::Change(filter) => {
+                    let diff = parse_number("diff", &filter.parameter.diff)?;
+                    let op = filter.parameter.logic_op;
+                    if !is_numeric(data_type)
+                        && (!matches!(op, LogicOp::Eq | LogicOp::Ne) || diff != 0.0)
+                    {
+                        return Err(invalid(
+                            "only eq and ne with a diff of 0 can be used with non-numeric values",
+                        ));
+                    }
+                    result.change = Some((op, diff));
+                }
+                Filter::Range(filter) => {
+                    if !is_numeric(data_type) {
+                        return Err(invalid("range can only be used with numeric values"));
+                    }
+                    let boundaries: Vec<Boundary> = filter.parameter.into();
+                    if boundaries.is_empty() || boundaries.len() > 2 {
+                        return Err(invalid("range needs one or two boundaries"));
+                    }
+                    let combination = boundaries
+                        .iter()
+                        .find_map(|boundary| boundary.combination_op)
+                        .unwrap_or(CombinationOp::And);
+                    let boundaries = boundaries
+                        .into_iter()
+                        .map(|boundary| {
+                            Ok((
+                                boundary.logic_op,
+                                parse_number("boundary", &boundary.boundary)?,
+                            ))
+                        })
+                        .collect::<Result<_, Error>>()?;
+                    result.range = Some((boundaries, combination));
+                }
+                Filter::Curvelog(filter) => {
+                    if !is_numeric(data_type) {
+                        return Err(invalid("curvelog can only be used with numeric values"));
+                    }
+                    let maxerr = parse_number("maxerr", &filter.parameter.maxerr)?;
+                    let bufsize = parse_number("bufsize", &filter.parameter.bufsize)?;
+                    if maxerr < 0.0 || bufsize < 2.0 {
+                        return Err(invalid(
+                            "maxerr must not be negative and bufsize must be at least 2",
+                        ));
+                    }
+                    result.curvelog = Some((maxerr, bufsize as usize));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Apply the filters to the values of a subscription. Every item holds
+    /// the values to send in one notification.
+    pub fn apply(
+        self,
+        stream: impl Stream<Item = broker::EntryUpdates> + Send + Sync + 'static,
+    ) -> impl Stream<Item = Result<(String, Vec<broker::Datapoint>), Error>> + Send + Sync {
+        let ticker = self.period.map(|period| {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker
+        });
+        let state = State {
+            stream: Box::pin(stream),
+            filters: self,
+            ticker,
+            latest: None,
+            last_sent: None,
+            buffer: Vec::new(),
+        };
+        stream::unfold(state, |mut state| async move {
+            let item = state.next().await?;
+            Some((item, state))
+        })
+    }
+
+    fn in_range(&self, value: f64) -> bool {
+        match &self.range {
+            Some((boundaries, CombinationOp::And)) => boundaries
+                .iter()
+                .all(|(op, boundary)| op.compare(value, *boundary)),
+            Some((boundaries, CombinationOp::Or)) => boundaries
+                .iter()
+                .any(|(op, boundary)| op.compare(value, *boundary)),
+            None => true,
+        }
+    }
+
+    fn changed(&self, value: &DataValue, last_sent: Option<&DataValue>) -> bool {
+        let (Some((op, diff)), Some(last_sent)) = (&self.change, last_sent) else {
+            return true;
+        };
+        match (value.as_f64(), last_sent.as_f64()) {
+            (Some(value), Some(last_sent)) => op.compare(value - last_sent, *diff),
+            _ => match op {
+                LogicOp::Eq => value == last_sent,
+                _ => value != last_sent,
+            },
+        }
+    }
+}
+
+impl LogicOp {
+    fn compare(&self, value: f64, other: f64) -> bool {
+        match self {
+            LogicOp::Eq => value == other,
+            LogicOp::Ne => value != other,
+            LogicOp::Gt => value > other,
+            LogicOp::Gte => value >= other,
+            LogicOp::Lt => value < other,
+            LogicOp::Lte => value <= other,
+        }
+    }
+}
+
+struct State {
+    stream: Pin<Box<dyn Stream<Item = broker::EntryUpdates> + Send + Sync>>,
+    filters: Filters,
+    ticker: Option<Interval>,
+    // The latest value, sampled by the timebased filter
+    latest: Option<(String, broker::Datapoint)>,
+    last_sent: Option<DataValue>,
+    // Values collected by the curvelog filter
+    buffer: Vec<broker::Datapoint>,
+}
+
+impl State {
+    async fn next(&mut self) -> Option<Result<(String, Vec<broker::Datapoint>), Error>> {
+        loop {
+            let sampled = tokio::select! {
+                updates = self.stream.next() => {
+                    for update in updates?.updates {
+                        let (Some(path), Some(datapoint)) = (update.update.path, update.update.datapoint) else {
+                            return Some(Err(Error::InternalServerError));
+                        };
+                        if self.ticker.is_some() {
+                            self.latest = Some((path, datapoint));
+                        } else if let Some(notification) = self.filter(path, datapoint) {
+                            return Some(Ok(notification));
+                        }
+                    }
+                    continue;
+                }
+                _ = tick(&mut self.ticker) => self.latest.clone(),
+            };
+            if let Some((path, datapoint)) = sampled {
+                if let Some(notification) = self.filter(path, datapoint) {
+                    return Some(Ok(notification));
+                }
+            }
+        }
+    }
+
+    fn filter(
+        &mut self,
+        path: String,
+        datapoint: broker::Datapoint,
+    ) -> Option<(String, Vec<broker::Datapoint>)> {
+        if let Some(value) = datapoint.value.as_f64() {
+            if !self.filters.in_range(value) {
+                return None;
+            }
+        } else if self.filters.range.is_some() || self.filters.curvelog.is_some() {
+            // E.g. a value that isn't available (anymore)
+            return None;
+        }
+        if !self
+            .filters
+            .changed(&datapoint.value, self.last_sent.as_ref())
+        {
+            return None;
+        }
+        self.last_sent = Some(datapoint.value.clone());
+
+        match self.filters.curvelog {
+            Some((maxerr, bufsize)) => {
+                self.buffer.push(datapoint);
+                if self.buffer.len() < bufsize {
+                    return None;
+                }
+                let buffer = std::mem::take(&mut self.buffer);
+                Some((path, curve_log(buffer, maxerr)))
+            }
+            None => Some((path, vec![datapoint])),
+        }
+    }
+}
+
+/// Wait for the next tick, forever without a ticker.
+async fn tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Reduce `datapoints` to the ones needed to reconstruct the curve by
+/// linear interpolation, with an error of at most `maxerr` for the others
+/// (Ramer-Douglas-Peucker with the vertical distance).
+fn curve_log(datapoints: Vec<broker::Datapoint>, maxerr: f64) -> Vec<broker::Datapoint> {
+    let Some(first) = datapoints.first() else {
+        return datapoints;
+    };
+    let start = first.ts;
+    let points: Vec<(f64, f64)> = datapoints
+        .iter()
+        .map(|datapoint| {
+            let t = datapoint
+                .ts
+                .duration_since(start)
+                .unwrap_or_default()
+                .as_secs_f64();
+            (t, datapoint.value.as_f64().unwrap_or_default())
+        })
+        .collect();
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut segments = vec![(0, points.len() - 1)];
+    while let Some((from, to)) = segments.pop() {
+        let (t0, v0) = points[from];
+        let (t1, v1) = points[to];
+        let error = |(t, v): (f64, f64)| {
+            let expected = if t1 > t0 {
+                v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+            } else {
+                v0
+            };
+            (v - expected).abs()
+        };
+        let worst = (from + 1..to)
+            .map(|index| (index, error(points[index])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, error)) = worst {
+            if error > maxerr {
+                keep[index] = true;
+                segments.push((from, index));
+                segments.push((index, to));
+            }
+        }
+    }
+
+    datapoints
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(datapoint, keep)| keep.then_some(datapoint))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    use crate::broker::{ChangeNotification, EntryUpdate, EntryUpdates, Field};
+
+    fn parse_filters(json: &str, data_type: DataType) -> Result<Filters, Error> {
+        let filters: super::super::types::Filters = serde_json::from_str(json).unwrap();
+        Filters::new(filters.into(), &data_type)
+    }
+
+    fn updates(values: &[f64]) -> Vec<EntryUpdates> {
+        let start = SystemTime::now();
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| EntryUpdates {
+                updates: vec![ChangeNotification {
+                    id: 1,
+                    update: EntryUpdate {
+                        path: Some("Vehicle.Speed".to_owned()),
+                        datapoint: Some(broker::Datapoint {
+                            ts: start + Duration::from_secs(index as u64),
+                            source_ts: None,
+                            value: DataValue::Double(*value),
+                        }),
+                        ..Default::default()
+                    },
+                    fields: [Field::Datapoint].into(),
+                }],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    async fn sent(filters: Filters, values: &[f64]) -> Vec<Vec<f64>> {
+        filters
+            .apply(stream::iter(updates(values)))
+            .map(|notification| {
+                let (_, datapoints) = notification.unwrap();
+                datapoints
+                    .into_iter()
+                    .map(|datapoint| datapoint.value.as_f64().unwrap())
+                    .collect()
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_change_filter() {
+        let filters = parse_filters(
+            r#"{"type": "change", "parameter": {"logic-op": "gt", "diff": "10"}}"#,
+            DataType::Double,
+        )
+        .unwrap();
+        assert_eq!(
+            sent(filters, &[0.0, 5.0, 11.0, 20.0, 22.0, 10.0]).await,
+            vec![vec![0.0], vec![11.0], vec![22.0]]
+        );
+
+        let filters = parse_filters(
+            r#"{"type": "change", "parameter": {"logic-op": "ne", "diff": "0"}}"#,
+            DataType::Bool,
+        );
+        assert!(filters.is_ok());
+        let filters = parse_filters(
+            r#"{"type": "change", "parameter": {"logic-op": "gt", "diff": "1"}}"#,
+            DataType::Bool,
+        );
+        assert!(filters.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_range_filter() {
+        let filters = parse_filters(
+            r#"{"type": "range", "parameter": [
+                {"logic-op": "gte", "boundary": "10"},
+                {"logic-op": "lt", "boundary": "20"}
+            ]}"#,
+            DataType::Double,
+        )
+        .unwrap();
+        assert_eq!(
+            sent(filters, &[5.0, 10.0, 15.0, 20.0]).await,
+            vec![vec![10.0], vec![15.0]]
+        );
+
+        let filters = parse_filters(
+            r#"{"type": "range", "parameter": [
+                {"logic-op": "lt", "boundary": "10"},
+                {"logic-op": "gt", "boundary": "20", "combination-op": "OR"}
+            ]}"#,
+            DataType::Double,
+        )
+        .unwrap();
+        assert_eq!(
+            sent(filters, &[5.0, 15.0, 25.0]).await,
+            vec![vec![5.0], vec![25.0]]
+        );
+
+        assert!(parse_filters(
+            r#"{"type": "range", "parameter": {"logic-op": "gt", "boundary": "1"}}"#,
+            DataType::String,
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_curvelog_filter() {
+        // Combined with a range filter dropping the outlier
+        let filters = parse_filters(
+            r#"[
+                {"type": "curvelog", "parameter": {"maxerr": "0.5", "bufsize": "5"}},
+                {"type": "range", "parameter": {"logic-op": "lt", "boundary": "100"}}
+            ]"#,
+            DataType::Double,
+        )
+        .unwrap();
+        // A straight line up to 4, then down to 0, where it stays
+        assert_eq!(
+            sent(
+                filters,
+                &[0.0, 1.0, 2.0, 1000.0, 3.0, 4.0, 3.0, 2.0, 1.1, 0.0, 0.0]
+            )
+            .await,
+            vec![vec![0.0, 4.0], vec![3.0, 0.0, 0.0]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timebased_filter() {
+        let filters = parse_filters(
+            r#"{"type": "timebased", "parameter": {"period": "50"}}"#,
+            DataType::Double,
+        )
+        .unwrap();
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        let mut stream =
+            Box::pin(filters.apply(tokio_stream::wrappers::ReceiverStream::new(receiver)));
+        for update in updates(&[1.0, 2.0]) {
+            sender.send(update).await.unwrap();
+        }
+        // The latest value, every period
+        for _ in 0..2 {
+            let (_, datapoints) = stream.next().await.unwrap().unwrap();
+            assert_eq!(datapoints[0].value, DataValue::Double(2.0));
+        }
+
+        assert!(parse_filters(
+            r#"{"type": "timebased", "parameter": {"period": "0"}}"#,
+            DataType::Double,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        assert!(parse_filters(
+            r#"{"type": "timebased", "parameter": {"period": "soon"}}"#,
+            DataType::Double,
+        )
+        .is_err());
+        assert!(parse_filters(
+            r#"{"type": "curvelog", "parameter": {"maxerr": "1", "bufsize": "1"}}"#,
+            DataType::Double,
+        )
+        .is_err());
+        assert!(parse_filters(r#"{"type": "static-metadata"}"#, DataType::Double).is_err());
+    }
+}
@@ -12,6 +12,7 @@
 ********************************************************************************/
 
 mod conversions;
+mod filter;
 
 pub(crate) mod server;
 pub(crate) mod types;
@@ -32,7 +32,7 @@ use crate::{
     permissions::{self, Permissions},
 };
 
-use super::{conversions, types::*};
+use super::{conversions, filter, types::*};
 
 #[tonic::async_trait]
 pub(crate) trait Viss: Send + Sync + 'static {
@@ -91,15 +91,27 @@ impl Viss for Server {
     async fn get(&self, request: GetRequest) -> Result<GetSuccessResponse, GetErrorResponse> {
         let request_id = request.request_id;
 
-        if let Some(Filter::StaticMetadata(_)) = &request.filter {
-            // Authorization not required for metadata, don't bail if an
-            // access token is missing.
-            let broker = self.broker.authorized_access(&permissions::ALLOW_NONE);
-            let metadata = generate_metadata(&broker, request.path.as_ref()).await;
-            return Ok(GetSuccessResponse::Metadata(MetadataResponse {
-                request_id,
-                metadata,
-            }));
+        match &request.filter {
+            Some(Filter::StaticMetadata(_)) => {
+                // Authorization not required for metadata, don't bail if an
+                // access token is missing.
+                let broker = self.broker.authorized_access(&permissions::ALLOW_NONE);
+                let metadata = generate_metadata(&broker, request.path.as_ref()).await;
+                return Ok(GetSuccessResponse::Metadata(MetadataResponse {
+                    request_id,
+                    metadata,
+                }));
+            }
+            Some(_) => {
+                return Err(GetErrorResponse {
+                    request_id,
+                    error: Error::BadRequestFilterInvalid {
+                        msg: Some("Only static-metadata can be used with get".into()),
+                    },
+                    ts: SystemTime::now().into(),
+                });
+            }
+            None => {}
         }
         let permissions = resolve_permissions(&self.authorization, &request.authorization)
             .map_err(|error| GetErrorResponse {
@@ -112,7 +124,7 @@ impl Viss for Server {
         // Get datapoints
         match broker.get_datapoint_by_path(request.path.as_ref()).await {
             Ok(datapoint) => {
-                let dp = DataPoint::from(datapoint);
+                let dp = DataPoints::Single(DataPoint::from(datapoint));
                 Ok(GetSuccessResponse::Data(DataResponse {
                     request_id,
                     data: Data::Object(DataObject {
@@ -208,11 +220,21 @@ impl Viss for Server {
                                 UpdateError::OutOfBoundsType => Error::BadRequest {
                                     msg: Some("Value out of type bounds.".into()),
                                 },
+                                UpdateError::OutOfBoundsIndex => Error::BadRequest {
+                                    msg: Some("Array index out of bounds.".into()),
+                                },
                                 UpdateError::UnsupportedType => Error::BadRequest {
                                     msg: Some("Unsupported data type.".into()),
                                 },
                                 UpdateError::PermissionDenied => Error::Forbidden,
                                 UpdateError::PermissionExpired => Error::UnauthorizedTokenExpired,
+                                UpdateError::Conflict => Error::BadRequest {
+                                    msg: Some("Value has changed.".into()),
+                                },
+                                UpdateError::Implausible(reason) => Error::BadRequest {
+                                    msg: Some(format!("Implausible value: {reason}")),
+                                },
+                                UpdateError::PolicyViolation(_) => Error::Forbidden,
                             }
                         } else {
                             Error::InternalServerError
@@ -259,19 +281,34 @@ impl Viss for Server {
             })?;
         let broker = self.broker.authorized_access(&permissions);
 
-        let Some(entries) = broker
-            .get_id_by_path(request.path.as_ref())
-            .await
-            .map(|id| HashMap::from([(id, HashSet::from([broker::Field::Datapoint]))]))
-        else {
+        let Some(metadata) = broker.get_metadata_by_path(request.path.as_ref()).await else {
             return Err(SubscribeErrorResponse {
                 request_id,
                 error: Error::NotFoundInvalidPath,
                 ts: SystemTime::now().into(),
             });
         };
+        let entries = HashMap::from([(metadata.id, HashSet::from([broker::Field::Datapoint]))]);
 
-        match broker.subscribe(entries, None).await {
+        let filters = match request.filter {
+            Some(filters) => filter::Filters::new(filters.into(), &metadata.data_type),
+            None => Ok(filter::Filters::default()),
+        };
+        let filters = filters.map_err(|error| SubscribeErrorResponse {
+            request_id: request_id.clone(),
+            error,
+            ts: SystemTime::now().into(),
+        })?;
+
+        let initial_snapshot = if request.changes_only {
+            broker::InitialSnapshot::Skip
+        } else {
+            broker::InitialSnapshot::Send
+        };
+        match broker
+            .subscribe_with_snapshot(entries, None, initial_snapshot)
+            .await
+        {
             Ok(stream) => {
                 let subscription_id = SubscriptionId::new();
 
@@ -286,7 +323,7 @@ impl Viss for Server {
                     SubscriptionHandle::from(abort_handle),
                 );
 
-                let stream = convert_to_viss_stream(subscription_id.clone(), stream);
+                let stream = convert_to_viss_stream(subscription_id.clone(), filters.apply(stream));
 
                 Ok((
                     SubscribeSuccessResponse {
@@ -304,6 +341,7 @@ impl Viss for Server {
                     broker::SubscriptionError::InvalidInput => Error::NotFoundInvalidPath,
                     broker::SubscriptionError::InternalError => Error::InternalServerError,
                     broker::SubscriptionError::InvalidBufferSize => Error::InternalServerError,
+                    broker::SubscriptionError::QuotaExceeded => Error::TooManyRequests,
                 },
                 ts: SystemTime::now().into(),
             }),
@@ -337,30 +375,30 @@ impl Viss for Server {
 
 fn convert_to_viss_stream(
     subscription_id: SubscriptionId,
-    stream: impl Stream<Item = broker::EntryUpdates>,
+    stream: impl Stream<Item = Result<(String, Vec<broker::Datapoint>), Error>>,
 ) -> impl Stream<Item = Result<SubscriptionEvent, SubscriptionErrorEvent>> {
-    stream.map(move |mut item| {
+    stream.map(move |item| {
         let ts = SystemTime::now().into();
         let subscription_id = subscription_id.clone();
-        match item.updates.pop() {
-            Some(item) => match (item.update.path, item.update.datapoint) {
-                (Some(path), Some(datapoint)) => Ok(SubscriptionEvent {
+        match item {
+            Ok((path, mut datapoints)) => {
+                let dp = if datapoints.len() == 1 {
+                    DataPoints::Single(datapoints.remove(0).into())
+                } else {
+                    DataPoints::Multiple(datapoints.into_iter().map(Into::into).collect())
+                };
+                Ok(SubscriptionEvent {
                     subscription_id,
                     data: Data::Object(DataObject {
                         path: path.into(),
-                        dp: datapoint.into(),
+                        dp,
                     }),
                     ts,
-                }),
-                (_, _) => Err(SubscriptionErrorEvent {
-                    subscription_id,
-                    error: Error::InternalServerError,
-                    ts,
-                }),
-            },
-            None => Err(SubscriptionErrorEvent {
+                })
+            }
+            Err(error) => Err(SubscriptionErrorEvent {
                 subscription_id,
-                error: Error::InternalServerError,
+                error,
                 ts,
             }),
         }
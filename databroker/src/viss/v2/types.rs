@@ -117,7 +117,10 @@ pub struct SubscribeRequest {
     pub path: Path,
     pub request_id: RequestId,
     pub authorization: Option<String>,
-    // filter: Option<Filter>,
+    pub filter: Option<Filters>,
+    /// Only send changes, not the current value first. Not part of VISS.
+    #[serde(default)]
+    pub changes_only: bool,
 }
 
 #[derive(Serialize)]
@@ -196,6 +199,14 @@ pub struct GenericErrorResponse {
 pub enum Filter {
     #[serde(rename = "static-metadata")]
     StaticMetadata(StaticMetadataFilter),
+    #[serde(rename = "timebased")]
+    Timebased(TimebasedFilter),
+    #[serde(rename = "change")]
+    Change(ChangeFilter),
+    #[serde(rename = "range")]
+    Range(RangeFilter),
+    #[serde(rename = "curvelog")]
+    Curvelog(CurvelogFilter),
 }
 
 #[derive(Deserialize)]
@@ -204,6 +215,127 @@ pub struct StaticMetadataFilter {
     // pub parameters: Option<StaticMetadataParameters>,
 }
 
+// Subscriptions can combine several filters, given as an array.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Filters {
+    Single(Filter),
+    Multiple(Vec<Filter>),
+}
+
+impl From<Filters> for Vec<Filter> {
+    fn from(filters: Filters) -> Self {
+        match filters {
+            Filters::Single(filter) => vec![filter],
+            Filters::Multiple(filters) => filters,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TimebasedFilter {
+    pub parameter: TimebasedParameter,
+}
+
+#[derive(Deserialize)]
+pub struct TimebasedParameter {
+    // In milliseconds
+    #[serde(deserialize_with = "string_or_number")]
+    pub period: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeFilter {
+    pub parameter: ChangeParameter,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeParameter {
+    #[serde(rename = "logic-op")]
+    pub logic_op: LogicOp,
+    #[serde(deserialize_with = "string_or_number")]
+    pub diff: String,
+}
+
+#[derive(Deserialize)]
+pub struct RangeFilter {
+    pub parameter: RangeParameter,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum RangeParameter {
+    Single(Boundary),
+    Multiple(Vec<Boundary>),
+}
+
+impl From<RangeParameter> for Vec<Boundary> {
+    fn from(parameter: RangeParameter) -> Self {
+        match parameter {
+            RangeParameter::Single(boundary) => vec![boundary],
+            RangeParameter::Multiple(boundaries) => boundaries,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Boundary {
+    #[serde(rename = "logic-op")]
+    pub logic_op: LogicOp,
+    #[serde(deserialize_with = "string_or_number")]
+    pub boundary: String,
+    #[serde(rename = "combination-op")]
+    pub combination_op: Option<CombinationOp>,
+}
+
+#[derive(Deserialize)]
+pub struct CurvelogFilter {
+    pub parameter: CurvelogParameter,
+}
+
+#[derive(Deserialize)]
+pub struct CurvelogParameter {
+    #[serde(deserialize_with = "string_or_number")]
+    pub maxerr: String,
+    #[serde(deserialize_with = "string_or_number")]
+    pub bufsize: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogicOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CombinationOp {
+    And,
+    Or,
+}
+
+// Filter parameters are strings in VISS, but plain numbers are accepted too.
+fn string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(serde_json::Number),
+    }
+    Ok(match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(value) => value,
+        StringOrNumber::Number(value) => value.to_string(),
+    })
+}
+
 // Unique id value specified by the client. Returned by the server in the
 // response and used by the client to link the request and response messages.
 // The value MAY be an integer or a Universally Unique Identifier (UUID).
@@ -254,7 +386,15 @@ pub enum Data {
 #[serde(rename_all = "camelCase")]
 pub struct DataObject {
     pub path: Path,
-    pub dp: DataPoint,
+    pub dp: DataPoints,
+}
+
+// Several data points are sent for a curvelog filter.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum DataPoints {
+    Single(DataPoint),
+    Multiple(Vec<DataPoint>),
 }
 
 #[derive(Serialize)]
@@ -380,11 +520,12 @@ pub struct ErrorSpec {
     pub message: String,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Debug, Serialize, Clone)]
 #[serde(into = "ErrorSpec")]
 #[allow(dead_code, clippy::enum_variant_names)]
 pub enum Error {
     BadRequest { msg: Option<String> },
+    BadRequestFilterInvalid { msg: Option<String> },
     UnauthorizedTokenExpired,
     UnauthorizedTokenInvalid,
     UnauthorizedTokenMissing,
@@ -393,6 +534,7 @@ pub enum Error {
     NotFoundInvalidPath,
     NotFoundUnavailableData,
     NotFoundInvalidSubscriptionId,
+    TooManyRequests,
     InternalServerError,
     NotImplemented,
 }
@@ -409,6 +551,11 @@ impl From<Error> for ErrorSpec {
                 message: custom_msg.unwrap_or("The server is unable to fulfil the client request because the request is malformed.".into()),
             },
             // BadRequest          400  filter_invalid            Filter requested on non-primitive type.
+            Error::BadRequestFilterInvalid{ msg: custom_msg } => ErrorSpec {
+                number: 400,
+                reason: "filter_invalid".into(),
+                message: custom_msg.unwrap_or("Filter requested on non-primitive type.".into()),
+            },
             // BadRequest          400  invalid_duration          Time duration is invalid.
             // BadRequest          400  invalid_value             The requested set value is invalid.
             // Unauthorized        401  token_expired             Access token has expired.
@@ -467,6 +614,11 @@ impl From<Error> for ErrorSpec {
             // NotAcceptable       406  insufficient_privileges   The privileges represented by the access token are not sufficient.
             // NotAcceptable       406  not_acceptable            The server is unable to generate content that is acceptable to the client
             // TooManyRequests     429  too_many_requests         The client has sent the server too many requests in a given amount of time.
+            Error::TooManyRequests => ErrorSpec {
+                number: 429,
+                reason: "too_many_requests".into(),
+                message: "The client has sent the server too many requests in a given amount of time.".into(),
+            },
             // InternalServerError 500  internal_server_error     The server encountered an unexpected condition which prevented it from fulfilling the request.
             Error::InternalServerError => ErrorSpec {
                 number: 500,
@@ -56,6 +56,14 @@ pub struct Entry {
     allowed: Option<Vec<serde_json::Value>>,
     #[serde(rename = "x-kuksa-changetype")]
     change_type: Option<ChangeType>,
+    #[serde(rename = "x-kuksa-safety-level")]
+    safety_level: Option<String>,
+    #[serde(rename = "x-kuksa-precision")]
+    precision: Option<u32>,
+
+    // actuator entry type only
+    #[serde(rename = "x-kuksa-target-ttl")]
+    target_ttl: Option<u64>,
 
     // attribute entry type only
     default: Option<serde_json::Value>,
@@ -72,6 +80,9 @@ pub struct DataEntry {
     pub max: Option<types::DataValue>,
     pub allowed: Option<types::DataValue>,
     pub default: Option<types::DataValue>,
+    pub safety_level: Option<types::SafetyLevel>,
+    pub target_ttl: Option<std::time::Duration>,
+    pub precision: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -97,6 +108,8 @@ pub enum ChangeType {
     OnChange,
     #[serde(rename = "continuous")]
     Continuous,
+    #[serde(rename = "event")]
+    Event,
 }
 
 #[derive(Debug, Deserialize)]
@@ -260,7 +273,7 @@ fn try_from_json_array(
 /// Will fail if the value does not match the given type,
 /// for example if a single value is given for an array type or vice versa
 /// This method is useful for instance when extracting the "default" value
-fn try_from_json_value(
+pub(crate) fn try_from_json_value(
     value: Option<serde_json::Value>,
     data_type: &types::DataType,
 ) -> Result<Option<types::DataValue>, Error> {
@@ -447,6 +460,9 @@ fn add_entry(
                     max: try_from_json_single_value(entry.max, &data_type)?,
                     allowed: try_from_json_array(entry.allowed, &data_type)?,
                     default: None, // isn't used by actuators
+                    safety_level: parse_safety_level(entry.safety_level)?,
+                    precision: entry.precision,
+                    target_ttl: entry.target_ttl.map(std::time::Duration::from_millis),
                     data_type,
                 },
             );
@@ -472,6 +488,9 @@ fn add_entry(
                     max: try_from_json_single_value(entry.max, &data_type)?,
                     allowed: try_from_json_array(entry.allowed, &data_type)?,
                     default: try_from_json_value(entry.default, &data_type)?,
+                    safety_level: parse_safety_level(entry.safety_level)?,
+                    precision: entry.precision,
+                    target_ttl: None, // isn't used by attributes
                     change_type: determine_change_type(
                         entry.change_type,
                         types::EntryType::Attribute,
@@ -502,6 +521,9 @@ fn add_entry(
                     allowed: try_from_json_array(entry.allowed, &data_type)?,
                     change_type: determine_change_type(entry.change_type, types::EntryType::Sensor),
                     default: None, // isn't used by sensors
+                    safety_level: parse_safety_level(entry.safety_level)?,
+                    precision: entry.precision,
+                    target_ttl: None, // isn't used by sensors
                     data_type,
                 },
             );
@@ -510,6 +532,12 @@ fn add_entry(
     }
 }
 
+fn parse_safety_level(safety_level: Option<String>) -> Result<Option<types::SafetyLevel>, Error> {
+    safety_level
+        .map(|safety_level| safety_level.parse().map_err(Error::ParseError))
+        .transpose()
+}
+
 fn determine_change_type(
     change_type: Option<ChangeType>,
     entry_type: types::EntryType,
@@ -519,6 +547,7 @@ fn determine_change_type(
             ChangeType::Continuous => types::ChangeType::Continuous,
             ChangeType::OnChange => types::ChangeType::OnChange,
             ChangeType::Static => types::ChangeType::Static,
+            ChangeType::Event => types::ChangeType::Event,
         },
         None => {
             //As a default return Continous Change type for sensors and actuators and static for attributes
@@ -565,7 +594,9 @@ fn test_parse_vss() {
                                 "datatype": "boolean",
                                 "description": "Indicates if ESC is enabled. True = Enabled. False = Disabled.",
                                 "type": "actuator",
-                                "uuid": "3f4f39b8d8c05c97a6de685282ba74b7"
+                                "uuid": "3f4f39b8d8c05c97a6de685282ba74b7",
+                                "x-kuksa-safety-level": "ASIL-B",
+                                "x-kuksa-target-ttl": 500
                             },
                             "IsEngaged": {
                                 "datatype": "boolean",
@@ -582,6 +613,7 @@ fn test_parse_vss() {
                                         "min": 0,
                                         "type": "sensor",
                                         "unit": "percent",
+                                        "x-kuksa-precision": 1,
                                         "uuid": "b0eb72430cd95bfbba0d187fcb6e2a62"
                                     }
                                 },
@@ -644,6 +676,11 @@ fn test_parse_vss() {
                         entry.description,
                         "Indicates if ESC is enabled. True = Enabled. False = Disabled."
                     );
+                    assert_eq!(entry.safety_level, Some(types::SafetyLevel::AsilB));
+                    assert_eq!(
+                        entry.target_ttl,
+                        Some(std::time::Duration::from_millis(500))
+                    );
                 }
                 None => panic!("Vehicle.ADAS.ESC.IsEnabled expected"),
             }
@@ -651,6 +688,9 @@ fn test_parse_vss() {
                 Some(entry) => {
                     assert_eq!(entry.data_type, types::DataType::Bool);
                     assert_eq!(entry.entry_type, types::EntryType::Sensor);
+                    assert_eq!(entry.safety_level, None);
+                    assert_eq!(entry.target_ttl, None);
+                    assert_eq!(entry.precision, None);
                 }
                 None => panic!("Vehicle.ADAS.ESC.IsEngaged expected"),
             }
@@ -662,6 +702,7 @@ fn test_parse_vss() {
                     assert_eq!(entry.min, Some(types::DataValue::Float(0.0)));
                     assert_eq!(entry.max, Some(types::DataValue::Float(100.0)));
                     assert_eq!(entry.unit, Some("percent".to_owned()));
+                    assert_eq!(entry.precision, Some(1));
                 }
                 None => panic!("Vehicle.ADAS.ESC.RoadFriction.MostProbable expected"),
             }
@@ -0,0 +1,369 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Fetching VSS files from a server at startup.
+//!
+//! The expected SHA-256 of the file can be given in the fragment of the URL,
+//! e.g. `https://example.com/vss.json#sha256=<64 hex digits>`. Files that
+//! don't match are rejected.
+//!
+//! Fetching is retried with exponential backoff. Each fetched file is kept
+//! in the cache directory, if one is configured, and used when the server
+//! can't be reached, so that a vehicle without connectivity still starts
+//! with the last catalog it got.
+
+use std::fmt;
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+const ATTEMPTS: u32 = 4;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidLocation(String),
+    ChecksumMismatch {
+        expected: String,
+        found: String,
+    },
+    /// Fetching failed and there is no usable cached copy
+    Unavailable {
+        url: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidLocation(msg) => write!(f, "invalid VSS location: {msg}"),
+            Error::ChecksumMismatch { expected, found } => {
+                write!(
+                    f,
+                    "checksum mismatch: expected sha256 {expected}, found {found}"
+                )
+            }
+            Error::Unavailable { url, reason } => write!(f, "failed to fetch {url}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Source {
+    url: String,
+    /// Lower case hex digits
+    sha256: Option<String>,
+}
+
+impl Source {
+    fn parse(location: &str) -> Result<Self, Error> {
+        let (url, fragment) = match location.split_once('#') {
+            Some((url, fragment)) => (url, Some(fragment)),
+            None => (location, None),
+        };
+        let sha256 = match fragment {
+            None => None,
+            Some(fragment) => {
+                let digits = fragment.strip_prefix("sha256=").ok_or_else(|| {
+                    Error::InvalidLocation(format!("unsupported fragment '{fragment}'"))
+                })?;
+                if digits.len() != 64 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(Error::InvalidLocation(
+                        "sha256 must be 64 hex digits".to_owned(),
+                    ));
+                }
+                Some(digits.to_ascii_lowercase())
+            }
+        };
+        Ok(Source {
+            url: url.to_owned(),
+            sha256,
+        })
+    }
+
+    fn verify(&self, contents: &[u8]) -> Result<(), Error> {
+        match &self.sha256 {
+            Some(expected) => {
+                let found = sha256(contents);
+                if *expected == found {
+                    Ok(())
+                } else {
+                    Err(Error::ChecksumMismatch {
+                        expected: expected.clone(),
+                        found,
+                    })
+                }
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn sha256(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// Reason a single attempt failed and whether trying again may help.
+struct Failure {
+    reason: String,
+    retryable: bool,
+}
+
+fn get(url: &str) -> Result<Vec<u8>, Failure> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    match agent.get(url).call() {
+        Ok(response) => {
+            let mut contents = Vec::new();
+            response
+                .into_reader()
+                .read_to_end(&mut contents)
+                .map_err(|err| Failure {
+                    reason: err.to_string(),
+                    retryable: true,
+                })?;
+            Ok(contents)
+        }
+        Err(ureq::Error::Status(status, response)) => Err(Failure {
+            reason: format!("HTTP {status} {}", response.status_text()),
+            retryable: status == 429 || status >= 500,
+        }),
+        Err(ureq::Error::Transport(err)) => Err(Failure {
+            // Without the URL ureq starts its messages with
+            reason: err
+                .to_string()
+                .trim_start_matches(&format!("{url}: "))
+                .to_owned(),
+            retryable: true,
+        }),
+    }
+}
+
+pub struct Fetcher {
+    cache_dir: Option<PathBuf>,
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl Fetcher {
+    /// Fetch files, keeping copies in `cache_dir` to fall back to.
+    pub fn new(cache_dir: Option<PathBuf>) -> Self {
+        Fetcher {
+            cache_dir,
+            attempts: ATTEMPTS,
+            backoff: INITIAL_BACKOFF,
+        }
+    }
+
+    /// The contents of the file at `location`, a URL with an optional
+    /// checksum, see the [module documentation](self).
+    pub async fn fetch(&self, location: &str) -> Result<Vec<u8>, Error> {
+        let source = Source::parse(location)?;
+        let cache_file = self.cache_file(&source.url);
+
+        let reason = match self.download(&source).await {
+            Ok(contents) => {
+                if let Some(cache_file) = &cache_file {
+                    if let Err(err) = store(cache_file, &contents) {
+                        warn!(
+                            "Failed to cache {} in {}: {err}",
+                            source.url,
+                            cache_file.display()
+                        );
+                    }
+                }
+                return Ok(contents);
+            }
+            Err(reason) => reason,
+        };
+
+        let Some(cache_file) = cache_file else {
+            return Err(Error::Unavailable {
+                url: source.url,
+                reason,
+            });
+        };
+        let contents = match fs::read(&cache_file) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::Unavailable {
+                    url: source.url,
+                    reason: format!("{reason}, no cached copy in {}", cache_file.display()),
+                });
+            }
+            Err(err) => {
+                return Err(Error::Unavailable {
+                    url: source.url,
+                    reason: format!("{reason}, reading cached copy failed: {err}"),
+                });
+            }
+        };
+        source.verify(&contents)?;
+        warn!(
+            "Failed to fetch {}: {reason}, using cached copy {}",
+            source.url,
+            cache_file.display()
+        );
+        Ok(contents)
+    }
+
+    async fn download(&self, source: &Source) -> Result<Vec<u8>, String> {
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            info!("Fetching VSS from {} (attempt {attempt})", source.url);
+            let url = source.url.clone();
+            let failure = match tokio::task::spawn_blocking(move || get(&url)).await {
+                Ok(Ok(contents)) => {
+                    return source
+                        .verify(&contents)
+                        .map(|_| contents)
+                        .map_err(|err| err.to_string());
+                }
+                Ok(Err(failure)) => failure,
+                Err(err) => Failure {
+                    reason: err.to_string(),
+                    retryable: false,
+                },
+            };
+            if !failure.retryable || attempt >= self.attempts {
+                return Err(failure.reason);
+            }
+            warn!(
+                "Failed to fetch {}: {}, retrying in {backoff:?}",
+                source.url, failure.reason
+            );
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    fn cache_file(&self, url: &str) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        Some(dir.join(format!("{}.json", sha256(url.as_bytes()))))
+    }
+}
+
+/// Replace `file` with `contents`, so that an interrupted write doesn't
+/// leave a partial copy.
+fn store(file: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = file.with_extension("partial");
+    fs::write(&partial, contents)?;
+    fs::rename(partial, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    const VSS: &[u8] = br#"{"Vehicle": {"type": "branch", "children": {}}}"#;
+
+    /// Serve `body` to a single request.
+    fn serve_once(body: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/vss.json", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+        });
+        url
+    }
+
+    fn fetcher(cache_dir: Option<PathBuf>) -> Fetcher {
+        Fetcher {
+            cache_dir,
+            attempts: 2,
+            backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_parse_location() {
+        let digits = "AB".repeat(32);
+        let source =
+            Source::parse(&format!("https://example.com/vss.json#sha256={digits}")).unwrap();
+        assert_eq!(source.url, "https://example.com/vss.json");
+        assert_eq!(source.sha256, Some("ab".repeat(32)));
+
+        let source = Source::parse("http://example.com/vss.json").unwrap();
+        assert_eq!(source.sha256, None);
+
+        assert!(Source::parse("https://example.com/vss.json#sha256=abc").is_err());
+        assert!(Source::parse("https://example.com/vss.json#md5=abc").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_falls_back_to_cache() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("kuksa-vss-cache-{}", std::process::id()));
+        let url = serve_once(VSS);
+        let location = format!("{url}#sha256={}", sha256(VSS));
+
+        let fetcher = fetcher(Some(cache_dir.clone()));
+        assert_eq!(fetcher.fetch(&location).await.unwrap(), VSS);
+        // The server is gone now
+        assert_eq!(fetcher.fetch(&location).await.unwrap(), VSS);
+
+        let other = format!("{url}#sha256={}", "0".repeat(64));
+        assert!(matches!(
+            fetcher.fetch(&other).await,
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        fs::remove_dir_all(cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_without_cache() {
+        let url = serve_once(VSS);
+        let location = format!("{url}#sha256={}", "0".repeat(64));
+        assert!(matches!(
+            fetcher(None).fetch(&location).await,
+            Err(Error::Unavailable { .. })
+        ));
+        assert!(matches!(
+            fetcher(None).fetch(&url).await,
+            Err(Error::Unavailable { .. })
+        ));
+    }
+}
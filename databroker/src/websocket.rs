@@ -0,0 +1,729 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Lightweight WebSocket API with plain JSON messages.
+//!
+//! Intended for web HMIs which only need to get, set and subscribe to a
+//! handful of signals and would rather not speak gRPC or full VISS.
+//! Every request is a JSON object with an `action` and an optional `id`,
+//! which is echoed in the response:
+//!
+//! ```json
+//! { "id": 1, "action": "authorize", "token": "eyJ0eXAiOiJKV1Qi..." }
+//! { "id": 2, "action": "get", "paths": ["Vehicle.Speed"] }
+//! { "id": 3, "action": "set", "values": { "Vehicle.Cabin.Light.IsDomeOn": true } }
+//! { "id": 4, "action": "subscribe", "paths": ["Vehicle.Speed"] }
+//! { "id": 5, "action": "unsubscribe", "subscription": 1 }
+//! ```
+//!
+//! Responses carry either the result or an `error`:
+//!
+//! ```json
+//! { "id": 2, "values": { "Vehicle.Speed": { "value": 42.5, "ts": 1700000000000 } } }
+//! { "id": 4, "subscription": 1 }
+//! { "id": 3, "error": { "code": "forbidden", "message": "Vehicle.Cabin.Light.IsDomeOn: permission denied" } }
+//! ```
+//!
+//! and notifications of a subscription look like
+//! `{ "subscription": 1, "values": { ... } }`. Timestamps are
+//! milliseconds since the UNIX epoch, unavailable values are `null`.
+//!
+//! The access token is the same as for the gRPC APIs. It is either sent
+//! in the `Authorization` header of the upgrade request or, since browsers
+//! can't set headers on WebSockets, with an `authorize` request.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::SystemTime;
+
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    http::{header, HeaderMap},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use crate::authorization::{self, Authorization};
+use crate::broker::{self, DataBroker};
+use crate::permissions::{self, PermissionError, Permissions};
+use crate::types::DataValue;
+use crate::vss;
+
+/// Notifications waiting to be written to a connection.
+const NOTIFICATION_BUFFER: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    action: Action,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Action {
+    Authorize {
+        token: String,
+    },
+    Get {
+        paths: Vec<String>,
+    },
+    Set {
+        values: HashMap<String, serde_json::Value>,
+    },
+    Subscribe {
+        paths: Vec<String>,
+    },
+    Unsubscribe {
+        subscription: u64,
+    },
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscription: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    values: Option<HashMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Error>,
+}
+
+#[derive(Debug, Serialize)]
+struct Value {
+    value: serde_json::Value,
+    ts: u128,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    TooManyRequests,
+    InternalError,
+}
+
+#[derive(Debug, Serialize)]
+struct Error {
+    code: ErrorCode,
+    message: String,
+}
+
+impl Error {
+    fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    broker: DataBroker,
+    authorization: Authorization,
+}
+
+pub async fn serve(
+    addr: impl Into<SocketAddr>,
+    broker: DataBroker,
+    authorization: Authorization,
+) -> Result<(), Box<dyn std::error::Error>> {
+    broker.add_served_api("websocket.json");
+    let app = Router::new()
+        .route("/", get(handle_upgrade))
+        .with_state(AppState {
+            broker,
+            authorization,
+        });
+
+    let addr = addr.into();
+    let builder = axum::Server::try_bind(&addr).map_err(|err| {
+        error!("Failed to bind address {addr}: {err}");
+        err
+    })?;
+
+    info!("WebSocket JSON service listening on {}", addr);
+    builder
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .map_err(|e| e.into())
+}
+
+async fn handle_upgrade(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    axum::extract::ConnectInfo(addr): axum::extract::ConnectInfo<SocketAddr>,
+    axum::extract::State(state): axum::extract::State<AppState>,
+) -> impl IntoResponse {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(str::to_owned);
+    ws.on_upgrade(move |socket| async move {
+        debug!("WebSocket connection from {addr}");
+        let (mut session, notifications) = Session::new(state.broker, state.authorization);
+        if let Some(token) = token {
+            if let Err(err) = session.authorize(&token) {
+                debug!("Ignoring access token of {addr}: {}", err.message);
+            }
+        }
+        handle_socket(socket, session, notifications).await;
+        info!("WebSocket connection closed ({addr})");
+    })
+}
+
+async fn handle_socket(
+    mut socket: WebSocket,
+    mut session: Session,
+    mut notifications: mpsc::Receiver<String>,
+) {
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let response = session.handle(&text).await;
+                    if socket.send(Message::Text(response)).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum
+                Some(Ok(_)) => {}
+            },
+            Some(notification) = notifications.recv() => {
+                if socket.send(Message::Text(notification)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// State of one connection.
+struct Session {
+    broker: DataBroker,
+    authorization: Authorization,
+    permissions: Option<Permissions>,
+    subscriptions: HashMap<u64, JoinHandle<()>>,
+    next_subscription: u64,
+    notifications: mpsc::Sender<String>,
+}
+
+impl Session {
+    fn new(broker: DataBroker, authorization: Authorization) -> (Self, mpsc::Receiver<String>) {
+        let permissions = match authorization {
+            Authorization::Disabled => Some(permissions::ALLOW_ALL.clone()),
+            Authorization::Enabled { .. } => None,
+        };
+        let (sender, receiver) = mpsc::channel(NOTIFICATION_BUFFER);
+        let session = Session {
+            broker,
+            authorization,
+            permissions,
+            subscriptions: HashMap::new(),
+            next_subscription: 1,
+            notifications: sender,
+        };
+        (session, receiver)
+    }
+
+    /// Handle a request and return the serialized response.
+    async fn handle(&mut self, text: &str) -> String {
+        let response = match serde_json::from_str::<Request>(text) {
+            Ok(request) => {
+                let id = request.id;
+                match self.handle_action(request.action).await {
+                    Ok(response) => Response { id, ..response },
+                    Err(error) => Response {
+                        id,
+                        error: Some(error),
+                        ..Default::default()
+                    },
+                }
+            }
+            Err(err) => Response {
+                error: Some(Error::new(ErrorCode::BadRequest, err.to_string())),
+                ..Default::default()
+            },
+        };
+        serde_json::to_string(&response).expect("response should be serializable")
+    }
+
+    async fn handle_action(&mut self, action: Action) -> Result<Response, Error> {
+        match action {
+            Action::Authorize { token } => {
+                self.authorize(&token)?;
+                Ok(Response::default())
+            }
+            Action::Get { paths } => self.get(paths).await,
+            Action::Set { values } => self.set(values).await,
+            Action::Subscribe { paths } => self.subscribe(paths).await,
+            Action::Unsubscribe { subscription } => {
+                match self.subscriptions.remove(&subscription) {
+                    Some(task) => {
+                        task.abort();
+                        Ok(Response {
+                            subscription: Some(subscription),
+                            ..Default::default()
+                        })
+                    }
+                    None => Err(Error::new(
+                        ErrorCode::NotFound,
+                        format!("no subscription {subscription}"),
+                    )),
+                }
+            }
+        }
+    }
+
+    fn authorize(&mut self, token: &str) -> Result<(), Error> {
+        let permissions = authorization::permissions_from_token(&self.authorization, Some(token))
+            .map_err(|err| {
+            self.broker.record_authorization_failure();
+            Error::new(ErrorCode::Unauthorized, err.to_string())
+        })?;
+        self.permissions = Some(permissions);
+        Ok(())
+    }
+
+    fn permissions(&self) -> Result<&Permissions, Error> {
+        self.permissions
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorCode::Unauthorized, "no access token provided"))
+    }
+
+    async fn get(&self, paths: Vec<String>) -> Result<Response, Error> {
+        let access = self.broker.authorized_access(self.permissions()?);
+        let mut values = HashMap::with_capacity(paths.len());
+        for path in paths {
+            let datapoint = access
+                .get_datapoint_by_path(&path)
+                .await
+                .map_err(|err| read_error(&path, err))?;
+            values.insert(path, Value::from(datapoint));
+        }
+        Ok(Response {
+            values: Some(values),
+            ..Default::default()
+        })
+    }
+
+    /// Set the target values of actuators.
+    async fn set(&self, values: HashMap<String, serde_json::Value>) -> Result<Response, Error> {
+        let access = self.broker.authorized_access(self.permissions()?);
+        let mut updates = Vec::with_capacity(values.len());
+        let mut paths = HashMap::with_capacity(values.len());
+        for (path, value) in values {
+            let Some(metadata) = access.get_metadata_by_path(&path).await else {
+                return Err(Error::new(
+                    ErrorCode::NotFound,
+                    format!("{path}: not found"),
+                ));
+            };
+            if metadata.entry_type != broker::EntryType::Actuator {
+                return Err(Error::new(
+                    ErrorCode::BadRequest,
+                    format!("{path}: not an actuator"),
+                ));
+            }
+            let value = match vss::try_from_json_value(Some(value), &metadata.data_type) {
+                Ok(Some(value)) => value,
+                Ok(None) => DataValue::NotAvailable,
+                Err(err) => {
+                    return Err(Error::new(
+                        ErrorCode::BadRequest,
+                        format!("{path}: expected a {}: {err}", metadata.data_type),
+                    ))
+                }
+            };
+            let update = broker::EntryUpdate {
+                actuator_target: Some(Some(broker::Datapoint {
+                    ts: SystemTime::now(),
+                    source_ts: None,
+                    value,
+                })),
+                ..Default::default()
+            };
+            paths.insert(metadata.id, path);
+            updates.push((metadata.id, update));
+        }
+        match access.update_entries(updates).await {
+            Ok(()) => Ok(Response::default()),
+            Err(errors) => match errors.into_iter().next() {
+                Some((id, err)) => {
+                    let path = paths.get(&id).map(String::as_str).unwrap_or_default();
+                    Err(update_error(path, err))
+                }
+                None => Err(Error::new(ErrorCode::InternalError, "update failed")),
+            },
+        }
+    }
+
+    async fn subscribe(&mut self, paths: Vec<String>) -> Result<Response, Error> {
+        let permissions = self.permissions()?;
+        let access = self.broker.authorized_access(permissions);
+        let mut entries = HashMap::with_capacity(paths.len());
+        for path in paths {
+            let Some(id) = access.get_id_by_path(&path).await else {
+                return Err(Error::new(
+                    ErrorCode::NotFound,
+                    format!("{path}: not found"),
+                ));
+            };
+            // Notifications leave out what can't be read, reject it upfront
+            permissions.can_read(&path).map_err(|err| match err {
+                PermissionError::Denied => {
+                    Error::new(ErrorCode::Forbidden, format!("{path}: permission denied"))
+                }
+                PermissionError::Expired => {
+                    Error::new(ErrorCode::Unauthorized, "access token expired")
+                }
+            })?;
+            entries.insert(id, [broker::Field::Datapoint].into());
+        }
+        let mut stream = access
+            .subscribe(entries, None)
+            .await
+            .map_err(subscription_error)?;
+
+        let subscription = self.next_subscription;
+        self.next_subscription += 1;
+        let sender = self.notifications.clone();
+        let task = tokio::spawn(async move {
+            while let Some(notification) = stream.next().await {
+                let values = notification
+                    .updates
+                    .into_iter()
+                    .filter_map(|change| Some((change.update.path?, change.update.datapoint?)))
+                    .map(|(path, datapoint)| (path, Value::from(datapoint)))
+                    .collect();
+                let notification = Response {
+                    subscription: Some(subscription),
+                    values: Some(values),
+                    ..Default::default()
+                };
+                let text =
+                    serde_json::to_string(&notification).expect("response should be serializable");
+                if sender.send(text).await.is_err() {
+                    break;
+                }
+            }
+        });
+        self.subscriptions.insert(subscription, task);
+        Ok(Response {
+            subscription: Some(subscription),
+            ..Default::default()
+        })
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        for task in self.subscriptions.values() {
+            task.abort();
+        }
+    }
+}
+
+impl From<broker::Datapoint> for Value {
+    fn from(datapoint: broker::Datapoint) -> Self {
+        Value {
+            value: datapoint.value.into(),
+            ts: datapoint
+                .ts
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|duration| duration.as_millis())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn read_error(path: &str, err: broker::ReadError) -> Error {
+    match err {
+        broker::ReadError::NotFound => {
+            Error::new(ErrorCode::NotFound, format!("{path}: not found"))
+        }
+        broker::ReadError::PermissionDenied => {
+            Error::new(ErrorCode::Forbidden, format!("{path}: permission denied"))
+        }
+        broker::ReadError::PermissionExpired => {
+            Error::new(ErrorCode::Unauthorized, "access token expired")
+        }
+    }
+}
+
+fn update_error(path: &str, err: broker::UpdateError) -> Error {
+    match err {
+        broker::UpdateError::NotFound => {
+            Error::new(ErrorCode::NotFound, format!("{path}: not found"))
+        }
+        broker::UpdateError::PermissionDenied | broker::UpdateError::PolicyViolation(_) => {
+            Error::new(ErrorCode::Forbidden, format!("{path}: permission denied"))
+        }
+        broker::UpdateError::PermissionExpired => {
+            Error::new(ErrorCode::Unauthorized, "access token expired")
+        }
+        broker::UpdateError::WrongType => {
+            Error::new(ErrorCode::BadRequest, format!("{path}: wrong data type"))
+        }
+        broker::UpdateError::OutOfBoundsAllowed => Error::new(
+            ErrorCode::BadRequest,
+            format!("{path}: value not in allowed values"),
+        ),
+        broker::UpdateError::OutOfBoundsMinMax => Error::new(
+            ErrorCode::BadRequest,
+            format!("{path}: value out of min/max bounds"),
+        ),
+        broker::UpdateError::OutOfBoundsType => Error::new(
+            ErrorCode::BadRequest,
+            format!("{path}: value out of type bounds"),
+        ),
+        broker::UpdateError::OutOfBoundsIndex => Error::new(
+            ErrorCode::BadRequest,
+            format!("{path}: array index out of bounds"),
+        ),
+        broker::UpdateError::UnsupportedType => Error::new(
+            ErrorCode::BadRequest,
+            format!("{path}: unsupported data type"),
+        ),
+        broker::UpdateError::Conflict => {
+            Error::new(ErrorCode::BadRequest, format!("{path}: value has changed"))
+        }
+        broker::UpdateError::Implausible(reason) => Error::new(
+            ErrorCode::BadRequest,
+            format!("{path}: implausible value: {reason}"),
+        ),
+    }
+}
+
+fn subscription_error(err: broker::SubscriptionError) -> Error {
+    match err {
+        broker::SubscriptionError::NotFound | broker::SubscriptionError::InvalidInput => {
+            Error::new(ErrorCode::NotFound, "not found")
+        }
+        broker::SubscriptionError::QuotaExceeded => {
+            Error::new(ErrorCode::TooManyRequests, "subscription quota exceeded")
+        }
+        broker::SubscriptionError::InternalError | broker::SubscriptionError::InvalidBufferSize => {
+            Error::new(ErrorCode::InternalError, "failed to subscribe")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization::jwt;
+    use crate::types::DataType;
+    use std::time::Duration;
+
+    async fn test_broker() -> DataBroker {
+        let broker = DataBroker::default();
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        for (path, entry_type) in [
+            ("Vehicle.Speed", broker::EntryType::Sensor),
+            (
+                "Vehicle.Cabin.Light.Brightness",
+                broker::EntryType::Actuator,
+            ),
+        ] {
+            access
+                .add_entry(
+                    path.to_owned(),
+                    DataType::Float,
+                    broker::ChangeType::OnChange,
+                    entry_type,
+                    "Test".to_owned(),
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        broker
+    }
+
+    async fn request(session: &mut Session, text: &str) -> serde_json::Value {
+        serde_json::from_str(&session.handle(text).await).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_and_set() {
+        let broker = test_broker().await;
+        let (mut session, _notifications) = Session::new(broker.clone(), Authorization::Disabled);
+
+        let response = request(
+            &mut session,
+            r#"{ "id": 1, "action": "get", "paths": ["Vehicle.Speed"] }"#,
+        )
+        .await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(
+            response["values"]["Vehicle.Speed"]["value"],
+            serde_json::Value::Null
+        );
+
+        let response = request(
+            &mut session,
+            r#"{ "id": "a", "action": "set", "values": { "Vehicle.Cabin.Light.Brightness": 40 } }"#,
+        )
+        .await;
+        assert_eq!(response, serde_json::json!({ "id": "a" }));
+        let target = broker
+            .authorized_access(&permissions::ALLOW_ALL)
+            .get_entry_by_path("Vehicle.Cabin.Light.Brightness")
+            .await
+            .unwrap()
+            .actuator_target
+            .unwrap();
+        assert_eq!(target.value, DataValue::Float(40.0));
+
+        let response = request(
+            &mut session,
+            r#"{ "action": "set", "values": { "Vehicle.Speed": 40 } }"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], "bad_request");
+
+        let response = request(
+            &mut session,
+            r#"{ "action": "get", "paths": ["Vehicle.Unknown"] }"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], "not_found");
+
+        let response = request(&mut session, r#"{ "action": "fly" }"#).await;
+        assert_eq!(response["error"]["code"], "bad_request");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let broker = test_broker().await;
+        let (mut session, mut notifications) =
+            Session::new(broker.clone(), Authorization::Disabled);
+
+        let response = request(
+            &mut session,
+            r#"{ "action": "subscribe", "paths": ["Vehicle.Speed"] }"#,
+        )
+        .await;
+        let subscription = response["subscription"].as_u64().unwrap();
+
+        // Initial values
+        let notification: serde_json::Value =
+            serde_json::from_str(&notifications.recv().await.unwrap()).unwrap();
+        assert_eq!(notification["subscription"], subscription);
+
+        let access = broker.authorized_access(&permissions::ALLOW_ALL);
+        let id = access.get_id_by_path("Vehicle.Speed").await.unwrap();
+        access
+            .update_entries([(
+                id,
+                broker::EntryUpdate {
+                    datapoint: Some(broker::Datapoint {
+                        ts: SystemTime::UNIX_EPOCH + Duration::from_millis(1500),
+                        source_ts: None,
+                        value: DataValue::Float(12.5),
+                    }),
+                    ..Default::default()
+                },
+            )])
+            .await
+            .unwrap();
+        let notification: serde_json::Value =
+            serde_json::from_str(&notifications.recv().await.unwrap()).unwrap();
+        assert_eq!(
+            notification["values"]["Vehicle.Speed"],
+            serde_json::json!({ "value": 12.5, "ts": 1500 })
+        );
+
+        let response = request(
+            &mut session,
+            &format!(r#"{{ "action": "unsubscribe", "subscription": {subscription} }}"#),
+        )
+        .await;
+        assert_eq!(response["subscription"], subscription);
+        let response = request(
+            &mut session,
+            &format!(r#"{{ "action": "unsubscribe", "subscription": {subscription} }}"#),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_authorization_required() {
+        let broker = test_broker().await;
+        let authorization = Authorization::new(jwt::DEV_PUBLIC_KEY.to_owned()).unwrap();
+        let (mut session, _notifications) = Session::new(broker.clone(), authorization);
+
+        let response = request(
+            &mut session,
+            r#"{ "action": "get", "paths": ["Vehicle.Speed"] }"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], "unauthorized");
+
+        let response = request(
+            &mut session,
+            r#"{ "action": "authorize", "token": "not-a-token" }"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], "unauthorized");
+        assert_eq!(broker.get_usage().await.authorization_failures, 1);
+
+        let token = jwt::Encoder::dev()
+            .mint("hmi", "read:Vehicle.Speed", Duration::from_secs(60))
+            .unwrap();
+        let response = request(
+            &mut session,
+            &format!(r#"{{ "action": "authorize", "token": "{token}" }}"#),
+        )
+        .await;
+        assert_eq!(response, serde_json::json!({}));
+        let response = request(
+            &mut session,
+            r#"{ "action": "get", "paths": ["Vehicle.Speed"] }"#,
+        )
+        .await;
+        assert!(response.get("values").is_some());
+        let response = request(
+            &mut session,
+            r#"{ "action": "set", "values": { "Vehicle.Cabin.Light.Brightness": 40 } }"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], "forbidden");
+        let response = request(
+            &mut session,
+            r#"{ "action": "subscribe", "paths": ["Vehicle.Cabin.Light.Brightness"] }"#,
+        )
+        .await;
+        assert_eq!(response["error"]["code"], "forbidden");
+    }
+}
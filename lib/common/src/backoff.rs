@@ -0,0 +1,126 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Delays between the attempts to connect or subscribe again, see
+//! [`Client::set_reconnect`](crate::Client::set_reconnect).
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// How long to wait before trying again, see [`Backoff::delay`].
+///
+/// By default the first retry happens after a second, every following one
+/// waits twice as long up to 30 seconds, with up to 10% less at random so
+/// that clients that lost the broker at the same time don't all come back at
+/// once. It retries until it succeeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    jitter: f64,
+    max_retries: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::exponential(Duration::from_secs(1), Duration::from_secs(30)).jitter(0.1)
+    }
+}
+
+impl Backoff {
+    /// Wait `interval` before every retry.
+    pub fn fixed(interval: Duration) -> Self {
+        Backoff {
+            initial: interval,
+            max: interval,
+            multiplier: 1.0,
+            jitter: 0.0,
+            max_retries: None,
+        }
+    }
+
+    /// Wait `initial` before the first retry and twice as long before every
+    /// following one, up to `max`.
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            initial,
+            max: max.max(initial),
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_retries: None,
+        }
+    }
+
+    /// Factor the delay grows by with every retry, at least 1.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Shorten every delay by up to `jitter` (0.0 to 1.0) of it at random.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Give up after `max_retries` consecutive failed retries.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Time to wait before retry number `attempt`, counting from 1, or None
+    /// if there are no retries left.
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.max_retries.is_some_and(|max| attempt > max) {
+            return None;
+        }
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = delay.min(self.max.as_secs_f64());
+        // Good enough randomness without pulling in a dependency for it
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(attempt);
+        let random = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+        Some(Duration::from_secs_f64(
+            delay * (1.0 - self.jitter * random),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::exponential(Duration::from_secs(1), Duration::from_secs(5));
+        let delays: Vec<_> = (1..=5).map(|attempt| backoff.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [1, 2, 4, 5, 5].map(|secs| Some(Duration::from_secs(secs)))
+        );
+
+        let backoff = Backoff::fixed(Duration::from_secs(2)).max_retries(2);
+        assert_eq!(backoff.delay(2), Some(Duration::from_secs(2)));
+        assert_eq!(backoff.delay(3), None);
+
+        let backoff = Backoff::fixed(Duration::from_secs(10)).jitter(0.5);
+        for attempt in 1..100 {
+            let delay = backoff.delay(attempt).unwrap();
+            assert!(delay >= Duration::from_secs(5) && delay <= Duration::from_secs(10));
+        }
+    }
+}
@@ -11,23 +11,49 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
-use crate::types::MetadataTypeV2;
+//! Conversions between the types of the API versions, each one available
+//! if the features of both versions are enabled.
+
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
+use crate::types::{
+    ActuateResponseSDVTypeV1, GetResponseSDVTypeV1, MetadataResponseSDVTypeV1, PathSDVTypeV1,
+    PublishResponseSDVTypeV1, PublishResponseTypeV1, SensorUpdateSDVTypeV1, SensorUpdateTypeV1,
+    SubscribeResponseSDVTypeV1, SubscribeSDVTypeV1, SubscribeTypeV1,
+};
+#[cfg(all(
+    feature = "kuksa-val-v1",
+    any(feature = "kuksa-val-v2", feature = "sdv-v1")
+))]
+use crate::types::{
+    ActuateResponseTypeV1, GetResponseTypeV1, MetadataResponseTypeV1, PathTypeV1,
+    SubscribeResponseTypeV1,
+};
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 use crate::types::{
-    ActuateResponseSDVTypeV1, ActuateResponseTypeV1, ActuateResponseTypeV2, GetResponseSDVTypeV1,
-    GetResponseTypeV1, MetadataResponseSDVTypeV1, MetadataResponseTypeV1, MetadataResponseTypeV2,
-    MultipleGetResponseTypeV2, MultipleUpdateActuationTypeV2, PathSDVTypeV1, PathTypeV1,
-    PathsTypeV2, PublishResponseSDVTypeV1, PublishResponseTypeV1, SensorUpdateSDVTypeV1,
-    SensorUpdateTypeV1, SensorUpdateTypeV2, SubscribeResponseSDVTypeV1, SubscribeResponseTypeV1,
-    SubscribeResponseTypeV2, SubscribeSDVTypeV1, SubscribeTypeV1, UpdateActuationTypeV1,
+    ActuateResponseTypeV2, MetadataResponseTypeV2, MetadataTypeV2, MultipleGetResponseTypeV2,
+    MultipleUpdateActuationTypeV2, PathsTypeV2, SensorUpdateTypeV2, SubscribeResponseTypeV2,
+    UpdateActuationTypeV1,
 };
+#[cfg(all(
+    feature = "kuksa-val-v1",
+    any(feature = "kuksa-val-v2", feature = "sdv-v1")
+))]
 use databroker_proto::kuksa::val::v1::{self as protoV1};
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 use databroker_proto::kuksa::val::v2::{self as protoV2};
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 use databroker_proto::sdv::databroker::v1 as SDVprotoV1;
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 use log::warn;
+#[cfg(all(
+    feature = "kuksa-val-v1",
+    any(feature = "kuksa-val-v2", feature = "sdv-v1")
+))]
 use std::collections::HashMap;
 
 // Idea: in the future we could use databroker internal datapoint structure and define it here then the conversion from databroker can be reused
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 fn find_common_root(paths: Vec<String>) -> String {
     if paths.is_empty() {
         return String::new();
@@ -57,6 +83,7 @@ pub trait ConvertToSDV<T> {
 
 // because the type of SensorUpdate(SDV)TypeV1 and UpdateActuation(SDV)TypeV1 the implementation is only once needed.
 // Hence no impl ConvertToV1<UpdateActuationTypeV1> for UpdateActuationSDVTypeV1{}
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<SensorUpdateSDVTypeV1> for SensorUpdateTypeV1 {
     fn convert_to_sdv(self) -> SensorUpdateSDVTypeV1 {
         self.into_iter()
@@ -74,18 +101,21 @@ impl ConvertToSDV<SensorUpdateSDVTypeV1> for SensorUpdateTypeV1 {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<PathSDVTypeV1> for PathTypeV1 {
     fn convert_to_sdv(self) -> PathSDVTypeV1 {
         self
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<SubscribeSDVTypeV1> for SubscribeTypeV1 {
     fn convert_to_sdv(self) -> SubscribeSDVTypeV1 {
         unimplemented!("SQL queries not supported anymore")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<PublishResponseSDVTypeV1> for PublishResponseTypeV1 {
     fn convert_to_sdv(self) -> PublishResponseSDVTypeV1 {
         SDVprotoV1::UpdateDatapointsReply {
@@ -94,6 +124,7 @@ impl ConvertToSDV<PublishResponseSDVTypeV1> for PublishResponseTypeV1 {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<GetResponseSDVTypeV1> for GetResponseTypeV1 {
     fn convert_to_sdv(self) -> GetResponseSDVTypeV1 {
         let transformed_map: HashMap<String, SDVprotoV1::Datapoint> = self
@@ -118,24 +149,28 @@ impl ConvertToSDV<GetResponseSDVTypeV1> for GetResponseTypeV1 {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<SubscribeResponseSDVTypeV1> for SubscribeResponseTypeV1 {
     fn convert_to_sdv(self) -> SubscribeResponseSDVTypeV1 {
         unimplemented!("Not possible to convert stream objects!")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<ActuateResponseSDVTypeV1> for ActuateResponseTypeV1 {
     fn convert_to_sdv(self) -> ActuateResponseSDVTypeV1 {
         unimplemented!("Not possible to convert stream objects!")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<MetadataResponseSDVTypeV1> for MetadataResponseTypeV1 {
     fn convert_to_sdv(self) -> MetadataResponseSDVTypeV1 {
         unimplemented!("Less information in metadata. It makes no sense to convert!")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToSDV<Option<SDVprotoV1::datapoint::Value>> for protoV1::Datapoint {
     fn convert_to_sdv(self) -> Option<SDVprotoV1::datapoint::Value> {
         if let Some(value) = self.value {
@@ -217,6 +252,7 @@ pub trait ConvertToV1<T> {
 
 // because the type of SensorUpdate(SDV)TypeV1 and UpdateActuation(SDV)TypeV1 the implementation is only once needed.
 // Hence no impl ConvertToV1<UpdateActuationTypeV1> for UpdateActuationSDVTypeV1{}
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<SensorUpdateTypeV1> for SensorUpdateSDVTypeV1 {
     fn convert_to_v1(self) -> SensorUpdateTypeV1 {
         self.into_iter()
@@ -234,22 +270,26 @@ impl ConvertToV1<SensorUpdateTypeV1> for SensorUpdateSDVTypeV1 {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<PathTypeV1> for PathSDVTypeV1 {
     fn convert_to_v1(self) -> PathTypeV1 {
         self
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<SubscribeTypeV1> for SubscribeSDVTypeV1 {
     fn convert_to_v1(self) -> SubscribeTypeV1 {
         unimplemented!("SQL queries not supported anymore")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<PublishResponseTypeV1> for PublishResponseSDVTypeV1 {
     fn convert_to_v1(self) -> PublishResponseTypeV1 {}
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<GetResponseTypeV1> for GetResponseSDVTypeV1 {
     fn convert_to_v1(self) -> GetResponseTypeV1 {
         self.into_iter()
@@ -271,18 +311,21 @@ impl ConvertToV1<GetResponseTypeV1> for GetResponseSDVTypeV1 {
 
 // because the type of SubscribeResponse(SDV)TypeV1 and ProvideResponse(SDV)TypeV1 the implementation is only once needed.
 // Hence no impl ConvertToV1<ProvideResponseTypeV1> for ProvideResponseSDVTypeV1{}
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<SubscribeResponseTypeV1> for SubscribeResponseSDVTypeV1 {
     fn convert_to_v1(self) -> SubscribeResponseTypeV1 {
         unimplemented!("Not possible to convert stream objects!")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<ActuateResponseTypeV1> for ActuateResponseSDVTypeV1 {
     fn convert_to_v1(self) -> ActuateResponseTypeV1 {
         unimplemented!("Not possible to convert stream objects!")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<MetadataResponseTypeV1> for MetadataResponseSDVTypeV1 {
     fn convert_to_v1(self) -> MetadataResponseTypeV1 {
         let transformed_metadata: Vec<protoV1::DataEntry> = self
@@ -298,6 +341,7 @@ impl ConvertToV1<MetadataResponseTypeV1> for MetadataResponseSDVTypeV1 {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<protoV1::Metadata> for SDVprotoV1::Metadata {
     fn convert_to_v1(self) -> protoV1::Metadata {
         let data_type = self.data_type().convert_to_v1();
@@ -411,6 +455,7 @@ impl ConvertToV1<protoV1::Metadata> for SDVprotoV1::Metadata {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<Option<i64>> for Option<SDVprotoV1::ValueRestriction> {
     fn convert_to_v1(self) -> Option<i64> {
         match self {
@@ -427,6 +472,7 @@ impl ConvertToV1<Option<i64>> for Option<SDVprotoV1::ValueRestriction> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<Option<f64>> for Option<SDVprotoV1::ValueRestriction> {
     fn convert_to_v1(self) -> Option<f64> {
         match self {
@@ -443,6 +489,7 @@ impl ConvertToV1<Option<f64>> for Option<SDVprotoV1::ValueRestriction> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<Option<u64>> for Option<SDVprotoV1::ValueRestriction> {
     fn convert_to_v1(self) -> Option<u64> {
         match self {
@@ -459,6 +506,7 @@ impl ConvertToV1<Option<u64>> for Option<SDVprotoV1::ValueRestriction> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<protoV1::EntryType> for SDVprotoV1::EntryType {
     fn convert_to_v1(self) -> protoV1::EntryType {
         match self {
@@ -470,6 +518,7 @@ impl ConvertToV1<protoV1::EntryType> for SDVprotoV1::EntryType {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<protoV1::DataType> for SDVprotoV1::DataType {
     fn convert_to_v1(self) -> protoV1::DataType {
         match self {
@@ -501,6 +550,7 @@ impl ConvertToV1<protoV1::DataType> for SDVprotoV1::DataType {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 impl ConvertToV1<Option<protoV1::datapoint::Value>> for SDVprotoV1::Datapoint {
     fn convert_to_v1(self) -> Option<protoV1::datapoint::Value> {
         if let Some(value) = self.value {
@@ -577,18 +627,21 @@ impl ConvertToV1<Option<protoV1::datapoint::Value>> for SDVprotoV1::Datapoint {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<ActuateResponseTypeV1> for ActuateResponseTypeV2 {
     fn convert_to_v1(self) -> ActuateResponseTypeV1 {
         self
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<SubscribeResponseTypeV1> for SubscribeResponseTypeV2 {
     fn convert_to_v1(self) -> SubscribeResponseTypeV1 {
         unimplemented!("Not possible to convert stream objects!")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<MetadataResponseTypeV1> for MetadataResponseTypeV2 {
     fn convert_to_v1(self) -> MetadataResponseTypeV1 {
         let transformed_vec = self
@@ -604,6 +657,7 @@ impl ConvertToV1<MetadataResponseTypeV1> for MetadataResponseTypeV2 {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<protoV1::Metadata> for protoV2::Metadata {
     fn convert_to_v1(self) -> protoV1::Metadata {
         protoV1::Metadata {
@@ -806,6 +860,7 @@ impl ConvertToV1<protoV1::Metadata> for protoV2::Metadata {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<Option<i64>> for Option<protoV2::Value> {
     fn convert_to_v1(self) -> Option<i64> {
         match self {
@@ -822,6 +877,7 @@ impl ConvertToV1<Option<i64>> for Option<protoV2::Value> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<Option<f64>> for Option<protoV2::Value> {
     fn convert_to_v1(self) -> Option<f64> {
         match self {
@@ -838,6 +894,7 @@ impl ConvertToV1<Option<f64>> for Option<protoV2::Value> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<Option<u64>> for Option<protoV2::Value> {
     fn convert_to_v1(self) -> Option<u64> {
         match self {
@@ -854,6 +911,7 @@ impl ConvertToV1<Option<u64>> for Option<protoV2::Value> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<Option<protoV1::datapoint::Value>> for Option<protoV2::Value> {
     fn convert_to_v1(self) -> Option<protoV1::datapoint::Value> {
         match self {
@@ -929,6 +987,7 @@ impl ConvertToV1<Option<protoV1::datapoint::Value>> for Option<protoV2::Value> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<Option<protoV1::Datapoint>> for Option<protoV2::Datapoint> {
     fn convert_to_v1(self) -> Option<protoV1::Datapoint> {
         match self {
@@ -941,6 +1000,7 @@ impl ConvertToV1<Option<protoV1::Datapoint>> for Option<protoV2::Datapoint> {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV1<GetResponseTypeV1> for MultipleGetResponseTypeV2 {
     fn convert_to_v1(self) -> GetResponseTypeV1 {
         warn!("This method is deprecated and conversion can not figure out which path the datapoint maps to. Dev must do this in his own code. E.g. by going through the requested paths.");
@@ -959,6 +1019,7 @@ pub trait ConvertToV2<T> {
     fn convert_to_v2(self) -> T;
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV2<SensorUpdateTypeV2> for protoV1::Datapoint {
     fn convert_to_v2(self) -> SensorUpdateTypeV2 {
         match self.value {
@@ -1048,12 +1109,14 @@ impl ConvertToV2<SensorUpdateTypeV2> for protoV1::Datapoint {
 }
 
 // Since SubscribeTypeV2 is PathsTypeV2 we do not need to have a separate conversion for that one
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV2<PathsTypeV2> for PathTypeV1 {
     fn convert_to_v2(self) -> PathsTypeV2 {
         self
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV2<MetadataTypeV2> for PathTypeV1 {
     fn convert_to_v2(self) -> MetadataTypeV2 {
         // in the future find_common_root() could also provide filters, like everything or something like "branch1.*, branch2.*"
@@ -1061,12 +1124,14 @@ impl ConvertToV2<MetadataTypeV2> for PathTypeV1 {
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV2<SubscribeResponseTypeV2> for SubscribeResponseTypeV1 {
     fn convert_to_v2(self) -> SubscribeResponseTypeV2 {
         unimplemented!("Not possible to convert stream objects!")
     }
 }
 
+#[cfg(all(feature = "kuksa-val-v1", feature = "kuksa-val-v2"))]
 impl ConvertToV2<MultipleUpdateActuationTypeV2> for UpdateActuationTypeV1 {
     fn convert_to_v2(self) -> MultipleUpdateActuationTypeV2 {
         let transformed_map: HashMap<String, protoV2::Value> = self
@@ -1077,7 +1142,12 @@ impl ConvertToV2<MultipleUpdateActuationTypeV2> for UpdateActuationTypeV1 {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(
+    test,
+    feature = "kuksa-val-v1",
+    feature = "kuksa-val-v2",
+    feature = "sdv-v1"
+))]
 mod tests {
     use super::*;
 
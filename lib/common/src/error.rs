@@ -0,0 +1,272 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Classification of the errors returned by the clients.
+//!
+//! All clients (`kuksa`, `kuksa-sdv` and `kuksa_val_v2`) return a
+//! [`ClientError`]. Whether it was a lost connection, a gRPC status or an
+//! error in the response body, [`ClientError::code`] maps it to an
+//! [`ErrorCode`], so that applications can decide how to react without
+//! matching on status messages:
+//!
+//! ```
+//! # use kuksa_common::ClientError;
+//! fn should_retry(err: &ClientError) -> bool {
+//!     err.is_retryable() && !err.is_auth_failure()
+//! }
+//! ```
+
+#[cfg(feature = "kuksa-val-v1")]
+use databroker_proto::kuksa::val::v1 as proto;
+
+use crate::ClientError;
+
+/// Protocol independent error code, following the gRPC status codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// The connection to the server couldn't be established or was lost
+    Connection,
+    Cancelled,
+    Unknown,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+}
+
+impl ErrorCode {
+    /// Map the HTTP style codes of kuksa.val.v1 response errors.
+    pub fn from_v1(code: u32) -> Self {
+        match code {
+            400 => ErrorCode::InvalidArgument,
+            401 => ErrorCode::Unauthenticated,
+            403 => ErrorCode::PermissionDenied,
+            404 => ErrorCode::NotFound,
+            408 | 504 => ErrorCode::DeadlineExceeded,
+            409 => ErrorCode::AlreadyExists,
+            412 => ErrorCode::FailedPrecondition,
+            429 => ErrorCode::ResourceExhausted,
+            500 => ErrorCode::Internal,
+            501 => ErrorCode::Unimplemented,
+            503 => ErrorCode::Unavailable,
+            _ => ErrorCode::Unknown,
+        }
+    }
+
+    /// Whether the same request may succeed when sent again later.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::Connection
+                | ErrorCode::DeadlineExceeded
+                | ErrorCode::ResourceExhausted
+                | ErrorCode::Aborted
+                | ErrorCode::Unavailable
+        )
+    }
+
+    /// Whether the token is missing, invalid or lacks the permission.
+    pub fn is_auth_failure(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::Unauthenticated | ErrorCode::PermissionDenied
+        )
+    }
+}
+
+impl From<tonic::Code> for ErrorCode {
+    fn from(code: tonic::Code) -> Self {
+        match code {
+            tonic::Code::Cancelled => ErrorCode::Cancelled,
+            tonic::Code::InvalidArgument => ErrorCode::InvalidArgument,
+            tonic::Code::DeadlineExceeded => ErrorCode::DeadlineExceeded,
+            tonic::Code::NotFound => ErrorCode::NotFound,
+            tonic::Code::AlreadyExists => ErrorCode::AlreadyExists,
+            tonic::Code::PermissionDenied => ErrorCode::PermissionDenied,
+            tonic::Code::ResourceExhausted => ErrorCode::ResourceExhausted,
+            tonic::Code::FailedPrecondition => ErrorCode::FailedPrecondition,
+            tonic::Code::Aborted => ErrorCode::Aborted,
+            tonic::Code::OutOfRange => ErrorCode::OutOfRange,
+            tonic::Code::Unimplemented => ErrorCode::Unimplemented,
+            tonic::Code::Internal => ErrorCode::Internal,
+            tonic::Code::Unavailable => ErrorCode::Unavailable,
+            tonic::Code::DataLoss => ErrorCode::DataLoss,
+            tonic::Code::Unauthenticated => ErrorCode::Unauthenticated,
+            tonic::Code::Ok | tonic::Code::Unknown => ErrorCode::Unknown,
+        }
+    }
+}
+
+/// An error reported in the body of a response, either for the whole
+/// request or for one of its paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseError {
+    /// The offending path, `None` if the error is about the whole request
+    pub path: Option<String>,
+    /// Code as sent by the server, see [`ResponseError::error_code`]
+    pub code: u32,
+    pub reason: String,
+    pub message: String,
+}
+
+impl ResponseError {
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::from_v1(self.code)
+    }
+
+    /// Collect the errors of a kuksa.val.v1 `GetResponse` or `SetResponse`.
+    #[cfg(feature = "kuksa-val-v1")]
+    pub fn from_v1_response(
+        error: Option<proto::Error>,
+        errors: Vec<proto::DataEntryError>,
+    ) -> Vec<ResponseError> {
+        error
+            .map(ResponseError::from)
+            .into_iter()
+            .chain(errors.into_iter().filter_map(|entry_error| {
+                let mut error = ResponseError::from(entry_error.error?);
+                error.path = Some(entry_error.path);
+                Some(error)
+            }))
+            .collect()
+    }
+}
+
+#[cfg(feature = "kuksa-val-v1")]
+impl From<proto::Error> for ResponseError {
+    fn from(error: proto::Error) -> Self {
+        ResponseError {
+            path: None,
+            code: error.code,
+            reason: error.reason,
+            message: error.message,
+        }
+    }
+}
+
+impl ClientError {
+    /// The code of the error, the one of the first error if the response
+    /// contained several.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ClientError::Connection(_) => ErrorCode::Connection,
+            ClientError::Status(status) => status.code().into(),
+            ClientError::Function(errors) => errors
+                .first()
+                .map_or(ErrorCode::Unknown, ResponseError::error_code),
+        }
+    }
+
+    /// The paths the server reported errors for.
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            ClientError::Function(errors) => errors
+                .iter()
+                .filter_map(|error| error.path.as_deref())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether the request may succeed when sent again later. A response
+    /// with several errors is only retryable if all of them are.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Function(errors) => {
+                !errors.is_empty() && errors.iter().all(|error| error.error_code().is_retryable())
+            }
+            _ => self.code().is_retryable(),
+        }
+    }
+
+    /// Whether the request failed for lack of (valid) authorization.
+    pub fn is_auth_failure(&self) -> bool {
+        match self {
+            ClientError::Function(errors) => errors
+                .iter()
+                .any(|error| error.error_code().is_auth_failure()),
+            _ => self.code().is_auth_failure(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "kuksa-val-v1"))]
+mod tests {
+    use super::*;
+
+    fn v1_error(code: u32) -> proto::Error {
+        proto::Error {
+            code,
+            reason: String::new(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_from_v1_response() {
+        let errors = ResponseError::from_v1_response(
+            Some(v1_error(400)),
+            vec![
+                proto::DataEntryError {
+                    path: "Vehicle.Speed".to_owned(),
+                    error: Some(v1_error(403)),
+                },
+                proto::DataEntryError {
+                    path: "Vehicle.Width".to_owned(),
+                    error: None,
+                },
+            ],
+        );
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].path, None);
+        assert_eq!(errors[1].path.as_deref(), Some("Vehicle.Speed"));
+
+        let err = ClientError::Function(errors);
+        assert_eq!(err.code(), ErrorCode::InvalidArgument);
+        assert_eq!(err.paths(), vec!["Vehicle.Speed"]);
+        assert!(err.is_auth_failure());
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_classification() {
+        let unavailable = ClientError::Status(tonic::Status::unavailable("restarting"));
+        assert_eq!(unavailable.code(), ErrorCode::Unavailable);
+        assert!(unavailable.is_retryable());
+        assert!(!unavailable.is_auth_failure());
+
+        let unauthenticated = ClientError::Status(tonic::Status::unauthenticated("expired"));
+        assert!(unauthenticated.is_auth_failure());
+        assert!(!unauthenticated.is_retryable());
+
+        let connection = ClientError::Connection("refused".to_owned());
+        assert_eq!(connection.code(), ErrorCode::Connection);
+        assert!(connection.is_retryable());
+        assert!(connection.paths().is_empty());
+
+        let throttled = ClientError::Function(vec![v1_error(429).into()]);
+        assert_eq!(throttled.code(), ErrorCode::ResourceExhausted);
+        assert!(throttled.is_retryable());
+        assert!(!ClientError::Function(vec![]).is_retryable());
+    }
+}
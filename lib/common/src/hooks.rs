@@ -0,0 +1,111 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Hooks run around the requests of a client.
+//!
+//! Request hooks may add metadata or reject a request, response and error
+//! hooks see the outcome, e.g. for logging or metrics:
+//!
+//! ```
+//! # use kuksa_common::Client;
+//! let mut client = Client::new(http::Uri::from_static("http://localhost:55555"));
+//! client.on_request(|_call, metadata| {
+//!     metadata.insert("x-request-source", "dashboard".parse().unwrap());
+//!     Ok(())
+//! });
+//! client.on_error(|call, err| eprintln!("{} failed: {err}", call.method));
+//! ```
+//!
+//! The hooks run for the requests of the clients that report their calls
+//! through [`Client::begin`] and [`Client::end`], like `kuksa::KuksaClient`.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+use std::time::Instant;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::ClientError;
+
+pub type RequestHook =
+    dyn Fn(&Call, &mut tonic::metadata::MetadataMap) -> Result<(), tonic::Status> + Send + Sync;
+pub type ResponseHook = dyn Fn(&Call) + Send + Sync;
+pub type ErrorHook = dyn Fn(&Call, &ClientError) + Send + Sync;
+
+/// A request in flight.
+#[derive(Debug)]
+pub struct Call {
+    /// Full gRPC method name, e.g. `kuksa.val.v1.VAL/Get`
+    pub method: &'static str,
+    // Instant isn't available in the browser
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    started: Instant,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Call {
+    pub(crate) fn new(method: &'static str, cancellation: Option<CancellationToken>) -> Self {
+        Call {
+            method,
+            #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+            started: Instant::now(),
+            cancellation,
+        }
+    }
+
+    /// Run `request`, aborting it with a `Cancelled` status once the
+    /// cancellation token of the client is cancelled.
+    pub async fn run<T>(
+        &self,
+        request: impl Future<Output = Result<T, ClientError>>,
+    ) -> Result<T, ClientError> {
+        let Some(token) = &self.cancellation else {
+            return request.await;
+        };
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(ClientError::Status(tonic::Status::cancelled(
+                format!("{} was cancelled", self.method),
+            ))),
+            result = request => result,
+        }
+    }
+
+    /// Time since the request was started, `None` with grpc-web.
+    pub fn elapsed(&self) -> Option<Duration> {
+        #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+        return Some(self.started.elapsed());
+        #[cfg(all(feature = "grpc-web", target_arch = "wasm32"))]
+        return None;
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    pub(crate) request: Vec<Arc<RequestHook>>,
+    pub(crate) response: Vec<Arc<ResponseHook>>,
+    pub(crate) error: Vec<Arc<ErrorHook>>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("request", &self.request.len())
+            .field("response", &self.response.len())
+            .field("error", &self.error.len())
+            .finish()
+    }
+}
@@ -11,17 +11,45 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
+pub mod backoff;
 pub mod conversion;
+pub mod error;
+pub mod hooks;
 pub mod types;
 
-use databroker_proto::kuksa::val::v1::Error;
+pub use backoff::Backoff;
+pub use error::{ErrorCode, ResponseError};
+pub use hooks::Call;
+pub use tokio_util::sync::CancellationToken;
+
+use hooks::Hooks;
+
 use http::Uri;
-use log::info;
+use log::{info, warn};
 use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "env-logger")]
 use std::sync::Once;
+use std::time::SystemTime;
+#[cfg(feature = "connection-state")]
 use tokio_stream::wrappers::BroadcastStream;
-use tonic::{async_trait, transport::Channel};
+use tonic::async_trait;
+
+#[cfg(not(any(
+    feature = "transport",
+    all(feature = "grpc-web", target_arch = "wasm32")
+)))]
+compile_error!("Either the \"transport\" or, on wasm32, the \"grpc-web\" feature must be enabled");
 
+/// Channel the generated gRPC clients are created with.
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+pub type Channel = tonic::transport::Channel;
+/// Channel the generated gRPC clients are created with.
+#[cfg(all(feature = "grpc-web", target_arch = "wasm32"))]
+pub type Channel = tonic_web_wasm_client::Client;
+
+#[cfg(feature = "env-logger")]
 static INIT: Once = Once::new();
 
 #[derive(Debug)]
@@ -30,8 +58,70 @@ pub struct Client {
     token: Option<tonic::metadata::AsciiMetadataValue>,
     #[cfg(feature = "tls")]
     tls_config: Option<tonic::transport::ClientTlsConfig>,
-    channel: Option<tonic::transport::Channel>,
+    channel: Option<Channel>,
+    /// Additional connections requests are distributed over
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    pool: ChannelPool,
+    #[cfg(feature = "connection-state")]
     connection_state_subs: Option<tokio::sync::broadcast::Sender<ConnectionState>>,
+    token_file: Option<TokenFile>,
+    hooks: Hooks,
+    cancellation: Option<CancellationToken>,
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    reconnect: Option<Reconnect>,
+}
+
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+#[derive(Debug)]
+struct ChannelPool {
+    size: usize,
+    channels: Vec<Channel>,
+    next: usize,
+}
+
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+impl ChannelPool {
+    /// The next additional connection, `None` when it's the turn of the
+    /// first one.
+    fn next(&mut self) -> Option<&Channel> {
+        let index = self.next % (self.channels.len() + 1);
+        self.next = self.next.wrapping_add(1);
+        index.checked_sub(1).map(|index| &self.channels[index])
+    }
+}
+
+/// The first connection and the additional ones of the pool.
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+type Connections = (Channel, Vec<Channel>);
+
+/// Connecting again in the background after the connection was lost.
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+#[derive(Debug)]
+struct Reconnect {
+    backoff: Backoff,
+    /// Connection established in the background, taken by the next request
+    connected: Arc<std::sync::Mutex<Option<Connections>>>,
+    task: Option<ReconnectTask>,
+}
+
+/// Stops reconnecting when the client is dropped.
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+#[derive(Debug)]
+struct ReconnectTask(tokio::task::JoinHandle<()>);
+
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+impl Drop for ReconnectTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Token file and the state of it when it was last read.
+#[derive(Debug)]
+struct TokenFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
 }
 
 #[derive(Clone)]
@@ -40,14 +130,19 @@ pub enum ConnectionState {
     Disconnected,
 }
 
+/// Error of a client request, see [`ClientError::code`] and
+/// [`ClientError::is_retryable`] for a protocol independent classification.
 #[derive(Debug, Clone)]
 pub enum ClientError {
     Connection(String),
     Status(tonic::Status),
-    Function(Vec<Error>),
+    /// Errors reported in the response of a kuksa.val.v1 request
+    Function(Vec<ResponseError>),
 }
 
-#[async_trait]
+// Requests made through grpc-web can't be sent between threads
+#[cfg_attr(all(feature = "grpc-web", target_arch = "wasm32"), async_trait(?Send))]
+#[cfg_attr(not(all(feature = "grpc-web", target_arch = "wasm32")), async_trait)]
 pub trait SDVClientTraitV1 {
     type SensorUpdateType;
     type UpdateActuationType;
@@ -95,7 +190,8 @@ pub trait SDVClientTraitV1 {
     ) -> Result<Self::MetadataResponseType, ClientError>;
 }
 
-#[async_trait]
+#[cfg_attr(all(feature = "grpc-web", target_arch = "wasm32"), async_trait(?Send))]
+#[cfg_attr(not(all(feature = "grpc-web", target_arch = "wasm32")), async_trait)]
 pub trait ClientTraitV1 {
     type SensorUpdateType;
     type UpdateActuationType;
@@ -254,11 +350,15 @@ impl std::fmt::Display for ClientError {
             ClientError::Function(err) => {
                 let formatted_result: String = err
                     .iter()
-                    .map(|element| {
-                        format!(
+                    .map(|element| match &element.path {
+                        Some(path) => format!(
+                            "path: {path}, code: {}, message: {}, reason: {}",
+                            element.code, element.message, element.reason
+                        ),
+                        None => format!(
                             "code: {}, message: {}, reason: {}",
                             element.code, element.message, element.reason
-                        )
+                        ),
                     })
                     .collect::<Vec<String>>()
                     .join(", "); // Join the elements with a comma and space
@@ -272,6 +372,7 @@ impl std::fmt::Display for ClientError {
 #[derive(Debug)]
 pub enum TokenError {
     MalformedTokenError(String),
+    TokenFileError(String),
 }
 
 impl std::error::Error for TokenError {}
@@ -279,14 +380,21 @@ impl std::fmt::Display for TokenError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TokenError::MalformedTokenError(msg) => f.pad(msg),
+            TokenError::TokenFileError(msg) => f.pad(msg),
         }
     }
 }
 
+/// The URI of a server, `http://` if no scheme is given. Unix domain sockets
+/// are given as `unix:///run/kuksa/databroker.sock` (or `unix:` followed by
+/// the absolute path).
 pub fn to_uri(uri: impl AsRef<str>) -> Result<Uri, String> {
+    if let Some(path) = uri.as_ref().strip_prefix("unix:") {
+        return unix_socket_uri(path);
+    }
     let uri = uri
         .as_ref()
-        .parse::<tonic::transport::Uri>()
+        .parse::<Uri>()
         .map_err(|err| format!("{err}"))?;
     let mut parts = uri.into_parts();
 
@@ -311,15 +419,37 @@ pub fn to_uri(uri: impl AsRef<str>) -> Result<Uri, String> {
         None => return Err("No server uri specified".to_owned()),
     }
     parts.path_and_query = Some("".parse().expect("uri path should be empty string"));
-    tonic::transport::Uri::from_parts(parts).map_err(|err| format!("{err}"))
+    Uri::from_parts(parts).map_err(|err| format!("{err}"))
+}
+
+/// A URI the path of the socket can be stored in, as `http::Uri` requires an
+/// authority along with the scheme.
+#[cfg(unix)]
+fn unix_socket_uri(path: &str) -> Result<Uri, String> {
+    let path = path.strip_prefix("//").unwrap_or(path);
+    if !path.starts_with('/') {
+        return Err(format!("Unix socket path {path} is not absolute"));
+    }
+    format!("unix://localhost{path}")
+        .parse::<Uri>()
+        .map_err(|err| format!("{err}"))
 }
 
+#[cfg(not(unix))]
+fn unix_socket_uri(_path: &str) -> Result<Uri, String> {
+    Err("Unix sockets are not supported on this platform".to_owned())
+}
+
+#[cfg(feature = "env-logger")]
 fn init_logger() {
     INIT.call_once(|| {
         env_logger::init();
     });
 }
 
+#[cfg(not(feature = "env-logger"))]
+fn init_logger() {}
+
 impl Client {
     pub fn new(uri: Uri) -> Self {
         init_logger();
@@ -330,7 +460,19 @@ impl Client {
             #[cfg(feature = "tls")]
             tls_config: None,
             channel: None,
+            #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+            pool: ChannelPool {
+                size: 1,
+                channels: Vec::new(),
+                next: 0,
+            },
+            #[cfg(feature = "connection-state")]
             connection_state_subs: None,
+            token_file: None,
+            hooks: Hooks::default(),
+            cancellation: None,
+            #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+            reconnect: None,
         }
     }
 
@@ -343,20 +485,180 @@ impl Client {
         self.tls_config = Some(tls_config);
     }
 
+    /// Open `size` connections to the server and distribute the requests
+    /// over them round robin, instead of multiplexing them all over one
+    /// connection. For applications with many parallel streams, which
+    /// would otherwise be limited by the concurrent streams the server
+    /// allows per connection. Takes effect when (re)connecting.
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    pub fn set_pool_size(&mut self, size: usize) {
+        self.pool.size = size.max(1);
+    }
+
+    /// Connect again in the background once the connection was lost (see
+    /// [`Client::disconnect`]) or couldn't be established, waiting as set by
+    /// `backoff` before every attempt. The next request uses the connection
+    /// once it's back, subscribers of the connection state are notified.
+    /// Requests made in the meantime still try to connect on their own.
+    /// Reconnecting needs a Tokio runtime, outside of one it doesn't start.
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    pub fn set_reconnect(&mut self, backoff: Backoff) {
+        self.reconnect = Some(Reconnect {
+            backoff,
+            connected: Arc::default(),
+            task: None,
+        });
+    }
+
     pub fn set_access_token(&mut self, token: impl AsRef<str>) -> Result<(), TokenError> {
-        match tonic::metadata::AsciiMetadataValue::try_from(&format!("Bearer {}", token.as_ref())) {
-            Ok(token) => {
-                self.token = Some(token);
-                Ok(())
+        self.token_file = None;
+        self.token = Some(Self::to_bearer(token.as_ref())?);
+        Ok(())
+    }
+
+    /// Use the token stored in the file at `path`.
+    ///
+    /// The file is checked for changes before each request and re-read when
+    /// it was modified, so rotated tokens are picked up without restarting.
+    /// If re-reading fails, the previous token is kept.
+    pub fn set_access_token_file(&mut self, path: impl AsRef<Path>) -> Result<(), TokenError> {
+        let path = path.as_ref().to_path_buf();
+        let (token, modified, len) = Self::read_token_file(&path)?;
+        self.token = Some(token);
+        self.token_file = Some(TokenFile {
+            path,
+            modified,
+            len,
+        });
+        Ok(())
+    }
+
+    fn to_bearer(token: &str) -> Result<tonic::metadata::AsciiMetadataValue, TokenError> {
+        tonic::metadata::AsciiMetadataValue::try_from(&format!("Bearer {token}"))
+            .map_err(|err| TokenError::MalformedTokenError(format!("{err}")))
+    }
+
+    fn read_token_file(
+        path: &Path,
+    ) -> Result<(tonic::metadata::AsciiMetadataValue, Option<SystemTime>, u64), TokenError> {
+        let file_error =
+            |err| TokenError::TokenFileError(format!("Failed to read {}: {err}", path.display()));
+        // Stat before reading, so that a change while reading is detected
+        // next time
+        let metadata = std::fs::metadata(path).map_err(file_error)?;
+        let token = std::fs::read_to_string(path).map_err(file_error)?;
+        Ok((
+            Self::to_bearer(token.trim())?,
+            metadata.modified().ok(),
+            metadata.len(),
+        ))
+    }
+
+    fn refresh_token_file(&mut self) {
+        let Some(token_file) = &mut self.token_file else {
+            return;
+        };
+        let changed = match std::fs::metadata(&token_file.path) {
+            Ok(metadata) => {
+                metadata.modified().ok() != token_file.modified || metadata.len() != token_file.len
+            }
+            // E.g. replaced by a rename, read it once it is back
+            Err(_) => false,
+        };
+        if changed {
+            match Self::read_token_file(&token_file.path) {
+                Ok((token, modified, len)) => {
+                    info!("Re-read access token from {}", token_file.path.display());
+                    self.token = Some(token);
+                    token_file.modified = modified;
+                    token_file.len = len;
+                }
+                Err(err) => warn!("Keeping previous access token: {err}"),
             }
-            Err(err) => Err(TokenError::MalformedTokenError(format!("{err}"))),
         }
     }
 
+    /// Run `hook` before each request, see [`hooks`].
+    pub fn on_request(
+        &mut self,
+        hook: impl Fn(&Call, &mut tonic::metadata::MetadataMap) -> Result<(), tonic::Status>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.hooks.request.push(Arc::new(hook));
+    }
+
+    /// Run `hook` after each successful request.
+    pub fn on_response(&mut self, hook: impl Fn(&Call) + Send + Sync + 'static) {
+        self.hooks.response.push(Arc::new(hook));
+    }
+
+    /// Run `hook` after each failed request, including the ones rejected by
+    /// a request hook.
+    pub fn on_error(&mut self, hook: impl Fn(&Call, &ClientError) + Send + Sync + 'static) {
+        self.hooks.error.push(Arc::new(hook));
+    }
+
+    /// Abort pending requests and close the streams opened through this
+    /// client once `token` is cancelled, e.g. when the application shuts
+    /// down. Pass a [`CancellationToken::child_token`] to cancel only this
+    /// client. Requests started afterwards fail right away.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    pub fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Start a request to `method`, running the request hooks on its
+    /// `metadata`. To be called by the clients, which pass the result of the
+    /// request to [`Client::end`].
+    // ClientError embeds a tonic::Status, which is large by design
+    #[allow(clippy::result_large_err)]
+    pub fn begin(
+        &self,
+        method: &'static str,
+        metadata: &mut tonic::metadata::MetadataMap,
+    ) -> Result<Call, ClientError> {
+        let call = Call::new(method, self.cancellation.clone());
+        for hook in &self.hooks.request {
+            if let Err(status) = hook(&call, metadata) {
+                return self.end(&call, Err(ClientError::Status(status)));
+            }
+        }
+        Ok(call)
+    }
+
+    /// Finish `call`, running the response or error hooks.
+    #[allow(clippy::result_large_err)]
+    pub fn end<T>(&self, call: &Call, result: Result<T, ClientError>) -> Result<T, ClientError> {
+        match &result {
+            Ok(_) => self.hooks.response.iter().for_each(|hook| hook(call)),
+            Err(err) => self.hooks.error.iter().for_each(|hook| hook(call, err)),
+        }
+        result
+    }
+
     pub fn is_connected(&self) -> bool {
         self.channel.is_some()
     }
 
+    /// Drop the connection, e.g. once it was lost, so that the next request
+    /// connects again. Subscribers of the connection state are notified.
+    pub fn disconnect(&mut self) {
+        if self.channel.take().is_some() {
+            #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+            self.pool.channels.clear();
+            self.notify_connection_state(ConnectionState::Disconnected)
+                .unwrap_or_default();
+            #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+            self.start_reconnect();
+        }
+    }
+
+    #[cfg(feature = "connection-state")]
     pub fn subscribe_to_connection_state(&mut self) -> BroadcastStream<ConnectionState> {
         match &self.connection_state_subs {
             Some(stream) => BroadcastStream::new(stream.subscribe()),
@@ -368,14 +670,55 @@ impl Client {
         }
     }
 
+    #[cfg(feature = "connection-state")]
+    fn notify_connection_state(&self, state: ConnectionState) -> Result<(), String> {
+        if let Some(subs) = &self.connection_state_subs {
+            subs.send(state)
+                .map_err(|err| format!("Failed to notify connection state change: {err}"))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "connection-state"))]
+    fn notify_connection_state(&self, _state: ConnectionState) -> Result<(), String> {
+        Ok(())
+    }
+
+    #[cfg(all(feature = "grpc-web", target_arch = "wasm32"))]
     async fn try_create_channel(&mut self) -> Result<&Channel, ClientError> {
+        // Requests are only sent once used, there is no connection to
+        // establish beforehand
+        let base_url = self.uri.to_string();
+        let channel = tonic_web_wasm_client::Client::new(base_url.trim_end_matches('/').to_owned());
+        self.notify_connection_state(ConnectionState::Connected)
+            .map_err(ClientError::Connection)?;
+        self.channel = Some(channel);
+        Ok(self.channel.as_ref().expect("Channel should exist"))
+    }
+
+    /// The endpoint to connect to, and the unix socket to connect through
+    /// if any.
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    #[allow(clippy::result_large_err)]
+    fn endpoint(&self) -> Result<(tonic::transport::Endpoint, Option<PathBuf>), ClientError> {
+        // Connections to a unix socket are made by the connector, the URI of
+        // the channel only names the host for HTTP/2
+        let socket = match self.uri.scheme_str() {
+            Some("unix") => Some(PathBuf::from(self.uri.path())),
+            _ => None,
+        };
+        let uri = match socket {
+            Some(_) => Uri::from_static("http://localhost"),
+            None => self.uri.clone(),
+        };
         #[cfg(feature = "tls")]
-        let mut builder = tonic::transport::Channel::builder(self.uri.clone());
+        let mut builder = tonic::transport::Channel::builder(uri);
         #[cfg(not(feature = "tls"))]
-        let builder = tonic::transport::Channel::builder(self.uri.clone());
+        let builder = tonic::transport::Channel::builder(uri);
 
+        // No TLS on unix sockets, the file permissions restrict access
         #[cfg(feature = "tls")]
-        if let Some(tls_config) = &self.tls_config {
+        if let (Some(tls_config), None) = (&self.tls_config, &socket) {
             match builder.tls_config(tls_config.clone()) {
                 Ok(new_builder) => {
                     builder = new_builder;
@@ -387,23 +730,24 @@ impl Client {
                 }
             }
         }
+        Ok((builder, socket))
+    }
 
-        match builder.connect().await {
-            Ok(channel) => {
-                if let Some(subs) = &self.connection_state_subs {
-                    subs.send(ConnectionState::Connected).map_err(|err| {
-                        ClientError::Connection(format!(
-                            "Failed to notify connection state change: {err}"
-                        ))
-                    })?;
-                }
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    async fn try_create_channel(&mut self) -> Result<&Channel, ClientError> {
+        let (builder, socket) = self.endpoint()?;
+        match connect_pool(&builder, socket.as_deref(), self.pool.size).await {
+            Ok((channel, pool)) => {
+                self.notify_connection_state(ConnectionState::Connected)
+                    .map_err(ClientError::Connection)?;
                 self.channel = Some(channel);
+                self.pool.channels = pool;
                 Ok(self.channel.as_ref().expect("Channel should exist"))
             }
             Err(err) => {
-                if let Some(subs) = &self.connection_state_subs {
-                    subs.send(ConnectionState::Disconnected).unwrap_or_default();
-                }
+                self.notify_connection_state(ConnectionState::Disconnected)
+                    .unwrap_or_default();
+                self.start_reconnect();
                 Err(ClientError::Connection(format!(
                     "Failed to connect to {}: {}",
                     self.uri, err
@@ -412,31 +756,114 @@ impl Client {
         }
     }
 
+    /// Start connecting in the background, if enabled and not already
+    /// underway.
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    fn start_reconnect(&mut self) {
+        // Without a runtime, e.g. when disconnecting from synchronous code,
+        // there is nothing to run the task on
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let Ok((endpoint, socket)) = self.endpoint() else {
+            return;
+        };
+        let uri = self.uri.clone();
+        let pool_size = self.pool.size;
+        let cancellation = self.cancellation.clone();
+        #[cfg(feature = "connection-state")]
+        let connection_state_subs = self.connection_state_subs.clone();
+        let Some(reconnect) = &mut self.reconnect else {
+            return;
+        };
+        if reconnect
+            .task
+            .as_ref()
+            .is_some_and(|task| !task.0.is_finished())
+        {
+            return;
+        }
+        let backoff = reconnect.backoff.clone();
+        let connected = reconnect.connected.clone();
+        let task = runtime.spawn(async move {
+            let mut attempt = 1;
+            while let Some(delay) = backoff.delay(attempt) {
+                tokio::time::sleep(delay).await;
+                if cancellation
+                    .as_ref()
+                    .is_some_and(|token| token.is_cancelled())
+                {
+                    return;
+                }
+                match connect_pool(&endpoint, socket.as_deref(), pool_size).await {
+                    Ok(channels) => {
+                        info!("Reconnected to {} after {} attempts", uri, attempt);
+                        *connected.lock().expect("reconnect lock poisoned") = Some(channels);
+                        #[cfg(feature = "connection-state")]
+                        if let Some(subs) = connection_state_subs {
+                            let _ = subs.send(ConnectionState::Connected);
+                        }
+                        return;
+                    }
+                    Err(err) => warn!("Failed to reconnect to {}: {}", uri, err),
+                }
+                attempt += 1;
+            }
+            warn!("Gave up reconnecting to {}", uri);
+        });
+        reconnect.task = Some(ReconnectTask(task));
+    }
+
+    /// Use the connection established in the background, if there is one.
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    fn take_reconnected(&mut self) {
+        let Some(reconnect) = &self.reconnect else {
+            return;
+        };
+        let connected = reconnect
+            .connected
+            .lock()
+            .expect("reconnect lock poisoned")
+            .take();
+        if let Some((channel, pool)) = connected {
+            self.channel = Some(channel);
+            self.pool.channels = pool;
+        }
+    }
+
     pub async fn try_connect(&mut self) -> Result<(), ClientError> {
         self.try_create_channel().await?;
         Ok(())
     }
 
-    pub async fn try_connect_to(&mut self, uri: tonic::transport::Uri) -> Result<(), ClientError> {
+    pub async fn try_connect_to(&mut self, uri: Uri) -> Result<(), ClientError> {
         self.uri = uri;
         self.try_create_channel().await?;
         Ok(())
     }
 
     pub async fn get_channel(&mut self) -> Result<&Channel, ClientError> {
+        #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
         if self.channel.is_none() {
-            self.try_create_channel().await
-        } else {
-            match &self.channel {
-                Some(channel) => Ok(channel),
-                None => unreachable!(),
-            }
+            self.take_reconnected();
+        }
+        if self.channel.is_none() {
+            return self.try_create_channel().await;
+        }
+        #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+        if let Some(channel) = self.pool.next() {
+            return Ok(channel);
+        }
+        match &self.channel {
+            Some(channel) => Ok(channel),
+            None => unreachable!(),
         }
     }
 
     pub fn get_auth_interceptor(
         &mut self,
     ) -> impl FnMut(tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> + '_ {
+        self.refresh_token_file();
         move |mut req: tonic::Request<()>| {
             if let Some(token) = &self.token {
                 // debug!("Inserting auth token: {:?}", token);
@@ -446,3 +873,258 @@ impl Client {
         }
     }
 }
+
+/// Open `size` connections to the server of `endpoint`, the first one and
+/// those of the pool.
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+async fn connect_pool(
+    endpoint: &tonic::transport::Endpoint,
+    socket: Option<&Path>,
+    size: usize,
+) -> Result<Connections, tonic::transport::Error> {
+    let channel = connect(endpoint, socket).await?;
+    let mut pool = Vec::with_capacity(size - 1);
+    for _ in 1..size {
+        pool.push(connect(endpoint, socket).await?);
+    }
+    Ok((channel, pool))
+}
+
+/// Connect to the server of `endpoint`, or through the unix socket at
+/// `socket` if given.
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+async fn connect(
+    endpoint: &tonic::transport::Endpoint,
+    socket: Option<&Path>,
+) -> Result<Channel, tonic::transport::Error> {
+    match socket {
+        #[cfg(unix)]
+        Some(path) => {
+            let path = path.to_owned();
+            endpoint
+                .connect_with_connector(tower::service_fn(move |_: Uri| {
+                    tokio::net::UnixStream::connect(path.clone())
+                }))
+                .await
+        }
+        _ => endpoint.connect().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization(client: &mut Client) -> Option<String> {
+        let mut interceptor = client.get_auth_interceptor();
+        let request = interceptor(tonic::Request::new(())).unwrap();
+        request
+            .metadata()
+            .get("authorization")
+            .map(|token| token.to_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn test_access_token_file_is_reread_when_modified() {
+        let path = std::env::temp_dir().join(format!("kuksa-token-{}", std::process::id()));
+        std::fs::write(&path, "first\n").unwrap();
+
+        let mut client = Client::new(Uri::from_static("http://localhost:55555"));
+        client.set_access_token_file(&path).unwrap();
+        assert_eq!(authorization(&mut client).as_deref(), Some("Bearer first"));
+
+        std::fs::write(&path, "second").unwrap();
+        // Don't depend on the timestamp resolution of the file system
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(authorization(&mut client).as_deref(), Some("Bearer second"));
+
+        // Keeps the previous token while the file is gone
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(authorization(&mut client).as_deref(), Some("Bearer second"));
+
+        client.set_access_token("explicit").unwrap();
+        assert_eq!(
+            authorization(&mut client).as_deref(),
+            Some("Bearer explicit")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let mut client = Client::new(Uri::from_static("http://localhost:55555"));
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        let call = client.begin("kuksa.val.v1.VAL/Get", &mut metadata).unwrap();
+        assert_eq!(call.run(async { Ok(1) }).await.unwrap(), 1);
+
+        let token = CancellationToken::new();
+        client.set_cancellation_token(token.child_token());
+        let call = client.begin("kuksa.val.v1.VAL/Get", &mut metadata).unwrap();
+        let pending = call.run(std::future::pending::<Result<(), ClientError>>());
+        token.cancel();
+        assert_eq!(pending.await.unwrap_err().code(), ErrorCode::Cancelled);
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn test_hooks() {
+        use std::sync::Mutex;
+
+        let mut client = Client::new(Uri::from_static("http://localhost:55555"));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let requests = log.clone();
+        client.on_request(move |call, metadata| {
+            requests
+                .lock()
+                .unwrap()
+                .push(format!("request {}", call.method));
+            if metadata.contains_key("x-reject") {
+                return Err(tonic::Status::permission_denied("rejected"));
+            }
+            metadata.insert("x-source", "test".parse().unwrap());
+            Ok(())
+        });
+        let responses = log.clone();
+        client.on_response(move |call| {
+            assert!(call.elapsed().is_some());
+            responses
+                .lock()
+                .unwrap()
+                .push(format!("response {}", call.method));
+        });
+        let errors = log.clone();
+        client.on_error(move |call, err| {
+            errors
+                .lock()
+                .unwrap()
+                .push(format!("error {}: {:?}", call.method, err.code()));
+        });
+
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        let call = client.begin("kuksa.val.v1.VAL/Get", &mut metadata).unwrap();
+        assert_eq!(metadata.get("x-source").unwrap(), "test");
+        assert_eq!(client.end(&call, Ok(1)).unwrap(), 1);
+
+        let call = client.begin("kuksa.val.v1.VAL/Set", &mut metadata).unwrap();
+        let result: Result<(), _> = client.end(&call, Err(ClientError::Connection("".to_owned())));
+        assert!(result.is_err());
+
+        metadata.insert("x-reject", "1".parse().unwrap());
+        assert!(client.begin("kuksa.val.v1.VAL/Set", &mut metadata).is_err());
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "request kuksa.val.v1.VAL/Get",
+                "response kuksa.val.v1.VAL/Get",
+                "request kuksa.val.v1.VAL/Set",
+                "error kuksa.val.v1.VAL/Set: Connection",
+                "request kuksa.val.v1.VAL/Set",
+                "error kuksa.val.v1.VAL/Set: PermissionDenied",
+            ]
+        );
+    }
+
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn test_channel_pool_round_robin() {
+        let channel =
+            || tonic::transport::Channel::from_static("http://localhost:55555").connect_lazy();
+        let mut pool = ChannelPool {
+            size: 3,
+            channels: vec![channel(), channel()],
+            next: 0,
+        };
+        let turns: Vec<bool> = (0..6).map(|_| pool.next().is_some()).collect();
+        assert_eq!(turns, [false, true, true, false, true, true]);
+    }
+
+    #[cfg(all(
+        feature = "connection-state",
+        not(all(feature = "grpc-web", target_arch = "wasm32"))
+    ))]
+    #[tokio::test]
+    async fn test_reconnect_in_background() {
+        use tokio_stream::StreamExt;
+
+        // A free port, nothing listens on it until later
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut client = Client::new(to_uri(format!("127.0.0.1:{port}")).unwrap());
+        client.set_reconnect(Backoff::fixed(std::time::Duration::from_millis(10)));
+        let mut states = client.subscribe_to_connection_state();
+        assert!(client.try_connect().await.is_err());
+        assert!(!client.is_connected());
+
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+        let connected = async {
+            while let Some(state) = states.next().await {
+                if let Ok(ConnectionState::Connected) = state {
+                    return;
+                }
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), connected)
+            .await
+            .unwrap();
+        client.get_channel().await.unwrap();
+        assert!(client.is_connected());
+    }
+
+    #[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+    #[test]
+    fn test_disconnect_outside_runtime() {
+        let mut client = Client::new(Uri::from_static("http://localhost:55555"));
+        client.set_reconnect(Backoff::default());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        client.channel = Some(runtime.block_on(async {
+            tonic::transport::Channel::from_static("http://localhost:55555").connect_lazy()
+        }));
+
+        client.disconnect();
+        assert!(!client.is_connected());
+        assert!(client.reconnect.as_ref().unwrap().task.is_none());
+    }
+
+    #[test]
+    fn test_missing_access_token_file() {
+        let mut client = Client::new(Uri::from_static("http://localhost:55555"));
+        let result = client.set_access_token_file("/nonexistent/kuksa-token");
+        assert!(matches!(result, Err(TokenError::TokenFileError(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_to_uri() {
+        assert_eq!(
+            to_uri("127.0.0.1:55555").unwrap().to_string(),
+            "http://127.0.0.1:55555/"
+        );
+        for uri in [
+            "unix:///run/kuksa/databroker.sock",
+            "unix:/run/kuksa/databroker.sock",
+        ] {
+            let uri = to_uri(uri).unwrap();
+            assert_eq!(uri.scheme_str(), Some("unix"));
+            assert_eq!(uri.path(), "/run/kuksa/databroker.sock");
+        }
+        assert!(to_uri("unix:databroker.sock").is_err());
+    }
+}
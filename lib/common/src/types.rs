@@ -11,71 +11,119 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
+#[cfg(any(feature = "kuksa-val-v1", feature = "kuksa-val-v2", feature = "sdv-v1"))]
 use std::collections::HashMap;
 
+#[cfg(feature = "kuksa-val-v1")]
 use databroker_proto::kuksa::val::v1 as protoV1;
+#[cfg(feature = "kuksa-val-v2")]
 use databroker_proto::kuksa::val::v2 as protoV2;
+#[cfg(feature = "sdv-v1")]
 use databroker_proto::sdv::databroker::v1 as SDVprotoV1;
 
+#[cfg(any(feature = "kuksa-val-v1", feature = "sdv-v1"))]
 use tonic::Streaming;
 
 // Type aliases SDV
+#[cfg(feature = "sdv-v1")]
 pub type SensorUpdateSDVTypeV1 = HashMap<String, SDVprotoV1::Datapoint>;
+#[cfg(feature = "sdv-v1")]
 pub type UpdateActuationSDVTypeV1 = HashMap<String, SDVprotoV1::Datapoint>;
+#[cfg(feature = "sdv-v1")]
 pub type PathSDVTypeV1 = Vec<String>;
+#[cfg(feature = "sdv-v1")]
 pub type SubscribeSDVTypeV1 = String;
+#[cfg(feature = "sdv-v1")]
 pub type PublishResponseSDVTypeV1 = SDVprotoV1::UpdateDatapointsReply;
+#[cfg(feature = "sdv-v1")]
 pub type GetResponseSDVTypeV1 = HashMap<String, SDVprotoV1::Datapoint>;
+#[cfg(feature = "sdv-v1")]
 pub type SubscribeResponseSDVTypeV1 = Streaming<SDVprotoV1::SubscribeReply>;
+#[cfg(feature = "sdv-v1")]
 pub type ProvideResponseSDVTypeV1 = Streaming<SDVprotoV1::SubscribeReply>;
+#[cfg(feature = "sdv-v1")]
 pub type ActuateResponseSDVTypeV1 = SDVprotoV1::SetDatapointsReply;
+#[cfg(feature = "sdv-v1")]
 pub type MetadataResponseSDVTypeV1 = Vec<SDVprotoV1::Metadata>;
 
 // Type aliases V1
+#[cfg(feature = "kuksa-val-v1")]
 pub type SensorUpdateTypeV1 = HashMap<String, protoV1::Datapoint>;
+#[cfg(feature = "kuksa-val-v1")]
 pub type UpdateActuationTypeV1 = HashMap<String, protoV1::Datapoint>;
+#[cfg(feature = "kuksa-val-v1")]
 pub type PathTypeV1 = Vec<String>;
+#[cfg(feature = "kuksa-val-v1")]
 pub type SubscribeTypeV1 = PathTypeV1;
+#[cfg(feature = "kuksa-val-v1")]
 pub type PublishResponseTypeV1 = ();
+#[cfg(feature = "kuksa-val-v1")]
 pub type GetResponseTypeV1 = Vec<protoV1::DataEntry>;
+#[cfg(feature = "kuksa-val-v1")]
 pub type SubscribeResponseTypeV1 = Streaming<protoV1::SubscribeResponse>;
+#[cfg(feature = "kuksa-val-v1")]
 pub type ProvideResponseTypeV1 = Streaming<protoV1::SubscribeResponse>;
+#[cfg(feature = "kuksa-val-v1")]
 pub type ActuateResponseTypeV1 = ();
+#[cfg(feature = "kuksa-val-v1")]
 pub type MetadataResponseTypeV1 = GetResponseTypeV1;
 
 // Type aliases V2
+#[cfg(feature = "kuksa-val-v2")]
 pub type SensorUpdateTypeV2 = protoV2::Value;
+#[cfg(feature = "kuksa-val-v2")]
 pub type UpdateActuationTypeV2 = SensorUpdateTypeV2;
+#[cfg(feature = "kuksa-val-v2")]
 pub type MultipleUpdateActuationTypeV2 = HashMap<PathTypeV2, UpdateActuationTypeV2>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type PathTypeV2 = String;
+#[cfg(feature = "kuksa-val-v2")]
 pub type PathsTypeV2 = Vec<PathTypeV2>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type IdsTypeV2 = Vec<i32>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type SubscribeTypeV2 = PathsTypeV2;
+#[cfg(feature = "kuksa-val-v2")]
 pub type SubscribeByIdTypeV2 = IdsTypeV2;
+#[cfg(feature = "kuksa-val-v2")]
 pub type PublishResponseTypeV2 = ();
+#[cfg(feature = "kuksa-val-v2")]
 pub type GetResponseTypeV2 = Option<protoV2::Datapoint>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type MultipleGetResponseTypeV2 = Vec<protoV2::Datapoint>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type SubscribeResponseTypeV2 = tonic::Streaming<protoV2::SubscribeResponse>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type SubscribeByIdResponseTypeV2 = tonic::Streaming<protoV2::SubscribeByIdResponse>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type ProvideResponseTypeV2 = ();
+#[cfg(feature = "kuksa-val-v2")]
 pub type ActuateResponseTypeV2 = ();
+#[cfg(feature = "kuksa-val-v2")]
 pub type OpenProviderStreamResponseTypeV2 = OpenProviderStream;
+#[cfg(feature = "kuksa-val-v2")]
 pub type MetadataTypeV2 = (PathTypeV2, String);
+#[cfg(feature = "kuksa-val-v2")]
 pub type MetadataResponseTypeV2 = Vec<protoV2::Metadata>;
+#[cfg(feature = "kuksa-val-v2")]
 pub type ServerInfoTypeV2 = ServerInfo;
 
+#[cfg(feature = "kuksa-val-v2")]
 #[derive(Debug)]
 pub struct ServerInfo {
     pub name: String,
     pub commit_hash: String,
     pub version: String,
+    pub capabilities: Option<protoV2::ServerCapabilities>,
 }
 
+#[cfg(feature = "kuksa-val-v2")]
 pub struct OpenProviderStream {
     pub sender: tokio::sync::mpsc::Sender<protoV2::OpenProviderStreamRequest>,
     pub receiver_stream: tonic::Streaming<protoV2::OpenProviderStreamResponse>,
 }
 
+#[cfg(feature = "kuksa-val-v2")]
 impl OpenProviderStream {
     pub fn new(
         sender: tokio::sync::mpsc::Sender<protoV2::OpenProviderStreamRequest>,
@@ -0,0 +1,22 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::path::Path;
+
+// Typed accessors used by the typed_accessors example
+fn main() {
+    let vss = "../../data/vss-core/vss_release_4.0.json";
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join("vss.rs");
+    kuksa_codegen::generate_file(vss, out).unwrap();
+    println!("cargo:rerun-if-changed={vss}");
+}
@@ -13,16 +13,10 @@
  * ******************************************************************************
  */
 
-use databroker_proto::kuksa::val::v2::open_provider_stream_request::Action;
-use databroker_proto::kuksa::val::v2::signal_id::Signal;
 use databroker_proto::kuksa::val::v2::value::TypedValue;
-use databroker_proto::kuksa::val::v2::{
-    open_provider_stream_response, OpenProviderStreamRequest, ProvideActuationRequest, SignalId,
-    Value,
-};
+use databroker_proto::kuksa::val::v2::Value;
 use kuksa_common::ClientTraitV2;
 use kuksa_val_v2::KuksaClientV2;
-use open_provider_stream_response::Action::BatchActuateStreamRequest;
 
 #[tokio::main]
 async fn main() {
@@ -111,48 +105,23 @@ async fn sample_publish_value(client: &mut KuksaClientV2) {
 }
 
 async fn sample_provide_actuation(client: &mut KuksaClientV2) {
-    let result = client.open_provider_stream(None).await;
+    let signal = "Vehicle.ADAS.ABS.IsEnabled".to_string();
+    let result = client
+        .provide_actuation(vec![signal.clone()], |path, value| async move {
+            // execute actuation
+            println!("Received actuation of {}: {:?}", path, value);
+            Ok(())
+        })
+        .await;
     match result {
-        Ok(mut stream) => {
-            let request = OpenProviderStreamRequest {
-                action: Some(Action::ProvideActuationRequest(ProvideActuationRequest {
-                    actuator_identifiers: vec![SignalId {
-                        signal: Some(Signal::Path("Vehicle.ADAS.ABS.IsEnabled".to_string())),
-                    }],
-                })),
-            };
-            match stream.sender.send(request).await {
-                Ok(_) => {
-                    println!("Successfully registered ProvideActuationRequest for Vehicle.ADAS.ABS.IsEnabled.");
-                }
-                Err(err) => {
-                    println!("Error: Could not send ProvideActuationRequest {:?}", err);
-                }
-            }
-
-            tokio::spawn(async move {
-                let result = stream.receiver_stream.message().await;
-                match result {
-                    Ok(option) => {
-                        let response = option.unwrap();
-                        if let Some(BatchActuateStreamRequest(batch_actuate_stream_request)) =
-                            response.action
-                        {
-                            let actuate_requests = batch_actuate_stream_request.actuate_requests;
-                            for actuate_request in actuate_requests {
-                                // execute actuate_requests
-                                println!("Received ActuateRequest: {:?}", actuate_request);
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        println!("Error: Could not receive response {:?}", err);
-                    }
-                }
-            });
+        Ok(_) => {
+            println!(
+                "Successfully registered as actuation provider for {}.",
+                signal
+            );
         }
         Err(err) => {
-            println!("Could not open provider stream: {}", err);
+            println!("Error: Could not provide actuation for {}: {}", signal, err);
         }
     }
 }
@@ -13,28 +13,24 @@
 
 use databroker_proto::sdv::databroker as proto;
 
-use prost_types::Timestamp;
-
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 
 use std::collections::HashMap;
 use std::env;
-use std::time::{Instant, SystemTime};
+use std::time::Instant;
 
 const DEFAULT_ITERATIONS: i32 = 1000;
 const DEFAULT_NTH_MESSAGE: i32 = 1;
 
 fn create_payload(value: &str, id: i32) -> proto::v1::StreamDatapointsRequest {
-    let ts = Timestamp::from(SystemTime::now());
     proto::v1::StreamDatapointsRequest {
         datapoints: HashMap::from([(
             id,
-            proto::v1::Datapoint {
-                timestamp: Some(ts),
-                value: Some(proto::v1::datapoint::Value::StringValue(value.to_string())),
-            },
+            proto::v1::Datapoint::now(proto::v1::datapoint::Value::StringValue(value.to_string())),
         )]),
+        sequence_number: 0,
+        ack: false,
     }
 }
 
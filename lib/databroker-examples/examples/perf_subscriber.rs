@@ -37,6 +37,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             let args = tonic::Request::new(proto::v1::SubscribeRequest {
                 query: String::from("SELECT Vehicle.ADAS.ABS.Error"),
+                changes_only: false,
             });
 
             let mut now = Instant::now();
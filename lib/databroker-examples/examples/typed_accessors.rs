@@ -0,0 +1,54 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+// Signals accessed through the accessors generated by kuksa-codegen (see
+// build.rs), instead of by path
+include!(concat!(env!("OUT_DIR"), "/vss.rs"));
+
+use std::time::Duration;
+
+use kuksa_val_v2::KuksaClientV2;
+
+#[tokio::main]
+async fn main() {
+    let host = if cfg!(target_os = "macos") {
+        "http://localhost:55556"
+    } else {
+        "http://localhost:55555"
+    };
+    let mut client = KuksaClientV2::from_host(host);
+
+    match vehicle::Speed::get(&mut client).await {
+        Ok(Some(speed)) => println!("Vehicle.Speed: {speed} km/h"),
+        Ok(None) => println!("Vehicle.Speed not set"),
+        Err(err) => println!("Error: Could not retrieve Vehicle.Speed: {err}"),
+    }
+
+    if let Err(err) = vehicle::Speed::publish(&mut client, 42.0).await {
+        println!("Error: Could not publish Vehicle.Speed: {err}");
+    }
+
+    // Holds right away, as the speed was just published
+    match vehicle::Speed::wait_for(&mut client, |speed| *speed > 40.0, Duration::from_secs(1)).await
+    {
+        Ok(speed) => println!("Vehicle.Speed above 40 km/h: {speed} km/h"),
+        Err(err) => println!("Error: Vehicle.Speed didn't exceed 40 km/h: {err}"),
+    }
+
+    // The temperature is an int8 actuator, i.e. 21.5 wouldn't compile
+    if let Err(err) =
+        vehicle::cabin::hvac::station::row1::driver::Temperature::set(&mut client, 21).await
+    {
+        println!("Error: Could not actuate the driver side temperature: {err}");
+    }
+}
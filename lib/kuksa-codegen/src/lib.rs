@@ -0,0 +1,359 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Generates strongly-typed signal accessors from a VSS JSON file, so that
+//! applications don't deal with paths as strings and get data types checked
+//! at compile time. Meant to be run from a build script:
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("vss.rs");
+//!     kuksa_codegen::generate_file("vss.json", out).unwrap();
+//!     println!("cargo:rerun-if-changed=vss.json");
+//! }
+//! ```
+//!
+//! ```ignore
+//! // main.rs
+//! include!(concat!(env!("OUT_DIR"), "/vss.rs"));
+//!
+//! vehicle::cabin::hvac::station::row1::driver::Temperature::set(&mut client, 21).await?;
+//! ```
+//!
+//! Every branch becomes a module named in snake case, every signal a unit
+//! struct implementing `kuksa_val_v2::typed::Signal`, with `get`, `wait_for`
+//! and `publish`, and `set` for actuators. Signals of data types without a Rust
+//! counterpart, e.g. structs, are left out.
+
+use std::fmt::{self, Write};
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+#[derive(Debug)]
+pub enum Error {
+    ParseError(String),
+    IoError(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(f, "failed to parse VSS: {msg}"),
+            Error::IoError(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "do", "dyn", "else", "enum", "extern",
+    "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait",
+    "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while",
+    "yield",
+];
+
+/// Module name of a branch, e.g. `driver_side` for `DriverSide` and `hvac`
+/// for `HVAC`.
+fn module_name(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut module = String::with_capacity(name.len() + 4);
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_lower = chars
+                .get(i + 1)
+                .is_some_and(|next| next.is_ascii_lowercase());
+            if previous.is_ascii_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_ascii_uppercase() && next_lower)
+            {
+                module.push('_');
+            }
+        }
+        module.push(c.to_ascii_lowercase());
+    }
+    identifier(module)
+}
+
+fn identifier(name: String) -> String {
+    match name.as_str() {
+        "self" | "super" | "crate" | "Self" => format!("{name}_"),
+        _ if KEYWORDS.contains(&name.as_str()) => format!("r#{name}"),
+        _ => name,
+    }
+}
+
+/// Rust type of a VSS data type, None if there is none.
+fn rust_type(datatype: &str) -> Option<String> {
+    let (element, array) = match datatype.strip_suffix("[]") {
+        Some(element) => (element, true),
+        None => (datatype, false),
+    };
+    let element = match element {
+        "boolean" => "bool",
+        "string" => "String",
+        "int8" => "i8",
+        "int16" => "i16",
+        "int32" => "i32",
+        "int64" => "i64",
+        "uint8" => "u8",
+        "uint16" => "u16",
+        "uint32" => "u32",
+        "uint64" => "u64",
+        "float" => "f32",
+        "double" => "f64",
+        _ => return None,
+    };
+    Some(if array {
+        format!("Vec<{element}>")
+    } else {
+        element.to_owned()
+    })
+}
+
+struct Generator {
+    out: String,
+    depth: usize,
+}
+
+impl Generator {
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            for _ in 0..self.depth {
+                self.out.push_str("    ");
+            }
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+    }
+
+    fn doc(&mut self, node: &Map<String, Value>) {
+        let mut doc = node
+            .get("description")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        if let Some(unit) = node.get("unit").and_then(Value::as_str) {
+            let _ = write!(doc, "\n\nUnit: {unit}");
+        }
+        if !doc.is_empty() {
+            self.line(&format!("#[doc = {doc:?}]"));
+        }
+    }
+
+    fn branch(&mut self, path: &str, name: &str, node: &Map<String, Value>, root: bool) {
+        self.doc(node);
+        if root {
+            self.line("#[allow(dead_code, clippy::upper_case_acronyms)]");
+        }
+        self.line(&format!("pub mod {} {{", module_name(name)));
+        self.depth += 1;
+        if let Some(children) = node.get("children").and_then(Value::as_object) {
+            for (child_name, child) in children {
+                let Some(child) = child.as_object() else {
+                    continue;
+                };
+                let child_path = format!("{path}.{child_name}");
+                match child.get("type").and_then(Value::as_str) {
+                    Some("branch") => self.branch(&child_path, child_name, child, false),
+                    Some(kind @ ("sensor" | "actuator" | "attribute")) => {
+                        self.signal(&child_path, child_name, child, kind == "actuator")
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn signal(&mut self, path: &str, name: &str, node: &Map<String, Value>, actuator: bool) {
+        let Some(value) = node
+            .get("datatype")
+            .and_then(Value::as_str)
+            .and_then(rust_type)
+        else {
+            return;
+        };
+        let name = identifier(name.to_owned());
+        let typed = "::kuksa_val_v2::typed";
+        let client = "client: &mut ::kuksa_val_v2::KuksaClientV2";
+        let error = "::kuksa_val_v2::ClientError";
+
+        self.doc(node);
+        self.line(&format!("pub struct {name};"));
+        self.line("");
+        self.line(&format!("impl {typed}::Signal for {name} {{"));
+        self.line(&format!("    const PATH: &'static str = {path:?};"));
+        self.line(&format!("    type Value = {value};"));
+        self.line("}");
+        self.line("");
+        if actuator {
+            self.line(&format!("impl {typed}::Actuator for {name} {{}}"));
+            self.line("");
+        }
+        self.line(&format!("impl {name} {{"));
+        self.depth += 1;
+        self.line("/// Latest value, None if there is none.");
+        self.line(&format!(
+            "pub async fn get({client}) -> Result<Option<{value}>, {error}> {{"
+        ));
+        self.line(&format!("    {typed}::get::<Self>(client).await"));
+        self.line("}");
+        self.line("");
+        self.line("/// Wait until `predicate` holds for the value, or fail after `timeout`.");
+        self.line("pub async fn wait_for(");
+        self.line(&format!("    {client},"));
+        self.line(&format!("    predicate: impl FnMut(&{value}) -> bool,"));
+        self.line("    timeout: ::std::time::Duration,");
+        self.line(&format!(") -> Result<{value}, {error}> {{"));
+        self.line(&format!(
+            "    {typed}::wait_for::<Self>(client, predicate, timeout).await"
+        ));
+        self.line("}");
+        self.line("");
+        self.line("/// Publish the current value, as provider of the signal.");
+        self.line(&format!(
+            "pub async fn publish({client}, value: {value}) -> Result<(), {error}> {{"
+        ));
+        self.line(&format!(
+            "    {typed}::publish::<Self>(client, value).await"
+        ));
+        self.line("}");
+        if actuator {
+            self.line("");
+            self.line("/// Request the actuator to take `value`.");
+            self.line(&format!(
+                "pub async fn set({client}, value: {value}) -> Result<(), {error}> {{"
+            ));
+            self.line(&format!(
+                "    {typed}::actuate::<Self>(client, value).await"
+            ));
+            self.line("}");
+        }
+        self.depth -= 1;
+        self.line("}");
+        self.line("");
+    }
+}
+
+/// Rust source with the accessors of the signals in `vss`.
+pub fn generate_from_str(vss: &str) -> Result<String, Error> {
+    let vss: Map<String, Value> =
+        serde_json::from_str(vss).map_err(|err| Error::ParseError(err.to_string()))?;
+    let mut generator = Generator {
+        out: String::from("// Generated by kuksa-codegen, do not edit\n\n"),
+        depth: 0,
+    };
+    for (name, node) in &vss {
+        let node = node
+            .as_object()
+            .filter(|node| node.get("type").and_then(Value::as_str) == Some("branch"))
+            .ok_or_else(|| Error::ParseError(format!("{name} is not a branch")))?;
+        generator.branch(name, name, node, true);
+    }
+    Ok(generator.out)
+}
+
+/// Write the accessors of the signals in the VSS file `vss` to `out`.
+pub fn generate_file(vss: impl AsRef<Path>, out: impl AsRef<Path>) -> Result<(), Error> {
+    let vss = vss.as_ref();
+    let data = std::fs::read_to_string(vss)
+        .map_err(|err| Error::IoError(format!("failed to read {}: {err}", vss.display())))?;
+    let code = generate_from_str(&data)?;
+    let out = out.as_ref();
+    std::fs::write(out, code)
+        .map_err(|err| Error::IoError(format!("failed to write {}: {err}", out.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_name() {
+        for (name, module) in [
+            ("Vehicle", "vehicle"),
+            ("DriverSide", "driver_side"),
+            ("HVAC", "hvac"),
+            ("OBD", "obd"),
+            ("ADASStation", "adas_station"),
+            ("Row1", "row1"),
+            ("Axle2Wheel", "axle2_wheel"),
+            ("Type", "r#type"),
+        ] {
+            assert_eq!(module_name(name), module);
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let code = generate_from_str(
+            r#"{
+                "Vehicle": {
+                    "type": "branch",
+                    "description": "High-level vehicle data.",
+                    "children": {
+                        "Speed": {
+                            "type": "sensor",
+                            "datatype": "float",
+                            "unit": "km/h",
+                            "description": "Vehicle speed."
+                        },
+                        "Cabin": {
+                            "type": "branch",
+                            "children": {
+                                "HVAC": {
+                                    "type": "branch",
+                                    "children": {
+                                        "Temperature": {
+                                            "type": "actuator",
+                                            "datatype": "int8"
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        "Trailer": {
+                            "type": "sensor",
+                            "datatype": "Types.Trailer"
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(code.contains("pub mod vehicle {"));
+        assert!(code.contains("#[doc = \"Vehicle speed.\\n\\nUnit: km/h\"]"));
+        assert!(code.contains("impl ::kuksa_val_v2::typed::Signal for Speed {"));
+        assert!(code.contains("const PATH: &'static str = \"Vehicle.Speed\";"));
+        assert!(code.contains("type Value = f32;"));
+        assert!(code.contains("predicate: impl FnMut(&f32) -> bool,"));
+        assert!(code.contains("        pub mod hvac {"));
+        assert!(code.contains("impl ::kuksa_val_v2::typed::Actuator for Temperature {}"));
+        assert!(code
+            .contains("pub async fn set(client: &mut ::kuksa_val_v2::KuksaClientV2, value: i8)"));
+        // Sensors can't be set
+        assert_eq!(code.matches("pub async fn set(").count(), 1);
+        // No Rust type for structs
+        assert!(!code.contains("Trailer"));
+
+        assert!(generate_from_str(r#"{"Vehicle": {"type": "sensor"}}"#).is_err());
+        assert!(generate_from_str("[]").is_err());
+    }
+}
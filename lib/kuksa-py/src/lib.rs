@@ -0,0 +1,385 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Python bindings of the `kuksa.val.v2` client.
+//!
+//! The client blocks while waiting for the databroker and releases the GIL
+//! meanwhile, so it can be used from plain Python test code as well as from
+//! threads:
+//!
+//! ```python
+//! import kuksa_py
+//!
+//! client = kuksa_py.Client("http://localhost:55555")
+//! client.publish_value("Vehicle.Speed", 42.0)
+//! print(client.get_value("Vehicle.Speed"))
+//! for updates in client.subscribe(["Vehicle.Speed"]):
+//!     print(updates["Vehicle.Speed"])
+//! ```
+//!
+//! Values are converted to the data type of the signal, which is looked up
+//! once per signal.
+
+// Triggered by the error conversions generated for #[pymethods]
+#![allow(clippy::useless_conversion)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use databroker_proto::kuksa::val::v2::{
+    value::TypedValue, BoolArray, DataType, DoubleArray, FloatArray, Int32Array, Int64Array,
+    StringArray, SubscribeResponse, Uint32Array, Uint64Array, Value,
+};
+use kuksa_val_v2::{ClientError, ClientTraitV2, KuksaClientV2};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use tokio::runtime::Runtime;
+
+create_exception!(kuksa_py, KuksaError, PyException);
+
+fn to_py_err(err: ClientError) -> PyErr {
+    match err {
+        ClientError::Status(status) => {
+            KuksaError::new_err(format!("{}: {}", status.code(), status.message()))
+        }
+        err => KuksaError::new_err(err.to_string()),
+    }
+}
+
+/// Blocking client of a databroker.
+#[pyclass(module = "kuksa_py")]
+struct Client {
+    runtime: Arc<Runtime>,
+    client: KuksaClientV2,
+    data_types: HashMap<String, DataType>,
+}
+
+#[pymethods]
+impl Client {
+    #[new]
+    #[pyo3(signature = (uri, token=None))]
+    fn new(uri: &str, token: Option<&str>) -> PyResult<Self> {
+        let uri = kuksa_common::to_uri(uri).map_err(KuksaError::new_err)?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let mut client = KuksaClientV2::new(uri);
+        if let Some(token) = token {
+            client
+                .basic_client
+                .set_access_token(token)
+                .map_err(|err| KuksaError::new_err(err.to_string()))?;
+        }
+        Ok(Client {
+            runtime: Arc::new(runtime),
+            client,
+            data_types: HashMap::new(),
+        })
+    }
+
+    fn set_access_token(&mut self, token: &str) -> PyResult<()> {
+        self.client
+            .basic_client
+            .set_access_token(token)
+            .map_err(|err| KuksaError::new_err(err.to_string()))
+    }
+
+    /// Use the token stored in the file at `path`, re-reading it when it
+    /// changes.
+    fn set_access_token_file(&mut self, path: &str) -> PyResult<()> {
+        self.client
+            .basic_client
+            .set_access_token_file(path)
+            .map_err(|err| KuksaError::new_err(err.to_string()))
+    }
+
+    fn is_connected(&self) -> bool {
+        self.client.basic_client.is_connected()
+    }
+
+    /// The current value of a signal, `None` if it has none.
+    fn get_value(&mut self, py: Python<'_>, path: String) -> PyResult<PyObject> {
+        let Self {
+            runtime, client, ..
+        } = self;
+        let datapoint =
+            py.allow_threads(|| runtime.block_on(client.get_value(path)).map_err(to_py_err))?;
+        Ok(to_python(
+            py,
+            datapoint.and_then(|datapoint| datapoint.value),
+        ))
+    }
+
+    /// The current values of signals, in the order of `paths`.
+    fn get_values(&mut self, py: Python<'_>, paths: Vec<String>) -> PyResult<Vec<PyObject>> {
+        let Self {
+            runtime, client, ..
+        } = self;
+        let datapoints = py.allow_threads(|| {
+            runtime
+                .block_on(client.get_values(paths))
+                .map_err(to_py_err)
+        })?;
+        Ok(datapoints
+            .into_iter()
+            .map(|datapoint| to_python(py, datapoint.value))
+            .collect())
+    }
+
+    fn publish_value(
+        &mut self,
+        py: Python<'_>,
+        path: String,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let value = self.value_of(py, &path, value)?;
+        let Self {
+            runtime, client, ..
+        } = self;
+        py.allow_threads(|| {
+            runtime
+                .block_on(client.publish_value(path, value))
+                .map_err(to_py_err)
+        })
+    }
+
+    fn actuate(&mut self, py: Python<'_>, path: String, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let value = self.value_of(py, &path, value)?;
+        let Self {
+            runtime, client, ..
+        } = self;
+        py.allow_threads(|| {
+            runtime
+                .block_on(client.actuate(path, value))
+                .map_err(to_py_err)
+        })
+    }
+
+    /// Actuate several actuators at once, given as a dict of path to value.
+    fn batch_actuate(
+        &mut self,
+        py: Python<'_>,
+        values: HashMap<String, Bound<'_, PyAny>>,
+    ) -> PyResult<()> {
+        let mut actuations = HashMap::new();
+        for (path, value) in values {
+            let value = self.value_of(py, &path, &value)?;
+            actuations.insert(path, value);
+        }
+        let Self {
+            runtime, client, ..
+        } = self;
+        py.allow_threads(|| {
+            runtime
+                .block_on(client.batch_actuate(actuations))
+                .map_err(to_py_err)
+        })
+    }
+
+    /// Subscribe to signals. Iterating the subscription yields a dict of
+    /// path to value per update, starting with the current values.
+    #[pyo3(signature = (paths, buffer_size=None))]
+    fn subscribe(
+        &mut self,
+        py: Python<'_>,
+        paths: Vec<String>,
+        buffer_size: Option<u32>,
+    ) -> PyResult<Subscription> {
+        let Self {
+            runtime, client, ..
+        } = self;
+        let stream = py.allow_threads(|| {
+            runtime
+                .block_on(client.subscribe(paths, buffer_size))
+                .map_err(to_py_err)
+        })?;
+        Ok(Subscription {
+            runtime: runtime.clone(),
+            stream,
+        })
+    }
+}
+
+impl Client {
+    fn data_type(&mut self, py: Python<'_>, path: &str) -> PyResult<DataType> {
+        if let Some(data_type) = self.data_types.get(path) {
+            return Ok(*data_type);
+        }
+        let Self {
+            runtime, client, ..
+        } = self;
+        let metadata = py.allow_threads(|| {
+            runtime
+                .block_on(client.list_metadata((path.to_owned(), String::new())))
+                .map_err(to_py_err)
+        })?;
+        let data_type = metadata
+            .iter()
+            .find(|metadata| metadata.path == path)
+            .map(|metadata| metadata.data_type())
+            .ok_or_else(|| KuksaError::new_err(format!("no such signal: {path}")))?;
+        self.data_types.insert(path.to_owned(), data_type);
+        Ok(data_type)
+    }
+
+    fn value_of(
+        &mut self,
+        py: Python<'_>,
+        path: &str,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<Value> {
+        let data_type = self.data_type(py, path)?;
+        from_python(value, data_type)
+    }
+}
+
+/// Updates of a subscription.
+#[pyclass(module = "kuksa_py")]
+struct Subscription {
+    runtime: Arc<Runtime>,
+    stream: tonic::Streaming<SubscribeResponse>,
+}
+
+#[pymethods]
+impl Subscription {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        let Self { runtime, stream } = self;
+        let response = py.allow_threads(|| {
+            runtime
+                .block_on(stream.message())
+                .map_err(|status| to_py_err(ClientError::Status(status)))
+        })?;
+        let Some(response) = response else {
+            // The databroker ended the subscription
+            return Ok(None);
+        };
+        let updates = PyDict::new_bound(py);
+        for (path, datapoint) in response.entries {
+            updates.set_item(path, to_python(py, datapoint.value))?;
+        }
+        Ok(Some(updates.into()))
+    }
+}
+
+fn to_python(py: Python<'_>, value: Option<Value>) -> PyObject {
+    match value.and_then(|value| value.typed_value) {
+        None => py.None(),
+        Some(TypedValue::String(value)) => value.into_py(py),
+        Some(TypedValue::Bool(value)) => value.into_py(py),
+        Some(TypedValue::Int32(value)) => value.into_py(py),
+        Some(TypedValue::Int64(value)) => value.into_py(py),
+        Some(TypedValue::Uint32(value)) => value.into_py(py),
+        Some(TypedValue::Uint64(value)) => value.into_py(py),
+        Some(TypedValue::Float(value)) => value.into_py(py),
+        Some(TypedValue::Double(value)) => value.into_py(py),
+        Some(TypedValue::StringArray(array)) => array.values.into_py(py),
+        Some(TypedValue::BoolArray(array)) => array.values.into_py(py),
+        Some(TypedValue::Int32Array(array)) => array.values.into_py(py),
+        Some(TypedValue::Int64Array(array)) => array.values.into_py(py),
+        Some(TypedValue::Uint32Array(array)) => array.values.into_py(py),
+        Some(TypedValue::Uint64Array(array)) => array.values.into_py(py),
+        Some(TypedValue::FloatArray(array)) => array.values.into_py(py),
+        Some(TypedValue::DoubleArray(array)) => array.values.into_py(py),
+    }
+}
+
+fn from_python(value: &Bound<'_, PyAny>, data_type: DataType) -> PyResult<Value> {
+    let typed_value = match data_type {
+        DataType::String => TypedValue::String(value.extract()?),
+        DataType::Boolean => TypedValue::Bool(value.extract()?),
+        DataType::Int8 => TypedValue::Int32(value.extract::<i8>()?.into()),
+        DataType::Int16 => TypedValue::Int32(value.extract::<i16>()?.into()),
+        DataType::Int32 => TypedValue::Int32(value.extract()?),
+        DataType::Int64 => TypedValue::Int64(value.extract()?),
+        DataType::Uint8 => TypedValue::Uint32(value.extract::<u8>()?.into()),
+        DataType::Uint16 => TypedValue::Uint32(value.extract::<u16>()?.into()),
+        DataType::Uint32 => TypedValue::Uint32(value.extract()?),
+        DataType::Uint64 => TypedValue::Uint64(value.extract()?),
+        DataType::Float => TypedValue::Float(value.extract()?),
+        DataType::Double => TypedValue::Double(value.extract()?),
+        DataType::StringArray => TypedValue::StringArray(StringArray {
+            values: value.extract()?,
+        }),
+        DataType::BooleanArray => TypedValue::BoolArray(BoolArray {
+            values: value.extract()?,
+        }),
+        DataType::Int8Array => TypedValue::Int32Array(Int32Array {
+            values: extract_array::<i8, _>(value)?,
+        }),
+        DataType::Int16Array => TypedValue::Int32Array(Int32Array {
+            values: extract_array::<i16, _>(value)?,
+        }),
+        DataType::Int32Array => TypedValue::Int32Array(Int32Array {
+            values: value.extract()?,
+        }),
+        DataType::Int64Array => TypedValue::Int64Array(Int64Array {
+            values: value.extract()?,
+        }),
+        DataType::Uint8Array => TypedValue::Uint32Array(Uint32Array {
+            values: extract_array::<u8, _>(value)?,
+        }),
+        DataType::Uint16Array => TypedValue::Uint32Array(Uint32Array {
+            values: extract_array::<u16, _>(value)?,
+        }),
+        DataType::Uint32Array => TypedValue::Uint32Array(Uint32Array {
+            values: value.extract()?,
+        }),
+        DataType::Uint64Array => TypedValue::Uint64Array(Uint64Array {
+            values: value.extract()?,
+        }),
+        DataType::FloatArray => TypedValue::FloatArray(FloatArray {
+            values: value.extract()?,
+        }),
+        DataType::DoubleArray => TypedValue::DoubleArray(DoubleArray {
+            values: value.extract()?,
+        }),
+        DataType::Unspecified | DataType::Timestamp | DataType::TimestampArray => {
+            return Err(PyTypeError::new_err(format!(
+                "values of type {} are not supported",
+                data_type.as_str_name()
+            )))
+        }
+    };
+    Ok(Value {
+        typed_value: Some(typed_value),
+    })
+}
+
+/// Extract a list of narrow integers, range checked, as the wider type they
+/// are sent as.
+fn extract_array<'py, T, U>(value: &Bound<'py, PyAny>) -> PyResult<Vec<U>>
+where
+    T: FromPyObject<'py>,
+    U: From<T>,
+{
+    Ok(value
+        .extract::<Vec<T>>()?
+        .into_iter()
+        .map(U::from)
+        .collect())
+}
+
+#[pymodule]
+fn kuksa_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Client>()?;
+    m.add_class::<Subscription>()?;
+    m.add("KuksaError", m.py().get_type_bound::<KuksaError>())?;
+    Ok(())
+}
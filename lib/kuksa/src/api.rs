@@ -0,0 +1,146 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use kuksa_common::types::{PathTypeV1, SensorUpdateTypeV1, UpdateActuationTypeV1};
+use kuksa_common::ClientTraitV1;
+use tokio_stream::Stream;
+use tokio_util::sync::WaitForCancellationFutureOwned;
+use tonic::async_trait;
+
+use crate::{proto, ClientError, DataEntry, KuksaClient, Subscription};
+
+pub type SubscribeStream =
+    Pin<Box<dyn Stream<Item = Result<proto::v1::SubscribeResponse, tonic::Status>> + Send>>;
+
+/// High level client operations.
+///
+/// Applications should depend on this trait rather than on [`KuksaClient`]
+/// directly, so that [`MockClient`](crate::mock::MockClient) can be used in
+/// unit tests.
+#[cfg_attr(all(feature = "grpc-web", target_arch = "wasm32"), async_trait(?Send))]
+#[cfg_attr(not(all(feature = "grpc-web", target_arch = "wasm32")), async_trait)]
+pub trait KuksaClientApi: Send {
+    async fn get_current_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<Vec<DataEntry>, ClientError>;
+    async fn get_target_values(&mut self, paths: PathTypeV1)
+        -> Result<Vec<DataEntry>, ClientError>;
+    async fn get_metadata(&mut self, paths: PathTypeV1) -> Result<Vec<DataEntry>, ClientError>;
+    async fn set_current_values(
+        &mut self,
+        datapoints: SensorUpdateTypeV1,
+    ) -> Result<(), ClientError>;
+    async fn set_target_values(
+        &mut self,
+        datapoints: UpdateActuationTypeV1,
+    ) -> Result<(), ClientError>;
+    /// Subscribe to the entries of `subscription`, see
+    /// [`Subscription::builder`].
+    async fn subscribe_with(
+        &mut self,
+        subscription: Subscription,
+    ) -> Result<SubscribeStream, ClientError>;
+    async fn subscribe_current_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<SubscribeStream, ClientError> {
+        self.subscribe_with(Subscription::current_values(paths))
+            .await
+    }
+    async fn subscribe_target_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<SubscribeStream, ClientError> {
+        self.subscribe_with(Subscription::target_values(paths))
+            .await
+    }
+    /// Drop the connection after it was lost, so that the next call
+    /// connects again rather than waiting for the old one to recover.
+    fn reset_connection(&mut self) {}
+}
+
+#[cfg_attr(all(feature = "grpc-web", target_arch = "wasm32"), async_trait(?Send))]
+#[cfg_attr(not(all(feature = "grpc-web", target_arch = "wasm32")), async_trait)]
+impl KuksaClientApi for KuksaClient {
+    async fn get_current_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<Vec<DataEntry>, ClientError> {
+        ClientTraitV1::get_current_values(self, paths).await
+    }
+
+    async fn get_target_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<Vec<DataEntry>, ClientError> {
+        ClientTraitV1::get_target_values(self, paths).await
+    }
+
+    async fn get_metadata(&mut self, paths: PathTypeV1) -> Result<Vec<DataEntry>, ClientError> {
+        ClientTraitV1::get_metadata(self, paths).await
+    }
+
+    async fn set_current_values(
+        &mut self,
+        datapoints: SensorUpdateTypeV1,
+    ) -> Result<(), ClientError> {
+        ClientTraitV1::set_current_values(self, datapoints).await
+    }
+
+    async fn set_target_values(
+        &mut self,
+        datapoints: UpdateActuationTypeV1,
+    ) -> Result<(), ClientError> {
+        ClientTraitV1::set_target_values(self, datapoints).await
+    }
+
+    async fn subscribe_with(
+        &mut self,
+        subscription: Subscription,
+    ) -> Result<SubscribeStream, ClientError> {
+        let stream = KuksaClient::subscribe_with(self, subscription).await?;
+        match self.basic_client.cancellation_token() {
+            Some(token) => Ok(Box::pin(Cancellable {
+                stream,
+                cancelled: Box::pin(token.clone().cancelled_owned()),
+            })),
+            None => Ok(Box::pin(stream)),
+        }
+    }
+
+    fn reset_connection(&mut self) {
+        self.basic_client.disconnect();
+    }
+}
+
+/// Ends `stream` once the cancellation token of the client is cancelled.
+struct Cancellable<S> {
+    stream: S,
+    cancelled: Pin<Box<WaitForCancellationFutureOwned>>,
+}
+
+impl<S: Stream + Unpin> Stream for Cancellable<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        Pin::new(&mut self.stream).poll_next(cx)
+    }
+}
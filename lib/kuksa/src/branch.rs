@@ -0,0 +1,537 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Map the signals of a VSS branch to and from user defined structs or
+//! nested JSON.
+//!
+//! Every leaf below the branch is placed into a nested object by its path
+//! suffix, so `Vehicle.Cabin.HVAC.Station.Row1.Driver.FanSpeed` read with
+//! the branch `Vehicle.Cabin.HVAC` ends up in `Station.Row1.Driver.FanSpeed`.
+//! VSS names are PascalCase, so structs usually want
+//! `#[serde(rename_all = "PascalCase")]`. Signals without a value are
+//! `null` and map to `Option::None`.
+//!
+//! ```no_run
+//! # async fn example(client: &mut kuksa::KuksaClient) -> Result<(), kuksa::branch::Error> {
+//! #[derive(serde::Deserialize)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Trunk {
+//!     is_open: Option<bool>,
+//!     is_locked: Option<bool>,
+//! }
+//!
+//! let trunk: Trunk = client.get_branch_as("Vehicle.Body.Trunk.Rear").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+// ClientError embeds a tonic::Status, which is large by design
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::api::KuksaClientApi;
+use crate::{proto, ClientError, DataEntry};
+
+use proto::v1::{datapoint::Value, DataType};
+
+#[derive(Debug)]
+pub enum Error {
+    Client(ClientError),
+    /// The branch could not be converted from or to the requested type.
+    Serde(serde_json::Error),
+    /// A field of the struct has no corresponding signal.
+    UnknownPath(String),
+    /// A field value doesn't fit the data type of its signal.
+    InvalidValue {
+        path: String,
+        data_type: DataType,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Client(err) => write!(f, "{err}"),
+            Error::Serde(err) => write!(f, "{err}"),
+            Error::UnknownPath(path) => write!(f, "no signal found for {path}"),
+            Error::InvalidValue { path, data_type } => {
+                write!(
+                    f,
+                    "value for {path} is not a valid {}",
+                    data_type.as_str_name()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        Error::Client(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serde(err)
+    }
+}
+
+/// Read the current values below `branch` into `T`.
+pub async fn get_branch_as<T: DeserializeOwned>(
+    client: &mut impl KuksaClientApi,
+    branch: &str,
+) -> Result<T, Error> {
+    Ok(serde_json::from_value(
+        get_subtree_json(client, branch).await?,
+    )?)
+}
+
+/// Publish the fields of `value` as current values of the signals below
+/// `branch`. Fields that serialize to `null` are left untouched.
+pub async fn set_branch_from<T: Serialize>(
+    client: &mut impl KuksaClientApi,
+    branch: &str,
+    value: &T,
+) -> Result<(), Error> {
+    set_subtree_json(client, branch, serde_json::to_value(value)?).await
+}
+
+/// Like [`set_branch_from`], but sets the actuator targets.
+pub async fn set_branch_target_from<T: Serialize>(
+    client: &mut impl KuksaClientApi,
+    branch: &str,
+    value: &T,
+) -> Result<(), Error> {
+    set_subtree_target_json(client, branch, serde_json::to_value(value)?).await
+}
+
+/// The current values below `branch` as nested JSON object, e.g.
+/// `{"Row1": {"DriverSide": {"Position": 20}}}` for
+/// `Vehicle.Cabin.Seat`. Signals without a value are `null`.
+pub async fn get_subtree_json(
+    client: &mut impl KuksaClientApi,
+    branch: &str,
+) -> Result<JsonValue, Error> {
+    let entries = client.get_current_values(vec![branch.to_owned()]).await?;
+    Ok(to_nested_json(branch, &entries))
+}
+
+/// Publish current values given in the shape returned by
+/// [`get_subtree_json`]. `null` leaves are left untouched.
+pub async fn set_subtree_json(
+    client: &mut impl KuksaClientApi,
+    branch: &str,
+    json: JsonValue,
+) -> Result<(), Error> {
+    let datapoints = to_datapoints(client, branch, json).await?;
+    Ok(client.set_current_values(datapoints).await?)
+}
+
+/// Like [`set_subtree_json`], but sets the actuator targets.
+pub async fn set_subtree_target_json(
+    client: &mut impl KuksaClientApi,
+    branch: &str,
+    json: JsonValue,
+) -> Result<(), Error> {
+    let datapoints = to_datapoints(client, branch, json).await?;
+    Ok(client.set_target_values(datapoints).await?)
+}
+
+async fn to_datapoints(
+    client: &mut impl KuksaClientApi,
+    branch: &str,
+    json: JsonValue,
+) -> Result<HashMap<String, proto::v1::Datapoint>, Error> {
+    let leaves = flatten(branch, json);
+    if leaves.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let data_types: HashMap<String, DataType> = client
+        .get_metadata(vec![branch.to_owned()])
+        .await?
+        .into_iter()
+        .filter_map(|entry| {
+            let data_type = DataType::try_from(entry.metadata?.data_type).ok()?;
+            Some((entry.path, data_type))
+        })
+        .collect();
+
+    leaves
+        .into_iter()
+        .map(|(path, json)| {
+            let data_type = *data_types
+                .get(&path)
+                .ok_or_else(|| Error::UnknownPath(path.clone()))?;
+            let value = from_json(&json, data_type).ok_or_else(|| Error::InvalidValue {
+                path: path.clone(),
+                data_type,
+            })?;
+            Ok((
+                path,
+                proto::v1::Datapoint {
+                    timestamp: None,
+                    value: Some(value),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Nest the current values of `entries` by their path relative to `branch`.
+fn to_nested_json(branch: &str, entries: &[DataEntry]) -> JsonValue {
+    let mut root = JsonValue::Object(Map::new());
+    for entry in entries {
+        let value = entry
+            .value
+            .as_ref()
+            .and_then(|datapoint| datapoint.value.as_ref())
+            .map(to_json)
+            .unwrap_or(JsonValue::Null);
+
+        let relative = match entry.path.strip_prefix(branch) {
+            Some("") => {
+                // The branch is a signal itself
+                return value;
+            }
+            Some(relative) if relative.starts_with('.') => &relative[1..],
+            _ => entry.path.as_str(),
+        };
+
+        let mut node = &mut root;
+        let mut names = relative.split('.').peekable();
+        while let Some(name) = names.next() {
+            let JsonValue::Object(object) = node else {
+                break;
+            };
+            if names.peek().is_none() {
+                object.insert(name.to_owned(), value);
+                break;
+            }
+            node = object
+                .entry(name.to_owned())
+                .or_insert_with(|| JsonValue::Object(Map::new()));
+        }
+    }
+    root
+}
+
+/// The inverse of [`to_nested_json`], skipping `null` leaves.
+fn flatten(branch: &str, json: JsonValue) -> Vec<(String, JsonValue)> {
+    let mut leaves = Vec::new();
+    flatten_into(branch.to_owned(), json, &mut leaves);
+    leaves
+}
+
+fn flatten_into(path: String, json: JsonValue, leaves: &mut Vec<(String, JsonValue)>) {
+    match json {
+        JsonValue::Object(object) => {
+            for (name, value) in object {
+                flatten_into(format!("{path}.{name}"), value, leaves);
+            }
+        }
+        JsonValue::Null => {}
+        value => leaves.push((path, value)),
+    }
+}
+
+/// The JSON representation of `value`, as used by [`get_subtree_json`].
+pub fn to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::String(value) => JsonValue::String(value.clone()),
+        Value::Bool(value) => JsonValue::Bool(*value),
+        Value::Int32(value) => JsonValue::from(*value),
+        Value::Int64(value) => JsonValue::from(*value),
+        Value::Uint32(value) => JsonValue::from(*value),
+        Value::Uint64(value) => JsonValue::from(*value),
+        Value::Float(value) => float_to_json(*value),
+        Value::Double(value) => Number::from_f64(*value)
+            .map(JsonValue::Number)
+            .unwrap_or(JsonValue::Null),
+        Value::StringArray(array) => array.values.iter().cloned().map(JsonValue::from).collect(),
+        Value::BoolArray(array) => array.values.iter().copied().map(JsonValue::from).collect(),
+        Value::Int32Array(array) => array.values.iter().copied().map(JsonValue::from).collect(),
+        Value::Int64Array(array) => array.values.iter().copied().map(JsonValue::from).collect(),
+        Value::Uint32Array(array) => array.values.iter().copied().map(JsonValue::from).collect(),
+        Value::Uint64Array(array) => array.values.iter().copied().map(JsonValue::from).collect(),
+        Value::FloatArray(array) => array.values.iter().copied().map(float_to_json).collect(),
+        Value::DoubleArray(array) => array
+            .values
+            .iter()
+            .map(|value| {
+                Number::from_f64(*value)
+                    .map(JsonValue::Number)
+                    .unwrap_or(JsonValue::Null)
+            })
+            .collect(),
+    }
+}
+
+/// Go through the shortest decimal representation, so that e.g. 0.1f32
+/// becomes 0.1 rather than 0.10000000149011612.
+fn float_to_json(value: f32) -> JsonValue {
+    value
+        .to_string()
+        .parse::<f64>()
+        .ok()
+        .and_then(Number::from_f64)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
+fn from_json(json: &JsonValue, data_type: DataType) -> Option<Value> {
+    fn array<T>(json: &JsonValue, element: impl Fn(&JsonValue) -> Option<T>) -> Option<Vec<T>> {
+        json.as_array()?.iter().map(element).collect()
+    }
+    let value = match data_type {
+        DataType::String => Value::String(json.as_str()?.to_owned()),
+        DataType::Boolean => Value::Bool(json.as_bool()?),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 => {
+            Value::Int32(i32::try_from(json.as_i64()?).ok()?)
+        }
+        DataType::Int64 => Value::Int64(json.as_i64()?),
+        DataType::Uint8 | DataType::Uint16 | DataType::Uint32 => {
+            Value::Uint32(u32::try_from(json.as_u64()?).ok()?)
+        }
+        DataType::Uint64 => Value::Uint64(json.as_u64()?),
+        DataType::Float => Value::Float(json.as_f64()? as f32),
+        DataType::Double => Value::Double(json.as_f64()?),
+        DataType::StringArray => Value::StringArray(proto::v1::StringArray {
+            values: array(json, |v| v.as_str().map(str::to_owned))?,
+        }),
+        DataType::BooleanArray => Value::BoolArray(proto::v1::BoolArray {
+            values: array(json, JsonValue::as_bool)?,
+        }),
+        DataType::Int8Array | DataType::Int16Array | DataType::Int32Array => {
+            Value::Int32Array(proto::v1::Int32Array {
+                values: array(json, |v| i32::try_from(v.as_i64()?).ok())?,
+            })
+        }
+        DataType::Int64Array => Value::Int64Array(proto::v1::Int64Array {
+            values: array(json, JsonValue::as_i64)?,
+        }),
+        DataType::Uint8Array | DataType::Uint16Array | DataType::Uint32Array => {
+            Value::Uint32Array(proto::v1::Uint32Array {
+                values: array(json, |v| u32::try_from(v.as_u64()?).ok())?,
+            })
+        }
+        DataType::Uint64Array => Value::Uint64Array(proto::v1::Uint64Array {
+            values: array(json, JsonValue::as_u64)?,
+        }),
+        DataType::FloatArray => Value::FloatArray(proto::v1::FloatArray {
+            values: array(json, |v| v.as_f64().map(|v| v as f32))?,
+        }),
+        DataType::DoubleArray => Value::DoubleArray(proto::v1::DoubleArray {
+            values: array(json, JsonValue::as_f64)?,
+        }),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    use crate::mock::MockClient;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Seat {
+        position: Option<u32>,
+        is_occupied: Option<bool>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct Row {
+        driver_side: Seat,
+        passenger_side: Seat,
+    }
+
+    fn metadata(data_type: DataType) -> proto::v1::Metadata {
+        proto::v1::Metadata {
+            data_type: data_type.into(),
+            ..Default::default()
+        }
+    }
+
+    fn mock() -> MockClient {
+        let mock = MockClient::new();
+        for side in ["DriverSide", "PassengerSide"] {
+            mock.set_metadata(
+                &format!("Vehicle.Cabin.Seat.Row1.{side}.Position"),
+                metadata(DataType::Uint16),
+            );
+            mock.set_metadata(
+                &format!("Vehicle.Cabin.Seat.Row1.{side}.IsOccupied"),
+                metadata(DataType::Boolean),
+            );
+        }
+        mock
+    }
+
+    #[tokio::test]
+    async fn test_to_nested_json() {
+        let mock = mock();
+        mock.set_current_value(
+            "Vehicle.Cabin.Seat.Row1.DriverSide.Position",
+            Value::Uint32(20),
+        );
+        mock.set_current_value(
+            "Vehicle.Cabin.Seat.Row1.DriverSide.IsOccupied",
+            Value::Bool(true),
+        );
+
+        let entries = mock
+            .clone()
+            .get_current_values(vec!["Vehicle.Cabin.Seat.Row1".to_owned()])
+            .await
+            .unwrap();
+        let json = to_nested_json("Vehicle.Cabin.Seat.Row1", &entries);
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "DriverSide": { "Position": 20, "IsOccupied": true },
+                "PassengerSide": { "Position": null, "IsOccupied": null },
+            })
+        );
+
+        let row: Row = serde_json::from_value(json).unwrap();
+        assert_eq!(row.driver_side.position, Some(20));
+        assert_eq!(row.passenger_side.is_occupied, None);
+    }
+
+    #[tokio::test]
+    async fn test_subtree_json_roundtrip() {
+        let mock = mock();
+        let mut client = mock.clone();
+        set_subtree_json(
+            &mut client,
+            "Vehicle.Cabin.Seat",
+            serde_json::json!({ "Row1": { "DriverSide": { "Position": 7, "IsOccupied": false } } }),
+        )
+        .await
+        .unwrap();
+
+        let json = get_subtree_json(&mut client, "Vehicle.Cabin.Seat")
+            .await
+            .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "Row1": {
+                    "DriverSide": { "Position": 7, "IsOccupied": false },
+                    "PassengerSide": { "Position": null, "IsOccupied": null },
+                }
+            })
+        );
+
+        let result = set_subtree_json(
+            &mut client,
+            "Vehicle.Cabin.Seat",
+            serde_json::json!({ "Row1": { "DriverSide": { "Position": "front" } } }),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_leaf_branch() {
+        let entries = vec![DataEntry {
+            path: "Vehicle.Speed".to_owned(),
+            value: Some(proto::v1::Datapoint {
+                timestamp: None,
+                value: Some(Value::Float(0.1)),
+            }),
+            actuator_target: None,
+            metadata: None,
+        }];
+        assert_eq!(
+            to_nested_json("Vehicle.Speed", &entries),
+            serde_json::json!(0.1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_branch_from() {
+        let mock = mock();
+        let seat = Seat {
+            position: Some(42),
+            is_occupied: None,
+        };
+
+        set_branch_from(
+            &mut mock.clone(),
+            "Vehicle.Cabin.Seat.Row1.PassengerSide",
+            &seat,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            mock.current_value("Vehicle.Cabin.Seat.Row1.PassengerSide.Position")
+                .and_then(|datapoint| datapoint.value),
+            Some(Value::Uint32(42))
+        );
+        assert_eq!(
+            mock.current_value("Vehicle.Cabin.Seat.Row1.PassengerSide.IsOccupied"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_unknown_path() {
+        #[derive(Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Unknown {
+            height: u32,
+        }
+
+        let result = set_branch_from(
+            &mut mock().clone(),
+            "Vehicle.Cabin.Seat.Row1.DriverSide",
+            &Unknown { height: 1 },
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::UnknownPath(path)) if path == "Vehicle.Cabin.Seat.Row1.DriverSide.Height"
+        ));
+    }
+
+    #[test]
+    fn test_from_json() {
+        assert_eq!(
+            from_json(&serde_json::json!(300), DataType::Uint16),
+            Some(Value::Uint32(300))
+        );
+        assert_eq!(from_json(&serde_json::json!(-1), DataType::Uint32), None);
+        assert_eq!(from_json(&serde_json::json!("on"), DataType::Boolean), None);
+        assert_eq!(
+            from_json(&serde_json::json!([1.5, 2.5]), DataType::FloatArray),
+            Some(Value::FloatArray(proto::v1::FloatArray {
+                values: vec![1.5, 2.5]
+            }))
+        );
+    }
+}
@@ -0,0 +1,288 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! A local mirror of the current values of a set of paths.
+//!
+//! Control loops often read the same signals at a high rate. Instead of a
+//! `Get` per iteration, a [`DatapointCache`] keeps the latest values up to
+//! date through a subscription in the background and serves reads from
+//! memory:
+//!
+//! ```no_run
+//! # async fn example(client: kuksa::KuksaClient) {
+//! use std::time::Duration;
+//! use kuksa::cache::DatapointCache;
+//!
+//! let (cache, driver) = DatapointCache::new(client, ["Vehicle.Speed"]);
+//! tokio::spawn(driver);
+//! cache.on_change(|entry| println!("{} is {:?}", entry.path, entry.value));
+//!
+//! loop {
+//!     match cache.latest("Vehicle.Speed") {
+//!         Some(speed) if !speed.is_stale(Duration::from_millis(500)) => {
+//!             println!("speed {:?}", speed.datapoint)
+//!         }
+//!         _ => println!("speed unknown"),
+//!     }
+//!     tokio::time::sleep(Duration::from_millis(10)).await;
+//! }
+//! # }
+//! ```
+//!
+//! The subscription is re-established when it is lost, see
+//! [`ResilientSubscription`]. Values received before are kept meanwhile, but
+//! marked as [`interrupted`](CachedValue::interrupted).
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::api::KuksaClientApi;
+use crate::resilient::{ResilientSubscription, Resume, SubscriptionEvent};
+use crate::{proto, ClientError, DataEntry, Subscription};
+
+/// The latest value of a path as known to the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedValue {
+    /// `None` if the entry has no value yet
+    pub datapoint: Option<proto::v1::Datapoint>,
+    /// When the value was received
+    pub received: Instant,
+    /// The subscription was lost since the value was received, so it may
+    /// be outdated.
+    pub interrupted: bool,
+}
+
+impl CachedValue {
+    /// Time since the value was received.
+    pub fn age(&self) -> Duration {
+        self.received.elapsed()
+    }
+
+    /// Whether the value may be outdated, i.e. it is older than `max_age`
+    /// or the subscription was lost since.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.interrupted || self.age() > max_age
+    }
+}
+
+type Callback = dyn Fn(&DataEntry) + Send + Sync;
+
+struct Inner {
+    values: Mutex<HashMap<String, CachedValue>>,
+    callbacks: Mutex<Vec<Arc<Callback>>>,
+    // Ends the subscription once the last handle of the cache is gone
+    _cancel: DropGuard,
+}
+
+impl Inner {
+    fn values(&self) -> MutexGuard<'_, HashMap<String, CachedValue>> {
+        self.values.lock().expect("cache should not be poisoned")
+    }
+
+    fn received(&self, entry: DataEntry) {
+        let changed = {
+            let mut values = self.values();
+            let previous = values.insert(
+                entry.path.clone(),
+                CachedValue {
+                    datapoint: entry.value.clone(),
+                    received: Instant::now(),
+                    interrupted: false,
+                },
+            );
+            // A snapshot after an interruption repeats unchanged values
+            previous.is_none_or(|previous| previous.datapoint != entry.value)
+        };
+        if changed {
+            // Called without holding a lock, so callbacks may use the cache
+            let callbacks = self
+                .callbacks
+                .lock()
+                .expect("cache should not be poisoned")
+                .clone();
+            for callback in callbacks {
+                callback(&entry);
+            }
+        }
+    }
+
+    fn interrupted(&self) {
+        for value in self.values().values_mut() {
+            value.interrupted = true;
+        }
+    }
+}
+
+/// Handle of the mirror, cheap to clone. The subscription ends when the
+/// last handle is dropped.
+#[derive(Clone)]
+pub struct DatapointCache {
+    inner: Arc<Inner>,
+}
+
+impl DatapointCache {
+    /// Mirror the current values of `paths` through `client`.
+    ///
+    /// The returned future maintains the mirror and has to be spawned, e.g.
+    /// with `tokio::spawn`. It completes once the cache is dropped, or with
+    /// an error the subscription can't recover from, e.g. an unknown path.
+    pub fn new<C: KuksaClientApi + Unpin + 'static>(
+        client: C,
+        paths: impl IntoIterator<Item = impl Into<String>>,
+    ) -> (Self, impl Future<Output = Result<(), ClientError>>) {
+        let cancel = CancellationToken::new();
+        let inner = Arc::new(Inner {
+            values: Mutex::new(HashMap::new()),
+            callbacks: Mutex::new(Vec::new()),
+            _cancel: cancel.clone().drop_guard(),
+        });
+        let events = ResilientSubscription::new(
+            client,
+            Subscription::current_values(paths),
+            Resume::Snapshot,
+        )
+        .cancel_on(cancel);
+        let driver = Self::run(events, Arc::downgrade(&inner));
+        (DatapointCache { inner }, driver)
+    }
+
+    async fn run<C: KuksaClientApi + Unpin + 'static>(
+        mut events: ResilientSubscription<C>,
+        inner: Weak<Inner>,
+    ) -> Result<(), ClientError> {
+        while let Some(event) = events.next().await {
+            let Some(inner) = inner.upgrade() else {
+                break;
+            };
+            match event? {
+                SubscriptionEvent::Snapshot(entry) | SubscriptionEvent::Update(entry) => {
+                    inner.received(entry)
+                }
+                SubscriptionEvent::Interrupted(_) => inner.interrupted(),
+                // Values are up to date again with the snapshot that follows
+                SubscriptionEvent::Resumed(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The latest value of `path`, `None` until the first value of it was
+    /// received.
+    pub fn latest(&self, path: &str) -> Option<CachedValue> {
+        self.inner.values().get(path).cloned()
+    }
+
+    /// The latest values of all paths received so far.
+    pub fn snapshot(&self) -> HashMap<String, CachedValue> {
+        self.inner.values().clone()
+    }
+
+    /// Call `callback` with every entry whose value changed, from the task
+    /// running the mirror. Callbacks should return quickly, as they hold up
+    /// further updates.
+    pub fn on_change(&self, callback: impl Fn(&DataEntry) + Send + Sync + 'static) {
+        self.inner
+            .callbacks
+            .lock()
+            .expect("cache should not be poisoned")
+            .push(Arc::new(callback));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockClient;
+    use crate::proto::v1::datapoint::Value;
+
+    fn value(cache: &DatapointCache, path: &str) -> Option<Value> {
+        cache
+            .latest(path)
+            .and_then(|cached| cached.datapoint)
+            .and_then(|datapoint| datapoint.value)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mirror_and_callbacks() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        mock.set_current_value("Vehicle.IsMoving", Value::Bool(true));
+        let (cache, driver) = DatapointCache::new(mock.clone(), ["Vehicle.Speed"]);
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        cache.on_change({
+            let changes = changes.clone();
+            move |entry| changes.lock().unwrap().push(entry.path.clone())
+        });
+        let driver = tokio::spawn(driver);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(value(&cache, "Vehicle.Speed"), Some(Value::Float(1.0)));
+        assert_eq!(cache.latest("Vehicle.IsMoving"), None);
+
+        mock.set_current_value("Vehicle.Speed", Value::Float(2.0));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let speed = cache.latest("Vehicle.Speed").unwrap();
+        assert_eq!(value(&cache, "Vehicle.Speed"), Some(Value::Float(2.0)));
+        assert!(!speed.is_stale(Duration::from_millis(100)));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(speed.is_stale(Duration::from_millis(100)));
+        assert_eq!(*changes.lock().unwrap(), vec!["Vehicle.Speed"; 2]);
+
+        drop(cache);
+        assert!(driver.await.unwrap().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interruption_marks_values() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        let (cache, driver) = DatapointCache::new(mock.clone(), ["Vehicle.Speed"]);
+        let changes = Arc::new(Mutex::new(0));
+        cache.on_change({
+            let changes = changes.clone();
+            move |_| *changes.lock().unwrap() += 1
+        });
+        tokio::spawn(driver);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // Subscribing again fails first, so the gap lasts a retry interval
+        mock.disconnect_subscribers();
+        mock.push_error(ClientError::Connection("refused".to_owned()));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let speed = cache.latest("Vehicle.Speed").unwrap();
+        assert!(speed.interrupted);
+        assert!(speed.is_stale(Duration::from_secs(60)));
+
+        // Refreshed by the snapshot after subscribing again, which doesn't
+        // count as change
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(!cache.latest("Vehicle.Speed").unwrap().interrupted);
+        assert_eq!(*changes.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_ends_driver() {
+        let mock = MockClient::new();
+        mock.push_error(ClientError::Status(tonic::Status::permission_denied("no")));
+        let (cache, driver) = DatapointCache::new(mock, ["Vehicle.Speed"]);
+
+        assert!(driver.await.is_err());
+        assert!(cache.snapshot().is_empty());
+    }
+}
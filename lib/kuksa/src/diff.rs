@@ -0,0 +1,410 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Compare the state of two brokers, e.g. to check that a replacement
+//! broker is in sync before switching over to it.
+//!
+//! Current values are compared without their timestamps. Metadata is
+//! compared field by field.
+//!
+//! [`target_diff`] compares the current values of actuators with their
+//! targets instead, listing actuation requests providers haven't honored.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::SystemTime;
+
+use crate::api::KuksaClientApi;
+use crate::{proto, ClientError, DataEntry, ErrorCode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    /// The path only exists on the left broker.
+    OnlyLeft,
+    /// The path only exists on the right broker.
+    OnlyRight,
+    Value {
+        left: Option<proto::v1::datapoint::Value>,
+        right: Option<proto::v1::datapoint::Value>,
+    },
+    Metadata {
+        field: &'static str,
+        left: String,
+        right: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathDifference {
+    pub path: String,
+    pub difference: Difference,
+}
+
+#[derive(Debug, Default)]
+pub struct StateDiff {
+    /// Number of distinct paths found on either broker.
+    pub compared: usize,
+    pub differences: Vec<PathDifference>,
+}
+
+impl StateDiff {
+    pub fn is_in_sync(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for PathDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.difference {
+            Difference::OnlyLeft => write!(f, "- {}: only on left", self.path),
+            Difference::OnlyRight => write!(f, "+ {}: only on right", self.path),
+            Difference::Value { left, right } => write!(
+                f,
+                "~ {} value: {} != {}",
+                self.path,
+                display_value(left),
+                display_value(right)
+            ),
+            Difference::Metadata { field, left, right } => {
+                write!(f, "~ {} {}: {:?} != {:?}", self.path, field, left, right)
+            }
+        }
+    }
+}
+
+/// An actuator whose current value differs from its target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingActuation {
+    pub path: String,
+    pub current: Option<proto::v1::datapoint::Value>,
+    pub target: Option<proto::v1::datapoint::Value>,
+    /// When the target was set, if the broker reported it.
+    pub target_ts: Option<SystemTime>,
+}
+
+impl fmt::Display for PendingActuation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "~ {} current: {} != target: {}",
+            self.path,
+            display_value(&self.current),
+            display_value(&self.target)
+        )
+    }
+}
+
+fn display_value(value: &Option<proto::v1::datapoint::Value>) -> String {
+    match value {
+        Some(value) => format!("{value:?}"),
+        None => "NotAvailable".to_owned(),
+    }
+}
+
+/// Compare current values and metadata of all entries matching `paths`
+/// (which may contain wildcards) on the `left` and `right` broker.
+pub async fn diff(
+    left: &mut impl KuksaClientApi,
+    right: &mut impl KuksaClientApi,
+    paths: Vec<String>,
+) -> Result<StateDiff, ClientError> {
+    let left = fetch(left, &paths, View::Current).await?;
+    let right = fetch(right, &paths, View::Current).await?;
+
+    let mut diff = StateDiff::default();
+    let mut all_paths: Vec<&String> = left.keys().chain(right.keys()).collect();
+    all_paths.sort();
+    all_paths.dedup();
+    diff.compared = all_paths.len();
+
+    for path in all_paths {
+        let differences = match (left.get(path), right.get(path)) {
+            (Some(left), Some(right)) => compare(left, right),
+            (Some(_), None) => vec![Difference::OnlyLeft],
+            (None, Some(_)) => vec![Difference::OnlyRight],
+            (None, None) => unreachable!("path comes from one of the maps"),
+        };
+        diff.differences
+            .extend(differences.into_iter().map(|difference| PathDifference {
+                path: path.clone(),
+                difference,
+            }));
+    }
+    Ok(diff)
+}
+
+/// List the actuators matching `paths` (which may contain wildcards) which
+/// have a target that differs from their current value.
+pub async fn target_diff(
+    client: &mut impl KuksaClientApi,
+    paths: Vec<String>,
+) -> Result<Vec<PendingActuation>, ClientError> {
+    let current = fetch(client, &paths, View::Current).await?;
+    let targets = fetch(client, &paths, View::Target).await?;
+
+    let mut pending = Vec::new();
+    for (path, entry) in current {
+        let is_actuator = entry
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.entry_type == proto::v1::EntryType::Actuator as i32);
+        let Some(target) = targets
+            .get(&path)
+            .and_then(|entry| entry.actuator_target.clone())
+        else {
+            continue;
+        };
+        let current = entry.value.and_then(|dp| dp.value);
+        if !is_actuator || target.value.is_none() || current == target.value {
+            continue;
+        }
+        pending.push(PendingActuation {
+            path,
+            current,
+            target_ts: target.system_time(),
+            target: target.value,
+        });
+    }
+    Ok(pending)
+}
+
+#[derive(Clone, Copy)]
+enum View {
+    Current,
+    Target,
+}
+
+async fn fetch(
+    client: &mut impl KuksaClientApi,
+    paths: &[String],
+    view: View,
+) -> Result<BTreeMap<String, DataEntry>, ClientError> {
+    let mut entries = BTreeMap::new();
+    // One path at a time, so that a path missing on this broker doesn't
+    // fail the others
+    for path in paths {
+        let found = match view {
+            View::Current => client.get_current_values(vec![path.clone()]).await,
+            View::Target => client.get_target_values(vec![path.clone()]).await,
+        };
+        match found {
+            Ok(found) => entries.extend(found.into_iter().map(|entry| (entry.path.clone(), entry))),
+            Err(ClientError::Function(errors))
+                if errors
+                    .iter()
+                    .all(|err| err.error_code() == ErrorCode::NotFound) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(entries)
+}
+
+fn compare(left: &DataEntry, right: &DataEntry) -> Vec<Difference> {
+    let mut differences = Vec::new();
+
+    let left_value = left.value.as_ref().and_then(|dp| dp.value.clone());
+    let right_value = right.value.as_ref().and_then(|dp| dp.value.clone());
+    if left_value != right_value {
+        differences.push(Difference::Value {
+            left: left_value,
+            right: right_value,
+        });
+    }
+
+    let left_fields = metadata_fields(left.metadata.as_ref());
+    let right_fields = metadata_fields(right.metadata.as_ref());
+    for ((field, left), (_, right)) in left_fields.into_iter().zip(right_fields) {
+        if left != right {
+            differences.push(Difference::Metadata { field, left, right });
+        }
+    }
+    differences
+}
+
+fn metadata_fields(metadata: Option<&proto::v1::Metadata>) -> [(&'static str, String); 7] {
+    let metadata = metadata.cloned().unwrap_or_default();
+    [
+        (
+            "data_type",
+            proto::v1::DataType::try_from(metadata.data_type)
+                .map(|data_type| data_type.as_str_name().to_owned())
+                .unwrap_or_else(|_| metadata.data_type.to_string()),
+        ),
+        (
+            "entry_type",
+            proto::v1::EntryType::try_from(metadata.entry_type)
+                .map(|entry_type| entry_type.as_str_name().to_owned())
+                .unwrap_or_else(|_| metadata.entry_type.to_string()),
+        ),
+        ("description", metadata.description.unwrap_or_default()),
+        ("comment", metadata.comment.unwrap_or_default()),
+        ("deprecation", metadata.deprecation.unwrap_or_default()),
+        ("unit", metadata.unit.unwrap_or_default()),
+        (
+            "value_restriction",
+            metadata
+                .value_restriction
+                .map(|restriction| format!("{restriction:?}"))
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::MockClient;
+    use proto::v1::datapoint::Value;
+
+    fn metadata(unit: &str) -> proto::v1::Metadata {
+        proto::v1::Metadata {
+            data_type: proto::v1::DataType::Float.into(),
+            entry_type: proto::v1::EntryType::Sensor.into(),
+            unit: Some(unit.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_sync() {
+        let left = MockClient::new();
+        let right = MockClient::new();
+        for mock in [&left, &right] {
+            mock.set_metadata("Vehicle.Speed", metadata("km/h"));
+            mock.set_current_value("Vehicle.Speed", Value::Float(50.0));
+        }
+
+        let diff = diff(
+            &mut left.clone(),
+            &mut right.clone(),
+            vec!["Vehicle.Speed".to_owned()],
+        )
+        .await
+        .unwrap();
+        assert!(diff.is_in_sync());
+        assert_eq!(diff.compared, 1);
+    }
+
+    #[tokio::test]
+    async fn test_differences() {
+        let left = MockClient::new();
+        let right = MockClient::new();
+        left.set_metadata("Vehicle.Speed", metadata("km/h"));
+        left.set_current_value("Vehicle.Speed", Value::Float(50.0));
+        right.set_metadata("Vehicle.Speed", metadata("m/s"));
+        right.set_current_value("Vehicle.Speed", Value::Float(40.0));
+        left.set_current_value("Vehicle.Width", Value::Uint32(1800));
+        right.set_current_value("Vehicle.Height", Value::Uint32(1500));
+
+        let diff = diff(
+            &mut left.clone(),
+            &mut right.clone(),
+            vec![
+                "Vehicle.Speed".to_owned(),
+                "Vehicle.Width".to_owned(),
+                "Vehicle.Height".to_owned(),
+            ],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(diff.compared, 3);
+        assert_eq!(
+            diff.differences,
+            vec![
+                PathDifference {
+                    path: "Vehicle.Height".to_owned(),
+                    difference: Difference::OnlyRight,
+                },
+                PathDifference {
+                    path: "Vehicle.Speed".to_owned(),
+                    difference: Difference::Value {
+                        left: Some(Value::Float(50.0)),
+                        right: Some(Value::Float(40.0)),
+                    },
+                },
+                PathDifference {
+                    path: "Vehicle.Speed".to_owned(),
+                    difference: Difference::Metadata {
+                        field: "unit",
+                        left: "km/h".to_owned(),
+                        right: "m/s".to_owned(),
+                    },
+                },
+                PathDifference {
+                    path: "Vehicle.Width".to_owned(),
+                    difference: Difference::OnlyLeft,
+                },
+            ]
+        );
+        assert_eq!(
+            diff.differences[1].to_string(),
+            "~ Vehicle.Speed value: Float(50.0) != Float(40.0)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_target_diff() {
+        let client = MockClient::new();
+        let actuator = proto::v1::Metadata {
+            data_type: proto::v1::DataType::Boolean.into(),
+            entry_type: proto::v1::EntryType::Actuator.into(),
+            ..Default::default()
+        };
+        for path in [
+            "Vehicle.Body.Trunk.IsOpen",
+            "Vehicle.Body.Hood.IsOpen",
+            "Vehicle.Body.Horn.IsActive",
+        ] {
+            client.set_metadata(path, actuator.clone());
+            client.set_current_value(path, Value::Bool(false));
+        }
+        // Not honored yet
+        client.set_target_value("Vehicle.Body.Trunk.IsOpen", Value::Bool(true));
+        // Honored
+        client.set_target_value("Vehicle.Body.Hood.IsOpen", Value::Bool(false));
+        // Vehicle.Body.Horn.IsActive has no target
+
+        let pending = target_diff(&mut client.clone(), vec!["Vehicle.Body".to_owned()])
+            .await
+            .unwrap();
+        assert_eq!(
+            pending,
+            vec![PendingActuation {
+                path: "Vehicle.Body.Trunk.IsOpen".to_owned(),
+                current: Some(Value::Bool(false)),
+                target: Some(Value::Bool(true)),
+                target_ts: None,
+            }]
+        );
+        assert_eq!(
+            pending[0].to_string(),
+            "~ Vehicle.Body.Trunk.IsOpen current: Bool(false) != target: Bool(true)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connection_error() {
+        let left = MockClient::new();
+        left.push_error(ClientError::Connection("unreachable".to_owned()));
+
+        let result = diff(
+            &mut left.clone(),
+            &mut MockClient::new(),
+            vec!["Vehicle.Speed".to_owned()],
+        )
+        .await;
+        assert!(matches!(result, Err(ClientError::Connection(_))));
+    }
+}
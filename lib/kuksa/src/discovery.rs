@@ -0,0 +1,375 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Subscribing to all entries below a branch, including the ones registered
+//! later.
+//!
+//! The broker resolves the paths of a subscription when it is created, so
+//! entries a provider registers afterwards aren't part of it. A
+//! [`BranchSubscription`] looks for new entries below the branch
+//! periodically, reports them as [`BranchEvent::Added`] and subscribes to
+//! them as well:
+//!
+//! ```no_run
+//! # async fn example(client: kuksa::KuksaClient) {
+//! use kuksa::discovery::BranchEvent;
+//! use tokio_stream::StreamExt;
+//!
+//! let mut events = client.subscribe_branch("Vehicle.Cabin");
+//! while let Some(Ok(event)) = events.next().await {
+//!     match event {
+//!         BranchEvent::Added(entry) => println!("new signal {}", entry.path),
+//!         BranchEvent::Update(entry) => println!("{} is {:?}", entry.path, entry.value),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::time::Sleep;
+use tokio_stream::Stream;
+
+use crate::api::{KuksaClientApi, SubscribeStream};
+use crate::{ClientError, DataEntry, ErrorCode, Subscription};
+
+const DEFAULT_DISCOVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+// Entries are the common case, boxing them isn't worth it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchEvent {
+    /// An entry was registered below the branch after the subscription
+    /// started, with its metadata. Its value follows as update.
+    Added(DataEntry),
+    /// Value of an entry, the current one when subscribing and then every
+    /// change
+    Update(DataEntry),
+}
+
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+#[cfg(all(feature = "grpc-web", target_arch = "wasm32"))]
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+enum State<C> {
+    Waiting(C, Pin<Box<Sleep>>),
+    Discovering(BoxFuture<(C, Result<Vec<DataEntry>, ClientError>)>),
+    Subscribing(BoxFuture<(C, Vec<String>, Result<SubscribeStream, ClientError>)>),
+    Done,
+}
+
+pub struct BranchSubscription<C> {
+    state: State<C>,
+    branch: String,
+    interval: Duration,
+    /// Paths reported so far
+    known: HashSet<String>,
+    /// Whether the entries found first are still to be subscribed to, which
+    /// aren't reported as added
+    initial: bool,
+    /// Subscriptions with the paths they cover
+    streams: Vec<(Vec<String>, SubscribeStream)>,
+    /// Paths whose subscription ended, to subscribe to again
+    orphaned: Vec<String>,
+    decoded: VecDeque<BranchEvent>,
+}
+
+impl<C: KuksaClientApi + 'static> BranchSubscription<C> {
+    /// Subscribe to all entries below `branch` through `client`.
+    pub fn new(client: C, branch: impl Into<String>) -> Self {
+        let branch = branch.into();
+        BranchSubscription {
+            state: State::Discovering(Self::discover(client, branch.clone())),
+            branch,
+            interval: DEFAULT_DISCOVERY_INTERVAL,
+            known: HashSet::new(),
+            initial: true,
+            streams: Vec::new(),
+            orphaned: Vec::new(),
+            decoded: VecDeque::new(),
+        }
+    }
+
+    /// Time between looking for new entries, five seconds by default.
+    pub fn discovery_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    fn discover(
+        mut client: C,
+        branch: String,
+    ) -> BoxFuture<(C, Result<Vec<DataEntry>, ClientError>)> {
+        Box::pin(async move {
+            let result = match client.get_metadata(vec![branch]).await {
+                // Nothing registered below the branch yet
+                Err(err) if err.code() == ErrorCode::NotFound => Ok(Vec::new()),
+                result => result,
+            };
+            (client, result)
+        })
+    }
+
+    fn subscribe(
+        mut client: C,
+        paths: Vec<String>,
+    ) -> BoxFuture<(C, Vec<String>, Result<SubscribeStream, ClientError>)> {
+        Box::pin(async move {
+            let subscription = Subscription::current_values(paths.iter().cloned());
+            let result = client.subscribe_with(subscription).await;
+            (client, paths, result)
+        })
+    }
+
+    fn wait(&mut self, client: C) {
+        let sleep = Box::pin(tokio::time::sleep(self.interval));
+        self.state = State::Waiting(client, sleep);
+    }
+
+    /// Report the entries not known yet and subscribe to them.
+    fn discovered(&mut self, client: C, entries: Vec<DataEntry>) {
+        let initial = std::mem::take(&mut self.initial);
+        let mut paths = std::mem::take(&mut self.orphaned);
+        for entry in entries {
+            if self.known.insert(entry.path.clone()) {
+                paths.push(entry.path.clone());
+                if !initial {
+                    self.decoded.push_back(BranchEvent::Added(entry));
+                }
+            }
+        }
+        if paths.is_empty() {
+            self.wait(client);
+        } else {
+            self.state = State::Subscribing(Self::subscribe(client, paths));
+        }
+    }
+
+    /// Retry at the next discovery if `err` is retryable, otherwise end the
+    /// stream with it.
+    fn failed(&mut self, client: C, err: ClientError) -> Option<ClientError> {
+        if err.is_retryable() {
+            self.wait(client);
+            None
+        } else {
+            self.state = State::Done;
+            self.streams.clear();
+            Some(err)
+        }
+    }
+
+    /// Poll the subscriptions, dropping the ones that ended.
+    fn poll_streams(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut index = 0;
+        while index < self.streams.len() {
+            match self.streams[index].1.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(response))) => {
+                    self.decoded.extend(
+                        response
+                            .updates
+                            .into_iter()
+                            .filter_map(|update| update.entry.map(BranchEvent::Update)),
+                    );
+                    return Poll::Ready(());
+                }
+                // Subscribed to again with the next discovery
+                Poll::Ready(Some(Err(_)) | None) => {
+                    let (paths, _) = self.streams.swap_remove(index);
+                    self.orphaned.extend(paths);
+                }
+                Poll::Pending => index += 1,
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<C: KuksaClientApi + Unpin + 'static> Stream for BranchSubscription<C> {
+    type Item = Result<BranchEvent, ClientError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.decoded.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            match std::mem::replace(&mut this.state, State::Done) {
+                State::Waiting(client, mut sleep) => match sleep.as_mut().poll(cx) {
+                    Poll::Ready(()) => {
+                        this.state =
+                            State::Discovering(Self::discover(client, this.branch.clone()));
+                        continue;
+                    }
+                    Poll::Pending => this.state = State::Waiting(client, sleep),
+                },
+                State::Discovering(mut future) => match future.as_mut().poll(cx) {
+                    Poll::Ready((client, Ok(entries))) => {
+                        this.discovered(client, entries);
+                        continue;
+                    }
+                    Poll::Ready((client, Err(err))) => {
+                        if let Some(err) = this.failed(client, err) {
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        continue;
+                    }
+                    Poll::Pending => this.state = State::Discovering(future),
+                },
+                State::Subscribing(mut future) => match future.as_mut().poll(cx) {
+                    Poll::Ready((client, paths, Ok(stream))) => {
+                        this.streams.push((paths, stream));
+                        this.wait(client);
+                        continue;
+                    }
+                    Poll::Ready((client, paths, Err(err))) => {
+                        this.orphaned.extend(paths);
+                        if let Some(err) = this.failed(client, err) {
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        continue;
+                    }
+                    Poll::Pending => this.state = State::Subscribing(future),
+                },
+                State::Done => return Poll::Ready(None),
+            }
+            return match this.poll_streams(cx) {
+                Poll::Ready(()) => continue,
+                Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockClient;
+    use crate::proto::v1::{datapoint::Value, Metadata};
+    use tokio_stream::StreamExt;
+
+    fn register(mock: &MockClient, path: &str) {
+        mock.set_metadata(
+            path,
+            Metadata {
+                description: Some(format!("{path} description")),
+                ..Default::default()
+            },
+        );
+    }
+
+    async fn next(
+        events: &mut BranchSubscription<MockClient>,
+    ) -> (&'static str, String, Option<Value>) {
+        match events.next().await.unwrap().unwrap() {
+            BranchEvent::Added(entry) => {
+                assert!(entry.metadata.is_some());
+                ("added", entry.path, None)
+            }
+            BranchEvent::Update(entry) => {
+                ("update", entry.path, entry.value.and_then(|dp| dp.value))
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_new_entries_are_reported() {
+        let mock = MockClient::new();
+        register(&mock, "Vehicle.Cabin.Light.IsDomeOn");
+        mock.set_current_value("Vehicle.Cabin.Light.IsDomeOn", Value::Bool(true));
+        let mut events = BranchSubscription::new(mock.clone(), "Vehicle.Cabin");
+
+        assert_eq!(
+            next(&mut events).await,
+            (
+                "update",
+                "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                Some(Value::Bool(true))
+            )
+        );
+
+        register(&mock, "Vehicle.Cabin.Seat.Row1.Heating");
+        mock.set_current_value("Vehicle.Cabin.Seat.Row1.Heating", Value::Int32(2));
+        // Outside of the branch
+        register(&mock, "Vehicle.Speed");
+        assert_eq!(
+            next(&mut events).await,
+            ("added", "Vehicle.Cabin.Seat.Row1.Heating".to_owned(), None)
+        );
+        assert_eq!(
+            next(&mut events).await,
+            (
+                "update",
+                "Vehicle.Cabin.Seat.Row1.Heating".to_owned(),
+                Some(Value::Int32(2))
+            )
+        );
+
+        mock.set_current_value("Vehicle.Cabin.Light.IsDomeOn", Value::Bool(false));
+        assert_eq!(
+            next(&mut events).await,
+            (
+                "update",
+                "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                Some(Value::Bool(false))
+            )
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_empty_branch_and_lost_subscription() {
+        let mock = MockClient::new();
+        let mut events = BranchSubscription::new(mock.clone(), "Vehicle.Cabin")
+            .discovery_interval(Duration::from_secs(1));
+        let nothing = tokio::time::timeout(Duration::from_millis(100), events.next()).await;
+        assert!(nothing.is_err());
+
+        register(&mock, "Vehicle.Cabin.Light.IsDomeOn");
+        assert_eq!(
+            next(&mut events).await,
+            ("added", "Vehicle.Cabin.Light.IsDomeOn".to_owned(), None)
+        );
+        assert_eq!(
+            next(&mut events).await,
+            ("update", "Vehicle.Cabin.Light.IsDomeOn".to_owned(), None)
+        );
+
+        // Resubscribed without being reported as added again
+        mock.disconnect_subscribers();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        mock.set_current_value("Vehicle.Cabin.Light.IsDomeOn", Value::Bool(true));
+        assert_eq!(
+            next(&mut events).await,
+            (
+                "update",
+                "Vehicle.Cabin.Light.IsDomeOn".to_owned(),
+                Some(Value::Bool(true))
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_ends_stream() {
+        let mock = MockClient::new();
+        mock.push_error(ClientError::Status(tonic::Status::permission_denied("no")));
+        let mut events = BranchSubscription::new(mock, "Vehicle.Cabin");
+
+        let err = events.next().await.unwrap().unwrap_err();
+        assert_eq!(err.code(), ErrorCode::PermissionDenied);
+        assert!(events.next().await.is_none());
+    }
+}
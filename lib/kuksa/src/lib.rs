@@ -11,14 +11,40 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 
+//! Client for the `kuksa.val.v1` API of KUKSA Databroker.
+//!
+//! By default the client connects through tonic's transport. To use it from
+//! a browser, build for `wasm32-unknown-unknown` with
+//! `default-features = false, features = ["grpc-web"]`. Requests are then
+//! sent as grpc-web, so the server (or a proxy in front of it) has to accept
+//! grpc-web. On other targets the feature has no effect, so it can be
+//! enabled along with the others, e.g. with `--all-features`.
+
+use std::collections::HashMap;
+
 use http::Uri;
-use kuksa_common::conversion::{ConvertToSDV, ConvertToV1};
-use kuksa_common::ClientTraitV1;
+#[cfg(feature = "sdv-v1")]
+use kuksa_common::{
+    conversion::{ConvertToSDV, ConvertToV1},
+    ClientTraitV1,
+};
 use tonic::async_trait;
 
 pub use databroker_proto::kuksa::val::{self as proto, v1::DataEntry};
 
-pub use kuksa_common::{Client, ClientError};
+pub use kuksa_common::{CancellationToken, Client, ClientError, ErrorCode, ResponseError};
+pub use subscription::Subscription;
+
+pub mod api;
+pub mod branch;
+pub mod cache;
+pub mod diff;
+pub mod discovery;
+pub mod mock;
+pub mod resilient;
+pub mod stream;
+pub mod subscription;
+pub mod vss;
 
 #[derive(Debug)]
 pub struct KuksaClient {
@@ -32,83 +58,359 @@ impl KuksaClient {
         }
     }
 
-    async fn set(&mut self, entry: DataEntry, _fields: Vec<i32>) -> Result<(), ClientError> {
-        let mut client = proto::v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-        let set_request = proto::v1::SetRequest {
-            updates: vec![proto::v1::EntryUpdate {
-                entry: Some(entry),
-                fields: _fields,
-            }],
-        };
-        match client.set(set_request).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                let mut errors: Vec<proto::v1::Error> = Vec::new();
-                if let Some(err) = message.error {
-                    errors.push(err);
-                }
-                for error in message.errors {
-                    if let Some(err) = error.error {
-                        errors.push(err);
-                    }
-                }
-                if errors.is_empty() {
-                    Ok(())
-                } else {
-                    Err(ClientError::Function(errors))
+    /// Read the current values below `branch` into `T`, see [`branch`].
+    pub async fn get_branch_as<T: serde::de::DeserializeOwned>(
+        &mut self,
+        branch: &str,
+    ) -> Result<T, branch::Error> {
+        branch::get_branch_as(self, branch).await
+    }
+
+    /// Publish the fields of `value` as current values below `branch`.
+    pub async fn set_branch_from<T: serde::Serialize>(
+        &mut self,
+        branch: &str,
+        value: &T,
+    ) -> Result<(), branch::Error> {
+        branch::set_branch_from(self, branch, value).await
+    }
+
+    /// The current values below `branch` as nested JSON object.
+    pub async fn get_subtree_json(
+        &mut self,
+        branch: &str,
+    ) -> Result<serde_json::Value, branch::Error> {
+        branch::get_subtree_json(self, branch).await
+    }
+
+    /// Publish current values below `branch` given as nested JSON object.
+    pub async fn set_subtree_json(
+        &mut self,
+        branch: &str,
+        json: serde_json::Value,
+    ) -> Result<(), branch::Error> {
+        branch::set_subtree_json(self, branch, json).await
+    }
+
+    /// Set actuator targets below `branch` given as nested JSON object.
+    pub async fn set_subtree_target_json(
+        &mut self,
+        branch: &str,
+        json: serde_json::Value,
+    ) -> Result<(), branch::Error> {
+        branch::set_subtree_target_json(self, branch, json).await
+    }
+
+    /// Set the fields of `value` as actuator targets below `branch`.
+    pub async fn set_branch_target_from<T: serde::Serialize>(
+        &mut self,
+        branch: &str,
+        value: &T,
+    ) -> Result<(), branch::Error> {
+        branch::set_branch_target_from(self, branch, value).await
+    }
+
+    /// Subscribe to the entries of `subscription` in one stream.
+    pub async fn subscribe_with(
+        &mut self,
+        subscription: Subscription,
+    ) -> Result<tonic::Streaming<proto::v1::SubscribeResponse>, ClientError> {
+        let mut request = tonic::Request::new(proto::v1::SubscribeRequest::from(subscription));
+        let call = self
+            .basic_client
+            .begin("kuksa.val.v1.VAL/Subscribe", request.metadata_mut())?;
+        let result = call
+            .run(async {
+                let mut client = proto::v1::val_client::ValClient::with_interceptor(
+                    self.basic_client.get_channel().await?.clone(),
+                    self.basic_client.get_auth_interceptor(),
+                );
+                match client.subscribe(request).await {
+                    Ok(response) => Ok(response.into_inner()),
+                    Err(err) => Err(ClientError::Status(err)),
                 }
-            }
-            Err(err) => Err(ClientError::Status(err)),
+            })
+            .await;
+        self.basic_client.end(&call, result)
+    }
+
+    /// Subscribe to all entries below `branch`, including the ones
+    /// registered later, which are reported as
+    /// [`discovery::BranchEvent::Added`]. The client is used to look for new
+    /// entries, so it is moved into the stream.
+    pub fn subscribe_branch(
+        self,
+        branch: impl Into<String>,
+    ) -> discovery::BranchSubscription<KuksaClient> {
+        discovery::BranchSubscription::new(self, branch)
+    }
+
+    /// Read the current values of `paths` with one request. Unlike
+    /// [`ClientTraitV1::get_current_values`], a path that can't be read
+    /// doesn't fail the others, see [`BatchResults`].
+    pub async fn try_get_current_values(
+        &mut self,
+        paths: Vec<String>,
+    ) -> Result<BatchResults<DataEntry>, ClientError> {
+        self.try_get(paths, proto::v1::View::CurrentValue).await
+    }
+
+    /// Read the target values of `paths` with one request, see
+    /// [`KuksaClient::try_get_current_values`].
+    pub async fn try_get_target_values(
+        &mut self,
+        paths: Vec<String>,
+    ) -> Result<BatchResults<DataEntry>, ClientError> {
+        self.try_get(paths, proto::v1::View::TargetValue).await
+    }
+
+    /// Read the metadata of `paths` with one request, see
+    /// [`KuksaClient::try_get_current_values`].
+    pub async fn try_get_metadata(
+        &mut self,
+        paths: Vec<String>,
+    ) -> Result<BatchResults<DataEntry>, ClientError> {
+        self.try_get(paths, proto::v1::View::Metadata).await
+    }
+
+    /// Set the current values of `datapoints` with one request. Values the
+    /// databroker rejects, e.g. as out of bounds, don't keep the others
+    /// from being set, see [`BatchResults`].
+    pub async fn try_set_current_values(
+        &mut self,
+        datapoints: HashMap<String, proto::v1::Datapoint>,
+    ) -> Result<BatchResults<()>, ClientError> {
+        let updates = datapoints.into_iter().map(current_value_update).collect();
+        self.try_set(updates).await
+    }
+
+    /// Set the target values of `datapoints` with one request, see
+    /// [`KuksaClient::try_set_current_values`].
+    pub async fn try_set_target_values(
+        &mut self,
+        datapoints: HashMap<String, proto::v1::Datapoint>,
+    ) -> Result<BatchResults<()>, ClientError> {
+        let updates = datapoints.into_iter().map(target_value_update).collect();
+        self.try_set(updates).await
+    }
+
+    async fn try_get(
+        &mut self,
+        paths: Vec<String>,
+        view: proto::v1::View,
+    ) -> Result<BatchResults<DataEntry>, ClientError> {
+        if paths.is_empty() {
+            return Ok(HashMap::new());
         }
+        get_results(self.get(paths, view).await?)
+            .map_err(|error| ClientError::Function(vec![error.into()]))
     }
 
+    async fn try_set(
+        &mut self,
+        updates: Vec<proto::v1::EntryUpdate>,
+    ) -> Result<BatchResults<()>, ClientError> {
+        let paths: Vec<String> = updates
+            .iter()
+            .filter_map(|update| Some(update.entry.as_ref()?.path.clone()))
+            .collect();
+        if paths.is_empty() {
+            return Ok(HashMap::new());
+        }
+        set_results(paths, self.set(updates).await?)
+            .map_err(|error| ClientError::Function(vec![error.into()]))
+    }
+
+    /// Send all `updates` in one request.
+    async fn set(
+        &mut self,
+        updates: Vec<proto::v1::EntryUpdate>,
+    ) -> Result<proto::v1::SetResponse, ClientError> {
+        let mut set_request = tonic::Request::new(proto::v1::SetRequest { updates });
+        let call = self
+            .basic_client
+            .begin("kuksa.val.v1.VAL/Set", set_request.metadata_mut())?;
+        let result = call
+            .run(async {
+                let mut client = proto::v1::val_client::ValClient::with_interceptor(
+                    self.basic_client.get_channel().await?.clone(),
+                    self.basic_client.get_auth_interceptor(),
+                );
+                match client.set(set_request).await {
+                    Ok(response) => Ok(response.into_inner()),
+                    Err(err) => Err(ClientError::Status(err)),
+                }
+            })
+            .await;
+        self.basic_client.end(&call, result)
+    }
+
+    /// Request `view` of all `paths` in one request.
     async fn get(
         &mut self,
-        path: &str,
+        paths: Vec<String>,
         view: proto::v1::View,
-        _fields: Vec<i32>,
-    ) -> Result<Vec<DataEntry>, ClientError> {
-        let mut client = proto::v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
-        let get_request = proto::v1::GetRequest {
-            entries: vec![proto::v1::EntryRequest {
-                path: path.to_string(),
-                view: view.into(),
-                fields: _fields,
-            }],
+    ) -> Result<proto::v1::GetResponse, ClientError> {
+        let fields = match view {
+            proto::v1::View::CurrentValue => vec![
+                proto::v1::Field::Value.into(),
+                proto::v1::Field::Metadata.into(),
+            ],
+            proto::v1::View::TargetValue => vec![
+                proto::v1::Field::ActuatorTarget.into(),
+                proto::v1::Field::Metadata.into(),
+            ],
+            _ => vec![proto::v1::Field::Metadata.into()],
         };
-
-        match client.get(get_request).await {
-            Ok(response) => {
-                let message = response.into_inner();
-                let mut errors = Vec::new();
-                if let Some(err) = message.error {
-                    errors.push(err);
-                }
-                for error in message.errors {
-                    if let Some(err) = error.error {
-                        errors.push(err);
-                    }
-                }
-                if !errors.is_empty() {
-                    Err(ClientError::Function(errors))
-                } else {
-                    // since there is only one DataEntry in the vector return only the according DataEntry
-                    Ok(message.entries.clone())
+        let entries = paths
+            .into_iter()
+            .map(|path| proto::v1::EntryRequest {
+                path,
+                view: view.into(),
+                fields: fields.clone(),
+            })
+            .collect();
+        let mut get_request = tonic::Request::new(proto::v1::GetRequest { entries });
+        let call = self
+            .basic_client
+            .begin("kuksa.val.v1.VAL/Get", get_request.metadata_mut())?;
+        let result = call
+            .run(async {
+                let mut client = proto::v1::val_client::ValClient::with_interceptor(
+                    self.basic_client.get_channel().await?.clone(),
+                    self.basic_client.get_auth_interceptor(),
+                );
+                match client.get(get_request).await {
+                    Ok(response) => Ok(response.into_inner()),
+                    Err(err) => Err(ClientError::Status(err)),
                 }
-            }
-            Err(err) => Err(ClientError::Status(err)),
+            })
+            .await;
+        self.basic_client.end(&call, result)
+    }
+
+    /// Request `view` of all `paths` in one request, failing if any of them
+    /// failed.
+    async fn get_all(
+        &mut self,
+        paths: Vec<String>,
+        view: proto::v1::View,
+    ) -> Result<Vec<DataEntry>, ClientError> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let response = self.get(paths, view).await?;
+        let errors = ResponseError::from_v1_response(response.error, response.errors);
+        if errors.is_empty() {
+            Ok(response.entries)
+        } else {
+            Err(ClientError::Function(errors))
+        }
+    }
+
+    /// Send all `updates` in one request, failing if any of them failed.
+    async fn set_all(&mut self, updates: Vec<proto::v1::EntryUpdate>) -> Result<(), ClientError> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+        let response = self.set(updates).await?;
+        let errors = ResponseError::from_v1_response(response.error, response.errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ClientError::Function(errors))
+        }
+    }
+}
+
+/// Result of each path of a batched request, e.g.
+/// [`KuksaClient::try_get_current_values`], the error of the path if it
+/// failed. Entries read are keyed by their own path, which differs from the
+/// requested one for wildcards. Note that the databroker doesn't return any
+/// entries if any of the requested paths may not be read.
+pub type BatchResults<T> = HashMap<String, Result<T, proto::v1::Error>>;
+
+/// The error of a response that isn't specific to any path.
+fn request_error(
+    error: Option<proto::v1::Error>,
+    errors: &[proto::v1::DataEntryError],
+) -> Result<(), proto::v1::Error> {
+    // The databroker repeats the first error of a path as the error of the
+    // request
+    match error {
+        Some(error) if errors.is_empty() => Err(error),
+        _ => Ok(()),
+    }
+}
+
+fn get_results(
+    response: proto::v1::GetResponse,
+) -> Result<BatchResults<DataEntry>, proto::v1::Error> {
+    request_error(response.error, &response.errors)?;
+    let mut results: BatchResults<DataEntry> = response
+        .entries
+        .into_iter()
+        .map(|entry| (entry.path.clone(), Ok(entry)))
+        .collect();
+    for entry_error in response.errors {
+        if let Some(error) = entry_error.error {
+            results.insert(entry_error.path, Err(error));
         }
     }
+    Ok(results)
 }
 
-#[async_trait]
+fn set_results(
+    paths: Vec<String>,
+    response: proto::v1::SetResponse,
+) -> Result<BatchResults<()>, proto::v1::Error> {
+    request_error(response.error, &response.errors)?;
+    let mut results: BatchResults<()> = paths.into_iter().map(|path| (path, Ok(()))).collect();
+    for entry_error in response.errors {
+        if let Some(error) = entry_error.error {
+            results.insert(entry_error.path, Err(error));
+        }
+    }
+    Ok(results)
+}
+
+fn current_value_update(
+    (path, datapoint): (String, proto::v1::Datapoint),
+) -> proto::v1::EntryUpdate {
+    proto::v1::EntryUpdate {
+        entry: Some(DataEntry {
+            path,
+            value: Some(datapoint),
+            actuator_target: None,
+            metadata: None,
+        }),
+        fields: vec![
+            proto::v1::Field::Value.into(),
+            proto::v1::Field::Path.into(),
+        ],
+    }
+}
+
+fn target_value_update(
+    (path, datapoint): (String, proto::v1::Datapoint),
+) -> proto::v1::EntryUpdate {
+    proto::v1::EntryUpdate {
+        entry: Some(DataEntry {
+            path,
+            value: None,
+            actuator_target: Some(datapoint),
+            metadata: None,
+        }),
+        fields: vec![
+            proto::v1::Field::ActuatorTarget.into(),
+            proto::v1::Field::Path.into(),
+        ],
+    }
+}
+
+#[cfg(feature = "sdv-v1")]
+#[cfg_attr(all(feature = "grpc-web", target_arch = "wasm32"), async_trait(?Send))]
+#[cfg_attr(not(all(feature = "grpc-web", target_arch = "wasm32")), async_trait)]
 impl kuksa_common::SDVClientTraitV1 for KuksaClient {
     type SensorUpdateType = kuksa_common::types::SensorUpdateSDVTypeV1;
     type UpdateActuationType = kuksa_common::types::UpdateActuationSDVTypeV1;
@@ -176,7 +478,8 @@ impl kuksa_common::SDVClientTraitV1 for KuksaClient {
     }
 }
 
-#[async_trait]
+#[cfg_attr(all(feature = "grpc-web", target_arch = "wasm32"), async_trait(?Send))]
+#[cfg_attr(not(all(feature = "grpc-web", target_arch = "wasm32")), async_trait)]
 impl kuksa_common::ClientTraitV1 for KuksaClient {
     type SensorUpdateType = kuksa_common::types::SensorUpdateTypeV1;
     type UpdateActuationType = kuksa_common::types::UpdateActuationTypeV1;
@@ -193,136 +496,38 @@ impl kuksa_common::ClientTraitV1 for KuksaClient {
         &mut self,
         datapoints: Self::SensorUpdateType,
     ) -> Result<Self::PublishResponseType, ClientError> {
-        for (path, datapoint) in datapoints {
-            match self
-                .set(
-                    proto::v1::DataEntry {
-                        path: path.clone(),
-                        value: Some(datapoint),
-                        actuator_target: None,
-                        metadata: None,
-                    },
-                    vec![
-                        proto::v1::Field::Value.into(),
-                        proto::v1::Field::Path.into(),
-                    ],
-                )
-                .await
-            {
-                Ok(_) => {
-                    continue;
-                }
-                Err(err) => return Err(err),
-            }
-        }
-
-        Ok(())
+        let updates = datapoints.into_iter().map(current_value_update).collect();
+        self.set_all(updates).await
     }
 
     async fn get_current_values(
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::GetResponseType, ClientError> {
-        let mut get_result = Vec::new();
-
-        for path in paths {
-            match self
-                .get(
-                    &path,
-                    proto::v1::View::CurrentValue,
-                    vec![
-                        proto::v1::Field::Value.into(),
-                        proto::v1::Field::Metadata.into(),
-                    ],
-                )
-                .await
-            {
-                Ok(mut entry) => get_result.append(&mut entry),
-                Err(err) => return Err(err),
-            }
-        }
-
-        Ok(get_result)
+        self.get_all(paths, proto::v1::View::CurrentValue).await
     }
 
     async fn subscribe_target_values(
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::ProvideResponseType, ClientError> {
-        let mut client = proto::v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-        let mut entries = Vec::new();
-        for path in paths {
-            entries.push(proto::v1::SubscribeEntry {
-                path: path.to_string(),
-                view: proto::v1::View::TargetValue.into(),
-                fields: vec![proto::v1::Field::ActuatorTarget.into()],
-            })
-        }
-
-        let req = proto::v1::SubscribeRequest { entries };
-
-        match client.subscribe(req).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        self.subscribe_with(Subscription::target_values(paths))
+            .await
     }
 
     async fn get_target_values(
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::GetResponseType, ClientError> {
-        let mut get_result = Vec::new();
-
-        for path in paths {
-            match self
-                .get(
-                    &path,
-                    proto::v1::View::TargetValue,
-                    vec![
-                        proto::v1::Field::ActuatorTarget.into(),
-                        proto::v1::Field::Metadata.into(),
-                    ],
-                )
-                .await
-            {
-                Ok(mut entry) => get_result.append(&mut entry),
-                Err(err) => return Err(err),
-            }
-        }
-
-        Ok(get_result)
+        self.get_all(paths, proto::v1::View::TargetValue).await
     }
 
     async fn subscribe_current_values(
         &mut self,
         paths: Self::SubscribeType,
     ) -> Result<Self::SubscribeResponseType, ClientError> {
-        let mut client = proto::v1::val_client::ValClient::with_interceptor(
-            self.basic_client.get_channel().await?.clone(),
-            self.basic_client.get_auth_interceptor(),
-        );
-
-        let mut entries = Vec::new();
-        for path in paths {
-            entries.push(proto::v1::SubscribeEntry {
-                path: path.to_string(),
-                view: proto::v1::View::CurrentValue.into(),
-                fields: vec![
-                    proto::v1::Field::Value.into(),
-                    proto::v1::Field::Metadata.into(),
-                ],
-            })
-        }
-
-        let req = proto::v1::SubscribeRequest { entries };
-
-        match client.subscribe(req).await {
-            Ok(response) => Ok(response.into_inner()),
-            Err(err) => Err(ClientError::Status(err)),
-        }
+        self.subscribe_with(Subscription::current_values(paths))
+            .await
     }
 
     async fn subscribe(
@@ -336,52 +541,75 @@ impl kuksa_common::ClientTraitV1 for KuksaClient {
         &mut self,
         datapoints: Self::UpdateActuationType,
     ) -> Result<Self::ActuateResponseType, ClientError> {
-        for (path, datapoint) in datapoints {
-            match self
-                .set(
-                    proto::v1::DataEntry {
-                        path: path.clone(),
-                        value: None,
-                        actuator_target: Some(datapoint),
-                        metadata: None,
-                    },
-                    vec![
-                        proto::v1::Field::ActuatorTarget.into(),
-                        proto::v1::Field::Path.into(),
-                    ],
-                )
-                .await
-            {
-                Ok(_) => {
-                    continue;
-                }
-                Err(err) => return Err(err),
-            }
-        }
-
-        Ok(())
+        let updates = datapoints.into_iter().map(target_value_update).collect();
+        self.set_all(updates).await
     }
 
     async fn get_metadata(
         &mut self,
         paths: Self::PathType,
     ) -> Result<Self::MetadataResponseType, ClientError> {
-        let mut metadata_result = Vec::new();
-
-        for path in paths {
-            match self
-                .get(
-                    &path,
-                    proto::v1::View::Metadata,
-                    vec![proto::v1::Field::Metadata.into()],
-                )
-                .await
-            {
-                Ok(mut entry) => metadata_result.append(&mut entry),
-                Err(err) => return Err(err),
-            }
+        self.get_all(paths, proto::v1::View::Metadata).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(code: u32) -> proto::v1::Error {
+        proto::v1::Error {
+            code,
+            reason: "test".to_string(),
+            message: "test".to_string(),
         }
+    }
+
+    fn entry_error(path: &str, code: u32) -> proto::v1::DataEntryError {
+        proto::v1::DataEntryError {
+            path: path.to_string(),
+            error: Some(error(code)),
+        }
+    }
 
-        Ok(metadata_result)
+    #[test]
+    fn test_get_results() {
+        let results = get_results(proto::v1::GetResponse {
+            entries: vec![DataEntry {
+                path: "Vehicle.Speed".to_string(),
+                value: None,
+                actuator_target: None,
+                metadata: None,
+            }],
+            errors: vec![entry_error("Vehicle.Unknown", 404)],
+            error: Some(error(404)),
+        })
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results["Vehicle.Speed"].is_ok());
+        assert_eq!(results["Vehicle.Unknown"].as_ref().unwrap_err().code, 404);
+
+        // An error of the request as a whole
+        let result = get_results(proto::v1::GetResponse {
+            entries: vec![],
+            errors: vec![],
+            error: Some(error(403)),
+        });
+        assert_eq!(result.unwrap_err().code, 403);
+    }
+
+    #[test]
+    fn test_set_results() {
+        let results = set_results(
+            vec!["Vehicle.Speed".to_string(), "Vehicle.Width".to_string()],
+            proto::v1::SetResponse {
+                error: Some(error(400)),
+                errors: vec![entry_error("Vehicle.Width", 400)],
+            },
+        )
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results["Vehicle.Speed"].is_ok());
+        assert_eq!(results["Vehicle.Width"].as_ref().unwrap_err().code, 400);
     }
 }
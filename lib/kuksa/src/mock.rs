@@ -0,0 +1,500 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! In-memory implementation of [`KuksaClientApi`] for unit tests.
+//!
+//! A `MockClient` keeps values, actuator targets and metadata in memory and
+//! answers requests like the broker would. Clones share the same state, so
+//! a test can hand one clone to the code under test and use another one to
+//! program responses and inspect what was called:
+//!
+//! ```
+//! # use kuksa::{api::KuksaClientApi, mock::MockClient, proto::v1::datapoint::Value};
+//! # async fn example() {
+//! let mock = MockClient::new();
+//! mock.set_current_value("Vehicle.Speed", Value::Float(42.0));
+//!
+//! let mut client = mock.clone();
+//! let entries = client.get_current_values(vec!["Vehicle.Speed".to_owned()]).await.unwrap();
+//! assert_eq!(entries[0].value.as_ref().unwrap().value, Some(Value::Float(42.0)));
+//! # }
+//! ```
+
+// ClientError embeds a tonic::Status, which is large by design
+#![allow(clippy::result_large_err)]
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use kuksa_common::types::{PathTypeV1, SensorUpdateTypeV1, UpdateActuationTypeV1};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::async_trait;
+
+use crate::api::{KuksaClientApi, SubscribeStream};
+use crate::subscription::{Field, View as SubscribeView};
+use crate::{proto, ClientError, DataEntry, ResponseError, Subscription};
+
+/// A call made through [`KuksaClientApi`], as recorded by [`MockClient`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockCall {
+    GetCurrentValues(PathTypeV1),
+    GetTargetValues(PathTypeV1),
+    GetMetadata(PathTypeV1),
+    SetCurrentValues(SensorUpdateTypeV1),
+    SetTargetValues(UpdateActuationTypeV1),
+    SubscribeCurrentValues(PathTypeV1),
+    SubscribeTargetValues(PathTypeV1),
+    Subscribe(Subscription),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum View {
+    Current,
+    Target,
+}
+
+struct Subscriber {
+    entries: Vec<proto::v1::SubscribeEntry>,
+    sender: mpsc::UnboundedSender<Result<proto::v1::SubscribeResponse, tonic::Status>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    entries: HashMap<String, DataEntry>,
+    errors: VecDeque<ClientError>,
+    calls: Vec<MockCall>,
+    subscribers: Vec<Subscriber>,
+}
+
+#[derive(Clone, Default)]
+pub struct MockClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+fn not_found(path: &str) -> ClientError {
+    ClientError::Function(vec![ResponseError {
+        path: Some(path.to_owned()),
+        code: 404,
+        reason: "not_found".to_owned(),
+        message: format!("No entries found for the provided path {path}"),
+    }])
+}
+
+fn datapoint(value: proto::v1::datapoint::Value) -> proto::v1::Datapoint {
+    proto::v1::Datapoint {
+        timestamp: None,
+        value: Some(value),
+    }
+}
+
+/// Whether a subscription entry receives the changes of `view`.
+fn wants(entry: &proto::v1::SubscribeEntry, view: View) -> bool {
+    let (entry_view, field) = match view {
+        View::Current => (SubscribeView::CurrentValue, Field::Value),
+        View::Target => (SubscribeView::TargetValue, Field::ActuatorTarget),
+    };
+    [entry_view as i32, SubscribeView::All as i32].contains(&entry.view)
+        || entry.fields.contains(&(field as i32))
+}
+
+impl MockState {
+    fn entry(&mut self, path: &str) -> &mut DataEntry {
+        self.entries
+            .entry(path.to_owned())
+            .or_insert_with(|| DataEntry {
+                path: path.to_owned(),
+                value: None,
+                actuator_target: None,
+                metadata: None,
+            })
+    }
+
+    fn update(&mut self, path: &str, view: View, datapoint: proto::v1::Datapoint) {
+        let entry = self.entry(path);
+        match view {
+            View::Current => entry.value = Some(datapoint),
+            View::Target => entry.actuator_target = Some(datapoint),
+        }
+        let entry = entry.clone();
+
+        let (field, reason) = match view {
+            View::Current => (
+                proto::v1::Field::Value,
+                proto::v1::UpdateReason::ValueChanged,
+            ),
+            View::Target => (
+                proto::v1::Field::ActuatorTarget,
+                proto::v1::UpdateReason::TargetChanged,
+            ),
+        };
+        self.subscribers.retain(|subscriber| {
+            if !subscriber
+                .entries
+                .iter()
+                .any(|entry| entry.path == path && wants(entry, view))
+            {
+                return !subscriber.sender.is_closed();
+            }
+            let response = proto::v1::SubscribeResponse {
+                updates: vec![proto::v1::EntryUpdate {
+                    entry: Some(entry.clone()),
+                    fields: vec![field.into()],
+                }],
+                reasons: vec![reason.into()],
+            };
+            subscriber.sender.send(Ok(response)).is_ok()
+        });
+    }
+
+    fn get(
+        &self,
+        paths: &PathTypeV1,
+        select: impl Fn(&DataEntry) -> DataEntry,
+    ) -> Result<Vec<DataEntry>, ClientError> {
+        let mut result = Vec::new();
+        for path in paths {
+            // Like the broker, a branch path selects all entries below it
+            let branch = format!("{path}.");
+            let mut found: Vec<&DataEntry> = self
+                .entries
+                .values()
+                .filter(|entry| entry.path == *path || entry.path.starts_with(&branch))
+                .collect();
+            if found.is_empty() {
+                return Err(not_found(path));
+            }
+            found.sort_by(|a, b| a.path.cmp(&b.path));
+            result.extend(found.into_iter().map(&select));
+        }
+        Ok(result)
+    }
+}
+
+impl MockClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock state lock poisoned")
+    }
+
+    /// Set the current value of `path`, notifying subscribers.
+    pub fn set_current_value(&self, path: &str, value: proto::v1::datapoint::Value) {
+        self.state().update(path, View::Current, datapoint(value));
+    }
+
+    /// Set the actuator target of `path`, notifying subscribers.
+    pub fn set_target_value(&self, path: &str, value: proto::v1::datapoint::Value) {
+        self.state().update(path, View::Target, datapoint(value));
+    }
+
+    /// End all subscription streams, like a lost connection would.
+    pub fn disconnect_subscribers(&self) {
+        self.state().subscribers.clear();
+    }
+
+    pub fn set_metadata(&self, path: &str, metadata: proto::v1::Metadata) {
+        self.state().entry(path).metadata = Some(metadata);
+    }
+
+    /// Make the next call fail with `error`. Queued errors are returned in
+    /// order, one per call.
+    pub fn push_error(&self, error: ClientError) {
+        self.state().errors.push_back(error);
+    }
+
+    pub fn current_value(&self, path: &str) -> Option<proto::v1::Datapoint> {
+        self.state()
+            .entries
+            .get(path)
+            .and_then(|entry| entry.value.clone())
+    }
+
+    pub fn target_value(&self, path: &str) -> Option<proto::v1::Datapoint> {
+        self.state()
+            .entries
+            .get(path)
+            .and_then(|entry| entry.actuator_target.clone())
+    }
+
+    /// All calls made so far, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state().calls.clone()
+    }
+
+    fn begin(&self, call: MockCall) -> Result<MutexGuard<'_, MockState>, ClientError> {
+        let mut state = self.state();
+        state.calls.push(call);
+        match state.errors.pop_front() {
+            Some(error) => Err(error),
+            None => Ok(state),
+        }
+    }
+
+    fn set(
+        &self,
+        call: MockCall,
+        datapoints: HashMap<String, proto::v1::Datapoint>,
+        view: View,
+    ) -> Result<(), ClientError> {
+        let mut state = self.begin(call)?;
+        for (path, datapoint) in datapoints {
+            state.update(&path, view, datapoint);
+        }
+        Ok(())
+    }
+
+    fn subscribe(
+        &self,
+        call: MockCall,
+        subscription: Subscription,
+        snapshot: bool,
+    ) -> Result<SubscribeStream, ClientError> {
+        let mut state = self.begin(call)?;
+        let entries = subscription.entries().to_vec();
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| !state.entries.contains_key(&entry.path))
+        {
+            return Err(not_found(&entry.path));
+        }
+        let (sender, receiver) = mpsc::unbounded_channel();
+        if snapshot {
+            let updates = entries
+                .iter()
+                .map(|subscribed| {
+                    let entry = &state.entries[&subscribed.path];
+                    proto::v1::EntryUpdate {
+                        entry: Some(DataEntry {
+                            path: entry.path.clone(),
+                            value: wants(subscribed, View::Current)
+                                .then(|| entry.value.clone())
+                                .flatten(),
+                            actuator_target: wants(subscribed, View::Target)
+                                .then(|| entry.actuator_target.clone())
+                                .flatten(),
+                            metadata: None,
+                        }),
+                        fields: subscribed.fields.clone(),
+                    }
+                })
+                .collect();
+            let response = proto::v1::SubscribeResponse {
+                updates,
+                reasons: Vec::new(),
+            };
+            sender.send(Ok(response)).expect("receiver should exist");
+        }
+        state.subscribers.push(Subscriber { entries, sender });
+        Ok(Box::pin(UnboundedReceiverStream::new(receiver)))
+    }
+}
+
+#[cfg_attr(all(feature = "grpc-web", target_arch = "wasm32"), async_trait(?Send))]
+#[cfg_attr(not(all(feature = "grpc-web", target_arch = "wasm32")), async_trait)]
+impl KuksaClientApi for MockClient {
+    async fn get_current_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<Vec<DataEntry>, ClientError> {
+        let state = self.begin(MockCall::GetCurrentValues(paths.clone()))?;
+        state.get(&paths, |entry| DataEntry {
+            actuator_target: None,
+            ..entry.clone()
+        })
+    }
+
+    async fn get_target_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<Vec<DataEntry>, ClientError> {
+        let state = self.begin(MockCall::GetTargetValues(paths.clone()))?;
+        state.get(&paths, |entry| DataEntry {
+            value: None,
+            ..entry.clone()
+        })
+    }
+
+    async fn get_metadata(&mut self, paths: PathTypeV1) -> Result<Vec<DataEntry>, ClientError> {
+        let state = self.begin(MockCall::GetMetadata(paths.clone()))?;
+        state.get(&paths, |entry| DataEntry {
+            path: entry.path.clone(),
+            value: None,
+            actuator_target: None,
+            metadata: entry.metadata.clone(),
+        })
+    }
+
+    async fn set_current_values(
+        &mut self,
+        datapoints: SensorUpdateTypeV1,
+    ) -> Result<(), ClientError> {
+        self.set(
+            MockCall::SetCurrentValues(datapoints.clone()),
+            datapoints,
+            View::Current,
+        )
+    }
+
+    async fn set_target_values(
+        &mut self,
+        datapoints: UpdateActuationTypeV1,
+    ) -> Result<(), ClientError> {
+        self.set(
+            MockCall::SetTargetValues(datapoints.clone()),
+            datapoints,
+            View::Target,
+        )
+    }
+
+    async fn subscribe_current_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<SubscribeStream, ClientError> {
+        self.subscribe(
+            MockCall::SubscribeCurrentValues(paths.clone()),
+            Subscription::current_values(paths),
+            false,
+        )
+    }
+
+    async fn subscribe_target_values(
+        &mut self,
+        paths: PathTypeV1,
+    ) -> Result<SubscribeStream, ClientError> {
+        self.subscribe(
+            MockCall::SubscribeTargetValues(paths.clone()),
+            Subscription::target_values(paths),
+            false,
+        )
+    }
+
+    async fn subscribe_with(
+        &mut self,
+        subscription: Subscription,
+    ) -> Result<SubscribeStream, ClientError> {
+        // Like the broker, send the current state first unless only changes
+        // are asked for
+        let snapshot = !subscription.changes_only();
+        self.subscribe(
+            MockCall::Subscribe(subscription.clone()),
+            subscription,
+            snapshot,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::v1::datapoint::Value;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_get_unknown_path() {
+        let mut client = MockClient::new();
+        let result = client
+            .get_current_values(vec!["Vehicle.Speed".to_owned()])
+            .await;
+        match result {
+            Err(ClientError::Function(errors)) => assert_eq!(errors[0].code, 404),
+            _ => panic!("expected not found error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_and_record_calls() {
+        let mock = MockClient::new();
+        let mut client = mock.clone();
+
+        let datapoints = HashMap::from([(
+            "Vehicle.Body.Trunk.Rear.IsOpen".to_owned(),
+            datapoint(Value::Bool(true)),
+        )]);
+        client.set_target_values(datapoints.clone()).await.unwrap();
+
+        assert_eq!(
+            mock.target_value("Vehicle.Body.Trunk.Rear.IsOpen"),
+            Some(datapoint(Value::Bool(true)))
+        );
+        assert_eq!(mock.current_value("Vehicle.Body.Trunk.Rear.IsOpen"), None);
+        assert_eq!(mock.calls(), vec![MockCall::SetTargetValues(datapoints)]);
+    }
+
+    #[tokio::test]
+    async fn test_programmed_error() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        mock.push_error(ClientError::Connection("unavailable".to_owned()));
+
+        let mut client = mock.clone();
+        let paths = vec!["Vehicle.Speed".to_owned()];
+        assert!(matches!(
+            client.get_current_values(paths.clone()).await,
+            Err(ClientError::Connection(_))
+        ));
+        assert!(client.get_current_values(paths).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        mock.set_current_value("Vehicle.IsMoving", Value::Bool(false));
+
+        let mut client = mock.clone();
+        let mut stream = client
+            .subscribe_current_values(vec!["Vehicle.Speed".to_owned()])
+            .await
+            .unwrap();
+
+        mock.set_current_value("Vehicle.IsMoving", Value::Bool(true));
+        mock.set_target_value("Vehicle.Speed", Value::Float(5.0));
+        mock.set_current_value("Vehicle.Speed", Value::Float(2.0));
+
+        let response = stream.next().await.unwrap().unwrap();
+        let entry = response.updates[0].entry.as_ref().unwrap();
+        assert_eq!(entry.path, "Vehicle.Speed");
+        assert_eq!(entry.value, Some(datapoint(Value::Float(2.0))));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_mixed_views() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        mock.set_target_value("Vehicle.Body.Trunk.Rear.IsOpen", Value::Bool(false));
+
+        let mut client = mock.clone();
+        let subscription = Subscription::builder()
+            .path("Vehicle.Speed")
+            .path_with_view("Vehicle.Body.Trunk.Rear.IsOpen", SubscribeView::TargetValue)
+            .changes_only(true)
+            .build();
+        let mut stream = client.subscribe_with(subscription.clone()).await.unwrap();
+
+        mock.set_current_value("Vehicle.Body.Trunk.Rear.IsOpen", Value::Bool(true));
+        mock.set_target_value("Vehicle.Body.Trunk.Rear.IsOpen", Value::Bool(true));
+        mock.set_current_value("Vehicle.Speed", Value::Float(2.0));
+
+        let response = stream.next().await.unwrap().unwrap();
+        let entry = response.updates[0].entry.as_ref().unwrap();
+        assert_eq!(entry.path, "Vehicle.Body.Trunk.Rear.IsOpen");
+        assert_eq!(entry.actuator_target, Some(datapoint(Value::Bool(true))));
+        let response = stream.next().await.unwrap().unwrap();
+        let entry = response.updates[0].entry.as_ref().unwrap();
+        assert_eq!(entry.path, "Vehicle.Speed");
+        assert_eq!(mock.calls(), vec![MockCall::Subscribe(subscription)]);
+    }
+}
@@ -0,0 +1,403 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Subscriptions that are re-established after the stream was lost.
+//!
+//! A [`ResilientSubscription`] subscribes again whenever the stream ends or
+//! fails with a retryable error, waiting longer after every failed attempt
+//! as set by its [`Backoff`]. Updates sent in between are lost, so the
+//! interruption is reported as [`SubscriptionEvent::Interrupted`], the
+//! subscription being back as [`SubscriptionEvent::Resumed`], and [`Resume`]
+//! decides how the stream continues:
+//!
+//! ```no_run
+//! # async fn example(client: kuksa::KuksaClient) {
+//! use kuksa::resilient::{Resume, ResilientSubscription, SubscriptionEvent};
+//! use kuksa::Subscription;
+//! use tokio_stream::StreamExt;
+//!
+//! let subscription = Subscription::current_values(["Vehicle.Speed"]);
+//! let mut events = ResilientSubscription::new(client, subscription, Resume::Snapshot);
+//! while let Some(Ok(event)) = events.next().await {
+//!     match event {
+//!         SubscriptionEvent::Snapshot(entry) => println!("now {:?}", entry.value),
+//!         SubscriptionEvent::Update(entry) => println!("changed {:?}", entry.value),
+//!         SubscriptionEvent::Interrupted(reason) => println!("gap: {reason}"),
+//!         SubscriptionEvent::Resumed(attempts) => println!("back after {attempts} attempts"),
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::time::Sleep;
+use tokio_stream::Stream;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
+
+use crate::api::{KuksaClientApi, SubscribeStream};
+use crate::{ClientError, DataEntry, Subscription};
+
+pub use kuksa_common::Backoff;
+
+/// How a subscription continues after it was re-established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Send the current state of all entries first, then the updates.
+    Snapshot,
+    /// Only send the updates from then on.
+    DeltasOnly,
+}
+
+// Entries are the common case, boxing them isn't worth it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionEvent {
+    /// State of an entry when the subscription was (re-)established
+    Snapshot(DataEntry),
+    /// Change of an entry while subscribed
+    Update(DataEntry),
+    /// The stream was lost, updates until the next snapshot or update are
+    /// missing.
+    Interrupted(String),
+    /// The subscription was re-established after being interrupted, with
+    /// the number of attempts it took.
+    Resumed(u32),
+}
+
+#[cfg(not(all(feature = "grpc-web", target_arch = "wasm32")))]
+type SubscribeFuture<C> =
+    Pin<Box<dyn Future<Output = (C, Result<SubscribeStream, ClientError>)> + Send>>;
+#[cfg(all(feature = "grpc-web", target_arch = "wasm32"))]
+type SubscribeFuture<C> = Pin<Box<dyn Future<Output = (C, Result<SubscribeStream, ClientError>)>>>;
+
+enum State<C> {
+    Subscribing(SubscribeFuture<C>),
+    Streaming(C, SubscribeStream),
+    Waiting(C, Pin<Box<Sleep>>),
+    Done,
+}
+
+pub struct ResilientSubscription<C> {
+    state: State<C>,
+    subscription: Subscription,
+    resume: Resume,
+    backoff: Backoff,
+    /// Retries since the subscription was last established
+    retries: u32,
+    /// Attempts to subscribe again since then
+    attempts: u32,
+    /// Whether the subscription was interrupted and not resumed yet
+    interrupted: bool,
+    /// Whether the next response is the initial snapshot
+    snapshot_pending: bool,
+    decoded: VecDeque<SubscriptionEvent>,
+    cancelled: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+}
+
+impl<C: KuksaClientApi + 'static> ResilientSubscription<C> {
+    /// Subscribe to `subscription` through `client`. Its `changes_only`
+    /// only applies to the first subscription, `resume` to the following
+    /// ones.
+    pub fn new(client: C, subscription: Subscription, resume: Resume) -> Self {
+        let snapshot_pending = !subscription.changes_only();
+        ResilientSubscription {
+            state: State::Subscribing(Self::subscribe(client, subscription.clone())),
+            subscription,
+            resume,
+            backoff: Backoff::default(),
+            retries: 0,
+            attempts: 0,
+            interrupted: false,
+            snapshot_pending,
+            decoded: VecDeque::new(),
+            cancelled: None,
+        }
+    }
+
+    /// End the stream once `token` is cancelled, without retrying.
+    pub fn cancel_on(mut self, token: CancellationToken) -> Self {
+        self.cancelled = Some(Box::pin(token.cancelled_owned()));
+        self
+    }
+
+    /// Wait `interval` before every attempt to subscribe again, instead of
+    /// backing off.
+    pub fn retry_interval(self, interval: Duration) -> Self {
+        self.backoff(Backoff::fixed(interval))
+    }
+
+    /// Time to wait before the attempts to subscribe again, see
+    /// [`Backoff::default`]. Once its retries are used up, the stream ends
+    /// with the last error.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    fn subscribe(mut client: C, subscription: Subscription) -> SubscribeFuture<C> {
+        Box::pin(async move {
+            let result = client.subscribe_with(subscription).await;
+            (client, result)
+        })
+    }
+
+    fn resubscribe(&mut self, client: C) {
+        self.attempts += 1;
+        let changes_only = self.resume == Resume::DeltasOnly;
+        self.snapshot_pending = !changes_only;
+        let subscription = self.subscription.clone().with_changes_only(changes_only);
+        self.state = State::Subscribing(Self::subscribe(client, subscription));
+    }
+
+    /// Retry after the backoff if `err` is retryable, otherwise, or if
+    /// there are no retries left, end the stream with it.
+    fn retry(&mut self, mut client: C, err: ClientError) -> Option<ClientError> {
+        let delay = match err.is_retryable() {
+            true => self.backoff.delay(self.retries + 1),
+            false => None,
+        };
+        if let Some(delay) = delay {
+            if matches!(err, ClientError::Connection(_)) {
+                client.reset_connection();
+            }
+            self.retries += 1;
+            let sleep = Box::pin(tokio::time::sleep(delay));
+            self.state = State::Waiting(client, sleep);
+            None
+        } else {
+            self.state = State::Done;
+            Some(err)
+        }
+    }
+}
+
+impl<C: KuksaClientApi + Unpin + 'static> Stream for ResilientSubscription<C> {
+    type Item = Result<SubscriptionEvent, ClientError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(cancelled) = &mut this.cancelled {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                this.cancelled = None;
+                this.decoded.clear();
+                this.state = State::Done;
+            }
+        }
+        loop {
+            if let Some(event) = this.decoded.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            match std::mem::replace(&mut this.state, State::Done) {
+                State::Subscribing(mut future) => match future.as_mut().poll(cx) {
+                    Poll::Ready((client, Ok(stream))) => {
+                        let attempts = std::mem::take(&mut this.attempts);
+                        this.retries = 0;
+                        this.state = State::Streaming(client, stream);
+                        if std::mem::take(&mut this.interrupted) {
+                            return Poll::Ready(Some(Ok(SubscriptionEvent::Resumed(attempts))));
+                        }
+                    }
+                    Poll::Ready((client, Err(err))) => {
+                        if let Some(err) = this.retry(client, err) {
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                    }
+                    Poll::Pending => {
+                        this.state = State::Subscribing(future);
+                        return Poll::Pending;
+                    }
+                },
+                State::Streaming(client, mut stream) => match stream.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(Ok(response))) => {
+                        let snapshot = std::mem::take(&mut this.snapshot_pending);
+                        this.decoded
+                            .extend(response.updates.into_iter().filter_map(|update| {
+                                let entry = update.entry?;
+                                Some(match snapshot {
+                                    true => SubscriptionEvent::Snapshot(entry),
+                                    false => SubscriptionEvent::Update(entry),
+                                })
+                            }));
+                        this.state = State::Streaming(client, stream);
+                    }
+                    Poll::Ready(Some(Err(status))) => {
+                        let reason = status.to_string();
+                        let err = match connection_lost(&status) {
+                            true => ClientError::Connection(reason.clone()),
+                            false => ClientError::Status(status),
+                        };
+                        if let Some(err) = this.retry(client, err) {
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        this.interrupted = true;
+                        return Poll::Ready(Some(Ok(SubscriptionEvent::Interrupted(reason))));
+                    }
+                    Poll::Ready(None) => {
+                        this.resubscribe(client);
+                        this.interrupted = true;
+                        let reason = "stream ended".to_owned();
+                        return Poll::Ready(Some(Ok(SubscriptionEvent::Interrupted(reason))));
+                    }
+                    Poll::Pending => {
+                        this.state = State::Streaming(client, stream);
+                        return Poll::Pending;
+                    }
+                },
+                State::Waiting(client, mut sleep) => match sleep.as_mut().poll(cx) {
+                    Poll::Ready(()) => this.resubscribe(client),
+                    Poll::Pending => {
+                        this.state = State::Waiting(client, sleep);
+                        return Poll::Pending;
+                    }
+                },
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Whether the stream failed in the transport, e.g. because the broker went
+/// away, rather than with a status sent by the broker.
+fn connection_lost(status: &tonic::Status) -> bool {
+    status.code() == tonic::Code::Unknown && std::error::Error::source(status).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockClient;
+    use crate::proto::v1::datapoint::Value;
+    use tokio_stream::StreamExt;
+
+    fn value(event: SubscriptionEvent) -> (&'static str, Option<Value>) {
+        match event {
+            SubscriptionEvent::Snapshot(entry) => ("snapshot", entry.value.and_then(|dp| dp.value)),
+            SubscriptionEvent::Update(entry) => ("update", entry.value.and_then(|dp| dp.value)),
+            SubscriptionEvent::Interrupted(_) => ("interrupted", None),
+            SubscriptionEvent::Resumed(_) => ("resumed", None),
+        }
+    }
+
+    async fn next(events: &mut ResilientSubscription<MockClient>) -> (&'static str, Option<Value>) {
+        value(events.next().await.unwrap().unwrap())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resume_with_snapshot() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        let subscription = Subscription::current_values(["Vehicle.Speed"]);
+        let mut events = ResilientSubscription::new(mock.clone(), subscription, Resume::Snapshot);
+
+        assert_eq!(
+            next(&mut events).await,
+            ("snapshot", Some(Value::Float(1.0)))
+        );
+        mock.set_current_value("Vehicle.Speed", Value::Float(2.0));
+        assert_eq!(next(&mut events).await, ("update", Some(Value::Float(2.0))));
+
+        mock.disconnect_subscribers();
+        mock.set_current_value("Vehicle.Speed", Value::Float(3.0));
+        assert_eq!(next(&mut events).await, ("interrupted", None));
+        assert_eq!(next(&mut events).await, ("resumed", None));
+        assert_eq!(
+            next(&mut events).await,
+            ("snapshot", Some(Value::Float(3.0)))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resume_with_deltas_only() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        let subscription = Subscription::current_values(["Vehicle.Speed"]);
+        let mut events = ResilientSubscription::new(mock.clone(), subscription, Resume::DeltasOnly);
+        assert_eq!(
+            next(&mut events).await,
+            ("snapshot", Some(Value::Float(1.0)))
+        );
+
+        mock.disconnect_subscribers();
+        mock.push_error(ClientError::Connection("refused".to_owned()));
+        assert_eq!(next(&mut events).await, ("interrupted", None));
+        // The failed and the successful attempt
+        assert_eq!(
+            events.next().await.unwrap().unwrap(),
+            SubscriptionEvent::Resumed(2)
+        );
+
+        let poll = tokio::time::timeout(Duration::from_secs(5), events.next()).await;
+        assert!(poll.is_err(), "no snapshot expected");
+
+        mock.set_current_value("Vehicle.Speed", Value::Float(4.0));
+        assert_eq!(next(&mut events).await, ("update", Some(Value::Float(4.0))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        let token = CancellationToken::new();
+        let subscription = Subscription::current_values(["Vehicle.Speed"]);
+        let mut events = ResilientSubscription::new(mock.clone(), subscription, Resume::Snapshot)
+            .cancel_on(token.clone());
+        assert_eq!(
+            next(&mut events).await,
+            ("snapshot", Some(Value::Float(1.0)))
+        );
+
+        mock.disconnect_subscribers();
+        mock.push_error(ClientError::Connection("refused".to_owned()));
+        assert_eq!(next(&mut events).await, ("interrupted", None));
+        token.cancel();
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_retries_ends_stream() {
+        let mock = MockClient::new();
+        mock.set_current_value("Vehicle.Speed", Value::Float(1.0));
+        let subscription = Subscription::current_values(["Vehicle.Speed"]);
+        let mut events = ResilientSubscription::new(mock.clone(), subscription, Resume::Snapshot)
+            .backoff(Backoff::fixed(Duration::from_secs(1)).max_retries(2));
+        assert_eq!(
+            next(&mut events).await,
+            ("snapshot", Some(Value::Float(1.0)))
+        );
+
+        mock.disconnect_subscribers();
+        for _ in 0..3 {
+            mock.push_error(ClientError::Connection("refused".to_owned()));
+        }
+        assert_eq!(next(&mut events).await, ("interrupted", None));
+        let err = events.next().await.unwrap().unwrap_err();
+        assert!(matches!(err, ClientError::Connection(_)));
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_ends_stream() {
+        let mock = MockClient::new();
+        let subscription = Subscription::current_values(["Vehicle.Speed"]);
+        let mut events = ResilientSubscription::new(mock, subscription, Resume::Snapshot);
+
+        let err = events.next().await.unwrap().unwrap_err();
+        assert_eq!(err.code(), crate::ErrorCode::NotFound);
+        assert!(events.next().await.is_none());
+    }
+}
@@ -0,0 +1,543 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Adapters for subscription streams.
+//!
+//! [`SubscribeStreamExt::entries`] decodes the responses of a subscription
+//! into one item per updated entry, the combinators of [`EntryStreamExt`]
+//! work on these:
+//!
+//! ```no_run
+//! # async fn example(mut client: kuksa::KuksaClient) -> Result<(), kuksa::ClientError> {
+//! use std::time::Duration;
+//!
+//! use kuksa::api::KuksaClientApi;
+//! use kuksa::stream::{EntryStreamExt, SubscribeStreamExt};
+//! use tokio_stream::StreamExt;
+//!
+//! let mut speed = client
+//!     .subscribe_current_values(vec!["Vehicle.Speed".to_owned()])
+//!     .await?
+//!     .entries()
+//!     .changed_only()
+//!     .sampled(Duration::from_millis(100));
+//! while let Some(Ok(entry)) = speed.next().await {
+//!     println!("{:?}", entry.value);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Errors of the underlying stream are passed through as they are.
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
+use tokio_stream::Stream;
+
+use crate::{proto, DataEntry};
+
+type EntryResult = Result<DataEntry, tonic::Status>;
+
+pub trait SubscribeStreamExt:
+    Stream<Item = Result<proto::v1::SubscribeResponse, tonic::Status>> + Sized + Unpin
+{
+    /// Yield every entry of the received updates on its own.
+    fn entries(self) -> Entries<Self> {
+        Entries {
+            inner: self,
+            decoded: VecDeque::new(),
+        }
+    }
+}
+
+impl<S> SubscribeStreamExt for S where
+    S: Stream<Item = Result<proto::v1::SubscribeResponse, tonic::Status>> + Unpin
+{
+}
+
+pub trait EntryStreamExt: Stream<Item = EntryResult> + Sized + Unpin {
+    /// Suppress entries whose value and actuator target are the same as in
+    /// the previous entry of the same path. Timestamps are not compared.
+    fn changed_only(self) -> ChangedOnly<Self> {
+        ChangedOnly {
+            inner: self,
+            last: HashMap::new(),
+        }
+    }
+
+    /// Yield at most one entry per path and `period`, the most recent one.
+    ///
+    /// Entries are held back until the end of the period they were
+    /// received in.
+    fn sampled(self, period: Duration) -> Sampled<Self> {
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Sampled {
+            inner: self,
+            interval,
+            pending: Pending::default(),
+            ready: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Conflate entries received while the consumer is busy, so that only
+    /// the most recent entry of each path is yielded.
+    fn latest(self) -> Latest<Self> {
+        Latest {
+            inner: self,
+            pending: Pending::default(),
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Report a path as stale when no entry was received for it within
+    /// `timeout` of its previous entry.
+    ///
+    /// Paths are only watched after their first entry, which a
+    /// subscription normally yields right away.
+    fn timeout_as_stale(self, timeout: Duration) -> TimeoutAsStale<Self> {
+        TimeoutAsStale {
+            inner: self,
+            timeout,
+            deadlines: HashMap::new(),
+            sleep: Box::pin(tokio::time::sleep(timeout)),
+        }
+    }
+}
+
+impl<S> EntryStreamExt for S where S: Stream<Item = EntryResult> + Unpin {}
+
+/// Item of [`EntryStreamExt::timeout_as_stale`].
+// Fresh entries are the common case, boxing them isn't worth it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Freshness {
+    Fresh(DataEntry),
+    /// No entry was received for this path within the timeout.
+    Stale(String),
+}
+
+pub struct Entries<S> {
+    inner: S,
+    decoded: VecDeque<DataEntry>,
+}
+
+impl<S> Stream for Entries<S>
+where
+    S: Stream<Item = Result<proto::v1::SubscribeResponse, tonic::Status>> + Unpin,
+{
+    type Item = EntryResult;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(entry) = this.decoded.pop_front() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(response))) => this.decoded.extend(
+                    response
+                        .updates
+                        .into_iter()
+                        .filter_map(|update| update.entry),
+                ),
+                Poll::Ready(Some(Err(status))) => return Poll::Ready(Some(Err(status))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+pub struct ChangedOnly<S> {
+    inner: S,
+    last: HashMap<String, Values>,
+}
+
+/// Value and actuator target, without timestamps.
+type Values = (
+    Option<proto::v1::datapoint::Value>,
+    Option<proto::v1::datapoint::Value>,
+);
+
+impl<S> Stream for ChangedOnly<S>
+where
+    S: Stream<Item = EntryResult> + Unpin,
+{
+    type Item = EntryResult;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => {
+                    let values = (
+                        entry.value.as_ref().and_then(|dp| dp.value.clone()),
+                        entry
+                            .actuator_target
+                            .as_ref()
+                            .and_then(|dp| dp.value.clone()),
+                    );
+                    if this.last.get(&entry.path) != Some(&values) {
+                        this.last.insert(entry.path.clone(), values);
+                        return Poll::Ready(Some(Ok(entry)));
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Most recent entry per path, in the order the paths were first received.
+#[derive(Default)]
+struct Pending {
+    order: VecDeque<String>,
+    entries: HashMap<String, DataEntry>,
+}
+
+impl Pending {
+    fn insert(&mut self, entry: DataEntry) {
+        if !self.entries.contains_key(&entry.path) {
+            self.order.push_back(entry.path.clone());
+        }
+        self.entries.insert(entry.path.clone(), entry);
+    }
+
+    fn pop_front(&mut self) -> Option<DataEntry> {
+        let path = self.order.pop_front()?;
+        self.entries.remove(&path)
+    }
+
+    fn take_all(&mut self) -> VecDeque<DataEntry> {
+        let mut all = VecDeque::with_capacity(self.order.len());
+        while let Some(entry) = self.pop_front() {
+            all.push_back(entry);
+        }
+        all
+    }
+}
+
+pub struct Sampled<S> {
+    inner: S,
+    interval: Interval,
+    pending: Pending,
+    ready: VecDeque<DataEntry>,
+    done: bool,
+}
+
+impl<S> Stream for Sampled<S>
+where
+    S: Stream<Item = EntryResult> + Unpin,
+{
+    type Item = EntryResult;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(entry) = this.ready.pop_front() {
+                return Poll::Ready(Some(Ok(entry)));
+            }
+            while !this.done {
+                match Pin::new(&mut this.inner).poll_next(cx) {
+                    Poll::Ready(Some(Ok(entry))) => this.pending.insert(entry),
+                    Poll::Ready(Some(Err(status))) => return Poll::Ready(Some(Err(status))),
+                    Poll::Ready(None) => this.done = true,
+                    Poll::Pending => break,
+                }
+            }
+            if this.done {
+                // Flush what is left without waiting for the period to end
+                this.ready = this.pending.take_all();
+                if this.ready.is_empty() {
+                    return Poll::Ready(None);
+                }
+                continue;
+            }
+            match this.interval.poll_tick(cx) {
+                Poll::Ready(_) => this.ready = this.pending.take_all(),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+pub struct Latest<S> {
+    inner: S,
+    pending: Pending,
+    error: Option<tonic::Status>,
+    done: bool,
+}
+
+impl<S> Stream for Latest<S>
+where
+    S: Stream<Item = EntryResult> + Unpin,
+{
+    type Item = EntryResult;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        // Take everything that is available right now, keeping only the
+        // most recent entry per path
+        while !this.done && this.error.is_none() {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => this.pending.insert(entry),
+                Poll::Ready(Some(Err(status))) => this.error = Some(status),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+        if let Some(entry) = this.pending.pop_front() {
+            Poll::Ready(Some(Ok(entry)))
+        } else if let Some(status) = this.error.take() {
+            Poll::Ready(Some(Err(status)))
+        } else if this.done {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pub struct TimeoutAsStale<S> {
+    inner: S,
+    timeout: Duration,
+    deadlines: HashMap<String, Instant>,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl<S> Stream for TimeoutAsStale<S>
+where
+    S: Stream<Item = EntryResult> + Unpin,
+{
+    type Item = Result<Freshness, tonic::Status>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(entry))) => {
+                    this.deadlines
+                        .insert(entry.path.clone(), Instant::now() + this.timeout);
+                    return Poll::Ready(Some(Ok(Freshness::Fresh(entry))));
+                }
+                Poll::Ready(Some(Err(status))) => return Poll::Ready(Some(Err(status))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => {}
+            }
+
+            let now = Instant::now();
+            let expired = this
+                .deadlines
+                .iter()
+                .find(|(_, deadline)| **deadline <= now)
+                .map(|(path, _)| path.clone());
+            if let Some(path) = expired {
+                // Reported once, until the path is updated again
+                this.deadlines.remove(&path);
+                return Poll::Ready(Some(Ok(Freshness::Stale(path))));
+            }
+
+            match this.deadlines.values().min() {
+                Some(next) => {
+                    this.sleep.as_mut().reset(*next);
+                    if this.sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                }
+                None => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proto::v1::datapoint::Value;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+    use tokio_stream::StreamExt;
+
+    fn entry(path: &str, value: i32) -> DataEntry {
+        DataEntry {
+            path: path.to_owned(),
+            value: Some(proto::v1::Datapoint {
+                timestamp: None,
+                value: Some(Value::Int32(value)),
+            }),
+            actuator_target: None,
+            metadata: None,
+        }
+    }
+
+    fn value(entry: &DataEntry) -> Option<Value> {
+        entry.value.as_ref().and_then(|dp| dp.value.clone())
+    }
+
+    fn response(entries: Vec<DataEntry>) -> proto::v1::SubscribeResponse {
+        proto::v1::SubscribeResponse {
+            updates: entries
+                .into_iter()
+                .map(|entry| proto::v1::EntryUpdate {
+                    entry: Some(entry),
+                    fields: vec![proto::v1::Field::Value.into()],
+                })
+                .collect(),
+            reasons: vec![proto::v1::UpdateReason::ValueChanged.into()],
+        }
+    }
+
+    fn channel() -> (
+        mpsc::UnboundedSender<EntryResult>,
+        UnboundedReceiverStream<EntryResult>,
+    ) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (sender, UnboundedReceiverStream::new(receiver))
+    }
+
+    #[tokio::test]
+    async fn test_entries() {
+        let responses = tokio_stream::iter(vec![
+            Ok(response(vec![entry("A", 1), entry("B", 2)])),
+            Err(tonic::Status::unavailable("gone")),
+        ]);
+
+        let items: Vec<_> = responses.entries().collect().await;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap().path, "A");
+        assert_eq!(items[1].as_ref().unwrap().path, "B");
+        assert_eq!(
+            items[2].as_ref().unwrap_err().code(),
+            tonic::Code::Unavailable
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changed_only() {
+        let entries = tokio_stream::iter(vec![
+            Ok(entry("A", 1)),
+            Ok(entry("A", 1)),
+            Ok(entry("B", 1)),
+            Ok(entry("A", 2)),
+            Ok(entry("A", 2)),
+            Ok(entry("A", 1)),
+        ]);
+
+        let changed: Vec<_> = entries
+            .changed_only()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.path.clone(), value(&entry))
+            })
+            .collect()
+            .await;
+        assert_eq!(
+            changed,
+            vec![
+                ("A".to_owned(), Some(Value::Int32(1))),
+                ("B".to_owned(), Some(Value::Int32(1))),
+                ("A".to_owned(), Some(Value::Int32(2))),
+                ("A".to_owned(), Some(Value::Int32(1))),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sampled() {
+        let (sender, entries) = channel();
+        let mut sampled = entries.sampled(Duration::from_millis(100));
+
+        sender.send(Ok(entry("A", 1))).unwrap();
+        sender.send(Ok(entry("B", 1))).unwrap();
+        sender.send(Ok(entry("A", 2))).unwrap();
+
+        // Nothing before the period ends
+        let start = Instant::now();
+        let first = sampled.next().await.unwrap().unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+        assert_eq!(
+            (first.path.as_str(), value(&first)),
+            ("A", Some(Value::Int32(2)))
+        );
+        let second = sampled.next().await.unwrap().unwrap();
+        assert_eq!(second.path, "B");
+
+        // Flushed when the stream ends
+        sender.send(Ok(entry("A", 3))).unwrap();
+        drop(sender);
+        let last = sampled.next().await.unwrap().unwrap();
+        assert_eq!(value(&last), Some(Value::Int32(3)));
+        assert!(sampled.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_latest() {
+        let (sender, entries) = channel();
+        let mut latest = entries.latest();
+
+        sender.send(Ok(entry("A", 1))).unwrap();
+        sender.send(Ok(entry("B", 1))).unwrap();
+        sender.send(Ok(entry("A", 2))).unwrap();
+        sender
+            .send(Err(tonic::Status::unavailable("gone")))
+            .unwrap();
+        drop(sender);
+
+        let first = latest.next().await.unwrap().unwrap();
+        assert_eq!(
+            (first.path.as_str(), value(&first)),
+            ("A", Some(Value::Int32(2)))
+        );
+        let second = latest.next().await.unwrap().unwrap();
+        assert_eq!(second.path, "B");
+        assert!(latest.next().await.unwrap().is_err());
+        assert!(latest.next().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout_as_stale() {
+        let (sender, entries) = channel();
+        let mut stale = entries.timeout_as_stale(Duration::from_secs(1));
+
+        sender.send(Ok(entry("A", 1))).unwrap();
+        assert!(matches!(
+            stale.next().await.unwrap().unwrap(),
+            Freshness::Fresh(_)
+        ));
+
+        let start = Instant::now();
+        assert_eq!(
+            stale.next().await.unwrap().unwrap(),
+            Freshness::Stale("A".to_owned())
+        );
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        // Not reported again until updated
+        sender.send(Ok(entry("A", 2))).unwrap();
+        assert!(matches!(
+            stale.next().await.unwrap().unwrap(),
+            Freshness::Fresh(_)
+        ));
+        drop(sender);
+        assert!(stale.next().await.is_none());
+    }
+}
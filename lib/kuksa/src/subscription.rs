@@ -0,0 +1,215 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Composing subscriptions.
+//!
+//! A [`Subscription`] lists the paths to subscribe to, each with its own view
+//! and fields, so that current values and actuator targets can be received
+//! through one stream:
+//!
+//! ```no_run
+//! # async fn example(mut client: kuksa::KuksaClient) -> Result<(), kuksa::ClientError> {
+//! use kuksa::subscription::{Field, Subscription, View};
+//!
+//! let subscription = Subscription::builder()
+//!     .path("Vehicle.Speed")
+//!     .fields([Field::Value])
+//!     .path_with_view("Vehicle.Body.Trunk.Rear.IsOpen", View::TargetValue)
+//!     .build();
+//! let stream = client.subscribe_with(subscription).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::proto;
+
+pub use proto::v1::{Field, View};
+
+/// The entries of a subscription, see [`Subscription::builder`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Subscription {
+    entries: Vec<proto::v1::SubscribeEntry>,
+    changes_only: bool,
+}
+
+impl Subscription {
+    pub fn builder() -> SubscriptionBuilder {
+        SubscriptionBuilder::default()
+    }
+
+    /// Values and metadata of `paths`.
+    pub fn current_values(paths: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        paths
+            .into_iter()
+            .fold(Self::builder(), SubscriptionBuilder::path)
+            .build()
+    }
+
+    /// Actuator targets of `paths`.
+    pub fn target_values(paths: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        paths
+            .into_iter()
+            .fold(Self::builder(), |builder, path| {
+                builder.path_with_view(path, View::TargetValue)
+            })
+            .build()
+    }
+
+    pub fn entries(&self) -> &[proto::v1::SubscribeEntry] {
+        &self.entries
+    }
+
+    pub fn changes_only(&self) -> bool {
+        self.changes_only
+    }
+
+    pub(crate) fn with_changes_only(mut self, changes_only: bool) -> Self {
+        self.changes_only = changes_only;
+        self
+    }
+}
+
+impl From<Subscription> for proto::v1::SubscribeRequest {
+    fn from(subscription: Subscription) -> Self {
+        proto::v1::SubscribeRequest {
+            entries: subscription.entries,
+            changes_only: subscription.changes_only,
+        }
+    }
+}
+
+/// The fields requested when none are given explicitly.
+fn default_fields(view: View) -> Vec<Field> {
+    match view {
+        View::CurrentValue => vec![Field::Value, Field::Metadata],
+        View::TargetValue => vec![Field::ActuatorTarget],
+        View::Metadata => vec![Field::Metadata],
+        View::All => vec![Field::Value, Field::ActuatorTarget, Field::Metadata],
+        View::Unspecified | View::Fields => Vec::new(),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SubscriptionBuilder {
+    entries: Vec<(String, View, Option<Vec<Field>>)>,
+    fields: Option<Vec<Field>>,
+    changes_only: bool,
+}
+
+impl SubscriptionBuilder {
+    /// Subscribe to the current value of `path`.
+    pub fn path(self, path: impl Into<String>) -> Self {
+        self.path_with_view(path, View::CurrentValue)
+    }
+
+    pub fn path_with_view(mut self, path: impl Into<String>, view: View) -> Self {
+        self.entries.push((path.into(), view, None));
+        self
+    }
+
+    /// The fields of the path added last. Given before any path, they are
+    /// used for all paths that don't set their own.
+    pub fn fields(mut self, fields: impl IntoIterator<Item = Field>) -> Self {
+        let fields = Some(fields.into_iter().collect());
+        match self.entries.last_mut() {
+            Some((_, _, entry_fields)) => *entry_fields = fields,
+            None => self.fields = fields,
+        }
+        self
+    }
+
+    /// Only send updates for entries that changed, not the initial values.
+    pub fn changes_only(mut self, changes_only: bool) -> Self {
+        self.changes_only = changes_only;
+        self
+    }
+
+    pub fn build(self) -> Subscription {
+        let entries = self
+            .entries
+            .into_iter()
+            .map(|(path, view, fields)| proto::v1::SubscribeEntry {
+                path,
+                view: view.into(),
+                fields: fields
+                    .or_else(|| self.fields.clone())
+                    .unwrap_or_else(|| default_fields(view))
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            })
+            .collect();
+        Subscription {
+            entries,
+            changes_only: self.changes_only,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let subscription = Subscription::builder()
+            .path("Vehicle.Speed")
+            .fields([Field::Value])
+            .path_with_view("Vehicle.Body.Trunk.Rear.IsOpen", View::TargetValue)
+            .path("Vehicle.IsMoving")
+            .build();
+
+        let entries = subscription.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].path, "Vehicle.Speed");
+        assert_eq!(entries[0].fields, vec![Field::Value as i32]);
+        assert_eq!(entries[1].view, View::TargetValue as i32);
+        assert_eq!(entries[1].fields, vec![Field::ActuatorTarget as i32]);
+        assert_eq!(
+            entries[2].fields,
+            vec![Field::Value as i32, Field::Metadata as i32]
+        );
+        assert!(!subscription.changes_only());
+    }
+
+    #[test]
+    fn test_default_fields() {
+        let request: proto::v1::SubscribeRequest = Subscription::builder()
+            .fields([Field::Path, Field::Value])
+            .path("Vehicle.Speed")
+            .path("Vehicle.IsMoving")
+            .fields([Field::Metadata])
+            .changes_only(true)
+            .build()
+            .into();
+
+        assert!(request.changes_only);
+        assert_eq!(
+            request.entries[0].fields,
+            vec![Field::Path as i32, Field::Value as i32]
+        );
+        assert_eq!(request.entries[1].fields, vec![Field::Metadata as i32]);
+    }
+
+    #[test]
+    fn test_shorthands() {
+        let current = Subscription::current_values(["Vehicle.Speed"]);
+        assert_eq!(
+            current,
+            Subscription::builder().path("Vehicle.Speed").build()
+        );
+
+        let target = Subscription::target_values(vec!["Vehicle.Speed".to_owned()]);
+        assert_eq!(target.entries()[0].view, View::TargetValue as i32);
+    }
+}
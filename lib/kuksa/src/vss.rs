@@ -0,0 +1,228 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Export the signal catalog of a running broker as VSS JSON.
+//!
+//! The tree is rebuilt from the metadata the broker reports, so it includes
+//! signals registered at runtime and can be loaded by Databroker with
+//! `--vss`. Branches aren't part of the metadata, so they have an empty
+//! description.
+
+// ClientError embeds a tonic::Status, which is large by design
+#![allow(clippy::result_large_err)]
+
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::api::KuksaClientApi;
+use crate::{proto, ClientError, DataEntry};
+
+use proto::v1::value_restriction::Type as Restriction;
+
+/// The VSS JSON tree of the signals matching `prefix`, or all signals.
+pub async fn export_vss(
+    client: &mut impl KuksaClientApi,
+    prefix: Option<&str>,
+) -> Result<JsonValue, ClientError> {
+    let entries = client
+        .get_metadata(vec![prefix.unwrap_or("**").to_owned()])
+        .await?;
+    Ok(to_vss_json(&entries))
+}
+
+/// Build a VSS JSON tree from the metadata of `entries`.
+pub fn to_vss_json(entries: &[DataEntry]) -> JsonValue {
+    let mut root = Map::new();
+    for entry in entries {
+        let Some(metadata) = &entry.metadata else {
+            continue;
+        };
+        let mut parts = entry.path.split('.').peekable();
+        let mut children = &mut root;
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                children.insert(part.to_owned(), leaf(metadata));
+                break;
+            }
+            let branch = children.entry(part.to_owned()).or_insert_with(|| {
+                json!({
+                    "type": "branch",
+                    "description": "",
+                    "children": {},
+                })
+            });
+            children = branch["children"]
+                .as_object_mut()
+                .expect("branches have children");
+        }
+    }
+    JsonValue::Object(root)
+}
+
+fn leaf(metadata: &proto::v1::Metadata) -> JsonValue {
+    let mut leaf = Map::new();
+    let entry_type = match proto::v1::EntryType::try_from(metadata.entry_type) {
+        Ok(proto::v1::EntryType::Actuator) => "actuator",
+        Ok(proto::v1::EntryType::Attribute) => "attribute",
+        _ => "sensor",
+    };
+    leaf.insert("type".to_owned(), entry_type.into());
+    if let Ok(data_type) = proto::v1::DataType::try_from(metadata.data_type) {
+        leaf.insert("datatype".to_owned(), vss_data_type(data_type).into());
+    }
+    leaf.insert(
+        "description".to_owned(),
+        metadata.description.clone().unwrap_or_default().into(),
+    );
+    for (key, value) in [
+        ("comment", &metadata.comment),
+        ("deprecation", &metadata.deprecation),
+        ("unit", &metadata.unit),
+    ] {
+        if let Some(value) = value {
+            leaf.insert(key.to_owned(), value.clone().into());
+        }
+    }
+
+    let (min, max, allowed) = match metadata
+        .value_restriction
+        .as_ref()
+        .and_then(|restriction| restriction.r#type.as_ref())
+    {
+        Some(Restriction::String(r)) => (None, None, json!(r.allowed_values)),
+        Some(Restriction::Signed(r)) => (
+            r.min.map(JsonValue::from),
+            r.max.map(JsonValue::from),
+            json!(r.allowed_values),
+        ),
+        Some(Restriction::Unsigned(r)) => (
+            r.min.map(JsonValue::from),
+            r.max.map(JsonValue::from),
+            json!(r.allowed_values),
+        ),
+        Some(Restriction::FloatingPoint(r)) => (
+            r.min.map(JsonValue::from),
+            r.max.map(JsonValue::from),
+            json!(r.allowed_values),
+        ),
+        None => (None, None, json!([])),
+    };
+    if let Some(min) = min {
+        leaf.insert("min".to_owned(), min);
+    }
+    if let Some(max) = max {
+        leaf.insert("max".to_owned(), max);
+    }
+    if allowed
+        .as_array()
+        .is_some_and(|allowed| !allowed.is_empty())
+    {
+        leaf.insert("allowed".to_owned(), allowed);
+    }
+    JsonValue::Object(leaf)
+}
+
+/// `DATA_TYPE_UINT8_ARRAY` becomes `uint8[]`.
+fn vss_data_type(data_type: proto::v1::DataType) -> String {
+    let name = data_type
+        .as_str_name()
+        .trim_start_matches("DATA_TYPE_")
+        .to_lowercase();
+    match name.strip_suffix("_array") {
+        Some(element) => format!("{element}[]"),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mock::MockClient;
+
+    #[tokio::test]
+    async fn test_export_vss() {
+        let mock = MockClient::new();
+        mock.set_metadata(
+            "Vehicle.Speed",
+            proto::v1::Metadata {
+                data_type: proto::v1::DataType::Float.into(),
+                entry_type: proto::v1::EntryType::Sensor.into(),
+                description: Some("Vehicle speed.".to_owned()),
+                unit: Some("km/h".to_owned()),
+                value_restriction: Some(proto::v1::ValueRestriction {
+                    r#type: Some(Restriction::FloatingPoint(
+                        proto::v1::ValueRestrictionFloat {
+                            min: Some(0.0),
+                            max: None,
+                            allowed_values: vec![],
+                        },
+                    )),
+                }),
+                ..Default::default()
+            },
+        );
+        mock.set_metadata(
+            "Vehicle.Cabin.Door.Position",
+            proto::v1::Metadata {
+                data_type: proto::v1::DataType::StringArray.into(),
+                entry_type: proto::v1::EntryType::Actuator.into(),
+                value_restriction: Some(proto::v1::ValueRestriction {
+                    r#type: Some(Restriction::String(proto::v1::ValueRestrictionString {
+                        allowed_values: vec!["OPEN".to_owned(), "CLOSED".to_owned()],
+                    })),
+                }),
+                ..Default::default()
+            },
+        );
+
+        let tree = export_vss(&mut mock.clone(), Some("Vehicle"))
+            .await
+            .unwrap();
+        assert_eq!(
+            tree,
+            json!({
+                "Vehicle": {
+                    "type": "branch",
+                    "description": "",
+                    "children": {
+                        "Speed": {
+                            "type": "sensor",
+                            "datatype": "float",
+                            "description": "Vehicle speed.",
+                            "unit": "km/h",
+                            "min": 0.0,
+                        },
+                        "Cabin": {
+                            "type": "branch",
+                            "description": "",
+                            "children": {
+                                "Door": {
+                                    "type": "branch",
+                                    "description": "",
+                                    "children": {
+                                        "Position": {
+                                            "type": "actuator",
+                                            "datatype": "string[]",
+                                            "description": "",
+                                            "allowed": ["OPEN", "CLOSED"],
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            })
+        );
+    }
+}
@@ -11,22 +11,34 @@
 * SPDX-License-Identifier: Apache-2.0
 ********************************************************************************/
 use databroker_proto::kuksa::val::v2::{
-    signal_id::Signal::Path, val_client::ValClient, ActuateRequest, BatchActuateRequest, Datapoint,
-    GetServerInfoRequest, GetValueRequest, GetValuesRequest, ListMetadataRequest,
+    compare_and_publish_value_request, open_provider_stream_request, open_provider_stream_response,
+    signal_id::Signal::{Id, Path},
+    val_client::ValClient,
+    value::TypedValue,
+    ActuateRequest, ActuationStatus, ArrayDelta, BatchActuateRequest, BatchActuateStreamResponse,
+    BatchPublishValuesRequest, CompareAndPublishValueRequest, Datapoint, Error, ErrorCode,
+    GetServerInfoRequest, GetValueRequest, GetValuesRequest, ListMetadataRequest, Metadata,
+    OpenProviderStreamRequest, ProvideActuationRequest, PublishArrayDeltaRequest,
     PublishValueRequest, SignalId, SubscribeByIdRequest, SubscribeRequest, Value,
 };
 use http::Uri;
 pub use kuksa_common::{Client, ClientError, ClientTraitV2};
 use prost_types::Timestamp;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
-use std::time::SystemTime;
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::async_trait;
 
+#[cfg(feature = "kuksa-val-v1")]
 use kuksa_common::conversion::{ConvertToV1, ConvertToV2};
 use kuksa_common::types::{OpenProviderStream, ServerInfo};
 
+pub mod provider;
+pub mod typed;
+
 #[derive(Debug)]
 pub struct KuksaClientV2 {
     pub basic_client: Client,
@@ -66,6 +78,377 @@ impl KuksaClientV2 {
         Ok(hash_map)
     }
 
+    /// Fetches one page of the metadata matching `root` (see `list_metadata`).
+    ///
+    /// A `page_size` of 0 returns all remaining entries. Pass `None` as
+    /// `page_token` for the first page and the returned token for the next
+    /// one; no token is returned with the last page.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if the specified root branch does not exist.
+    ///   INVALID_ARGUMENT if the provided path, wildcard or page token is wrong.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///
+    pub async fn list_metadata_page(
+        &mut self,
+        root: String,
+        page_size: u32,
+        page_token: Option<String>,
+    ) -> Result<(Vec<Metadata>, Option<String>), ClientError> {
+        let mut client = ValClient::with_interceptor(
+            self.basic_client.get_channel().await?.clone(),
+            self.basic_client.get_auth_interceptor(),
+        );
+
+        let list_metadata_request = ListMetadataRequest {
+            root,
+            page_size,
+            page_token: page_token.unwrap_or_default(),
+            ..Default::default()
+        };
+
+        match client.list_metadata(list_metadata_request).await {
+            Ok(response) => {
+                let metadata_response = response.into_inner();
+                let next_page_token = Some(metadata_response.next_page_token)
+                    .filter(|page_token| !page_token.is_empty());
+                Ok((metadata_response.metadata, next_page_token))
+            }
+            Err(err) => Err(ClientError::Status(err)),
+        }
+    }
+
+    /// Iterates over the metadata matching `root`, fetching `page_size`
+    /// entries at a time as the iteration proceeds.
+    pub fn list_metadata_paged(&mut self, root: String, page_size: u32) -> MetadataPager<'_> {
+        MetadataPager {
+            client: self,
+            root,
+            page_size,
+            page: VecDeque::new(),
+            next_page_token: None,
+            done: false,
+        }
+    }
+
+    /// Publish multiple signal values at once, either all or none of them.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if any of the signals are non-existant.
+    ///   PERMISSION_DENIED if access is denied for any of the signals.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///   INVALID_ARGUMENT
+    ///       - if the data type used in the request does not match
+    ///            the data type of the addressed signal
+    ///       - if any of the published values is not accepted,
+    ///            e.g. if sending an unsupported enum value
+    ///       - if any of the published values is out of the min/max range specified
+    ///
+    pub async fn batch_publish_values(
+        &mut self,
+        values: HashMap<String, Value>,
+    ) -> Result<(), ClientError> {
+        let mut client = ValClient::with_interceptor(
+            self.basic_client.get_channel().await?.clone(),
+            self.basic_client.get_auth_interceptor(),
+        );
+
+        let timestamp: Timestamp = SystemTime::now().into();
+        let publish_requests = values
+            .into_iter()
+            .map(|(signal_path, value)| PublishValueRequest {
+                signal_id: Some(SignalId {
+                    signal: Some(Path(signal_path)),
+                }),
+                data_point: Some(Datapoint {
+                    timestamp: Some(timestamp.clone()),
+                    value: Some(value),
+                }),
+            })
+            .collect();
+
+        match client
+            .batch_publish_values(BatchPublishValuesRequest { publish_requests })
+            .await
+        {
+            Ok(_response) => Ok(()),
+            Err(err) => Err(ClientError::Status(err)),
+        }
+    }
+
+    /// Publish a signal value only if the signal still has the `expected`
+    /// value or timestamp, e.g. as returned by a previous `get_value`.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if the signal is non-existant.
+    ///   PERMISSION_DENIED if access is denied for the signal.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///   ABORTED if the signal does not have the expected value or timestamp.
+    ///   INVALID_ARGUMENT
+    ///       - if the data type used in the request does not match
+    ///            the data type of the addressed signal
+    ///       - if the published value is not accepted,
+    ///            e.g. if sending an unsupported enum value
+    ///       - if the published value is out of the min/max range specified
+    ///
+    pub async fn compare_and_publish_value(
+        &mut self,
+        signal_path: String,
+        expected: compare_and_publish_value_request::Expected,
+        value: Value,
+    ) -> Result<(), ClientError> {
+        let mut client = ValClient::with_interceptor(
+            self.basic_client.get_channel().await?.clone(),
+            self.basic_client.get_auth_interceptor(),
+        );
+
+        let request = CompareAndPublishValueRequest {
+            signal_id: Some(SignalId {
+                signal: Some(Path(signal_path)),
+            }),
+            data_point: Some(Datapoint::now(value)),
+            expected: Some(expected),
+        };
+
+        match client.compare_and_publish_value(request).await {
+            Ok(_response) => Ok(()),
+            Err(err) => Err(ClientError::Status(err)),
+        }
+    }
+
+    /// Publish only the elements at `indices` of an array signal, given as
+    /// an array `values` of the signal's type in the order of `indices`.
+    /// The other elements keep their current value.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if the signal is non-existant.
+    ///   PERMISSION_DENIED if access is denied for the signal.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///   ABORTED if the array kept being changed concurrently.
+    ///   INVALID_ARGUMENT
+    ///       - if an index is beyond the end of the array,
+    ///            or the array has no value yet
+    ///       - if the number of indices and values differ
+    ///       - if the data type used in the request does not match
+    ///            the data type of the addressed signal
+    ///       - if the resulting array is out of the min/max range specified
+    ///
+    pub async fn publish_array_delta(
+        &mut self,
+        signal_path: String,
+        indices: Vec<u32>,
+        values: Value,
+    ) -> Result<(), ClientError> {
+        let mut client = ValClient::with_interceptor(
+            self.basic_client.get_channel().await?.clone(),
+            self.basic_client.get_auth_interceptor(),
+        );
+
+        let request = PublishArrayDeltaRequest {
+            signal_id: Some(SignalId {
+                signal: Some(Path(signal_path)),
+            }),
+            delta: Some(ArrayDelta {
+                timestamp: Some(SystemTime::now().into()),
+                indices,
+                values: Some(values),
+            }),
+        };
+
+        match client.publish_array_delta(request).await {
+            Ok(_response) => Ok(()),
+            Err(err) => Err(ClientError::Status(err)),
+        }
+    }
+
+    /// Waits until `predicate` holds for the value of a signal, checking its
+    /// current value first and then every change, and returns that value.
+    /// Changes to no value at all are skipped. Subscribing is part of the
+    /// `timeout`.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if the signal is non-existant.
+    ///   PERMISSION_DENIED if access is denied for the signal.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///   DEADLINE_EXCEEDED if the predicate didn't hold within `timeout`
+    ///   CANCELLED if the databroker ended the subscription
+    ///
+    pub async fn wait_for<P>(
+        &mut self,
+        signal_path: String,
+        mut predicate: P,
+        timeout: Duration,
+    ) -> Result<Value, ClientError>
+    where
+        P: FnMut(&Value) -> bool,
+    {
+        let wait = async {
+            let mut stream =
+                ClientTraitV2::subscribe(self, vec![signal_path.clone()], None).await?;
+            loop {
+                match stream.message().await {
+                    Ok(Some(response)) => {
+                        let value = response
+                            .entries
+                            .into_values()
+                            .next()
+                            .and_then(|datapoint| datapoint.value);
+                        if let Some(value) = value {
+                            if predicate(&value) {
+                                return Ok(value);
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        return Err(ClientError::Status(tonic::Status::cancelled(
+                            "Subscription ended",
+                        )))
+                    }
+                    Err(err) => return Err(ClientError::Status(err)),
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(result) => result,
+            Err(_) => Err(ClientError::Status(tonic::Status::deadline_exceeded(
+                format!("Condition on {signal_path} didn't hold in time"),
+            ))),
+        }
+    }
+
+    /// Registers this client as the actuation provider of `paths` and calls
+    /// `handler` with the path and the requested value of every actuation
+    /// forwarded by the databroker. The result of the handler is reported
+    /// back to the databroker.
+    ///
+    /// Returns once the databroker accepted the registration. The returned
+    /// task serves the actuations until the provider stream is closed, abort
+    /// it to stop providing.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if any of the actuators are non-existant.
+    ///   PERMISSION_DENIED if access is denied for any of the actuators.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///   ALREADY_EXISTS if a provider already claimed the ownership of an actuator
+    ///
+    pub async fn provide_actuation<H, F>(
+        &mut self,
+        paths: Vec<String>,
+        mut handler: H,
+    ) -> Result<JoinHandle<Result<(), ClientError>>, ClientError>
+    where
+        H: FnMut(String, Value) -> F + Send + 'static,
+        F: Future<Output = Result<(), Error>> + Send,
+    {
+        // The databroker forwards actuations by id
+        let paths_by_id: HashMap<i32, String> = self
+            .resolve_ids_for_paths(paths)
+            .await?
+            .into_iter()
+            .map(|(path, id)| (id, path))
+            .collect();
+
+        let OpenProviderStream {
+            sender,
+            mut receiver_stream,
+        } = ClientTraitV2::open_provider_stream(self, None).await?;
+
+        let provide_actuation_request = OpenProviderStreamRequest {
+            action: Some(
+                open_provider_stream_request::Action::ProvideActuationRequest(
+                    ProvideActuationRequest {
+                        actuator_identifiers: paths_by_id
+                            .keys()
+                            .map(|id| SignalId {
+                                signal: Some(Id(*id)),
+                            })
+                            .collect(),
+                    },
+                ),
+            ),
+        };
+        if sender.send(provide_actuation_request).await.is_err() {
+            return Err(ClientError::Connection(
+                "Provider stream closed".to_string(),
+            ));
+        }
+        match receiver_stream.message().await {
+            Ok(Some(response))
+                if matches!(
+                    response.action,
+                    Some(open_provider_stream_response::Action::ProvideActuationResponse(_))
+                ) => {}
+            Ok(_) => {
+                return Err(ClientError::Connection(
+                    "Provider stream closed before the registration was confirmed".to_string(),
+                ))
+            }
+            Err(err) => return Err(ClientError::Status(err)),
+        }
+
+        Ok(tokio::spawn(async move {
+            while let Some(response) = receiver_stream
+                .message()
+                .await
+                .map_err(ClientError::Status)?
+            {
+                let Some(open_provider_stream_response::Action::BatchActuateStreamRequest(
+                    batch_actuate_stream_request,
+                )) = response.action
+                else {
+                    continue;
+                };
+                let actuation_ids = batch_actuate_stream_request.actuation_ids;
+                for (index, actuate_request) in batch_actuate_stream_request
+                    .actuate_requests
+                    .into_iter()
+                    .enumerate()
+                {
+                    let path = match actuate_request
+                        .signal_id
+                        .as_ref()
+                        .and_then(|signal_id| signal_id.signal.as_ref())
+                    {
+                        Some(Id(id)) => paths_by_id.get(id).cloned(),
+                        Some(Path(path)) => Some(path.clone()),
+                        None => None,
+                    };
+                    let error = match (path, actuate_request.value) {
+                        (Some(path), Some(value)) => handler(path, value).await.err(),
+                        _ => Some(Error {
+                            code: ErrorCode::InvalidArgument.into(),
+                            message: "Unknown actuator or missing value".to_string(),
+                        }),
+                    };
+                    // The handler is done with the actuation, report its outcome
+                    let status = match error {
+                        Some(_) => ActuationStatus::Failed,
+                        None => ActuationStatus::Executed,
+                    };
+                    let batch_actuate_stream_response = OpenProviderStreamRequest {
+                        action: Some(
+                            open_provider_stream_request::Action::BatchActuateStreamResponse(
+                                BatchActuateStreamResponse {
+                                    signal_id: actuate_request.signal_id,
+                                    error,
+                                    actuation_id: actuation_ids
+                                        .get(index)
+                                        .copied()
+                                        .unwrap_or_default(),
+                                    status: status.into(),
+                                },
+                            ),
+                        ),
+                    };
+                    if sender.send(batch_actuate_stream_response).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            Ok(())
+        }))
+    }
+
     fn convert_to_actuate_requests(values: HashMap<String, Value>) -> Vec<ActuateRequest> {
         let mut actuate_requests = Vec::with_capacity(values.len());
         for (signal_path, value) in values {
@@ -82,6 +465,114 @@ impl KuksaClientV2 {
     }
 }
 
+/// Pages through a metadata listing, see [`KuksaClientV2::list_metadata_paged`].
+pub struct MetadataPager<'a> {
+    client: &'a mut KuksaClientV2,
+    root: String,
+    page_size: u32,
+    page: VecDeque<Metadata>,
+    next_page_token: Option<String>,
+    done: bool,
+}
+
+impl MetadataPager<'_> {
+    /// Returns the next entry, fetching the next page when needed, or
+    /// `None` after the last entry. An error ends the iteration.
+    pub async fn next(&mut self) -> Option<Result<Metadata, ClientError>> {
+        while self.page.is_empty() {
+            if self.done {
+                return None;
+            }
+            match self
+                .client
+                .list_metadata_page(
+                    self.root.clone(),
+                    self.page_size,
+                    self.next_page_token.take(),
+                )
+                .await
+            {
+                Ok((metadata, next_page_token)) => {
+                    self.page.extend(metadata);
+                    self.done = next_page_token.is_none();
+                    self.next_page_token = next_page_token;
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+/// Applies a delta received on a subscription with delta encoding to the
+/// datapoint previously received for the same signal.
+///
+/// Returns `false`, leaving `datapoint` untouched, if the delta doesn't fit
+/// it, e.g. because a response was missed. The whole value is sent again
+/// periodically, or the signal can be subscribed again.
+pub fn apply_array_delta(datapoint: &mut Datapoint, delta: &ArrayDelta) -> bool {
+    fn apply<T: Clone>(values: &mut [T], indices: &[u32], changed: &[T]) -> bool {
+        if indices.len() != changed.len()
+            || indices.iter().any(|index| *index as usize >= values.len())
+        {
+            return false;
+        }
+        for (index, value) in indices.iter().zip(changed) {
+            values[*index as usize] = value.clone();
+        }
+        true
+    }
+
+    let (Some(value), Some(changed)) = (
+        datapoint
+            .value
+            .as_mut()
+            .and_then(|value| value.typed_value.as_mut()),
+        delta
+            .values
+            .as_ref()
+            .and_then(|value| value.typed_value.as_ref()),
+    ) else {
+        return false;
+    };
+    let indices = &delta.indices;
+    let applied = match (value, changed) {
+        (TypedValue::StringArray(values), TypedValue::StringArray(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        (TypedValue::BoolArray(values), TypedValue::BoolArray(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        (TypedValue::Int32Array(values), TypedValue::Int32Array(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        (TypedValue::Int64Array(values), TypedValue::Int64Array(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        (TypedValue::Uint32Array(values), TypedValue::Uint32Array(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        (TypedValue::Uint64Array(values), TypedValue::Uint64Array(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        (TypedValue::FloatArray(values), TypedValue::FloatArray(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        (TypedValue::DoubleArray(values), TypedValue::DoubleArray(changed)) => {
+            apply(&mut values.values, indices, &changed.values)
+        }
+        _ => false,
+    };
+    if applied {
+        datapoint.timestamp.clone_from(&delta.timestamp);
+    }
+    applied
+}
+
+#[cfg(feature = "kuksa-val-v1")]
 #[async_trait]
 impl kuksa_common::ClientTraitV1 for KuksaClientV2 {
     type SensorUpdateType = kuksa_common::types::SensorUpdateTypeV1;
@@ -298,21 +789,11 @@ impl ClientTraitV2 for KuksaClientV2 {
             self.basic_client.get_auth_interceptor(),
         );
 
-        let now = SystemTime::now();
-        let duration_since_epoch = now
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("Clock may have gone backwards");
-        let seconds = duration_since_epoch.as_secs() as i64;
-        let nanos = duration_since_epoch.subsec_nanos() as i32;
-
         let publish_value_request = PublishValueRequest {
             signal_id: Some(SignalId {
                 signal: Some(Path(signal_path)),
             }),
-            data_point: Some(Datapoint {
-                timestamp: Some(Timestamp { seconds, nanos }),
-                value: Some(value),
-            }),
+            data_point: Some(Datapoint::now(value)),
         };
 
         match client.publish_value(publish_value_request).await {
@@ -429,6 +910,10 @@ impl ClientTraitV2 for KuksaClientV2 {
             signal_paths,
             buffer_size: buffer_size.unwrap_or(0),
             filter: None,
+            changes_only: false,
+            delta_encoding: None,
+            backfill_since: None,
+            priority: 0,
         };
 
         match client.subscribe(subscribe_request).await {
@@ -469,6 +954,10 @@ impl ClientTraitV2 for KuksaClientV2 {
             signal_ids,
             buffer_size: buffer_size.unwrap_or(0),
             filter: None,
+            changes_only: false,
+            delta_encoding: None,
+            backfill_since: None,
+            priority: 0,
         };
 
         match client.subscribe_by_id(subscribe_by_id_request).await {
@@ -548,6 +1037,7 @@ impl ClientTraitV2 for KuksaClientV2 {
         let list_metadata_request = ListMetadataRequest {
             root: tuple.0,
             filter: tuple.1,
+            ..Default::default()
         };
 
         match client.list_metadata(list_metadata_request).await {
@@ -575,6 +1065,7 @@ impl ClientTraitV2 for KuksaClientV2 {
                     name: get_server_info_response.name,
                     commit_hash: get_server_info_response.commit_hash,
                     version: get_server_info_response.version,
+                    capabilities: get_server_info_response.capabilities,
                 };
                 Ok(server_info)
             }
@@ -594,13 +1085,16 @@ impl ClientTraitV2 for KuksaClientV2 {
 mod tests {
     use super::*;
     use crate::tests::TokenType::{Read, ReadWrite};
+    use databroker_proto::kuksa::val::v2::compare_and_publish_value_request::Expected;
     use databroker_proto::kuksa::val::v2::open_provider_stream_request::Action;
-    use databroker_proto::kuksa::val::v2::value::TypedValue;
     use databroker_proto::kuksa::val::v2::ProvideActuationRequest;
     use std::fs;
     use test_tag::tag;
     use tokio::test;
-    use tonic::Code::{InvalidArgument, NotFound, PermissionDenied, Unauthenticated, Unavailable};
+    use tonic::Code::{
+        Aborted, DeadlineExceeded, InvalidArgument, NotFound, PermissionDenied, Unauthenticated,
+        Unavailable,
+    };
 
     impl KuksaClientV2 {
         fn new_test_client(token_type: Option<TokenType>) -> Self {
@@ -624,6 +1118,40 @@ mod tests {
         }
     }
 
+    #[test]
+    async fn test_apply_array_delta() {
+        use databroker_proto::kuksa::val::v2::Int32Array;
+        let int32_array = |values: Vec<i32>| Value {
+            typed_value: Some(TypedValue::Int32Array(Int32Array { values })),
+        };
+        let mut datapoint = Datapoint {
+            timestamp: None,
+            value: Some(int32_array(vec![1, 2, 3, 4])),
+        };
+        let timestamp = Timestamp::from(SystemTime::now());
+        assert!(apply_array_delta(
+            &mut datapoint,
+            &ArrayDelta {
+                timestamp: Some(timestamp.clone()),
+                indices: vec![1, 3],
+                values: Some(int32_array(vec![5, 6])),
+            }
+        ));
+        assert_eq!(datapoint.value, Some(int32_array(vec![1, 5, 3, 6])));
+        assert_eq!(datapoint.timestamp, Some(timestamp));
+
+        // Out of range
+        assert!(!apply_array_delta(
+            &mut datapoint,
+            &ArrayDelta {
+                timestamp: None,
+                indices: vec![4],
+                values: Some(int32_array(vec![7])),
+            }
+        ));
+        assert_eq!(datapoint.value, Some(int32_array(vec![1, 5, 3, 6])));
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_get_value() {
@@ -762,6 +1290,155 @@ mod tests {
         assert_eq!(value, datapoint.value.unwrap());
     }
 
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_batch_publish_values_will_return_ok() {
+        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+
+        let values = HashMap::from([
+            (
+                "Vehicle.Speed".to_string(),
+                Value {
+                    typed_value: Some(TypedValue::Float(40.0)),
+                },
+            ),
+            (
+                "Vehicle.Powertrain.Transmission.CurrentGear".to_string(),
+                Value {
+                    typed_value: Some(TypedValue::Int32(3)),
+                },
+            ),
+        ]);
+        let response = client.batch_publish_values(values).await;
+        assert!(response.is_ok());
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_batch_publish_values_with_invalid_value_will_publish_nothing() {
+        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+
+        let speed = Value {
+            typed_value: Some(TypedValue::Float(123.0)),
+        };
+        let values = HashMap::from([
+            ("Vehicle.Speed".to_string(), speed.clone()),
+            (
+                "Vehicle.Powertrain.Transmission.CurrentGear".to_string(),
+                Value {
+                    typed_value: Some(TypedValue::String("third".to_string())),
+                },
+            ),
+        ]);
+        let response = client.batch_publish_values(values).await;
+        expect_status_code(response.unwrap_err(), InvalidArgument);
+
+        let datapoint = client.get_value("Vehicle.Speed".to_string()).await.unwrap();
+        assert_ne!(datapoint.and_then(|datapoint| datapoint.value), Some(speed));
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_compare_and_publish_value_with_stale_value_will_return_aborted() {
+        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+
+        let signal_path = "Vehicle.Speed".to_string();
+        let speed = |speed| Value {
+            typed_value: Some(TypedValue::Float(speed)),
+        };
+        client
+            .publish_value(signal_path.clone(), speed(10.0))
+            .await
+            .unwrap();
+
+        let response = client
+            .compare_and_publish_value(
+                signal_path.clone(),
+                Expected::ExpectedValue(speed(11.0)),
+                speed(20.0),
+            )
+            .await;
+        expect_status_code(response.unwrap_err(), Aborted);
+
+        let response = client
+            .compare_and_publish_value(
+                signal_path.clone(),
+                Expected::ExpectedValue(speed(10.0)),
+                speed(20.0),
+            )
+            .await;
+        assert!(response.is_ok());
+
+        let datapoint = client.get_value(signal_path).await.unwrap();
+        assert_eq!(
+            datapoint.and_then(|datapoint| datapoint.value),
+            Some(speed(20.0))
+        );
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_publish_array_delta() {
+        use databroker_proto::kuksa::val::v2::StringArray;
+        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+
+        let signal_path = "Vehicle.OBD.DTCList".to_string();
+        let dtcs = |values: &[&str]| Value {
+            typed_value: Some(TypedValue::StringArray(StringArray {
+                values: values.iter().map(|value| value.to_string()).collect(),
+            })),
+        };
+        client
+            .publish_value(signal_path.clone(), dtcs(&["P0001", "P0002", "P0003"]))
+            .await
+            .unwrap();
+
+        client
+            .publish_array_delta(signal_path.clone(), vec![1], dtcs(&["P0102"]))
+            .await
+            .unwrap();
+        let datapoint = client.get_value(signal_path.clone()).await.unwrap();
+        assert_eq!(
+            datapoint.and_then(|datapoint| datapoint.value),
+            Some(dtcs(&["P0001", "P0102", "P0003"]))
+        );
+
+        let response = client
+            .publish_array_delta(signal_path, vec![3], dtcs(&["P0104"]))
+            .await;
+        expect_status_code(response.unwrap_err(), InvalidArgument);
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_wait_for() {
+        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let signal_path = "Vehicle.Speed".to_string();
+
+        let response = client
+            .wait_for(signal_path.clone(), |_| false, Duration::from_millis(200))
+            .await;
+        expect_status_code(response.unwrap_err(), DeadlineExceeded);
+
+        let value = Value {
+            typed_value: Some(TypedValue::Float(42.0)),
+        };
+        let waiting = {
+            let mut client = KuksaClientV2::new_test_client(Some(Read));
+            let (signal_path, value) = (signal_path.clone(), value.clone());
+            tokio::spawn(async move {
+                client
+                    .wait_for(signal_path, |v| *v == value, Duration::from_secs(5))
+                    .await
+            })
+        };
+        client
+            .publish_value(signal_path, value.clone())
+            .await
+            .unwrap();
+        assert_eq!(waiting.await.unwrap().unwrap(), value);
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_publish_value_with_invalid_data_type_will_return_invalid_argument() {
@@ -923,6 +1600,107 @@ mod tests {
         assert!(response.is_ok());
     }
 
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_provide_actuation_calls_handler() {
+        let mut provider = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+
+        let signal_path = "Vehicle.Body.Trunk.Rear.IsOpen".to_string(); // is an actuator
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let task = provider
+            .provide_actuation(vec![signal_path.clone()], move |path, value| {
+                let sender = sender.clone();
+                async move {
+                    sender.send((path, value)).unwrap();
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+        let value = Value {
+            typed_value: Some(TypedValue::Bool(true)),
+        };
+        let response = client.actuate(signal_path.clone(), value.clone()).await;
+        assert!(response.is_ok());
+        assert_eq!(receiver.recv().await, Some((signal_path, value)));
+
+        task.abort();
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_provider_publishes_values_and_serves_actuations() {
+        use crate::provider::ProviderEvent;
+
+        let mut client = KuksaClientV2::new_test_client(Some(ReadWrite));
+        let sensor = "Vehicle.Powertrain.TractionBattery.StateOfCharge.Current".to_string();
+        let actuator = "Vehicle.Body.Trunk.Front.IsOpen".to_string();
+
+        let mut provider = client
+            .open_provider(vec![sensor.clone(), actuator.clone()])
+            .await
+            .unwrap();
+        provider.provide_actuation([&actuator]).await.unwrap();
+        assert_eq!(
+            provider.next_event().await.unwrap(),
+            Some(ProviderEvent::ActuationProvided)
+        );
+
+        let value = Value {
+            typed_value: Some(TypedValue::Float(77.5)),
+        };
+        provider.publish([(&sensor, value.clone())]).await.unwrap();
+        let wrong_type = Value {
+            typed_value: Some(TypedValue::String("full".to_string())),
+        };
+        let request_id = provider.publish([(&sensor, wrong_type)]).await.unwrap();
+        let event = provider.next_event().await.unwrap();
+        assert!(matches!(
+            &event,
+            Some(ProviderEvent::Rejected { request_id: id, errors })
+                if *id == request_id && errors.contains_key(&sensor)
+        ));
+        let datapoint = client.get_value(sensor.clone()).await.unwrap();
+        assert_eq!(datapoint.and_then(|datapoint| datapoint.value), Some(value));
+
+        let open = Value {
+            typed_value: Some(TypedValue::Bool(true)),
+        };
+        client
+            .actuate(actuator.clone(), open.clone())
+            .await
+            .unwrap();
+        let actuation = match provider.next_event().await.unwrap() {
+            Some(ProviderEvent::Actuation(actuation)) => Some(actuation),
+            _ => None,
+        }
+        .expect("the actuation should be forwarded");
+        assert_eq!(
+            (actuation.path.as_str(), &actuation.value),
+            (actuator.as_str(), &open)
+        );
+        provider.report(actuation, Ok(())).await.unwrap();
+
+        let result = provider.publish([("Vehicle.Speed", open)]).await;
+        expect_status_code(result.unwrap_err(), NotFound);
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_provide_actuation_with_invalid_path_will_return_not_found() {
+        let mut provider = KuksaClientV2::new_test_client(Some(ReadWrite));
+
+        let result = provider
+            .provide_actuation(vec!["Vehicle.Invalid.Path".to_string()], |_, _| async {
+                Ok(())
+            })
+            .await;
+        expect_status_code(result.unwrap_err(), NotFound);
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_actuate_with_no_actuation_provider_will_return_unavailable() {
@@ -1357,6 +2135,48 @@ mod tests {
         assert!(!metadata_list.is_empty());
     }
 
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_list_metadata_paged() {
+        let mut client = KuksaClientV2::new_test_client(Some(Read));
+
+        let all = client
+            .list_metadata(("Vehicle.Cabin.Door".to_string(), "*".to_string()))
+            .await
+            .unwrap();
+        assert!(all.len() > 3);
+
+        let (first_page, page_token) = client
+            .list_metadata_page("Vehicle.Cabin.Door".to_string(), 3, None)
+            .await
+            .unwrap();
+        assert_eq!(first_page.len(), 3);
+        assert!(page_token.is_some());
+
+        let mut paged = Vec::new();
+        let mut pager = client.list_metadata_paged("Vehicle.Cabin.Door".to_string(), 3);
+        while let Some(metadata) = pager.next().await {
+            paged.push(metadata.unwrap().id);
+        }
+        let mut all_ids: Vec<i32> = all.iter().map(|metadata| metadata.id).collect();
+        all_ids.sort();
+        assert_eq!(paged, all_ids);
+    }
+
+    #[tag(integration, insecure)]
+    #[test]
+    async fn test_list_metadata_with_invalid_page_token_will_return_invalid_argument() {
+        let mut client = KuksaClientV2::new_test_client(Some(Read));
+
+        let response = client
+            .list_metadata_page("Vehicle".to_string(), 3, Some("invalid".to_string()))
+            .await;
+        assert!(response.is_err());
+
+        let err = response.unwrap_err();
+        expect_status_code(err, InvalidArgument);
+    }
+
     #[tag(integration, insecure)]
     #[test]
     async fn test_list_metadata_with_invalid_root_will_return_not_found() {
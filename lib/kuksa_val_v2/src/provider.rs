@@ -0,0 +1,292 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Providers publishing values and serving actuations over one provider
+//! stream (`OpenProviderStream`), rather than a call per value:
+//!
+//! ```no_run
+//! # async fn example(mut client: kuksa_val_v2::KuksaClientV2) -> Result<(), kuksa_val_v2::ClientError> {
+//! use kuksa_val_v2::provider::ProviderEvent;
+//! # use databroker_proto::kuksa::val::v2::{value::TypedValue, Value};
+//! # let speed = Value { typed_value: Some(TypedValue::Float(50.0)) };
+//!
+//! let mut provider = client
+//!     .open_provider(vec!["Vehicle.Speed".into(), "Vehicle.Body.Trunk.Rear.IsOpen".into()])
+//!     .await?;
+//! provider.provide_actuation(["Vehicle.Body.Trunk.Rear.IsOpen"]).await?;
+//! provider.publish([("Vehicle.Speed", speed)]).await?;
+//! while let Some(event) = provider.next_event().await? {
+//!     match event {
+//!         ProviderEvent::Actuation(actuation) => {
+//!             // Move the trunk, then report the outcome
+//!             provider.report(actuation, Ok(())).await?;
+//!         }
+//!         ProviderEvent::Rejected { request_id, errors } => {
+//!             eprintln!("values of request {request_id} rejected: {errors:?}");
+//!         }
+//!         ProviderEvent::ActuationProvided => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The databroker only answers published values if it rejects some of them,
+//! and may hold off further messages until the responses are read, so
+//! [`Provider::next_event`] should be polled all along.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use databroker_proto::kuksa::val::v2::{
+    open_provider_stream_request::Action,
+    open_provider_stream_response,
+    signal_id::Signal::{Id, Path},
+    ActuationStatus, BatchActuateStreamResponse, Datapoint, Error, ErrorCode,
+    OpenProviderStreamRequest, ProvideActuationRequest, PublishValuesRequest, SignalId, Value,
+};
+use kuksa_common::types::OpenProviderStream;
+use kuksa_common::{ClientError, ClientTraitV2};
+use prost_types::Timestamp;
+use tokio::sync::mpsc;
+
+use crate::KuksaClientV2;
+
+/// A request of the databroker to actuate an actuator of the provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actuation {
+    pub path: String,
+    pub value: Value,
+    signal_id: Option<SignalId>,
+    actuation_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProviderEvent {
+    /// The databroker confirmed [`Provider::provide_actuation`].
+    ActuationProvided,
+    /// An actuator is to take a value, its outcome is expected to be
+    /// [reported](Provider::report).
+    Actuation(Actuation),
+    /// Values published by the request with `request_id` were rejected,
+    /// with the error of each rejected path.
+    Rejected {
+        request_id: u32,
+        errors: HashMap<String, Error>,
+    },
+}
+
+pub struct Provider {
+    sender: mpsc::Sender<OpenProviderStreamRequest>,
+    receiver: tonic::Streaming<databroker_proto::kuksa::val::v2::OpenProviderStreamResponse>,
+    ids: HashMap<String, i32>,
+    paths: HashMap<i32, String>,
+    next_request_id: u32,
+    /// Actuations received but not returned yet, the next one last
+    pending: Vec<ProviderEvent>,
+}
+
+impl KuksaClientV2 {
+    /// Open a provider stream to publish values of, and provide the
+    /// actuators among, `paths`. Their ids are resolved up front, as the
+    /// stream addresses signals by id.
+    ///
+    /// Returns (GRPC error code):
+    ///   NOT_FOUND if any of the signals are non-existant.
+    ///   UNAUTHENTICATED if no credentials provided or credentials has expired
+    ///
+    pub async fn open_provider(&mut self, paths: Vec<String>) -> Result<Provider, ClientError> {
+        let ids = self.resolve_ids_for_paths(paths).await?;
+        let OpenProviderStream {
+            sender,
+            receiver_stream,
+        } = ClientTraitV2::open_provider_stream(self, None).await?;
+        Ok(Provider {
+            sender,
+            receiver: receiver_stream,
+            paths: ids.iter().map(|(path, id)| (*id, path.clone())).collect(),
+            ids,
+            next_request_id: 0,
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl Provider {
+    async fn send(&self, action: Action) -> Result<(), ClientError> {
+        self.sender
+            .send(OpenProviderStreamRequest {
+                action: Some(action),
+            })
+            .await
+            .map_err(|_| ClientError::Connection("Provider stream closed".to_string()))
+    }
+
+    /// Claim the actuators among `paths`, confirmed by
+    /// [`ProviderEvent::ActuationProvided`]. The stream is closed with an
+    /// error if any of them can't be claimed, e.g. with ALREADY_EXISTS if
+    /// another provider claimed it.
+    pub async fn provide_actuation(
+        &mut self,
+        paths: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<(), ClientError> {
+        let mut actuator_identifiers = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            let id = self.ids.get(path).ok_or_else(|| not_opened(path))?;
+            actuator_identifiers.push(SignalId {
+                signal: Some(Id(*id)),
+            });
+        }
+        self.send(Action::ProvideActuationRequest(ProvideActuationRequest {
+            actuator_identifiers,
+        }))
+        .await
+    }
+
+    /// Publish the current `values` of signals, all with the same
+    /// timestamp. Returns the id of the request, to match it with a
+    /// [`ProviderEvent::Rejected`].
+    pub async fn publish(
+        &mut self,
+        values: impl IntoIterator<Item = (impl AsRef<str>, Value)>,
+    ) -> Result<u32, ClientError> {
+        let timestamp: Timestamp = SystemTime::now().into();
+        let mut data_points = HashMap::new();
+        for (path, value) in values {
+            let path = path.as_ref();
+            let id = self.ids.get(path).ok_or_else(|| not_opened(path))?;
+            data_points.insert(
+                *id,
+                Datapoint {
+                    timestamp: Some(timestamp.clone()),
+                    value: Some(value),
+                },
+            );
+        }
+        let request_id = self.next_request_id;
+        self.next_request_id = self.next_request_id.wrapping_add(1);
+        self.send(Action::PublishValuesRequest(PublishValuesRequest {
+            request_id,
+            data_points,
+        }))
+        .await?;
+        Ok(request_id)
+    }
+
+    /// Report the outcome of `actuation` to the databroker.
+    pub async fn report(
+        &mut self,
+        actuation: Actuation,
+        result: Result<(), Error>,
+    ) -> Result<(), ClientError> {
+        let status = match result {
+            Ok(()) => ActuationStatus::Executed,
+            Err(_) => ActuationStatus::Failed,
+        };
+        self.send(Action::BatchActuateStreamResponse(
+            BatchActuateStreamResponse {
+                signal_id: actuation.signal_id,
+                error: result.err(),
+                actuation_id: actuation.actuation_id,
+                status: status.into(),
+            },
+        ))
+        .await
+    }
+
+    /// Wait for the next message of the databroker, None once it closed
+    /// the stream.
+    pub async fn next_event(&mut self) -> Result<Option<ProviderEvent>, ClientError> {
+        loop {
+            if let Some(event) = self.pending.pop() {
+                return Ok(Some(event));
+            }
+            let Some(response) = self.receiver.message().await.map_err(ClientError::Status)? else {
+                return Ok(None);
+            };
+            match response.action {
+                Some(open_provider_stream_response::Action::ProvideActuationResponse(_)) => {
+                    return Ok(Some(ProviderEvent::ActuationProvided))
+                }
+                Some(open_provider_stream_response::Action::PublishValuesResponse(response)) => {
+                    let errors = response
+                        .status
+                        .into_iter()
+                        .map(|(id, error)| {
+                            let path = self.paths.get(&id).cloned();
+                            (path.unwrap_or_else(|| id.to_string()), error)
+                        })
+                        .collect();
+                    return Ok(Some(ProviderEvent::Rejected {
+                        request_id: response.request_id,
+                        errors,
+                    }));
+                }
+                Some(open_provider_stream_response::Action::BatchActuateStreamRequest(request)) => {
+                    // Queued in reverse, to be popped in order
+                    for (index, actuate_request) in
+                        request.actuate_requests.into_iter().enumerate().rev()
+                    {
+                        let actuation_id = request
+                            .actuation_ids
+                            .get(index)
+                            .copied()
+                            .unwrap_or_default();
+                        let path = match actuate_request
+                            .signal_id
+                            .as_ref()
+                            .and_then(|signal_id| signal_id.signal.as_ref())
+                        {
+                            Some(Id(id)) => self.paths.get(id).cloned(),
+                            Some(Path(path)) => Some(path.clone()),
+                            None => None,
+                        };
+                        match (path, actuate_request.value) {
+                            (Some(path), Some(value)) => {
+                                self.pending.push(ProviderEvent::Actuation(Actuation {
+                                    path,
+                                    value,
+                                    signal_id: actuate_request.signal_id,
+                                    actuation_id,
+                                }))
+                            }
+                            _ => {
+                                self.send(Action::BatchActuateStreamResponse(
+                                    BatchActuateStreamResponse {
+                                        signal_id: actuate_request.signal_id,
+                                        error: Some(Error {
+                                            code: ErrorCode::InvalidArgument.into(),
+                                            message: "Unknown actuator or missing value"
+                                                .to_string(),
+                                        }),
+                                        actuation_id,
+                                        status: ActuationStatus::Failed.into(),
+                                    },
+                                ))
+                                .await?
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn not_opened(path: &str) -> ClientError {
+    ClientError::Status(tonic::Status::not_found(format!(
+        "{path} was not passed to open_provider"
+    )))
+}
@@ -0,0 +1,242 @@
+/********************************************************************************
+* Copyright (c) 2025 Contributors to the Eclipse Foundation
+*
+* See the NOTICE file(s) distributed with this work for additional
+* information regarding copyright ownership.
+*
+* This program and the accompanying materials are made available under the
+* terms of the Apache License 2.0 which is available at
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* SPDX-License-Identifier: Apache-2.0
+********************************************************************************/
+
+//! Strongly-typed access to signals, as used by the accessors that
+//! `kuksa-codegen` generates from a VSS file:
+//!
+//! ```ignore
+//! vehicle::cabin::hvac::station::row1::driver::Temperature::set(&mut client, 21).await?;
+//! let speed: Option<f32> = vehicle::Speed::get(&mut client).await?;
+//! typed::wait_for::<vehicle::Speed>(&mut client, |speed| *speed > 50.0, timeout).await?;
+//! ```
+//!
+//! Paths and data types are checked at compile time, the broker still checks
+//! bounds and permissions.
+
+use databroker_proto::kuksa::val::v2::{
+    value::TypedValue, BoolArray, DoubleArray, FloatArray, Int32Array, Int64Array, StringArray,
+    Uint32Array, Uint64Array, Value,
+};
+use kuksa_common::{ClientError, ClientTraitV2};
+use std::time::Duration;
+
+use crate::KuksaClientV2;
+
+/// Rust type of the values of a signal.
+pub trait SignalValue: Sized {
+    fn into_value(self) -> Value;
+    /// None if `value` is of another type.
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+/// A signal, with its path and the type of its values.
+pub trait Signal {
+    const PATH: &'static str;
+    type Value: SignalValue;
+}
+
+/// Signals that can be actuated.
+pub trait Actuator: Signal {}
+
+macro_rules! signal_value {
+    ($type:ty, $variant:ident) => {
+        impl SignalValue for $type {
+            fn into_value(self) -> Value {
+                Value {
+                    typed_value: Some(TypedValue::$variant(self)),
+                }
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value.typed_value {
+                    Some(TypedValue::$variant(value)) => Some(value),
+                    _ => None,
+                }
+            }
+        }
+    };
+    // Values sent as a wider type
+    ($type:ty, $variant:ident, $wire:ty) => {
+        impl SignalValue for $type {
+            fn into_value(self) -> Value {
+                Value {
+                    typed_value: Some(TypedValue::$variant(<$wire>::from(self))),
+                }
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value.typed_value {
+                    Some(TypedValue::$variant(value)) => value.try_into().ok(),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+macro_rules! signal_array_value {
+    ($type:ty, $variant:ident, $array:ident) => {
+        impl SignalValue for Vec<$type> {
+            fn into_value(self) -> Value {
+                Value {
+                    typed_value: Some(TypedValue::$variant($array { values: self })),
+                }
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value.typed_value {
+                    Some(TypedValue::$variant(array)) => Some(array.values),
+                    _ => None,
+                }
+            }
+        }
+    };
+    ($type:ty, $variant:ident, $array:ident, $wire:ty) => {
+        impl SignalValue for Vec<$type> {
+            fn into_value(self) -> Value {
+                Value {
+                    typed_value: Some(TypedValue::$variant($array {
+                        values: self.into_iter().map(<$wire>::from).collect(),
+                    })),
+                }
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value.typed_value {
+                    Some(TypedValue::$variant(array)) => array
+                        .values
+                        .into_iter()
+                        .map(|value| value.try_into().ok())
+                        .collect(),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+signal_value!(String, String);
+signal_value!(bool, Bool);
+signal_value!(i8, Int32, i32);
+signal_value!(i16, Int32, i32);
+signal_value!(i32, Int32);
+signal_value!(i64, Int64);
+signal_value!(u8, Uint32, u32);
+signal_value!(u16, Uint32, u32);
+signal_value!(u32, Uint32);
+signal_value!(u64, Uint64);
+signal_value!(f32, Float);
+signal_value!(f64, Double);
+
+signal_array_value!(String, StringArray, StringArray);
+signal_array_value!(bool, BoolArray, BoolArray);
+signal_array_value!(i8, Int32Array, Int32Array, i32);
+signal_array_value!(i16, Int32Array, Int32Array, i32);
+signal_array_value!(i32, Int32Array, Int32Array);
+signal_array_value!(i64, Int64Array, Int64Array);
+signal_array_value!(u8, Uint32Array, Uint32Array, u32);
+signal_array_value!(u16, Uint32Array, Uint32Array, u32);
+signal_array_value!(u32, Uint32Array, Uint32Array);
+signal_array_value!(u64, Uint64Array, Uint64Array);
+signal_array_value!(f32, FloatArray, FloatArray);
+signal_array_value!(f64, DoubleArray, DoubleArray);
+
+/// Latest value of signal `S`, None if it has none.
+pub async fn get<S: Signal>(client: &mut KuksaClientV2) -> Result<Option<S::Value>, ClientError> {
+    let value = client
+        .get_value(S::PATH.to_owned())
+        .await?
+        .and_then(|datapoint| datapoint.value);
+    match value {
+        Some(value) => match S::Value::from_value(value) {
+            Some(value) => Ok(Some(value)),
+            None => Err(ClientError::Status(tonic::Status::internal(format!(
+                "Unexpected data type of {}",
+                S::PATH
+            )))),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Wait until `predicate` holds for the value of signal `S`, e.g. until the
+/// ignition is on, and return that value. Fails with DEADLINE_EXCEEDED if it
+/// didn't hold within `timeout`.
+pub async fn wait_for<S: Signal>(
+    client: &mut KuksaClientV2,
+    mut predicate: impl FnMut(&S::Value) -> bool,
+    timeout: Duration,
+) -> Result<S::Value, ClientError> {
+    let value = client
+        .wait_for(
+            S::PATH.to_owned(),
+            |value| S::Value::from_value(value.clone()).is_some_and(|value| predicate(&value)),
+            timeout,
+        )
+        .await?;
+    S::Value::from_value(value).ok_or_else(|| {
+        ClientError::Status(tonic::Status::internal(format!(
+            "Unexpected data type of {}",
+            S::PATH
+        )))
+    })
+}
+
+/// Publish the current value of signal `S`, as its provider.
+pub async fn publish<S: Signal>(
+    client: &mut KuksaClientV2,
+    value: S::Value,
+) -> Result<(), ClientError> {
+    client
+        .publish_value(S::PATH.to_owned(), value.into_value())
+        .await
+}
+
+/// Request actuator `S` to take `value`.
+pub async fn actuate<S: Actuator>(
+    client: &mut KuksaClientV2,
+    value: S::Value,
+) -> Result<(), ClientError> {
+    client.actuate(S::PATH.to_owned(), value.into_value()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_value() {
+        assert_eq!(
+            21.5f32.into_value().typed_value,
+            Some(TypedValue::Float(21.5))
+        );
+        assert_eq!(
+            u8::from_value(Value {
+                typed_value: Some(TypedValue::Uint32(200))
+            }),
+            Some(200)
+        );
+        // Out of range of the signal's type
+        assert_eq!(
+            u8::from_value(Value {
+                typed_value: Some(TypedValue::Uint32(300))
+            }),
+            None
+        );
+        assert_eq!(bool::from_value(7u32.into_value()), None);
+        assert_eq!(
+            Vec::<i16>::from_value(vec![-1i16, 2].into_value()),
+            Some(vec![-1, 2])
+        );
+    }
+}
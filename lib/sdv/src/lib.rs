@@ -12,6 +12,7 @@
 ********************************************************************************/
 
 use std::collections::HashMap;
+use std::time::SystemTime;
 
 use databroker_proto::sdv::databroker as proto;
 use http::Uri;
@@ -28,6 +29,31 @@ impl SDVClient {
             basic_client: Client::new(uri),
         }
     }
+
+    /// The past values of the data point `name` kept in history, oldest
+    /// first: those since `since` (or all), and of these only the most
+    /// recent `limit` (or all if 0). Empty if the data point keeps no
+    /// history.
+    pub async fn get_history(
+        &mut self,
+        name: String,
+        since: Option<SystemTime>,
+        limit: u32,
+    ) -> Result<Vec<proto::v1::Datapoint>, ClientError> {
+        let mut client = proto::v1::broker_client::BrokerClient::with_interceptor(
+            self.basic_client.get_channel().await?.clone(),
+            self.basic_client.get_auth_interceptor(),
+        );
+        let args = tonic::Request::new(proto::v1::GetHistoryRequest {
+            name,
+            since: since.map(Into::into),
+            limit,
+        });
+        match client.get_history(args).await {
+            Ok(response) => Ok(response.into_inner().datapoints),
+            Err(err) => Err(ClientError::Status(err)),
+        }
+    }
 }
 
 #[async_trait]
@@ -97,7 +123,10 @@ impl SDVClientTraitV1 for SDVClient {
             self.basic_client.get_channel().await?.clone(),
             self.basic_client.get_auth_interceptor(),
         );
-        let args = tonic::Request::new(proto::v1::SubscribeRequest { query: paths });
+        let args = tonic::Request::new(proto::v1::SubscribeRequest {
+            query: paths,
+            changes_only: false,
+        });
 
         match client.subscribe(args).await {
             Ok(response) => Ok(response.into_inner()),
@@ -71,7 +71,7 @@ CARGO_REGISTRIES_CRATES_IO_PROTOCOL=sparse
 # Check if a certain feature set was requested
 if [ -z "$KUKSA_DATABROKER_FEATURES" ]; then
     # If not set, assign a default value
-    KUKSA_DATABROKER_FEATURES="databroker/default"
+    KUKSA_DATABROKER_FEATURES="databroker/default,databroker/vss-fetch"
 fi
 
 # Check if a certain profile is requested